@@ -54,6 +54,21 @@ typedef struct VAEFatPtr_i32 {
 
 typedef void (*VAEVaeFun)(uintptr_t, struct VAEFatPtr_f64*, struct VAEFatPtr_f64*, struct VAEFatPtr_f64*, struct VAEFatPtr_i32*, const char**, struct VAEFatPtr_f64*, struct VAEFatPtr_i32*, struct VAEFatPtr_f64*, void*);
 
+/**
+ * A model function that also calculates the derivatives listed in the `derivatives` global.
+ * The last argument contains one pointer per derivative, derivatives are not calculated
+ * if their pointer is null.
+ */
+typedef void (*VAEVaeJacobian)(uintptr_t, struct VAEFatPtr_f64*, struct VAEFatPtr_f64*, struct VAEFatPtr_f64*, struct VAEFatPtr_i32*, const char**, struct VAEFatPtr_f64*, struct VAEFatPtr_i32*, struct VAEFatPtr_f64*, void*, double**);
+
+/**
+ * Calculates all operating point variables (listed in the `opvars` global). The last argument
+ * contains one pointer per operating point variable, variables are not calculated if their
+ * pointer is null. The arguments are described by the same globals as a model function
+ * with the name `opvars`.
+ */
+typedef void (*VAEVaeOpvars)(uintptr_t, struct VAEFatPtr_f64*, struct VAEFatPtr_f64*, struct VAEFatPtr_f64*, struct VAEFatPtr_i32*, const char**, struct VAEFatPtr_f64*, struct VAEFatPtr_i32*, struct VAEFatPtr_f64*, double**);
+
 typedef struct VAESlice_u8 {
   uint8_t *ptr;
   uintptr_t len;
@@ -94,6 +109,11 @@ typedef struct VAEOpts {
   struct VAESlice_u8 target;
   struct VAESlice_Slice_u8 cg_flags;
   VAEVfs vfs;
+  /**
+   * The parameters that the model functions are differentiated by (in addition to the
+   * voltages). No derivatives are calculated if this is null.
+   */
+  struct VAESlice_Slice_u8 derivative_params;
 } VAEOpts;
 
 /**
@@ -256,6 +276,36 @@ const char *const *verilogae_str_param_groups(const void *lib);
  */
 const char *const *verilogae_nodes(const void *lib);
 
+/**
+ *This function returns a pointer to the `small_signal.unknowns` global
+ * of a VerilogAE model loaded with `load`.
+ *
+ * # Safety
+ *
+ * `lib` must be a valid pointer returned by the `load` functions or `dlopen`
+ */
+const char *const *verilogae_small_signal_unknowns(const void *lib);
+
+/**
+ *This function returns a pointer to the `small_signal.jacobian.rows` global
+ * of a VerilogAE model loaded with `load`.
+ *
+ * # Safety
+ *
+ * `lib` must be a valid pointer returned by the `load` functions or `dlopen`
+ */
+const uint32_t *verilogae_small_signal_rows(const void *lib);
+
+/**
+ *This function returns a pointer to the `small_signal.jacobian.cols` global
+ * of a VerilogAE model loaded with `load`.
+ *
+ * # Safety
+ *
+ * `lib` must be a valid pointer returned by the `load` functions or `dlopen`
+ */
+const uint32_t *verilogae_small_signal_cols(const void *lib);
+
 /**
  *This function returns the value stored in the `functions.cnt` global
  * of a VerilogAE model loaded with `load`.
@@ -316,6 +366,26 @@ uintptr_t verilogae_str_param_cnt(const void *lib);
  */
 uintptr_t verilogae_node_cnt(const void *lib);
 
+/**
+ *This function returns the value stored in the `small_signal.unknowns.cnt` global
+ * of a VerilogAE model loaded with `load`.
+ *
+ * # Safety
+ *
+ * `lib` must be a valid pointer returned by the `load` functions or `dlopen`
+ */
+uintptr_t verilogae_small_signal_unknown_cnt(const void *lib);
+
+/**
+ *This function returns the value stored in the `small_signal.jacobian.rows.cnt` global
+ * of a VerilogAE model loaded with `load`.
+ *
+ * # Safety
+ *
+ * `lib` must be a valid pointer returned by the `load` functions or `dlopen`
+ */
+uintptr_t verilogae_small_signal_entry_cnt(const void *lib);
+
 /**
  *This function returns a pointer to the `params.real` global
  * of a VerilogAE model loaded with `load`.
@@ -415,6 +485,17 @@ const double *verilogae_fun_voltage_defaults(const void *lib, const char *fun);
  */
 const double *verilogae_fun_current_defaults(const void *lib, const char *fun);
 
+/**
+ *This function returns a pointer to the `derivatives` global
+ * of a VerilogAE model loaded with `load`.
+ *
+ * # Safety
+ *
+ * `lib` must be a valid pointer returned by the `load` functions or `dlopen`
+ *`sym_name` must batch the schema fun.{NUM}derivatives
+ */
+const char *const *verilogae_fun_derivatives(const void *lib, const char *fun);
+
 /**
  *This function returns a pointer to the `params.real.cnt` global
  * of a VerilogAE model loaded with `load`.
@@ -505,6 +586,16 @@ uintptr_t verilogae_fun_voltage_default_cnt(const void *lib, const char *fun);
  */
 uintptr_t verilogae_fun_current_default_cnt(const void *lib, const char *fun);
 
+/**
+ *This function returns a pointer to the `derivatives.cnt` global
+ * of a VerilogAE model loaded with `load`.
+ *
+ * # Safety
+ *
+ * `lib` must be a valid pointer returned by the `load` functions or `dlopen`
+ */
+uintptr_t verilogae_fun_derivative_cnt(const void *lib, const char *fun);
+
 /**
  * Obtains a pointer to the modelcard initialization function of a VerilogAE model loaded with `load`.
  *
@@ -523,6 +614,42 @@ VAEModelcardInit verilogae_init_modelcard(const void *lib);
  */
 VAEVaeFun verilogae_fun_ptr(const void *lib, const char *fun);
 
+/**
+ * Obtains a pointer to the jacobian function of a model function of a VerilogAE model
+ * loaded with `load`. The jacobian functions are only generated if the `derivative_params`
+ * option is set (null otherwise).
+ *
+ * # Safety
+ *
+ * `lib` must be a valid pointer returned by the `load` functions or `dlopen`
+ */
+VAEVaeJacobian verilogae_jacobian_ptr(const void *lib, const char *fun);
+
+/**
+ * Obtains a pointer to the function that calculates all operating point variables of a
+ * VerilogAE model loaded with `load`.
+ *
+ * # Safety
+ *
+ * `lib` must be a valid pointer returned by the `load` functions or `dlopen`
+ */
+VAEVaeOpvars verilogae_opvars_ptr(const void *lib);
+
+/**
+ * Obtains a pointer to the function that evaluates the DAE system of a VerilogAE model
+ * loaded with `load`. It has the same signature as the function that calculates the
+ * operating point variables and can be called with `verilogae_call_opvars_parallel`.
+ * Its arguments are described by the globals of a model function with the name
+ * `small_signal`. The outputs are the resistive and reactive residual of each unknown
+ * (`small_signal.unknowns`) followed by the resistive and reactive jacobian entries
+ * (with the rows/columns in `small_signal.jacobian.rows`/`small_signal.jacobian.cols`).
+ *
+ * # Safety
+ *
+ * `lib` must be a valid pointer returned by the `load` functions or `dlopen`
+ */
+VAEVaeOpvars verilogae_small_signal_ptr(const void *lib);
+
 /**
  * # Safety
  * handle must be a valid model compiled with VerilogAE
@@ -530,6 +657,9 @@ VAEVaeFun verilogae_fun_ptr(const void *lib, const char *fun);
 const char *verilogae_module_name(const void *lib);
 
 /**
+ * Evaluates `fun` for `cnt` operating points in parallel. Same as
+ * `verilogae_call_fun_parallel_threads` with one thread per logical core.
+ *
  * # Safety
  *
  * All required parameters must be initialized appropriately
@@ -546,6 +676,93 @@ int32_t verilogae_call_fun_parallel(VAEVaeFun fun,
                                     struct VAEFatPtr_f64 *temp,
                                     void *out);
 
+/**
+ * Evaluates `fun` for `cnt` operating points. The operating points are split into chunks
+ * that are evaluated by `threads` threads in parallel. If `threads` is 0 the number of
+ * threads is chosen automatically (one per logical core).
+ *
+ * # Safety
+ *
+ * All required parameters must be initialized appropriately
+ */
+int32_t verilogae_call_fun_parallel_threads(VAEVaeFun fun,
+                                            uintptr_t cnt,
+                                            uintptr_t threads,
+                                            struct VAEFatPtr_f64 *voltages,
+                                            struct VAEFatPtr_f64 *currents,
+                                            struct VAEFatPtr_f64 *real_params,
+                                            struct VAEFatPtr_i32 *int_params,
+                                            const char **str_params,
+                                            struct VAEFatPtr_f64 *real_dep_break,
+                                            struct VAEFatPtr_i32 *int_dep_break,
+                                            struct VAEFatPtr_f64 *temp,
+                                            void *out);
+
+/**
+ * Same as `verilogae_call_fun_parallel` but also calculates the derivatives of the function.
+ * `derivatives` must contain one (possibly null) pointer for each derivative of the function.
+ *
+ * # Safety
+ *
+ * All required parameters must be initialized appropriately
+ */
+int32_t verilogae_call_jacobian_parallel(VAEVaeJacobian fun,
+                                         uintptr_t cnt,
+                                         struct VAEFatPtr_f64 *voltages,
+                                         struct VAEFatPtr_f64 *currents,
+                                         struct VAEFatPtr_f64 *real_params,
+                                         struct VAEFatPtr_i32 *int_params,
+                                         const char **str_params,
+                                         struct VAEFatPtr_f64 *real_dep_break,
+                                         struct VAEFatPtr_i32 *int_dep_break,
+                                         struct VAEFatPtr_f64 *temp,
+                                         void *out,
+                                         double **derivatives);
+
+/**
+ * Same as `verilogae_call_fun_parallel_threads` but also calculates the derivatives of the
+ * function. `derivatives` must contain one (possibly null) pointer for each derivative of
+ * the function.
+ *
+ * # Safety
+ *
+ * All required parameters must be initialized appropriately
+ */
+int32_t verilogae_call_jacobian_parallel_threads(VAEVaeJacobian fun,
+                                                 uintptr_t cnt,
+                                                 uintptr_t threads,
+                                                 struct VAEFatPtr_f64 *voltages,
+                                                 struct VAEFatPtr_f64 *currents,
+                                                 struct VAEFatPtr_f64 *real_params,
+                                                 struct VAEFatPtr_i32 *int_params,
+                                                 const char **str_params,
+                                                 struct VAEFatPtr_f64 *real_dep_break,
+                                                 struct VAEFatPtr_i32 *int_dep_break,
+                                                 struct VAEFatPtr_f64 *temp,
+                                                 void *out,
+                                                 double **derivatives);
+
+/**
+ * Calculates the operating point variables for `cnt` operating points with `fun` (obtained
+ * from `verilogae_opvars_ptr`). `opvars` must contain one (possibly null) pointer for each
+ * operating point variable. The parallelization is the same as for
+ * `verilogae_call_fun_parallel_threads`.
+ *
+ * # Safety
+ *
+ * All required parameters must be initialized appropriately
+ */
+int32_t verilogae_call_opvars_parallel(VAEVaeOpvars fun,
+                                       uintptr_t cnt,
+                                       uintptr_t threads,
+                                       struct VAEFatPtr_f64 *voltages,
+                                       struct VAEFatPtr_f64 *currents,
+                                       struct VAEFatPtr_f64 *real_params,
+                                       struct VAEFatPtr_i32 *int_params,
+                                       const char **str_params,
+                                       struct VAEFatPtr_f64 *temp,
+                                       double **opvars);
+
 struct VAEOpts *verilogae_new_opts(void);
 
 /**
@@ -576,4 +793,28 @@ void verilogae_free_vfs(VAEVfs vfs);
  * * opts must be valid for reads or null
  * * opts must only contain valid data
  */
-const void *verilogae_load(VAENativePath path, bool full_compile, const struct VAEOpts *opts);
\ No newline at end of file
+const void *verilogae_load(VAENativePath path, bool full_compile, const struct VAEOpts *opts);
+
+/**
+ * Same as `verilogae_load` but compiles the model in memory with a JIT instead of
+ * building (and caching) a shared library. The returned handle can be used with all
+ * functions that accept a handle returned by `verilogae_load`. It must be freed with
+ * `verilogae_unload` (instead of `dlclose`).
+ *
+ * # Safety
+ * * path must be valid for reads
+ * * opts must be valid for reads or null
+ * * opts must only contain valid data
+ */
+const void *verilogae_load_jit(VAENativePath path,
+                               bool full_compile,
+                               const struct VAEOpts *opts);
+
+/**
+ * Frees a model loaded with `verilogae_load` or `verilogae_load_jit`.
+ * None of the functions of the model may be used afterwards.
+ *
+ * # Safety
+ * `lib` must be a valid pointer returned by the `load` functions
+ */
+void verilogae_unload(const void *lib);
\ No newline at end of file
@@ -45,6 +45,17 @@ struct FatPtr {
 
 using VaeFun = void(*)(uintptr_t, FatPtr<double>*, FatPtr<double>*, FatPtr<double>*, FatPtr<int32_t>*, const char**, FatPtr<double>*, FatPtr<int32_t>*, FatPtr<double>*, void*);
 
+/// A model function that also calculates the derivatives listed in the `derivatives` global.
+/// The last argument contains one pointer per derivative, derivatives are not calculated
+/// if their pointer is null.
+using VaeJacobian = void(*)(uintptr_t, FatPtr<double>*, FatPtr<double>*, FatPtr<double>*, FatPtr<int32_t>*, const char**, FatPtr<double>*, FatPtr<int32_t>*, FatPtr<double>*, void*, double**);
+
+/// Calculates all operating point variables (listed in the `opvars` global). The last argument
+/// contains one pointer per operating point variable, variables are not calculated if their
+/// pointer is null. The arguments are described by the same globals as a model function
+/// with the name `opvars`.
+using VaeOpvars = void(*)(uintptr_t, FatPtr<double>*, FatPtr<double>*, FatPtr<double>*, FatPtr<int32_t>*, const char**, FatPtr<double>*, FatPtr<int32_t>*, FatPtr<double>*, double**);
+
 template<typename T>
 struct Slice {
   T *ptr;
@@ -71,6 +82,9 @@ struct Opts {
   Slice<uint8_t> target;
   Slice<Slice<uint8_t>> cg_flags;
   Vfs vfs;
+  /// The parameters that the model functions are differentiated by (in addition to the
+  /// voltages). No derivatives are calculated if this is null.
+  Slice<Slice<uint8_t>> derivative_params;
 };
 
 extern "C" {
@@ -203,6 +217,30 @@ const char *const *verilogae_str_param_groups(const void *lib);
 /// `lib` must be a valid pointer returned by the `load` functions or `dlopen`
 const char *const *verilogae_nodes(const void *lib);
 
+///This function returns a pointer to the `small_signal.unknowns` global
+/// of a VerilogAE model loaded with `load`.
+///
+/// # Safety
+///
+/// `lib` must be a valid pointer returned by the `load` functions or `dlopen`
+const char *const *verilogae_small_signal_unknowns(const void *lib);
+
+///This function returns a pointer to the `small_signal.jacobian.rows` global
+/// of a VerilogAE model loaded with `load`.
+///
+/// # Safety
+///
+/// `lib` must be a valid pointer returned by the `load` functions or `dlopen`
+const uint32_t *verilogae_small_signal_rows(const void *lib);
+
+///This function returns a pointer to the `small_signal.jacobian.cols` global
+/// of a VerilogAE model loaded with `load`.
+///
+/// # Safety
+///
+/// `lib` must be a valid pointer returned by the `load` functions or `dlopen`
+const uint32_t *verilogae_small_signal_cols(const void *lib);
+
 ///This function returns the value stored in the `functions.cnt` global
 /// of a VerilogAE model loaded with `load`.
 ///
@@ -251,6 +289,22 @@ uintptr_t verilogae_str_param_cnt(const void *lib);
 /// `lib` must be a valid pointer returned by the `load` functions or `dlopen`
 uintptr_t verilogae_node_cnt(const void *lib);
 
+///This function returns the value stored in the `small_signal.unknowns.cnt` global
+/// of a VerilogAE model loaded with `load`.
+///
+/// # Safety
+///
+/// `lib` must be a valid pointer returned by the `load` functions or `dlopen`
+uintptr_t verilogae_small_signal_unknown_cnt(const void *lib);
+
+///This function returns the value stored in the `small_signal.jacobian.rows.cnt` global
+/// of a VerilogAE model loaded with `load`.
+///
+/// # Safety
+///
+/// `lib` must be a valid pointer returned by the `load` functions or `dlopen`
+uintptr_t verilogae_small_signal_entry_cnt(const void *lib);
+
 ///This function returns a pointer to the `params.real` global
 /// of a VerilogAE model loaded with `load`.
 ///
@@ -332,6 +386,15 @@ const double *verilogae_fun_voltage_defaults(const void *lib, const char *fun);
 ///`sym_name` must batch the schema fun.{NUM}currents.default
 const double *verilogae_fun_current_defaults(const void *lib, const char *fun);
 
+///This function returns a pointer to the `derivatives` global
+/// of a VerilogAE model loaded with `load`.
+///
+/// # Safety
+///
+/// `lib` must be a valid pointer returned by the `load` functions or `dlopen`
+///`sym_name` must batch the schema fun.{NUM}derivatives
+const char *const *verilogae_fun_derivatives(const void *lib, const char *fun);
+
 ///This function returns a pointer to the `params.real.cnt` global
 /// of a VerilogAE model loaded with `load`.
 ///
@@ -404,6 +467,14 @@ uintptr_t verilogae_fun_voltage_default_cnt(const void *lib, const char *fun);
 /// `lib` must be a valid pointer returned by the `load` functions or `dlopen`
 uintptr_t verilogae_fun_current_default_cnt(const void *lib, const char *fun);
 
+///This function returns a pointer to the `derivatives.cnt` global
+/// of a VerilogAE model loaded with `load`.
+///
+/// # Safety
+///
+/// `lib` must be a valid pointer returned by the `load` functions or `dlopen`
+uintptr_t verilogae_fun_derivative_cnt(const void *lib, const char *fun);
+
 /// Obtains a pointer to the modelcard initialization function of a VerilogAE model loaded with `load`.
 ///
 /// # Safety
@@ -418,10 +489,43 @@ ModelcardInit verilogae_init_modelcard(const void *lib);
 /// `lib` must be a valid pointer returned by the `load` functions or `dlopen`
 VaeFun verilogae_fun_ptr(const void *lib, const char *fun);
 
+/// Obtains a pointer to the jacobian function of a model function of a VerilogAE model
+/// loaded with `load`. The jacobian functions are only generated if the `derivative_params`
+/// option is set (null otherwise).
+///
+/// # Safety
+///
+/// `lib` must be a valid pointer returned by the `load` functions or `dlopen`
+VaeJacobian verilogae_jacobian_ptr(const void *lib, const char *fun);
+
+/// Obtains a pointer to the function that calculates all operating point variables of a
+/// VerilogAE model loaded with `load`.
+///
+/// # Safety
+///
+/// `lib` must be a valid pointer returned by the `load` functions or `dlopen`
+VaeOpvars verilogae_opvars_ptr(const void *lib);
+
+/// Obtains a pointer to the function that evaluates the DAE system of a VerilogAE model
+/// loaded with `load`. It has the same signature as the function that calculates the
+/// operating point variables and can be called with `verilogae_call_opvars_parallel`.
+/// Its arguments are described by the globals of a model function with the name
+/// `small_signal`. The outputs are the resistive and reactive residual of each unknown
+/// (`small_signal.unknowns`) followed by the resistive and reactive jacobian entries
+/// (with the rows/columns in `small_signal.jacobian.rows`/`small_signal.jacobian.cols`).
+///
+/// # Safety
+///
+/// `lib` must be a valid pointer returned by the `load` functions or `dlopen`
+VaeOpvars verilogae_small_signal_ptr(const void *lib);
+
 /// # Safety
 /// handle must be a valid model compiled with VerilogAE
 const char *verilogae_module_name(const void *lib);
 
+/// Evaluates `fun` for `cnt` operating points in parallel. Same as
+/// `verilogae_call_fun_parallel_threads` with one thread per logical core.
+///
 /// # Safety
 ///
 /// All required parameters must be initialized appropriately
@@ -437,6 +541,85 @@ int32_t verilogae_call_fun_parallel(VaeFun fun,
                                     FatPtr<double> *temp,
                                     void *out);
 
+/// Evaluates `fun` for `cnt` operating points. The operating points are split into chunks
+/// that are evaluated by `threads` threads in parallel. If `threads` is 0 the number of
+/// threads is chosen automatically (one per logical core).
+///
+/// # Safety
+///
+/// All required parameters must be initialized appropriately
+int32_t verilogae_call_fun_parallel_threads(VaeFun fun,
+                                            uintptr_t cnt,
+                                            uintptr_t threads,
+                                            FatPtr<double> *voltages,
+                                            FatPtr<double> *currents,
+                                            FatPtr<double> *real_params,
+                                            FatPtr<int32_t> *int_params,
+                                            const char **str_params,
+                                            FatPtr<double> *real_dep_break,
+                                            FatPtr<int32_t> *int_dep_break,
+                                            FatPtr<double> *temp,
+                                            void *out);
+
+/// Same as `verilogae_call_fun_parallel` but also calculates the derivatives of the function.
+/// `derivatives` must contain one (possibly null) pointer for each derivative of the function.
+///
+/// # Safety
+///
+/// All required parameters must be initialized appropriately
+int32_t verilogae_call_jacobian_parallel(VaeJacobian fun,
+                                         uintptr_t cnt,
+                                         FatPtr<double> *voltages,
+                                         FatPtr<double> *currents,
+                                         FatPtr<double> *real_params,
+                                         FatPtr<int32_t> *int_params,
+                                         const char **str_params,
+                                         FatPtr<double> *real_dep_break,
+                                         FatPtr<int32_t> *int_dep_break,
+                                         FatPtr<double> *temp,
+                                         void *out,
+                                         double **derivatives);
+
+/// Same as `verilogae_call_fun_parallel_threads` but also calculates the derivatives of the
+/// function. `derivatives` must contain one (possibly null) pointer for each derivative of
+/// the function.
+///
+/// # Safety
+///
+/// All required parameters must be initialized appropriately
+int32_t verilogae_call_jacobian_parallel_threads(VaeJacobian fun,
+                                                 uintptr_t cnt,
+                                                 uintptr_t threads,
+                                                 FatPtr<double> *voltages,
+                                                 FatPtr<double> *currents,
+                                                 FatPtr<double> *real_params,
+                                                 FatPtr<int32_t> *int_params,
+                                                 const char **str_params,
+                                                 FatPtr<double> *real_dep_break,
+                                                 FatPtr<int32_t> *int_dep_break,
+                                                 FatPtr<double> *temp,
+                                                 void *out,
+                                                 double **derivatives);
+
+/// Calculates the operating point variables for `cnt` operating points with `fun` (obtained
+/// from `verilogae_opvars_ptr`). `opvars` must contain one (possibly null) pointer for each
+/// operating point variable. The parallelization is the same as for
+/// `verilogae_call_fun_parallel_threads`.
+///
+/// # Safety
+///
+/// All required parameters must be initialized appropriately
+int32_t verilogae_call_opvars_parallel(VaeOpvars fun,
+                                       uintptr_t cnt,
+                                       uintptr_t threads,
+                                       FatPtr<double> *voltages,
+                                       FatPtr<double> *currents,
+                                       FatPtr<double> *real_params,
+                                       FatPtr<int32_t> *int_params,
+                                       const char **str_params,
+                                       FatPtr<double> *temp,
+                                       double **opvars);
+
 Opts *verilogae_new_opts();
 
 /// # Safety
@@ -461,6 +644,24 @@ void verilogae_free_vfs(Vfs vfs);
 /// * opts must only contain valid data
 const void *verilogae_load(NativePath path, bool full_compile, const Opts *opts);
 
+/// Same as `verilogae_load` but compiles the model in memory with a JIT instead of
+/// building (and caching) a shared library. The returned handle can be used with all
+/// functions that accept a handle returned by `verilogae_load`. It must be freed with
+/// `verilogae_unload` (instead of `dlclose`).
+///
+/// # Safety
+/// * path must be valid for reads
+/// * opts must be valid for reads or null
+/// * opts must only contain valid data
+const void *verilogae_load_jit(NativePath path, bool full_compile, const Opts *opts);
+
+/// Frees a model loaded with `verilogae_load` or `verilogae_load_jit`.
+/// None of the functions of the model may be used afterwards.
+///
+/// # Safety
+/// `lib` must be a valid pointer returned by the `load` functions
+void verilogae_unload(const void *lib);
+
 } // extern "C"
 
 } // namespace vae
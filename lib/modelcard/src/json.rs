@@ -0,0 +1,140 @@
+use crate::Value;
+
+/// Parses `src`, which must contain a single JSON object that maps parameter names to
+/// numbers, strings or booleans. Booleans are returned as integers.
+pub fn parse_json(src: &str) -> Result<Vec<(String, Value)>, String> {
+    JsonParser { src, pos: 0 }.object()
+}
+
+/// A parser for the subset of JSON used by model cards: a flat object whose values are
+/// numbers, strings or booleans.
+struct JsonParser<'a> {
+    src: &'a str,
+    pos: usize,
+}
+
+impl JsonParser<'_> {
+    fn object(&mut self) -> Result<Vec<(String, Value)>, String> {
+        let mut res = Vec::new();
+        self.expect(b'{')?;
+        self.skip_whitespace();
+        if self.peek() == Some(b'}') {
+            self.pos += 1;
+        } else {
+            loop {
+                let name = self.string()?;
+                self.expect(b':')?;
+                let val = self.value()?;
+                res.push((name, val));
+                self.skip_whitespace();
+                match self.peek() {
+                    Some(b',') => self.pos += 1,
+                    Some(b'}') => {
+                        self.pos += 1;
+                        break;
+                    }
+                    _ => return self.error("expected ',' or '}'"),
+                }
+            }
+        }
+
+        self.skip_whitespace();
+        if self.pos != self.src.len() {
+            return self.error("expected end of file");
+        }
+        Ok(res)
+    }
+
+    fn value(&mut self) -> Result<Value, String> {
+        self.skip_whitespace();
+        if self.peek() == Some(b'"') {
+            return Ok(Value::Str(self.string()?));
+        }
+
+        let rest = &self.src[self.pos..];
+        for (literal, val) in [("true", 1), ("false", 0)] {
+            if rest.starts_with(literal) {
+                self.pos += literal.len();
+                return Ok(Value::Int(val));
+            }
+        }
+
+        let end = rest
+            .find(|c: char| !matches!(c, '0'..='9' | '-' | '+' | '.' | 'e' | 'E'))
+            .unwrap_or(rest.len());
+        match rest[..end].parse() {
+            Ok(val) => {
+                self.pos += end;
+                Ok(Value::Real(val))
+            }
+            Err(_) => self.error("expected a number or a string"),
+        }
+    }
+
+    fn string(&mut self) -> Result<String, String> {
+        self.expect(b'"')?;
+        let mut res = String::new();
+        loop {
+            let rest = &self.src[self.pos..];
+            let end = match rest.find(['"', '\\']) {
+                Some(end) => end,
+                None => return self.error("unterminated string"),
+            };
+            res.push_str(&rest[..end]);
+            self.pos += end + 1;
+            if rest.as_bytes()[end] == b'"' {
+                return Ok(res);
+            }
+
+            let escaped = match self.peek() {
+                Some(b'"') => '"',
+                Some(b'\\') => '\\',
+                Some(b'/') => '/',
+                Some(b'b') => '\u{8}',
+                Some(b'f') => '\u{c}',
+                Some(b'n') => '\n',
+                Some(b'r') => '\r',
+                Some(b't') => '\t',
+                Some(b'u') => {
+                    let code = self.src.get(self.pos + 1..self.pos + 5);
+                    let code = code.and_then(|code| u32::from_str_radix(code, 16).ok());
+                    match code.and_then(char::from_u32) {
+                        Some(c) => {
+                            self.pos += 4;
+                            c
+                        }
+                        None => return self.error("invalid unicode escape"),
+                    }
+                }
+                _ => return self.error("invalid escape sequence"),
+            };
+            self.pos += 1;
+            res.push(escaped);
+        }
+    }
+
+    fn expect(&mut self, c: u8) -> Result<(), String> {
+        self.skip_whitespace();
+        if self.peek() == Some(c) {
+            self.pos += 1;
+            Ok(())
+        } else {
+            self.error(&format!("expected '{}'", c as char))
+        }
+    }
+
+    fn skip_whitespace(&mut self) {
+        while matches!(self.peek(), Some(b' ' | b'\t' | b'\n' | b'\r')) {
+            self.pos += 1;
+        }
+    }
+
+    fn peek(&self) -> Option<u8> {
+        self.src.as_bytes().get(self.pos).copied()
+    }
+
+    fn error<T>(&self, msg: &str) -> Result<T, String> {
+        let line = self.src[..self.pos].matches('\n').count() + 1;
+        Err(format!("{msg} in line {line}"))
+    }
+}
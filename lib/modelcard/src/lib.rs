@@ -0,0 +1,19 @@
+//! Parsers for the model card formats supported by VerilogAE: SPICE `.model` statements
+//! and flat JSON objects that map parameter names to their values.
+
+mod json;
+mod spice;
+
+#[cfg(test)]
+mod tests;
+
+pub use json::parse_json;
+pub use spice::{parse_models, parse_number, select_bin, write_model, SpiceModel, BIN_PARAMS};
+
+/// The value of a parameter in a model card.
+#[derive(Clone, Debug, PartialEq)]
+pub enum Value {
+    Real(f64),
+    Int(i32),
+    Str(String),
+}
@@ -0,0 +1,277 @@
+//! A minimal parser for SPICE `.model` cards. Only the `.model` statements of a file are
+//! read, everything else (subcircuits, includes, ...) is ignored.
+
+use std::fmt::Write;
+
+use crate::Value;
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct SpiceModel {
+    pub name: String,
+    pub params: Vec<(String, Value)>,
+}
+
+impl SpiceModel {
+    fn num_param(&self, name: &str) -> Option<f64> {
+        self.params.iter().find_map(|(param, val)| match val {
+            Value::Real(val) if param.eq_ignore_ascii_case(name) => Some(*val),
+            _ => None,
+        })
+    }
+
+    /// Whether the geometry `l`/`w` falls into the bin of this model. Missing bounds
+    /// (and missing geometry) are treated as unbounded. Like in BSIM the lower bound is
+    /// inclusive and the upper bound is exclusive.
+    fn contains(&self, l: Option<f64>, w: Option<f64>) -> bool {
+        let in_bin = |val: Option<f64>, min: &str, max: &str| {
+            let val = match val {
+                Some(val) => val,
+                None => return true,
+            };
+            self.num_param(min).map_or(true, |min| min <= val)
+                && self.num_param(max).map_or(true, |max| val < max)
+        };
+        in_bin(l, "lmin", "lmax") && in_bin(w, "wmin", "wmax")
+    }
+}
+
+/// Parameters that describe the geometry range of a bin. These are used for bin selection
+/// and are ignored if the model does not define them.
+pub const BIN_PARAMS: [&str; 4] = ["lmin", "lmax", "wmin", "wmax"];
+
+/// Parses all `.model` statements in `src`.
+pub fn parse_models(src: &str) -> Result<Vec<SpiceModel>, String> {
+    let mut statements: Vec<(usize, String)> = Vec::new();
+    for (line_nr, line) in src.lines().enumerate() {
+        let line = line.trim();
+        if line.starts_with('*') {
+            continue;
+        }
+        // $ and ; start inline comments
+        let line = line.split(['$', ';']).next().unwrap_or_default();
+        match line.strip_prefix('+') {
+            Some(continuation) => {
+                if let Some((_, statement)) = statements.last_mut() {
+                    statement.push(' ');
+                    statement.push_str(continuation);
+                }
+            }
+            None => statements.push((line_nr + 1, line.to_owned())),
+        }
+    }
+
+    let mut models = Vec::new();
+    for (line_nr, statement) in statements {
+        let tokens = tokenize(&statement).map_err(|err| format!("line {line_nr}: {err}"))?;
+        match tokens.first() {
+            Some(Token::Word(word)) if word.eq_ignore_ascii_case(".model") => (),
+            _ => continue,
+        }
+        let model = parse_model(&tokens[1..]).map_err(|err| format!("line {line_nr}: {err}"))?;
+        models.push(model);
+    }
+
+    Ok(models)
+}
+
+/// Selects the model for a device with the geometry `l`/`w` from `models`. If `name` is
+/// provided only models called `name` (or binned models `name.<n>`) are considered.
+pub fn select_bin<'a>(
+    models: &'a [SpiceModel],
+    name: Option<&str>,
+    l: Option<f64>,
+    w: Option<f64>,
+) -> Result<&'a SpiceModel, String> {
+    let mut candidates = models.iter().filter(|model| match name {
+        Some(name) => {
+            let base_name = model.name.split_once('.').map_or(&*model.name, |(base, _)| base);
+            model.name.eq_ignore_ascii_case(name) || base_name.eq_ignore_ascii_case(name)
+        }
+        None => true,
+    });
+
+    let first = match candidates.next() {
+        Some(model) => model,
+        None => {
+            return Err(match name {
+                Some(name) => format!("no model card called '{name}' was found"),
+                None => "no model card was found".to_owned(),
+            })
+        }
+    };
+
+    let candidates: Vec<_> =
+        [first].into_iter().chain(candidates).filter(|model| model.contains(l, w)).collect();
+    match *candidates {
+        [model] => Ok(model),
+        [] => Err(format!("no model card matches the geometry {}", geometry(l, w))),
+        _ => {
+            let mut msg = format!("multiple model cards match the geometry {}: ", geometry(l, w));
+            for (i, model) in candidates.iter().enumerate() {
+                if i != 0 {
+                    msg.push_str(", ");
+                }
+                write!(msg, "'{}'", model.name).unwrap();
+            }
+            Err(msg)
+        }
+    }
+}
+
+/// Writes a `.model` statement for the model `name` of type `ty` with one parameter per
+/// continuation line. Real numbers are written with full precision.
+pub fn write_model<'a>(
+    name: &str,
+    ty: &str,
+    params: impl IntoIterator<Item = (&'a str, Value)>,
+) -> String {
+    let mut res = format!(".model {name} {ty}\n");
+    for (param, val) in params {
+        match val {
+            Value::Real(val) => writeln!(res, "+ {param}={val:?}"),
+            Value::Int(val) => writeln!(res, "+ {param}={val}"),
+            Value::Str(val) => writeln!(res, "+ {param}=\"{val}\""),
+        }
+        .unwrap();
+    }
+    res
+}
+
+fn geometry(l: Option<f64>, w: Option<f64>) -> String {
+    match (l, w) {
+        (Some(l), Some(w)) => format!("l={l}, w={w}"),
+        (Some(l), None) => format!("l={l}"),
+        (None, Some(w)) => format!("w={w}"),
+        (None, None) => "(no l or w specified)".to_owned(),
+    }
+}
+
+#[derive(PartialEq)]
+enum Token {
+    Word(String),
+    Str(String),
+    Eq,
+}
+
+fn tokenize(statement: &str) -> Result<Vec<Token>, String> {
+    let mut tokens = Vec::new();
+    let mut chars = statement.chars().peekable();
+    while let Some(c) = chars.next() {
+        match c {
+            // parenthesis around the parameter list and commas between parameters are optional
+            c if c.is_whitespace() || matches!(c, '(' | ')' | ',') => (),
+            '=' => tokens.push(Token::Eq),
+            '"' | '\'' => {
+                let mut val = String::new();
+                loop {
+                    match chars.next() {
+                        Some(end) if end == c => break,
+                        Some(c) => val.push(c),
+                        None => return Err("unterminated string".to_owned()),
+                    }
+                }
+                tokens.push(Token::Str(val))
+            }
+            c => {
+                let mut word = c.to_string();
+                while let Some(&c) = chars.peek() {
+                    if c.is_whitespace() || matches!(c, '(' | ')' | ',' | '=' | '"' | '\'') {
+                        break;
+                    }
+                    word.push(c);
+                    chars.next();
+                }
+                tokens.push(Token::Word(word))
+            }
+        }
+    }
+    Ok(tokens)
+}
+
+fn parse_model(tokens: &[Token]) -> Result<SpiceModel, String> {
+    let (name, tokens) = match tokens {
+        [Token::Word(name), Token::Word(_ty), tokens @ ..] => (name.clone(), tokens),
+        _ => return Err("expected '.model <name> <type>'".to_owned()),
+    };
+
+    let mut params = Vec::new();
+    let mut tokens = tokens.iter();
+    while let Some(token) = tokens.next() {
+        let param = match token {
+            Token::Word(param) => param,
+            _ => return Err(format!("expected a parameter name in model '{name}'")),
+        };
+        if tokens.next() != Some(&Token::Eq) {
+            return Err(format!("expected '=' after parameter '{param}' in model '{name}'"));
+        }
+        let val = match tokens.next() {
+            Some(Token::Str(val)) => Value::Str(val.clone()),
+            Some(Token::Word(val)) => match parse_number(val) {
+                Some(val) => Value::Real(val),
+                None => Value::Str(val.clone()),
+            },
+            _ => return Err(format!("expected a value for parameter '{param}' in model '{name}'")),
+        };
+        params.push((param.clone(), val));
+    }
+
+    Ok(SpiceModel { name, params })
+}
+
+/// Parses a SPICE number like `1.5e-3`, `10p` or `2.2megohm`. Letters following the
+/// scale factor are ignored (like units). Scale factors that are powers of ten are added
+/// to the exponent, so that `1.5n` and `1.5e-9` are parsed to exactly the same value.
+pub fn parse_number(src: &str) -> Option<f64> {
+    let bytes = src.as_bytes();
+    let mut end = 0;
+    if matches!(bytes.first(), Some(b'+' | b'-')) {
+        end += 1;
+    }
+    let digits_start = end;
+    while end < bytes.len() && (bytes[end].is_ascii_digit() || bytes[end] == b'.') {
+        end += 1;
+    }
+    if end == digits_start {
+        return None;
+    }
+    let mantissa = &src[..end];
+    let mut exp = 0;
+    if matches!(bytes.get(end), Some(b'e' | b'E')) {
+        let mut exp_end = end + 1;
+        if matches!(bytes.get(exp_end), Some(b'+' | b'-')) {
+            exp_end += 1;
+        }
+        if bytes.get(exp_end).map_or(false, u8::is_ascii_digit) {
+            while exp_end < bytes.len() && bytes[exp_end].is_ascii_digit() {
+                exp_end += 1;
+            }
+            exp = src[end + 1..exp_end].parse().ok()?;
+            end = exp_end;
+        }
+    }
+
+    let suffix = src[end..].to_ascii_lowercase();
+    let mut factor = 1.0;
+    if suffix.starts_with("meg") {
+        exp += 6;
+    } else if suffix.starts_with("mil") {
+        factor = 25.4e-6;
+    } else {
+        exp += match suffix.bytes().next() {
+            Some(b't') => 12,
+            Some(b'g') => 9,
+            Some(b'k') => 3,
+            Some(b'm') => -3,
+            Some(b'u') => -6,
+            Some(b'n') => -9,
+            Some(b'p') => -12,
+            Some(b'f') => -15,
+            Some(b'a') => -18,
+            Some(c) if c.is_ascii_alphabetic() => 0,
+            None => 0,
+            Some(_) => return None,
+        }
+    }
+    let val: f64 = format!("{mantissa}e{exp}").parse().ok()?;
+    Some(val * factor)
+}
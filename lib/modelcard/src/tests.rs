@@ -0,0 +1,218 @@
+use crate::{parse_json, parse_models, parse_number, select_bin, write_model, SpiceModel, Value};
+
+fn assert_close(val: Option<f64>, expected: f64) {
+    let val = val.unwrap_or_else(|| panic!("failed to parse {expected}"));
+    assert!((val - expected).abs() <= 1e-12 * expected.abs(), "{val} != {expected}");
+}
+
+#[test]
+fn number_suffixes() {
+    assert_close(parse_number("1.5e-3"), 1.5e-3);
+    assert_close(parse_number("-2E+2"), -200.0);
+    assert_close(parse_number("+.5"), 0.5);
+    assert_close(parse_number("10p"), 10e-12);
+    assert_close(parse_number("3f"), 3e-15);
+    assert_close(parse_number("4n"), 4e-9);
+    assert_close(parse_number("1u"), 1e-6);
+    assert_close(parse_number("2m"), 2e-3);
+    assert_close(parse_number("2M"), 2e-3);
+    assert_close(parse_number("5k"), 5e3);
+    assert_close(parse_number("2.2meg"), 2.2e6);
+    assert_close(parse_number("2.2MEGohm"), 2.2e6);
+    assert_close(parse_number("1g"), 1e9);
+    assert_close(parse_number("1t"), 1e12);
+    assert_close(parse_number("7a"), 7e-18);
+    assert_close(parse_number("2mil"), 50.8e-6);
+    // letters after the scale factor are units
+    assert_close(parse_number("10pF"), 10e-12);
+    assert_close(parse_number("3V"), 3.0);
+    // an exponent without digits is a unit
+    assert_close(parse_number("1e"), 1.0);
+    assert_close(parse_number("1e-3k"), 1.0);
+
+    assert_eq!(parse_number("abc"), None);
+    assert_eq!(parse_number("-"), None);
+    assert_eq!(parse_number(""), None);
+    assert_eq!(parse_number("1.0.0"), None);
+    assert_eq!(parse_number("1%"), None);
+
+    // scale factors do not introduce rounding errors
+    assert_eq!(parse_number("1.5n"), Some(1.5e-9));
+    assert_eq!(parse_number("0.1meg"), Some(1e5));
+    assert_eq!(parse_number("3.3e-2u"), Some(3.3e-8));
+}
+
+#[test]
+fn continuation_lines() {
+    let src = "* a comment
+.model nmos_a nmos level=54 $ inline comment
++ vth0=0.4 ; another comment
+* a comment between continuation lines
++ (tox = 1.5n, version=\"4.8\")
+R1 a b 10k
++ x=1
+.MODEL pnp_b PNP(is=1e-16
++bf=100)
+.subckt foo a b
+.ends
+";
+    let models = parse_models(src).unwrap();
+    let expected = [
+        SpiceModel {
+            name: "nmos_a".to_owned(),
+            params: vec![
+                ("level".to_owned(), Value::Real(54.0)),
+                ("vth0".to_owned(), Value::Real(0.4)),
+                ("tox".to_owned(), Value::Real(1.5e-9)),
+                ("version".to_owned(), Value::Str("4.8".to_owned())),
+            ],
+        },
+        SpiceModel {
+            name: "pnp_b".to_owned(),
+            params: vec![
+                ("is".to_owned(), Value::Real(1e-16)),
+                ("bf".to_owned(), Value::Real(100.0)),
+            ],
+        },
+    ];
+    assert_eq!(models, expected);
+}
+
+#[test]
+fn invalid_models() {
+    let err = |src| match parse_models(src) {
+        Ok(_) => panic!("{src} was parsed successfully"),
+        Err(err) => err,
+    };
+    assert_eq!(err(".model foo"), "line 1: expected '.model <name> <type>'");
+    assert_eq!(
+        err("\n.model foo nmos\n+ vth0"),
+        "line 2: expected '=' after parameter 'vth0' in model 'foo'"
+    );
+    assert_eq!(
+        err(".model foo nmos vth0="),
+        "line 1: expected a value for parameter 'vth0' in model 'foo'"
+    );
+    assert_eq!(err(".model foo nmos version=\"4.8"), "line 1: unterminated string");
+    // statements other than .model are not tokenized
+    assert_eq!(parse_models("R1 a b 'unterminated").unwrap_or_default(), Vec::new());
+}
+
+const BINNED: &str = "
+.model nch.1 nmos lmin=1u lmax=10u wmin=1u wmax=10u vth0=0.1
+.model nch.2 nmos lmin=10u lmax=100u wmin=1u wmax=10u vth0=0.2
+.model nch.3 nmos lmin=1u lmax=100u wmin=10u wmax=100u vth0=0.3
+.model pch pmos vth0=-0.4
+";
+
+fn select<'a>(
+    models: &'a [SpiceModel],
+    name: Option<&str>,
+    l: Option<f64>,
+    w: Option<f64>,
+) -> &'a str {
+    match select_bin(models, name, l, w) {
+        Ok(model) => &model.name,
+        Err(err) => panic!("{err}"),
+    }
+}
+
+#[test]
+fn bin_selection() {
+    let models = parse_models(BINNED).unwrap();
+    assert_eq!(select(&models, Some("nch"), Some(5e-6), Some(5e-6)), "nch.1");
+    assert_eq!(select(&models, Some("NCH"), Some(50e-6), Some(5e-6)), "nch.2");
+    assert_eq!(select(&models, Some("nch"), Some(50e-6), Some(50e-6)), "nch.3");
+    // the lower bound is inclusive and the upper bound is exclusive
+    assert_eq!(select(&models, Some("nch"), Some(10e-6), Some(5e-6)), "nch.2");
+    assert_eq!(select(&models, Some("nch"), Some(1e-6), Some(1e-6)), "nch.1");
+    // an exact name selects a single bin
+    assert_eq!(select(&models, Some("nch.2"), None, None), "nch.2");
+    // models without bounds match any geometry
+    assert_eq!(select(&models, Some("pch"), Some(1.0), Some(1.0)), "pch");
+}
+
+#[test]
+fn bin_selection_errors() {
+    let models = parse_models(BINNED).unwrap();
+    let err = |name, l, w| match select_bin(&models, name, l, w) {
+        Ok(model) => panic!("selected {}", model.name),
+        Err(err) => err,
+    };
+
+    assert_eq!(err(Some("foo"), None, None), "no model card called 'foo' was found");
+    assert_eq!(select_bin(&[], None, None, None).unwrap_err(), "no model card was found");
+    assert_eq!(
+        err(Some("nch"), Some(0.5e-6), Some(5e-6)),
+        "no model card matches the geometry l=0.0000005, w=0.000005"
+    );
+    assert_eq!(err(Some("nch"), Some(100e-6), None), "no model card matches the geometry l=0.0001");
+    // missing geometry matches every bin
+    assert_eq!(
+        err(Some("nch"), None, None),
+        "multiple model cards match the geometry (no l or w specified): 'nch.1', 'nch.2', \
+         'nch.3'"
+    );
+    assert_eq!(
+        err(None, Some(5e-6), Some(5e-6)),
+        "multiple model cards match the geometry l=0.000005, w=0.000005: 'nch.1', 'pch'"
+    );
+
+    let overlapping =
+        parse_models(".model nch.1 nmos lmin=1u lmax=20u\n.model nch.2 nmos lmin=10u lmax=100u")
+            .unwrap();
+    assert_eq!(select(&overlapping, Some("nch"), Some(5e-6), None), "nch.1");
+    assert_eq!(
+        select_bin(&overlapping, Some("nch"), Some(15e-6), None).unwrap_err(),
+        "multiple model cards match the geometry l=0.000015: 'nch.1', 'nch.2'"
+    );
+}
+
+#[test]
+fn write_and_parse() {
+    let params = [
+        ("r", Value::Real(1.0 / 3.0)),
+        ("tiny", Value::Real(1.234e-300)),
+        ("mult", Value::Int(2)),
+        ("version", Value::Str("1.0".to_owned())),
+    ];
+    let src = write_model("res", "resistor", params.clone());
+    assert_eq!(
+        src,
+        ".model res resistor\n+ r=0.3333333333333333\n+ tiny=1.234e-300\n+ mult=2\n+ \
+         version=\"1.0\"\n"
+    );
+
+    let models = parse_models(&src).unwrap();
+    let expected = params.map(|(name, val)| match val {
+        Value::Int(val) => (name.to_owned(), Value::Real(val.into())),
+        val => (name.to_owned(), val),
+    });
+    assert_eq!(models, [SpiceModel { name: "res".to_owned(), params: expected.to_vec() }]);
+}
+
+#[test]
+fn json() {
+    let src = r#"{
+        "r": 1.5e3,
+        "mult": 2,
+        "enable": true,
+        "disable": false,
+        "name": "a \"quoted\" \\ µ string"
+    }"#;
+    let expected = vec![
+        ("r".to_owned(), Value::Real(1.5e3)),
+        ("mult".to_owned(), Value::Real(2.0)),
+        ("enable".to_owned(), Value::Int(1)),
+        ("disable".to_owned(), Value::Int(0)),
+        ("name".to_owned(), Value::Str("a \"quoted\" \\ \u{b5} string".to_owned())),
+    ];
+    assert_eq!(parse_json(src).unwrap(), expected);
+    assert_eq!(parse_json(" { } ").unwrap(), Vec::new());
+
+    assert_eq!(parse_json(r#"{"a": 1,}"#).unwrap_err(), "expected '\"' in line 1");
+    assert_eq!(parse_json("{\n\"a\": x}").unwrap_err(), "expected a number or a string in line 2");
+    assert_eq!(parse_json(r#"{"a": 1} 2"#).unwrap_err(), "expected end of file in line 1");
+    assert_eq!(parse_json(r#"{"a": "\q"}"#).unwrap_err(), "invalid escape sequence in line 1");
+    assert_eq!(parse_json(r#"{"a": "b"#).unwrap_err(), "unterminated string in line 1");
+}
@@ -1,3 +1,4 @@
+use std::collections::hash_map::Entry;
 use std::ops::{Index, IndexMut};
 
 use ahash::AHashMap;
@@ -38,9 +39,14 @@ pub struct Circuit {
 impl Circuit {
     /// Creates a new empty circuit
     pub fn new(name: String, earena: &mut Arena) -> Circuit {
+        Circuit::with_ctx(name, earena.add_ctx())
+    }
+
+    /// Creates a new empty circuit whose parameters are defined in `ctx`
+    pub fn with_ctx(name: String, ctx: CircuitParamCtx) -> Circuit {
         let mut circ = Circuit {
             name,
-            ctx: earena.add_ctx(),
+            ctx,
             nodes: TiSet::with_capacity(16),
             devices: TiMap::with_capacity(32),
             models: TiVec::with_capacity(16),
@@ -106,10 +112,14 @@ impl Circuit {
         name: String,
         item: impl Into<NameSpaceEntry>,
     ) -> Result<()> {
-        let item = item.into();
-        if let Some(old) = self.namespace.insert(name.clone(), item) {
-            let old = old.kind();
-            unreachable!("an {old} '{name}' was already declared in this circuit")
+        match self.namespace.entry(name) {
+            Entry::Occupied(entry) => {
+                let old = entry.get().kind();
+                bail!("an {old} '{}' was already declared in this circuit", entry.key())
+            }
+            Entry::Vacant(entry) => {
+                entry.insert(item.into());
+            }
         }
         Ok(())
     }
@@ -284,6 +294,7 @@ impl Circuit {
         let dev_info = DeviceInfo {
             va_file: None,
             terminals: dev_impl.get_terminals(),
+            terminal_aliases: dev_impl.get_terminal_aliases(),
             parameters: dev_impl.get_params(),
             dev_impl,
             name,
@@ -334,6 +345,7 @@ impl Circuit {
             let dev_info = DeviceInfo {
                 va_file: None,
                 terminals: dev_impl.get_terminals(),
+                terminal_aliases: dev_impl.get_terminal_aliases(),
                 parameters: dev_impl.get_params(),
                 dev_impl,
                 name,
@@ -360,6 +372,20 @@ impl Circuit {
         self.devices.index(name)
     }
 
+    /// Lookup an instance by name
+    ///
+    /// # Returns
+    ///
+    /// The instance in this circuit that has the name `name`
+    ///
+    /// If no such instance exists returns `None`
+    pub fn lookup_instance(&self, name: &str) -> Option<InstanceId> {
+        match self.namespace.get(name) {
+            Some(&NameSpaceEntry::Instance(instance)) => Some(instance),
+            _ => None,
+        }
+    }
+
     /// Lookup various information about a device implementation
     ///
     /// # Returns
@@ -478,6 +504,11 @@ impl Circuit {
     pub fn lookup_param(&mut self, name: &str, earena: &Arena) -> Option<(CircuitParam, Expr)> {
         earena.lookup_param_by_name(self.ctx, name)
     }
+
+    /// The context in which the parameters of this circuit are defined
+    pub fn param_ctx(&self) -> CircuitParamCtx {
+        self.ctx
+    }
 }
 
 impl Index<DeviceId> for Circuit {
@@ -9,24 +9,39 @@ use stdx::{impl_debug_display, impl_idx_from};
 use typed_index_collections::TiSlice;
 
 use crate::circuit::Node;
+use crate::devices::capacitor::Capacitor;
+use crate::devices::controlled::ControlledSrc;
+use crate::devices::inductor::Inductor;
+use crate::devices::isource::CurrentSrc;
+use crate::devices::mutual::MutualInductor;
 pub use crate::devices::params::{DeviceParams, ParamId, Type};
 use crate::devices::resistor::Resistor;
 use crate::devices::vsource::VoltageSrc;
-use crate::simulation::{MatrixEntryIter, SimBuilder, SimInfo};
+use crate::simulation::{MatrixEntryIter, RawSimParams, SimBuilder, SimInfo};
 
+mod capacitor;
+mod controlled;
+mod inductor;
+mod isource;
+mod mutual;
 mod params;
 mod resistor;
+mod source;
 mod vsource;
 
 pub trait DeviceImpl {
     fn get_name(&self) -> &'static str;
     fn get_terminals(&self) -> Box<[&'static str]>;
+    /// Alternative names `(alias, terminal)` under which terminals can be looked up.
+    fn get_terminal_aliases(&self) -> Box<[(&'static str, &'static str)]> {
+        Box::default()
+    }
     fn get_params(&self) -> DeviceParams;
     fn new_model(&self) -> Rc<dyn ModelImpl>;
 }
 
 pub trait ModelImpl {
-    fn process_params(&self) -> Result<()>;
+    fn process_params(&self, sim_params: &RawSimParams) -> Result<()>;
     fn set_real_param(&self, param: ParamId, val: f64);
     fn set_int_param(&self, param: ParamId, _val: i32) {
         unreachable!("unknown int param {param:?}")
@@ -79,6 +94,12 @@ pub trait InstanceImpl {
     ) -> Result<()>;
     fn set_real_param(&mut self, param: ParamId, val: f64);
 
+    /// The current value of the real parameter `param`. Only required for the parameters that
+    /// can be swept by [`Simulation::dc_sweep`](crate::simulation::Simulation::dc_sweep).
+    fn get_real_param(&self, param: ParamId) -> f64 {
+        unreachable!("real param {param:?} can not be read")
+    }
+
     fn set_int_param(&mut self, param: ParamId, _val: i32) {
         unreachable!("unknown int param {param:?}")
     }
@@ -89,13 +110,17 @@ pub trait InstanceImpl {
 
     fn populate_matrix_ptrs(&mut self, matrix_entries: MatrixEntryIter);
 
-    fn eval(&mut self, sim_info: SimInfo<'_>) -> Result<()>;
+    /// Evaluates the instance at `sim_info.prev_solve`. Returns whether the instance limited
+    /// its inputs (`$limit`), in which case the Newton iteration has not converged yet.
+    fn eval(&mut self, sim_info: SimInfo<'_>) -> Result<bool>;
 
     unsafe fn load_matrix_resist(&self);
     unsafe fn load_matrix_react(&self, alpha: f64);
 
     fn load_residual_react(&self, prev_solve: &TiSlice<Node, f64>, rhs: &mut TiSlice<Node, f64>);
     fn load_residual_resist(&self, prev_solve: &TiSlice<Node, f64>, rhs: &mut TiSlice<Node, f64>);
+    /// Adds the correction for the inputs limited during the last call to `eval` to `rhs`.
+    fn load_limit_rhs_resist(&self, _rhs: &mut TiSlice<Node, f64>) {}
     fn load_ac_residual(
         &self,
         _dc_solve: &TiSlice<Node, f64>,
@@ -103,19 +128,80 @@ pub trait InstanceImpl {
     ) {
     }
 
+    /// Appends the noise sources of this instance at frequency `freq` to `dst`.
+    /// Only valid after the instance was evaluated for noise analysis.
+    fn load_noise(&self, _freq: f64, _dst: &mut Vec<NoiseDensity>) {}
+
+    /// The (real) operating point variables of this instance with their values.
+    /// Only valid after the instance was evaluated at the operating point.
+    fn opvars(&self) -> Vec<OpVar> {
+        Vec::new()
+    }
+
+    /// The pairs of nodes that were collapsed into a single node by `process_params`.
+    /// The second node is `None` if the first node was collapsed into ground.
+    fn collapsed_nodes(&self) -> Vec<(&'static str, Option<&'static str>)> {
+        Vec::new()
+    }
+
     fn load_lead_current_resist(&self, _dc_solve: &TiSlice<Node, f64>, dst: &mut [f64]);
     fn load_lead_current_react(&self, _dc_solve: &TiSlice<Node, f64>, dst: &mut [f64]);
     fn load_ac_lead_current(&self, _ac_solve: &TiSlice<Node, Complex64>, _dst: &mut [Complex64]) {}
 }
 
+/// The (cross-)spectral densities of a single noise source.
+#[derive(Debug, Clone)]
+pub struct NoiseDensity {
+    pub name: &'static str,
+    /// The node pairs (residuals) the noise source is injected into.
+    pub nodes: Vec<(Node, Node)>,
+    /// Dense (row major) correlation matrix of the injections into `nodes`.
+    pub correlation: Vec<Complex64>,
+}
+
+/// An operating point variable of an instance.
+#[derive(Debug, Clone)]
+pub struct OpVar {
+    pub name: &'static str,
+    pub value: f64,
+    pub units: &'static str,
+    pub description: &'static str,
+}
+
 pub struct DeviceInfo {
     pub name: &'static str,
     pub dev_impl: Box<dyn DeviceImpl>,
     pub va_file: Option<Utf8PathBuf>,
     pub terminals: Box<[&'static str]>,
+    pub terminal_aliases: Box<[(&'static str, &'static str)]>,
     pub parameters: DeviceParams,
 }
 
+impl DeviceInfo {
+    /// Returns the position of the terminal called `name` (or one of its aliases).
+    pub fn terminal(&self, name: &str) -> Option<usize> {
+        let name = self
+            .terminal_aliases
+            .iter()
+            .find(|(alias, _)| *alias == name)
+            .map_or(name, |&(_, terminal)| terminal);
+        self.terminals.iter().position(|&it| it == name)
+    }
+}
+
+/// The `anode` and `cathode` aliases of the `A` and `C` terminals of two terminal devices.
+fn two_terminal_aliases() -> Box<[(&'static str, &'static str)]> {
+    vec![("anode", "A"), ("cathode", "C")].into_boxed_slice()
+}
+
 pub(crate) fn default_devices() -> impl Iterator<Item = Box<dyn DeviceImpl>> {
-    [VoltageSrc::init_dev(), Resistor::init_dev()].into_iter()
+    let devices = [
+        VoltageSrc::init_dev(),
+        CurrentSrc::init_dev(),
+        Resistor::init_dev(),
+        Capacitor::init_dev(),
+        Inductor::init_dev(),
+        MutualInductor::init_dev(),
+    ];
+    devices.into_iter().chain(ControlledSrc::init_devs())
 }
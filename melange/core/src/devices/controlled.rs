@@ -0,0 +1,260 @@
+use std::cell::Cell;
+use std::ptr::NonNull;
+use std::rc::Rc;
+
+use anyhow::{bail, Result};
+use num_complex::Complex64;
+use stdx::iter::zip;
+use typed_index_collections::TiSlice;
+
+use super::{ModelImpl, ParamId, SimInfo};
+use crate::circuit::Node;
+use crate::devices::{update_matrix_entry, DeviceImpl, DeviceParams, InstanceImpl, Type};
+use crate::simulation::{MatrixEntryIter, RawSimParams, SimBuilder};
+
+/// The four linear controlled sources. The output is connected between `A` and `C` (the
+/// output current flows from `A` through the source to `C`). The controlling voltage is
+/// `V(CA) - V(CC)`, the controlling current flows from `CA` through a zero volt source
+/// (that is part of the instance) to `CC`.
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub enum ControlledSrc {
+    /// Voltage controlled voltage source, `V(A, C) = gain * V(CA, CC)`
+    Vcvs,
+    /// Voltage controlled current source, `I(A, C) = gm * V(CA, CC)`
+    Vccs,
+    /// Current controlled voltage source, `V(A, C) = rm * I(CA, CC)`
+    Ccvs,
+    /// Current controlled current source, `I(A, C) = gain * I(CA, CC)`
+    Cccs,
+}
+
+impl ControlledSrc {
+    pub fn init_devs() -> [Box<dyn DeviceImpl>; 4] {
+        [
+            Box::new(ControlledSrc::Vcvs),
+            Box::new(ControlledSrc::Vccs),
+            Box::new(ControlledSrc::Ccvs),
+            Box::new(ControlledSrc::Cccs),
+        ]
+    }
+
+    fn name(self) -> &'static str {
+        match self {
+            ControlledSrc::Vcvs => "vcvs",
+            ControlledSrc::Vccs => "vccs",
+            ControlledSrc::Ccvs => "ccvs",
+            ControlledSrc::Cccs => "cccs",
+        }
+    }
+}
+
+impl DeviceImpl for ControlledSrc {
+    fn get_name(&self) -> &'static str {
+        self.name()
+    }
+
+    fn get_terminals(&self) -> Box<[&'static str]> {
+        vec!["A", "C", "CA", "CC"].into_boxed_slice()
+    }
+
+    fn get_params(&self) -> DeviceParams {
+        let name = match self {
+            ControlledSrc::Vcvs | ControlledSrc::Cccs => "gain",
+            ControlledSrc::Vccs => "gm",
+            ControlledSrc::Ccvs => "rm",
+        };
+        let mut res = DeviceParams::default();
+        res.insert_instance_param(name, Type::Real);
+        res
+    }
+
+    fn new_model(&self) -> Rc<dyn ModelImpl> {
+        Rc::new(ControlledSrcModel { kind: *self, gain: Cell::new(None) })
+    }
+}
+
+const GAIN: ParamId = ParamId(0u32);
+
+struct ControlledSrcModel {
+    kind: ControlledSrc,
+    gain: Cell<Option<f64>>,
+}
+
+impl ModelImpl for ControlledSrcModel {
+    fn process_params(&self, _sim_params: &RawSimParams) -> Result<()> {
+        Ok(())
+    }
+
+    fn set_real_param(&self, param: ParamId, val: f64) {
+        match param {
+            GAIN => self.gain.set(Some(val)),
+            _ => unreachable!("{}: unknown numeric parameter {param:?}", self.kind.name()),
+        };
+    }
+
+    fn new_instance(self: Rc<Self>) -> Box<dyn super::InstanceImpl> {
+        Box::new(ControlledSrcInstance {
+            kind: self.kind,
+            gain: self.gain.get(),
+            stamps: Vec::new(),
+            matrix_entries: Vec::new(),
+        })
+    }
+}
+
+/// A single (constant) entry of the Jacobian.
+/// As the source is linear the residual is the Jacobian times the solution.
+struct Stamp {
+    equation: Node,
+    unknown: Node,
+    val: f64,
+    /// The terminal whose lead current `equation` is (if any)
+    lead: Option<usize>,
+}
+
+struct ControlledSrcInstance {
+    kind: ControlledSrc,
+    gain: Option<f64>,
+    stamps: Vec<Stamp>,
+    matrix_entries: Vec<NonNull<Cell<f64>>>,
+}
+
+impl ControlledSrcInstance {
+    /// Adds the stamps of a voltage source between the terminals `anode` and `cathode` with
+    /// the current `branch`. The branch equation is `V(anode) - V(cathode)`, the controlled
+    /// part is added by the caller.
+    fn stamp_vsource(&mut self, terminals: [(usize, Node); 2], branch: Node) {
+        let [(anode_idx, anode), (cathode_idx, cathode)] = terminals;
+        self.stamps.extend([
+            Stamp { equation: anode, unknown: branch, val: 1.0, lead: Some(anode_idx) },
+            Stamp { equation: branch, unknown: anode, val: 1.0, lead: None },
+            Stamp { equation: cathode, unknown: branch, val: -1.0, lead: Some(cathode_idx) },
+            Stamp { equation: branch, unknown: cathode, val: -1.0, lead: None },
+        ])
+    }
+
+    /// Creates the zero volt source that measures the control current and returns its branch.
+    fn stamp_ctrl_branch(
+        &mut self,
+        builder: &mut SimBuilder,
+        ctrl_anode: Node,
+        ctrl_cathode: Node,
+    ) -> Node {
+        let branch = builder.new_internal_branch("ctrl_branch");
+        self.stamp_vsource([(2, ctrl_anode), (3, ctrl_cathode)], branch);
+        branch
+    }
+
+    /// Adds the stamps of `val * x[unknown]` flowing from `anode` to `cathode`.
+    fn stamp_current(&mut self, anode: Node, cathode: Node, unknown: Node, val: f64) {
+        self.stamps.extend([
+            Stamp { equation: anode, unknown, val, lead: Some(0) },
+            Stamp { equation: cathode, unknown, val: -val, lead: Some(1) },
+        ])
+    }
+}
+
+impl InstanceImpl for ControlledSrcInstance {
+    fn process_params(
+        &mut self,
+        _temp: f64,
+        builder: &mut SimBuilder,
+        terminals: &[Node],
+    ) -> Result<()> {
+        let name = self.kind.name();
+        let [anode, cathode, ctrl_anode, ctrl_cathode] =
+            if let &[anode, cathode, ctrl_anode, ctrl_cathode] = terminals {
+                [anode, cathode, ctrl_anode, ctrl_cathode]
+            } else {
+                bail!("{name}: all terminals must be connected")
+            };
+        let gain = match self.gain {
+            Some(gain) => gain,
+            None => bail!("{name}: the gain must be set"),
+        };
+
+        self.stamps.clear();
+        match self.kind {
+            ControlledSrc::Vcvs => {
+                let branch = builder.new_internal_branch("branch");
+                self.stamp_vsource([(0, anode), (1, cathode)], branch);
+                self.stamps.extend([
+                    Stamp { equation: branch, unknown: ctrl_anode, val: -gain, lead: None },
+                    Stamp { equation: branch, unknown: ctrl_cathode, val: gain, lead: None },
+                ])
+            }
+            ControlledSrc::Vccs => {
+                self.stamp_current(anode, cathode, ctrl_anode, gain);
+                self.stamp_current(anode, cathode, ctrl_cathode, -gain);
+            }
+            ControlledSrc::Ccvs => {
+                let ctrl = self.stamp_ctrl_branch(builder, ctrl_anode, ctrl_cathode);
+                let branch = builder.new_internal_branch("branch");
+                self.stamp_vsource([(0, anode), (1, cathode)], branch);
+                self.stamps.push(Stamp { equation: branch, unknown: ctrl, val: -gain, lead: None })
+            }
+            ControlledSrc::Cccs => {
+                let ctrl = self.stamp_ctrl_branch(builder, ctrl_anode, ctrl_cathode);
+                self.stamp_current(anode, cathode, ctrl, gain)
+            }
+        }
+
+        for stamp in &self.stamps {
+            builder.ensure_matrix_entry(stamp.equation, stamp.unknown);
+        }
+
+        Ok(())
+    }
+
+    fn set_real_param(&mut self, param: ParamId, val: f64) {
+        match param {
+            GAIN => self.gain = Some(val),
+            _ => unreachable!("{}: unknown numeric parameter {param:?}", self.kind.name()),
+        };
+    }
+
+    fn populate_matrix_ptrs(&mut self, matrix_entries: MatrixEntryIter) {
+        self.matrix_entries = matrix_entries.map(|entry| entry.resist()).collect();
+    }
+
+    fn eval(&mut self, _sim_info: SimInfo<'_>) -> Result<bool> {
+        Ok(false)
+    }
+
+    unsafe fn load_matrix_resist(&self) {
+        for (entry, stamp) in zip(&self.matrix_entries, &self.stamps) {
+            update_matrix_entry(entry.as_ref(), stamp.val);
+        }
+    }
+
+    unsafe fn load_matrix_react(&self, _alpha: f64) {}
+
+    fn load_residual_react(&self, _prev_solve: &TiSlice<Node, f64>, _rhs: &mut TiSlice<Node, f64>) {
+    }
+
+    fn load_residual_resist(&self, prev_solve: &TiSlice<Node, f64>, rhs: &mut TiSlice<Node, f64>) {
+        for stamp in &self.stamps {
+            rhs[stamp.equation] += stamp.val * prev_solve[stamp.unknown];
+        }
+    }
+
+    fn load_lead_current_resist(&self, dc_solve: &TiSlice<Node, f64>, dst: &mut [f64]) {
+        dst.fill(0.0);
+        for stamp in &self.stamps {
+            if let Some(lead) = stamp.lead {
+                dst[lead] += stamp.val * dc_solve[stamp.unknown];
+            }
+        }
+    }
+
+    fn load_lead_current_react(&self, _dc_solve: &TiSlice<Node, f64>, _dst: &mut [f64]) {}
+
+    fn load_ac_lead_current(&self, ac_solve: &TiSlice<Node, Complex64>, dst: &mut [Complex64]) {
+        dst.fill(Complex64::default());
+        for stamp in &self.stamps {
+            if let Some(lead) = stamp.lead {
+                dst[lead] += stamp.val * ac_solve[stamp.unknown];
+            }
+        }
+    }
+}
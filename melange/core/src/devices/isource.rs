@@ -0,0 +1,160 @@
+use std::cell::RefCell;
+use std::rc::Rc;
+
+use anyhow::{bail, Result};
+use num_complex::Complex64;
+use typed_index_collections::TiSlice;
+
+use crate::circuit::Node;
+use crate::devices::source::{source_params, SourceParams, Waveform, DC};
+use crate::devices::{two_terminal_aliases, DeviceImpl, DeviceParams, InstanceImpl};
+use crate::simulation::{MatrixEntryIter, RawSimParams, SimBuilder};
+
+use super::{ModelImpl, ParamId, SimInfo};
+
+/// An independent current source, the current flows from `A` through the source to `C`.
+pub struct CurrentSrc;
+
+impl CurrentSrc {
+    pub fn init_dev() -> Box<dyn DeviceImpl> {
+        Box::new(Self)
+    }
+}
+
+impl DeviceImpl for CurrentSrc {
+    fn get_name(&self) -> &'static str {
+        "isource"
+    }
+
+    fn get_terminals(&self) -> Box<[&'static str]> {
+        vec!["A", "C"].into_boxed_slice()
+    }
+
+    fn get_terminal_aliases(&self) -> Box<[(&'static str, &'static str)]> {
+        two_terminal_aliases()
+    }
+
+    fn get_params(&self) -> DeviceParams {
+        source_params()
+    }
+
+    fn new_model(&self) -> Rc<dyn ModelImpl> {
+        Rc::new(CurrentSrcModel::default())
+    }
+}
+
+#[derive(Default)]
+struct CurrentSrcModel {
+    params: RefCell<SourceParams>,
+}
+
+impl ModelImpl for CurrentSrcModel {
+    fn process_params(&self, _sim_params: &RawSimParams) -> Result<()> {
+        Ok(())
+    }
+
+    fn set_real_param(&self, param: ParamId, val: f64) {
+        self.params.borrow_mut().set_real_param(param, val)
+    }
+
+    fn set_str_param(&self, param: ParamId, val: &str) {
+        self.params.borrow_mut().set_str_param(param, val)
+    }
+
+    fn new_instance(self: Rc<Self>) -> Box<dyn super::InstanceImpl> {
+        Box::new(CurrentSrcInstance {
+            anode: Node::GROUND,
+            cathode: Node::GROUND,
+            params: self.params.borrow().clone(),
+            waveform: Waveform::Dc,
+            val: 0.0,
+        })
+    }
+}
+
+struct CurrentSrcInstance {
+    anode: Node,
+    cathode: Node,
+    params: SourceParams,
+    waveform: Waveform,
+    /// the value at the current time point, reduced during source stepping
+    val: f64,
+}
+
+impl InstanceImpl for CurrentSrcInstance {
+    fn process_params(
+        &mut self,
+        _temp: f64,
+        _builder: &mut SimBuilder,
+        terminals: &[Node],
+    ) -> Result<()> {
+        let [anode, cathode] = if let &[anode, cathode] = terminals {
+            [anode, cathode]
+        } else {
+            bail!("isource: all terminals must be connected")
+        };
+
+        self.waveform = self.params.waveform()?;
+        self.anode = anode;
+        self.cathode = cathode;
+        Ok(())
+    }
+
+    fn set_real_param(&mut self, param: ParamId, val: f64) {
+        self.params.set_real_param(param, val)
+    }
+
+    fn get_real_param(&self, param: ParamId) -> f64 {
+        match param {
+            DC => self.params.dc(&self.waveform),
+            _ => unreachable!("source: param {param:?} can not be read"),
+        }
+    }
+
+    fn set_str_param(&mut self, param: ParamId, val: &str) {
+        self.params.set_str_param(param, val)
+    }
+
+    fn populate_matrix_ptrs(&mut self, _matrix_entries: MatrixEntryIter) {}
+
+    fn eval(&mut self, sim_info: SimInfo<'_>) -> Result<bool> {
+        let scale = sim_info.sim_params.source_scale_factor();
+        self.val = self.params.value(&self.waveform, &sim_info) * scale;
+        Ok(false)
+    }
+
+    unsafe fn load_matrix_resist(&self) {}
+
+    unsafe fn load_matrix_react(&self, _alpha: f64) {}
+
+    fn load_residual_react(&self, _prev_solve: &TiSlice<Node, f64>, _rhs: &mut TiSlice<Node, f64>) {
+    }
+
+    fn load_residual_resist(&self, _prev_solve: &TiSlice<Node, f64>, rhs: &mut TiSlice<Node, f64>) {
+        rhs[self.anode] += self.val;
+        rhs[self.cathode] -= self.val;
+    }
+
+    fn load_ac_residual(
+        &self,
+        _prev_solve: &TiSlice<Node, f64>,
+        rhs: &mut TiSlice<Node, Complex64>,
+    ) {
+        let ac = self.params.ac();
+        rhs[self.anode] -= ac;
+        rhs[self.cathode] += ac;
+    }
+
+    fn load_lead_current_resist(&self, _dc_solve: &TiSlice<Node, f64>, dst: &mut [f64]) {
+        dst[0] = self.val;
+        dst[1] = -self.val;
+    }
+
+    fn load_lead_current_react(&self, _dc_solve: &TiSlice<Node, f64>, _dst: &mut [f64]) {}
+
+    fn load_ac_lead_current(&self, _ac_solve: &TiSlice<Node, Complex64>, dst: &mut [Complex64]) {
+        let ac = self.params.ac();
+        dst[0] = ac;
+        dst[1] = -ac;
+    }
+}
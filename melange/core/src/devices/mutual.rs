@@ -0,0 +1,232 @@
+use std::cell::Cell;
+use std::ptr::NonNull;
+use std::rc::Rc;
+
+use anyhow::{bail, Result};
+use num_complex::Complex64;
+use typed_index_collections::TiSlice;
+
+use super::{ModelImpl, ParamId, SimInfo};
+use crate::circuit::Node;
+use crate::devices::{update_matrix_entry, DeviceImpl, DeviceParams, InstanceImpl, Type};
+use crate::simulation::{MatrixEntryIter, RawSimParams, SimBuilder};
+
+/// Two magnetically coupled inductors (`A1`-`C1` and `A2`-`C2`) with the mutual inductance
+/// `coupling * sqrt(l1 * l2)`. Both windings are part of the same instance because instances
+/// can not access the unknowns of other instances.
+pub struct MutualInductor;
+
+impl MutualInductor {
+    pub fn init_dev() -> Box<dyn DeviceImpl> {
+        Box::new(Self)
+    }
+}
+
+impl DeviceImpl for MutualInductor {
+    fn get_name(&self) -> &'static str {
+        "mutual_inductor"
+    }
+
+    fn get_terminals(&self) -> Box<[&'static str]> {
+        vec!["A1", "C1", "A2", "C2"].into_boxed_slice()
+    }
+
+    fn get_params(&self) -> DeviceParams {
+        let mut res = DeviceParams::default();
+        res.insert_instance_param("l1", Type::Real);
+        res.insert_instance_param("l2", Type::Real);
+        res.insert_instance_param("coupling", Type::Real);
+        res.insert_alias("k", COUPLING);
+        res
+    }
+
+    fn new_model(&self) -> Rc<dyn ModelImpl> {
+        Rc::new(MutualInductorModel::default())
+    }
+}
+
+const L1: ParamId = ParamId(0u32);
+const L2: ParamId = ParamId(1u32);
+const COUPLING: ParamId = ParamId(2u32);
+
+const MATRIX_ANODE1_BR1: usize = 0;
+const MATRIX_BR1_ANODE1: usize = 1;
+const MATRIX_CATHODE1_BR1: usize = 2;
+const MATRIX_BR1_CATHODE1: usize = 3;
+const MATRIX_ANODE2_BR2: usize = 4;
+const MATRIX_BR2_ANODE2: usize = 5;
+const MATRIX_CATHODE2_BR2: usize = 6;
+const MATRIX_BR2_CATHODE2: usize = 7;
+// reactive entries
+const MATRIX_BR1_BR1: usize = 8;
+const MATRIX_BR1_BR2: usize = 9;
+const MATRIX_BR2_BR1: usize = 10;
+const MATRIX_BR2_BR2: usize = 11;
+
+#[derive(Default, Clone)]
+struct MutualInductorModel {
+    l1: Cell<Option<f64>>,
+    l2: Cell<Option<f64>>,
+    coupling: Cell<Option<f64>>,
+}
+
+impl ModelImpl for MutualInductorModel {
+    fn process_params(&self, _sim_params: &RawSimParams) -> Result<()> {
+        Ok(())
+    }
+
+    fn set_real_param(&self, param: ParamId, val: f64) {
+        let dst = match param {
+            L1 => &self.l1,
+            L2 => &self.l2,
+            COUPLING => &self.coupling,
+            _ => unreachable!("mutual_inductor: unknown numeric parameter {param:?}"),
+        };
+        dst.set(Some(val));
+    }
+
+    fn new_instance(self: Rc<Self>) -> Box<dyn super::InstanceImpl> {
+        Box::new(MutualInductorInstance {
+            terminals: [Node::GROUND; 4],
+            branches: [Node::GROUND; 2],
+            l1: self.l1.get(),
+            l2: self.l2.get(),
+            coupling: self.coupling.get(),
+            inductance: [0.0; 3],
+            matrix_entries: [NonNull::dangling(); 12],
+        })
+    }
+}
+
+struct MutualInductorInstance {
+    /// `[A1, C1, A2, C2]`
+    terminals: [Node; 4],
+    branches: [Node; 2],
+    l1: Option<f64>,
+    l2: Option<f64>,
+    coupling: Option<f64>,
+    /// `[L1, L2, M]`
+    inductance: [f64; 3],
+    matrix_entries: [NonNull<Cell<f64>>; 12],
+}
+
+impl InstanceImpl for MutualInductorInstance {
+    fn process_params(
+        &mut self,
+        _temp: f64,
+        builder: &mut SimBuilder,
+        terminals: &[Node],
+    ) -> Result<()> {
+        self.terminals = if let &[a1, c1, a2, c2] = terminals {
+            [a1, c1, a2, c2]
+        } else {
+            bail!("mutual_inductor: all terminals must be connected")
+        };
+        let (l1, l2, coupling) = match (self.l1, self.l2, self.coupling) {
+            (Some(l1), Some(l2), Some(coupling)) => (l1, l2, coupling),
+            _ => bail!("mutual_inductor: l1, l2 and coupling must be set"),
+        };
+        if coupling.abs() > 1.0 {
+            bail!("mutual_inductor: the coupling must be between -1 and 1 but was {coupling}")
+        }
+        self.inductance = [l1, l2, coupling * (l1 * l2).sqrt()];
+
+        let [a1, c1, a2, c2] = self.terminals;
+        let br1 = builder.new_internal_branch("branch1");
+        let br2 = builder.new_internal_branch("branch2");
+        self.branches = [br1, br2];
+
+        // IMPORTANT: keep the order here in sync with the MATRIX_ indices
+        builder.ensure_matrix_entry(a1, br1);
+        builder.ensure_matrix_entry(br1, a1);
+        builder.ensure_matrix_entry(c1, br1);
+        builder.ensure_matrix_entry(br1, c1);
+        builder.ensure_matrix_entry(a2, br2);
+        builder.ensure_matrix_entry(br2, a2);
+        builder.ensure_matrix_entry(c2, br2);
+        builder.ensure_matrix_entry(br2, c2);
+        builder.ensure_matrix_entry(br1, br1);
+        builder.ensure_matrix_entry(br1, br2);
+        builder.ensure_matrix_entry(br2, br1);
+        builder.ensure_matrix_entry(br2, br2);
+
+        Ok(())
+    }
+
+    fn set_real_param(&mut self, param: ParamId, val: f64) {
+        let dst = match param {
+            L1 => &mut self.l1,
+            L2 => &mut self.l2,
+            COUPLING => &mut self.coupling,
+            _ => unreachable!("mutual_inductor: unknown numeric parameter {param:?}"),
+        };
+        *dst = Some(val);
+    }
+
+    fn populate_matrix_ptrs(&mut self, matrix_entries: MatrixEntryIter) {
+        for (i, entry) in matrix_entries.enumerate() {
+            self.matrix_entries[i] =
+                if i >= MATRIX_BR1_BR1 { entry.react() } else { entry.resist() };
+        }
+    }
+
+    fn eval(&mut self, _sim_info: SimInfo<'_>) -> Result<bool> {
+        Ok(false)
+    }
+
+    unsafe fn load_matrix_resist(&self) {
+        update_matrix_entry(self.matrix_entries[MATRIX_ANODE1_BR1].as_ref(), 1.0);
+        update_matrix_entry(self.matrix_entries[MATRIX_BR1_ANODE1].as_ref(), 1.0);
+        update_matrix_entry(self.matrix_entries[MATRIX_CATHODE1_BR1].as_ref(), -1.0);
+        update_matrix_entry(self.matrix_entries[MATRIX_BR1_CATHODE1].as_ref(), -1.0);
+        update_matrix_entry(self.matrix_entries[MATRIX_ANODE2_BR2].as_ref(), 1.0);
+        update_matrix_entry(self.matrix_entries[MATRIX_BR2_ANODE2].as_ref(), 1.0);
+        update_matrix_entry(self.matrix_entries[MATRIX_CATHODE2_BR2].as_ref(), -1.0);
+        update_matrix_entry(self.matrix_entries[MATRIX_BR2_CATHODE2].as_ref(), -1.0);
+    }
+
+    unsafe fn load_matrix_react(&self, alpha: f64) {
+        let [l1, l2, m] = self.inductance;
+        update_matrix_entry(self.matrix_entries[MATRIX_BR1_BR1].as_ref(), -alpha * l1);
+        update_matrix_entry(self.matrix_entries[MATRIX_BR1_BR2].as_ref(), -alpha * m);
+        update_matrix_entry(self.matrix_entries[MATRIX_BR2_BR1].as_ref(), -alpha * m);
+        update_matrix_entry(self.matrix_entries[MATRIX_BR2_BR2].as_ref(), -alpha * l2);
+    }
+
+    fn load_residual_react(&self, prev_solve: &TiSlice<Node, f64>, rhs: &mut TiSlice<Node, f64>) {
+        let [l1, l2, m] = self.inductance;
+        let [br1, br2] = self.branches;
+        // the flux linked with each winding
+        rhs[br1] -= l1 * prev_solve[br1] + m * prev_solve[br2];
+        rhs[br2] -= m * prev_solve[br1] + l2 * prev_solve[br2];
+    }
+
+    fn load_residual_resist(&self, prev_solve: &TiSlice<Node, f64>, rhs: &mut TiSlice<Node, f64>) {
+        let [a1, c1, a2, c2] = self.terminals;
+        for ([anode, cathode], branch) in
+            [([a1, c1], self.branches[0]), ([a2, c2], self.branches[1])]
+        {
+            rhs[anode] += prev_solve[branch];
+            rhs[cathode] -= prev_solve[branch];
+            rhs[branch] += prev_solve[anode] - prev_solve[cathode];
+        }
+    }
+
+    fn load_lead_current_resist(&self, dc_solve: &TiSlice<Node, f64>, dst: &mut [f64]) {
+        let [br1, br2] = self.branches;
+        dst[0] = dc_solve[br1];
+        dst[1] = -dc_solve[br1];
+        dst[2] = dc_solve[br2];
+        dst[3] = -dc_solve[br2];
+    }
+
+    fn load_lead_current_react(&self, _dc_solve: &TiSlice<Node, f64>, _dst: &mut [f64]) {}
+
+    fn load_ac_lead_current(&self, ac_solve: &TiSlice<Node, Complex64>, dst: &mut [Complex64]) {
+        let [br1, br2] = self.branches;
+        dst[0] = ac_solve[br1];
+        dst[1] = -ac_solve[br1];
+        dst[2] = ac_solve[br2];
+        dst[3] = -ac_solve[br2];
+    }
+}
@@ -8,8 +8,10 @@ use typed_index_collections::TiSlice;
 
 use super::{ModelImpl, ParamId, SimInfo};
 use crate::circuit::Node;
-use crate::devices::{update_matrix_entry, DeviceImpl, DeviceParams, InstanceImpl, Type};
-use crate::simulation::{MatrixEntryIter, SimBuilder};
+use crate::devices::{
+    two_terminal_aliases, update_matrix_entry, DeviceImpl, DeviceParams, InstanceImpl, Type,
+};
+use crate::simulation::{MatrixEntryIter, RawSimParams, SimBuilder};
 
 pub struct Resistor;
 
@@ -28,6 +30,10 @@ impl DeviceImpl for Resistor {
         vec!["A", "C"].into_boxed_slice()
     }
 
+    fn get_terminal_aliases(&self) -> Box<[(&'static str, &'static str)]> {
+        two_terminal_aliases()
+    }
+
     fn get_params(&self) -> DeviceParams {
         let mut res = DeviceParams::default();
         res.insert_instance_param("r", Type::Real);
@@ -52,7 +58,7 @@ struct ResistorModel {
 }
 
 impl ModelImpl for ResistorModel {
-    fn process_params(&self) -> Result<()> {
+    fn process_params(&self, _sim_params: &RawSimParams) -> Result<()> {
         Ok(())
     }
 
@@ -89,8 +95,8 @@ impl InstanceImpl for ResistorInstance {
         }
     }
 
-    fn eval(&mut self, _sim_info: SimInfo<'_>) -> Result<()> {
-        Ok(())
+    fn eval(&mut self, _sim_info: SimInfo<'_>) -> Result<bool> {
+        Ok(false)
     }
 
     unsafe fn load_matrix_resist(&self) {
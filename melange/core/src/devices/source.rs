@@ -0,0 +1,330 @@
+//! Parameters and waveforms shared by the independent sources (`vsource` and `isource`).
+//! The waveforms follow the SPICE/spectre definitions.
+
+use std::f64::consts::PI;
+
+use anyhow::{bail, Context, Result};
+use num_complex::Complex64;
+
+use crate::devices::{DeviceParams, ParamId, SimInfo, Type};
+use crate::simulation::EvalFlags;
+
+#[cfg(test)]
+mod tests;
+
+pub(super) const DC: ParamId = ParamId(0u32);
+pub(super) const MAG: ParamId = ParamId(1u32);
+pub(super) const PHASE: ParamId = ParamId(2u32);
+const TYPE: ParamId = ParamId(3u32);
+const VAL0: ParamId = ParamId(4u32);
+const VAL1: ParamId = ParamId(5u32);
+const DELAY: ParamId = ParamId(6u32);
+const RISE: ParamId = ParamId(7u32);
+const FALL: ParamId = ParamId(8u32);
+const WIDTH: ParamId = ParamId(9u32);
+const PERIOD: ParamId = ParamId(10u32);
+const SINEDC: ParamId = ParamId(11u32);
+const AMPL: ParamId = ParamId(12u32);
+const FREQ: ParamId = ParamId(13u32);
+const SINEPHASE: ParamId = ParamId(14u32);
+const DAMP: ParamId = ParamId(15u32);
+const TD1: ParamId = ParamId(16u32);
+const TAU1: ParamId = ParamId(17u32);
+const TD2: ParamId = ParamId(18u32);
+const TAU2: ParamId = ParamId(19u32);
+const WAVE: ParamId = ParamId(20u32);
+
+/// The parameters of an independent source, keep the order in sync with the ParamIds above.
+pub(super) fn source_params() -> DeviceParams {
+    let mut res = DeviceParams::default();
+    res.insert_instance_param("dc", Type::Real);
+    res.insert_instance_param("mag", Type::Real);
+    res.insert_alias("ac", MAG);
+    res.insert_instance_param("phase", Type::Real);
+    res.insert_instance_param("type", Type::String);
+    res.insert_instance_param("val0", Type::Real);
+    res.insert_instance_param("val1", Type::Real);
+    res.insert_instance_param("delay", Type::Real);
+    res.insert_instance_param("rise", Type::Real);
+    res.insert_instance_param("fall", Type::Real);
+    res.insert_instance_param("width", Type::Real);
+    res.insert_instance_param("period", Type::Real);
+    res.insert_instance_param("sinedc", Type::Real);
+    res.insert_instance_param("ampl", Type::Real);
+    res.insert_instance_param("freq", Type::Real);
+    res.insert_instance_param("sinephase", Type::Real);
+    res.insert_instance_param("damp", Type::Real);
+    res.insert_instance_param("td1", Type::Real);
+    res.insert_instance_param("tau1", Type::Real);
+    res.insert_instance_param("td2", Type::Real);
+    res.insert_instance_param("tau2", Type::Real);
+    // pwl time/value pairs separated by whitespace (or commas)
+    res.insert_instance_param("wave", Type::String);
+    res
+}
+
+#[derive(Clone)]
+pub(super) struct SourceParams {
+    dc: Option<f64>,
+    mag: f64,
+    phase: f64,
+    ty: String,
+    val0: f64,
+    val1: f64,
+    delay: f64,
+    rise: f64,
+    fall: f64,
+    width: f64,
+    period: f64,
+    sinedc: f64,
+    ampl: f64,
+    freq: f64,
+    sinephase: f64,
+    damp: f64,
+    td1: f64,
+    tau1: f64,
+    td2: f64,
+    tau2: f64,
+    wave: String,
+}
+
+impl Default for SourceParams {
+    fn default() -> Self {
+        SourceParams {
+            dc: None,
+            mag: 0.0,
+            phase: 0.0,
+            ty: "dc".to_owned(),
+            val0: 0.0,
+            val1: 0.0,
+            delay: 0.0,
+            rise: 0.0,
+            fall: 0.0,
+            width: f64::INFINITY,
+            period: f64::INFINITY,
+            sinedc: 0.0,
+            ampl: 0.0,
+            freq: 0.0,
+            sinephase: 0.0,
+            damp: 0.0,
+            td1: 0.0,
+            tau1: 0.0,
+            td2: f64::INFINITY,
+            tau2: 0.0,
+            wave: String::new(),
+        }
+    }
+}
+
+impl SourceParams {
+    pub fn set_real_param(&mut self, param: ParamId, val: f64) {
+        let dst = match param {
+            DC => {
+                self.dc = Some(val);
+                return;
+            }
+            MAG => &mut self.mag,
+            PHASE => &mut self.phase,
+            VAL0 => &mut self.val0,
+            VAL1 => &mut self.val1,
+            DELAY => &mut self.delay,
+            RISE => &mut self.rise,
+            FALL => &mut self.fall,
+            WIDTH => &mut self.width,
+            PERIOD => &mut self.period,
+            SINEDC => &mut self.sinedc,
+            AMPL => &mut self.ampl,
+            FREQ => &mut self.freq,
+            SINEPHASE => &mut self.sinephase,
+            DAMP => &mut self.damp,
+            TD1 => &mut self.td1,
+            TAU1 => &mut self.tau1,
+            TD2 => &mut self.td2,
+            TAU2 => &mut self.tau2,
+            _ => unreachable!("source: unknown num param {param:?}"),
+        };
+        *dst = val;
+    }
+
+    pub fn set_str_param(&mut self, param: ParamId, val: &str) {
+        match param {
+            TYPE => self.ty = val.to_owned(),
+            WAVE => self.wave = val.to_owned(),
+            _ => unreachable!("source: unknown str param {param:?}"),
+        }
+    }
+
+    /// The value used by static analyses. Without an explicit `dc` parameter that is the value
+    /// of `waveform` at the start of the simulation.
+    pub fn dc(&self, waveform: &Waveform) -> f64 {
+        self.dc.unwrap_or_else(|| waveform.eval(0.0, 0.0))
+    }
+
+    /// The small signal excitation of the source during AC analysis.
+    pub fn ac(&self) -> Complex64 {
+        Complex64::from_polar(self.mag, self.phase)
+    }
+
+    pub fn waveform(&self) -> Result<Waveform> {
+        let res = match &*self.ty {
+            "dc" => Waveform::Dc,
+            "pulse" => {
+                if self.rise < 0.0 || self.fall < 0.0 || self.width < 0.0 {
+                    bail!("rise, fall and width of a pulse source can not be negative")
+                }
+                if self.period <= 0.0 {
+                    bail!("the period of a pulse source must be positive")
+                }
+                Waveform::Pulse {
+                    val0: self.val0,
+                    val1: self.val1,
+                    delay: self.delay,
+                    rise: self.rise,
+                    fall: self.fall,
+                    width: self.width,
+                    period: self.period,
+                }
+            }
+            "sine" | "sin" => Waveform::Sine {
+                offset: self.sinedc,
+                ampl: self.ampl,
+                freq: self.freq,
+                phase: self.sinephase,
+                damp: self.damp,
+                delay: self.delay,
+            },
+            "exp" => {
+                if self.tau1 < 0.0 || self.tau2 < 0.0 {
+                    bail!("the time constants of an exp source can not be negative")
+                }
+                Waveform::Exp {
+                    val0: self.val0,
+                    val1: self.val1,
+                    td1: self.td1,
+                    tau1: self.tau1,
+                    td2: self.td2,
+                    tau2: self.tau2,
+                }
+            }
+            "pwl" => Waveform::Pwl(parse_pwl(&self.wave).context("invalid pwl wave")?),
+            ty => bail!("unknown source type \"{ty}\" (expected dc, pulse, sine, exp or pwl)"),
+        };
+        Ok(res)
+    }
+
+    /// The value of the source at the time point of `sim_info` (without source scaling).
+    /// Static analyses use the `dc` parameter if it was provided.
+    pub fn value(&self, waveform: &Waveform, sim_info: &SimInfo<'_>) -> f64 {
+        match self.dc {
+            Some(dc) if sim_info.flags.contains(EvalFlags::ANALYSIS_STATIC) => dc,
+            dc => waveform.eval(sim_info.abstime, dc.unwrap_or(0.0)),
+        }
+    }
+}
+
+/// The time dependent value of an independent source.
+#[derive(Debug, Clone, PartialEq)]
+pub(super) enum Waveform {
+    Dc,
+    Pulse {
+        val0: f64,
+        val1: f64,
+        delay: f64,
+        rise: f64,
+        fall: f64,
+        width: f64,
+        period: f64,
+    },
+    Sine {
+        offset: f64,
+        ampl: f64,
+        freq: f64,
+        phase: f64,
+        damp: f64,
+        delay: f64,
+    },
+    Exp {
+        val0: f64,
+        val1: f64,
+        td1: f64,
+        tau1: f64,
+        td2: f64,
+        tau2: f64,
+    },
+    /// Time/value pairs sorted by time
+    Pwl(Vec<(f64, f64)>),
+}
+
+impl Waveform {
+    pub fn eval(&self, t: f64, dc: f64) -> f64 {
+        match *self {
+            Waveform::Dc => dc,
+            Waveform::Pulse { val0, val1, delay, rise, fall, width, period } => {
+                if t <= delay {
+                    return val0;
+                }
+                let t = if period.is_finite() { (t - delay) % period } else { t - delay };
+                if t < rise {
+                    val0 + (val1 - val0) * t / rise
+                } else if t < rise + width {
+                    val1
+                } else if t < rise + width + fall {
+                    val1 + (val0 - val1) * (t - rise - width) / fall
+                } else {
+                    val0
+                }
+            }
+            Waveform::Sine { offset, ampl, freq, phase, damp, delay } => {
+                let t = (t - delay).max(0.0);
+                offset + ampl * (-t * damp).exp() * (2.0 * PI * freq * t + phase).sin()
+            }
+            Waveform::Exp { val0, val1, td1, tau1, td2, tau2 } => {
+                let mut res = val0;
+                if t > td1 {
+                    res += (val1 - val0) * exp_step(t - td1, tau1);
+                }
+                if t > td2 {
+                    res += (val0 - val1) * exp_step(t - td2, tau2);
+                }
+                res
+            }
+            Waveform::Pwl(ref points) => {
+                let idx = points.partition_point(|&(time, _)| time <= t);
+                if idx == 0 {
+                    return points[0].1;
+                }
+                if idx == points.len() {
+                    return points[idx - 1].1;
+                }
+                let (t0, v0) = points[idx - 1];
+                let (t1, v1) = points[idx];
+                v0 + (v1 - v0) * (t - t0) / (t1 - t0)
+            }
+        }
+    }
+}
+
+/// `1 - exp(-t/tau)`, a step for `tau == 0`
+fn exp_step(t: f64, tau: f64) -> f64 {
+    if tau > 0.0 {
+        1.0 - (-t / tau).exp()
+    } else {
+        1.0
+    }
+}
+
+fn parse_pwl(wave: &str) -> Result<Vec<(f64, f64)>> {
+    let vals = wave
+        .split(|c: char| c.is_whitespace() || c == ',')
+        .filter(|val| !val.is_empty())
+        .map(|val| val.parse::<f64>().with_context(|| format!("\"{val}\" is not a number")))
+        .collect::<Result<Vec<_>>>()?;
+    if vals.is_empty() || vals.len() % 2 != 0 {
+        bail!("expected a non-empty list of time/value pairs")
+    }
+    let points: Vec<_> = vals.chunks(2).map(|pair| (pair[0], pair[1])).collect();
+    if points.windows(2).any(|window| window[1].0 <= window[0].0) {
+        bail!("the time points must be strictly increasing")
+    }
+    Ok(points)
+}
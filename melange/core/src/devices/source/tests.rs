@@ -0,0 +1,116 @@
+use std::f64::consts::PI;
+
+use super::{parse_pwl, SourceParams, Waveform};
+
+#[track_caller]
+fn assert_close(val: f64, ref_val: f64) {
+    assert!((val - ref_val).abs() <= 1e-12 * ref_val.abs().max(1.0), "{val} != {ref_val}");
+}
+
+#[test]
+fn dc() {
+    assert_close(Waveform::Dc.eval(0.0, 2.5), 2.5);
+    assert_close(Waveform::Dc.eval(1.0, 2.5), 2.5);
+}
+
+#[test]
+fn pulse() {
+    let pulse = Waveform::Pulse {
+        val0: 0.0,
+        val1: 1.0,
+        delay: 1.0,
+        rise: 1.0,
+        fall: 1.0,
+        width: 2.0,
+        period: 10.0,
+    };
+    assert_close(pulse.eval(0.5, 0.0), 0.0);
+    assert_close(pulse.eval(1.5, 0.0), 0.5);
+    assert_close(pulse.eval(3.0, 0.0), 1.0);
+    assert_close(pulse.eval(4.5, 0.0), 0.5);
+    assert_close(pulse.eval(6.0, 0.0), 0.0);
+    // the second period
+    assert_close(pulse.eval(11.5, 0.0), 0.5);
+}
+
+#[test]
+fn sine() {
+    let sine =
+        Waveform::Sine { offset: 1.0, ampl: 2.0, freq: 1e3, phase: 0.0, damp: 0.0, delay: 1e-3 };
+    // the offset before the delay
+    assert_close(sine.eval(0.0, 0.0), 1.0);
+    assert_close(sine.eval(1e-3, 0.0), 1.0);
+    assert_close(sine.eval(1.25e-3, 0.0), 3.0);
+    assert_close(sine.eval(1.75e-3, 0.0), -1.0);
+
+    let damped =
+        Waveform::Sine { offset: 1.0, ampl: 2.0, freq: 1e3, phase: 0.0, damp: 1e3, delay: 0.0 };
+    assert_close(damped.eval(0.25e-3, 0.0), 1.0 + 2.0 * (-0.25f64).exp());
+
+    let shifted = Waveform::Sine {
+        offset: 1.0,
+        ampl: 2.0,
+        freq: 1e3,
+        phase: PI / 2.0,
+        damp: 0.0,
+        delay: 0.0,
+    };
+    assert_close(shifted.eval(0.0, 0.0), 3.0);
+}
+
+#[test]
+fn exp() {
+    let exp = Waveform::Exp { val0: 0.0, val1: 1.0, td1: 1e-9, tau1: 1e-9, td2: 5e-9, tau2: 2e-9 };
+    assert_close(exp.eval(0.0, 0.0), 0.0);
+    assert_close(exp.eval(1e-9, 0.0), 0.0);
+    assert_close(exp.eval(2e-9, 0.0), 1.0 - (-1f64).exp());
+    assert_close(exp.eval(5e-9, 0.0), 1.0 - (-4f64).exp());
+    // the fall starts from the value reached by the rise
+    assert_close(exp.eval(7e-9, 0.0), (-1f64).exp() - (-6f64).exp());
+
+    // a step without time constant
+    let step = Waveform::Exp { val0: 0.0, val1: 1.0, td1: 1e-9, tau1: 0.0, td2: 5e-9, tau2: 0.0 };
+    assert_close(step.eval(2e-9, 0.0), 1.0);
+    assert_close(step.eval(6e-9, 0.0), 0.0);
+}
+
+#[test]
+fn pwl() {
+    let pwl = Waveform::Pwl(vec![(1.0, 0.0), (2.0, 1.0), (4.0, -1.0)]);
+    // the first value before the first point
+    assert_close(pwl.eval(0.0, 2.5), 0.0);
+    assert_close(pwl.eval(1.0, 2.5), 0.0);
+    assert_close(pwl.eval(1.5, 2.5), 0.5);
+    assert_close(pwl.eval(2.0, 2.5), 1.0);
+    assert_close(pwl.eval(3.0, 2.5), 0.0);
+    assert_close(pwl.eval(3.5, 2.5), -0.5);
+    // the last value after the last point
+    assert_close(pwl.eval(4.0, 2.5), -1.0);
+    assert_close(pwl.eval(10.0, 2.5), -1.0);
+}
+
+#[test]
+fn parse_pwl_points() {
+    let points = parse_pwl("0 0, 1e-9 1,2e-9\t 0.5 ").unwrap();
+    assert_eq!(points, [(0.0, 0.0), (1e-9, 1.0), (2e-9, 0.5)]);
+    assert_eq!(parse_pwl("1,2").unwrap(), [(1.0, 2.0)]);
+}
+
+#[test]
+fn parse_pwl_errors() {
+    let error = |wave: &str| parse_pwl(wave).unwrap_err().to_string();
+    assert_eq!(error(""), "expected a non-empty list of time/value pairs");
+    assert_eq!(error(" , "), "expected a non-empty list of time/value pairs");
+    assert_eq!(error("0 1 2"), "expected a non-empty list of time/value pairs");
+    assert_eq!(error("0 1 1 x"), "\"x\" is not a number");
+    assert_eq!(error("0 1 0 2"), "the time points must be strictly increasing");
+    assert_eq!(error("1 0 0 1"), "the time points must be strictly increasing");
+
+    let params =
+        SourceParams { ty: "pwl".to_owned(), wave: "0 1 x".to_owned(), ..SourceParams::default() };
+    let err = params.waveform().unwrap_err();
+    assert_eq!(
+        format!("{err:#}"),
+        "invalid pwl wave: \"x\" is not a number: invalid float literal"
+    );
+}
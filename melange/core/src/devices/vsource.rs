@@ -1,5 +1,4 @@
-use std::cell::Cell;
-
+use std::cell::{Cell, RefCell};
 use std::ptr::NonNull;
 use std::rc::Rc;
 
@@ -9,8 +8,11 @@ use stdx::iter::zip;
 use typed_index_collections::TiSlice;
 
 use crate::circuit::Node;
-use crate::devices::{update_matrix_entry, DeviceImpl, DeviceParams, InstanceImpl, Type};
-use crate::simulation::{MatrixEntryIter, SimBuilder};
+use crate::devices::source::{source_params, SourceParams, Waveform, DC};
+use crate::devices::{
+    two_terminal_aliases, update_matrix_entry, DeviceImpl, DeviceParams, InstanceImpl,
+};
+use crate::simulation::{MatrixEntryIter, RawSimParams, SimBuilder};
 
 use super::{ModelImpl, ParamId, SimInfo};
 
@@ -22,10 +24,6 @@ impl VoltageSrc {
     }
 }
 
-const DC: ParamId = ParamId(0u32);
-const MAG: ParamId = ParamId(1u32);
-const PHASE: ParamId = ParamId(2u32);
-
 const MATRIX_ANODE_BR: usize = 0;
 const MATRIX_BR_ANODE: usize = 1;
 const MATRIX_CATHODE_BR: usize = 2;
@@ -40,12 +38,12 @@ impl DeviceImpl for VoltageSrc {
         vec!["A", "C"].into_boxed_slice()
     }
 
+    fn get_terminal_aliases(&self) -> Box<[(&'static str, &'static str)]> {
+        two_terminal_aliases()
+    }
+
     fn get_params(&self) -> DeviceParams {
-        let mut res = DeviceParams::default();
-        res.insert_instance_param("dc", Type::Real);
-        res.insert_instance_param("mag", Type::Real);
-        res.insert_instance_param("phase", Type::Real);
-        res
+        source_params()
     }
 
     fn new_model(&self) -> Rc<dyn ModelImpl> {
@@ -55,24 +53,20 @@ impl DeviceImpl for VoltageSrc {
 
 #[derive(Default)]
 struct VoltageSrcModel {
-    dc: Cell<f64>,
-    mag: Cell<f64>,
-    phase: Cell<f64>,
+    params: RefCell<SourceParams>,
 }
 
 impl ModelImpl for VoltageSrcModel {
-    fn process_params(&self) -> Result<()> {
+    fn process_params(&self, _sim_params: &RawSimParams) -> Result<()> {
         Ok(())
     }
 
     fn set_real_param(&self, param: ParamId, val: f64) {
-        let dst = match param {
-            DC => &self.dc,
-            MAG => &self.mag,
-            PHASE => &self.phase,
-            _ => unreachable!("vsource: unknown num param {param:?}"),
-        };
-        dst.set(val);
+        self.params.borrow_mut().set_real_param(param, val)
+    }
+
+    fn set_str_param(&self, param: ParamId, val: &str) {
+        self.params.borrow_mut().set_str_param(param, val)
     }
 
     fn new_instance(self: Rc<Self>) -> Box<dyn super::InstanceImpl> {
@@ -80,8 +74,9 @@ impl ModelImpl for VoltageSrcModel {
             anode: Node::GROUND,
             cathode: Node::GROUND,
             branch: Node::GROUND,
-            dc: self.dc.get(),
-            ac: Complex64::from_polar(self.mag.get(), self.phase.get()),
+            params: self.params.borrow().clone(),
+            waveform: Waveform::Dc,
+            val: 0.0,
             matrix_entries: [NonNull::dangling(); 4],
         })
     }
@@ -91,8 +86,10 @@ struct VoltageSrcInstance {
     anode: Node,
     cathode: Node,
     branch: Node,
-    dc: f64,
-    ac: Complex64,
+    params: SourceParams,
+    waveform: Waveform,
+    /// the value at the current time point, reduced during source stepping
+    val: f64,
     matrix_entries: [NonNull<Cell<f64>>; 4],
 }
 
@@ -109,6 +106,8 @@ impl InstanceImpl for VoltageSrcInstance {
             bail!("expected at least 2 connections")
         };
 
+        self.waveform = self.params.waveform()?;
+
         let branch = builder.new_internal_branch("branch");
         self.anode = anode;
         self.cathode = cathode;
@@ -124,12 +123,18 @@ impl InstanceImpl for VoltageSrcInstance {
     }
 
     fn set_real_param(&mut self, param: ParamId, val: f64) {
+        self.params.set_real_param(param, val)
+    }
+
+    fn get_real_param(&self, param: ParamId) -> f64 {
         match param {
-            DC => self.dc = val,
-            MAG => self.ac = Complex64::from_polar(val, self.ac.arg()),
-            PHASE => self.ac = Complex64::from_polar(self.ac.norm_sqr(), val),
-            _ => unreachable!("vsource: unknown num param {param:?}"),
-        };
+            DC => self.params.dc(&self.waveform),
+            _ => unreachable!("source: param {param:?} can not be read"),
+        }
+    }
+
+    fn set_str_param(&mut self, param: ParamId, val: &str) {
+        self.params.set_str_param(param, val)
     }
 
     fn populate_matrix_ptrs(&mut self, matrix_entries: MatrixEntryIter) {
@@ -138,8 +143,10 @@ impl InstanceImpl for VoltageSrcInstance {
         }
     }
 
-    fn eval(&mut self, _sim_info: SimInfo<'_>) -> Result<()> {
-        Ok(())
+    fn eval(&mut self, sim_info: SimInfo<'_>) -> Result<bool> {
+        let scale = sim_info.sim_params.source_scale_factor();
+        self.val = self.params.value(&self.waveform, &sim_info) * scale;
+        Ok(false)
     }
 
     unsafe fn load_matrix_resist(&self) {
@@ -157,7 +164,7 @@ impl InstanceImpl for VoltageSrcInstance {
     fn load_residual_resist(&self, prev_solve: &TiSlice<Node, f64>, rhs: &mut TiSlice<Node, f64>) {
         rhs[self.anode] += prev_solve[self.branch];
         rhs[self.cathode] -= prev_solve[self.branch];
-        rhs[self.branch] -= self.dc;
+        rhs[self.branch] -= self.val;
         rhs[self.branch] += prev_solve[self.anode] - prev_solve[self.cathode];
     }
 
@@ -166,7 +173,7 @@ impl InstanceImpl for VoltageSrcInstance {
         _prev_solve: &TiSlice<Node, f64>,
         rhs: &mut TiSlice<Node, Complex64>,
     ) {
-        rhs[self.branch] += self.ac;
+        rhs[self.branch] += self.params.ac();
     }
 
     fn load_lead_current_resist(&self, dc_solve: &TiSlice<Node, f64>, dst: &mut [f64]) {
@@ -10,7 +10,7 @@ use camino::Utf8PathBuf;
 use typed_index_collections::TiVec;
 
 use crate::circuit::{Circuit, DeviceId, InstanceId, ModelId, NameSpaceEntry, Node};
-use crate::{veriloga, Arena, Expr};
+use crate::{veriloga, CircuitParam, CircuitParamCtx, Expr};
 
 /// A textual description of a circuit from which a circuit can be built.
 /// This serves primarily as an intermediate step for the netlist parser.
@@ -30,9 +30,11 @@ pub struct CircuitDescription {
     pub models: TiVec<ModelId, CircuitModelDescription>,
     /// A list of Verilog-A files that need to be compiled
     pub va_files: Vec<Utf8PathBuf>,
+    /// Circuit parameters and the expressions that are assigned to them
+    pub parameters: Vec<(CircuitParam, Expr)>,
 
-    /// Arena allocator for expressions
-    pub earena: Arena,
+    /// The context in which all circuit parameters referenced by this description are defined
+    pub ctx: CircuitParamCtx,
 }
 
 /// A device instance inside a [`CircuitDescription`](create::circuit::CircuitDescription).
@@ -103,29 +105,54 @@ impl CircuitDescription {
     /// If any of the following conditions occurs, an error is returned instead:
     /// * Verilog-A compilation fails
     /// * A model/subcircuit/device is not found
-    pub fn elaborate(self, earena: &mut Arena, opts: &veriloga::Opts) -> Result<Circuit> {
-        let mut res = Circuit::new(self.name, earena);
-        for va_file in self.va_files {
-            res.load_veriloga_file(va_file, opts)?;
+    pub fn elaborate(self, opts: &veriloga::Opts) -> Result<Circuit> {
+        let mut res = Circuit::with_ctx(self.name.clone(), self.ctx);
+        res.add_description(self, opts)?;
+        Ok(res)
+    }
+}
+
+impl Circuit {
+    /// Elaborates the information in `descr` (see [`CircuitDescription::elaborate`]) and adds the
+    /// resulting models and instances to this circuit.
+    ///
+    /// The parameters of `descr` must have been defined in the context of this circuit.
+    pub fn add_description(
+        &mut self,
+        descr: CircuitDescription,
+        opts: &veriloga::Opts,
+    ) -> Result<()> {
+        if descr.ctx != self.ctx {
+            bail!(
+                "the parameters of '{}' are not defined in the context of circuit '{}'",
+                descr.name,
+                self.name
+            );
+        }
+
+        for va_file in descr.va_files {
+            self.load_veriloga_file(va_file, opts)?;
+        }
+
+        for (param, val) in descr.parameters {
+            self.param_assignments.insert(param, val);
         }
 
-        for model in self.models {
+        for model in descr.models {
             let name = model.name.clone();
-            res.elaborate_model(model)
+            self.elaborate_model(model)
                 .with_context(|| format!("while elaborating model '{name}'"))?;
         }
 
-        for inst in self.instances {
+        for inst in descr.instances {
             let name = inst.name.clone();
-            res.elaborate_instance(inst)
+            self.elaborate_instance(inst)
                 .with_context(|| format!("while elaborating instance '{name}'"))?;
         }
 
-        Ok(res)
+        Ok(())
     }
-}
 
-impl Circuit {
     /// Creates a circuit model from a [`CircuitDescription`]
     pub fn elaborate_model(&mut self, descr: CircuitModelDescription) -> Result<ModelId> {
         let device = match self.lookup_device(&descr.device) {
@@ -194,7 +221,7 @@ impl Circuit {
             }
 
             None => {
-                bail!("'{}' not found", instance.name);
+                bail!("'{}' not found", instance.master);
             }
         };
 
@@ -34,6 +34,10 @@ impl Expr {
         Expr::Eval(arena.alloc(ExprData::Param(param)))
     }
 
+    pub fn str(arena: &mut Arena, val: &str) -> Expr {
+        Expr::Value(Value::Str(arena.intern.get_or_intern(val)))
+    }
+
     pub fn cond(arena: &mut Arena, cond: Expr, then_val: Expr, else_val: Expr) -> Result<Expr> {
         let res = match cond {
             _ if then_val == else_val => then_val,
@@ -154,7 +158,7 @@ impl Expr {
                 };
                 Ok(ptr.into())
             }
-            Expr::Value(arg) => Ok((1.0 / arg.to_num()?).into()),
+            Expr::Value(arg) => Ok((-arg.to_num()?).into()),
         }
     }
 
@@ -1,6 +1,6 @@
 pub use crate::circuit::Circuit;
 pub use crate::elaboration::CircuitDescription;
-pub use crate::expr::{Arena, Expr, ExprEvalCtx, Value};
+pub use crate::expr::{Arena, CircuitParam, CircuitParamCtx, Expr, ExprEvalCtx, Value};
 
 // #[macro_use]
 // mod utils;
@@ -9,8 +9,9 @@ mod devices;
 pub mod elaboration;
 mod expr;
 pub mod simulation;
+pub mod spectre;
 mod utils;
-mod veriloga;
+pub mod veriloga;
 
-// #[cfg(all(test, not(windows)))]
-// mod tests;
+#[cfg(test)]
+mod tests;
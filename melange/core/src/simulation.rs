@@ -1,24 +1,41 @@
-use std::mem::replace;
 use std::rc::Rc;
 
+use ahash::AHashMap;
 use anyhow::{bail, Context, Result};
 use cli_table::{print_stdout, Cell, Style, Table, TableStruct};
 use klu_rs::{FixedKluMatrix, KluData};
+use log::info;
 use num_complex::Complex64;
 use stdx::iter::zip;
 use typed_index_collections::{TiSlice, TiVec};
 
 use crate::circuit::{CircuitModelSrc, InstanceId, ModelId, Node};
+pub use crate::devices::OpVar;
 use crate::devices::{InstanceImpl, ModelImpl, Type};
 use crate::expr::{CircuitParam, ExprEvalCtxRef};
-use crate::simulation::flags::{EvalFlags, OperatingPointAnalysis, SimulationState};
+pub(crate) use crate::simulation::flags::EvalFlags;
+use crate::simulation::flags::{OperatingPointAnalysis, SimulationState};
+pub use crate::simulation::harmonic_balance::HbSolver;
+pub use crate::simulation::homotopy::Homotopy;
 pub use crate::simulation::matrix::MatrixEntryIter;
 use crate::simulation::matrix::{MatrixBuilder, SimulationMatrix};
+pub use crate::simulation::newton::{ConvergenceFailure, NonConvergedUnknown};
+use crate::simulation::newton::{NewtonStatus, Shunt};
+pub use crate::simulation::node_report::InstanceNodes;
+pub use crate::simulation::sim_params::{RawSimParams, SimParams};
+pub use crate::simulation::sweep::{Sweep, SweepResult, SweepVar};
 use crate::utils::PrettyPrint;
 use crate::{Arena, Circuit, Value};
 
 mod flags;
+mod harmonic_balance;
+mod homotopy;
 mod matrix;
+mod newton;
+mod node_report;
+mod op_report;
+mod sim_params;
+mod sweep;
 
 pub struct Simulation<'a> {
     circ: &'a Circuit,
@@ -35,14 +52,19 @@ pub struct Simulation<'a> {
     residual_resist: TiVec<Node, f64>,
     residual_react: TiVec<Node, f64>,
     pub config: SimConfig,
+    sim_params: RawSimParams,
     state: SimulationState,
 
     omega: f64,
+    hb_freq: f64,
+    hb_solution: TiVec<Node, Vec<Complex64>>,
 }
 
 #[derive(Debug, Clone)]
 pub struct NodeInfo {
     pub name: String,
+    /// The instance that created this internal unknown (`None` for the nodes of the circuit)
+    pub instance: Option<InstanceId>,
     pub atol: f64,
     pub units: &'static str,
     pub residual_units: &'static str,
@@ -54,6 +76,7 @@ pub struct SimBuilder<'a> {
     node_info: &'a mut TiVec<Node, NodeInfo>,
     circ: &'a Circuit,
     pub config: &'a SimConfig,
+    pub sim_params: &'a RawSimParams,
 }
 
 impl<'a> SimBuilder<'a> {
@@ -77,7 +100,8 @@ impl<'a> SimBuilder<'a> {
         residual_units: &'static str,
     ) -> Node {
         let name = format!("{}::{name}", self.circ[self.instance].name);
-        self.node_info.push_and_get_key(NodeInfo { atol, name, units, residual_units })
+        let instance = Some(self.instance);
+        self.node_info.push_and_get_key(NodeInfo { atol, name, instance, units, residual_units })
     }
 
     pub fn ensure_matrix_entry(&mut self, column: Node, row: Node) {
@@ -118,6 +142,7 @@ impl Circuit {
             .nodes()
             .map(|node| NodeInfo {
                 name: self.node_name(node).to_owned(),
+                instance: None,
                 atol: config.current_atol,
                 units: "V",
                 residual_units: "A",
@@ -132,12 +157,15 @@ impl Circuit {
             nodes,
             solution: vec![0f64; self.num_nodes() as usize].into(),
             circ: self,
+            sim_params: RawSimParams::new(&config.sim_params),
             config,
             state: SimulationState::empty(),
             ac_solution: vec![Complex64::default(); self.num_nodes() as usize].into(),
             residual_resist: vec![0f64; self.num_nodes() as usize].into(),
             residual_react: vec![0f64; self.num_nodes() as usize].into(),
             omega: 1.0,
+            hb_freq: 0.0,
+            hb_solution: TiVec::new(),
         };
 
         Ok(res)
@@ -154,6 +182,11 @@ impl Simulation<'_> {
         res.table()
     }
 
+    /// The nodes and internal unknowns of the simulated circuit
+    pub fn nodes(&self) -> &TiSlice<Node, NodeInfo> {
+        &self.nodes
+    }
+
     pub fn print_solution(&self) {
         print_stdout(Self::vec_table(&self.solution.raw, &self.nodes.raw)).unwrap()
     }
@@ -203,6 +236,7 @@ impl Simulation<'_> {
 
     pub fn prepare_solver(&mut self, mut eval_ctx: ExprEvalCtxRef, arena: &Arena) -> Result<()> {
         self.wipe_solution();
+        self.sim_params = RawSimParams::new(&self.config.sim_params);
         for param in arena.ctx_params(self.circ.ctx) {
             if self.circ.param_assignments.contains_key(&param) {
                 continue;
@@ -256,7 +290,7 @@ impl Simulation<'_> {
                     }
                 }
             }
-            model_data.process_params()?;
+            model_data.process_params(&self.sim_params)?;
         }
 
         self.matrix_builder.reset(self.circ);
@@ -268,9 +302,14 @@ impl Simulation<'_> {
             matrix_builder: &mut self.matrix_builder,
             node_info: &mut self.nodes,
             config: &self.config,
+            sim_params: &self.sim_params,
         };
 
-        let temp = eval_ctx[CircuitParam::TEMPERATURE].to_num().context("invalid tempetaure")?;
+        // the temperature is passed to the models ($temperature) in Kelvin
+        let temp = eval_ctx[CircuitParam::TEMPERATURE].to_num().context("invalid temperature")?;
+        if temp.is_nan() || temp <= 0.0 {
+            bail!("the temperature must be positive (in Kelvin) but was {temp}")
+        }
 
         for inst in self.circ.instances() {
             let instance_info = &self.circ[inst];
@@ -312,6 +351,8 @@ impl Simulation<'_> {
         self.residual_react.resize(num_nodes, 0f64);
         self.ac_solution.resize(num_nodes, Complex64::default());
 
+        // gmin stepping requires the diagonal
+        self.matrix_builder.ensure_diagonal(num_nodes - 1);
         let matrix = SimulationMatrix::new_or_reset(self.matrix.take(), &self.matrix_builder);
         for (instance, data) in self.instance_data.iter_mut_enumerated() {
             let instance_entries = MatrixEntryIter::new(&matrix, &self.matrix_builder, instance);
@@ -347,15 +388,15 @@ impl Simulation<'_> {
         self.state = SimulationState::AT_AC_OP;
     }
 
-    // pub fn noise_op(&mut self) -> Result<&TiSlice<Node, f64>> {
-    //     self.solve_op(OperatingPointAnalysis::Noise)?;
-    //     Ok(&self.solution)
-    // }
+    pub fn noise_op(&mut self) -> Result<&TiSlice<Node, f64>> {
+        self.solve_op(OperatingPointAnalysis::Noise)?;
+        Ok(&self.solution)
+    }
 
-    // pub fn restore_noise_op(&mut self, op: &TiSlice<Node, f64>) {
-    //     self.solution.copy_from_slice(op);
-    //     self.stat = SimulationState::AT_NOISE_OP;
-    // }
+    pub fn restore_noise_op(&mut self, op: &TiSlice<Node, f64>) {
+        self.solution.copy_from_slice(op);
+        self.state = SimulationState::AT_NOISE_OP;
+    }
 
     pub fn set_initial_guess(&mut self, guess: &TiSlice<Node, f64>) {
         self.solution.copy_from_slice(guess);
@@ -369,79 +410,26 @@ impl Simulation<'_> {
             return Ok(());
         }
 
-        let flags = analysis.eval_flags();
-
-        let debug = self.config.debug;
-        let matrix =
-            self.matrix.as_mut().context("Simulation must be populated before it can run")?;
-
-        let mut i = 0;
-        loop {
-            let sim_info = SimInfo { abstime: 0f64, prev_solve: &self.solution, flags };
-            for inst in &mut *self.instance_data {
-                inst.eval(sim_info)?;
-
-                // this is save because we call populate_matrix_ptrs during Simulation construction
-                unsafe { inst.load_matrix_resist() }
-                inst.load_residual_resist(&self.solution, &mut self.residual_resist);
-
-                if analysis.time_integration() {
-                    let alpha = 0.0;
-                    unsafe { inst.load_matrix_react(alpha) }
-                    inst.load_residual_react(&self.solution, &mut self.residual_react);
-                }
-            }
-
-            // TODO time integration for tran analysis
-
-            if debug {
-                print_stdout(Self::matrix_table(&self.nodes, &matrix.nonlinear_matrix)).unwrap();
-            }
-
-            let singular = matrix.nonlinear_matrix.lu_factorize(None);
-            if singular {
-                bail!("matrix is singular")
-            }
-
-            matrix.nonlinear_matrix.solve_linear_system(&mut self.residual_resist.raw[1..]);
-
-            if debug {
-                print_stdout(Self::vec_table(&self.residual_resist.raw, &self.nodes.raw)).unwrap();
-            }
-
-            // reset matrix
-            matrix.nonlinear_matrix.write_zero();
-            let mut found_solution = true;
-            for ((dst, delta), node_info) in
-                zip(&mut self.solution.raw[1..], &mut self.residual_resist.raw[1..])
-                    .zip(&self.nodes.raw[1..])
-            {
-                let delta = replace(delta, 0f64);
-                let new_val = *dst - delta;
-                let atol = node_info.atol;
-                let tol = atol.max(new_val * self.config.rtol);
-                if delta > tol {
-                    found_solution = false;
-                }
-                *dst = new_val;
-            }
-
-            if debug {
-                print_stdout(Self::vec_table(&self.solution.raw, &self.nodes.raw)).unwrap();
+        let initial_guess = self.solution.clone();
+        let mut failure = match self.newton(analysis, true, Shunt::NONE)? {
+            NewtonStatus::Converged => {
+                self.state = op_flag;
+                return Ok(());
             }
+            NewtonStatus::Failed(failure) => failure,
+        };
 
-            if found_solution && i > 0 {
-                break;
-            }
-            i += 1;
-
-            if i == self.config.maxiters {
-                bail!("Simulation failed to converge after {i} iterations")
+        for homotopy in self.config.homotopy.clone() {
+            info!("Newton iteration failed to converge, attempting {homotopy}");
+            self.solution.copy_from_slice(&initial_guess);
+            if let NewtonStatus::Converged = self.homotopy(homotopy, analysis)? {
+                self.state = op_flag;
+                return Ok(());
             }
+            failure.homotopy.push(homotopy);
         }
 
-        self.state = op_flag;
-        Ok(())
+        Err(failure.into())
     }
 
     pub fn set_omega(&mut self, omega: f64) {
@@ -471,8 +459,12 @@ impl Simulation<'_> {
                 inst.load_ac_residual(&self.solution, &mut self.ac_solution);
             }
         } else {
-            let sim_info =
-                SimInfo { abstime: 0f64, prev_solve: &self.solution, flags: EvalFlags::AC };
+            let sim_info = SimInfo {
+                abstime: 0f64,
+                prev_solve: &self.solution,
+                sim_params: &self.sim_params,
+                flags: EvalFlags::AC,
+            };
             for inst in &mut *self.instance_data {
                 inst.eval(sim_info)?;
 
@@ -506,6 +498,80 @@ impl Simulation<'_> {
         Ok(&self.ac_solution)
     }
 
+    /// Calculates the noise power spectral density at `out` (relative to ground) for
+    /// the frequency `freq`. Correlated noise sources are taken into account with their
+    /// full correlation matrix.
+    pub fn noise(&mut self, out: Node, freq: f64) -> Result<f64> {
+        self.noise_op()?;
+
+        let matrix =
+            self.matrix.as_mut().context("simulation must be setup before noise() is called")?;
+        matrix.nonlinear_matrix.write_zero();
+        matrix.ac_matrix.write_zero();
+
+        let omega = 2.0 * std::f64::consts::PI * freq;
+        let sim_info = SimInfo {
+            abstime: 0f64,
+            prev_solve: &self.solution,
+            sim_params: &self.sim_params,
+            flags: EvalFlags::NOISE,
+        };
+        let mut sources = Vec::new();
+        for inst in &mut *self.instance_data {
+            inst.eval(sim_info)?;
+
+            unsafe {
+                inst.load_matrix_resist();
+                inst.load_matrix_react(omega);
+            }
+            inst.load_noise(freq, &mut sources);
+        }
+        // the ac matrix now contains the small signal matrix at a different frequency
+        self.state.remove(SimulationState::AT_AC | SimulationState::HAS_AC_EVAL);
+
+        for (dst, src) in zip(matrix.ac_matrix.data(), matrix.nonlinear_matrix.data()) {
+            let val = Complex64::new(src.get(), dst.get().im);
+            dst.set(val);
+        }
+
+        if self.config.debug {
+            println!("noise frequency = {freq}");
+            print_stdout(Self::matrix_table(&self.nodes, &matrix.ac_matrix)).unwrap();
+        }
+
+        let is_singular = matrix.ac_matrix.lu_factorize(None);
+        if is_singular {
+            bail!("noise matrix is singular!")
+        }
+
+        // the output noise is S = sum_kl z_k * C_kl * conj(z_l) where z_k is the transfer
+        // function from the k-th injection of a noise source to the output node
+        let mut rhs = vec![Complex64::default(); self.nodes.len()];
+        let mut res = 0f64;
+        for src in &sources {
+            let transfer: Vec<_> = src
+                .nodes
+                .iter()
+                .map(|&(hi, lo)| {
+                    rhs.fill(Complex64::default());
+                    rhs[usize::from(hi)] += 1.0;
+                    rhs[usize::from(lo)] -= 1.0;
+                    matrix.ac_matrix.solve_linear_system(&mut rhs[1..]);
+                    rhs[usize::from(out)]
+                })
+                .collect();
+
+            let n = transfer.len();
+            for (k, z_k) in transfer.iter().enumerate() {
+                for (l, z_l) in transfer.iter().enumerate() {
+                    res += (z_k * src.correlation[k * n + l] * z_l.conj()).re;
+                }
+            }
+        }
+
+        Ok(res)
+    }
+
     pub fn ac_lead_current(&mut self, inst: InstanceId) -> Result<Vec<Complex64>> {
         self.ac()?;
         let mut dst = vec![Complex64::default(); self.circ[inst].connections.len()];
@@ -527,6 +593,29 @@ pub struct SimConfig {
     pub voltage_atol: f64,
     pub current_atol: f64,
     pub rtol: f64,
+    /// The largest change of a voltage during a single Newton iteration, larger steps are
+    /// clipped.
+    pub max_voltage_step: f64,
+    /// The largest change of individual unknowns (by name) during a single Newton iteration.
+    /// Overrides `max_voltage_step` and also applies to unknowns that are not voltages.
+    pub max_node_step: AHashMap<String, f64>,
+    /// The convergence aids that are attempted (in order) if the Newton iteration fails to find
+    /// the operating point.
+    pub homotopy: Vec<Homotopy>,
+    /// The maximum number of steps of each convergence aid.
+    pub max_homotopy_steps: u32,
+    /// The values returned by `$simparam`, changes only take effect once the solver is prepared
+    /// again (see [`Simulation::prepare_solver`]).
+    pub sim_params: SimParams,
+    /// Whether the collapsible node pairs of compact models are collapsed into a single node,
+    /// changes only take effect once the solver is prepared again. Disabling collapsing only
+    /// adds unknowns and should not change the results (useful for verifying models).
+    pub collapse_nodes: bool,
+    /// The number of harmonics of the fundamental frequency that are solved by the harmonic
+    /// balance analysis (see [`Simulation::hb`]).
+    pub hb_harmonics: u32,
+    /// The linear solver used by the harmonic balance analysis.
+    pub hb_solver: HbSolver,
 }
 
 impl Default for SimConfig {
@@ -537,6 +626,29 @@ impl Default for SimConfig {
             voltage_atol: 1e-6,
             current_atol: 1e-12,
             rtol: 1e-3,
+            max_voltage_step: f64::INFINITY,
+            max_node_step: AHashMap::new(),
+            homotopy: vec![
+                Homotopy::GminStepping,
+                Homotopy::SourceStepping,
+                Homotopy::PseudoTransient,
+            ],
+            max_homotopy_steps: 100,
+            sim_params: SimParams::default(),
+            collapse_nodes: true,
+            hb_harmonics: 8,
+            hb_solver: HbSolver::Direct,
+        }
+    }
+}
+
+impl SimConfig {
+    /// The largest change of the unknown `node` during a single Newton iteration.
+    pub fn max_step(&self, node: &NodeInfo) -> f64 {
+        match self.max_node_step.get(&node.name) {
+            Some(&step) => step,
+            None if node.units == "V" => self.max_voltage_step,
+            None => f64::INFINITY,
         }
     }
 }
@@ -545,5 +657,6 @@ impl Default for SimConfig {
 pub struct SimInfo<'a> {
     pub abstime: f64,
     pub prev_solve: &'a TiSlice<Node, f64>,
+    pub sim_params: &'a RawSimParams,
     pub flags: EvalFlags,
 }
@@ -0,0 +1,726 @@
+use std::f64::consts::PI;
+
+use anyhow::{bail, Context, Result};
+use klu_rs::{KluMatrixBuilder, KluSettings};
+use log::info;
+use num_complex::Complex64;
+use stdx::iter::zip;
+use typed_index_collections::{TiSlice, TiVec};
+
+use crate::circuit::{InstanceId, Node};
+use crate::simulation::flags::{EvalFlags, OperatingPointAnalysis, SimulationState};
+use crate::simulation::matrix::RealMatrix;
+use crate::simulation::{SimInfo, Simulation};
+
+#[cfg(test)]
+mod tests;
+
+/// The linear solver used during the Newton iteration of the harmonic balance analysis
+/// (see [`SimConfig::hb_solver`](crate::simulation::SimConfig::hb_solver)).
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum HbSolver {
+    /// Factorizes the full Jacobian with KLU. Every entry of the circuit matrix becomes a
+    /// dense block that couples all harmonics.
+    Direct,
+    /// Solves the Jacobian with restarted GMRES without assembling it. The Jacobian of the
+    /// time averaged circuit (which does not couple the harmonics) is the preconditioner.
+    Krylov {
+        /// The reduction of the residual required from every linear solve
+        rtol: f64,
+        /// The maximum number of GMRES iterations of every linear solve
+        maxiters: u32,
+    },
+}
+
+const GMRES_RESTART: usize = 30;
+
+/// Transforms between the samples of a periodic signal at the collocation points and its
+/// real Fourier coefficients `[a0, a1, b1, a2, b2, ...]` where
+/// `x(t) = a0 + sum_h a_h * cos(h*w*t) + b_h * sin(h*w*t)`.
+struct Fourier {
+    num_harmonics: usize,
+    buf: Vec<Complex64>,
+}
+
+impl Fourier {
+    fn new(num_harmonics: usize) -> Fourier {
+        // sample twice as often as required to reduce the aliasing of the nonlinearities
+        let num_samples = (4 * num_harmonics + 2).next_power_of_two();
+        Fourier { num_harmonics, buf: vec![Complex64::default(); num_samples] }
+    }
+
+    fn num_samples(&self) -> usize {
+        self.buf.len()
+    }
+
+    fn num_coeffs(&self) -> usize {
+        2 * self.num_harmonics + 1
+    }
+
+    /// The DFT of `samples` divided by the number of samples. The entry `n` is the complex
+    /// amplitude of `exp(j*n*w*t)`.
+    fn spectrum(&mut self, samples: &[f64]) -> &[Complex64] {
+        for (dst, &val) in zip(&mut self.buf, samples) {
+            *dst = val.into();
+        }
+        fft(&mut self.buf, false);
+        let scale = 1.0 / self.buf.len() as f64;
+        for val in &mut self.buf {
+            *val *= scale;
+        }
+        &self.buf
+    }
+
+    fn analyze(&mut self, samples: &[f64], coeffs: &mut [f64]) {
+        let spectrum = self.spectrum(samples);
+        for (coeff, dst) in coeffs.iter_mut().enumerate() {
+            *dst = real_coeff(spectrum[harmonic(coeff)], coeff);
+        }
+    }
+
+    fn synthesize(&mut self, coeffs: &[f64], samples: &mut [f64]) {
+        let num_samples = self.buf.len();
+        self.buf.fill(Complex64::default());
+        self.buf[0] = coeffs[0].into();
+        for h in 1..=self.num_harmonics {
+            let amplitude = Complex64::new(coeffs[2 * h - 1], -coeffs[2 * h]) / 2.0;
+            self.buf[h] = amplitude;
+            self.buf[num_samples - h] = amplitude.conj();
+        }
+        fft(&mut self.buf, true);
+        for (dst, val) in zip(samples, &self.buf) {
+            *dst = val.re;
+        }
+    }
+
+    /// The samples of all signals (`[signal][sample]`) from their Fourier coefficients
+    /// (`[signal][coeff]`).
+    fn synthesize_all(&mut self, coeffs: &[f64]) -> Vec<f64> {
+        let num_coeffs = self.num_coeffs();
+        let num_samples = self.num_samples();
+        let mut res = vec![0f64; coeffs.len() / num_coeffs * num_samples];
+        for (coeffs, samples) in
+            zip(coeffs.chunks_exact(num_coeffs), res.chunks_exact_mut(num_samples))
+        {
+            self.synthesize(coeffs, samples)
+        }
+        res
+    }
+
+    /// The Fourier coefficients of `resist + d/dt react` for all signals from their samples.
+    fn analyze_all(&mut self, omega: f64, resist: &[f64], react: &[f64], dst: &mut [f64]) {
+        let num_coeffs = self.num_coeffs();
+        let num_samples = self.num_samples();
+        let mut charge = vec![0f64; num_coeffs];
+        for ((resist, react), dst) in resist
+            .chunks_exact(num_samples)
+            .zip(react.chunks_exact(num_samples))
+            .zip(dst.chunks_exact_mut(num_coeffs))
+        {
+            self.analyze(resist, dst);
+            self.analyze(react, &mut charge);
+            add_derivative(omega, &charge, dst);
+        }
+    }
+}
+
+/// In place radix-2 FFT `X_n = sum_k x_k * exp(-+ j*2*pi*n*k/N)` (the sign is positive for the
+/// `inverse` transform). The result is not normalized.
+fn fft(data: &mut [Complex64], inverse: bool) {
+    let len = data.len();
+    debug_assert!(len.is_power_of_two());
+
+    let mut j = 0;
+    for i in 1..len {
+        let mut bit = len >> 1;
+        while j & bit != 0 {
+            j ^= bit;
+            bit >>= 1;
+        }
+        j |= bit;
+        if i < j {
+            data.swap(i, j);
+        }
+    }
+
+    let sign = if inverse { 1.0 } else { -1.0 };
+    let mut size = 2;
+    while size <= len {
+        let half = size / 2;
+        for start in (0..len).step_by(size) {
+            for k in 0..half {
+                let twiddle = Complex64::from_polar(1.0, sign * 2.0 * PI * k as f64 / size as f64);
+                let upper = data[start + k];
+                let lower = data[start + k + half] * twiddle;
+                data[start + k] = upper + lower;
+                data[start + k + half] = upper - lower;
+            }
+        }
+        size *= 2;
+    }
+}
+
+/// The harmonic of the real Fourier coefficient `coeff`.
+fn harmonic(coeff: usize) -> usize {
+    (coeff + 1) / 2
+}
+
+/// The real Fourier coefficient `coeff` of a real signal whose harmonic `harmonic(coeff)` has
+/// the complex amplitude `amplitude`.
+fn real_coeff(amplitude: Complex64, coeff: usize) -> f64 {
+    if coeff == 0 {
+        amplitude.re
+    } else if coeff % 2 == 1 {
+        2.0 * amplitude.re
+    } else {
+        -2.0 * amplitude.im
+    }
+}
+
+/// The real Fourier coefficients of a signal from its phasors `X_h` where
+/// `x(t) = Re(sum_h X_h * exp(j*h*w*t))`.
+fn from_phasors(phasors: &[Complex64], coeffs: &mut [f64]) {
+    coeffs[0] = phasors[0].re;
+    for (h, phasor) in phasors.iter().enumerate().skip(1) {
+        coeffs[2 * h - 1] = phasor.re;
+        coeffs[2 * h] = -phasor.im;
+    }
+}
+
+fn to_phasors(coeffs: &[f64]) -> Vec<Complex64> {
+    let mut res = vec![Complex64::new(coeffs[0], 0.0)];
+    res.extend(coeffs[1..].chunks_exact(2).map(|coeffs| Complex64::new(coeffs[0], -coeffs[1])));
+    res
+}
+
+/// Adds the real Fourier coefficients of the time derivative of the signal with the
+/// coefficients `coeffs` to `dst`.
+fn add_derivative(omega: f64, coeffs: &[f64], dst: &mut [f64]) {
+    for h in 1..=coeffs.len() / 2 {
+        let omega = h as f64 * omega;
+        dst[2 * h - 1] += omega * coeffs[2 * h];
+        dst[2 * h] -= omega * coeffs[2 * h - 1];
+    }
+}
+
+/// The derivative of the complex amplitude of harmonic `n` of `g(t) * x(t)` by the real
+/// Fourier coefficient `coeff` of `x(t)`, where `spectrum` is the spectrum of `g(t)`.
+fn mixing(spectrum: &[Complex64], n: usize, coeff: usize) -> Complex64 {
+    let len = spectrum.len() as isize;
+    let amplitude = |i: isize| spectrum[i.rem_euclid(len) as usize];
+    let n = n as isize;
+    if coeff == 0 {
+        return amplitude(n);
+    }
+    let h = harmonic(coeff) as isize;
+    let (lower, upper) = (amplitude(n - h), amplitude(n + h));
+    if coeff % 2 == 1 {
+        (lower + upper) / 2.0
+    } else {
+        Complex64::i() * (upper - lower) / 2.0
+    }
+}
+
+/// The derivative of the real Fourier coefficient `row` of the residual by the real Fourier
+/// coefficient `col` of the unknown, where `resist` and `react` are the spectra of the
+/// resistive and reactive Jacobian entry.
+fn block_entry(
+    resist: &[Complex64],
+    react: &[Complex64],
+    omega: f64,
+    row: usize,
+    col: usize,
+) -> f64 {
+    let n = harmonic(row);
+    let mut res = real_coeff(mixing(resist, n, col), row);
+    if row != 0 {
+        // the time derivative of the charges couples the cosine and sine coefficients
+        let omega = n as f64 * omega;
+        let charge = mixing(react, n, col);
+        if row % 2 == 1 {
+            res += omega * real_coeff(charge, row + 1);
+        } else {
+            res -= omega * real_coeff(charge, row - 1);
+        }
+    }
+    res
+}
+
+fn dot(lhs: &[f64], rhs: &[f64]) -> f64 {
+    zip(lhs, rhs).map(|(lhs, rhs)| lhs * rhs).sum()
+}
+
+fn norm(vec: &[f64]) -> f64 {
+    dot(vec, vec).sqrt()
+}
+
+/// `dst += factor * src`
+fn axpy(factor: f64, src: &[f64], dst: &mut [f64]) {
+    for (dst, src) in zip(dst, src) {
+        *dst += factor * src;
+    }
+}
+
+/// The state of the harmonic balance analysis. All vectors store the unknowns (without
+/// ground) in the order of the circuit matrix, each unknown occupies a contiguous range of
+/// Fourier coefficients or samples.
+struct HarmonicBalance {
+    fourier: Fourier,
+    freq: f64,
+    /// The entries of the circuit matrix as `(equation, unknown)` pairs
+    entries: Vec<(Node, Node)>,
+    /// The Jacobian of the harmonic balance equations (or the preconditioner)
+    matrix: RealMatrix,
+    coeffs: Vec<f64>,
+    /// The residual of the harmonic balance equations (in the frequency domain)
+    residual: Vec<f64>,
+    /// The resistive and reactive Jacobian entries at each collocation point
+    /// (`[entry][sample]`)
+    resist_jacobian: Vec<f64>,
+    react_jacobian: Vec<f64>,
+}
+
+impl HarmonicBalance {
+    fn new(
+        entries: impl Iterator<Item = (Node, Node)>,
+        num_unknowns: usize,
+        num_harmonics: usize,
+        freq: f64,
+        solver: HbSolver,
+    ) -> HarmonicBalance {
+        let fourier = Fourier::new(num_harmonics);
+        let num_coeffs = fourier.num_coeffs();
+        let num_samples = fourier.num_samples();
+
+        let mut entries: Vec<_> = entries
+            .filter(|&(equation, unknown)| equation != Node::GROUND && unknown != Node::GROUND)
+            .collect();
+        entries.sort_unstable_by_key(|&(equation, unknown)| {
+            (equation.matrix_idx(), unknown.matrix_idx())
+        });
+        entries.dedup();
+
+        let averaged = matches!(solver, HbSolver::Krylov { .. });
+        let mut builder = KluMatrixBuilder::new((num_unknowns * num_coeffs) as i32);
+        for &(equation, unknown) in &entries {
+            for row in 0..num_coeffs {
+                for col in 0..num_coeffs {
+                    if !averaged || harmonic(row) == harmonic(col) {
+                        builder.add_entry(
+                            Self::coeff_idx(unknown, col, num_coeffs),
+                            Self::coeff_idx(equation, row, num_coeffs),
+                        );
+                    }
+                }
+            }
+        }
+        for i in 0..(num_unknowns * num_coeffs) as i32 {
+            builder.add_entry(i, i);
+        }
+        let matrix = RealMatrix::new(builder.finish(KluSettings::new())).expect("non empty matrix");
+
+        HarmonicBalance {
+            fourier,
+            freq,
+            matrix,
+            coeffs: vec![0f64; num_unknowns * num_coeffs],
+            residual: vec![0f64; num_unknowns * num_coeffs],
+            resist_jacobian: vec![0f64; entries.len() * num_samples],
+            react_jacobian: vec![0f64; entries.len() * num_samples],
+            entries,
+        }
+    }
+
+    fn coeff_idx(node: Node, coeff: usize, num_coeffs: usize) -> i32 {
+        node.matrix_idx() * num_coeffs as i32 + coeff as i32
+    }
+
+    fn omega(&self) -> f64 {
+        2.0 * PI * self.freq
+    }
+
+    /// The time point of the collocation point `sample`.
+    fn abstime(&self, sample: usize) -> f64 {
+        sample as f64 / (self.freq * self.fourier.num_samples() as f64)
+    }
+
+    /// Loads the Jacobian into the matrix. If `averaged` is set only the time averaged
+    /// Jacobian entries are used so that the harmonics are not coupled (preconditioner).
+    fn load_matrix(&mut self, averaged: bool) {
+        let num_coeffs = self.fourier.num_coeffs();
+        let num_samples = self.fourier.num_samples();
+        let omega = self.omega();
+        for (i, &(equation, unknown)) in self.entries.iter().enumerate() {
+            let samples = i * num_samples..(i + 1) * num_samples;
+            let mut resist = self.fourier.spectrum(&self.resist_jacobian[samples.clone()]).to_vec();
+            let mut react = self.fourier.spectrum(&self.react_jacobian[samples]).to_vec();
+            if averaged {
+                resist[1..].fill(Complex64::default());
+                react[1..].fill(Complex64::default());
+            }
+
+            for row in 0..num_coeffs {
+                for col in 0..num_coeffs {
+                    if averaged && harmonic(row) != harmonic(col) {
+                        continue;
+                    }
+                    let val = block_entry(&resist, &react, omega, row, col);
+                    let entry = &self.matrix[(
+                        Self::coeff_idx(unknown, col, num_coeffs),
+                        Self::coeff_idx(equation, row, num_coeffs),
+                    )];
+                    entry.set(entry.get() + val);
+                }
+            }
+        }
+    }
+
+    /// Multiplies the Jacobian (at the last evaluation) with `src`.
+    fn jacobian_product(&mut self, src: &[f64], dst: &mut [f64]) {
+        let num_samples = self.fourier.num_samples();
+        let src = self.fourier.synthesize_all(src);
+        let mut resist = vec![0f64; src.len()];
+        let mut react = vec![0f64; src.len()];
+        for (i, &(equation, unknown)) in self.entries.iter().enumerate() {
+            let entry = i * num_samples;
+            let equation = equation.matrix_idx() as usize * num_samples;
+            let unknown = unknown.matrix_idx() as usize * num_samples;
+            for sample in 0..num_samples {
+                let val = src[unknown + sample];
+                resist[equation + sample] += self.resist_jacobian[entry + sample] * val;
+                react[equation + sample] += self.react_jacobian[entry + sample] * val;
+            }
+        }
+        let omega = self.omega();
+        self.fourier.analyze_all(omega, &resist, &react, dst)
+    }
+
+    /// Solves the Jacobian with GMRES (right preconditioned with the factorized matrix).
+    /// `rhs` is overwritten with the solution. Returns whether the required residual
+    /// reduction was reached.
+    fn gmres(&mut self, rhs: &mut [f64], rtol: f64, maxiters: u32) -> bool {
+        let len = rhs.len();
+        let tol = rtol * norm(rhs);
+        let target = rhs.to_vec();
+        let mut residual = target.clone();
+        let solution = rhs;
+        solution.fill(0.0);
+
+        let mut precond = vec![0f64; len];
+        let mut product = vec![0f64; len];
+        let mut iterations = 0;
+        loop {
+            let beta = norm(&residual);
+            if beta <= tol {
+                return true;
+            }
+            if iterations >= maxiters {
+                return false;
+            }
+
+            let mut basis = vec![residual.iter().map(|val| val / beta).collect::<Vec<_>>()];
+            // the columns of the (rotated) Hessenberg matrix
+            let mut hessenberg: Vec<Vec<f64>> = Vec::new();
+            let mut rotations: Vec<(f64, f64)> = Vec::new();
+            let mut projected = vec![beta];
+            while basis.len() <= GMRES_RESTART && iterations < maxiters {
+                iterations += 1;
+                precond.copy_from_slice(&basis[basis.len() - 1]);
+                self.matrix.solve_linear_system(&mut precond);
+                self.jacobian_product(&precond, &mut product);
+
+                // modified Gram-Schmidt
+                let mut column = Vec::with_capacity(basis.len() + 1);
+                for vec in &basis {
+                    let val = dot(&product, vec);
+                    axpy(-val, vec, &mut product);
+                    column.push(val);
+                }
+                let product_norm = norm(&product);
+                column.push(product_norm);
+
+                for (j, &(cos, sin)) in rotations.iter().enumerate() {
+                    let (upper, lower) = (column[j], column[j + 1]);
+                    column[j] = cos * upper + sin * lower;
+                    column[j + 1] = cos * lower - sin * upper;
+                }
+                let j = rotations.len();
+                let diag = column[j].hypot(column[j + 1]);
+                let (cos, sin) =
+                    if diag == 0.0 { (1.0, 0.0) } else { (column[j] / diag, column[j + 1] / diag) };
+                column[j] = diag;
+                column.pop();
+                rotations.push((cos, sin));
+                projected.push(-sin * projected[j]);
+                projected[j] *= cos;
+                hessenberg.push(column);
+
+                if projected[j + 1].abs() <= tol || product_norm == 0.0 {
+                    break;
+                }
+                basis.push(product.iter().map(|val| val / product_norm).collect());
+            }
+
+            // back substitution of the upper triangular system
+            let mut weights = projected[..hessenberg.len()].to_vec();
+            for (i, column) in hessenberg.iter().enumerate().rev() {
+                weights[i] /= column[i];
+                let weight = weights[i];
+                for (dst, val) in zip(&mut weights[..i], &column[..i]) {
+                    *dst -= val * weight;
+                }
+            }
+
+            precond.fill(0.0);
+            for (vec, &weight) in zip(&basis, &weights) {
+                axpy(weight, vec, &mut precond);
+            }
+            self.matrix.solve_linear_system(&mut precond);
+            axpy(1.0, &precond, solution);
+
+            // restart with the true residual
+            self.jacobian_product(solution, &mut product);
+            for ((dst, &target), &product) in residual.iter_mut().zip(&target).zip(&product) {
+                *dst = target - product;
+            }
+        }
+    }
+}
+
+impl Simulation<'_> {
+    /// Sets the fundamental frequency of the harmonic balance analysis. The periodic
+    /// independent sources should have a frequency that is a multiple of `freq`.
+    pub fn set_hb_freq(&mut self, freq: f64) {
+        if (self.hb_freq - freq).abs() > f64::EPSILON {
+            self.state.remove(SimulationState::AT_HB)
+        }
+        self.hb_freq = freq;
+    }
+
+    /// Solves the periodic steady state of the circuit with a single tone harmonic balance
+    /// analysis with [`SimConfig::hb_harmonics`](crate::simulation::SimConfig::hb_harmonics)
+    /// harmonics of the frequency set with [`set_hb_freq`](Simulation::set_hb_freq).
+    /// Returns the phasors `X_h` of every unknown such that
+    /// `x(t) = Re(sum_h X_h * exp(j*h*w*t))` (`X_0` is the DC component).
+    pub fn hb(&mut self) -> Result<&TiSlice<Node, Vec<Complex64>>> {
+        if self.state.contains(SimulationState::AT_HB) {
+            return Ok(&self.hb_solution);
+        }
+
+        let freq = self.hb_freq;
+        if !(freq.is_finite() && freq > 0.0) {
+            bail!("the harmonic balance frequency must be positive but was {freq}")
+        }
+        let num_harmonics = self.config.hb_harmonics as usize;
+        if num_harmonics == 0 {
+            bail!("harmonic balance requires at least one harmonic")
+        }
+
+        // the DC solution is the initial guess
+        self.solve_op(OperatingPointAnalysis::HBIc)?;
+        let mut hb = HarmonicBalance::new(
+            self.matrix_builder.instance_entries.iter().flatten().copied(),
+            self.nodes.len() - 1,
+            num_harmonics,
+            freq,
+            self.config.hb_solver,
+        );
+        let num_coeffs = hb.fourier.num_coeffs();
+        for (coeffs, &val) in zip(hb.coeffs.chunks_exact_mut(num_coeffs), &self.solution.raw[1..]) {
+            coeffs[0] = val;
+        }
+
+        // the instances now store the large signal state
+        self.state.remove(SimulationState::HAS_AC_EVAL);
+        self.hb_newton(&mut hb)?;
+
+        let ground = vec![Complex64::default(); num_harmonics + 1];
+        self.hb_solution = std::iter::once(ground)
+            .chain(hb.coeffs.chunks_exact(num_coeffs).map(to_phasors))
+            .collect();
+        self.state.insert(SimulationState::AT_HB);
+        Ok(&self.hb_solution)
+    }
+
+    fn hb_newton(&mut self, hb: &mut HarmonicBalance) -> Result<()> {
+        let num_coeffs = hb.fourier.num_coeffs();
+        let maxiters = self.config.maxiters;
+        let mut delta = vec![0f64; hb.coeffs.len()];
+        for i in 0..maxiters {
+            self.hb_eval(hb, i + 1)?;
+            delta.copy_from_slice(&hb.residual);
+
+            let singular = match self.config.hb_solver {
+                HbSolver::Direct => {
+                    hb.load_matrix(false);
+                    let singular = hb.matrix.lu_factorize(None);
+                    if !singular {
+                        hb.matrix.solve_linear_system(&mut delta);
+                    }
+                    singular
+                }
+                HbSolver::Krylov { rtol, maxiters } => {
+                    hb.load_matrix(true);
+                    let singular = hb.matrix.lu_factorize(None);
+                    if !singular && !hb.gmres(&mut delta, rtol, maxiters) {
+                        info!("GMRES did not converge within {maxiters} iterations");
+                    }
+                    singular
+                }
+            };
+            hb.matrix.write_zero();
+            if singular {
+                bail!("harmonic balance matrix is singular (after {} iterations)", i + 1)
+            }
+
+            let mut nonconverged = Vec::new();
+            for ((coeffs, delta), node_info) in hb
+                .coeffs
+                .chunks_exact_mut(num_coeffs)
+                .zip(delta.chunks_exact(num_coeffs))
+                .zip(&self.nodes.raw[1..])
+            {
+                let mut converged = true;
+                let max_step = self.config.max_step(node_info);
+                for (dst, &delta) in zip(&mut *coeffs, delta) {
+                    let mut delta = delta;
+                    if delta.abs() > max_step {
+                        delta = max_step.copysign(delta);
+                        converged = false;
+                    }
+                    *dst -= delta;
+                }
+                // all harmonics are compared to the largest one
+                let scale = coeffs.iter().fold(0f64, |scale, coeff| scale.max(coeff.abs()));
+                let tol = node_info.atol.max(scale * self.config.rtol);
+                if !converged || delta.iter().any(|delta| delta.is_nan() || delta.abs() > tol) {
+                    nonconverged.push(node_info.name.as_str());
+                }
+            }
+
+            if nonconverged.is_empty() && i > 0 {
+                return Ok(());
+            }
+
+            if i + 1 == maxiters {
+                nonconverged.truncate(10);
+                bail!(
+                    "harmonic balance failed to converge after {maxiters} iterations\n\
+                     unknowns that did not converge: {}",
+                    nonconverged.join(", ")
+                )
+            }
+        }
+
+        bail!("harmonic balance failed to converge after {maxiters} iterations")
+    }
+
+    /// Evaluates all instances at the collocation points. Calculates the residual of the
+    /// harmonic balance equations and stores the Jacobian entries at each collocation point.
+    fn hb_eval(&mut self, hb: &mut HarmonicBalance, iteration: u32) -> Result<()> {
+        let matrix =
+            self.matrix.as_mut().context("simulation must be setup before hb() is called")?;
+        let num_samples = hb.fourier.num_samples();
+        let samples = hb.fourier.synthesize_all(&hb.coeffs);
+        let mut resist = vec![0f64; samples.len()];
+        let mut react = vec![0f64; samples.len()];
+        let mut solution: TiVec<Node, f64> = vec![0f64; self.nodes.len()].into();
+
+        self.sim_params.set_iteration(iteration, false);
+        for sample in 0..num_samples {
+            for (dst, unknown_samples) in
+                zip(&mut solution.raw[1..], samples.chunks_exact(num_samples))
+            {
+                *dst = unknown_samples[sample];
+            }
+            self.residual_resist.raw.fill(0.0);
+            self.residual_react.raw.fill(0.0);
+
+            let sim_info = SimInfo {
+                abstime: hb.abstime(sample),
+                prev_solve: &solution,
+                sim_params: &self.sim_params,
+                flags: EvalFlags::HB,
+            };
+            for inst in &mut *self.instance_data {
+                inst.eval(sim_info)?;
+                // this is save because we call populate_matrix_ptrs during Simulation construction
+                unsafe {
+                    inst.load_matrix_resist();
+                    inst.load_matrix_react(1.0);
+                }
+                inst.load_residual_resist(&solution, &mut self.residual_resist);
+                inst.load_residual_react(&solution, &mut self.residual_react);
+            }
+
+            for (unknown, (&resist_val, &react_val)) in
+                zip(&self.residual_resist.raw[1..], &self.residual_react.raw[1..]).enumerate()
+            {
+                resist[unknown * num_samples + sample] = resist_val;
+                react[unknown * num_samples + sample] = react_val;
+            }
+
+            for (i, &(equation, unknown)) in hb.entries.iter().enumerate() {
+                let idx = (unknown.matrix_idx(), equation.matrix_idx());
+                hb.resist_jacobian[i * num_samples + sample] = matrix.nonlinear_matrix[idx].get();
+                hb.react_jacobian[i * num_samples + sample] = matrix.ac_matrix[idx].get().im;
+            }
+            matrix.nonlinear_matrix.write_zero();
+            matrix.ac_matrix.write_zero();
+        }
+
+        let omega = hb.omega();
+        hb.fourier.analyze_all(omega, &resist, &react, &mut hb.residual);
+        Ok(())
+    }
+
+    /// The phasors of the lead currents of `inst` (see [`Simulation::hb`]).
+    pub fn hb_lead_current(&mut self, inst: InstanceId) -> Result<Vec<Vec<Complex64>>> {
+        self.hb()?;
+
+        let num_harmonics = self.config.hb_harmonics as usize;
+        let mut fourier = Fourier::new(num_harmonics);
+        let num_coeffs = fourier.num_coeffs();
+        let num_samples = fourier.num_samples();
+        let omega = 2.0 * PI * self.hb_freq;
+
+        let mut coeffs = vec![0f64; num_coeffs];
+        let mut samples = vec![0f64; self.nodes.len() * num_samples];
+        for (phasors, samples) in zip(&self.hb_solution, samples.chunks_exact_mut(num_samples)) {
+            from_phasors(phasors, &mut coeffs);
+            fourier.synthesize(&coeffs, samples);
+        }
+
+        let num_terminals = self.circ[inst].connections.len();
+        let mut resist = vec![0f64; num_terminals * num_samples];
+        let mut react = vec![0f64; num_terminals * num_samples];
+        let mut dst = vec![0f64; num_terminals];
+        let mut solution: TiVec<Node, f64> = vec![0f64; self.nodes.len()].into();
+        for sample in 0..num_samples {
+            for (dst, node_samples) in zip(&mut solution, samples.chunks_exact(num_samples)) {
+                *dst = node_samples[sample];
+            }
+            let sim_info = SimInfo {
+                abstime: sample as f64 / (self.hb_freq * num_samples as f64),
+                prev_solve: &solution,
+                sim_params: &self.sim_params,
+                flags: EvalFlags::HB,
+            };
+            let inst = &mut self.instance_data[inst];
+            inst.eval(sim_info)?;
+            inst.load_lead_current_resist(&solution, &mut dst);
+            for (terminal, &val) in dst.iter().enumerate() {
+                resist[terminal * num_samples + sample] = val;
+            }
+            inst.load_lead_current_react(&solution, &mut dst);
+            for (terminal, &val) in dst.iter().enumerate() {
+                react[terminal * num_samples + sample] = val;
+            }
+        }
+        self.state.remove(SimulationState::HAS_AC_EVAL);
+
+        let mut coeffs = vec![0f64; num_terminals * num_coeffs];
+        fourier.analyze_all(omega, &resist, &react, &mut coeffs);
+        Ok(coeffs.chunks_exact(num_coeffs).map(to_phasors).collect())
+    }
+}
@@ -0,0 +1,97 @@
+use std::f64::consts::PI;
+
+use num_complex::Complex64;
+
+use super::{fft, from_phasors, to_phasors, Fourier};
+
+fn assert_close(val: f64, ref_val: f64) {
+    assert!((val - ref_val).abs() < 1e-12, "{val} != {ref_val}");
+}
+
+fn test_signal(len: usize) -> Vec<Complex64> {
+    (0..len).map(|i| Complex64::new((i as f64 * 0.7).sin() + 0.3, (i * i) as f64 * 0.01)).collect()
+}
+
+#[test]
+fn fft_dft() {
+    let data = test_signal(16);
+    let mut res = data.clone();
+    fft(&mut res, false);
+    for (n, &val) in res.iter().enumerate() {
+        let ref_val: Complex64 = data
+            .iter()
+            .enumerate()
+            .map(|(k, &x)| x * Complex64::from_polar(1.0, -2.0 * PI * (n * k) as f64 / 16.0))
+            .sum();
+        assert_close(val.re, ref_val.re);
+        assert_close(val.im, ref_val.im);
+    }
+}
+
+#[test]
+fn fft_round_trip() {
+    for len in [1, 2, 8, 64] {
+        let data = test_signal(len);
+        let mut res = data.clone();
+        fft(&mut res, false);
+        fft(&mut res, true);
+        for (&val, &ref_val) in res.iter().zip(&data) {
+            assert_close(val.re / len as f64, ref_val.re);
+            assert_close(val.im / len as f64, ref_val.im);
+        }
+    }
+}
+
+#[test]
+fn fourier_synthesize() {
+    let coeffs = [0.5, 1.0, -2.0, 0.25, 0.0, 0.0, 3.0];
+    let mut fourier = Fourier::new(3);
+    let num_samples = fourier.num_samples();
+    assert!(num_samples >= 2 * fourier.num_coeffs());
+
+    let mut samples = vec![0f64; num_samples];
+    fourier.synthesize(&coeffs, &mut samples);
+    for (k, &val) in samples.iter().enumerate() {
+        let phase = 2.0 * PI * k as f64 / num_samples as f64;
+        let ref_val = coeffs[0]
+            + (1..=3)
+                .map(|h| {
+                    let phase = h as f64 * phase;
+                    coeffs[2 * h - 1] * phase.cos() + coeffs[2 * h] * phase.sin()
+                })
+                .sum::<f64>();
+        assert_close(val, ref_val);
+    }
+}
+
+#[test]
+fn fourier_round_trip() {
+    let coeffs = [-1.0, 0.3, 0.7, -0.2, 1.5, 0.01, -0.04];
+    let mut fourier = Fourier::new(3);
+    let mut samples = vec![0f64; fourier.num_samples()];
+    fourier.synthesize(&coeffs, &mut samples);
+    let mut res = [0f64; 7];
+    fourier.analyze(&samples, &mut res);
+    for (&val, &ref_val) in res.iter().zip(&coeffs) {
+        assert_close(val, ref_val);
+    }
+
+    // the phasors describe the same signal
+    let phasors = to_phasors(&coeffs);
+    assert_eq!(phasors[1], Complex64::new(0.3, -0.7));
+    from_phasors(&phasors, &mut res);
+    assert_eq!(res, coeffs);
+}
+
+#[test]
+fn fourier_derivative() {
+    // x(t) = sin(2*w*t) => x(t) + d/dt x(t) = sin(2*w*t) + 2*w*cos(2*w*t)
+    let omega = 3.0;
+    let mut fourier = Fourier::new(2);
+    let samples = fourier.synthesize_all(&[0.0, 0.0, 0.0, 0.0, 1.0]);
+    let mut res = [0f64; 5];
+    fourier.analyze_all(omega, &samples, &samples, &mut res);
+    for (&val, ref_val) in res.iter().zip([0.0, 0.0, 0.0, 2.0 * omega, 1.0]) {
+        assert_close(val, ref_val);
+    }
+}
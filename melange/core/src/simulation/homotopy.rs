@@ -0,0 +1,170 @@
+use std::fmt;
+
+use anyhow::Result;
+use log::info;
+
+use crate::simulation::flags::OperatingPointAnalysis;
+use crate::simulation::newton::{NewtonStatus, Shunt};
+use crate::simulation::Simulation;
+
+/// Convergence aids that are attempted (in the order specified in
+/// [`SimConfig::homotopy`](crate::simulation::SimConfig::homotopy)) when the plain Newton
+/// iteration fails to find the operating point.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Homotopy {
+    /// Connects a conductance from every unknown to ground that is gradually removed
+    GminStepping,
+    /// Ramps all independent sources up from zero
+    SourceStepping,
+    /// Connects every unknown with a conductance to its value at the previous pseudo time
+    /// step, the conductance (`C/dt`) is gradually removed
+    PseudoTransient,
+}
+
+impl fmt::Display for Homotopy {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Homotopy::GminStepping => f.write_str("gmin stepping"),
+            Homotopy::SourceStepping => f.write_str("source stepping"),
+            Homotopy::PseudoTransient => f.write_str("pseudo transient"),
+        }
+    }
+}
+
+const GMIN_START: f64 = 1e-2;
+const GMIN_END: f64 = 1e-12;
+const MIN_GMIN_FACTOR: f64 = 1.01;
+
+const MIN_SOURCE_STEP: f64 = 1e-4;
+
+const PSEUDO_TRAN_START: f64 = 1e-1;
+const PSEUDO_TRAN_END: f64 = 1e-12;
+const PSEUDO_TRAN_MAX: f64 = 1e3;
+
+impl Simulation<'_> {
+    /// Runs `homotopy` starting at the current solution. The last step is always a plain
+    /// Newton iteration of the original circuit.
+    pub(super) fn homotopy(
+        &mut self,
+        homotopy: Homotopy,
+        analysis: OperatingPointAnalysis,
+    ) -> Result<NewtonStatus> {
+        let res = match homotopy {
+            Homotopy::GminStepping => self.gmin_stepping(analysis),
+            Homotopy::SourceStepping => self.source_stepping(analysis),
+            Homotopy::PseudoTransient => self.pseudo_transient(analysis),
+        };
+        self.sim_params.set_source_scale(1.0);
+        res
+    }
+
+    fn gmin_stepping(&mut self, analysis: OperatingPointAnalysis) -> Result<NewtonStatus> {
+        let mut gmin = GMIN_START;
+        let mut factor = 10f64;
+        let mut last_gmin = None;
+        let mut last_solution = self.solution.clone();
+        let mut init_lim = true;
+
+        for _ in 0..self.config.max_homotopy_steps {
+            let shunt = Shunt { conductance: gmin, anchor: None };
+            match self.newton(analysis, init_lim, shunt)? {
+                NewtonStatus::Converged => {
+                    info!("gmin stepping: converged with gmin = {gmin:e}");
+                    init_lim = false;
+                    if gmin <= GMIN_END {
+                        return self.newton(analysis, init_lim, Shunt::NONE);
+                    }
+                    last_solution.copy_from_slice(&self.solution);
+                    last_gmin = Some(gmin);
+                    factor = (factor * 2.0).min(10.0);
+                    gmin /= factor;
+                }
+                NewtonStatus::Failed(failure) => {
+                    let last_gmin = match last_gmin {
+                        Some(last_gmin) => last_gmin,
+                        None => return Ok(NewtonStatus::Failed(failure)),
+                    };
+                    self.solution.copy_from_slice(&last_solution);
+                    factor = factor.sqrt();
+                    if factor < MIN_GMIN_FACTOR {
+                        return Ok(NewtonStatus::Failed(failure));
+                    }
+                    gmin = last_gmin / factor;
+                }
+            }
+        }
+
+        self.newton(analysis, init_lim, Shunt::NONE)
+    }
+
+    fn source_stepping(&mut self, analysis: OperatingPointAnalysis) -> Result<NewtonStatus> {
+        let mut scale = 0f64;
+        let mut step = 0.1;
+        let mut last_scale = None;
+        let mut last_solution = self.solution.clone();
+        let mut init_lim = true;
+
+        for _ in 0..self.config.max_homotopy_steps {
+            self.sim_params.set_source_scale(scale);
+            match self.newton(analysis, init_lim, Shunt::NONE)? {
+                NewtonStatus::Converged => {
+                    info!("source stepping: converged with sources scaled by {scale}");
+                    if scale >= 1.0 {
+                        return Ok(NewtonStatus::Converged);
+                    }
+                    init_lim = false;
+                    last_solution.copy_from_slice(&self.solution);
+                    last_scale = Some(scale);
+                    step *= 2.0;
+                    scale = (scale + step).min(1.0);
+                }
+                NewtonStatus::Failed(failure) => {
+                    let last_scale = match last_scale {
+                        Some(last_scale) => last_scale,
+                        None => return Ok(NewtonStatus::Failed(failure)),
+                    };
+                    self.solution.copy_from_slice(&last_solution);
+                    step /= 4.0;
+                    if step < MIN_SOURCE_STEP {
+                        return Ok(NewtonStatus::Failed(failure));
+                    }
+                    scale = last_scale + step;
+                }
+            }
+        }
+
+        self.sim_params.set_source_scale(1.0);
+        self.newton(analysis, init_lim, Shunt::NONE)
+    }
+
+    fn pseudo_transient(&mut self, analysis: OperatingPointAnalysis) -> Result<NewtonStatus> {
+        // conductance C/dt that connects each unknown to its value at the last time step
+        let mut conductance = PSEUDO_TRAN_START;
+        let mut last_solution = self.solution.clone();
+        let mut init_lim = true;
+
+        for _ in 0..self.config.max_homotopy_steps {
+            let shunt = Shunt { conductance, anchor: Some(&*last_solution) };
+            match self.newton(analysis, init_lim, shunt)? {
+                NewtonStatus::Converged => {
+                    info!("pseudo transient: converged with C/dt = {conductance:e}");
+                    init_lim = false;
+                    if conductance <= PSEUDO_TRAN_END {
+                        return self.newton(analysis, init_lim, Shunt::NONE);
+                    }
+                    last_solution.copy_from_slice(&self.solution);
+                    conductance /= 4.0;
+                }
+                NewtonStatus::Failed(failure) => {
+                    self.solution.copy_from_slice(&last_solution);
+                    conductance *= 8.0;
+                    if conductance > PSEUDO_TRAN_MAX {
+                        return Ok(NewtonStatus::Failed(failure));
+                    }
+                }
+            }
+        }
+
+        self.newton(analysis, init_lim, Shunt::NONE)
+    }
+}
@@ -89,6 +89,13 @@ impl MatrixBuilder {
         }
     }
 
+    /// Ensures that all diagonal entries of a matrix with `num_unknowns` unknowns exist.
+    pub fn ensure_diagonal(&mut self, num_unknowns: usize) {
+        for i in 0..num_unknowns as i32 {
+            self.inner.add_entry(i, i);
+        }
+    }
+
     pub fn reset(&mut self, circ: &Circuit) {
         self.inner.reset(circ.num_unknowns() as i32);
         for instance_entries in &mut *self.instance_entries {
@@ -0,0 +1,303 @@
+use std::fmt;
+use std::mem::replace;
+
+use anyhow::{Context, Result};
+use cli_table::print_stdout;
+use stdx::iter::zip;
+use typed_index_collections::TiSlice;
+
+use crate::circuit::{InstanceId, Node};
+use crate::simulation::flags::{EvalFlags, OperatingPointAnalysis};
+use crate::simulation::homotopy::Homotopy;
+use crate::simulation::matrix::RealMatrix;
+use crate::simulation::{SimInfo, Simulation};
+use crate::utils::PrettyPrint;
+
+/// A conductance from every unknown to `anchor` (or ground) that is added to the circuit
+/// as a convergence aid (gmin stepping and pseudo transient continuation).
+#[derive(Clone, Copy)]
+pub(super) struct Shunt<'a> {
+    pub conductance: f64,
+    pub anchor: Option<&'a TiSlice<Node, f64>>,
+}
+
+impl Shunt<'_> {
+    pub const NONE: Shunt<'static> = Shunt { conductance: 0.0, anchor: None };
+
+    fn load(
+        &self,
+        solution: &TiSlice<Node, f64>,
+        matrix: &RealMatrix,
+        rhs: &mut TiSlice<Node, f64>,
+    ) {
+        if self.conductance == 0.0 {
+            return;
+        }
+
+        for (node, &val) in solution.iter_enumerated().skip(1) {
+            let anchor = self.anchor.map_or(0.0, |anchor| anchor[node]);
+            rhs[node] += self.conductance * (val - anchor);
+            let entry = &matrix[(node.matrix_idx(), node.matrix_idx())];
+            entry.set(entry.get() + self.conductance);
+        }
+    }
+}
+
+pub(super) enum NewtonStatus {
+    Converged,
+    Failed(ConvergenceFailure),
+}
+
+/// An unknown that did not converge during the last Newton iteration.
+#[derive(Debug, Clone)]
+pub struct NonConvergedUnknown {
+    pub node: Node,
+    pub name: String,
+    pub value: f64,
+    /// The change of the unknown during the last iteration
+    pub delta: f64,
+    pub tolerance: f64,
+    pub residual: f64,
+    pub units: &'static str,
+    pub residual_units: &'static str,
+}
+
+/// Returned (as the source of the error) when the operating point could not be found.
+#[derive(Debug, Clone)]
+pub struct ConvergenceFailure {
+    pub iterations: u32,
+    /// The matrix was singular during the last iteration
+    pub singular: bool,
+    /// The convergence aids that were attempted after plain Newton failed
+    pub homotopy: Vec<Homotopy>,
+    /// Sorted by the ratio of the change to the tolerance (largest first)
+    pub unknowns: Vec<NonConvergedUnknown>,
+    /// The instances connected to the unknowns that did not converge
+    pub instances: Vec<String>,
+    /// The instances that still limited their inputs (`$limit`) during the last iteration
+    pub limiting_instances: Vec<String>,
+}
+
+impl fmt::Display for ConvergenceFailure {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.singular {
+            write!(f, "matrix is singular (after {} iterations)", self.iterations)?;
+        } else {
+            write!(f, "Simulation failed to converge after {} iterations", self.iterations)?;
+        }
+
+        if !self.homotopy.is_empty() {
+            let homotopy: Vec<_> = self.homotopy.iter().map(|it| it.to_string()).collect();
+            write!(f, "\nconvergence aids that failed: {}", homotopy.join(", "))?;
+        }
+
+        if !self.unknowns.is_empty() {
+            f.write_str("\nunknowns that did not converge:")?;
+            for unknown in self.unknowns.iter().take(10) {
+                write!(
+                    f,
+                    "\n  {}: value = {} {}, delta = {} {}, residual = {} {}",
+                    unknown.name,
+                    unknown.value.pretty_str(),
+                    unknown.units,
+                    unknown.delta.pretty_str(),
+                    unknown.units,
+                    unknown.residual.pretty_str(),
+                    unknown.residual_units
+                )?;
+            }
+            if self.unknowns.len() > 10 {
+                write!(f, "\n  ... and {} more", self.unknowns.len() - 10)?;
+            }
+        }
+
+        if !self.instances.is_empty() {
+            write!(f, "\nconnected instances: {}", self.instances.join(", "))?;
+        }
+
+        if !self.limiting_instances.is_empty() {
+            write!(f, "\ninstances still limiting: {}", self.limiting_instances.join(", "))?;
+        }
+
+        Ok(())
+    }
+}
+
+impl std::error::Error for ConvergenceFailure {}
+
+impl Simulation<'_> {
+    /// Runs a Newton iteration starting at the current solution. `shunt` is added to the
+    /// circuit while solving. If `init_lim` is set the models initialize limiting during the first
+    /// iteration (there is no previous solution).
+    pub(super) fn newton(
+        &mut self,
+        analysis: OperatingPointAnalysis,
+        init_lim: bool,
+        shunt: Shunt<'_>,
+    ) -> Result<NewtonStatus> {
+        let debug = self.config.debug;
+        let maxiters = self.config.maxiters;
+        let max_steps: Vec<_> =
+            self.nodes.raw[1..].iter().map(|node| self.config.max_step(node)).collect();
+        let matrix =
+            self.matrix.as_mut().context("Simulation must be populated before it can run")?;
+
+        let mut i = 0;
+        loop {
+            let mut flags = analysis.eval_flags();
+            if init_lim && i == 0 {
+                flags |= EvalFlags::INIT_LIM;
+            }
+            let last_iteration = i + 1 >= maxiters;
+            self.sim_params.set_iteration(i + 1, flags.contains(EvalFlags::INIT_LIM));
+            let sim_info = SimInfo {
+                abstime: 0f64,
+                prev_solve: &self.solution,
+                sim_params: &self.sim_params,
+                flags,
+            };
+            let mut limited = false;
+            let mut limiting_instances = Vec::new();
+            for (id, inst) in self.instance_data.iter_mut_enumerated() {
+                if inst.eval(sim_info)? {
+                    limited = true;
+                    if last_iteration {
+                        limiting_instances.push(id);
+                    }
+                }
+
+                // this is save because we call populate_matrix_ptrs during Simulation construction
+                unsafe { inst.load_matrix_resist() }
+                inst.load_residual_resist(&self.solution, &mut self.residual_resist);
+                inst.load_limit_rhs_resist(&mut self.residual_resist);
+
+                if analysis.time_integration() {
+                    let alpha = 0.0;
+                    unsafe { inst.load_matrix_react(alpha) }
+                    inst.load_residual_react(&self.solution, &mut self.residual_react);
+                }
+            }
+
+            // TODO time integration for tran analysis
+
+            shunt.load(&self.solution, &matrix.nonlinear_matrix, &mut self.residual_resist);
+
+            if debug {
+                print_stdout(Self::matrix_table(&self.nodes, &matrix.nonlinear_matrix)).unwrap();
+            }
+
+            let singular = matrix.nonlinear_matrix.lu_factorize(None);
+            if singular {
+                matrix.nonlinear_matrix.write_zero();
+                let residual = self.residual_resist.clone();
+                self.residual_resist.raw.fill(0.0);
+                let failure = self.convergence_failure(i + 1, true, &residual, &[], Vec::new());
+                return Ok(NewtonStatus::Failed(failure));
+            }
+
+            let residual = if last_iteration { Some(self.residual_resist.clone()) } else { None };
+            matrix.nonlinear_matrix.solve_linear_system(&mut self.residual_resist.raw[1..]);
+
+            if debug {
+                print_stdout(Self::vec_table(&self.residual_resist.raw, &self.nodes.raw)).unwrap();
+            }
+
+            // reset matrix
+            matrix.nonlinear_matrix.write_zero();
+            let mut found_solution = true;
+            let mut deltas = Vec::new();
+            for (((dst, delta), node_info), &max_step) in
+                zip(&mut self.solution.raw[1..], &mut self.residual_resist.raw[1..])
+                    .zip(&self.nodes.raw[1..])
+                    .zip(&max_steps)
+            {
+                let mut delta = replace(delta, 0f64);
+                if delta.abs() > max_step {
+                    delta = max_step.copysign(delta);
+                    found_solution = false;
+                }
+                let new_val = *dst - delta;
+                let atol = node_info.atol;
+                // the solution can approach its final value from either side so the magnitudes
+                // are compared, NaN (a model evaluated to inf) never converges
+                let tol = atol.max(new_val.abs() * self.config.rtol);
+                if delta.is_nan() || delta.abs() > tol {
+                    found_solution = false;
+                }
+                if last_iteration {
+                    deltas.push((delta, tol));
+                }
+                *dst = new_val;
+            }
+
+            if debug {
+                print_stdout(Self::vec_table(&self.solution.raw, &self.nodes.raw)).unwrap();
+            }
+
+            if found_solution && !limited && i > 0 {
+                return Ok(NewtonStatus::Converged);
+            }
+            i += 1;
+
+            if let Some(residual) = residual {
+                let failure =
+                    self.convergence_failure(i, false, &residual, &deltas, limiting_instances);
+                return Ok(NewtonStatus::Failed(failure));
+            }
+        }
+    }
+
+    fn convergence_failure(
+        &self,
+        iterations: u32,
+        singular: bool,
+        residual: &TiSlice<Node, f64>,
+        deltas: &[(f64, f64)],
+        limiting_instances: Vec<InstanceId>,
+    ) -> ConvergenceFailure {
+        let mut unknowns: Vec<_> = self
+            .nodes
+            .iter_enumerated()
+            .skip(1)
+            .zip(deltas)
+            .filter(|(_, (delta, tol))| delta.is_nan() || delta.abs() > *tol)
+            .map(|((node, info), &(delta, tolerance))| NonConvergedUnknown {
+                node,
+                name: info.name.clone(),
+                value: self.solution[node],
+                delta,
+                tolerance,
+                residual: residual[node],
+                units: info.units,
+                residual_units: info.residual_units,
+            })
+            .collect();
+        unknowns.sort_by(|unknown1, unknown2| {
+            let ratio1 = unknown1.delta.abs() / unknown1.tolerance;
+            let ratio2 = unknown2.delta.abs() / unknown2.tolerance;
+            ratio2.total_cmp(&ratio1)
+        });
+
+        let instances = self
+            .circ
+            .instances()
+            .filter(|&inst| {
+                self.matrix_builder.instance_entries[inst].iter().any(|&(col, row)| {
+                    unknowns.iter().any(|unknown| unknown.node == col || unknown.node == row)
+                })
+            })
+            .map(|inst| self.circ[inst].name.clone())
+            .collect();
+        let limiting_instances =
+            limiting_instances.into_iter().map(|inst| self.circ[inst].name.clone()).collect();
+
+        ConvergenceFailure {
+            iterations,
+            singular,
+            homotopy: Vec::new(),
+            unknowns,
+            instances,
+            limiting_instances,
+        }
+    }
+}
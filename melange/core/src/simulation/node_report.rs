@@ -0,0 +1,63 @@
+use cli_table::{print_stdout, Cell, Style, Table};
+use typed_index_collections::TiVec;
+
+use crate::circuit::InstanceId;
+use crate::simulation::Simulation;
+
+/// The internal unknowns of an instance and the node pairs that were collapsed
+/// (see [`SimConfig::collapse_nodes`](crate::simulation::SimConfig::collapse_nodes)).
+#[derive(Debug, Clone)]
+pub struct InstanceNodes {
+    pub instance: InstanceId,
+    pub name: String,
+    /// The names of the internal unknowns that remain after collapsing
+    pub internal_nodes: Vec<String>,
+    /// The collapsed node pairs, the second node is `None` if the first node was collapsed into
+    /// ground
+    pub collapsed: Vec<(&'static str, Option<&'static str>)>,
+}
+
+impl Simulation<'_> {
+    /// The internal unknowns and collapsed node pairs of every instance
+    /// (as created by the last call to [`prepare_solver`](Simulation::prepare_solver)).
+    pub fn node_report(&self) -> Vec<InstanceNodes> {
+        let mut internal_nodes: TiVec<InstanceId, Vec<String>> =
+            vec![Vec::new(); self.circ.num_instances() as usize].into();
+        for node in &self.nodes {
+            if let Some(instance) = node.instance {
+                internal_nodes[instance].push(node.name.clone());
+            }
+        }
+
+        internal_nodes
+            .into_iter_enumerated()
+            .map(|(instance, internal_nodes)| InstanceNodes {
+                instance,
+                name: self.circ[instance].name.clone(),
+                internal_nodes,
+                collapsed: self.instance_data[instance].collapsed_nodes(),
+            })
+            .collect()
+    }
+
+    pub fn print_node_report(&self) {
+        let title = ["instance", "internal nodes", "collapsed"].map(|name| name.cell().bold(true));
+        let rows = self
+            .node_report()
+            .into_iter()
+            .filter(|report| !report.internal_nodes.is_empty() || !report.collapsed.is_empty())
+            .map(|report| {
+                let collapsed: Vec<_> = report
+                    .collapsed
+                    .iter()
+                    .map(|(from, to)| format!("{from} -> {}", to.unwrap_or("gnd")))
+                    .collect();
+                vec![
+                    report.name.cell(),
+                    report.internal_nodes.join(", ").cell(),
+                    collapsed.join(", ").cell(),
+                ]
+            });
+        print_stdout(rows.table().title(title)).unwrap()
+    }
+}
@@ -0,0 +1,52 @@
+use anyhow::{bail, Result};
+use cli_table::{print_stdout, Cell, Style, Table};
+
+use crate::circuit::InstanceId;
+use crate::devices::OpVar;
+use crate::simulation::flags::SimulationState;
+use crate::simulation::Simulation;
+use crate::utils::PrettyPrint;
+
+impl Simulation<'_> {
+    /// The operating point variables of `instance` (with units and descriptions) at the last
+    /// operating point solved by [`dc_op`](Simulation::dc_op), [`ac_op`](Simulation::ac_op) or
+    /// [`noise_op`](Simulation::noise_op).
+    pub fn opvars(&self, instance: InstanceId) -> Result<Vec<OpVar>> {
+        if !self.state.intersects(SimulationState::AT_OP) {
+            bail!("the operating point must be solved before opvars can be accessed")
+        }
+        Ok(self.instance_data[instance].opvars())
+    }
+
+    /// The operating point variables of all instances that have any (see [`Simulation::opvars`]).
+    pub fn op_report(&self) -> Result<Vec<(InstanceId, Vec<OpVar>)>> {
+        let mut res = Vec::new();
+        for instance in self.circ.instances() {
+            let opvars = self.opvars(instance)?;
+            if !opvars.is_empty() {
+                res.push((instance, opvars))
+            }
+        }
+        Ok(res)
+    }
+
+    pub fn print_op_report(&self) -> Result<()> {
+        let title =
+            ["instance", "opvar", "value", "units", "description"].map(|it| it.cell().bold(true));
+        let mut rows = Vec::new();
+        for (instance, opvars) in self.op_report()? {
+            let name = &self.circ[instance].name;
+            rows.extend(opvars.into_iter().map(|opvar| {
+                vec![
+                    name.cell(),
+                    opvar.name.cell(),
+                    opvar.value.pretty_str().cell(),
+                    opvar.units.cell(),
+                    opvar.description.cell(),
+                ]
+            }));
+        }
+        print_stdout(rows.table().title(title)).unwrap();
+        Ok(())
+    }
+}
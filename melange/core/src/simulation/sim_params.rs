@@ -0,0 +1,143 @@
+use std::ffi::CString;
+use std::os::raw::c_char;
+use std::ptr;
+
+use ahash::AHashMap;
+
+/// Simulator parameters that models can query with `$simparam` and `$simparam$str`.
+#[derive(Debug, Clone)]
+pub struct SimParams {
+    pub real: AHashMap<String, f64>,
+    pub string: AHashMap<String, String>,
+}
+
+impl SimParams {
+    /// The number of the current Newton iteration (starting at 1), set by the simulator.
+    pub const ITERATION: &'static str = "iteration";
+    /// `1` during the first Newton iteration where limiting is initialized, set by the simulator.
+    pub const INI_LIM: &'static str = "iniLim";
+    /// The factor all independent sources are scaled with, reduced by the simulator during
+    /// source stepping.
+    pub const SOURCE_SCALE_FACTOR: &'static str = "sourceScaleFactor";
+}
+
+impl Default for SimParams {
+    fn default() -> Self {
+        let real = [
+            ("gmin", 1e-12),
+            ("gdev", 0.0),
+            ("tnom", 300.15),
+            ("scale", 1.0),
+            ("shrink", 0.0),
+            ("imax", 1.0),
+            (SimParams::SOURCE_SCALE_FACTOR, 1.0),
+        ];
+        SimParams {
+            real: real.into_iter().map(|(name, val)| (name.to_owned(), val)).collect(),
+            string: AHashMap::new(),
+        }
+    }
+}
+
+/// [`SimParams`] stored as null terminated arrays of C strings and values
+/// (the layout used by OSDI).
+pub struct RawSimParams {
+    // the pointers below point into these strings
+    _strings: Vec<CString>,
+    names: Vec<*mut c_char>,
+    vals: Vec<f64>,
+    str_names: Vec<*mut c_char>,
+    str_vals: Vec<*mut c_char>,
+    iteration: usize,
+    ini_lim: usize,
+    source_scale_factor: usize,
+    base_source_scale_factor: f64,
+}
+
+impl RawSimParams {
+    pub(crate) fn new(params: &SimParams) -> RawSimParams {
+        let mut strings = Vec::new();
+        let mut c_str = |val: &str| {
+            let val = CString::new(val).expect("string may not contain null terminators");
+            let ptr = val.as_ptr() as *mut c_char;
+            strings.push(val);
+            ptr
+        };
+
+        // the parameters modified by the simulator are stored separately
+        let simulator_params =
+            [SimParams::ITERATION, SimParams::INI_LIM, SimParams::SOURCE_SCALE_FACTOR];
+        let mut names = Vec::with_capacity(params.real.len() + 4);
+        let mut vals = Vec::with_capacity(params.real.len() + 3);
+        for (name, &val) in &params.real {
+            if !simulator_params.contains(&name.as_str()) {
+                names.push(c_str(name));
+                vals.push(val);
+            }
+        }
+        let iteration = names.len();
+        names.push(c_str(SimParams::ITERATION));
+        vals.push(0.0);
+        let ini_lim = names.len();
+        names.push(c_str(SimParams::INI_LIM));
+        vals.push(0.0);
+        let base_source_scale_factor =
+            params.real.get(SimParams::SOURCE_SCALE_FACTOR).copied().unwrap_or(1.0);
+        let source_scale_factor = names.len();
+        names.push(c_str(SimParams::SOURCE_SCALE_FACTOR));
+        vals.push(base_source_scale_factor);
+        names.push(ptr::null_mut());
+
+        let mut str_names = Vec::with_capacity(params.string.len() + 1);
+        let mut str_vals = Vec::with_capacity(params.string.len());
+        for (name, val) in &params.string {
+            str_names.push(c_str(name));
+            str_vals.push(c_str(val));
+        }
+        str_names.push(ptr::null_mut());
+
+        RawSimParams {
+            _strings: strings,
+            names,
+            vals,
+            str_names,
+            str_vals,
+            iteration,
+            ini_lim,
+            source_scale_factor,
+            base_source_scale_factor,
+        }
+    }
+
+    /// Updates the parameters that depend on the progress of the Newton iteration.
+    pub(crate) fn set_iteration(&mut self, iteration: u32, ini_lim: bool) {
+        self.vals[self.iteration] = iteration as f64;
+        self.vals[self.ini_lim] = if ini_lim { 1.0 } else { 0.0 };
+    }
+
+    /// Scales the `sourceScaleFactor` provided by the user with `scale`.
+    pub(crate) fn set_source_scale(&mut self, scale: f64) {
+        self.vals[self.source_scale_factor] = self.base_source_scale_factor * scale;
+    }
+
+    /// The factor independent sources must be multiplied with.
+    pub fn source_scale_factor(&self) -> f64 {
+        self.vals[self.source_scale_factor]
+    }
+
+    pub(crate) fn names(&self) -> *mut *mut c_char {
+        self.names.as_ptr() as *mut _
+    }
+
+    pub(crate) fn vals(&self) -> *mut f64 {
+        self.vals.as_ptr() as *mut _
+    }
+
+    pub(crate) fn str_names(&self) -> *mut *mut c_char {
+        self.str_names.as_ptr() as *mut _
+    }
+
+    pub(crate) fn str_vals(&self) -> *mut *mut c_char {
+        self.str_vals.as_ptr() as *mut _
+    }
+}
@@ -0,0 +1,224 @@
+use anyhow::{bail, Context, Result};
+use cli_table::{print_stdout, Cell, Style, Table};
+
+use crate::circuit::{InstanceId, Node};
+use crate::devices::{ParamId, Type};
+use crate::expr::{CircuitParam, ExprEvalCtxRef};
+use crate::simulation::Simulation;
+use crate::utils::PrettyPrint;
+use crate::Arena;
+
+/// A quantity that is varied during a DC sweep.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SweepVar {
+    /// A circuit parameter, the solver is prepared again for every value.
+    Param(CircuitParam),
+    /// The `dc` value of a source instance.
+    Source(InstanceId),
+}
+
+/// A single (possibly nested) sweep of `var` over `values`.
+#[derive(Debug, Clone)]
+pub struct Sweep {
+    pub var: SweepVar,
+    pub values: Vec<f64>,
+}
+
+impl Sweep {
+    pub fn new(var: SweepVar, values: Vec<f64>) -> Sweep {
+        Sweep { var, values }
+    }
+
+    /// `num_points` equidistant values from `start` to `stop` (inclusive).
+    pub fn linear(var: SweepVar, start: f64, stop: f64, num_points: usize) -> Sweep {
+        let step = if num_points > 1 { (stop - start) / (num_points - 1) as f64 } else { 0.0 };
+        let values = (0..num_points).map(|i| start + step * i as f64).collect();
+        Sweep { var, values }
+    }
+}
+
+/// The result of a DC sweep with one row for every point. The columns are the swept
+/// variables, the circuit node voltages (`V(node)`), the lead currents of all instances
+/// (`I(instance:terminal)`) and the operating point variables of all instances
+/// (`instance.opvar`).
+#[derive(Debug, Clone, Default)]
+pub struct SweepResult {
+    pub columns: Vec<String>,
+    pub rows: Vec<Vec<f64>>,
+}
+
+impl SweepResult {
+    /// The values of the column `name` at every point
+    pub fn column(&self, name: &str) -> Option<Vec<f64>> {
+        let idx = self.columns.iter().position(|column| column == name)?;
+        Some(self.rows.iter().map(|row| row[idx]).collect())
+    }
+
+    pub fn print(&self) {
+        let title = self.columns.iter().map(|column| column.clone().cell().bold(true));
+        let rows = self.rows.iter().map(|row| row.iter().map(|val| val.pretty_str().cell()));
+        print_stdout(rows.table().title(title)).unwrap()
+    }
+}
+
+/// A [`SweepVar`] resolved to the data required to modify it
+#[derive(Clone, Copy)]
+enum ResolvedVar {
+    Param(CircuitParam),
+    Source(InstanceId, ParamId),
+}
+
+impl Simulation<'_> {
+    /// Solves the DC operating point for every combination of the values in `sweeps`. The
+    /// first sweep is the outermost one. The solution of each point is used as the initial
+    /// guess for the next point. Once the sweep is finished the solver is prepared again with
+    /// the original parameter and source values.
+    pub fn dc_sweep(
+        &mut self,
+        sweeps: &[Sweep],
+        mut eval_ctx: ExprEvalCtxRef,
+        arena: &Arena,
+    ) -> Result<SweepResult> {
+        let mut columns = Vec::with_capacity(sweeps.len());
+        let mut vars = Vec::with_capacity(sweeps.len());
+        let mut original_params = Vec::new();
+        let mut original_sources = Vec::new();
+        for sweep in sweeps {
+            let var = match sweep.var {
+                SweepVar::Param(param) => {
+                    let name = arena.lookup_param_info(param).map_or("temp", |(name, _)| name);
+                    if self.circ.param_assignments.contains_key(&param) {
+                        bail!("parameter {name} is assigned in the circuit and can not be swept")
+                    }
+                    columns.push(name.to_owned());
+                    original_params.push((param, eval_ctx[param]));
+                    ResolvedVar::Param(param)
+                }
+                SweepVar::Source(inst) => {
+                    let info = &self.circ[inst];
+                    let dev = &self.circ[self.circ[info.model].device];
+                    let param = match dev.parameters.lookup_param("dc") {
+                        Some((param, param_info))
+                            if param_info.ty == Type::Real && param_info.is_instance_param =>
+                        {
+                            param
+                        }
+                        _ => bail!("instance {} is not a source and can not be swept", info.name),
+                    };
+                    columns.push(format!("{}.dc", info.name));
+                    // sources without an explicit dc value are not reset by prepare_solver
+                    let val = self.instance_data[inst].get_real_param(param);
+                    original_sources.push((inst, param, val));
+                    ResolvedVar::Source(inst, param)
+                }
+            };
+            vars.push((var, &*sweep.values));
+        }
+
+        let mut res = SweepResult { columns, rows: Vec::new() };
+        let mut point = Vec::with_capacity(sweeps.len());
+        let sweep_res = self.sweep_level(&vars, &mut point, eval_ctx.borrow(), arena, &mut res);
+
+        for (param, val) in original_params {
+            eval_ctx.set_param(param, val);
+        }
+        for (inst, param, val) in original_sources {
+            self.instance_data[inst].set_real_param(param, val);
+        }
+        self.prepare_solver(eval_ctx, arena)?;
+        sweep_res?;
+        Ok(res)
+    }
+
+    /// Sweeps `vars[point.len()]` and all inner sweeps. `point` contains the current values of
+    /// the outer sweeps.
+    fn sweep_level(
+        &mut self,
+        vars: &[(ResolvedVar, &[f64])],
+        point: &mut Vec<f64>,
+        mut eval_ctx: ExprEvalCtxRef,
+        arena: &Arena,
+        res: &mut SweepResult,
+    ) -> Result<()> {
+        let (outer, var, values) = match vars.get(point.len()) {
+            Some(&(var, values)) => (&vars[..point.len()], var, values),
+            None => return self.record_sweep_point(point, res),
+        };
+
+        for &val in values {
+            match var {
+                ResolvedVar::Param(param) => {
+                    // continue from the last solution unless the unknowns changed
+                    let guess = self.solution.clone();
+                    eval_ctx.set_param(param, val.into());
+                    self.prepare_solver(eval_ctx.borrow(), arena)?;
+                    // preparing the solver resets the instance parameters so the values of
+                    // the outer source sweeps must be applied again
+                    for (&(outer_var, _), &outer_val) in outer.iter().zip(&*point) {
+                        if let ResolvedVar::Source(inst, param) = outer_var {
+                            self.instance_data[inst].set_real_param(param, outer_val);
+                        }
+                    }
+                    if guess.len() == self.solution.len() {
+                        self.set_initial_guess(&guess);
+                    }
+                }
+                ResolvedVar::Source(inst, param) => {
+                    self.instance_data[inst].set_real_param(param, val);
+                    self.state.clear();
+                }
+            }
+
+            point.push(val);
+            self.sweep_level(vars, point, eval_ctx.borrow(), arena, res)?;
+            point.pop();
+        }
+
+        Ok(())
+    }
+
+    fn record_sweep_point(&mut self, point: &[f64], res: &mut SweepResult) -> Result<()> {
+        self.dc_op().with_context(|| {
+            let point: Vec<_> = zip_names(&res.columns, point);
+            format!("failed to solve the operating point at {}", point.join(", "))
+        })?;
+
+        let record_columns = res.rows.is_empty();
+        let mut row = point.to_owned();
+        for node in self.circ.nodes().filter(|&node| node != Node::GROUND) {
+            if record_columns {
+                res.columns.push(format!("V({})", self.circ.node_name(node)));
+            }
+            row.push(self.solution[node]);
+        }
+
+        for inst in self.circ.instances() {
+            let info = &self.circ[inst];
+            let mut currents = vec![0f64; info.connections.len()];
+            self.instance_data[inst].load_lead_current_resist(&self.solution, &mut currents);
+            if record_columns {
+                let terminals = &self.circ[self.circ[info.model].device].terminals;
+                for terminal in terminals.iter().take(currents.len()) {
+                    res.columns.push(format!("I({}:{terminal})", info.name));
+                }
+            }
+            row.extend(currents);
+        }
+
+        for inst in self.circ.instances() {
+            let opvars = self.instance_data[inst].opvars();
+            if record_columns {
+                let name = &self.circ[inst].name;
+                res.columns.extend(opvars.iter().map(|opvar| format!("{name}.{}", opvar.name)));
+            }
+            row.extend(opvars.into_iter().map(|opvar| opvar.value));
+        }
+
+        res.rows.push(row);
+        Ok(())
+    }
+}
+
+fn zip_names(names: &[String], vals: &[f64]) -> Vec<String> {
+    names.iter().zip(vals).map(|(name, val)| format!("{name} = {}", val.pretty_str())).collect()
+}
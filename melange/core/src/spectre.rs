@@ -0,0 +1,459 @@
+//! A reader for (a subset of) the netlist format of the spectre simulator.
+//!
+//! Netlists are [parsed] into a [`CircuitDescription`]. The following statements are supported:
+//!
+//! * instances: `name (n1 n2 ...) master param=value ...`
+//! * models: `model name master param=value ...`
+//! * circuit parameters: `parameters name=value ...`
+//! * subcircuits: `subckt name (p1 p2 ...)` ... `ends`
+//! * `include "file" [section=name]` and `section name` ... `endsection`
+//! * `ahdl_include "file.va"`, the Verilog-A file is compiled during elaboration
+//! * `global`, `simulator lang=spectre`
+//!
+//! Analyses and other statements that control the simulator are ignored.
+//! Subcircuits are flattened while reading the netlist: Instances, models and internal nodes
+//! of a subcircuit instance `x1` are prefixed with `x1.`. Similarly each subcircuit parameter `p`
+//! becomes a circuit parameter `x1.p`.
+//!
+//! Any names within expressions that are not defined by the netlist are interpreted as
+//! circuit parameters, whose value must be provided when the simulation is prepared.
+//!
+//! [parsed]: crate::spectre::parse
+
+use ahash::{AHashMap, AHashSet};
+use anyhow::{bail, Context, Result};
+use camino::Utf8Path;
+use typed_index_collections::TiVec;
+
+use crate::elaboration::{
+    CircuitDescription, CircuitInstanceDescription, CircuitModelDescription, ParamDescription,
+};
+use crate::spectre::parser::{parse_file, AstExpr, Instance, Params, Stmt, Subckt};
+use crate::{veriloga, Arena, Circuit, CircuitParam, CircuitParamCtx, Expr};
+
+mod lexer;
+mod parser;
+
+#[cfg(test)]
+mod tests;
+
+/// Maximum nesting of included files and subcircuit instances.
+/// Used to detect recursive includes/subcircuits.
+const MAX_DEPTH: u32 = 64;
+
+/// Reads the spectre netlist `path` (and all files it includes) into a [`CircuitDescription`].
+/// If `section` is provided only the statements inside `section name` ... `endsection` (and those
+/// outside of any section) are read.
+///
+/// Circuit parameters are defined in `ctx`, the description must therefore be elaborated into a
+/// circuit with the same context.
+pub fn parse(
+    path: &Utf8Path,
+    section: Option<&str>,
+    earena: &mut Arena,
+    ctx: CircuitParamCtx,
+) -> Result<CircuitDescription> {
+    let mut stmts = Vec::new();
+    load_file(path, section, 0, &mut stmts)?;
+    let name = path.file_stem().unwrap_or("netlist").to_owned();
+    lower(name, &stmts, earena, ctx)
+}
+
+impl Circuit {
+    /// Reads a spectre netlist (see [`parse`]) and adds its models and instances to this circuit.
+    pub fn load_spectre_netlist(
+        &mut self,
+        path: &Utf8Path,
+        section: Option<&str>,
+        earena: &mut Arena,
+        opts: &veriloga::Opts,
+    ) -> Result<()> {
+        let descr = parse(path, section, earena, self.param_ctx())?;
+        self.add_description(descr, opts)
+    }
+}
+
+/// Parses `path` and appends its statements to `dst`. Includes are expanded and only the
+/// contents of the selected section are retained.
+fn load_file(
+    path: &Utf8Path,
+    section: Option<&str>,
+    depth: u32,
+    dst: &mut Vec<Stmt>,
+) -> Result<()> {
+    if depth > MAX_DEPTH {
+        bail!("files are included recursively");
+    }
+    let stmts = parse_file(path)?;
+    let mut found = false;
+    expand(stmts, section, depth, &mut found, dst)?;
+    if let (Some(section), false) = (section, found) {
+        bail!("section '{section}' not found in {path}")
+    }
+    Ok(())
+}
+
+fn expand(
+    stmts: Vec<Stmt>,
+    section: Option<&str>,
+    depth: u32,
+    found: &mut bool,
+    dst: &mut Vec<Stmt>,
+) -> Result<()> {
+    for stmt in stmts {
+        match stmt {
+            Stmt::Include { path, section, loc } => {
+                load_file(&path, section.as_deref(), depth + 1, dst)
+                    .with_context(|| format!("included at {loc}"))?;
+            }
+            Stmt::Section { name, body } => {
+                if Some(&*name) == section {
+                    *found = true;
+                    expand(body, None, depth, found, dst)?;
+                }
+            }
+            Stmt::Subckt(mut subckt) => {
+                let mut body = Vec::with_capacity(subckt.body.len());
+                expand(subckt.body, None, depth, found, &mut body)?;
+                subckt.body = body;
+                dst.push(Stmt::Subckt(subckt))
+            }
+            stmt => dst.push(stmt),
+        }
+    }
+    Ok(())
+}
+
+fn lower(
+    name: String,
+    stmts: &[Stmt],
+    earena: &mut Arena,
+    ctx: CircuitParamCtx,
+) -> Result<CircuitDescription> {
+    let mut lowering = Lowering {
+        earena,
+        ctx,
+        globals: AHashSet::new(),
+        subckts: AHashMap::new(),
+        res: CircuitDescription {
+            name,
+            instances: TiVec::new(),
+            models: TiVec::new(),
+            va_files: Vec::new(),
+            parameters: Vec::new(),
+            ctx,
+        },
+    };
+
+    let mut scope = Scope::default();
+    for stmt in stmts {
+        match stmt {
+            Stmt::Global(nodes) => lowering.globals.extend(nodes.iter().map(String::as_str)),
+            Stmt::Subckt(subckt) => {
+                lowering.subckts.insert(&subckt.name, subckt);
+            }
+            Stmt::Model(model) => {
+                scope.models.insert(&model.name);
+            }
+            Stmt::AhdlInclude(path) => lowering.res.va_files.push(path.clone()),
+            _ => (),
+        }
+    }
+
+    for stmt in stmts {
+        if let Stmt::Parameters(params) = stmt {
+            for (name, val) in params {
+                let val = lowering.lower_expr(val, &scope)?;
+                let (param, _) = match lowering.earena.lookup_param_by_name(ctx, name) {
+                    Some(param) => param,
+                    None => lowering.earena.def_param(ctx, name.clone())?,
+                };
+                lowering.res.parameters.push((param, val));
+            }
+        }
+    }
+
+    lowering.lower_block(stmts, &scope, 0)?;
+    Ok(lowering.res)
+}
+
+/// Names visible inside a (flattened) subcircuit instance
+#[derive(Default)]
+struct Scope<'a> {
+    /// Prepended to the names of instances, models and internal nodes
+    prefix: String,
+    /// Maps ports to the nodes they are connected to
+    ports: AHashMap<&'a str, String>,
+    /// Subcircuit parameters
+    params: AHashMap<&'a str, Expr>,
+    /// Models defined in this subcircuit
+    models: AHashSet<&'a str>,
+    /// Subcircuits defined in this subcircuit
+    subckts: AHashMap<&'a str, &'a Subckt>,
+}
+
+struct Lowering<'a, 'b> {
+    earena: &'b mut Arena,
+    ctx: CircuitParamCtx,
+    globals: AHashSet<&'a str>,
+    /// Subcircuits defined at the top level
+    subckts: AHashMap<&'a str, &'a Subckt>,
+    res: CircuitDescription,
+}
+
+impl<'a> Lowering<'a, '_> {
+    fn lower_block(&mut self, stmts: &'a [Stmt], scope: &Scope<'a>, depth: u32) -> Result<()> {
+        for stmt in stmts {
+            match stmt {
+                Stmt::Instance(inst) => self.lower_instance(inst, scope, depth)?,
+                Stmt::Model(model) => {
+                    let parameters = self
+                        .lower_params(&model.params, scope)
+                        .with_context(|| format!("in model '{}' at {}", model.name, model.loc))?;
+                    self.res.models.push(CircuitModelDescription {
+                        name: format!("{}{}", scope.prefix, model.name),
+                        device: model.master.clone(),
+                        parameters,
+                    });
+                }
+                _ => (),
+            }
+        }
+        Ok(())
+    }
+
+    fn lower_instance(&mut self, inst: &'a Instance, scope: &Scope<'a>, depth: u32) -> Result<()> {
+        let name = format!("{}{}", scope.prefix, inst.name);
+        let terminal_connections: Vec<_> =
+            inst.nodes.iter().map(|node| self.node_name(node, scope)).collect();
+        let parameters = self
+            .lower_params(&inst.params, scope)
+            .with_context(|| format!("in instance '{}' at {}", inst.name, inst.loc))?;
+
+        let master = &*inst.master;
+        let subckt = scope.subckts.get(master).or_else(|| self.subckts.get(master)).copied();
+        if let Some(subckt) = subckt {
+            return self
+                .instantiate(subckt, name, terminal_connections, parameters, depth + 1)
+                .with_context(|| format!("in instance '{}' at {}", inst.name, inst.loc));
+        }
+
+        let master = if scope.models.contains(master) {
+            format!("{}{master}", scope.prefix)
+        } else {
+            master.to_owned()
+        };
+        self.res.instances.push(CircuitInstanceDescription {
+            name,
+            master,
+            parameters,
+            terminal_connections,
+        });
+        Ok(())
+    }
+
+    /// Flattens an instance `name` of `subckt` into the circuit
+    fn instantiate(
+        &mut self,
+        subckt: &'a Subckt,
+        name: String,
+        nodes: Vec<String>,
+        mut params: ParamDescription,
+        depth: u32,
+    ) -> Result<()> {
+        if depth > MAX_DEPTH {
+            bail!("subcircuit '{}' is instantiated recursively", subckt.name);
+        }
+        if nodes.len() != subckt.ports.len() {
+            bail!(
+                "subcircuit '{}' has {} ports but {} nodes were connected",
+                subckt.name,
+                subckt.ports.len(),
+                nodes.len()
+            );
+        }
+
+        let mut scope = Scope {
+            prefix: format!("{name}."),
+            ports: subckt.ports.iter().map(String::as_str).zip(nodes).collect(),
+            ..Scope::default()
+        };
+        for stmt in &subckt.body {
+            match stmt {
+                Stmt::Model(model) => {
+                    scope.models.insert(&model.name);
+                }
+                Stmt::Subckt(subckt) => {
+                    scope.subckts.insert(&subckt.name, subckt);
+                }
+                _ => (),
+            }
+        }
+
+        for stmt in &subckt.body {
+            if let Stmt::Parameters(defaults) = stmt {
+                for (param_name, default) in defaults {
+                    let val = match params.iter().position(|(name, _)| name == param_name) {
+                        Some(i) => params.remove(i).1,
+                        None => self.lower_expr(default, &scope)?,
+                    };
+                    let (param, read_expr) =
+                        self.earena.def_param(self.ctx, format!("{name}.{param_name}"))?;
+                    self.res.parameters.push((param, val));
+                    scope.params.insert(param_name, read_expr);
+                }
+            }
+        }
+
+        if let Some((param, _)) = params.first() {
+            bail!("subcircuit '{}' has no parameter '{param}'", subckt.name);
+        }
+
+        self.lower_block(&subckt.body, &scope, depth)
+    }
+
+    fn node_name(&self, node: &str, scope: &Scope) -> String {
+        if node == "0" {
+            "ground".to_owned()
+        } else if let Some(outer) = scope.ports.get(node) {
+            outer.clone()
+        } else if self.globals.contains(node) {
+            node.to_owned()
+        } else {
+            format!("{}{node}", scope.prefix)
+        }
+    }
+
+    fn lower_params(&mut self, params: &Params, scope: &Scope) -> Result<ParamDescription> {
+        params
+            .iter()
+            .map(|(name, val)| {
+                let val = self
+                    .lower_expr(val, scope)
+                    .with_context(|| format!("in the value of parameter '{name}'"))?;
+                Ok((name.clone(), val))
+            })
+            .collect()
+    }
+
+    /// Resolves a name used within an expression
+    fn lookup(&mut self, name: &str, scope: &Scope) -> Result<Expr> {
+        if let Some(&val) = scope.params.get(name) {
+            return Ok(val);
+        }
+
+        // spectre uses celsius
+        if name == "temp" {
+            let temp = Expr::param(self.earena, CircuitParam::TEMPERATURE);
+            return Expr::add(self.earena, temp, (-273.15).into());
+        }
+
+        let param = match self.earena.lookup_param_by_name(self.ctx, name) {
+            Some((_, read_expr)) => read_expr,
+            None => self.earena.def_param(self.ctx, name.to_owned())?.1,
+        };
+        Ok(param)
+    }
+
+    fn lower_expr(&mut self, expr: &AstExpr, scope: &Scope) -> Result<Expr> {
+        let res = match *expr {
+            AstExpr::Num(val) => val.into(),
+            AstExpr::Str(ref val) => Expr::str(self.earena, val),
+            AstExpr::Ident(ref name) => return self.lookup(name, scope),
+            AstExpr::Unary(op, ref arg) => {
+                let arg = self.lower_expr(arg, scope)?;
+                let arena = &mut *self.earena;
+                match op {
+                    "-" => Expr::neg(arena, arg)?,
+                    "!" => Expr::eq(arena, arg, 0.0.into()),
+                    // ~x == -x - 1 for integers
+                    "~" => {
+                        let arg = Expr::int(arena, arg)?;
+                        let arg = Expr::neg(arena, arg)?;
+                        Expr::add(arena, arg, (-1.0).into())?
+                    }
+                    _ => arg,
+                }
+            }
+            AstExpr::Binary(op, ref lhs, ref rhs) => {
+                let lhs = self.lower_expr(lhs, scope)?;
+                let rhs = self.lower_expr(rhs, scope)?;
+                let arena = &mut *self.earena;
+                match op {
+                    "+" => Expr::add(arena, lhs, rhs)?,
+                    "-" => {
+                        let rhs = Expr::neg(arena, rhs)?;
+                        Expr::add(arena, lhs, rhs)?
+                    }
+                    "*" => Expr::mul(arena, lhs, rhs)?,
+                    "/" => {
+                        let rhs = Expr::inv(arena, rhs)?;
+                        Expr::mul(arena, lhs, rhs)?
+                    }
+                    "%" => Expr::modulo(arena, lhs, rhs)?,
+                    "**" => Expr::pow(arena, lhs, rhs)?,
+                    "==" => Expr::eq(arena, lhs, rhs),
+                    "!=" => Expr::neq(arena, lhs, rhs),
+                    "<" => Expr::lt(arena, lhs, rhs)?,
+                    "<=" => Expr::le(arena, lhs, rhs)?,
+                    ">" => Expr::lt(arena, rhs, lhs)?,
+                    ">=" => Expr::le(arena, rhs, lhs)?,
+                    "<<" => Expr::shl(arena, lhs, rhs)?,
+                    ">>" => Expr::shr(arena, lhs, rhs)?,
+                    "&&" => Expr::logic_and(arena, lhs, rhs)?,
+                    "||" => Expr::logic_or(arena, lhs, rhs)?,
+                    "&" => Expr::and(arena, lhs, rhs)?,
+                    "|" => Expr::or(arena, lhs, rhs)?,
+                    "^" => Expr::xolr(arena, lhs, rhs)?,
+                    _ => unreachable!("unknown operator {op}"),
+                }
+            }
+            AstExpr::Cond(ref cond, ref then_val, ref else_val) => {
+                let cond = self.lower_expr(cond, scope)?;
+                let then_val = self.lower_expr(then_val, scope)?;
+                let else_val = self.lower_expr(else_val, scope)?;
+                Expr::cond(self.earena, cond, then_val, else_val)?
+            }
+            AstExpr::Call(ref name, ref args) => {
+                let args = args
+                    .iter()
+                    .map(|arg| self.lower_expr(arg, scope))
+                    .collect::<Result<Vec<_>>>()?;
+                self.lower_call(name, args)?
+            }
+        };
+        Ok(res)
+    }
+
+    fn lower_call(&mut self, name: &str, args: Vec<Expr>) -> Result<Expr> {
+        let arena = &mut *self.earena;
+        match (name, &*args) {
+            ("exp", &[arg]) => Expr::exp(arena, arg),
+            ("log" | "ln", &[arg]) => Expr::log(arena, arg),
+            ("log10", &[arg]) => Expr::log10(arena, arg),
+            ("sqrt", &[arg]) => Expr::sqrt(arena, arg),
+            ("abs", &[arg]) => Expr::abs(arena, arg),
+            ("sin", &[arg]) => Expr::sin(arena, arg),
+            ("cos", &[arg]) => Expr::cos(arena, arg),
+            ("tan", &[arg]) => Expr::tan(arena, arg),
+            ("asin", &[arg]) => Expr::asin(arena, arg),
+            ("acos", &[arg]) => Expr::acos(arena, arg),
+            ("atan", &[arg]) => Expr::atam(arena, arg),
+            ("sinh", &[arg]) => Expr::sinh(arena, arg),
+            ("cosh", &[arg]) => Expr::cosh(arena, arg),
+            ("tanh", &[arg]) => Expr::tanh(arena, arg),
+            ("asinh", &[arg]) => Expr::asinh(arena, arg),
+            ("atanh", &[arg]) => Expr::atanh(arena, arg),
+            ("ceil", &[arg]) => Expr::ceil(arena, arg),
+            ("floor", &[arg]) => Expr::floor(arena, arg),
+            ("int", &[arg]) => Expr::int(arena, arg),
+            ("pow", &[lhs, rhs]) => Expr::pow(arena, lhs, rhs),
+            ("min", &[lhs, rhs]) => Expr::min(arena, lhs, rhs),
+            ("max", &[lhs, rhs]) => Expr::max(arena, lhs, rhs),
+            ("atan2", &[lhs, rhs]) => Expr::atan2(arena, lhs, rhs),
+            ("hypot", &[lhs, rhs]) => Expr::hypot(arena, lhs, rhs),
+            ("fmod", &[lhs, rhs]) => Expr::fmod(arena, lhs, rhs),
+            _ => bail!("unknown function {name} with {} arguments", args.len()),
+        }
+    }
+}
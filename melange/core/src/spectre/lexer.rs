@@ -0,0 +1,180 @@
+use std::fmt;
+
+use anyhow::{bail, Result};
+use camino::Utf8Path;
+
+#[derive(Debug, Clone, PartialEq)]
+pub(super) enum Token {
+    Ident(String),
+    /// A number literal with its value (including scale factors) and its text.
+    /// The text is retained because numbers are also valid node names.
+    Num(f64, String),
+    Str(String),
+    /// An operator or delimiter
+    Op(&'static str),
+    Newline,
+    Eof,
+}
+
+impl fmt::Display for Token {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Token::Ident(ident) => write!(f, "'{ident}'"),
+            Token::Num(_, text) => write!(f, "'{text}'"),
+            Token::Str(str) => write!(f, "\"{str}\""),
+            Token::Op(op) => write!(f, "'{op}'"),
+            Token::Newline => f.write_str("end of line"),
+            Token::Eof => f.write_str("end of file"),
+        }
+    }
+}
+
+/// Operators and delimiters, operators that are the prefix of another operator must come last
+const OPERATORS: [&str; 31] = [
+    "**", "==", "!=", "<=", ">=", "&&", "||", "<<", ">>", "(", ")", "[", "]", "{", "}", ",", "=",
+    "+", "-", "*", "/", "%", "^", "!", "~", "<", ">", "&", "|", "?", ":",
+];
+
+/// Splits the contents of a spectre netlist into tokens. Each token is returned together with
+/// the line it starts at. Comments and line continuations are removed and the result is always
+/// terminated by [`Token::Eof`].
+pub(super) fn tokenize(src: &str, file: &Utf8Path) -> Result<Vec<(Token, u32)>> {
+    let bytes = src.as_bytes();
+    let mut res: Vec<(Token, u32)> = Vec::with_capacity(src.len() / 4);
+    let mut line = 1;
+    let mut pos = 0;
+    let mut line_start = true;
+
+    while pos < bytes.len() {
+        let c = bytes[pos];
+        match c {
+            b'\\'
+                if bytes[pos + 1..].starts_with(b"\n") || bytes[pos + 1..].starts_with(b"\r\n") =>
+            {
+                pos = memchr_newline(bytes, pos) + 1;
+                line += 1;
+                continue;
+            }
+            b'\n' => {
+                if !matches!(res.last(), None | Some((Token::Newline, _))) {
+                    res.push((Token::Newline, line));
+                }
+                pos += 1;
+                line += 1;
+                line_start = true;
+                continue;
+            }
+            _ if c.is_ascii_whitespace() => {
+                pos += 1;
+                continue;
+            }
+            b'/' if bytes.get(pos + 1) == Some(&b'/') => {
+                pos = memchr_newline(bytes, pos);
+                continue;
+            }
+            b'*' if line_start => {
+                pos = memchr_newline(bytes, pos);
+                continue;
+            }
+            b'"' => {
+                let start = pos + 1;
+                let end = match bytes[start..].iter().position(|&c| c == b'"' || c == b'\n') {
+                    Some(len) if bytes[start + len] == b'"' => start + len,
+                    _ => bail!("{file}:{line}: unterminated string"),
+                };
+                res.push((Token::Str(src[start..end].to_owned()), line));
+                pos = end + 1;
+            }
+            b'0'..=b'9' => {
+                let (tok, end) = lex_num(src, pos);
+                res.push((tok, line));
+                pos = end;
+            }
+            b'.' if bytes.get(pos + 1).map_or(false, u8::is_ascii_digit) => {
+                let (tok, end) = lex_num(src, pos);
+                res.push((tok, line));
+                pos = end;
+            }
+            _ if c.is_ascii_alphabetic() || c == b'_' => {
+                let start = pos;
+                while pos < bytes.len() {
+                    match bytes[pos] {
+                        c if c.is_ascii_alphanumeric() || matches!(c, b'_' | b'.' | b'$') => {
+                            pos += 1
+                        }
+                        // node names like vdd! but not vdd!=...
+                        b'!' if bytes.get(pos + 1) != Some(&b'=') => pos += 1,
+                        _ => break,
+                    }
+                }
+                res.push((Token::Ident(src[start..pos].to_owned()), line));
+            }
+            _ => match OPERATORS.iter().find(|op| bytes[pos..].starts_with(op.as_bytes())) {
+                Some(op) => {
+                    res.push((Token::Op(op), line));
+                    pos += op.len();
+                }
+                None => {
+                    let c = src[pos..].chars().next().unwrap();
+                    bail!("{file}:{line}: unexpected character '{c}'")
+                }
+            },
+        }
+        line_start = false;
+    }
+
+    res.push((Token::Newline, line));
+    res.push((Token::Eof, line));
+    Ok(res)
+}
+
+/// Returns the position of the next newline (or the end of the input)
+fn memchr_newline(bytes: &[u8], pos: usize) -> usize {
+    bytes[pos..].iter().position(|&c| c == b'\n').map_or(bytes.len(), |off| pos + off)
+}
+
+fn lex_num(src: &str, start: usize) -> (Token, usize) {
+    let bytes = src.as_bytes();
+    let digits = |mut pos: usize| {
+        while bytes.get(pos).map_or(false, u8::is_ascii_digit) {
+            pos += 1
+        }
+        pos
+    };
+
+    let mut pos = digits(start);
+    if bytes.get(pos) == Some(&b'.') {
+        pos = digits(pos + 1);
+    }
+    if matches!(bytes.get(pos), Some(b'e' | b'E')) {
+        let exp_start =
+            if matches!(bytes.get(pos + 1), Some(b'+' | b'-')) { pos + 2 } else { pos + 1 };
+        if bytes.get(exp_start).map_or(false, u8::is_ascii_digit) {
+            pos = digits(exp_start);
+        }
+    }
+
+    let num_end = pos;
+    while bytes.get(pos).map_or(false, |&c| c.is_ascii_alphanumeric() || c == b'_') {
+        pos += 1
+    }
+
+    let val: f64 = src[start..num_end].parse().unwrap();
+    let scale = match &bytes[num_end..pos] {
+        [] => 1.0,
+        b"T" => 1e12,
+        b"G" => 1e9,
+        b"M" => 1e6,
+        b"K" | b"k" => 1e3,
+        b"m" => 1e-3,
+        b"u" => 1e-6,
+        b"n" => 1e-9,
+        b"p" => 1e-12,
+        b"f" => 1e-15,
+        b"a" => 1e-18,
+        // node names may start with a digit
+        _ => return (Token::Ident(src[start..pos].to_owned()), pos),
+    };
+
+    (Token::Num(val * scale, src[start..pos].to_owned()), pos)
+}
@@ -0,0 +1,514 @@
+use anyhow::{bail, Result};
+use camino::{Utf8Path, Utf8PathBuf};
+use log::warn;
+
+use crate::spectre::lexer::{tokenize, Token};
+
+/// Statements that are used to control the simulator. Melange is controlled with its API instead
+/// so these statements are ignored.
+const ANALYSES: [&str; 20] = [
+    "ac",
+    "alter",
+    "altergroup",
+    "check",
+    "checklimit",
+    "dc",
+    "hb",
+    "info",
+    "montecarlo",
+    "noise",
+    "options",
+    "pss",
+    "pz",
+    "set",
+    "shell",
+    "sp",
+    "stb",
+    "sweep",
+    "tran",
+    "xf",
+];
+
+#[derive(Debug, Clone, PartialEq)]
+pub(super) enum AstExpr {
+    Num(f64),
+    Str(String),
+    Ident(String),
+    Unary(&'static str, Box<AstExpr>),
+    Binary(&'static str, Box<AstExpr>, Box<AstExpr>),
+    Cond(Box<AstExpr>, Box<AstExpr>, Box<AstExpr>),
+    Call(String, Vec<AstExpr>),
+}
+
+pub(super) type Params = Vec<(String, AstExpr)>;
+
+#[derive(Debug, Clone, PartialEq)]
+pub(super) struct Instance {
+    pub name: String,
+    pub nodes: Vec<String>,
+    pub master: String,
+    pub params: Params,
+    /// `file:line` of the statement for error messages
+    pub loc: String,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub(super) struct Model {
+    pub name: String,
+    pub master: String,
+    pub params: Params,
+    pub loc: String,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub(super) struct Subckt {
+    pub name: String,
+    pub ports: Vec<String>,
+    pub body: Vec<Stmt>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub(super) enum Stmt {
+    Instance(Instance),
+    Model(Model),
+    Parameters(Params),
+    Subckt(Subckt),
+    /// `include "path" [section=name]`, the path is resolved relative to the including file
+    Include {
+        path: Utf8PathBuf,
+        section: Option<String>,
+        loc: String,
+    },
+    AhdlInclude(Utf8PathBuf),
+    Global(Vec<String>),
+    Section {
+        name: String,
+        body: Vec<Stmt>,
+    },
+}
+
+/// Parses a single spectre netlist. Included files are not parsed.
+pub(super) fn parse_file(path: &Utf8Path) -> Result<Vec<Stmt>> {
+    let src = match std::fs::read_to_string(path) {
+        Ok(src) => src,
+        Err(err) => bail!("failed to read {path}: {err}"),
+    };
+    parse_str(&src, path)
+}
+
+pub(super) fn parse_str(src: &str, path: &Utf8Path) -> Result<Vec<Stmt>> {
+    let tokens = tokenize(src, path)?;
+    let mut parser = Parser { tokens, pos: 0, file: path };
+    parser.parse_block(None)
+}
+
+struct Parser<'a> {
+    tokens: Vec<(Token, u32)>,
+    pos: usize,
+    file: &'a Utf8Path,
+}
+
+impl Parser<'_> {
+    fn peek(&self) -> &Token {
+        &self.tokens[self.pos].0
+    }
+
+    fn nth(&self, n: usize) -> &Token {
+        let pos = (self.pos + n).min(self.tokens.len() - 1);
+        &self.tokens[pos].0
+    }
+
+    fn bump(&mut self) -> Token {
+        let tok = self.tokens[self.pos].0.clone();
+        if tok != Token::Eof {
+            self.pos += 1;
+        }
+        tok
+    }
+
+    fn at_op(&self, op: &str) -> bool {
+        matches!(self.peek(), Token::Op(found) if *found == op)
+    }
+
+    fn loc(&self) -> String {
+        format!("{}:{}", self.file, self.tokens[self.pos].1)
+    }
+
+    fn unexpected<T>(&self, expected: &str) -> Result<T> {
+        bail!("{}: expected {expected} but found {}", self.loc(), self.peek())
+    }
+
+    fn expect_op(&mut self, op: &str) -> Result<()> {
+        if self.at_op(op) {
+            self.bump();
+            Ok(())
+        } else {
+            self.unexpected(&format!("'{op}'"))
+        }
+    }
+
+    fn expect_ident(&mut self) -> Result<String> {
+        match self.peek() {
+            Token::Ident(ident) => {
+                let ident = ident.clone();
+                self.bump();
+                Ok(ident)
+            }
+            _ => self.unexpected("an identifier"),
+        }
+    }
+
+    fn expect_str(&mut self) -> Result<String> {
+        match self.peek() {
+            Token::Str(str) => {
+                let str = str.clone();
+                self.bump();
+                Ok(str)
+            }
+            _ => self.unexpected("a string"),
+        }
+    }
+
+    /// Node names can be identifiers or numbers
+    fn eat_node(&mut self) -> Option<String> {
+        match self.peek() {
+            Token::Ident(name) | Token::Num(_, name) => {
+                let name = name.clone();
+                self.bump();
+                Some(name)
+            }
+            _ => None,
+        }
+    }
+
+    fn expect_eol(&mut self) -> Result<()> {
+        match self.peek() {
+            Token::Newline => {
+                self.bump();
+                Ok(())
+            }
+            Token::Eof => Ok(()),
+            _ => self.unexpected("end of line"),
+        }
+    }
+
+    fn skip_line(&mut self) {
+        while !matches!(self.bump(), Token::Newline | Token::Eof) {}
+    }
+
+    /// Skips a statement including any `{ ... }` block (like in statistics or sweep)
+    fn skip_stmt(&mut self) -> Result<()> {
+        while !matches!(self.peek(), Token::Newline | Token::Eof) {
+            if self.at_op("{") {
+                return self.skip_braces();
+            }
+            self.bump();
+        }
+        self.expect_eol()
+    }
+
+    fn skip_newlines(&mut self) {
+        while *self.peek() == Token::Newline {
+            self.bump();
+        }
+    }
+
+    /// Resolves a path relative to the directory of the file being parsed
+    fn resolve_path(&self, path: String) -> Utf8PathBuf {
+        let path = Utf8PathBuf::from(path);
+        match self.file.parent() {
+            Some(dir) if path.is_relative() => dir.join(path),
+            _ => path,
+        }
+    }
+
+    /// Parses statements until the keyword `end` (or the end of the file if `end` is `None`)
+    fn parse_block(&mut self, end: Option<&str>) -> Result<Vec<Stmt>> {
+        let mut stmts = Vec::new();
+        loop {
+            self.skip_newlines();
+            let kw = match self.peek() {
+                Token::Eof => match end {
+                    Some(end) => bail!("{}: missing '{end}'", self.loc()),
+                    None => break,
+                },
+                Token::Ident(kw) => kw.clone(),
+                _ => return self.unexpected("a statement"),
+            };
+
+            if Some(&*kw) == end {
+                // the name after ends/endsection is optional and not checked
+                self.skip_line();
+                break;
+            }
+
+            match &*kw {
+                "simulator" => {
+                    self.bump();
+                    self.parse_simulator()?;
+                }
+                "include" => {
+                    let loc = self.loc();
+                    self.bump();
+                    let path = self.expect_str()?;
+                    let path = self.resolve_path(path);
+                    let section = if matches!(self.peek(), Token::Ident(ident) if ident == "section")
+                    {
+                        self.bump();
+                        self.expect_op("=")?;
+                        Some(self.expect_ident()?)
+                    } else {
+                        None
+                    };
+                    self.expect_eol()?;
+                    stmts.push(Stmt::Include { path, section, loc })
+                }
+                "ahdl_include" => {
+                    self.bump();
+                    let path = self.expect_str()?;
+                    stmts.push(Stmt::AhdlInclude(self.resolve_path(path)));
+                    self.expect_eol()?;
+                }
+                "parameters" => {
+                    self.bump();
+                    let params = self.parse_params()?;
+                    self.expect_eol()?;
+                    stmts.push(Stmt::Parameters(params));
+                }
+                "model" => {
+                    let loc = self.loc();
+                    self.bump();
+                    let name = self.expect_ident()?;
+                    let master = self.expect_ident()?;
+                    let params = self.parse_params()?;
+                    self.expect_eol()?;
+                    stmts.push(Stmt::Model(Model { name, master, params, loc }))
+                }
+                "inline" if matches!(self.nth(1), Token::Ident(kw) if kw == "subckt") => {
+                    self.bump();
+                    stmts.push(Stmt::Subckt(self.parse_subckt()?));
+                }
+                "subckt" => stmts.push(Stmt::Subckt(self.parse_subckt()?)),
+                "section" => {
+                    self.bump();
+                    let name = self.expect_ident()?;
+                    self.expect_eol()?;
+                    let body = self.parse_block(Some("endsection"))?;
+                    stmts.push(Stmt::Section { name, body })
+                }
+                "global" => {
+                    self.bump();
+                    let mut nodes = Vec::new();
+                    while let Some(node) = self.eat_node() {
+                        nodes.push(node)
+                    }
+                    self.expect_eol()?;
+                    stmts.push(Stmt::Global(nodes));
+                }
+                // libraries only group sections
+                "library" | "endlibrary" | "save" | "ic" | "nodeset" => self.skip_line(),
+                "statistics" => self.skip_stmt()?,
+                "ends" | "endsection" => bail!("{}: unexpected '{kw}'", self.loc()),
+                "real" | "if" | "paramset" | "export" => {
+                    bail!("{}: '{kw}' statements are not supported", self.loc())
+                }
+                _ => {
+                    if let Some(inst) = self.parse_instance()? {
+                        stmts.push(Stmt::Instance(inst))
+                    }
+                }
+            }
+        }
+
+        Ok(stmts)
+    }
+
+    fn parse_simulator(&mut self) -> Result<()> {
+        while let Token::Ident(name) = self.peek() {
+            let name = name.clone();
+            self.bump();
+            self.expect_op("=")?;
+            let val = self.expect_ident()?;
+            if name == "lang" && val != "spectre" {
+                bail!("{}: only spectre syntax is supported (found lang={val})", self.loc())
+            }
+        }
+        self.expect_eol()
+    }
+
+    fn parse_subckt(&mut self) -> Result<Subckt> {
+        self.bump();
+        let name = self.expect_ident()?;
+        let parens = self.at_op("(");
+        if parens {
+            self.bump();
+        }
+        let mut ports = Vec::new();
+        while let Some(port) = self.eat_node() {
+            ports.push(port)
+        }
+        if parens {
+            self.expect_op(")")?;
+        }
+        self.expect_eol()?;
+        let body = self.parse_block(Some("ends"))?;
+        Ok(Subckt { name, ports, body })
+    }
+
+    /// Parses `name [(] node1 ... nodeN [)] master [param=value ...]`.
+    /// Returns `None` for analyses, which are skipped.
+    fn parse_instance(&mut self) -> Result<Option<Instance>> {
+        let loc = self.loc();
+        let name = self.expect_ident()?;
+        let (nodes, master) = if self.at_op("(") {
+            self.bump();
+            let mut nodes = Vec::new();
+            while let Some(node) = self.eat_node() {
+                nodes.push(node)
+            }
+            self.expect_op(")")?;
+            (nodes, self.expect_ident()?)
+        } else {
+            // without parenthesis the last name before the parameters is the master
+            let mut nodes = Vec::new();
+            while self.nth(1) != &Token::Op("=") {
+                match self.eat_node() {
+                    Some(node) => nodes.push(node),
+                    None => break,
+                }
+            }
+            match nodes.pop() {
+                Some(master) => (nodes, master),
+                None => return self.unexpected("a master name"),
+            }
+        };
+
+        if ANALYSES.contains(&&*master) {
+            warn!("{loc}: ignoring {master} statement '{name}'");
+            self.skip_stmt()?;
+            return Ok(None);
+        }
+
+        let params = self.parse_params()?;
+        self.expect_eol()?;
+        Ok(Some(Instance { name, nodes, master, params, loc }))
+    }
+
+    fn parse_params(&mut self) -> Result<Params> {
+        let mut params = Vec::new();
+        while let Token::Ident(name) = self.peek() {
+            let name = name.clone();
+            self.bump();
+            self.expect_op("=")?;
+            params.push((name, self.parse_expr()?));
+        }
+        Ok(params)
+    }
+
+    fn skip_braces(&mut self) -> Result<()> {
+        let mut depth = 0;
+        loop {
+            match self.bump() {
+                Token::Op("{") => depth += 1,
+                Token::Op("}") => {
+                    depth -= 1;
+                    if depth == 0 {
+                        break;
+                    }
+                }
+                Token::Eof => bail!("{}: missing '}}'", self.loc()),
+                _ => (),
+            }
+        }
+        self.expect_eol()
+    }
+
+    fn parse_expr(&mut self) -> Result<AstExpr> {
+        let cond = self.parse_binary(0)?;
+        if !self.at_op("?") {
+            return Ok(cond);
+        }
+        self.bump();
+        let then_val = self.parse_expr()?;
+        self.expect_op(":")?;
+        let else_val = self.parse_expr()?;
+        Ok(AstExpr::Cond(Box::new(cond), Box::new(then_val), Box::new(else_val)))
+    }
+
+    fn parse_binary(&mut self, min_bp: u8) -> Result<AstExpr> {
+        let mut lhs = self.parse_unary()?;
+        loop {
+            let op = match *self.peek() {
+                Token::Op(op) => op,
+                _ => break,
+            };
+            let bp = match op {
+                "||" => 1,
+                "&&" => 2,
+                "|" => 3,
+                "^" => 4,
+                "&" => 5,
+                "==" | "!=" => 6,
+                "<" | "<=" | ">" | ">=" => 7,
+                "<<" | ">>" => 8,
+                "+" | "-" => 9,
+                "*" | "/" | "%" => 10,
+                "**" => 11,
+                _ => break,
+            };
+            if bp <= min_bp {
+                break;
+            }
+            self.bump();
+            // ** is right associative
+            let rhs = self.parse_binary(if op == "**" { bp - 1 } else { bp })?;
+            lhs = AstExpr::Binary(op, Box::new(lhs), Box::new(rhs));
+        }
+        Ok(lhs)
+    }
+
+    fn parse_unary(&mut self) -> Result<AstExpr> {
+        let expr = match self.peek().clone() {
+            Token::Op(op @ ("-" | "+" | "!" | "~")) => {
+                self.bump();
+                // unary operators bind stronger than any binary operator except **
+                let arg = self.parse_binary(10)?;
+                AstExpr::Unary(op, Box::new(arg))
+            }
+            Token::Op("(") => {
+                self.bump();
+                let expr = self.parse_expr()?;
+                self.expect_op(")")?;
+                expr
+            }
+            Token::Op("[") => bail!("{}: vector values are not supported", self.loc()),
+            Token::Num(val, _) => {
+                self.bump();
+                AstExpr::Num(val)
+            }
+            Token::Str(str) => {
+                self.bump();
+                AstExpr::Str(str)
+            }
+            Token::Ident(name) => {
+                self.bump();
+                if !self.at_op("(") {
+                    return Ok(AstExpr::Ident(name));
+                }
+                self.bump();
+                let mut args = Vec::new();
+                if !self.at_op(")") {
+                    args.push(self.parse_expr()?);
+                    while self.at_op(",") {
+                        self.bump();
+                        args.push(self.parse_expr()?);
+                    }
+                }
+                self.expect_op(")")?;
+                AstExpr::Call(name, args)
+            }
+            _ => return self.unexpected("an expression"),
+        };
+        Ok(expr)
+    }
+}
@@ -0,0 +1,154 @@
+use camino::Utf8Path;
+
+use crate::elaboration::CircuitDescription;
+use crate::spectre::lexer::{tokenize, Token};
+use crate::spectre::parser::parse_str;
+use crate::spectre::{expand, lower};
+use crate::{Arena, CircuitParam, Expr, ExprEvalCtx, Value};
+
+fn lower_str(src: &str, section: Option<&str>, arena: &mut Arena) -> CircuitDescription {
+    let stmts = parse_str(src, Utf8Path::new("test.scs")).unwrap();
+    let mut expanded = Vec::new();
+    let mut found = false;
+    expand(stmts, section, 0, &mut found, &mut expanded).unwrap();
+    let ctx = arena.add_ctx();
+    lower("test".to_owned(), &expanded, arena, ctx).unwrap()
+}
+
+/// Evaluates all parameters of `descr` and returns the value of `name`
+fn eval_param(descr: &CircuitDescription, arena: &Arena, name: &str) -> f64 {
+    let mut ctx = ExprEvalCtx::new(arena);
+    ctx.set_param(CircuitParam::TEMPERATURE, 300.15.into());
+    for &(param, val) in &descr.parameters {
+        let val = val.eval(ctx.borrow()).unwrap();
+        ctx.set_param(param, val);
+    }
+    let (param, _) = arena.lookup_param_by_name(descr.ctx, name).unwrap();
+    ctx[param].to_num().unwrap()
+}
+
+#[test]
+fn tokens() {
+    let src = "* comment\nR1 (a 0) resistor r=1.5k \\\n  w=2e-6 // comment\n\"str\" vdd! a!=b";
+    let tokens: Vec<_> =
+        tokenize(src, Utf8Path::new("test.scs")).unwrap().into_iter().map(|(tok, _)| tok).collect();
+    let ident = |name: &str| Token::Ident(name.to_owned());
+    let expected = [
+        ident("R1"),
+        Token::Op("("),
+        ident("a"),
+        Token::Num(0.0, "0".to_owned()),
+        Token::Op(")"),
+        ident("resistor"),
+        ident("r"),
+        Token::Op("="),
+        Token::Num(1500.0, "1.5k".to_owned()),
+        ident("w"),
+        Token::Op("="),
+        Token::Num(2e-6, "2e-6".to_owned()),
+        Token::Newline,
+        Token::Str("str".to_owned()),
+        ident("vdd!"),
+        ident("a"),
+        Token::Op("!="),
+        ident("b"),
+        Token::Newline,
+        Token::Eof,
+    ];
+    assert_eq!(tokens, expected);
+}
+
+#[test]
+fn instances() {
+    let src = r#"
+simulator lang=spectre
+model nmos bsim4 type=1 vth0=0.4
+v1 (vdd 0) vsource dc=1.8
+m1 (d g 0 0) nmos w=1u l=100n
+r1 d vdd resistor r=1k
+dc1 dc param=temp start=0 stop=100
+"#;
+    let mut arena = Arena::new();
+    let descr = lower_str(src, None, &mut arena);
+
+    assert_eq!(descr.models.len(), 1);
+    assert_eq!(descr.models.raw[0].name, "nmos");
+    assert_eq!(descr.models.raw[0].device, "bsim4");
+
+    let instances: Vec<_> = descr
+        .instances
+        .iter()
+        .map(|inst| (&*inst.name, &*inst.master, inst.terminal_connections.join(" ")))
+        .collect();
+    assert_eq!(
+        instances,
+        [
+            ("v1", "vsource", "vdd ground".to_owned()),
+            ("m1", "nmos", "d g ground ground".to_owned()),
+            ("r1", "resistor", "d vdd".to_owned())
+        ]
+    );
+    assert_eq!(
+        descr.instances.raw[1].parameters,
+        [("w".to_owned(), Expr::from(1e-6)), ("l".to_owned(), Expr::from(100.0 * 1e-9))]
+    );
+}
+
+#[test]
+fn expressions() {
+    let src = r#"
+parameters a=2 b=a*3+1 c=-(2**3) d=max(a, 1) > 1 ? 10 : 20 e=7/2-1
+parameters f=temp g=log10(100)
+"#;
+    let mut arena = Arena::new();
+    let descr = lower_str(src, None, &mut arena);
+    assert_eq!(eval_param(&descr, &arena, "b"), 7.0);
+    assert_eq!(eval_param(&descr, &arena, "c"), -8.0);
+    assert_eq!(eval_param(&descr, &arena, "d"), 10.0);
+    assert_eq!(eval_param(&descr, &arena, "e"), 2.5);
+    assert!((eval_param(&descr, &arena, "f") - 27.0).abs() < 1e-9);
+    assert_eq!(eval_param(&descr, &arena, "g"), 2.0);
+}
+
+#[test]
+fn subckt() {
+    let src = r#"
+subckt inv (in out vdd)
+parameters wn=1u wp=2*wn
+mp (out in vdd vdd) pch w=wp
+mn (out in mid 0) nch w=wn
+ends inv
+x1 (a b supply) inv wn=2u
+"#;
+    let mut arena = Arena::new();
+    let descr = lower_str(src, None, &mut arena);
+    let instances: Vec<_> = descr
+        .instances
+        .iter()
+        .map(|inst| (&*inst.name, inst.terminal_connections.join(" ")))
+        .collect();
+    assert_eq!(
+        instances,
+        [("x1.mp", "b a supply supply".to_owned()), ("x1.mn", "b a x1.mid ground".to_owned())]
+    );
+    assert_eq!(eval_param(&descr, &arena, "x1.wn"), 2e-6);
+    assert_eq!(eval_param(&descr, &arena, "x1.wp"), 4e-6);
+}
+
+#[test]
+fn sections() {
+    let src = r#"
+library models
+section tt
+parameters corner=0
+endsection tt
+section ff
+parameters corner=1
+endsection ff
+endlibrary
+"#;
+    let mut arena = Arena::new();
+    let descr = lower_str(src, Some("ff"), &mut arena);
+    assert_eq!(descr.parameters.len(), 1);
+    assert_eq!(descr.parameters[0].1, Expr::Value(Value::Num(1.0)));
+}
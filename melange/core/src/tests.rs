@@ -1,11 +1,17 @@
+use std::f64::consts::PI;
+
 use anyhow::Result;
 use camino::Utf8PathBuf;
+use num_complex::Complex64;
+use openvaf::CorrelatedNoise;
 use stdx::project_root;
 
 use crate::expr::CircuitParam;
-use crate::simulation::SimConfig;
+use crate::simulation::{
+    ConvergenceFailure, HbSolver, Homotopy, InstanceNodes, SimConfig, Simulation, Sweep, SweepVar,
+};
 use crate::utils::PrettyPrint;
-use crate::{veriloga, Arena, Circuit, ExprEvalCtx};
+use crate::{veriloga, Arena, Circuit, Expr, ExprEvalCtx};
 
 const ATOL: f64 = 1e-9;
 const RTOL: f64 = 1e-2;
@@ -16,7 +22,7 @@ fn approx_eq(val: f64, ref_val: f64) -> bool {
 macro_rules! assert_approx_eq {
     ($val: expr, $ref: expr, $($fmt: tt)+) => {{
         if !approx_eq($val, $ref) {
-            panic!("assertion failed (left == right): {}\n left: {}\n right: {}", format_args!($($fmt)*),$val.pretty_str(), $refl.pretty_str())
+            panic!("assertion failed (left == right): {}\n left: {}\n right: {}", format_args!($($fmt)*),$val.pretty_str(), $ref.pretty_str())
         }
     }};
     ($val: expr, $ref: expr) => {{
@@ -28,8 +34,8 @@ macro_rules! assert_approx_eq {
 
 macro_rules! assert_approx_eq_cmplx {
     ($val: expr, $ref_real: literal + j $ref_imag: literal, $($fmt: tt)+) => {{
-        if !approx_eq($val.re, $ref_real) || !approx_eq($val.im, $ref_imag) ||  {
-            panic!("assertion failed (left == right): {}\n left: {}\n right: {}", format_args!($($fmt)*),$vall.pretty_str(), Complex64::new($ref_real, $ref_imag).pretty_str())
+        if !approx_eq($val.re, $ref_real) || !approx_eq($val.im, $ref_imag) {
+            panic!("assertion failed (left == right): {}\n left: {}\n right: {}", format_args!($($fmt)*),$val.pretty_str(), Complex64::new($ref_real, $ref_imag).pretty_str())
         }
     }};
 
@@ -41,7 +47,7 @@ macro_rules! assert_approx_eq_cmplx {
 
     ($val: expr, $ref_real: literal - j $ref_imag: literal, $($fmt: tt)+) => {{
         if !approx_eq($val.re, $ref_real) || !approx_eq($val.im, -$ref_imag) {
-            panic!("assertion failed (left == right): {}\n left: {}\n right: {}", format_args!($($fmt)*),$vall.pretty_str(), Complex64::new($ref_real, - $ref_imag).pretty_str())
+            panic!("assertion failed (left == right): {}\n left: {}\n right: {}", format_args!($($fmt)*),$val.pretty_str(), Complex64::new($ref_real, - $ref_imag).pretty_str())
         }
     }};
 
@@ -137,3 +143,872 @@ fn veriloga() -> Result<()> {
 
     Ok(())
 }
+
+/// Current through a diode with limiting (and a series resistance) that is biased with 5V.
+fn diode_lim_current(opts: &veriloga::Opts) -> Result<f64> {
+    let mut arena = Arena::new();
+    let mut circ = Circuit::new("test_circ".to_owned(), &mut arena);
+
+    let gnd = circ.lookup_node("ground").expect("ground node");
+    let node_x = circ.node("X".to_owned());
+    let node_t = circ.node("T".to_owned());
+
+    let path = Utf8PathBuf::from_path_buf(project_root())
+        .expect("only utf8 paths are supported")
+        .join("openvaf")
+        .join("test_data")
+        .join("osdi")
+        .join("diode_lim.va");
+    circ.load_veriloga_file(path, opts)?;
+
+    let (vsrc1, _) =
+        circ.new_device_instance_by_name("vsrc1".to_owned(), "vsource", vec![node_x, gnd])?;
+    circ.set_instance_param(vsrc1, "dc", 5.0.into())?;
+
+    let (_, diode1) = circ.new_device_instance_by_name(
+        "diode1".to_owned(),
+        "diode_va",
+        vec![node_x, gnd, node_t],
+    )?;
+    circ.set_model_param(diode1, "rs", 5f64.into())?;
+    circ.set_model_param(diode1, "is", 1e-13.into())?;
+
+    let mut ctx = ExprEvalCtx::new(&arena);
+    ctx.set_param(CircuitParam::TEMPERATURE, 300.15.into());
+
+    let mut sim = circ.prepare_simulation(ctx.borrow(), &arena, SimConfig::default())?;
+    let curr = sim.dc_lead_current(vsrc1)?[0];
+    Ok(curr)
+}
+
+#[test]
+fn veriloga_limiting() -> Result<()> {
+    // exp(5V / vt) overflows without pnjlim
+    let curr = diode_lim_current(&veriloga::Opts::default())?;
+    assert_approx_eq!(curr, -0.846);
+    Ok(())
+}
+
+#[test]
+fn veriloga_jit() -> Result<()> {
+    // the JIT has to expose the same symbols (including the limit table) as the shared library
+    let curr = diode_lim_current(&veriloga::Opts { jit: true, ..veriloga::Opts::default() })?;
+    assert_approx_eq!(curr, -0.846);
+    assert_approx_eq!(curr, diode_lim_current(&veriloga::Opts::default())?);
+    Ok(())
+}
+
+#[test]
+fn veriloga_simparam() -> Result<()> {
+    let mut arena = Arena::new();
+    let mut circ = Circuit::new("test_circ".to_owned(), &mut arena);
+
+    let gnd = circ.lookup_node("ground").expect("ground node");
+    let node_x = circ.node("X".to_owned());
+
+    let path = Utf8PathBuf::from_path_buf(project_root())
+        .expect("only utf8 paths are supported")
+        .join("integration_tests")
+        .join("DIODE")
+        .join("diode.va");
+    circ.load_veriloga_file(path, &veriloga::Opts::default())?;
+
+    let (vsrc1, _) =
+        circ.new_device_instance_by_name("vsrc1".to_owned(), "vsource", vec![node_x, gnd])?;
+    circ.set_instance_param(vsrc1, "dc", (-1.0).into())?;
+
+    let (_, diode1) =
+        circ.new_device_instance_by_name("diode1".to_owned(), "diode_va", vec![node_x, gnd])?;
+    circ.set_model_param(diode1, "rs", 5f64.into())?;
+
+    let mut ctx = ExprEvalCtx::new(&arena);
+    ctx.set_param(CircuitParam::TEMPERATURE, 300.15.into());
+
+    // in reverse bias the current is dominated by gmin
+    let mut config = SimConfig::default();
+    config.sim_params.real.insert("gmin".to_owned(), 1e-3);
+    let mut sim = circ.prepare_simulation(ctx.borrow(), &arena, config)?;
+    let curr = sim.dc_lead_current(vsrc1)?[0];
+    assert_approx_eq!(curr, 0.995e-3);
+
+    sim.config.sim_params.real.insert("gmin".to_owned(), 1e-6);
+    sim.prepare_solver(ctx.borrow(), &arena)?;
+    let curr = sim.dc_lead_current(vsrc1)?[0];
+    assert_approx_eq!(curr, 1e-6);
+
+    Ok(())
+}
+
+#[test]
+fn veriloga_simparam_str() -> Result<()> {
+    let mut arena = Arena::new();
+    let mut circ = Circuit::new("test_circ".to_owned(), &mut arena);
+
+    let gnd = circ.lookup_node("ground").expect("ground node");
+    let node_x = circ.node("X".to_owned());
+
+    let path = Utf8PathBuf::from_path_buf(project_root())
+        .expect("only utf8 paths are supported")
+        .join("openvaf")
+        .join("test_data")
+        .join("osdi")
+        .join("simparam.va");
+    circ.load_veriloga_file(path, &veriloga::Opts::default())?;
+
+    let (vsrc1, _) =
+        circ.new_device_instance_by_name("vsrc1".to_owned(), "vsource", vec![node_x, gnd])?;
+    circ.set_instance_param(vsrc1, "dc", 1f64.into())?;
+    circ.new_device_instance_by_name("res1".to_owned(), "simparam_res", vec![node_x, gnd])?;
+
+    let mut ctx = ExprEvalCtx::new(&arena);
+    ctx.set_param(CircuitParam::TEMPERATURE, 300.15.into());
+
+    // "gextra" is not set so its default is used, the default gmin is negligible
+    let mut config = SimConfig::default();
+    config.sim_params.string.insert("mode".to_owned(), "single".to_owned());
+    let mut sim = circ.prepare_simulation(ctx.borrow(), &arena, config)?;
+    let curr = sim.dc_lead_current(vsrc1)?[0];
+    assert_approx_eq!(curr, -1e-3);
+
+    sim.config.sim_params.real.insert("gextra".to_owned(), 2e-3);
+    sim.config.sim_params.real.insert("gmin".to_owned(), 1e-3);
+    sim.config.sim_params.string.insert("mode".to_owned(), "double".to_owned());
+    sim.prepare_solver(ctx.borrow(), &arena)?;
+    let curr = sim.dc_lead_current(vsrc1)?[0];
+    assert_approx_eq!(curr, -6e-3);
+
+    // $simparam$str without a default fails for unknown names
+    sim.config.sim_params.string.clear();
+    sim.prepare_solver(ctx.borrow(), &arena)?;
+    assert!(sim.dc_op().is_err());
+
+    Ok(())
+}
+
+/// Loads `convergence.va` that contains devices which are hard to solve with a plain Newton
+/// iteration.
+fn load_convergence_devices(circ: &mut Circuit) -> Result<()> {
+    let path = Utf8PathBuf::from_path_buf(project_root())
+        .expect("only utf8 paths are supported")
+        .join("openvaf")
+        .join("test_data")
+        .join("osdi")
+        .join("convergence.va");
+    circ.load_veriloga_file(path, &veriloga::Opts::default())?;
+    Ok(())
+}
+
+/// Returns the error of a simulation that must fail to find the operating point.
+fn convergence_failure(sim: &mut Simulation) -> anyhow::Error {
+    match sim.dc_op() {
+        Ok(solution) => panic!("found an operating point {:?}", solution),
+        Err(err) => {
+            assert!(err.downcast_ref::<ConvergenceFailure>().is_some(), "{err:?}");
+            err
+        }
+    }
+}
+
+#[test]
+fn homotopy() -> Result<()> {
+    let mut arena = Arena::new();
+    let mut circ = Circuit::new("test_circ".to_owned(), &mut arena);
+    load_convergence_devices(&mut circ)?;
+
+    // 1mA driven into a cubic conductor, its conductance (and therefore the jacobian) is zero
+    // at the initial guess
+    let gnd = circ.lookup_node("ground").expect("ground node");
+    let x = circ.node("X".to_owned());
+    let (isrc1, _) =
+        circ.new_device_instance_by_name("isrc1".to_owned(), "isource", vec![gnd, x])?;
+    circ.set_instance_param(isrc1, "dc", 1e-3.into())?;
+    circ.new_device_instance_by_name("cubic1".to_owned(), "cubic_conductor", vec![x, gnd])?;
+
+    let mut ctx = ExprEvalCtx::new(&arena);
+    ctx.set_param(CircuitParam::TEMPERATURE, 300.0.into());
+
+    let config = SimConfig { homotopy: Vec::new(), ..SimConfig::default() };
+    let mut sim = circ.prepare_simulation(ctx.borrow(), &arena, config)?;
+    let err = convergence_failure(&mut sim);
+    assert!(err.downcast_ref::<ConvergenceFailure>().unwrap().homotopy.is_empty());
+
+    for homotopy in [Homotopy::GminStepping, Homotopy::PseudoTransient] {
+        let config = SimConfig { homotopy: vec![homotopy], ..SimConfig::default() };
+        let mut sim = circ.prepare_simulation(ctx.borrow(), &arena, config)?;
+        let solution = sim.dc_op()?;
+        assert_approx_eq!(solution[x], 1.0, "{homotopy}");
+    }
+
+    // source stepping starts with all sources turned off where the jacobian is still singular
+    let config = SimConfig { homotopy: vec![Homotopy::SourceStepping], ..SimConfig::default() };
+    let mut sim = circ.prepare_simulation(ctx.borrow(), &arena, config)?;
+    let err = convergence_failure(&mut sim);
+    let failure = err.downcast_ref::<ConvergenceFailure>().unwrap();
+    assert_eq!(failure.homotopy, [Homotopy::SourceStepping]);
+    assert!(err.to_string().contains("convergence aids that failed: source stepping"), "{err}");
+
+    // the default convergence aids find the operating point
+    let mut sim = circ.prepare_simulation(ctx.borrow(), &arena, SimConfig::default())?;
+    let solution = sim.dc_op()?;
+    assert_approx_eq!(solution[x], 1.0);
+
+    Ok(())
+}
+
+const DIODE_IS: f64 = 1e-14;
+const DIODE_VT: f64 = 0.025;
+
+/// Solves the operating point of an `exp_diode` that is forward biased by the voltage source
+/// `bias` through a 1 kOhm resistor. The diode current must match the resistor current.
+fn solve_exp_diode(bias: f64, homotopy: Vec<Homotopy>) -> Result<f64> {
+    let mut arena = Arena::new();
+    let mut circ = Circuit::new("test_circ".to_owned(), &mut arena);
+    load_convergence_devices(&mut circ)?;
+
+    let gnd = circ.lookup_node("ground").expect("ground node");
+    let [x, y] = ["X", "Y"].map(|name| circ.node(name.to_owned()));
+    let (vsrc1, _) =
+        circ.new_device_instance_by_name("vsrc1".to_owned(), "vsource", vec![y, gnd])?;
+    circ.set_instance_param(vsrc1, "dc", bias.into())?;
+    let (res1, _) = circ.new_device_instance_by_name("res1".to_owned(), "resistor", vec![y, x])?;
+    circ.set_instance_param(res1, "r", 1e3.into())?;
+    let terminals = if bias > 0.0 { vec![x, gnd] } else { vec![gnd, x] };
+    circ.new_device_instance_by_name("diode1".to_owned(), "exp_diode", terminals)?;
+
+    let mut ctx = ExprEvalCtx::new(&arena);
+    ctx.set_param(CircuitParam::TEMPERATURE, 300.0.into());
+    let config = SimConfig { homotopy, ..SimConfig::default() };
+    let mut sim = circ.prepare_simulation(ctx.borrow(), &arena, config)?;
+    let vx = sim.dc_op()?[x];
+
+    let diode_curr = DIODE_IS * ((vx.abs() / DIODE_VT).exp() - 1.0);
+    let res_curr = (bias - vx).abs() / 1e3;
+    assert_approx_eq!(diode_curr, res_curr, "bias = {bias}");
+    Ok(vx)
+}
+
+/// The solution must be accepted only once it converged, independent of whether it approaches
+/// the final value from above (positive bias) or from below (negative bias).
+#[test]
+fn newton_acceptance() -> Result<()> {
+    let vx = solve_exp_diode(1.0, Vec::new())?;
+    assert!(vx > 0.5 && vx < 0.7, "{vx}");
+    let vx_neg = solve_exp_diode(-1.0, Vec::new())?;
+    assert_approx_eq!(vx_neg, -vx);
+    Ok(())
+}
+
+#[test]
+fn source_stepping() -> Result<()> {
+    for bias in [20.0, -20.0] {
+        // the first Newton step sets the diode voltage to the bias and exp(20V / vt) overflows
+        assert!(solve_exp_diode(bias, Vec::new()).is_err());
+        let vx = solve_exp_diode(bias, vec![Homotopy::SourceStepping])?;
+        assert!(vx.abs() > 0.6 && vx.abs() < 0.8, "{vx}");
+    }
+    Ok(())
+}
+
+#[test]
+fn max_node_step() -> Result<()> {
+    let mut arena = Arena::new();
+    let mut circ = Circuit::new("test_circ".to_owned(), &mut arena);
+
+    let gnd = circ.lookup_node("ground").expect("ground node");
+    let x = circ.node("X".to_owned());
+    let (vsrc1, _) =
+        circ.new_device_instance_by_name("vsrc1".to_owned(), "vsource", vec![x, gnd])?;
+    circ.set_instance_param(vsrc1, "dc", 1f64.into())?;
+    let (res1, _) =
+        circ.new_device_instance_by_name("res1".to_owned(), "resistor", vec![x, gnd])?;
+    circ.set_instance_param(res1, "r", 1e3.into())?;
+
+    let mut ctx = ExprEvalCtx::new(&arena);
+    ctx.set_param(CircuitParam::TEMPERATURE, 300.0.into());
+
+    // V(X) can only change by 100mV per iteration
+    let mut config = SimConfig { maxiters: 5, homotopy: Vec::new(), ..SimConfig::default() };
+    config.max_node_step.insert("X".to_owned(), 0.1);
+    let mut sim = circ.prepare_simulation(ctx.borrow(), &arena, config)?;
+    let err = convergence_failure(&mut sim);
+    let failure = err.downcast_ref::<ConvergenceFailure>().unwrap();
+    assert!(!failure.singular);
+    assert_eq!(failure.iterations, 5);
+    assert_eq!(failure.unknowns.len(), 1, "{err}");
+    assert_eq!(failure.unknowns[0].name, "X");
+    assert_approx_eq!(failure.unknowns[0].value, 0.5);
+    assert_approx_eq!(failure.unknowns[0].delta, -0.1);
+    assert_eq!(failure.instances, ["vsrc1", "res1"]);
+    assert!(err.to_string().contains("connected instances: vsrc1, res1"), "{err}");
+
+    let mut config = SimConfig { maxiters: 20, homotopy: Vec::new(), ..SimConfig::default() };
+    config.max_node_step.insert("X".to_owned(), 0.1);
+    let mut sim = circ.prepare_simulation(ctx.borrow(), &arena, config)?;
+    let solution = sim.dc_op()?;
+    assert_approx_eq!(solution[x], 1.0);
+
+    Ok(())
+}
+
+#[test]
+fn dc_sweep() -> Result<()> {
+    let mut arena = Arena::new();
+    let mut circ = Circuit::new("test_circ".to_owned(), &mut arena);
+
+    let gnd = circ.lookup_node("ground").expect("ground node");
+    let node_x = circ.node("X".to_owned());
+    let (r_param, r) = arena.def_param(circ.ctx, "r".to_owned())?;
+
+    let (vsrc1, _) =
+        circ.new_device_instance_by_name("vsrc1".to_owned(), "vsource", vec![node_x, gnd])?;
+    circ.set_instance_param(vsrc1, "dc", 1f64.into())?;
+
+    let (res1, _) =
+        circ.new_device_instance_by_name("res1".to_owned(), "resistor", vec![node_x, gnd])?;
+    circ.set_instance_param(res1, "r", r)?;
+
+    let mut ctx = ExprEvalCtx::new(&arena);
+    ctx.set_param(CircuitParam::TEMPERATURE, 300.0.into());
+    ctx.set_param(r_param, 1e3.into());
+    let mut sim = circ.prepare_simulation(ctx.borrow(), &arena, SimConfig::default())?;
+
+    let sweeps = [
+        Sweep::new(SweepVar::Param(r_param), vec![1e3, 2e3]),
+        Sweep::linear(SweepVar::Source(vsrc1), 0.0, 1.0, 3),
+    ];
+    let res = sim.dc_sweep(&sweeps, ctx.borrow(), &arena)?;
+    assert_eq!(res.rows.len(), 6);
+    assert_eq!(res.column("vsrc1.dc").unwrap(), [0.0, 0.5, 1.0, 0.0, 0.5, 1.0]);
+    let voltages = res.column("V(X)").unwrap();
+    let currents = res.column("I(vsrc1:A)").unwrap();
+    for (i, &r) in res.column("r").unwrap().iter().enumerate() {
+        assert_approx_eq!(voltages[i], res.rows[i][1]);
+        assert_approx_eq!(currents[i], -voltages[i] / r);
+    }
+
+    // the source values of an outer sweep are kept when an inner parameter sweep prepares
+    // the solver again
+    let sweeps = [
+        Sweep::linear(SweepVar::Source(vsrc1), 0.0, 1.0, 3),
+        Sweep::new(SweepVar::Param(r_param), vec![1e3, 2e3]),
+    ];
+    let res = sim.dc_sweep(&sweeps, ctx.borrow(), &arena)?;
+    assert_eq!(res.rows.len(), 6);
+    assert_eq!(res.column("r").unwrap(), [1e3, 2e3, 1e3, 2e3, 1e3, 2e3]);
+    let voltages = res.column("V(X)").unwrap();
+    let currents = res.column("I(vsrc1:A)").unwrap();
+    let resistances = res.column("r").unwrap();
+    for (i, &vdc) in res.column("vsrc1.dc").unwrap().iter().enumerate() {
+        assert_approx_eq!(voltages[i], vdc);
+        assert_approx_eq!(currents[i], -vdc / resistances[i]);
+    }
+
+    // the original parameters are restored after the sweep
+    let curr = sim.dc_lead_current(vsrc1)?[0];
+    assert_approx_eq!(curr, -1e-3);
+
+    Ok(())
+}
+
+#[test]
+fn dc_sweep_restores_sources() -> Result<()> {
+    let mut arena = Arena::new();
+    let mut circ = Circuit::new("test_circ".to_owned(), &mut arena);
+
+    let gnd = circ.lookup_node("ground").expect("ground node");
+    let node_x = circ.node("X".to_owned());
+    let node_y = circ.node("Y".to_owned());
+
+    // neither source has an explicit dc value
+    let (vsrc1, _) =
+        circ.new_device_instance_by_name("vsrc1".to_owned(), "vsource", vec![node_x, gnd])?;
+    let (vsrc2, _) =
+        circ.new_device_instance_by_name("vsrc2".to_owned(), "vsource", vec![node_y, gnd])?;
+    circ.set_instance_param(vsrc2, "type", Expr::str(&mut arena, "pulse"))?;
+    circ.set_instance_param(vsrc2, "val0", 0.25.into())?;
+    circ.set_instance_param(vsrc2, "val1", 1.0.into())?;
+    for (name, node) in [("res1", node_x), ("res2", node_y)] {
+        let (res, _) =
+            circ.new_device_instance_by_name(name.to_owned(), "resistor", vec![node, gnd])?;
+        circ.set_instance_param(res, "r", 1e3.into())?;
+    }
+
+    let mut ctx = ExprEvalCtx::new(&arena);
+    ctx.set_param(CircuitParam::TEMPERATURE, 300.0.into());
+    let mut sim = circ.prepare_simulation(ctx.borrow(), &arena, SimConfig::default())?;
+
+    let sweeps = [
+        Sweep::new(SweepVar::Source(vsrc1), vec![0.5, 1.0]),
+        Sweep::new(SweepVar::Source(vsrc2), vec![2.0, 3.0]),
+    ];
+    let res = sim.dc_sweep(&sweeps, ctx.borrow(), &arena)?;
+    assert_eq!(res.column("vsrc2.dc").unwrap(), [2.0, 3.0, 2.0, 3.0]);
+    let (voltages_x, voltages_y) = (res.column("V(X)").unwrap(), res.column("V(Y)").unwrap());
+    for (i, &vdc) in res.column("vsrc1.dc").unwrap().iter().enumerate() {
+        assert_approx_eq!(voltages_x[i], vdc);
+        assert_approx_eq!(voltages_y[i], res.rows[i][1]);
+    }
+
+    // the dc source is back at 0 and the pulse source at its initial value
+    let solution = sim.dc_op()?;
+    assert_approx_eq!(solution[node_x], 0.0);
+    assert_approx_eq!(solution[node_y], 0.25);
+    assert_approx_eq!(sim.dc_lead_current(vsrc1)?[0], 0.0);
+    assert_approx_eq!(sim.dc_lead_current(vsrc2)?[0], -0.25e-3);
+
+    Ok(())
+}
+
+#[test]
+fn linear_devices() -> Result<()> {
+    let mut arena = Arena::new();
+    let mut circ = Circuit::new("test_circ".to_owned(), &mut arena);
+
+    let gnd = circ.lookup_node("ground").expect("ground node");
+    let [x, y, z, w, v, t, u, p] =
+        ["X", "Y", "Z", "W", "V", "T", "U", "P"].map(|name| circ.node(name.to_owned()));
+
+    // RC low pass
+    let (vsrc1, _) =
+        circ.new_device_instance_by_name("vsrc1".to_owned(), "vsource", vec![x, gnd])?;
+    circ.set_instance_param(vsrc1, "dc", 1f64.into())?;
+    circ.set_instance_param(vsrc1, "mag", 1f64.into())?;
+    let (res1, _) = circ.new_device_instance_by_name("res1".to_owned(), "resistor", vec![x, y])?;
+    circ.set_instance_param(res1, "r", 1e3.into())?;
+    let (cap1, _) =
+        circ.new_device_instance_by_name("cap1".to_owned(), "capacitor", vec![y, gnd])?;
+    circ.set_instance_param(cap1, "c", 1e-6.into())?;
+
+    // V(Z) = 2 * V(X)
+    let (vcvs1, _) =
+        circ.new_device_instance_by_name("vcvs1".to_owned(), "vcvs", vec![z, gnd, x, gnd])?;
+    circ.set_instance_param(vcvs1, "gain", 2f64.into())?;
+    let (res2, _) =
+        circ.new_device_instance_by_name("res2".to_owned(), "resistor", vec![z, gnd])?;
+    circ.set_instance_param(res2, "r", 1e3.into())?;
+
+    // 1mA through an inductor (short at DC) and the control input of a cccs
+    let (isrc1, _) =
+        circ.new_device_instance_by_name("isrc1".to_owned(), "isource", vec![gnd, w])?;
+    circ.set_instance_param(isrc1, "dc", 1e-3.into())?;
+    let (ind1, _) = circ.new_device_instance_by_name("ind1".to_owned(), "inductor", vec![w, v])?;
+    circ.set_instance_param(ind1, "l", 1e-3.into())?;
+    let (cccs1, _) =
+        circ.new_device_instance_by_name("cccs1".to_owned(), "cccs", vec![u, gnd, v, t])?;
+    circ.set_instance_param(cccs1, "gain", 2f64.into())?;
+    let (res3, _) =
+        circ.new_device_instance_by_name("res3".to_owned(), "resistor", vec![t, gnd])?;
+    circ.set_instance_param(res3, "r", 1e3.into())?;
+    let (res4, _) =
+        circ.new_device_instance_by_name("res4".to_owned(), "resistor", vec![u, gnd])?;
+    circ.set_instance_param(res4, "r", 1e3.into())?;
+
+    // without a dc value the operating point uses the value of the waveform at t = 0
+    let (vsrc2, _) =
+        circ.new_device_instance_by_name("vsrc2".to_owned(), "vsource", vec![p, gnd])?;
+    circ.set_instance_param(vsrc2, "type", Expr::str(&mut arena, "pulse"))?;
+    circ.set_instance_param(vsrc2, "val0", 0.5.into())?;
+    circ.set_instance_param(vsrc2, "val1", 1f64.into())?;
+    let (res5, _) =
+        circ.new_device_instance_by_name("res5".to_owned(), "resistor", vec![p, gnd])?;
+    circ.set_instance_param(res5, "r", 1e3.into())?;
+
+    let mut ctx = ExprEvalCtx::new(&arena);
+    ctx.set_param(CircuitParam::TEMPERATURE, 300.0.into());
+    let mut sim = circ.prepare_simulation(ctx.borrow(), &arena, SimConfig::default())?;
+    let solution = sim.dc_op()?;
+    assert_approx_eq!(solution[x], 1.0);
+    assert_approx_eq!(solution[y], 1.0);
+    assert_approx_eq!(solution[z], 2.0);
+    assert_approx_eq!(solution[w], 1.0);
+    assert_approx_eq!(solution[v], 1.0);
+    assert_approx_eq!(solution[t], 1.0);
+    assert_approx_eq!(solution[u], -2.0);
+    assert_approx_eq!(solution[p], 0.5);
+    assert_approx_eq!(sim.dc_lead_current(ind1)?[0], 1e-3);
+    assert_approx_eq!(sim.dc_lead_current(cccs1)?[2], 1e-3);
+
+    // the corner frequency of the low pass
+    sim.set_omega(1e3);
+    let solution = sim.ac()?;
+    assert_approx_eq_cmplx!(solution[y], 0.5 - j 0.5);
+    assert_approx_eq_cmplx!(solution[z], 2.0 + j 0.0);
+
+    Ok(())
+}
+
+#[test]
+fn coupled_and_controlled_devices() -> Result<()> {
+    let mut arena = Arena::new();
+    let mut circ = Circuit::new("test_circ".to_owned(), &mut arena);
+
+    let gnd = circ.lookup_node("ground").expect("ground node");
+    let [x, a, b, o, c, p, s, q] =
+        ["X", "A", "B", "O", "C", "P", "S", "Q"].map(|name| circ.node(name.to_owned()));
+    let mut resistor = |name: &str, node| -> Result<()> {
+        let (res, _) =
+            circ.new_device_instance_by_name(name.to_owned(), "resistor", vec![node, gnd])?;
+        circ.set_instance_param(res, "r", 1e3.into())
+    };
+    resistor("res2", b)?;
+    resistor("res3", o)?;
+    resistor("res4", p)?;
+    resistor("res5", q)?;
+
+    // transformer with a resistive load, the primary winding is driven through a resistor
+    let (vsrc1, _) =
+        circ.new_device_instance_by_name("vsrc1".to_owned(), "vsource", vec![x, gnd])?;
+    circ.set_instance_param(vsrc1, "dc", 1f64.into())?;
+    circ.set_instance_param(vsrc1, "mag", 1f64.into())?;
+    let (res1, _) = circ.new_device_instance_by_name("res1".to_owned(), "resistor", vec![x, a])?;
+    circ.set_instance_param(res1, "r", 1e3.into())?;
+    let (mut1, _) = circ.new_device_instance_by_name(
+        "mut1".to_owned(),
+        "mutual_inductor",
+        vec![a, gnd, b, gnd],
+    )?;
+    circ.set_instance_param(mut1, "l1", 1e-3.into())?;
+    circ.set_instance_param(mut1, "l2", 1e-3.into())?;
+    circ.set_instance_param(mut1, "k", 0.5.into())?;
+
+    // I(O, gnd) = 1mS * V(X)
+    let (vccs1, _) =
+        circ.new_device_instance_by_name("vccs1".to_owned(), "vccs", vec![o, gnd, x, gnd])?;
+    circ.set_instance_param(vccs1, "gm", 1e-3.into())?;
+
+    // V(P) = 500 Ohm * I(C, gnd)
+    let (isrc1, _) =
+        circ.new_device_instance_by_name("isrc1".to_owned(), "isource", vec![gnd, c])?;
+    circ.set_instance_param(isrc1, "dc", 2e-3.into())?;
+    circ.set_instance_param(isrc1, "mag", 2e-3.into())?;
+    let (ccvs1, _) =
+        circ.new_device_instance_by_name("ccvs1".to_owned(), "ccvs", vec![p, gnd, c, gnd])?;
+    circ.set_instance_param(ccvs1, "rm", 500f64.into())?;
+
+    // sources that are only active during AC analysis, the phase is given in radians
+    let (vsrc2, _) =
+        circ.new_device_instance_by_name("vsrc2".to_owned(), "vsource", vec![s, gnd])?;
+    circ.set_instance_param(vsrc2, "mag", 2f64.into())?;
+    circ.set_instance_param(vsrc2, "phase", (PI / 2.0).into())?;
+    let (isrc2, _) =
+        circ.new_device_instance_by_name("isrc2".to_owned(), "isource", vec![gnd, q])?;
+    circ.set_instance_param(isrc2, "mag", 1e-3.into())?;
+    circ.set_instance_param(isrc2, "phase", PI.into())?;
+
+    let mut ctx = ExprEvalCtx::new(&arena);
+    ctx.set_param(CircuitParam::TEMPERATURE, 300.0.into());
+    let mut sim = circ.prepare_simulation(ctx.borrow(), &arena, SimConfig::default())?;
+    let solution = sim.dc_op()?;
+    // the windings are shorts at DC
+    assert_approx_eq!(solution[a], 0.0);
+    assert_approx_eq!(solution[b], 0.0);
+    assert_approx_eq!(solution[o], -1.0);
+    assert_approx_eq!(solution[c], 0.0);
+    assert_approx_eq!(solution[p], 1.0);
+    assert_approx_eq!(solution[s], 0.0);
+    assert_approx_eq!(solution[q], 0.0);
+    let currents = sim.dc_lead_current(mut1)?;
+    assert_approx_eq!(currents[0], 1e-3);
+    assert_approx_eq!(currents[2], 0.0);
+    assert_approx_eq!(sim.dc_lead_current(vccs1)?[0], 1e-3);
+    assert_approx_eq!(sim.dc_lead_current(ccvs1)?[2], 2e-3);
+
+    // wL1 = wL2 = 1 kOhm and wM = 500 Ohm
+    sim.set_omega(1e6);
+    let solution = sim.ac()?;
+    assert_approx_eq_cmplx!(solution[x], 1.0 + j 0.0);
+    assert_approx_eq_cmplx!(solution[a], 0.446154 + j 0.430769);
+    assert_approx_eq_cmplx!(solution[b], 0.246154 + j 0.0307692);
+    assert_approx_eq_cmplx!(solution[o], -1.0 + j 0.0);
+    assert_approx_eq_cmplx!(solution[p], 1.0 + j 0.0);
+    assert_approx_eq_cmplx!(solution[s], 0.0 + j 2.0);
+    assert_approx_eq_cmplx!(solution[q], -1.0 + j 0.0);
+    let currents = sim.ac_lead_current(mut1)?;
+    assert_approx_eq_cmplx!(currents[0], 0.553846e-3 - j 0.430769e-3);
+    assert_approx_eq_cmplx!(currents[2], -0.246154e-3 - j 0.0307692e-3);
+
+    Ok(())
+}
+
+/// Solves the operating point of a single compact model (the first module in `va_file`) with
+/// every terminal driven by a voltage source (`bias`, missing values are 0 V). Returns the
+/// terminal currents and the node report of the instance.
+fn solve_biased_model(
+    va_file: &[&str],
+    bias: &[f64],
+    collapse_nodes: bool,
+) -> Result<(Vec<f64>, InstanceNodes)> {
+    let mut arena = Arena::new();
+    let mut circ = Circuit::new("test_circ".to_owned(), &mut arena);
+    let gnd = circ.lookup_node("ground").expect("ground node");
+
+    let mut path = Utf8PathBuf::from_path_buf(project_root())
+        .expect("only utf8 paths are supported")
+        .join("integration_tests");
+    for component in va_file {
+        path.push(component);
+    }
+    let devices = circ.load_veriloga_file(path, &veriloga::Opts::default())?;
+    let dev = devices[0];
+
+    let mut terminals = Vec::new();
+    let mut sources = Vec::new();
+    for i in 0..circ[dev].terminals.len() {
+        let node = circ.node(format!("T{i}"));
+        let (vsrc, _) =
+            circ.new_device_instance_by_name(format!("vsrc{i}"), "vsource", vec![node, gnd])?;
+        circ.set_instance_param(vsrc, "dc", bias.get(i).copied().unwrap_or(0.0).into())?;
+        terminals.push(node);
+        sources.push(vsrc);
+    }
+    let (dut, _) = circ.new_device_instance("dut".to_owned(), dev, terminals)?;
+
+    let mut ctx = ExprEvalCtx::new(&arena);
+    ctx.set_param(CircuitParam::TEMPERATURE, 300.15.into());
+    let config = SimConfig { collapse_nodes, ..SimConfig::default() };
+    let mut sim = circ.prepare_simulation(ctx.borrow(), &arena, config)?;
+    let mut currents = Vec::new();
+    for vsrc in sources {
+        currents.push(sim.dc_lead_current(vsrc)?[0]);
+    }
+    let report = sim.node_report().into_iter().find(|report| report.instance == dut);
+    Ok((currents, report.expect("every instance is part of the report")))
+}
+
+#[test]
+fn node_collapsing() -> Result<()> {
+    let models: [(&[&str], &[f64]); 6] = [
+        (&["DIODE", "diode.va"], &[0.6]),
+        (&["DIODE_CMC", "diode_cmc.va"], &[0.6]),
+        (&["HICUML2", "hicuml2.va"], &[1.0, 0.7]),
+        (&["MEXTRAM", "bjt505.va"], &[1.0, 0.7]),
+        (&["BSIM4", "bsim4.va"], &[1.0, 1.0]),
+        (&["PSP103", "psp103.va"], &[1.0, 1.0]),
+    ];
+
+    for (va_file, bias) in models {
+        let (currents, report) = solve_biased_model(va_file, bias, true)?;
+        let (ref_currents, ref_report) = solve_biased_model(va_file, bias, false)?;
+
+        // every collapsed pair removes exactly one internal node
+        assert!(ref_report.collapsed.is_empty(), "{va_file:?}: nodes were collapsed");
+        assert_eq!(
+            report.internal_nodes.len() + report.collapsed.len(),
+            ref_report.internal_nodes.len(),
+            "{va_file:?}: {report:?}"
+        );
+
+        // collapsing nodes must not change the results
+        for (curr, ref_curr) in currents.into_iter().zip(ref_currents) {
+            assert_approx_eq!(curr, ref_curr);
+        }
+    }
+
+    Ok(())
+}
+
+#[test]
+fn opvars() -> Result<()> {
+    let mut arena = Arena::new();
+    let mut circ = Circuit::new("test_circ".to_owned(), &mut arena);
+
+    let gnd = circ.lookup_node("ground").expect("ground node");
+    let node_x = circ.node("X".to_owned());
+
+    let path = Utf8PathBuf::from_path_buf(project_root())
+        .expect("only utf8 paths are supported")
+        .join("integration_tests")
+        .join("DIODE")
+        .join("diode.va");
+    // the opvars of the diode are only declared with OPVARS
+    let opts = veriloga::Opts { defines: vec!["OPVARS".to_owned()], ..veriloga::Opts::default() };
+    circ.load_veriloga_file(path, &opts)?;
+
+    let (vsrc1, _) =
+        circ.new_device_instance_by_name("vsrc1".to_owned(), "vsource", vec![node_x, gnd])?;
+    circ.set_instance_param(vsrc1, "dc", 0.6.into())?;
+
+    let (diode1, _) =
+        circ.new_device_instance_by_name("diode1".to_owned(), "diode_va", vec![node_x, gnd])?;
+
+    let mut ctx = ExprEvalCtx::new(&arena);
+    ctx.set_param(CircuitParam::TEMPERATURE, 300.15.into());
+    let mut sim = circ.prepare_simulation(ctx.borrow(), &arena, SimConfig::default())?;
+    assert!(sim.opvars(diode1).is_err());
+
+    let curr = sim.dc_lead_current(vsrc1)?[0];
+    let opvars = sim.opvars(diode1)?;
+    let info: Vec<_> =
+        opvars.iter().map(|opvar| (opvar.name, opvar.units, opvar.description)).collect();
+    assert_eq!(info, [("cd", "F", "diode junction capcitance"), ("gd", "S", "diode admittance")]);
+    // the junction capacitance is disabled by default (cj0 = 0)
+    assert_approx_eq!(opvars[0].value, 0.0);
+    // exponential diode without series resistance: gd = I / vt
+    let vt = 1.380649e-23 * 300.15 / 1.602176634e-19;
+    assert_approx_eq!(opvars[1].value, -curr / vt);
+
+    // the report contains all instances with opvars
+    let report = sim.op_report()?;
+    assert_eq!(report.len(), 1);
+    assert_eq!(report[0].0, diode1);
+    assert_eq!(report[0].1.len(), 2);
+
+    // the operating point is discarded when the solver is prepared again
+    sim.prepare_solver(ctx.borrow(), &arena)?;
+    assert!(sim.opvars(diode1).is_err());
+    assert!(sim.op_report().is_err());
+
+    Ok(())
+}
+
+#[test]
+fn harmonic_balance() -> Result<()> {
+    let mut arena = Arena::new();
+    let mut circ = Circuit::new("test_circ".to_owned(), &mut arena);
+
+    let gnd = circ.lookup_node("ground").expect("ground node");
+    let [x, y, z, w] = ["X", "Y", "Z", "W"].map(|name| circ.node(name.to_owned()));
+
+    let path = Utf8PathBuf::from_path_buf(project_root())
+        .expect("only utf8 paths are supported")
+        .join("integration_tests")
+        .join("DIODE")
+        .join("diode.va");
+    circ.load_veriloga_file(path, &veriloga::Opts::default())?;
+
+    // RC low pass driven at its corner frequency
+    let freq = 1e3 / (2.0 * PI);
+    let (vsrc1, _) =
+        circ.new_device_instance_by_name("vsrc1".to_owned(), "vsource", vec![x, gnd])?;
+    circ.set_instance_param(vsrc1, "type", Expr::str(&mut arena, "sine"))?;
+    circ.set_instance_param(vsrc1, "ampl", 1f64.into())?;
+    circ.set_instance_param(vsrc1, "freq", freq.into())?;
+    let (res1, _) = circ.new_device_instance_by_name("res1".to_owned(), "resistor", vec![x, y])?;
+    circ.set_instance_param(res1, "r", 1e3.into())?;
+    let (cap1, _) =
+        circ.new_device_instance_by_name("cap1".to_owned(), "capacitor", vec![y, gnd])?;
+    circ.set_instance_param(cap1, "c", 1e-6.into())?;
+
+    // half wave rectifier
+    let (vsrc2, _) =
+        circ.new_device_instance_by_name("vsrc2".to_owned(), "vsource", vec![z, gnd])?;
+    circ.set_instance_param(vsrc2, "type", Expr::str(&mut arena, "sine"))?;
+    circ.set_instance_param(vsrc2, "ampl", 1f64.into())?;
+    circ.set_instance_param(vsrc2, "freq", freq.into())?;
+    let (res2, _) = circ.new_device_instance_by_name("res2".to_owned(), "resistor", vec![z, w])?;
+    circ.set_instance_param(res2, "r", 1e3.into())?;
+    circ.new_device_instance_by_name("diode1".to_owned(), "diode_va", vec![w, gnd])?;
+
+    let mut ctx = ExprEvalCtx::new(&arena);
+    ctx.set_param(CircuitParam::TEMPERATURE, 300.0.into());
+    let mut sim = circ.prepare_simulation(ctx.borrow(), &arena, SimConfig::default())?;
+    sim.set_hb_freq(freq);
+    let solution = sim.hb()?;
+    // sin(w*t) has the phasor -j
+    assert_approx_eq_cmplx!(solution[x][1], 0.0 - j 1.0);
+    assert_approx_eq_cmplx!(solution[y][1], -0.5 - j 0.5);
+    // a linear circuit does not generate harmonics
+    assert_approx_eq!(solution[y][0].norm(), 0.0);
+    assert_approx_eq!(solution[y][2].norm(), 0.0);
+    // the diode clips the positive half wave
+    assert!(solution[w][0].re < 0.0);
+    assert!(solution[w][2].norm() > 1e-3);
+    let direct: Vec<_> = solution.iter().cloned().collect();
+    let curr = sim.hb_lead_current(vsrc1)?[0][1];
+    assert_approx_eq_cmplx!(curr, -5e-4 + j 5e-4);
+
+    // the matrix free solver must converge to the same solution
+    let config = SimConfig {
+        hb_solver: HbSolver::Krylov { rtol: 1e-10, maxiters: 500 },
+        ..SimConfig::default()
+    };
+    let mut sim = circ.prepare_simulation(ctx.borrow(), &arena, config)?;
+    sim.set_hb_freq(freq);
+    let solution = sim.hb()?;
+    assert_eq!(solution.len(), direct.len());
+    for (phasors, ref_phasors) in solution.iter().zip(&direct) {
+        assert_eq!(phasors.len(), ref_phasors.len());
+        for (&val, &ref_val) in phasors.iter().zip(ref_phasors) {
+            assert_approx_eq!(val.re, ref_val.re);
+            assert_approx_eq!(val.im, ref_val.im);
+        }
+    }
+
+    Ok(())
+}
+
+/// A noise source that is contributed to two nodes, once through `ddt`. The output noise
+/// must account for the (complex) correlation between the injections.
+#[test]
+fn correlated_noise() -> Result<()> {
+    const PWR: f64 = 1.0;
+    const K: f64 = 2.0;
+    const TAU: f64 = 1e-7;
+    const R: f64 = 1e3;
+    const C: f64 = 1e-10;
+
+    let mut arena = Arena::new();
+    let mut circ = Circuit::new("test_circ".to_owned(), &mut arena);
+
+    let gnd = circ.lookup_node("ground").expect("ground node");
+    let a = circ.node("A".to_owned());
+    let c = circ.node("C".to_owned());
+
+    let path = Utf8PathBuf::from_path_buf(project_root())
+        .expect("only utf8 paths are supported")
+        .join("openvaf")
+        .join("test_data")
+        .join("osdi")
+        .join("correlated_noise.va");
+    let mut opts = veriloga::Opts::default();
+    opts.correlated_noise = CorrelatedNoise::Matrix;
+    circ.load_veriloga_file(path, &opts)?;
+
+    let (_, dut) =
+        circ.new_device_instance_by_name("dut".to_owned(), "correlated_noise", vec![a, c])?;
+    circ.set_model_param(dut, "pwr", PWR.into())?;
+    circ.set_model_param(dut, "k", K.into())?;
+    circ.set_model_param(dut, "tau", TAU.into())?;
+
+    let (res1, _) =
+        circ.new_device_instance_by_name("res1".to_owned(), "resistor", vec![a, gnd])?;
+    circ.set_instance_param(res1, "r", R.into())?;
+    let (res2, _) =
+        circ.new_device_instance_by_name("res2".to_owned(), "resistor", vec![c, gnd])?;
+    circ.set_instance_param(res2, "r", R.into())?;
+    let (cap1, _) = circ.new_device_instance_by_name("cap1".to_owned(), "capacitor", vec![a, c])?;
+    circ.set_instance_param(cap1, "c", C.into())?;
+
+    let mut ctx = ExprEvalCtx::new(&arena);
+    ctx.set_param(CircuitParam::TEMPERATURE, 300.0.into());
+    let mut sim = circ.prepare_simulation(ctx.borrow(), &arena, SimConfig::default())?;
+
+    for freq in [1e3, 1e6, 1e7] {
+        let omega = 2.0 * PI * freq;
+        // admittance matrix of the external network (nodes A and C)
+        let y11 = Complex64::new(1.0 / R, omega * C);
+        let y12 = Complex64::new(0.0, -omega * C);
+        let det = y11 * y11 - y12 * y12;
+        // transfer from a current injected into A/C to V(A)
+        let transfer = [y11 / det, -y12 / det];
+        // I(a) <+ n; I(c) <+ k * n + ddt(tau * n)
+        let coeffs = [Complex64::new(1.0, 0.0), Complex64::new(K, omega * TAU)];
+
+        let expected = (transfer[0] * coeffs[0] + transfer[1] * coeffs[1]).norm_sqr() * PWR;
+        let uncorrelated = (transfer[0] * coeffs[0]).norm_sqr() * PWR
+            + (transfer[1] * K).norm_sqr() * PWR
+            + (transfer[1] * omega * TAU).norm_sqr() * PWR;
+        let conjugated =
+            (transfer[0] * coeffs[0] + transfer[1] * coeffs[1].conj()).norm_sqr() * PWR;
+
+        let noise = sim.noise(a, freq)?;
+        assert_approx_eq!(noise, expected);
+        // at low frequencies the capacitor decouples both nodes
+        if freq > 1e3 {
+            assert!(!approx_eq(noise, uncorrelated), "correlation was ignored at {freq} Hz");
+            assert!(!approx_eq(noise, conjugated), "wrong sign of the imaginary part at {freq} Hz");
+        }
+    }
+
+    Ok(())
+}
@@ -3,28 +3,31 @@ use std::os::raw::c_char;
 use std::panic::catch_unwind;
 use std::slice;
 
-use anyhow::{bail, Context, Result};
+use anyhow::{anyhow, bail, Context, Result};
 use camino::{Utf8Path, Utf8PathBuf};
 use libc::c_void;
 use libloading::Library;
 use log::{debug, error, info, warn};
 use openvaf::{
-    AbsPathBuf, CompilationDestination, CompilationTermination, LintLevel, OptLevel, Target,
+    AbsPathBuf, CompilationDestination, CompilationTermination, CorrelatedNoise, Emit, Jit,
+    JitTermination, LintLevel, OptLevel, OutputType, Target,
 };
 
 use crate::devices::DeviceImpl;
+use crate::veriloga::limit::populate_lim_table;
 use crate::veriloga::osdi_0_3::{
-    OsdiDescriptor, LOG_FMT_ERR, LOG_LVL_DEBUG, LOG_LVL_DISPLAY, LOG_LVL_ERR, LOG_LVL_FATAL,
-    LOG_LVL_INFO, LOG_LVL_MASK, LOG_LVL_WARN,
+    OsdiDescriptor, OsdiLimFunction, OsdiNoiseCorrelation, LOG_FMT_ERR, LOG_LVL_DEBUG,
+    LOG_LVL_DISPLAY, LOG_LVL_ERR, LOG_LVL_FATAL, LOG_LVL_INFO, LOG_LVL_MASK, LOG_LVL_WARN,
 };
 use crate::veriloga::osdi_device::OsdiDevice;
 
 pub(crate) use osdi_0_3::{
     ANALYSIS_AC, ANALYSIS_DC, ANALYSIS_IC, ANALYSIS_NOISE, ANALYSIS_STATIC, ANALYSIS_TRAN,
-    CALC_NOISE, CALC_REACT_JACOBIAN, CALC_REACT_RESIDUAL, CALC_RESIST_JACOBIAN,
-    CALC_RESIST_RESIDUAL,
+    CALC_NOISE, CALC_OP, CALC_REACT_JACOBIAN, CALC_REACT_RESIDUAL, CALC_RESIST_JACOBIAN,
+    CALC_RESIST_LIM_RHS, CALC_RESIST_RESIDUAL, ENABLE_LIM, INIT_LIM,
 };
 
+mod limit;
 // autogenerated
 #[allow(warnings)]
 mod osdi_0_3;
@@ -38,6 +41,10 @@ pub struct Opts {
     pub lints: Vec<(String, LintLevel)>,
     include: Vec<AbsPathBuf>,
     pub opt_lvl: Option<OptLevel>,
+    pub correlated_noise: CorrelatedNoise,
+    /// compile the models in memory with a JIT instead of building (and caching) a
+    /// shared library, this avoids invoking the system linker
+    pub jit: bool,
 }
 
 impl Opts {
@@ -49,58 +56,87 @@ impl Opts {
 }
 
 pub fn compile_va(path: &Utf8Path, opts: &Opts) -> Result<Vec<Box<dyn DeviceImpl>>> {
-    let cache_dir = if let Some(dir) = &opts.cache_dir {
-        dir.clone()
+    let output = if opts.jit {
+        // nothing is written to disk
+        CompilationDestination::Path { lib_file: Utf8PathBuf::default() }
     } else {
-        let path = directories_next::ProjectDirs::from("com", "semimod", "melange")
-            .context("failed to find cache directory\nhelp: consider setting it manually")?
-            .cache_dir()
-            .to_owned();
-        if let Ok(path) = Utf8PathBuf::from_path_buf(path) {
-            path
-        } else {
-            bail!("failed to find cache directory\nhelp: consider setting it manually")
-        }
+        CompilationDestination::Cache { cache_dir: cache_dir(opts)? }
     };
     let openvaf_opts = openvaf::Opts {
         defines: opts.defines.clone(),
         codegen_opts: opts.codegen_opts.clone(),
         lints: opts.lints.clone(),
         input: path.to_owned(),
-        output: CompilationDestination::Cache { cache_dir },
+        output,
         include: opts.include.clone(),
         opt_lvl: opts.opt_lvl.unwrap_or(OptLevel::Aggressive),
         target: Target::host_target()
             .context("openvaf does currently not support this hardware/os")?,
         target_cpu: "native".to_owned(),
+        correlated_noise: opts.correlated_noise,
+        emit: Emit::default(),
+        emit_lib: true,
+        output_type: OutputType::SharedLib,
+        symbol_prefix: String::new(),
         dry_run: false,
     };
 
-    let res = openvaf::compile(&openvaf_opts);
-    let res = res.with_context(|| format!("openvaf: compilation of {path} failed"))?;
-    let lib_file = match res {
-        CompilationTermination::Compiled { lib_file } => lib_file,
-        CompilationTermination::FatalDiagnostic => {
-            bail!("openvaf: compilation of {path} failed");
-        }
+    let (descriptors, noise_correlation) = if opts.jit {
+        let res = openvaf::compile_jit(&openvaf_opts);
+        let res = res.with_context(|| format!("openvaf: compilation of {path} failed"))?;
+        let jit = match res {
+            JitTermination::Compiled { jit } => jit,
+            JitTermination::FatalDiagnostic => {
+                bail!("openvaf: compilation of {path} failed");
+            }
+        };
+        unsafe { load_osdi_jit(jit, path)? }
+    } else {
+        let res = openvaf::compile(&openvaf_opts);
+        let res = res.with_context(|| format!("openvaf: compilation of {path} failed"))?;
+        let lib_file = match res {
+            CompilationTermination::Compiled { lib_file } => lib_file,
+            CompilationTermination::FatalDiagnostic => {
+                bail!("openvaf: compilation of {path} failed");
+            }
+        };
+        unsafe { load_osdi_lib(&lib_file)? }
     };
-    let libs = unsafe { load_osdi_lib(&lib_file)? };
-    let libs = libs.iter().map(|descriptor| Box::new(OsdiDevice { descriptor }) as _).collect();
+    let libs = descriptors
+        .iter()
+        .enumerate()
+        .map(|(i, descriptor)| {
+            let noise_correlation = noise_correlation.map(|it| &it[i]);
+            Box::new(OsdiDevice { descriptor, noise_correlation }) as _
+        })
+        .collect();
     Ok(libs)
 }
 
-unsafe fn load_osdi_lib(path: &Utf8Path) -> Result<&'static [OsdiDescriptor]> {
+fn cache_dir(opts: &Opts) -> Result<Utf8PathBuf> {
+    if let Some(dir) = &opts.cache_dir {
+        return Ok(dir.clone());
+    }
+    let path = directories_next::ProjectDirs::from("com", "semimod", "melange")
+        .context("failed to find cache directory\nhelp: consider setting it manually")?
+        .cache_dir()
+        .to_owned();
+    if let Ok(path) = Utf8PathBuf::from_path_buf(path) {
+        Ok(path)
+    } else {
+        bail!("failed to find cache directory\nhelp: consider setting it manually")
+    }
+}
+
+type OsdiLib = (&'static [OsdiDescriptor], Option<&'static [OsdiNoiseCorrelation]>);
+
+unsafe fn load_osdi_lib(path: &Utf8Path) -> Result<OsdiLib> {
     let lib = Library::new(path)?;
     let lib = Box::leak(Box::new(lib));
 
     let major_version: &u32 = *lib.get(b"OSDI_VERSION_MAJOR\0")?;
     let minor_version: &u32 = *lib.get(b"OSDI_VERSION_MINOR\0")?;
-
-    if *major_version != 0 || *minor_version != 3 {
-        bail!(
-            "melange only supports OSDI v0.3 but {path} targets v{major_version}.{minor_version}",
-        );
-    }
+    check_osdi_version(path, *major_version, *minor_version)?;
 
     let num_descriptors: &u32 = *lib.get(b"OSDI_NUM_DESCRIPTORS\0")?;
     let descriptors: *const OsdiDescriptor = *lib.get(b"OSDI_DESCRIPTORS\0")?;
@@ -108,12 +144,66 @@ unsafe fn load_osdi_lib(path: &Utf8Path) -> Result<&'static [OsdiDescriptor]> {
     let descriptors: &[OsdiDescriptor] =
         slice::from_raw_parts(descriptors, *num_descriptors as usize);
 
+    // optional extension: only exported for models compiled with CorrelatedNoise::Matrix
+    let noise_correlation = lib
+        .get::<*const OsdiNoiseCorrelation>(b"OSDI_NOISE_CORRELATION\0")
+        .ok()
+        .map(|ptr| slice::from_raw_parts(*ptr, *num_descriptors as usize));
+
     if let Ok(osdi_log_ptr) =
         lib.get::<*mut unsafe fn(*mut c_void, *const c_char, u32)>(b"osdi_log\0")
     {
         osdi_log_ptr.write(osdi_log)
     }
-    Ok(descriptors)
+
+    // only libraries containing models that call $limit export a lim table
+    if let Ok(lim_table) = lib.get::<*mut OsdiLimFunction>(b"OSDI_LIM_TABLE\0") {
+        let lim_table_len: &u32 = *lib.get(b"OSDI_LIM_TABLE_LEN\0")?;
+        let lim_table = slice::from_raw_parts_mut(*lim_table, *lim_table_len as usize);
+        populate_lim_table(lim_table, path)?;
+    }
+    Ok((descriptors, noise_correlation))
+}
+
+unsafe fn load_osdi_jit(jit: Jit, path: &Utf8Path) -> Result<OsdiLib> {
+    // the compiled code must stay alive as long as the descriptors are used
+    let jit = Box::leak(Box::new(jit));
+    let lookup = |sym: &str| {
+        jit.lookup(sym).map_err(|err| anyhow!("failed to find {sym} for {path}: {err}"))
+    };
+
+    let major_version = *(lookup("OSDI_VERSION_MAJOR")? as *const u32);
+    let minor_version = *(lookup("OSDI_VERSION_MINOR")? as *const u32);
+    check_osdi_version(path, major_version, minor_version)?;
+
+    let num_descriptors = *(lookup("OSDI_NUM_DESCRIPTORS")? as *const u32) as usize;
+    let descriptors = lookup("OSDI_DESCRIPTORS")? as *const OsdiDescriptor;
+    let descriptors = slice::from_raw_parts(descriptors, num_descriptors);
+
+    // optional extension: only exported for models compiled with CorrelatedNoise::Matrix
+    let noise_correlation = jit.lookup("OSDI_NOISE_CORRELATION").ok().map(|ptr| {
+        slice::from_raw_parts(ptr as *const OsdiNoiseCorrelation, num_descriptors)
+    });
+
+    let osdi_log_ptr = lookup("osdi_log")? as *mut unsafe fn(*mut c_void, *const c_char, u32);
+    osdi_log_ptr.write(osdi_log);
+
+    if let Ok(lim_table) = jit.lookup("OSDI_LIM_TABLE") {
+        let lim_table_len = *(lookup("OSDI_LIM_TABLE_LEN")? as *const u32) as usize;
+        let lim_table = slice::from_raw_parts_mut(lim_table as *mut OsdiLimFunction, lim_table_len);
+        populate_lim_table(lim_table, path)?;
+    }
+
+    Ok((descriptors, noise_correlation))
+}
+
+fn check_osdi_version(path: &Utf8Path, major_version: u32, minor_version: u32) -> Result<()> {
+    if major_version != 0 || minor_version != 3 {
+        bail!(
+            "melange only supports OSDI v0.3 but {path} targets v{major_version}.{minor_version}",
+        );
+    }
+    Ok(())
 }
 
 unsafe fn osdi_log(handle: *mut c_void, msg: *const c_char, lvl: u32) {
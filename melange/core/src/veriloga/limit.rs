@@ -0,0 +1,182 @@
+//! The limiting functions that Verilog-A models can invoke with `$limit`.
+//! The implementations follow the well known SPICE algorithms.
+
+use std::ffi::{c_void, CStr};
+
+use anyhow::{bail, Result};
+use camino::Utf8Path;
+
+use crate::veriloga::osdi_0_3::OsdiLimFunction;
+
+#[cfg(test)]
+mod tests;
+
+type LimFn0 = unsafe extern "C" fn(bool, *mut bool, f64, f64) -> f64;
+type LimFn1 = unsafe extern "C" fn(bool, *mut bool, f64, f64, f64) -> f64;
+type LimFn2 = unsafe extern "C" fn(bool, *mut bool, f64, f64, f64, f64) -> f64;
+
+/// Fills in the function pointers of the `OSDI_LIM_TABLE` exported by a compiled model.
+///
+/// # Safety
+///
+/// `table` must be the lim table of a valid OSDI library
+pub(super) unsafe fn populate_lim_table(
+    table: &mut [OsdiLimFunction],
+    path: &Utf8Path,
+) -> Result<()> {
+    for lim_func in table {
+        let name = CStr::from_ptr(lim_func.name)
+            .to_str()
+            .expect("All OSDI strings must be encoded in UTF-8");
+        let (func_ptr, num_args) = match name {
+            "pnjlim" => (osdi_pnjlim as LimFn2 as *mut c_void, 2),
+            "fetlim" => (osdi_fetlim as LimFn1 as *mut c_void, 1),
+            "limvds" => (osdi_limvds as LimFn0 as *mut c_void, 0),
+            _ => bail!("{path}: $limit function \"{name}\" is not supported by melange"),
+        };
+        if lim_func.num_args != num_args {
+            bail!(
+                "{path}: $limit function \"{name}\" expects {num_args} arguments but {} were \
+                 provided",
+                lim_func.num_args
+            )
+        }
+        lim_func.func_ptr = func_ptr;
+    }
+    Ok(())
+}
+
+unsafe extern "C" fn osdi_pnjlim(
+    init: bool,
+    check: *mut bool,
+    vnew: f64,
+    vold: f64,
+    vt: f64,
+    vcrit: f64,
+) -> f64 {
+    if init {
+        *check = true;
+        return vcrit;
+    }
+    let res = pnjlim(vnew, vold, vt, vcrit);
+    if res != vnew {
+        *check = true;
+    }
+    res
+}
+
+unsafe extern "C" fn osdi_fetlim(
+    init: bool,
+    check: *mut bool,
+    vnew: f64,
+    vold: f64,
+    vto: f64,
+) -> f64 {
+    if init {
+        *check = true;
+        return vto + 0.1;
+    }
+    let res = fetlim(vnew, vold, vto);
+    if res != vnew {
+        *check = true;
+    }
+    res
+}
+
+unsafe extern "C" fn osdi_limvds(init: bool, check: *mut bool, vnew: f64, vold: f64) -> f64 {
+    if init {
+        *check = true;
+        return 0.1;
+    }
+    let res = limvds(vnew, vold);
+    if res != vnew {
+        *check = true;
+    }
+    res
+}
+
+/// Limits the change of the voltage across a pn-junction to avoid overflowing the exponential.
+fn pnjlim(vnew: f64, vold: f64, vt: f64, vcrit: f64) -> f64 {
+    if vnew <= vcrit || (vnew - vold).abs() <= 2.0 * vt {
+        return vnew;
+    }
+
+    if vold > 0.0 {
+        let arg = 1.0 + (vnew - vold) / vt;
+        if arg > 0.0 {
+            vold + vt * arg.ln()
+        } else {
+            vcrit
+        }
+    } else {
+        vt * (vnew / vt).ln()
+    }
+}
+
+/// Limits the change of the gate voltage of a FET relative to the threshold voltage `vto`.
+fn fetlim(vnew: f64, vold: f64, vto: f64) -> f64 {
+    let vtsthi = (2.0 * (vold - vto)).abs() + 2.0;
+    let vtstlo = (vold - vto).abs() + 1.0;
+    let vtox = vto + 3.5;
+    let delv = vnew - vold;
+
+    if vold >= vto {
+        if vold >= vtox {
+            if delv <= 0.0 {
+                // going off
+                if vnew >= vtox {
+                    if -delv > vtstlo {
+                        return vold - vtstlo;
+                    }
+                    vnew
+                } else {
+                    vnew.max(vto + 2.0)
+                }
+            } else if delv >= vtsthi {
+                // staying on
+                vold + vtsthi
+            } else {
+                vnew
+            }
+        } else if delv <= 0.0 {
+            // middle region, decreasing
+            vnew.max(vto - 0.5)
+        } else {
+            // middle region, increasing
+            vnew.min(vto + 4.0)
+        }
+    } else if delv <= 0.0 {
+        // off
+        if -delv > vtsthi {
+            vold - vtsthi
+        } else {
+            vnew
+        }
+    } else {
+        let vtemp = vto + 0.5;
+        if vnew > vtemp {
+            vtemp
+        } else if delv > vtstlo {
+            vold + vtstlo
+        } else {
+            vnew
+        }
+    }
+}
+
+/// Limits the change of the drain-source voltage of a FET.
+fn limvds(vnew: f64, vold: f64) -> f64 {
+    if vold >= 3.5 {
+        if vnew > vold {
+            vnew.min(3.0 * vold + 2.0)
+        } else if vnew < 3.5 {
+            vnew.max(2.0)
+        } else {
+            vnew
+        }
+    } else if vnew > vold {
+        vnew.min(4.0)
+    } else {
+        vnew.max(-0.5)
+    }
+}
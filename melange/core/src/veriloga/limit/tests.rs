@@ -0,0 +1,99 @@
+use super::{fetlim, limvds, osdi_fetlim, osdi_limvds, osdi_pnjlim, pnjlim};
+
+const VT: f64 = 0.025;
+const VCRIT: f64 = 0.6;
+
+fn assert_close(val: f64, ref_val: f64) {
+    assert!((val - ref_val).abs() < 1e-9, "{val} != {ref_val}");
+}
+
+#[test]
+fn pnjlim_small_steps() {
+    // steps below 2 vt and voltages below vcrit are never limited
+    assert_eq!(pnjlim(0.7, 0.66, VT, VCRIT), 0.7);
+    assert_eq!(pnjlim(0.5, -3.0, VT, VCRIT), 0.5);
+    assert_eq!(pnjlim(-5.0, 0.8, VT, VCRIT), -5.0);
+}
+
+#[test]
+fn pnjlim_large_steps() {
+    // forward biased: the step is compressed logarithmically
+    assert_close(pnjlim(5.0, 0.7, VT, VCRIT), 0.8288322898624444);
+    // starting from reverse bias the new voltage itself is compressed
+    assert_close(pnjlim(5.0, 0.0, VT, VCRIT), 0.1324579341637009);
+    assert_close(pnjlim(5.0, -1.0, VT, VCRIT), 0.1324579341637009);
+    // a large decrease that would make the logarithm undefined falls back to vcrit
+    assert_eq!(pnjlim(0.7, 1.0, VT, VCRIT), VCRIT);
+    // repeated limiting converges to the unlimited voltage
+    let mut v = 0.0;
+    for _ in 0..100 {
+        v = pnjlim(0.9, v, VT, VCRIT);
+    }
+    assert_eq!(v, 0.9);
+}
+
+#[test]
+fn fetlim_off() {
+    const VTO: f64 = 0.5;
+    // increasing: limited to just above the threshold or by vtstlo
+    assert_eq!(fetlim(3.0, 0.0, VTO), VTO + 0.5);
+    assert_eq!(fetlim(0.9, -2.0, VTO), 0.9);
+    // decreasing: limited by vtsthi
+    assert_eq!(fetlim(-10.0, 0.0, VTO), -3.0);
+    assert_eq!(fetlim(-2.0, 0.0, VTO), -2.0);
+}
+
+#[test]
+fn fetlim_on() {
+    const VTO: f64 = 0.5;
+    // middle region
+    assert_eq!(fetlim(10.0, 1.0, VTO), VTO + 4.0);
+    assert_eq!(fetlim(-5.0, 1.0, VTO), VTO - 0.5);
+    assert_eq!(fetlim(2.0, 1.0, VTO), 2.0);
+    // strongly on
+    assert_eq!(fetlim(20.0, 5.0, VTO), 5.0 + 11.0);
+    assert_eq!(fetlim(10.0, 5.0, VTO), 10.0);
+    assert_eq!(fetlim(4.2, 5.0, VTO), 4.2);
+    assert_eq!(fetlim(0.0, 5.0, VTO), VTO + 2.0);
+}
+
+#[test]
+fn limvds_steps() {
+    assert_eq!(limvds(30.0, 5.0), 17.0);
+    assert_eq!(limvds(1.0, 5.0), 2.0);
+    assert_eq!(limvds(4.0, 5.0), 4.0);
+    assert_eq!(limvds(10.0, 1.0), 4.0);
+    assert_eq!(limvds(-3.0, 1.0), -0.5);
+    assert_eq!(limvds(0.5, 1.0), 0.5);
+}
+
+#[test]
+fn osdi_check_flag() {
+    unsafe {
+        // initialization always requests another iteration
+        let mut check = false;
+        assert_eq!(osdi_pnjlim(true, &mut check, 5.0, 0.0, VT, VCRIT), VCRIT);
+        assert!(check);
+        let mut check = false;
+        assert_eq!(osdi_fetlim(true, &mut check, 5.0, 0.0, 0.5), 0.6);
+        assert!(check);
+        let mut check = false;
+        assert_eq!(osdi_limvds(true, &mut check, 5.0, 0.0), 0.1);
+        assert!(check);
+
+        // the flag is only set if the voltage was limited
+        let mut check = false;
+        assert_eq!(osdi_pnjlim(false, &mut check, 0.7, 0.66, VT, VCRIT), 0.7);
+        assert_eq!(osdi_fetlim(false, &mut check, 2.0, 1.0, 0.5), 2.0);
+        assert_eq!(osdi_limvds(false, &mut check, 0.5, 1.0), 0.5);
+        assert!(!check);
+        assert_close(osdi_pnjlim(false, &mut check, 5.0, 0.7, VT, VCRIT), 0.8288322898624444);
+        assert!(check);
+        let mut check = false;
+        assert_eq!(osdi_fetlim(false, &mut check, 10.0, 1.0, 0.5), 4.5);
+        assert!(check);
+        let mut check = false;
+        assert_eq!(osdi_limvds(false, &mut check, 10.0, 1.0), 4.0);
+        assert!(check);
+    }
+}
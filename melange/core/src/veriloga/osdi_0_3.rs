@@ -254,3 +254,26 @@ impl OsdiDescriptor {
         (self.load_jacobian_tran)(inst, model, alpha)
     }
 }
+#[repr(C)]
+pub struct OsdiCorrelatedNoiseSource {
+    pub name: *mut c_char,
+    pub num_nodes: u32,
+    pub nodes: *mut OsdiNodePair,
+}
+#[repr(C)]
+pub struct OsdiNoiseCorrelation {
+    pub num_correlated_noise_src: u32,
+    pub correlated_noise_sources: *mut OsdiCorrelatedNoiseSource,
+    pub load_noise_correlation: fn(*mut c_void, *mut c_void, f64, *mut f64),
+}
+impl OsdiNoiseCorrelation {
+    pub fn load_noise_correlation(
+        &self,
+        inst: *mut c_void,
+        model: *mut c_void,
+        freq: f64,
+        noise_dens: *mut f64,
+    ) {
+        (self.load_noise_correlation)(inst, model, freq, noise_dens)
+    }
+}
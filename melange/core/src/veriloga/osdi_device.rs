@@ -1,4 +1,5 @@
 use anyhow::{bail, Result};
+use num_complex::Complex64;
 use std::alloc::{alloc_zeroed, handle_alloc_error, Layout};
 use std::cell::Cell;
 use std::ffi::{c_void, CStr, CString};
@@ -11,14 +12,21 @@ use stdx::iter::zip;
 use typed_index_collections::TiSlice;
 
 use crate::circuit::Node;
-use crate::devices::{DeviceImpl, DeviceParams, InstanceImpl, ModelImpl, ParamId, Type};
-use crate::simulation::{MatrixEntryIter, SimBuilder, SimInfo};
+use crate::devices::{
+    DeviceImpl, DeviceParams, InstanceImpl, ModelImpl, NoiseDensity, OpVar, ParamId, Type,
+};
+use crate::simulation::{MatrixEntryIter, RawSimParams, SimBuilder, SimInfo};
 use crate::veriloga::osdi_0_3::{
-    OsdiDescriptor, OsdiInitInfo, OsdiJacobianEntry, OsdiNode, OsdiNodePair, OsdiParamOpvar,
-    OsdiSimInfo, OsdiSimParas, ACCESS_FLAG_SET, EVAL_RET_FLAG_FATAL, INIT_ERR_OUT_OF_BOUNDS,
-    PARA_KIND_INST, PARA_TY_INT, PARA_TY_MASK, PARA_TY_REAL, PARA_TY_STR,
+    OsdiCorrelatedNoiseSource, OsdiDescriptor, OsdiInitInfo, OsdiJacobianEntry, OsdiNode,
+    OsdiNodePair, OsdiNoiseCorrelation, OsdiNoiseSource, OsdiParamOpvar, OsdiSimInfo, OsdiSimParas,
+    ACCESS_FLAG_INSTANCE, ACCESS_FLAG_READ, ACCESS_FLAG_SET, EVAL_RET_FLAG_FATAL,
+    EVAL_RET_FLAG_LIM, INIT_ERR_OUT_OF_BOUNDS, PARA_KIND_INST, PARA_TY_INT, PARA_TY_MASK,
+    PARA_TY_REAL, PARA_TY_STR,
 };
 
+#[cfg(test)]
+mod tests;
+
 impl OsdiDescriptor {
     fn nodes(&self) -> &[OsdiNode] {
         // # SAFETY: OsdiDescriptor can only be constructed from FFI and is assumed to contain
@@ -36,11 +44,27 @@ impl OsdiDescriptor {
         unsafe { slice::from_raw_parts(self.param_opvar, self.num_params as usize) }
     }
 
+    fn opvars(&self) -> &[OsdiParamOpvar] {
+        // # SAFETY: OsdiDescriptor can only be constructed from FFI and is assumed to contain
+        // valid data
+        unsafe {
+            slice::from_raw_parts(
+                self.param_opvar.add(self.num_params as usize),
+                self.num_opvars as usize,
+            )
+        }
+    }
+
     fn collapsible(&self) -> &[OsdiNodePair] {
         // SAFETY: self.data is a valid allocation and the descriptor is assumed valid
         unsafe { slice::from_raw_parts(self.collapsible, self.num_collapsible as usize) }
     }
 
+    fn noise_sources(&self) -> &[OsdiNoiseSource] {
+        // SAFETY: self.data is a valid allocation and the descriptor is assumed valid
+        unsafe { slice::from_raw_parts(self.noise_sources, self.num_noise_src as usize) }
+    }
+
     fn matrix_entries(&self) -> &[OsdiJacobianEntry] {
         // SAFETY: self.data is a valid allocation and the descriptor is assumed valid
         unsafe { slice::from_raw_parts(self.jacobian_entries, self.num_jacobian_entries as usize) }
@@ -76,6 +100,25 @@ impl OsdiDescriptor {
     }
 }
 
+impl OsdiNoiseCorrelation {
+    fn correlated_noise_sources(&self) -> &[OsdiCorrelatedNoiseSource] {
+        // SAFETY: the noise correlation is assumed valid like the descriptor
+        unsafe {
+            slice::from_raw_parts(
+                self.correlated_noise_sources,
+                self.num_correlated_noise_src as usize,
+            )
+        }
+    }
+}
+
+impl OsdiCorrelatedNoiseSource {
+    fn nodes(&self) -> &[OsdiNodePair] {
+        // SAFETY: the noise correlation is assumed valid like the descriptor
+        unsafe { slice::from_raw_parts(self.nodes, self.num_nodes as usize) }
+    }
+}
+
 impl Drop for OsdiInitInfo {
     fn drop(&mut self) {
         // # SAFETY: this is save because OSDI api promises malloc allocated data and the struct can
@@ -88,6 +131,15 @@ impl Drop for OsdiInitInfo {
     }
 }
 
+fn osdi_sim_params(sim_params: &RawSimParams) -> OsdiSimParas {
+    OsdiSimParas {
+        names: sim_params.names(),
+        vals: sim_params.vals(),
+        names_str: sim_params.str_names(),
+        vals_str: sim_params.str_vals(),
+    }
+}
+
 unsafe fn osdi_str(raw: *mut c_char) -> &'static str {
     CStr::from_ptr(raw).to_str().expect("All OSDI strings must be encoded in UTF-8")
 }
@@ -141,6 +193,7 @@ unsafe fn dealloc(ptr: *mut c_void, size: usize) {
 
 pub(super) struct OsdiDevice {
     pub descriptor: &'static OsdiDescriptor,
+    pub noise_correlation: Option<&'static OsdiNoiseCorrelation>,
 }
 impl DeviceImpl for OsdiDevice {
     fn get_name(&self) -> &'static str {
@@ -177,12 +230,14 @@ impl DeviceImpl for OsdiDevice {
         Rc::new(OsdiModel {
             data: alloc(self.descriptor.model_size as usize),
             descriptor: self.descriptor,
+            noise_correlation: self.noise_correlation,
         })
     }
 }
 
 struct OsdiModel {
     descriptor: &'static OsdiDescriptor,
+    noise_correlation: Option<&'static OsdiNoiseCorrelation>,
     data: *mut c_void,
 }
 
@@ -194,13 +249,8 @@ impl Drop for OsdiModel {
 }
 
 impl ModelImpl for OsdiModel {
-    fn process_params(&self) -> Result<()> {
-        let mut sim_params = OsdiSimParas {
-            names: &mut ptr::null_mut(),
-            vals: ptr::null_mut(),
-            names_str: &mut ptr::null_mut(),
-            vals_str: ptr::null_mut(),
-        };
+    fn process_params(&self, sim_params: &RawSimParams) -> Result<()> {
+        let mut sim_params = osdi_sim_params(sim_params);
 
         let mut res = OsdiInitInfo { flags: 0, num_errors: 0, errors: ptr::null_mut() };
         self.descriptor.setup_model(
@@ -244,8 +294,11 @@ impl ModelImpl for OsdiModel {
     fn new_instance(self: Rc<Self>) -> Box<dyn crate::devices::InstanceImpl> {
         Box::new(OsdiInstance {
             descriptor: self.descriptor,
+            noise_correlation: self.noise_correlation,
             data: alloc(self.descriptor.instance_size as usize),
             model_data: self.data,
+            state: vec![0f64; self.descriptor.num_states as usize].into_boxed_slice(),
+            collapsed_pairs: Vec::new(),
             _model: self,
         })
     }
@@ -253,8 +306,14 @@ impl ModelImpl for OsdiModel {
 
 struct OsdiInstance {
     descriptor: &'static OsdiDescriptor,
+    noise_correlation: Option<&'static OsdiNoiseCorrelation>,
     data: *mut c_void,
     model_data: *mut c_void,
+    /// the state vector used by `$limit`, the previous and next state are stored in the same
+    /// vector because the model only ever reads a state before overwriting it
+    state: Box<[f64]>,
+    /// the (OSDI) node pairs collapsed during the last call to `process_params`
+    collapsed_pairs: Vec<(u32, u32)>,
     _model: Rc<OsdiModel>, // only kept to ensure the data stays live
 }
 
@@ -283,6 +342,15 @@ impl OsdiInstance {
         }
     }
 
+    fn state_idx(&self) -> &[Cell<u32>] {
+        let ptr = self.data as *mut u8;
+        // SAFETY: self.data is a valid allocation and the descriptor is assumed valid
+        unsafe {
+            let ptr = ptr.add(self.descriptor.state_idx_off as usize) as *mut Cell<u32>;
+            slice::from_raw_parts_mut(ptr, self.descriptor.num_states as usize)
+        }
+    }
+
     fn collapsed(&self) -> &[bool] {
         let ptr = self.data as *mut u8;
         // SAFETY: self.data is a valid allocation and the descriptor is assumed valid
@@ -292,63 +360,83 @@ impl OsdiInstance {
         }
     }
 
-    fn collapse_nodes(&self, connected_terminals: u32) -> Vec<u32> {
-        let collapsed = self.collapsed();
+    /// maps a pair of OSDI nodes to the circuit nodes they were assigned to
+    fn map_node_pair(&self, nodes: &OsdiNodePair) -> (Node, Node) {
         let node_mapping = self.node_mapping();
-        let collapsible = self.descriptor.collapsible();
-
-        let mut back_map: Vec<u32> = (connected_terminals..self.descriptor.num_nodes).collect();
-
-        //  populate nodes with themselves
-        for (node, node_mapping) in node_mapping.iter().enumerate() {
-            node_mapping.set(node as u32)
-        }
-
-        for (candidate, is_collapsed) in zip(collapsible, collapsed) {
-            if !is_collapsed {
-                continue;
+        let map = |node: u32| {
+            if node == u32::MAX {
+                0u32.into()
+            } else {
+                node_mapping[node as usize].get().into()
             }
+        };
+        (map(nodes.node_1), map(nodes.node_2))
+    }
+
+    /// Collapses the node pairs the model marked as collapsed (if `enabled`) and returns the
+    /// OSDI nodes that remain as internal unknowns.
+    fn collapse_nodes(&mut self, connected_terminals: u32, enabled: bool) -> Vec<u32> {
+        let pairs = zip(self.descriptor.collapsible(), self.collapsed())
+            .filter(|&(_, &is_collapsed)| is_collapsed && enabled)
+            .map(|(candidate, _)| (candidate.node_1, candidate.node_2));
+        let (back_map, collapsed_pairs) =
+            collapse_node_pairs(self.node_mapping(), pairs, connected_terminals);
+        self.collapsed_pairs = collapsed_pairs;
+        back_map
+    }
+}
 
-            let from = candidate.node_1;
-            let to = candidate.node_2;
-
-            let mut mapped_from = node_mapping[from as usize].get();
-            let mut collapse_to_gnd = to == u32::MAX;
-            let mut mapped_to = if collapse_to_gnd {
-                u32::MAX
-            } else {
-                let mapped = node_mapping[to as usize].get();
-                collapse_to_gnd |= mapped == u32::MAX;
-                mapped
-            };
+/// Collapses the node `pairs` (`u32::MAX` is ground) by updating `node_mapping` and returns
+/// the nodes that remain as internal unknowns together with the pairs that were collapsed.
+fn collapse_node_pairs(
+    node_mapping: &[Cell<u32>],
+    pairs: impl Iterator<Item = (u32, u32)>,
+    connected_terminals: u32,
+) -> (Vec<u32>, Vec<(u32, u32)>) {
+    let mut collapsed_pairs = Vec::new();
+    let mut back_map: Vec<u32> = (connected_terminals..node_mapping.len() as u32).collect();
+
+    //  populate nodes with themselves
+    for (node, node_mapping) in node_mapping.iter().enumerate() {
+        node_mapping.set(node as u32)
+    }
+
+    for (from, to) in pairs {
+        let mut mapped_from = node_mapping[from as usize].get();
+        let mut mapped_to = if to == u32::MAX { u32::MAX } else { node_mapping[to as usize].get() };
+
+        // ensure that to is always the smaller node (ground is always the target)
+        let swapped = mapped_from == u32::MAX || (mapped_to != u32::MAX && mapped_from < mapped_to);
+        if swapped {
+            swap(&mut mapped_from, &mut mapped_to)
+        }
 
-            // terminals cannot be collapsed
-            if mapped_from < connected_terminals
-                && (collapse_to_gnd || mapped_to < connected_terminals)
-            {
-                continue;
-            }
+        // the nodes were already collapsed by a previous pair
+        if mapped_from == mapped_to {
+            continue;
+        }
 
-            // ensure that to is always the smaller node
-            if !collapse_to_gnd && mapped_from < mapped_to {
-                swap(&mut mapped_from, &mut mapped_to)
-            }
+        // terminals cannot be collapsed (to is a terminal or ground if from is a terminal)
+        if mapped_from < connected_terminals {
+            continue;
+        }
 
-            // replace nodes mapped to from with to and reduce the number of nodes
-            for dst in node_mapping {
-                let mapping = dst.get();
-                if mapping == mapped_from {
-                    dst.set(mapped_to)
-                } else if mapping > mapped_from && mapping != u32::MAX {
-                    dst.set(mapping - 1)
-                }
+        // replace nodes mapped to from with to and reduce the number of nodes
+        for dst in node_mapping {
+            let mapping = dst.get();
+            if mapping == mapped_from {
+                dst.set(mapped_to)
+            } else if mapping > mapped_from && mapping != u32::MAX {
+                dst.set(mapping - 1)
             }
-            // public nodes can not be removed to no need to track them
-            back_map.remove((mapped_from - connected_terminals) as usize);
         }
-
-        back_map
+        // public nodes can not be removed to no need to track them
+        back_map.remove((mapped_from - connected_terminals) as usize);
+        // the removed node first, the node it was collapsed into second
+        collapsed_pairs.push(if swapped { (to, from) } else { (from, to) });
     }
+
+    (back_map, collapsed_pairs)
 }
 
 impl InstanceImpl for OsdiInstance {
@@ -358,12 +446,7 @@ impl InstanceImpl for OsdiInstance {
         sim_builder: &mut SimBuilder,
         terminals: &[Node],
     ) -> Result<()> {
-        let mut sim_params = OsdiSimParas {
-            names: &mut ptr::null_mut(),
-            vals: ptr::null_mut(),
-            names_str: &mut ptr::null_mut(),
-            vals_str: ptr::null_mut(),
-        };
+        let mut sim_params = osdi_sim_params(sim_builder.sim_params);
 
         let mut res = OsdiInitInfo { flags: 0, num_errors: 0, errors: ptr::null_mut() };
         self.descriptor.setup_instance(
@@ -376,7 +459,8 @@ impl InstanceImpl for OsdiInstance {
             &mut res,
         );
         self.descriptor.check_init_result(res)?;
-        let mut internal_nodes = self.collapse_nodes(terminals.len() as u32);
+        let mut internal_nodes =
+            self.collapse_nodes(terminals.len() as u32, sim_builder.config.collapse_nodes);
 
         // create internal nodes
         for node in &mut internal_nodes {
@@ -412,6 +496,11 @@ impl InstanceImpl for OsdiInstance {
             sim_builder.ensure_matrix_entry(column, row)
         }
 
+        // every instance has its own state vector
+        for (i, idx) in self.state_idx().iter().enumerate() {
+            idx.set(i as u32)
+        }
+
         Ok(())
     }
 
@@ -424,6 +513,16 @@ impl InstanceImpl for OsdiInstance {
         unsafe { ptr.write(val) };
     }
 
+    fn get_real_param(&self, param: ParamId) -> f64 {
+        let ptr =
+            self.descriptor.access(ptr::null_mut(), self.data, param.into(), ACCESS_FLAG_READ);
+        let ptr = ptr as *const f64;
+        if ptr.is_null() {
+            unreachable!("invalid parameter access")
+        }
+        unsafe { ptr.read() }
+    }
+
     fn set_str_param(&mut self, param: ParamId, val: &str) {
         let val = CString::new(val).expect("string may not contain null terminators");
         let ptr = self.descriptor.access(ptr::null_mut(), self.data, param.into(), ACCESS_FLAG_SET);
@@ -459,20 +558,13 @@ impl InstanceImpl for OsdiInstance {
         }
     }
 
-    fn eval(&mut self, sim_info: SimInfo<'_>) -> Result<()> {
-        let sim_params = OsdiSimParas {
-            names: &mut ptr::null_mut(),
-            vals: ptr::null_mut(),
-            names_str: &mut ptr::null_mut(),
-            vals_str: ptr::null_mut(),
-        };
-
+    fn eval(&mut self, sim_info: SimInfo<'_>) -> Result<bool> {
         let mut info = OsdiSimInfo {
-            paras: sim_params,
+            paras: osdi_sim_params(sim_info.sim_params),
             abstime: sim_info.abstime,
             prev_solve: sim_info.prev_solve.as_ptr() as *mut f64,
-            prev_state: ptr::null_mut(),
-            next_state: ptr::null_mut(),
+            prev_state: self.state.as_mut_ptr(),
+            next_state: self.state.as_mut_ptr(),
             flags: sim_info.flags.bits(),
         };
 
@@ -491,7 +583,7 @@ impl InstanceImpl for OsdiInstance {
         //     bail!("Simulation aborted with $finish")
         // }
 
-        Ok(())
+        Ok((ret_flags & EVAL_RET_FLAG_LIM) != 0)
     }
 
     unsafe fn load_matrix_resist(&self) {
@@ -518,6 +610,79 @@ impl InstanceImpl for OsdiInstance {
         self.descriptor.load_residual_resist(self.data, self.model_data, residual.as_mut_ptr())
     }
 
+    fn load_limit_rhs_resist(&self, rhs: &mut TiSlice<Node, f64>) {
+        self.descriptor.load_limit_rhs_resist(self.data, self.model_data, rhs.as_mut_ptr())
+    }
+
+    fn load_noise(&self, freq: f64, dst: &mut Vec<NoiseDensity>) {
+        let noise_sources = self.descriptor.noise_sources();
+        let mut densities = vec![0f64; noise_sources.len()];
+        self.descriptor.load_noise(self.data, self.model_data, freq, densities.as_mut_ptr());
+        dst.extend(zip(noise_sources, densities).map(|(src, density)| NoiseDensity {
+            name: unsafe { osdi_str(src.name) },
+            nodes: vec![self.map_node_pair(&src.nodes)],
+            correlation: vec![density.into()],
+        }));
+
+        let noise_correlation = if let Some(noise_correlation) = self.noise_correlation {
+            noise_correlation
+        } else {
+            return;
+        };
+        let sources = noise_correlation.correlated_noise_sources();
+        let len = sources.iter().map(|src| 2 * src.num_nodes as usize * src.num_nodes as usize);
+        let mut vals = vec![0f64; len.sum()];
+        noise_correlation.load_noise_correlation(
+            self.data,
+            self.model_data,
+            freq,
+            vals.as_mut_ptr(),
+        );
+
+        let mut vals = vals.chunks_exact(2).map(|val| Complex64::new(val[0], val[1]));
+        for src in sources {
+            let nodes: Vec<_> = src.nodes().iter().map(|pair| self.map_node_pair(pair)).collect();
+            let correlation = vals.by_ref().take(nodes.len() * nodes.len()).collect();
+            dst.push(NoiseDensity { name: unsafe { osdi_str(src.name) }, nodes, correlation })
+        }
+    }
+
+    fn opvars(&self) -> Vec<OpVar> {
+        let num_params = self.descriptor.num_params;
+        let opvars = self.descriptor.opvars().iter().enumerate();
+        // only scalar real opvars are supported
+        let opvars =
+            opvars.filter(|(_, opvar)| opvar.len == 0 && osdi_param_ty(opvar.flags) == Type::Real);
+        opvars
+            .map(|(i, opvar)| {
+                let id = num_params + i as u32;
+                let ptr =
+                    self.descriptor.access(self.data, self.model_data, id, ACCESS_FLAG_INSTANCE);
+                let ptr = ptr as *const f64;
+                if ptr.is_null() {
+                    unreachable!("invalid opvar access")
+                }
+                unsafe {
+                    OpVar {
+                        name: osdi_str(*opvar.name),
+                        value: ptr.read(),
+                        units: osdi_str(opvar.units),
+                        description: osdi_str(opvar.description),
+                    }
+                }
+            })
+            .collect()
+    }
+
+    fn collapsed_nodes(&self) -> Vec<(&'static str, Option<&'static str>)> {
+        let nodes = self.descriptor.nodes();
+        let name = |node: u32| unsafe { osdi_str(nodes[node as usize].name) };
+        self.collapsed_pairs
+            .iter()
+            .map(|&(from, to)| (name(from), if to == u32::MAX { None } else { Some(name(to)) }))
+            .collect()
+    }
+
     fn load_lead_current_resist(&self, _dc_solve: &TiSlice<Node, f64>, dst: &mut [f64]) {
         for (node, dst) in zip(self.descriptor.terminals(), dst) {
             unsafe {
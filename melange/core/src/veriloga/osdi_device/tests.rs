@@ -0,0 +1,60 @@
+use std::cell::Cell;
+
+use super::collapse_node_pairs;
+
+const GND: u32 = u32::MAX;
+
+fn collapse(
+    num_nodes: u32,
+    connected_terminals: u32,
+    pairs: &[(u32, u32)],
+) -> (Vec<u32>, Vec<u32>, Vec<(u32, u32)>) {
+    let node_mapping: Vec<_> = (0..num_nodes).map(|_| Cell::new(0)).collect();
+    let (back_map, collapsed) =
+        collapse_node_pairs(&node_mapping, pairs.iter().copied(), connected_terminals);
+    let node_mapping = node_mapping.iter().map(Cell::get).collect();
+    (node_mapping, back_map, collapsed)
+}
+
+#[test]
+fn no_collapse() {
+    let (node_mapping, back_map, collapsed) = collapse(4, 2, &[]);
+    assert_eq!(node_mapping, [0, 1, 2, 3]);
+    assert_eq!(back_map, [2, 3]);
+    assert_eq!(collapsed, []);
+}
+
+#[test]
+fn collapse_internal_nodes() {
+    // nodes 0 and 1 are terminals, 2 to 4 are internal nodes
+    let pairs = [
+        // internal node into a terminal, the following nodes are shifted down
+        (2, 0),
+        // the terminal is the first node: swapped before checking for terminals and recorded
+        // with the removed node first
+        (0, 3),
+        // both nodes are already mapped to terminal 0
+        (3, 2),
+        // two terminals are never collapsed (independent of order)
+        (0, 1),
+        (1, 0),
+        // a terminal is never collapsed into ground
+        (1, GND),
+        // internal node into ground
+        (4, GND),
+    ];
+    let (node_mapping, back_map, collapsed) = collapse(5, 2, &pairs);
+    assert_eq!(node_mapping, [0, 1, 0, 0, GND]);
+    assert_eq!(back_map, []);
+    assert_eq!(collapsed, [(2, 0), (3, 0), (4, GND)]);
+}
+
+#[test]
+fn collapse_into_internal_node() {
+    // the larger node is always removed and the nodes are collapsed transitively, node 4
+    // is removed first so it is recorded first
+    let (node_mapping, back_map, collapsed) = collapse(5, 1, &[(2, 4), (4, 1), (1, 2), (3, GND)]);
+    assert_eq!(node_mapping, [0, 1, 1, GND, 1]);
+    assert_eq!(back_map, [1]);
+    assert_eq!(collapsed, [(4, 2), (4, 1), (3, GND)]);
+}
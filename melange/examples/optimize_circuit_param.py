@@ -1,44 +1,56 @@
-# MOCKUP currently melange is not complete
-# This file represents a goal API for melange but some details may change
+from pathlib import Path
+
 from melange import Circuit, CircuitInstance
 
 import numpy as np
-from matplotlib import pyplot as plt
-# This file demonstrates optimizing the FET length and width for maximum power gain
+# This file demonstrates optimizing the FET length and width for maximum small signal gain
+# of a common source amplifier at 10 GHz
 
-# simulation parameters 
-time = np.linspace(0,1e-9, 100)
+# simulation parameters
+freq = 10e9
+lengths = np.geomspace(30e-9, 1e-6, 30)
+widths = np.geomspace(1e-6, 1e-2, 40)
 
 # create circuit
 circ = Circuit("test_circuit")
 # load PDK from spectre netlist
-circ.load_netlist("spectre", "pdk/include.scs", "example_corner")
+circ.load_netlist("spectre", Path(__file__).parent / "pdk" / "include.scs", "example_corner")
 
-fet = CircuitInstance(circ, "test_fet", "example_fet", ports=["drain", "gate" ,"gnd"])
+fet = CircuitInstance(circ, "test_fet", "example_fet", ports=["drain", "gate", "gnd"])
 fet.set_param("L", "length")
 fet.set_param("W", "width")
 vdd = CircuitInstance(circ, "vdd", "vsource", ports=["vdd", "gnd"])
-vdd.set_param("dc", 2)
+vdd.set_param("dc", 1.2)
 rf_block = CircuitInstance(circ, "rf_block", "inductor", ports=["vdd", "drain"])
 rf_block.set_param("l", 1e-3)
-vin = CircuitInstance(circ, "vin", "vsource", ports=["gate", "gnd"])
-vin.set_param("type", "sin")
-vin.set_param("sindc", 0.3)
-vin.set_param("amp", 0.5)
-vin.set_param("freq", 10e9)
+vin = CircuitInstance(circ, "vin", "vsource", ports=["in", "gnd"])
+vin.set_param("dc", 0.7)
+vin.set_param("ac", 1)
+rsrc = CircuitInstance(circ, "rsrc", "resistor", ports=["in", "gate"])
+rsrc.set_param("r", 50)
+cout = CircuitInstance(circ, "cout", "capacitor", ports=["drain", "out"])
+cout.set_param("c", 10e-12)
+rload = CircuitInstance(circ, "rload", "resistor", ports=["out", "gnd"])
+rload.set_param("r", 200)
+
 
 def amplifier_gain(length, width):
-    # set circuit parameters and setup simulation
-    simulation = circ.prepare_sim(temp=300, length=length, width = width)
-
-    simulation.tran(time_steps=time)
-    id = simulation.tran_lead_current("inductor", "drain")
-    vd = simulation.tran_voltage("drain")
-    pout = 10*np.log10(np.average(id*vd))
-    ig = simulation.tran_lead_current("vin", "gate")
-    vg = simulation.tran_voltage("gate")
-    pin = 10*np.log10(np.average(ig*vg))
-    gain = pout-pin
-    return gain
-
-# use standard mathematical package like scipy (or machine learning) to opimzie the amplifier_gain function
+    # set circuit parameters and setup simulation, arrays create one simulation per element
+    simulation = circ.prepare_sim(temp=300, length=length, width=width)
+    simulation.dc_op()
+    simulation.ac(freq=freq)
+    vout = simulation.ac_voltage("out")
+    vin = simulation.ac_voltage("in")
+    return 20 * np.log10(np.abs(vout / vin))
+
+
+# evaluate the gain on a grid (all points are simulated by a single vectorized call)
+length, width = np.meshgrid(lengths, widths, indexing="ij")
+gain = amplifier_gain(length.ravel(), width.ravel()).reshape(length.shape)
+best = np.unravel_index(np.argmax(gain), gain.shape)
+print(f"maximum gain {gain[best]:.2f} dB for L = {length[best]:.3g} m and W = {width[best]:.3g} m")
+
+# amplifier_gain can also be passed to standard mathematical packages like scipy
+# (or machine learning) to refine the optimum starting at the best grid point.
+# Transient analysis (tran) is out of scope for melange, so large signal metrics like
+# the power gain of a sinusoidal input can not be optimized yet
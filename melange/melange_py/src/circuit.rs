@@ -0,0 +1,441 @@
+use std::ffi::c_void;
+use std::os::raw::c_char;
+use std::ptr;
+
+use camino::Utf8Path;
+use melange_core::circuit::{CircuitModelSrc, InstanceId};
+use melange_core::elaboration::CircuitInstanceDescription;
+use melange_core::veriloga::Opts;
+use melange_core::{Arena, Circuit, Expr};
+use pyo3_ffi::*;
+
+use crate::ffi::new_type;
+use crate::simulation::PySimulation;
+use crate::util::{
+    parse_args, py_none, py_to_f64, py_to_path, py_to_str, py_to_vec, raise_exception,
+    raise_melange_exception, unlikely,
+};
+
+pub static mut CIRCUIT_TY: PyTypeObject = {
+    let mut res = new_type::<PyCircuit>();
+    res.tp_name = "melange.Circuit\0".as_ptr() as *const c_char;
+    res.tp_doc = "Circuit(name)\n--\n\nA circuit that is built from CircuitInstances and \
+                  simulated with prepare_sim\0"
+        .as_ptr() as *const c_char;
+    res.tp_methods = unsafe { &mut CIRCUIT_METHODS } as *mut _;
+    res.tp_new = Some(PyCircuit::new);
+    res.tp_dealloc = Some(PyCircuit::dealloc);
+    res
+};
+
+static mut CIRCUIT_METHODS: [PyMethodDef; 4] = [
+    PyMethodDef {
+        ml_name: "load_veriloga_file\0".as_ptr() as *const c_char,
+        ml_meth: PyMethodDefPointer { PyCFunctionWithKeywords: PyCircuit::load_veriloga_file },
+        ml_flags: METH_VARARGS | METH_KEYWORDS,
+        ml_doc: "load_veriloga_file(path)\n--\n\ncompiles a Verilog-A file and makes all its \
+                 modules available as devices\0"
+            .as_ptr() as *const c_char,
+    },
+    PyMethodDef {
+        ml_name: "load_netlist\0".as_ptr() as *const c_char,
+        ml_meth: PyMethodDefPointer { PyCFunctionWithKeywords: PyCircuit::load_netlist },
+        ml_flags: METH_VARARGS | METH_KEYWORDS,
+        ml_doc: "load_netlist(format, path, section=None)\n--\n\nadds the models and instances \
+                 of a netlist to the circuit\0"
+            .as_ptr() as *const c_char,
+    },
+    PyMethodDef {
+        ml_name: "prepare_sim\0".as_ptr() as *const c_char,
+        ml_meth: PyMethodDefPointer { PyCFunctionWithKeywords: PyCircuit::prepare_sim },
+        ml_flags: METH_VARARGS | METH_KEYWORDS,
+        ml_doc: "prepare_sim(temp=300.15, **params)\n--\n\ncreates a Simulation of the circuit \
+                 for the temperature temp and the values of the circuit parameters passed as \
+                 keyword arguments. If sequences (like numpy arrays) are passed, a separate \
+                 simulation is prepared for each element and the results of the simulation are \
+                 arrays with the same length\0"
+            .as_ptr() as *const c_char,
+    },
+    unsafe { zero!(PyMethodDef) },
+];
+
+#[repr(C)]
+pub struct PyCircuit {
+    ob_base: PyObject,
+    pub circ: Circuit,
+    pub arena: Arena,
+    opts: Opts,
+    /// The number of simulations that currently borrow `circ`.
+    /// The circuit can only be modified while there are none.
+    pub simulations: usize,
+}
+
+impl PyCircuit {
+    unsafe extern "C" fn new(
+        subtype: *mut PyTypeObject,
+        args: *mut PyObject,
+        kwds: *mut PyObject,
+    ) -> *mut PyObject {
+        let [name] = match parse_args("Circuit", args, kwds, &["name"], 1) {
+            Some(args) => args,
+            None => return ptr::null_mut(),
+        };
+        let name = match py_to_str(name) {
+            Some(name) => name.to_owned(),
+            None => {
+                return raise_exception(PyExc_TypeError, "Circuit() argument 'name' must be a str")
+            }
+        };
+
+        let ptr = PyType_GenericAlloc(subtype, 0);
+        if ptr.is_null() {
+            return ptr::null_mut();
+        }
+        let mut arena = Arena::new();
+        let circ = Circuit::new(name, &mut arena);
+        ptr::write(
+            ptr as *mut PyCircuit,
+            PyCircuit {
+                ob_base: ptr::read(ptr),
+                circ,
+                arena,
+                opts: Opts::default(),
+                simulations: 0,
+            },
+        );
+        ptr
+    }
+
+    unsafe extern "C" fn dealloc(self_: *mut PyObject) {
+        let sel = &mut *(self_ as *mut Self);
+        ptr::drop_in_place(&mut sel.circ);
+        ptr::drop_in_place(&mut sel.arena);
+        ptr::drop_in_place(&mut sel.opts);
+        if let Some(free) = (*ob_type!(self_)).tp_free {
+            free(self_ as *mut c_void)
+        }
+    }
+
+    /// Returns the circuit `obj` if it is a `Circuit`. Otherwise an exception is raised.
+    pub unsafe fn downcast<'a>(obj: *mut PyObject, fun: &str) -> Option<&'a mut PyCircuit> {
+        if unlikely(PyObject_TypeCheck(obj, &mut CIRCUIT_TY) == 0) {
+            raise_exception(
+                PyExc_TypeError,
+                &format!("{fun}() argument 'circ' must have type Circuit"),
+            );
+            return None;
+        }
+        Some(&mut *(obj as *mut PyCircuit))
+    }
+
+    /// Raises an exception and returns `false` if the circuit can not be modified because it
+    /// is borrowed by a simulation.
+    unsafe fn check_mutable(&self, fun: &str) -> bool {
+        if unlikely(self.simulations != 0) {
+            raise_exception(
+                PyExc_RuntimeError,
+                &format!(
+                    "{fun}() can not modify circuit '{}' while a simulation of it exists",
+                    self.circ.name
+                ),
+            );
+            return false;
+        }
+        true
+    }
+
+    unsafe extern "C" fn load_veriloga_file(
+        self_: *mut PyObject,
+        args: *mut PyObject,
+        kwds: *mut PyObject,
+    ) -> *mut PyObject {
+        const FUN: &str = "load_veriloga_file";
+        let [path] = match parse_args(FUN, args, kwds, &["path"], 1) {
+            Some(args) => args,
+            None => return ptr::null_mut(),
+        };
+        let path = match py_to_path(path) {
+            Some(path) => path,
+            None => {
+                return raise_exception(
+                    PyExc_TypeError,
+                    "load_veriloga_file() argument 'path' must be a pathlib Path or str",
+                )
+            }
+        };
+
+        let sel = &mut *(self_ as *mut Self);
+        if !sel.check_mutable(FUN) {
+            return ptr::null_mut();
+        }
+        match sel.circ.load_veriloga_file(path.into(), &sel.opts) {
+            Ok(_) => py_none(),
+            Err(err) => raise_melange_exception(FUN, err),
+        }
+    }
+
+    unsafe extern "C" fn load_netlist(
+        self_: *mut PyObject,
+        args: *mut PyObject,
+        kwds: *mut PyObject,
+    ) -> *mut PyObject {
+        const FUN: &str = "load_netlist";
+        let [format, path, section] =
+            match parse_args(FUN, args, kwds, &["format", "path", "section"], 2) {
+                Some(args) => args,
+                None => return ptr::null_mut(),
+            };
+        let format = match py_to_str(format) {
+            Some(format) => format,
+            None => {
+                return raise_exception(
+                    PyExc_TypeError,
+                    "load_netlist() argument 'format' must be a str",
+                )
+            }
+        };
+        if format != "spectre" {
+            return raise_exception(
+                PyExc_NotImplementedError,
+                &format!("{FUN}() netlists in the '{format}' format can not be parsed yet"),
+            );
+        }
+        let path = match py_to_path(path) {
+            Some(path) => path,
+            None => {
+                return raise_exception(
+                    PyExc_TypeError,
+                    "load_netlist() argument 'path' must be a pathlib Path or str",
+                )
+            }
+        };
+        let section = if section.is_null() || section == Py_None() {
+            None
+        } else {
+            match py_to_str(section) {
+                Some(section) => Some(section),
+                None => {
+                    return raise_exception(
+                        PyExc_TypeError,
+                        "load_netlist() argument 'section' must be a str or None",
+                    )
+                }
+            }
+        };
+
+        let sel = &mut *(self_ as *mut Self);
+        if !sel.check_mutable(FUN) {
+            return ptr::null_mut();
+        }
+        match sel.circ.load_spectre_netlist(
+            Utf8Path::new(&path),
+            section,
+            &mut sel.arena,
+            &sel.opts,
+        ) {
+            Ok(()) => py_none(),
+            Err(err) => raise_melange_exception(FUN, err),
+        }
+    }
+
+    unsafe extern "C" fn prepare_sim(
+        self_: *mut PyObject,
+        args: *mut PyObject,
+        kwds: *mut PyObject,
+    ) -> *mut PyObject {
+        if unlikely(PyTuple_GET_SIZE(args) != 0) {
+            return raise_exception(PyExc_TypeError, "prepare_sim() takes only keyword arguments");
+        }
+        PySimulation::new(self_, kwds)
+    }
+
+    /// Converts the value of a device parameter to an expression. Numbers are used directly,
+    /// a str refers to the circuit parameter with that name (which is created if necessary).
+    unsafe fn param_expr(&mut self, fun: &str, name: &str, val: *mut PyObject) -> Option<Expr> {
+        if let Some(val) = py_to_f64(val) {
+            return Some(val.into());
+        }
+
+        let param = match py_to_str(val) {
+            Some(param) => param,
+            None => {
+                raise_exception(
+                    PyExc_TypeError,
+                    &format!("{fun}() the value of '{name}' must be a number or a str"),
+                );
+                return None;
+            }
+        };
+
+        if let Some((_, expr)) = self.circ.lookup_param(param, &self.arena) {
+            return Some(expr);
+        }
+        match self.circ.def_param(param.to_owned(), None, &mut self.arena) {
+            Ok((_, expr)) => Some(expr),
+            Err(err) => {
+                raise_melange_exception(fun, err);
+                None
+            }
+        }
+    }
+}
+
+/// Returns the name of the circuit node called `name`. Just like in spectre netlists `0`
+/// (and `gnd`) refer to the ground node.
+pub fn node_name(name: &str) -> &str {
+    match name {
+        "0" | "gnd" => "ground",
+        _ => name,
+    }
+}
+
+pub static mut CIRCUIT_INSTANCE_TY: PyTypeObject = {
+    let mut res = new_type::<PyCircuitInstance>();
+    res.tp_name = "melange.CircuitInstance\0".as_ptr() as *const c_char;
+    res.tp_doc = "CircuitInstance(circ, name, device, ports=[])\n--\n\nAn instance of a device \
+                  (or model) within a circuit whose terminals are connected to the nodes \
+                  called ports (0 and gnd refer to the ground node)\0"
+        .as_ptr() as *const c_char;
+    res.tp_methods = unsafe { &mut CIRCUIT_INSTANCE_METHODS } as *mut _;
+    res.tp_new = Some(PyCircuitInstance::new);
+    res.tp_dealloc = Some(PyCircuitInstance::dealloc);
+    res
+};
+
+static mut CIRCUIT_INSTANCE_METHODS: [PyMethodDef; 2] = [
+    PyMethodDef {
+        ml_name: "set_param\0".as_ptr() as *const c_char,
+        ml_meth: PyMethodDefPointer { PyCFunctionWithKeywords: PyCircuitInstance::set_param },
+        ml_flags: METH_VARARGS | METH_KEYWORDS,
+        ml_doc: "set_param(name, value)\n--\n\nsets the parameter name of the instance to a \
+                 number or to the circuit parameter called value if a str is passed\0"
+            .as_ptr() as *const c_char,
+    },
+    unsafe { zero!(PyMethodDef) },
+];
+
+#[repr(C)]
+pub struct PyCircuitInstance {
+    ob_base: PyObject,
+    /// the circuit that contains this instance
+    pub circ: *mut PyObject,
+    pub inst: InstanceId,
+}
+
+impl PyCircuitInstance {
+    unsafe extern "C" fn new(
+        subtype: *mut PyTypeObject,
+        args: *mut PyObject,
+        kwds: *mut PyObject,
+    ) -> *mut PyObject {
+        const FUN: &str = "CircuitInstance";
+        let [circ_obj, name, device, ports] =
+            match parse_args(FUN, args, kwds, &["circ", "name", "device", "ports"], 3) {
+                Some(args) => args,
+                None => return ptr::null_mut(),
+            };
+        let circ = match PyCircuit::downcast(circ_obj, FUN) {
+            Some(circ) => circ,
+            None => return ptr::null_mut(),
+        };
+        let (name, device) = match (py_to_str(name), py_to_str(device)) {
+            (Some(name), Some(device)) => (name.to_owned(), device.to_owned()),
+            _ => {
+                return raise_exception(
+                    PyExc_TypeError,
+                    "CircuitInstance() arguments 'name' and 'device' must be a str",
+                )
+            }
+        };
+        let ports = if ports.is_null() {
+            Vec::new()
+        } else {
+            match py_to_vec(ports, |port| py_to_str(port).map(|port| node_name(port).to_owned())) {
+                Some(ports) => ports,
+                None => {
+                    return raise_exception(
+                        PyExc_TypeError,
+                        "CircuitInstance() argument 'ports' must be a list of str",
+                    )
+                }
+            }
+        };
+
+        if !circ.check_mutable(FUN) {
+            return ptr::null_mut();
+        }
+        let descr = CircuitInstanceDescription {
+            name,
+            master: device,
+            parameters: Vec::new(),
+            terminal_connections: ports,
+        };
+        let inst = match circ.circ.elaborate_instance(descr) {
+            Ok(inst) => inst,
+            Err(err) => return raise_melange_exception(FUN, err),
+        };
+
+        let ptr = PyType_GenericAlloc(subtype, 0);
+        if ptr.is_null() {
+            return ptr::null_mut();
+        }
+        Py_INCREF(circ_obj);
+        ptr::write(
+            ptr as *mut PyCircuitInstance,
+            PyCircuitInstance { ob_base: ptr::read(ptr), circ: circ_obj, inst },
+        );
+        ptr
+    }
+
+    unsafe extern "C" fn dealloc(self_: *mut PyObject) {
+        let sel = &mut *(self_ as *mut Self);
+        Py_DECREF(sel.circ);
+        if let Some(free) = (*ob_type!(self_)).tp_free {
+            free(self_ as *mut c_void)
+        }
+    }
+
+    unsafe extern "C" fn set_param(
+        self_: *mut PyObject,
+        args: *mut PyObject,
+        kwds: *mut PyObject,
+    ) -> *mut PyObject {
+        const FUN: &str = "set_param";
+        let [name, val] = match parse_args(FUN, args, kwds, &["name", "value"], 2) {
+            Some(args) => args,
+            None => return ptr::null_mut(),
+        };
+        let name = match py_to_str(name) {
+            Some(name) => name,
+            None => {
+                return raise_exception(
+                    PyExc_TypeError,
+                    "set_param() argument 'name' must be a str",
+                )
+            }
+        };
+
+        let sel = &*(self_ as *const Self);
+        let circ = &mut *(sel.circ as *mut PyCircuit);
+        if !circ.check_mutable(FUN) {
+            return ptr::null_mut();
+        }
+        let val = match circ.param_expr(FUN, name, val) {
+            Some(val) => val,
+            None => return ptr::null_mut(),
+        };
+
+        // instances of devices own an implicit model that receives all parameters (just like
+        // during elaboration of a netlist)
+        let model = circ.circ[sel.inst].model;
+        let res = if circ.circ[model].src == CircuitModelSrc::Implicit(sel.inst) {
+            circ.circ.set_model_param(model, name, val)
+        } else {
+            circ.circ.set_instance_param(sel.inst, name, val)
+        };
+        match res {
+            Ok(()) => py_none(),
+            Err(err) => raise_melange_exception(FUN, err),
+        }
+    }
+}
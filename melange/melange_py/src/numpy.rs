@@ -0,0 +1,65 @@
+use std::os::raw::c_char;
+use std::ptr;
+use std::sync::Once;
+
+use pyo3_ffi::*;
+
+/// `numpy.array` or null if numpy is not installed
+static mut NUMPY_ARRAY: *mut PyObject = 0 as *mut PyObject;
+
+static INIT: Once = Once::new();
+
+#[cold]
+pub fn init_numpy() {
+    INIT.call_once(|| unsafe {
+        let numpy = PyImport_ImportModule("numpy\0".as_ptr() as *const c_char);
+        if numpy.is_null() {
+            PyErr_Clear();
+            return;
+        }
+        NUMPY_ARRAY = PyObject_GetAttrString(numpy, "array\0".as_ptr() as *const c_char);
+        if NUMPY_ARRAY.is_null() {
+            PyErr_Clear();
+        }
+        Py_DECREF(numpy);
+    });
+}
+
+/// Creates a float64 numpy array (or a list if numpy is not installed).
+pub unsafe fn new_real_array(vals: &[f64]) -> *mut PyObject {
+    new_array(vals.iter().map(|&val| PyFloat_FromDouble(val)), vals.len())
+}
+
+/// Creates a complex128 numpy array (or a list if numpy is not installed) from the real and
+/// imaginary parts of each element.
+pub unsafe fn new_complex_array(vals: &[(f64, f64)]) -> *mut PyObject {
+    new_array(vals.iter().map(|&(re, im)| PyComplex_FromDoubles(re, im)), vals.len())
+}
+
+/// Creates an array from `len` new references in `items`.
+/// Returns null if an exception was raised.
+unsafe fn new_array(items: impl Iterator<Item = *mut PyObject>, len: usize) -> *mut PyObject {
+    let list = PyList_New(len as Py_ssize_t);
+    if list.is_null() {
+        return ptr::null_mut();
+    }
+
+    let mut failed = false;
+    for (i, item) in items.enumerate() {
+        failed |= item.is_null();
+        PyList_SET_ITEM(list, i as Py_ssize_t, item);
+    }
+
+    if failed {
+        Py_DECREF(list);
+        return ptr::null_mut();
+    }
+
+    if NUMPY_ARRAY.is_null() {
+        return list;
+    }
+
+    let res = PyObject_CallFunctionObjArgs(NUMPY_ARRAY, list, ptr::null_mut::<PyObject>());
+    Py_DECREF(list);
+    res
+}
@@ -0,0 +1,537 @@
+use std::f64::consts::TAU;
+use std::ffi::c_void;
+use std::os::raw::c_char;
+use std::ptr;
+
+use anyhow::{Context, Result};
+use melange_core::circuit::{InstanceId, Node};
+use melange_core::simulation::{SimConfig, Simulation};
+use melange_core::{Circuit, CircuitParam, ExprEvalCtx};
+use pyo3_ffi::*;
+
+use crate::circuit::{node_name, PyCircuit, PyCircuitInstance, CIRCUIT_INSTANCE_TY};
+use crate::ffi::new_type;
+use crate::numpy::{new_complex_array, new_real_array};
+use crate::util::{
+    parse_args, py_none, py_to_f64, py_to_str, py_to_vec, raise_exception, raise_melange_exception,
+    unlikely,
+};
+
+/// The temperature (in Kelvin) that is used if `prepare_sim` is called without `temp`
+const DEFAULT_TEMP: f64 = 300.15;
+
+pub static mut SIMULATION_TY: PyTypeObject = {
+    let mut res = new_type::<PySimulation>();
+    res.tp_name = "melange.Simulation\0".as_ptr() as *const c_char;
+    res.tp_doc = "A simulation of a circuit created with Circuit.prepare_sim.\nThe circuit can \
+                  not be modified while a simulation of it exists\0"
+        .as_ptr() as *const c_char;
+    res.tp_methods = unsafe { &mut SIMULATION_METHODS } as *mut _;
+    res.tp_dealloc = Some(PySimulation::dealloc);
+    res
+};
+
+static mut SIMULATION_METHODS: [PyMethodDef; 8] = [
+    PyMethodDef {
+        ml_name: "dc_op\0".as_ptr() as *const c_char,
+        ml_meth: PyMethodDefPointer { PyCFunction: PySimulation::dc_op },
+        ml_flags: METH_NOARGS,
+        ml_doc: "dc_op()\n--\n\nsolves the dc operating point, the operating point of the \
+                 previous parameter value is used as the initial guess for a sweep\0"
+            .as_ptr() as *const c_char,
+    },
+    PyMethodDef {
+        ml_name: "voltage\0".as_ptr() as *const c_char,
+        ml_meth: PyMethodDefPointer { PyCFunctionWithKeywords: PySimulation::voltage },
+        ml_flags: METH_VARARGS | METH_KEYWORDS,
+        ml_doc: "voltage(node)\n--\n\nreturns the voltage of node at the dc operating point\0"
+            .as_ptr() as *const c_char,
+    },
+    PyMethodDef {
+        ml_name: "lead_current\0".as_ptr() as *const c_char,
+        ml_meth: PyMethodDefPointer { PyCFunctionWithKeywords: PySimulation::lead_current },
+        ml_flags: METH_VARARGS | METH_KEYWORDS,
+        ml_doc: "lead_current(instance, terminal)\n--\n\nreturns the current that flows into \
+                 the terminal (a name or index) of instance (a CircuitInstance or its name) at \
+                 the dc operating point\0"
+            .as_ptr() as *const c_char,
+    },
+    PyMethodDef {
+        ml_name: "ac\0".as_ptr() as *const c_char,
+        ml_meth: PyMethodDefPointer { PyCFunctionWithKeywords: PySimulation::ac },
+        ml_flags: METH_VARARGS | METH_KEYWORDS,
+        ml_doc: "ac(freq)\n--\n\nperforms a small signal analysis at the frequency freq\0".as_ptr()
+            as *const c_char,
+    },
+    PyMethodDef {
+        ml_name: "ac_voltage\0".as_ptr() as *const c_char,
+        ml_meth: PyMethodDefPointer { PyCFunctionWithKeywords: PySimulation::ac_voltage },
+        ml_flags: METH_VARARGS | METH_KEYWORDS,
+        ml_doc: "ac_voltage(node)\n--\n\nreturns the complex small signal voltage of node \
+                 calculated by the last call to ac\0"
+            .as_ptr() as *const c_char,
+    },
+    PyMethodDef {
+        ml_name: "ac_lead_current\0".as_ptr() as *const c_char,
+        ml_meth: PyMethodDefPointer { PyCFunctionWithKeywords: PySimulation::ac_lead_current },
+        ml_flags: METH_VARARGS | METH_KEYWORDS,
+        ml_doc: "ac_lead_current(instance, terminal)\n--\n\nreturns the complex small signal \
+                 current that flows into the terminal of instance calculated by the last call \
+                 to ac\0"
+            .as_ptr() as *const c_char,
+    },
+    PyMethodDef {
+        ml_name: "tran\0".as_ptr() as *const c_char,
+        ml_meth: PyMethodDefPointer { PyCFunctionWithKeywords: PySimulation::tran },
+        ml_flags: METH_VARARGS | METH_KEYWORDS,
+        ml_doc: "tran(time_steps)\n--\n\ntransient analysis, out of scope for now: melange only \
+                 supports dc and ac analyses and this method always raises NotImplementedError\0"
+            .as_ptr() as *const c_char,
+    },
+    unsafe { zero!(PyMethodDef) },
+];
+
+#[repr(C)]
+pub struct PySimulation {
+    ob_base: PyObject,
+    /// the simulated circuit which must outlive (and can not be modified by) `points`
+    circ: *mut PyObject,
+    /// a simulation for each value of the swept circuit parameters
+    points: Vec<Simulation<'static>>,
+    /// whether any circuit parameters were swept, otherwise results are returned as scalars
+    sweep: bool,
+    /// whether the dc operating point of all points was solved
+    dc_solved: bool,
+    /// the frequency of the last ac analysis
+    freq: Option<f64>,
+}
+
+impl PySimulation {
+    /// Implementation of `Circuit.prepare_sim(temp=300.15, **params)`.
+    #[allow(clippy::new_ret_no_self)]
+    pub unsafe fn new(circ_obj: *mut PyObject, kwds: *mut PyObject) -> *mut PyObject {
+        const FUN: &str = "prepare_sim";
+        let circ = &mut *(circ_obj as *mut PyCircuit);
+
+        let mut params = vec![(CircuitParam::TEMPERATURE, vec![DEFAULT_TEMP])];
+        let mut len = None;
+        if !kwds.is_null() {
+            let mut pos = 0;
+            let mut name: *mut PyObject = ptr::null_mut();
+            let mut val: *mut PyObject = ptr::null_mut();
+            while PyDict_Next(kwds, &mut pos, &mut name, &mut val) != 0 {
+                let name = py_to_str(name).unwrap_or_default();
+                let (vals, sweep) = match py_to_f64(val) {
+                    Some(val) => (vec![val], false),
+                    None => match py_to_vec(val, |val| py_to_f64(val)) {
+                        Some(vals) => (vals, true),
+                        None => {
+                            return raise_exception(
+                                PyExc_TypeError,
+                                &format!(
+                                    "{FUN}() argument '{name}' must be a number or a sequence \
+                                     of numbers"
+                                ),
+                            )
+                        }
+                    },
+                };
+
+                if sweep {
+                    match len {
+                        Some(len) if unlikely(len != vals.len()) => {
+                            return raise_exception(
+                                PyExc_ValueError,
+                                &format!(
+                                    "{FUN}() all swept parameters must have the same length but \
+                                     '{name}' has length {} (expected {len})",
+                                    vals.len()
+                                ),
+                            )
+                        }
+                        _ => len = Some(vals.len()),
+                    }
+                }
+
+                if name == "temp" {
+                    params[0].1 = vals;
+                    continue;
+                }
+                match circ.circ.lookup_param(name, &circ.arena) {
+                    Some((param, _)) => params.push((param, vals)),
+                    None => {
+                        return raise_exception(
+                            PyExc_TypeError,
+                            &format!("{FUN}() got an unknown circuit parameter '{name}'"),
+                        )
+                    }
+                }
+            }
+        }
+
+        // SAFETY: the simulations are dropped before the reference to the circuit is released
+        // and the circuit is not modified while a simulation exists
+        let sim_circ: &'static Circuit = &*(&circ.circ as *const Circuit);
+        let mut ctx = ExprEvalCtx::new(&circ.arena);
+        let mut points = Vec::with_capacity(len.unwrap_or(1));
+        for i in 0..len.unwrap_or(1) {
+            for (param, vals) in &params {
+                let val = if vals.len() == 1 { vals[0] } else { vals[i] };
+                ctx.set_param(*param, val.into());
+            }
+            match sim_circ.prepare_simulation(ctx.borrow(), &circ.arena, SimConfig::default()) {
+                Ok(sim) => points.push(sim),
+                Err(err) => return raise_melange_exception(FUN, err),
+            }
+        }
+
+        let ptr = PyType_GenericAlloc(&mut SIMULATION_TY, 0);
+        if ptr.is_null() {
+            return ptr::null_mut();
+        }
+        Py_INCREF(circ_obj);
+        circ.simulations += 1;
+        ptr::write(
+            ptr as *mut PySimulation,
+            PySimulation {
+                ob_base: ptr::read(ptr),
+                circ: circ_obj,
+                points,
+                sweep: len.is_some(),
+                dc_solved: false,
+                freq: None,
+            },
+        );
+        ptr
+    }
+
+    unsafe extern "C" fn dealloc(self_: *mut PyObject) {
+        let sel = &mut *(self_ as *mut Self);
+        ptr::drop_in_place(&mut sel.points);
+        (*(sel.circ as *mut PyCircuit)).simulations -= 1;
+        Py_DECREF(sel.circ);
+        if let Some(free) = (*ob_type!(self_)).tp_free {
+            free(self_ as *mut c_void)
+        }
+    }
+
+    unsafe fn circ<'a>(&self) -> &'a PyCircuit {
+        &*(self.circ as *const PyCircuit)
+    }
+
+    /// Solves the dc operating point of all points (if that did not happen already).
+    fn solve_dc(&mut self) -> Result<()> {
+        if self.dc_solved {
+            return Ok(());
+        }
+
+        for i in 0..self.points.len() {
+            let (prev, rest) = self.points.split_at_mut(i);
+            let sim = &mut rest[0];
+            // the operating point of the previous parameter value is usually a good initial
+            // guess (unless different internal nodes were collapsed)
+            if let Some(prev) = prev.last_mut() {
+                let guess = prev.dc_op()?;
+                if guess.len() == sim.nodes().len() {
+                    sim.set_initial_guess(guess);
+                }
+            }
+            let res = sim.dc_op();
+            if self.sweep {
+                res.with_context(|| format!("dc operating point {i} failed"))?;
+            } else {
+                res?;
+            }
+        }
+
+        self.dc_solved = true;
+        Ok(())
+    }
+
+    unsafe extern "C" fn dc_op(self_: *mut PyObject, _args: *mut PyObject) -> *mut PyObject {
+        let sel = &mut *(self_ as *mut Self);
+        match sel.solve_dc() {
+            Ok(()) => py_none(),
+            Err(err) => raise_melange_exception("dc_op", err),
+        }
+    }
+
+    unsafe extern "C" fn voltage(
+        self_: *mut PyObject,
+        args: *mut PyObject,
+        kwds: *mut PyObject,
+    ) -> *mut PyObject {
+        const FUN: &str = "voltage";
+        let sel = &mut *(self_ as *mut Self);
+        let node = match sel.parse_node(FUN, args, kwds) {
+            Some(node) => node,
+            None => return ptr::null_mut(),
+        };
+        if let Err(err) = sel.solve_dc() {
+            return raise_melange_exception(FUN, err);
+        }
+
+        let mut res = Vec::with_capacity(sel.points.len());
+        for sim in &mut sel.points {
+            match sim.dc_op() {
+                Ok(solution) => res.push(solution[node]),
+                Err(err) => return raise_melange_exception(FUN, err),
+            }
+        }
+        sel.real_result(&res)
+    }
+
+    unsafe extern "C" fn lead_current(
+        self_: *mut PyObject,
+        args: *mut PyObject,
+        kwds: *mut PyObject,
+    ) -> *mut PyObject {
+        const FUN: &str = "lead_current";
+        let sel = &mut *(self_ as *mut Self);
+        let (inst, terminal) = match sel.parse_terminal(FUN, args, kwds) {
+            Some(res) => res,
+            None => return ptr::null_mut(),
+        };
+        if let Err(err) = sel.solve_dc() {
+            return raise_melange_exception(FUN, err);
+        }
+
+        let mut res = Vec::with_capacity(sel.points.len());
+        for sim in &mut sel.points {
+            match sim.dc_lead_current(inst) {
+                Ok(currents) => res.push(currents[terminal]),
+                Err(err) => return raise_melange_exception(FUN, err),
+            }
+        }
+        sel.real_result(&res)
+    }
+
+    unsafe extern "C" fn ac(
+        self_: *mut PyObject,
+        args: *mut PyObject,
+        kwds: *mut PyObject,
+    ) -> *mut PyObject {
+        const FUN: &str = "ac";
+        let [freq] = match parse_args(FUN, args, kwds, &["freq"], 1) {
+            Some(args) => args,
+            None => return ptr::null_mut(),
+        };
+        let freq = match py_to_f64(freq) {
+            Some(freq) => freq,
+            None => {
+                return raise_exception(PyExc_TypeError, "ac() argument 'freq' must be a float")
+            }
+        };
+
+        let sel = &mut *(self_ as *mut Self);
+        for sim in &mut sel.points {
+            sim.set_omega(TAU * freq);
+            if let Err(err) = sim.ac() {
+                return raise_melange_exception(FUN, err);
+            }
+        }
+        sel.freq = Some(freq);
+        py_none()
+    }
+
+    /// Raises an exception and returns `false` if no ac analysis was performed yet.
+    fn check_ac(&self, fun: &str) -> bool {
+        if unlikely(self.freq.is_none()) {
+            raise_exception(
+                unsafe { PyExc_RuntimeError },
+                &format!("{fun}() requires a previous call to ac()"),
+            );
+            return false;
+        }
+        true
+    }
+
+    unsafe extern "C" fn ac_voltage(
+        self_: *mut PyObject,
+        args: *mut PyObject,
+        kwds: *mut PyObject,
+    ) -> *mut PyObject {
+        const FUN: &str = "ac_voltage";
+        let sel = &mut *(self_ as *mut Self);
+        let node = match sel.parse_node(FUN, args, kwds) {
+            Some(node) => node,
+            None => return ptr::null_mut(),
+        };
+        if !sel.check_ac(FUN) {
+            return ptr::null_mut();
+        }
+
+        let mut res = Vec::with_capacity(sel.points.len());
+        for sim in &mut sel.points {
+            match sim.ac() {
+                Ok(solution) => res.push((solution[node].re, solution[node].im)),
+                Err(err) => return raise_melange_exception(FUN, err),
+            }
+        }
+        sel.complex_result(&res)
+    }
+
+    unsafe extern "C" fn ac_lead_current(
+        self_: *mut PyObject,
+        args: *mut PyObject,
+        kwds: *mut PyObject,
+    ) -> *mut PyObject {
+        const FUN: &str = "ac_lead_current";
+        let sel = &mut *(self_ as *mut Self);
+        let (inst, terminal) = match sel.parse_terminal(FUN, args, kwds) {
+            Some(res) => res,
+            None => return ptr::null_mut(),
+        };
+        if !sel.check_ac(FUN) {
+            return ptr::null_mut();
+        }
+
+        let mut res = Vec::with_capacity(sel.points.len());
+        for sim in &mut sel.points {
+            match sim.ac_lead_current(inst) {
+                Ok(currents) => res.push((currents[terminal].re, currents[terminal].im)),
+                Err(err) => return raise_melange_exception(FUN, err),
+            }
+        }
+        sel.complex_result(&res)
+    }
+
+    unsafe extern "C" fn tran(
+        _self: *mut PyObject,
+        _args: *mut PyObject,
+        _kwds: *mut PyObject,
+    ) -> *mut PyObject {
+        raise_exception(
+            PyExc_NotImplementedError,
+            "tran() transient analysis is out of scope for melange, use dc_op() or ac() instead",
+        )
+    }
+
+    unsafe fn real_result(&self, vals: &[f64]) -> *mut PyObject {
+        if self.sweep {
+            new_real_array(vals)
+        } else {
+            PyFloat_FromDouble(vals[0])
+        }
+    }
+
+    unsafe fn complex_result(&self, vals: &[(f64, f64)]) -> *mut PyObject {
+        if self.sweep {
+            new_complex_array(vals)
+        } else {
+            PyComplex_FromDoubles(vals[0].0, vals[0].1)
+        }
+    }
+
+    /// Parses the `node` argument of `fun`. Returns `None` if an exception was raised.
+    unsafe fn parse_node(
+        &self,
+        fun: &str,
+        args: *mut PyObject,
+        kwds: *mut PyObject,
+    ) -> Option<Node> {
+        let [node] = parse_args(fun, args, kwds, &["node"], 1)?;
+        let name = match py_to_str(node) {
+            Some(name) => name,
+            None => {
+                raise_exception(PyExc_TypeError, &format!("{fun}() argument 'node' must be a str"));
+                return None;
+            }
+        };
+        let node = self.circ().circ.lookup_node(node_name(name));
+        if node.is_none() {
+            raise_exception(PyExc_ValueError, &format!("{fun}() unknown node '{name}'"));
+        }
+        node
+    }
+
+    /// Parses the `instance` and `terminal` arguments of `fun` and returns the instance and
+    /// the position of the terminal. Returns `None` if an exception was raised.
+    unsafe fn parse_terminal(
+        &self,
+        fun: &str,
+        args: *mut PyObject,
+        kwds: *mut PyObject,
+    ) -> Option<(InstanceId, usize)> {
+        let [inst, terminal] = parse_args(fun, args, kwds, &["instance", "terminal"], 2)?;
+        let circ = &self.circ().circ;
+
+        let inst = if PyObject_TypeCheck(inst, &mut CIRCUIT_INSTANCE_TY) != 0 {
+            let inst = &*(inst as *const PyCircuitInstance);
+            if unlikely(inst.circ != self.circ) {
+                raise_exception(
+                    PyExc_ValueError,
+                    &format!("{fun}() the instance belongs to a different circuit"),
+                );
+                return None;
+            }
+            inst.inst
+        } else {
+            let name = match py_to_str(inst) {
+                Some(name) => name,
+                None => {
+                    raise_exception(
+                        PyExc_TypeError,
+                        &format!("{fun}() argument 'instance' must be a CircuitInstance or a str"),
+                    );
+                    return None;
+                }
+            };
+            match circ.lookup_instance(name) {
+                Some(inst) => inst,
+                None => {
+                    raise_exception(
+                        PyExc_ValueError,
+                        &format!("{fun}() unknown instance '{name}'"),
+                    );
+                    return None;
+                }
+            }
+        };
+
+        let info = &circ[inst];
+        let dev = &circ[circ[info.model].device];
+        let pos = if let Some(name) = py_to_str(terminal) {
+            match dev.terminal(name) {
+                Some(pos) => pos,
+                None => {
+                    raise_exception(
+                        PyExc_ValueError,
+                        &format!(
+                            "{fun}() '{}' has no terminal '{name}' (expected one of {:?})",
+                            info.name, dev.terminals
+                        ),
+                    );
+                    return None;
+                }
+            }
+        } else {
+            let pos = PyLong_AsLong(terminal);
+            if pos == -1 && !PyErr_Occurred().is_null() {
+                PyErr_Clear();
+                raise_exception(
+                    PyExc_TypeError,
+                    &format!("{fun}() argument 'terminal' must be a str or an int"),
+                );
+                return None;
+            }
+            if unlikely(pos < 0) {
+                raise_exception(
+                    PyExc_ValueError,
+                    &format!("{fun}() argument 'terminal' must not be negative"),
+                );
+                return None;
+            }
+            pos as usize
+        };
+
+        if unlikely(pos >= info.connections.len()) {
+            raise_exception(
+                PyExc_ValueError,
+                &format!("{fun}() terminal {pos} of '{}' is not connected", info.name),
+            );
+            return None;
+        }
+        Some((inst, pos))
+    }
+}
@@ -0,0 +1,167 @@
+use std::ffi::CStr;
+use std::os::raw::c_char;
+use std::ptr;
+
+use pyo3_ffi::*;
+
+#[inline]
+#[cold]
+fn cold() {}
+
+#[inline]
+pub fn unlikely(b: bool) -> bool {
+    if b {
+        cold()
+    }
+    b
+}
+
+#[cold]
+#[inline(never)]
+pub fn raise_exception(ty: *mut PyObject, msg: &str) -> *mut PyObject {
+    unsafe {
+        let err_msg =
+            PyUnicode_FromStringAndSize(msg.as_ptr() as *const c_char, msg.len() as isize);
+        PyErr_SetObject(ty, err_msg);
+        Py_DECREF(err_msg);
+    };
+    ptr::null_mut()
+}
+
+/// Raises a `RuntimeError` for an error (and all its causes) reported by melange.
+#[cold]
+#[inline(never)]
+pub fn raise_melange_exception(fun: &str, err: anyhow::Error) -> *mut PyObject {
+    raise_exception(unsafe { PyExc_RuntimeError }, &format!("{fun}() failed: {err:#}"))
+}
+
+pub unsafe fn py_none() -> *mut PyObject {
+    Py_INCREF(Py_None());
+    Py_None()
+}
+
+/// Parses the positional and keyword arguments `args`/`kwds` of `fun` that accepts the
+/// parameters `params`. The first `required` parameters must always be provided, the others
+/// are null if they are missing. Returns `None` if an exception was raised.
+pub unsafe fn parse_args<const N: usize>(
+    fun: &str,
+    args: *mut PyObject,
+    kwds: *mut PyObject,
+    params: &[&str; N],
+    required: usize,
+) -> Option<[*mut PyObject; N]> {
+    let num_args = PyTuple_GET_SIZE(args) as usize;
+    if unlikely(num_args > N) {
+        raise_exception(
+            PyExc_TypeError,
+            &format!("{fun}() takes at most {N} arguments ({num_args} given)"),
+        );
+        return None;
+    }
+
+    let mut res = [ptr::null_mut(); N];
+    for (i, dst) in res.iter_mut().enumerate().take(num_args) {
+        *dst = PyTuple_GET_ITEM(args, i as Py_ssize_t);
+    }
+
+    if !kwds.is_null() {
+        let mut pos = 0;
+        let mut name: *mut PyObject = ptr::null_mut();
+        let mut val: *mut PyObject = ptr::null_mut();
+        while PyDict_Next(kwds, &mut pos, &mut name, &mut val) != 0 {
+            let name = py_to_str(name).unwrap_or_default();
+            match params.iter().position(|&param| param == name) {
+                Some(i) if res[i].is_null() => res[i] = val,
+                Some(_) => {
+                    raise_exception(
+                        PyExc_TypeError,
+                        &format!("{fun}() got multiple values for argument '{name}'"),
+                    );
+                    return None;
+                }
+                None => {
+                    raise_exception(
+                        PyExc_TypeError,
+                        &format!("{fun}() got an unexpected keyword argument '{name}'"),
+                    );
+                    return None;
+                }
+            }
+        }
+    }
+
+    if let Some(i) = res[..required].iter().position(|arg| arg.is_null()) {
+        raise_exception(
+            PyExc_TypeError,
+            &format!("{fun}() missing required argument '{}'", params[i]),
+        );
+        return None;
+    }
+
+    Some(res)
+}
+
+/// Returns the contents of the str `obj`. The result borrows from `obj` and must be copied if
+/// it is used after `obj` is released.
+pub unsafe fn py_to_str<'a>(obj: *mut PyObject) -> Option<&'a str> {
+    let res = PyUnicode_AsUTF8(obj);
+    if res.is_null() {
+        PyErr_Clear();
+        return None;
+    }
+    Some(CStr::from_ptr(res).to_str().unwrap())
+}
+
+/// Converts a str or pathlib Path to a string.
+pub unsafe fn py_to_path(obj: *mut PyObject) -> Option<String> {
+    let path = PyOS_FSPath(obj);
+    if path.is_null() {
+        PyErr_Clear();
+        return None;
+    }
+    let res = py_to_str(path).map(str::to_owned);
+    Py_DECREF(path);
+    res
+}
+
+/// Converts any number (including numpy scalars) but not a str to a float.
+pub unsafe fn py_to_f64(obj: *mut PyObject) -> Option<f64> {
+    if PyUnicode_Check(obj) != 0 {
+        return None;
+    }
+    let val = PyFloat_AsDouble(obj);
+    if val == -1.0 && !PyErr_Occurred().is_null() {
+        PyErr_Clear();
+        return None;
+    }
+    Some(val)
+}
+
+/// Copies the elements of a sequence (like a list or a numpy array) that is not a str.
+/// Returns `None` if `obj` is not a sequence or if any element can not be converted by `conv`.
+pub unsafe fn py_to_vec<T>(
+    obj: *mut PyObject,
+    conv: impl Fn(*mut PyObject) -> Option<T>,
+) -> Option<Vec<T>> {
+    if PySequence_Check(obj) == 0 || PyUnicode_Check(obj) != 0 {
+        return None;
+    }
+    let len = PySequence_Size(obj);
+    if len < 0 {
+        PyErr_Clear();
+        return None;
+    }
+
+    let mut res = Vec::with_capacity(len as usize);
+    for i in 0..len {
+        let item = PySequence_GetItem(obj, i);
+        if item.is_null() {
+            PyErr_Clear();
+            return None;
+        }
+        let val = conv(item);
+        Py_DECREF(item);
+        res.push(val?);
+    }
+    Some(res)
+}
@@ -0,0 +1,85 @@
+from pathlib import Path
+import numpy as np
+from melange import Circuit, CircuitInstance
+
+tests = Path(__file__).parent
+pdk = tests.parent.parent / "examples" / "pdk" / "include.scs"
+
+
+def expect_error(ty, fun, *args, **kwargs):
+    try:
+        fun(*args, **kwargs)
+    except ty:
+        return
+    raise AssertionError(f"{fun.__name__} did not raise {ty.__name__}")
+
+
+# dc operating point of a voltage divider
+circ = Circuit("divider")
+vdd = CircuitInstance(circ, "vdd", "vsource", ports=["supply", "gnd"])
+vdd.set_param("dc", "vsupply")
+vdd.set_param("ac", 1)
+r1 = CircuitInstance(circ, "r1", "resistor", ports=["supply", "mid"])
+r1.set_param("r", 1e3)
+r2 = CircuitInstance(circ, "r2", "resistor", ports=["mid", "0"])
+r2.set_param("r", 3e3)
+
+sim = circ.prepare_sim(vsupply=2.0)
+sim.dc_op()
+assert np.isclose(sim.voltage("mid"), 1.5)
+assert sim.voltage("gnd") == 0.0
+assert np.isclose(sim.lead_current("r1", "A"), 0.5e-3)
+# anode and cathode are aliases of the terminals of two terminal devices
+assert sim.lead_current("vdd", "anode") == sim.lead_current("vdd", "A")
+assert np.isclose(sim.lead_current(r2, "cathode"), -0.5e-3)
+assert sim.lead_current("r2", 1) == sim.lead_current("r2", "C")
+
+# the circuit can not be modified while a simulation exists
+expect_error(RuntimeError, r1.set_param, "r", 2e3)
+expect_error(ValueError, sim.voltage, "missing")
+expect_error(ValueError, sim.lead_current, "missing", "A")
+expect_error(ValueError, sim.lead_current, "r1", "gate")
+# transient analysis is out of scope
+expect_error(NotImplementedError, sim.tran, np.linspace(0, 1e-9, 10))
+
+# small signal analysis
+sim.ac(freq=1e6)
+assert np.isclose(sim.ac_voltage("mid"), 0.75)
+assert np.isclose(sim.ac_lead_current("r1", "anode"), 0.25e-3)
+del sim
+
+# swept circuit parameters produce arrays
+vsupply = np.linspace(0, 2, 5)
+sim = circ.prepare_sim(vsupply=vsupply)
+sim.dc_op()
+assert np.allclose(sim.voltage("mid"), 0.75 * vsupply)
+assert np.allclose(sim.lead_current("vdd", "anode"), -vsupply / 4e3)
+expect_error(ValueError, circ.prepare_sim, vsupply=vsupply, temp=[300.0, 310.0])
+del sim
+
+# spectre netlists with sections
+for section, vmid in [("nominal", 1.0), ("high", 1.5)]:
+    circ = Circuit(section)
+    circ.load_netlist("spectre", tests / "divider.scs", section=section)
+    sim = circ.prepare_sim()
+    sim.dc_op()
+    assert np.isclose(sim.voltage("mid"), vmid)
+    del sim
+expect_error(RuntimeError, Circuit("missing").load_netlist, "spectre", tests / "divider.scs", "ss")
+expect_error(NotImplementedError, Circuit("spice").load_netlist, "spice", tests / "divider.scs")
+
+# Verilog-A models included by a netlist
+circ = Circuit("fet")
+circ.load_netlist("spectre", pdk, "example_corner")
+fet = CircuitInstance(circ, "fet", "example_fet", ports=["drain", "gate", "gnd"])
+fet.set_param("W", "width")
+vd = CircuitInstance(circ, "vd", "vsource", ports=["drain", "gnd"])
+vd.set_param("dc", 1.2)
+vg = CircuitInstance(circ, "vg", "vsource", ports=["gate", "gnd"])
+vg.set_param("dc", 0.8)
+width = np.array([1e-6, 2e-6])
+sim = circ.prepare_sim(width=width)
+sim.dc_op()
+id = -sim.lead_current("vd", "anode")
+assert np.all(id > 0)
+assert np.isclose(id[1], 2 * id[0])
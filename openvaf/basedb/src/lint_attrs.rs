@@ -49,6 +49,11 @@ impl LintAttrTree {
         Arc::new(res)
     }
 
+    /// Returns whether any attribute sets `lint` to a level other than `allow`.
+    pub fn raises_lvl(&self, lint: Lint) -> bool {
+        self.overwrites.iter().any(|(&(_, lint_), &lvl)| lint_ == lint && lvl != LintLevel::Allow)
+    }
+
     pub fn lint_lvl(&self, map: &AstIdMap, mut id: ErasedAstId, lint: Lint) -> Option<LintLevel> {
         loop {
             if let Some(lvl) = self.overwrites.get(&(id, lint)) {
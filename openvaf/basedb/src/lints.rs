@@ -167,7 +167,7 @@ pub mod builtin {
         // pub const infinite_loop = LintData{default_lvl: Deny, documentation_id: 3};
         pub const macro_overwritten = LintData{default_lvl: Warn, documentation_id: 4};
         // pub const attribute_overwritten = LintData{default_lvl: Warn, documentation_id:5};
-        // pub const rounding_derivative = LintData{default_lvl: Warn, documentation_id: 6};
+        pub const rounding_derivative = LintData{default_lvl: Allow, documentation_id: 6};
         // pub const noise_derivative = LintData{default_lvl: Warn, documentation_id: 7};
         pub const lint_not_found = LintData{default_lvl: Deny, documentation_id: 8};
         pub const lint_level_overwrite = LintData{default_lvl: Warn, documentation_id: 9};
@@ -179,5 +179,12 @@ pub mod builtin {
         pub const variant_const_simparam = LintData{default_lvl: Warn, documentation_id: 15};
         pub const port_without_direction = LintData{default_lvl: Deny, documentation_id: 16};
         pub const trivial_probe = LintData{default_lvl: Warn, documentation_id: 17};
+        pub const uninitialized_variable = LintData{default_lvl: Allow, documentation_id: 18};
+        pub const dead_assignment = LintData{default_lvl: Allow, documentation_id: 19};
+        pub const unlimited_exp = LintData{default_lvl: Allow, documentation_id: 20};
+        pub const non_smooth_contribution = LintData{default_lvl: Allow, documentation_id: 21};
+        pub const probe_division = LintData{default_lvl: Allow, documentation_id: 22};
+        pub const discontinuous_ddt = LintData{default_lvl: Allow, documentation_id: 23};
+        pub const unit_mismatch = LintData{default_lvl: Allow, documentation_id: 24};
     }
 }
@@ -5,6 +5,7 @@ use hir_def::DefWithBodyId;
 use hir_ty::db::HirTyDB;
 use hir_ty::inference;
 use hir_ty::types::{Signature, Ty};
+use typed_index_collections::TiVec;
 
 pub use hir_def::{expr::Event, BuiltIn, Case, ExprId, Literal, ParamSysFun, StmtId, Type};
 pub use syntax::ast::{BinaryOp, UnaryOp};
@@ -43,6 +44,12 @@ impl<'a> BodyRef<'a> {
         self.infere.expr_types[expr].to_value().unwrap()
     }
 
+    /// Returns whether this expression produces a value
+    /// (and does not just reference a node, branch, ...)
+    pub fn is_value(&self, expr: ExprId) -> bool {
+        self.infere.expr_types[expr].to_value().is_some()
+    }
+
     /// Returns whether the result of an expression
     /// needs to be cast to a different type before use.
     pub fn needs_cast(&self, expr: ExprId) -> Option<(Type, &'a Type)> {
@@ -144,6 +151,25 @@ impl<'a> BodyRef<'a> {
         self.get_stmt(self.entry()[i]).unwrap().unwrap_expr()
     }
 
+    /// Returns all statements of this body (including nested statements).
+    pub fn stmts(&self) -> impl Iterator<Item = StmtId> + 'a {
+        self.body.stmts.keys()
+    }
+
+    /// Returns the statement that each expression of this body belongs to.
+    pub fn expr_stmts(&self) -> TiVec<ExprId, Option<StmtId>> {
+        let mut res: TiVec<ExprId, Option<StmtId>> = vec![None; self.body.exprs.len()].into();
+        let mut worklist = Vec::new();
+        for (stmt, data) in self.body.stmts.iter_enumerated() {
+            data.walk_child_exprs(|expr| worklist.push(expr));
+            while let Some(expr) = worklist.pop() {
+                res[expr] = Some(stmt);
+                self.body.exprs[expr].walk_child_exprs(|child| worklist.push(child));
+            }
+        }
+        res
+    }
+
     pub fn get_stmt(&self, stmnt: StmtId) -> Option<Stmt<'a>> {
         match self.body.stmts[stmnt] {
             hir_def::Stmt::Empty | hir_def::Stmt::Missing => None,
@@ -151,27 +177,33 @@ impl<'a> BodyRef<'a> {
             hir_def::Stmt::EventControl { ref event, body } => {
                 Some(Stmt::EventControl { event, body })
             }
-            hir_def::Stmt::Assignment { val, .. } => {
+            hir_def::Stmt::Assignment { dst, val, .. } => {
                 let stmt = match self.infere.assignment_destination[&stmnt] {
-                    inference::AssignDst::Var(id) => {
-                        Stmt::Assignment { lhs: AssignmentLhs::Variable(Variable { id }), rhs: val }
-                    }
+                    inference::AssignDst::Var(id) => Stmt::Assignment {
+                        lhs: AssignmentLhs::Variable(Variable { id }),
+                        dst,
+                        rhs: val,
+                    },
                     inference::AssignDst::FunVar { fun, arg: None } => Stmt::Assignment {
                         lhs: AssignmentLhs::FunctionReturn(Function { id: fun }),
+                        dst,
                         rhs: val,
                     },
                     inference::AssignDst::FunVar { fun, arg: Some(arg) } => Stmt::Assignment {
                         lhs: AssignmentLhs::FunctionArg(FunctionArg { fun_id: fun, arg_id: arg }),
+                        dst,
                         rhs: val,
                     },
                     inference::AssignDst::Flow(branch) => Stmt::Contribute {
                         kind: ContributeKind::Flow,
                         branch: branch.into(),
+                        dst,
                         rhs: val,
                     },
                     inference::AssignDst::Potential(branch) => Stmt::Contribute {
                         kind: ContributeKind::Potential,
                         branch: branch.into(),
+                        dst,
                         rhs: val,
                     },
                 };
@@ -207,8 +239,8 @@ pub enum ContributeKind {
 pub enum Stmt<'a> {
     Expr(ExprId),
     EventControl { event: &'a Event, body: StmtId },
-    Contribute { kind: ContributeKind, branch: BranchWrite, rhs: ExprId },
-    Assignment { lhs: AssignmentLhs, rhs: ExprId },
+    Contribute { kind: ContributeKind, branch: BranchWrite, dst: ExprId, rhs: ExprId },
+    Assignment { lhs: AssignmentLhs, dst: ExprId, rhs: ExprId },
     Block { body: &'a [StmtId] },
     If { cond: ExprId, then_branch: StmtId, else_branch: StmtId },
     ForLoop { init: StmtId, cond: ExprId, incr: StmtId, body: StmtId },
@@ -4,15 +4,14 @@ use hir_def::nameres::diagnostics::DefDiagnosticWrapped;
 use hir_def::nameres::{DefMap, LocalScopeId, ScopeDefItem, ScopeOrigin};
 use hir_def::DefWithBodyId;
 use hir_ty::diagnostics::InferenceDiagnosticWrapped;
-use hir_ty::validation::{
-    self, BodyValidationDiagnostic, BodyValidationDiagnosticWrapped,
-    TypeValidationDiagnosticWrapped,
-};
+use hir_ty::validation::{self, BodyValidationDiagnosticWrapped, TypeValidationDiagnosticWrapped};
 use syntax::sourcemap::SourceMap;
 use syntax::{Parse, SourceFile};
 
 pub use basedb::diagnostics::*;
+pub use basedb::lints::{builtin as lints, Lint};
 pub use basedb::{BaseDB, FileId};
+pub use hir_ty::validation::{BodyValidationDiagnostic, NonSmooth, PathStep};
 
 use crate::{CompilationDB, HirDatabase};
 
@@ -148,3 +147,29 @@ fn collect_body_diagnostcs(
         dst.add_diagnostic(&diag, root_file, db.upcast())
     }
 }
+
+/// Reports diagnostics for `def` that were not found by [`collect`] but later in the
+/// compilation (for example on the MIR of the body).
+pub(crate) fn report_body_diagnostics(
+    db: &CompilationDB,
+    def: DefWithBodyId,
+    root_file: FileId,
+    diagnostics: &[BodyValidationDiagnostic],
+    sink: &mut impl DiagnosticSink,
+) {
+    let parse = db.parse(root_file);
+    let sm = db.sourcemap(root_file);
+    let ast_id_map = db.ast_id_map(root_file);
+    let body_sm = db.body_source_map(def);
+    for diag in diagnostics {
+        let diag = BodyValidationDiagnosticWrapped {
+            body_sm: &body_sm,
+            diag,
+            parse: &parse,
+            db,
+            sm: &sm,
+            map: &ast_id_map,
+        };
+        sink.add_diagnostic(&diag, root_file, db)
+    }
+}
@@ -13,6 +13,7 @@ use std::sync::Arc;
 
 use basedb::diagnostics::sink::Buffer;
 use basedb::diagnostics::ConsoleSink;
+use basedb::lints::{Lint, LintLevel};
 use basedb::BaseDB;
 use basedb::FileId;
 use hir_def::db::HirDefDB;
@@ -174,6 +175,41 @@ impl Module {
         Body::new(DefWithBodyId::ModuleId { initial: false, module: self.id }, db)
     }
 
+    /// Reports diagnostics for the analog block that were found after it was lowered to MIR.
+    pub fn report_analog_block_diagnostics(
+        self,
+        db: &CompilationDB,
+        diagnostics: &[diagnostics::BodyValidationDiagnostic],
+        sink: &mut impl DiagnosticSink,
+    ) {
+        let def = DefWithBodyId::ModuleId { initial: false, module: self.id };
+        let root_file = self.lookup(db).scope.root_file;
+        diagnostics::report_body_diagnostics(db, def, root_file, diagnostics, sink)
+    }
+
+    /// Reports diagnostics for the analog initial block that were found after it was lowered
+    /// to MIR.
+    pub fn report_analog_initial_block_diagnostics(
+        self,
+        db: &CompilationDB,
+        diagnostics: &[diagnostics::BodyValidationDiagnostic],
+        sink: &mut impl DiagnosticSink,
+    ) {
+        let def = DefWithBodyId::ModuleId { initial: true, module: self.id };
+        let root_file = self.lookup(db).scope.root_file;
+        diagnostics::report_body_diagnostics(db, def, root_file, diagnostics, sink)
+    }
+
+    /// Returns whether `lint` may be reported for this module. That is the case if the lint is
+    /// not allowed for the module itself or if any attribute in the file raises its level.
+    pub fn lint_enabled(self, db: &CompilationDB, lint: Lint) -> bool {
+        let loc = self.lookup(db);
+        let root_file = loc.scope.root_file;
+        let ast = loc.ast_id(db).erased();
+        db.lint_lvl(lint, root_file, Some(ast)).0 != LintLevel::Allow
+            || db.lint_attr_tree(root_file).raises_lvl(lint)
+    }
+
     // todo: just temporary for VAE, this needs to be cleaned up
     pub fn lookup_var(
         &self,
@@ -238,6 +274,18 @@ impl Function {
     pub fn body(&self, db: &CompilationDB) -> Body {
         Body::new(self.id.into(), db)
     }
+
+    /// Reports diagnostics for the body of this function that were found after it was lowered
+    /// to MIR.
+    pub fn report_diagnostics(
+        self,
+        db: &CompilationDB,
+        diagnostics: &[diagnostics::BodyValidationDiagnostic],
+        sink: &mut impl DiagnosticSink,
+    ) {
+        let root_file = self.id.lookup(db).scope.root_file;
+        diagnostics::report_body_diagnostics(db, self.id.into(), root_file, diagnostics, sink)
+    }
 }
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
@@ -423,6 +471,12 @@ pub struct Variable {
     id: VarId,
 }
 
+impl From<Variable> for VarId {
+    fn from(var: Variable) -> VarId {
+        var.id
+    }
+}
+
 stdx::impl_debug! {
     match Variable{
         Variable{ id } => "{id:?}";
@@ -442,6 +496,12 @@ impl Variable {
         Body::new(self.id.into(), db)
     }
 
+    /// Returns whether the declaration of this variable has an initializer. Otherwise the
+    /// default value of its type is used.
+    pub fn has_initializer(self, db: &CompilationDB) -> bool {
+        db.body_source_map(self.id.into()).expr_map_back.iter().any(Option::is_some)
+    }
+
     pub fn get_attr(&self, db: &CompilationDB, ast: &AstCache, name: &str) -> Option<ast::Attr> {
         ast.resolve_attribute(name, self.id.lookup(db).ast_id(db).erased())
     }
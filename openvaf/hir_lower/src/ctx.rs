@@ -1,5 +1,6 @@
 use ahash::AHashSet;
-use hir::{CompilationDB, Node, Type, Variable};
+use hir::diagnostics::PathStep;
+use hir::{CompilationDB, ExprId, Node, StmtId, Type, Variable};
 use mir::builder::{InsertBuilder, InstBuilder};
 use mir::{
     Block, DataFlowGraph, FuncRef, Inst, Opcode, SourceLoc, Value, FALSE, F_ZERO, INFINITY, TRUE,
@@ -8,8 +9,8 @@ use mir_build::{FuncInstBuilder, FunctionBuilder, Place};
 use typed_indexmap::TiSet;
 
 use crate::{
-    CallBackKind, HirInterner, ImplicitEquation, ImplicitEquationKind, LimitState, ParamKind,
-    PlaceKind,
+    BodyOwner, CallBackKind, HirInterner, ImplicitEquation, ImplicitEquationKind, LimitState,
+    ParamKind, PlaceKind, VarRead, VarWrite,
 };
 
 pub struct LoweringCtx<'a, 'c> {
@@ -20,6 +21,14 @@ pub struct LoweringCtx<'a, 'c> {
     pub places: TiSet<Place, PlaceKind>,
     tagged_vars: AHashSet<Variable>,
     pub inside_lim: bool,
+    /// Set while the body of a user function is inlined. The inlined instructions keep the
+    /// source location of the call because the expressions of the function body belong to a
+    /// different body.
+    pub inlining: bool,
+    /// Record all variable accesses and branches in [`HirInterner::accesses`].
+    pub track_accesses: bool,
+    /// The body that is currently lowered, only used for tracking accesses.
+    pub body_owner: BodyOwner,
     /// We create a dedicated callback for each noise source
     /// by giving each callback a unique index. Kind of ineffcient
     /// but necessary to avoid accidental correlation/opimization.
@@ -41,6 +50,9 @@ impl<'a, 'c> LoweringCtx<'a, 'c> {
             places: TiSet::default(),
             tagged_vars: AHashSet::default(),
             inside_lim: false,
+            inlining: false,
+            track_accesses: false,
+            body_owner: BodyOwner::AnalogBlock,
             intern,
             num_noise_sources: 0,
         }
@@ -53,9 +65,14 @@ impl<'a, 'c> LoweringCtx<'a, 'c> {
 
     /// This function should be used for reading variables to correctly
     /// handle value tagging
-    pub fn read_variable(&mut self, var: Variable) -> Value {
+    pub fn read_variable(&mut self, var: Variable, expr: ExprId) -> Value {
         let place = self.dec_place(PlaceKind::Var(var));
         let mut val = self.func.use_var(place);
+        if self.track_accesses {
+            val = self.func.ins().optbarrier(val);
+            let read = VarRead { var, owner: self.body_owner, expr };
+            self.intern.accesses.reads.insert(val, read);
+        }
         if self.tagged_vars.contains(&var) {
             val = self.func.ins().optbarrier(val);
             self.intern.tagged_reads.insert(val, var);
@@ -91,6 +108,25 @@ impl<'a, 'c> LoweringCtx<'a, 'c> {
         place
     }
 
+    /// Assigns `val` to `kind` like [`def_place`](Self::def_place) but records the write if
+    /// it is a variable and accesses are tracked.
+    pub fn assign_place(&mut self, kind: PlaceKind, mut val: Value, stmt: Option<StmtId>) {
+        if let (true, PlaceKind::Var(var)) = (self.track_accesses, kind) {
+            val = self.func.ins().optbarrier(val);
+            let write = VarWrite { var, owner: self.body_owner, stmt };
+            self.intern.accesses.writes.insert(val, write);
+        }
+        self.def_place(kind, val)
+    }
+
+    /// Records that the current block is only entered by taking `decision`.
+    pub fn decide(&mut self, decision: PathStep) {
+        if self.track_accesses {
+            let bb = self.func.current_block();
+            self.intern.accesses.decisions.insert(bb, (self.body_owner, decision));
+        }
+    }
+
     pub fn def_place(&mut self, kind: PlaceKind, val: Value) {
         let place = self.dec_place(kind);
         self.func.def_var(place, val)
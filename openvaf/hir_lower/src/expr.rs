@@ -1,3 +1,5 @@
+use std::mem::replace;
+
 use hir::builtin::{
     FLICKER_NOISE_NAME, NOISE_TABLE_FILE_NAME, NOISE_TABLE_INLINE_NAME, WHITE_NOISE_NAME,
 };
@@ -19,16 +21,19 @@ use syntax::ast::{BinaryOp, UnaryOp};
 use crate::body::BodyLoweringCtx;
 use crate::fmt::DisplayKind;
 use crate::{
-    CallBackKind, CurrentKind, IdtKind, ImplicitEquationKind, NoiseTable, ParamKind, PlaceKind,
+    BodyOwner, CallBackKind, CurrentKind, IdtKind, ImplicitEquationKind, NoiseTable, ParamKind,
+    PlaceKind,
 };
 
 impl BodyLoweringCtx<'_, '_, '_> {
     pub fn lower_expr(&mut self, expr: ExprId) -> Value {
         let old_loc = self.ctx.get_srcloc();
-        self.ctx.set_srcloc(mir::SourceLoc::new(u32::from(expr) as i32 + 1));
+        if !self.ctx.inlining {
+            self.ctx.set_srcloc(mir::SourceLoc::new(u32::from(expr) as i32 + 1));
+        }
 
         let mut res = match self.body.get_expr(expr) {
-            Expr::Read(Ref::Variable(var)) => self.ctx.read_variable(var),
+            Expr::Read(Ref::Variable(var)) => self.ctx.read_variable(var, expr),
             Expr::Read(Ref::ParamSysFun(param)) => {
                 self.ctx.use_param(ParamKind::ParamSysFun(param))
             }
@@ -242,7 +247,11 @@ impl BodyLoweringCtx<'_, '_, '_> {
         self.ctx.def_place(PlaceKind::FunctionReturn(fun), init);
 
         let body = fun.body(self.ctx.db);
+        let inlining = replace(&mut self.ctx.inlining, true);
+        let owner = replace(&mut self.ctx.body_owner, BodyOwner::Function(fun));
         BodyLoweringCtx { body: body.borrow(), path: self.path, ctx: self.ctx }.lower_entry_stmts();
+        self.ctx.inlining = inlining;
+        self.ctx.body_owner = owner;
 
         // write outputs back to original (including possibly required cast)
         for (arg, &expr) in args {
@@ -253,7 +262,7 @@ impl BodyLoweringCtx<'_, '_, '_> {
                     val = self.ctx.insert_cast(val, src, &dst)
                 }
                 let dst = self.body.get_expr(expr).as_assignment_lhs();
-                self.ctx.def_place(dst.into(), val);
+                self.ctx.assign_place(dst.into(), val, None);
             }
         }
 
@@ -2,13 +2,15 @@ use std::iter::FilterMap;
 
 use ahash::{AHashMap, AHashSet};
 use bitset::HybridBitSet;
+use hir::diagnostics::PathStep;
 use hir::{
-    Branch, BranchWrite, CompilationDB, Module, Node, ParamSysFun, Parameter, Type, Variable,
+    Branch, BranchWrite, CompilationDB, ExprId, Module, Node, ParamSysFun, Parameter, StmtId, Type,
+    Variable,
 };
 use indexmap::IndexMap;
 use lasso::Rodeo;
 use mir::builder::InstBuilder;
-use mir::{DataFlowGraph, FuncRef, Function, Inst, KnownDerivatives, Param, Unknown, Value};
+use mir::{Block, DataFlowGraph, FuncRef, Function, Inst, KnownDerivatives, Param, Unknown, Value};
 use mir_build::{FunctionBuilder, FunctionBuilderContext, RetBuilder};
 use stdx::packed_option::PackedOption;
 use stdx::{impl_debug_display, impl_idx_from};
@@ -219,6 +221,45 @@ impl_debug_display! {
     match LimitState {LimitState(i) => "lim_state{}", i;}
 }
 
+/// The body that a variable access or a branch was lowered from. User functions are inlined so
+/// their bodies are lowered once for every call.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum BodyOwner {
+    AnalogInitialBlock,
+    AnalogBlock,
+    Function(hir::Function),
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct VarRead {
+    pub var: Variable,
+    pub owner: BodyOwner,
+    pub expr: ExprId,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct VarWrite {
+    pub var: Variable,
+    pub owner: BodyOwner,
+    /// The assignment statement, `None` for output arguments of functions
+    pub stmt: Option<StmtId>,
+}
+
+/// The variable accesses and branches of a module that were recorded during lowering
+/// (see [`MirBuilder::with_tracked_accesses`]).
+///
+/// Every read and every write of a variable is an `optbarrier` so that each access is a
+/// distinct value. The argument of a read is the value that it observes.
+#[derive(Debug, PartialEq, Default, Clone)]
+pub struct TrackedAccesses {
+    pub reads: IndexMap<Value, VarRead, ahash::RandomState>,
+    pub writes: IndexMap<Value, VarWrite, ahash::RandomState>,
+    /// The blocks that are only entered by taking a decision in an `if`, loop or `case`
+    /// statement, in program order. The exit of a loop is also reached after iterating so it
+    /// only corresponds to skipping the loop if it is reached from the loop header.
+    pub decisions: IndexMap<Block, (BodyOwner, PathStep), ahash::RandomState>,
+}
+
 /// A mapping between abstractions used in the MIR and the corresponding
 /// information from the HIR. This allows the MIR to remain independent of the frontend/HIR
 #[derive(Debug, PartialEq, Default, Clone)]
@@ -230,6 +271,7 @@ pub struct HirInterner {
     pub tagged_reads: IndexMap<Value, Variable, ahash::RandomState>,
     pub implicit_equations: TiVec<ImplicitEquation, ImplicitEquationKind>,
     pub lim_state: TiMap<LimitState, Value, Vec<(Value, bool)>>,
+    pub accesses: TrackedAccesses,
 }
 
 pub type LiveParams<'a> = FilterMap<
@@ -406,6 +448,7 @@ pub struct MirBuilder<'a> {
     tag_writes: bool,
     ctx: Option<&'a mut FunctionBuilderContext>,
     lower_equations: bool,
+    track_accesses: bool,
 }
 
 impl<'a> MirBuilder<'a> {
@@ -424,6 +467,7 @@ impl<'a> MirBuilder<'a> {
             ctx: None,
             lower_equations: false,
             tag_writes: false,
+            track_accesses: false,
         }
     }
 
@@ -454,6 +498,14 @@ impl<'a> MirBuilder<'a> {
         self
     }
 
+    /// Records all variable accesses and branches in [`HirInterner::accesses`]. The
+    /// additional `optbarrier` instructions prevent optimizations so this is only intended
+    /// for analyzing the dataflow of variables.
+    pub fn with_tracked_accesses(mut self) -> Self {
+        self.track_accesses = true;
+        self
+    }
+
     pub fn with_ctx(mut self, ctx: &'a mut FunctionBuilderContext) -> Self {
         self.ctx = Some(ctx);
         self
@@ -484,12 +536,15 @@ impl<'a> MirBuilder<'a> {
 
         let mut ctx = LoweringCtx::new(self.db, builder, !self.lower_equations, &mut interner)
             .with_tagged_vars(self.tagged_reads);
+        ctx.track_accesses = self.track_accesses;
+        ctx.body_owner = BodyOwner::AnalogInitialBlock;
         let mut body_ctx =
             BodyLoweringCtx { ctx: &mut ctx, body: analog_initial_body.borrow(), path: &path };
 
         // lower analog initial blocks first
         body_ctx.lower_entry_stmts();
         // ... and normal analog blocks afterwards
+        body_ctx.ctx.body_owner = BodyOwner::AnalogBlock;
         body_ctx.body = analog_body.borrow();
         body_ctx.lower_entry_stmts();
 
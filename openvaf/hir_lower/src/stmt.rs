@@ -1,3 +1,4 @@
+use hir::diagnostics::PathStep;
 use hir::{BranchWrite, Case, CaseCond, ContributeKind, ExprId, Node, Stmt, StmtId, Type};
 use mir::builder::InstBuilder;
 use mir::{Opcode, F_ZERO};
@@ -6,9 +7,9 @@ use crate::body::BodyLoweringCtx;
 use crate::{CallBackKind, CurrentKind, ParamKind, PlaceKind};
 
 impl BodyLoweringCtx<'_, '_, '_> {
-    pub(super) fn lower_stmt(&mut self, stmnt: StmtId) {
+    pub(super) fn lower_stmt(&mut self, stmt_id: StmtId) {
         // TODO(msrv): let .. else
-        let stmnt = if let Some(stmnt) = self.body.get_stmt(stmnt) {
+        let stmnt = if let Some(stmnt) = self.body.get_stmt(stmt_id) {
             stmnt
         } else {
             return;
@@ -21,11 +22,11 @@ impl BodyLoweringCtx<'_, '_, '_> {
                 // TODO handle porperly
                 self.lower_stmt(body);
             }
-            Stmt::Assignment { lhs, rhs } => {
+            Stmt::Assignment { lhs, rhs, .. } => {
                 let val_ = self.lower_expr(rhs);
-                self.ctx.def_place(lhs.into(), val_);
+                self.ctx.assign_place(lhs.into(), val_, Some(stmt_id));
             }
-            Stmt::Contribute { kind, branch, rhs } => {
+            Stmt::Contribute { kind, branch, rhs, .. } => {
                 self.contribute(kind == ContributeKind::Potential, branch, rhs)
             }
 
@@ -38,6 +39,7 @@ impl BodyLoweringCtx<'_, '_, '_> {
                 let cond_ = self.lower_expr(cond);
 
                 self.ctx.make_cond(cond_, |ctx, branch| {
+                    ctx.decide(PathStep::If { cond, val: branch });
                     let stmt = if branch { then_branch } else { else_branch };
                     BodyLoweringCtx { body: self.body, path: self.path, ctx }.lower_stmt(stmt);
                 });
@@ -54,8 +56,8 @@ impl BodyLoweringCtx<'_, '_, '_> {
         }
     }
 
-    fn lower_case(&mut self, discr: ExprId, case_arms: &[Case]) {
-        let discr_op = match self.body.expr_type(discr) {
+    fn lower_case(&mut self, discr_expr: ExprId, case_arms: &[Case]) {
+        let discr_op = match self.body.expr_type(discr_expr) {
             Type::Real => Opcode::Feq,
             Type::Integer => Opcode::Ieq,
             Type::Bool => Opcode::Beq,
@@ -63,8 +65,9 @@ impl BodyLoweringCtx<'_, '_, '_> {
             Type::Array { .. } => todo!(),
             ty => unreachable!("Invalid type {}", ty),
         };
-        let discr = self.lower_expr(discr);
+        let discr = self.lower_expr(discr_expr);
         let end = self.ctx.create_block();
+        let mut has_vals = false;
 
         for Case { cond, body } in case_arms {
             // TODO does default mean that further cases are ignored?
@@ -85,7 +88,9 @@ impl BodyLoweringCtx<'_, '_, '_> {
                 let val_ = self.lower_expr(*val);
 
                 let old_loc = self.ctx.get_srcloc();
-                self.ctx.set_srcloc(mir::SourceLoc::new(u32::from(*val) as i32 + 1));
+                if !self.ctx.inlining {
+                    self.ctx.set_srcloc(mir::SourceLoc::new(u32::from(*val) as i32 + 1));
+                }
                 let cond = self.ctx.ins().binary1(discr_op, val_, discr);
                 self.ctx.set_srcloc(old_loc);
 
@@ -101,14 +106,26 @@ impl BodyLoweringCtx<'_, '_, '_> {
             // lower the body
             let next = self.ctx.current_block();
             self.ctx.switch_to_block(body_head);
+            if let Some(&expr) = vals.first() {
+                self.ctx.decide(PathStep::CaseArm { expr, default: false });
+                has_vals = true;
+            }
             self.lower_stmt(*body);
             self.ctx.ins().jump(end);
             self.ctx.switch_to_block(next);
         }
 
-        if let Some(default_case) =
-            case_arms.iter().find(|arm| matches!(arm.cond, CaseCond::Default))
-        {
+        let default_case = case_arms.iter().find(|arm| matches!(arm.cond, CaseCond::Default));
+        // the remaining block is only reached if no other arm matches
+        if has_vals {
+            let decision = if default_case.is_some() {
+                PathStep::CaseArm { expr: discr_expr, default: true }
+            } else {
+                PathStep::NoCaseMatch { discr: discr_expr }
+            };
+            self.ctx.decide(decision);
+        }
+        if let Some(default_case) = default_case {
             self.lower_stmt(default_case.body);
         }
 
@@ -119,7 +136,7 @@ impl BodyLoweringCtx<'_, '_, '_> {
         self.ctx.switch_to_block(end);
     }
 
-    fn lower_loop(&mut self, cond: ExprId, lower_body: impl FnOnce(&mut Self)) {
+    fn lower_loop(&mut self, cond_expr: ExprId, lower_body: impl FnOnce(&mut Self)) {
         let loop_cond_head = self.ctx.create_block();
         let loop_body_head = self.ctx.create_block();
         let loop_end = self.ctx.create_block();
@@ -127,7 +144,7 @@ impl BodyLoweringCtx<'_, '_, '_> {
         self.ctx.ins().jump(loop_cond_head);
         self.ctx.switch_to_block(loop_cond_head);
 
-        let cond = self.lower_expr(cond);
+        let cond = self.lower_expr(cond_expr);
         self.ctx.ins().br_loop(cond, loop_body_head, loop_end);
         self.ctx.seal_block(loop_body_head);
         self.ctx.seal_block(loop_end);
@@ -139,6 +156,7 @@ impl BodyLoweringCtx<'_, '_, '_> {
         self.ctx.seal_block(loop_cond_head);
 
         self.ctx.switch_to_block(loop_end);
+        self.ctx.decide(PathStep::LoopSkipped { cond: cond_expr });
     }
 
     fn contribute(&mut self, voltage_src: bool, mut write: BranchWrite, rhs: ExprId) {
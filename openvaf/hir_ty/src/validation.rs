@@ -1,11 +1,15 @@
 use basedb::diagnostics::{Diagnostic, Label, LabelStyle, Report};
-use basedb::lints::builtin::{const_simparam, trivial_probe, variant_const_simparam};
+use basedb::lints::builtin::{
+    const_simparam, dead_assignment, discontinuous_ddt, non_smooth_contribution, probe_division,
+    rounding_derivative, trivial_probe, uninitialized_variable, unit_mismatch, unlimited_exp,
+    variant_const_simparam,
+};
 use basedb::lints::{self, Lint, LintSrc};
 use basedb::{AstIdMap, BaseDB, FileId};
-pub use body::BodyValidationDiagnostic;
+pub use body::{BodyValidationDiagnostic, NonSmooth, PathStep};
 use hir_def::body::BodySourceMap;
 use hir_def::{
-    DisciplineAttr, ExprId, ItemLoc, ItemTree, ItemTreeNode, Lookup, NatureAttr, NodeId,
+    BuiltIn, DisciplineAttr, ExprId, ItemLoc, ItemTree, ItemTreeNode, Lookup, NatureAttr, NodeId,
     NodeTypeDecl,
 };
 use syntax::name::Name;
@@ -20,6 +24,7 @@ use crate::validation::types::DuplicateItem;
 
 mod body;
 mod types;
+mod units;
 
 #[derive(PartialEq, Eq, Clone, Debug)]
 struct IncompatibleBranchDiagnostic {
@@ -103,6 +108,44 @@ impl BodyValidationDiagnosticWrapped<'_> {
         let src = loc.ast_ptr(self.db.upcast()).range();
         (loc.name(self.db.upcast()), self.parse.to_file_span(src, self.sm))
     }
+
+    fn non_smooth_label(&self, origin: NonSmooth) -> Label {
+        let (expr, message) = match origin {
+            NonSmooth::Call { expr, fun: BuiltIn::floor | BuiltIn::ceil } => {
+                (expr, "discontinuous function of a probe")
+            }
+            NonSmooth::Call { expr, .. } => (expr, "not differentiable for some probe values"),
+            NonSmooth::Condition(expr) => (expr, "condition depends on a probe"),
+        };
+        let FileSpan { range, file } = self.expr_src(expr);
+        Label {
+            style: LabelStyle::Primary,
+            file_id: file,
+            range: range.into(),
+            message: message.to_owned(),
+        }
+    }
+
+    fn non_smooth_help(&self, origin: NonSmooth) -> String {
+        match origin {
+            NonSmooth::Call { fun: BuiltIn::abs, .. } => {
+                "help: replace abs(x) with a smooth approximation such as sqrt(x*x + eps)"
+                    .to_owned()
+            }
+            NonSmooth::Call { fun: fun @ (BuiltIn::min | BuiltIn::max), .. } => {
+                let sign = if fun == BuiltIn::max { '+' } else { '-' };
+                format!(
+                    "help: replace {fun:?}(a, b) with a smooth approximation such as 0.5*(a + b {sign} sqrt((a - b)**2 + eps))"
+                )
+            }
+            NonSmooth::Call { fun, .. } => {
+                format!("help: '{fun:?}' of a probe jumps between integer values; avoid rounding probe dependent values")
+            }
+            NonSmooth::Condition(_) => {
+                "help: blend between the branches with a smooth transition function".to_owned()
+            }
+        }
+    }
 }
 
 impl Diagnostic for BodyValidationDiagnosticWrapped<'_> {
@@ -130,6 +173,39 @@ impl Diagnostic for BodyValidationDiagnosticWrapped<'_> {
                 let src = self.body_sm.lint_src(stmt, trivial_probe);
                 Some((trivial_probe, src))
             }
+            BodyValidationDiagnostic::UninitializedVariable { stmt, .. } => {
+                let src = self.body_sm.lint_src(stmt, uninitialized_variable);
+                Some((uninitialized_variable, src))
+            }
+            BodyValidationDiagnostic::DeadAssignment { stmt, .. } => {
+                let src = self.body_sm.lint_src(stmt, dead_assignment);
+                Some((dead_assignment, src))
+            }
+            BodyValidationDiagnostic::UnlimitedExp { stmt, .. } => {
+                let src = self.body_sm.lint_src(stmt, unlimited_exp);
+                Some((unlimited_exp, src))
+            }
+            BodyValidationDiagnostic::NonSmoothContribution { stmt, .. } => {
+                let src = self.body_sm.lint_src(stmt, non_smooth_contribution);
+                Some((non_smooth_contribution, src))
+            }
+            BodyValidationDiagnostic::ProbeDivision { stmt, .. } => {
+                let src = self.body_sm.lint_src(stmt, probe_division);
+                Some((probe_division, src))
+            }
+            BodyValidationDiagnostic::DiscontinuousDdt { stmt, .. } => {
+                let src = self.body_sm.lint_src(stmt, discontinuous_ddt);
+                Some((discontinuous_ddt, src))
+            }
+            BodyValidationDiagnostic::RoundingDerivative { stmt, .. } => {
+                let src = self.body_sm.lint_src(stmt, rounding_derivative);
+                Some((rounding_derivative, src))
+            }
+            BodyValidationDiagnostic::MixedUnits { stmt, .. }
+            | BodyValidationDiagnostic::AssignmentUnitMismatch { stmt, .. } => {
+                let src = self.body_sm.lint_src(stmt, unit_mismatch);
+                Some((unit_mismatch, src))
+            }
             _ => None,
         }
     }
@@ -502,6 +578,206 @@ impl Diagnostic for BodyValidationDiagnosticWrapped<'_> {
 
                 res
             }
+            BodyValidationDiagnostic::UninitializedVariable { var, expr, ref path, .. } => {
+                let name = &self.db.var_data(var).name;
+                let FileSpan { range, file } = self.expr_src(expr);
+                let mut labels = vec![Label {
+                    style: LabelStyle::Primary,
+                    file_id: file,
+                    range: range.into(),
+                    message: format!("'{name}' is read here"),
+                }];
+                for step in path.iter() {
+                    let (expr, message) = match *step {
+                        PathStep::If { cond, val: true } => (cond, "if this condition is true"),
+                        PathStep::If { cond, val: false } => (cond, "if this condition is false"),
+                        PathStep::LoopSkipped { cond } => (cond, "if this loop is never entered"),
+                        PathStep::CaseArm { expr, default: false } => {
+                            (expr, "if this case is taken")
+                        }
+                        PathStep::CaseArm { expr, default: true } => {
+                            (expr, "if the default case is taken")
+                        }
+                        PathStep::NoCaseMatch { discr } => (discr, "if no case matches"),
+                    };
+                    let src = self.expr_src(expr);
+                    labels.push(Label {
+                        style: LabelStyle::Secondary,
+                        file_id: src.file,
+                        range: src.range.into(),
+                        message: format!("not assigned {message}"),
+                    });
+                }
+                let note = format!(
+                    "note: '{name}' has its initial value or the value from the previous evaluation"
+                );
+                Report::warning()
+                    .with_message(format!("'{name}' may be read before it is assigned"))
+                    .with_labels(labels)
+                    .with_notes(vec![note])
+            }
+            BodyValidationDiagnostic::DeadAssignment { var, dst, .. } => {
+                let name = &self.db.var_data(var).name;
+                let FileSpan { range, file } = self.expr_src(dst);
+                Report::warning()
+                    .with_message(format!("value assigned to '{name}' is never read"))
+                    .with_labels(vec![Label {
+                        style: LabelStyle::Primary,
+                        file_id: file,
+                        range: range.into(),
+                        message: "assigned value is never read".to_owned(),
+                    }])
+                    .with_notes(vec![format!(
+                        "help: '{name}' is overwritten or not used after this assignment"
+                    )])
+            }
+            BodyValidationDiagnostic::UnlimitedExp { expr, .. } => {
+                let FileSpan { range, file } = self.expr_src(expr);
+                Report::warning()
+                    .with_message("exponential of a probe is not limited")
+                    .with_labels(vec![Label {
+                        style: LabelStyle::Primary,
+                        file_id: file,
+                        range: range.into(),
+                        message: "may overflow during Newton iterations".to_owned(),
+                    }])
+                    .with_notes(vec![
+                        "help: use 'limexp' or limit the probe with '$limit'".to_owned()
+                    ])
+            }
+            BodyValidationDiagnostic::NonSmoothContribution { origin, dst, .. } => {
+                let FileSpan { range, file } = self.expr_src(dst);
+                let mut labels = vec![Label {
+                    style: LabelStyle::Secondary,
+                    file_id: file,
+                    range: range.into(),
+                    message: "contributed here".to_owned(),
+                }];
+                labels.push(self.non_smooth_label(origin));
+                Report::warning()
+                    .with_message("contribution is not a smooth function of the probes")
+                    .with_labels(labels)
+                    .with_notes(vec![self.non_smooth_help(origin)])
+            }
+            BodyValidationDiagnostic::ProbeDivision { denominator, .. } => {
+                let FileSpan { range, file } = self.expr_src(denominator);
+                Report::warning()
+                    .with_message("division by a probe dependent value that may be zero")
+                    .with_labels(vec![Label {
+                        style: LabelStyle::Primary,
+                        file_id: file,
+                        range: range.into(),
+                        message: "may be zero".to_owned(),
+                    }])
+                    .with_notes(vec![
+                        "help: keep the denominator away from zero, for example by adding a small positive constant".to_owned(),
+                    ])
+            }
+            BodyValidationDiagnostic::DiscontinuousDdt { expr, origin, .. } => {
+                let FileSpan { range, file } = self.expr_src(expr);
+                let mut labels = vec![Label {
+                    style: LabelStyle::Secondary,
+                    file_id: file,
+                    range: range.into(),
+                    message: "time derivative is infinite at the discontinuity".to_owned(),
+                }];
+                labels.push(self.non_smooth_label(origin));
+                Report::warning()
+                    .with_message("time derivative of a discontinuous value")
+                    .with_labels(labels)
+                    .with_notes(vec![self.non_smooth_help(origin)])
+            }
+            BodyValidationDiagnostic::RoundingDerivative { expr, fun, dst, .. } => {
+                let dst = self.expr_src(dst);
+                let FileSpan { range, file } = self.expr_src(expr);
+                Report::warning()
+                    .with_message("derivative of a rounded probe is zero")
+                    .with_labels(vec![
+                        Label {
+                            style: LabelStyle::Secondary,
+                            file_id: dst.file,
+                            range: dst.range.into(),
+                            message: "contributed here".to_owned(),
+                        },
+                        Label {
+                            style: LabelStyle::Primary,
+                            file_id: file,
+                            range: range.into(),
+                            message: format!("'{fun:?}' hides the probe from the simulator"),
+                        },
+                    ])
+                    .with_notes(vec![
+                        "help: the jacobian misses the dependence on the probe; avoid rounding probe dependent values".to_owned(),
+                    ])
+            }
+            BodyValidationDiagnostic::MixedUnits { lhs, lhs_dim, rhs, rhs_dim, .. } => {
+                let lhs = self.expr_src(lhs);
+                let rhs = self.expr_src(rhs);
+                Report::warning()
+                    .with_message(format!("mixed units '{lhs_dim}' and '{rhs_dim}'"))
+                    .with_labels(vec![
+                        Label {
+                            style: LabelStyle::Secondary,
+                            file_id: lhs.file,
+                            range: lhs.range.into(),
+                            message: format!("has unit '{lhs_dim}'"),
+                        },
+                        Label {
+                            style: LabelStyle::Primary,
+                            file_id: rhs.file,
+                            range: rhs.range.into(),
+                            message: format!("has unit '{rhs_dim}'"),
+                        },
+                    ])
+                    .with_notes(vec![
+                        "help: only values with the same units can be added or subtracted"
+                            .to_owned(),
+                    ])
+            }
+            BodyValidationDiagnostic::AssignmentUnitMismatch {
+                var,
+                dst,
+                val,
+                expected,
+                found,
+                ..
+            } => {
+                let dst = self.expr_src(dst);
+                let val = self.expr_src(val);
+                let (message, note) = match var {
+                    Some(var) => {
+                        let name = &self.db.var_data(var).name;
+                        (
+                            format!("value with unit '{found}' assigned to '{name}'"),
+                            format!("help: the units of '{name}' are set by its 'units' attribute"),
+                        )
+                    }
+                    None => (
+                        format!(
+                            "contribution with unit '{found}' to a branch with unit '{expected}'"
+                        ),
+                        "help: the units of a branch are the units of its discipline's natures"
+                            .to_owned(),
+                    ),
+                };
+                Report::warning()
+                    .with_message(message)
+                    .with_labels(vec![
+                        Label {
+                            style: LabelStyle::Secondary,
+                            file_id: dst.file,
+                            range: dst.range.into(),
+                            message: format!("expects unit '{expected}'"),
+                        },
+                        Label {
+                            style: LabelStyle::Primary,
+                            file_id: val.file,
+                            range: val.range.into(),
+                            message: format!("has unit '{found}'"),
+                        },
+                    ])
+                    .with_notes(vec![note])
+            }
         }
     }
 
@@ -19,6 +19,7 @@ use crate::db::HirTyDB;
 use crate::inference::{BranchWrite, InferenceResult, ResolvedFun};
 use crate::lower::BranchKind;
 use crate::types::{Signature, Ty};
+use crate::validation::units::{self, Dimension};
 
 #[derive(PartialEq, Eq, Clone, Debug)]
 pub enum IllegalCtxAccessKind {
@@ -96,6 +97,82 @@ pub enum BodyValidationDiagnostic {
         node1: NodeId,
         node2: NodeId,
     },
+
+    UninitializedVariable {
+        var: VarId,
+        expr: ExprId,
+        stmt: StmtId,
+        path: Box<[PathStep]>,
+    },
+
+    DeadAssignment {
+        var: VarId,
+        dst: ExprId,
+        stmt: StmtId,
+    },
+
+    UnlimitedExp {
+        expr: ExprId,
+        stmt: StmtId,
+    },
+
+    NonSmoothContribution {
+        origin: NonSmooth,
+        dst: ExprId,
+        stmt: StmtId,
+    },
+
+    ProbeDivision {
+        denominator: ExprId,
+        stmt: StmtId,
+    },
+
+    DiscontinuousDdt {
+        expr: ExprId,
+        origin: NonSmooth,
+        stmt: StmtId,
+    },
+
+    RoundingDerivative {
+        expr: ExprId,
+        fun: BuiltIn,
+        dst: ExprId,
+        stmt: StmtId,
+    },
+
+    MixedUnits {
+        lhs: ExprId,
+        lhs_dim: Dimension,
+        rhs: ExprId,
+        rhs_dim: Dimension,
+        stmt: StmtId,
+    },
+
+    /// `var` is `None` for contributions
+    AssignmentUnitMismatch {
+        var: Option<VarId>,
+        dst: ExprId,
+        val: ExprId,
+        expected: Dimension,
+        found: Dimension,
+        stmt: StmtId,
+    },
+}
+
+/// A decision in the control flow of a body that leads to a variable not being assigned.
+#[derive(PartialEq, Eq, Clone, Copy, Debug)]
+pub enum PathStep {
+    If { cond: ExprId, val: bool },
+    LoopSkipped { cond: ExprId },
+    CaseArm { expr: ExprId, default: bool },
+    NoCaseMatch { discr: ExprId },
+}
+
+/// A function or condition that causes a value to not be a smooth function of the probes.
+#[derive(PartialEq, Eq, Hash, Clone, Copy, Debug)]
+pub enum NonSmooth {
+    Call { expr: ExprId, fun: BuiltIn },
+    Condition(ExprId),
 }
 
 impl BodyValidationDiagnostic {
@@ -136,6 +213,10 @@ impl BodyValidationDiagnostic {
             }
         }
 
+        if matches!(def, DefWithBodyId::ModuleId { .. }) {
+            units::collect(db, &body, &infere, &mut validator.diagnostics);
+        }
+
         validator.diagnostics
     }
 }
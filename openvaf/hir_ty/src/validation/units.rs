@@ -0,0 +1,642 @@
+//! Dimensional analysis of the expressions in a body (`unit_mismatch`).
+//!
+//! The SI dimensions of probes are obtained from the `units` attribute of the accessed nature.
+//! Parameters and variables can be annotated with a `units` attribute. The dimensions of all
+//! other variables are inferred from their assignments. Numeric literals adapt to the
+//! dimension of the values they are combined with.
+
+use std::fmt;
+
+use ahash::HashMap;
+use basedb::{ErasedAstId, FileId};
+use hir_def::body::Body;
+use hir_def::{
+    BuiltIn, CaseCond, DisciplineId, Expr, ExprId, Literal, Lookup, NodeId, ParamId, Stmt, StmtId,
+    VarId,
+};
+use syntax::ast::{self, AssignOp, BinaryOp, UnaryOp};
+use syntax::AstNode;
+
+use crate::db::HirTyDB;
+use crate::inference::{AssignDst, BranchWrite, InferenceResult, ResolvedFun};
+use crate::types::Ty;
+use crate::validation::body::BodyValidationDiagnostic;
+
+#[cfg(test)]
+mod tests;
+
+/// The exponents of the SI base units m, kg, s, A, K, mol and cd.
+#[derive(PartialEq, Eq, Clone, Copy, Debug)]
+pub struct Dimension([i8; 7]);
+
+const BASE_UNITS: [&str; 7] = ["m", "kg", "s", "A", "K", "mol", "cd"];
+
+const DIMENSIONLESS: Dimension = Dimension([0; 7]);
+const METER: Dimension = Dimension([1, 0, 0, 0, 0, 0, 0]);
+const KILOGRAM: Dimension = Dimension([0, 1, 0, 0, 0, 0, 0]);
+const SECOND: Dimension = Dimension([0, 0, 1, 0, 0, 0, 0]);
+const AMPERE: Dimension = Dimension([0, 0, 0, 1, 0, 0, 0]);
+const KELVIN: Dimension = Dimension([0, 0, 0, 0, 1, 0, 0]);
+const VOLT: Dimension = Dimension([2, 1, -3, -1, 0, 0, 0]);
+
+/// Units with a name, in the order in which they are preferred when displaying a dimension.
+const NAMED_UNITS: [(&str, Dimension); 19] = [
+    ("V", VOLT),
+    ("A", AMPERE),
+    ("Ohm", Dimension([2, 1, -3, -2, 0, 0, 0])),
+    ("S", Dimension([-2, -1, 3, 2, 0, 0, 0])),
+    ("F", Dimension([-2, -1, 4, 2, 0, 0, 0])),
+    ("H", Dimension([2, 1, -2, -2, 0, 0, 0])),
+    ("C", Dimension([0, 0, 1, 1, 0, 0, 0])),
+    ("W", Dimension([2, 1, -3, 0, 0, 0, 0])),
+    ("J", Dimension([2, 1, -2, 0, 0, 0, 0])),
+    ("s", SECOND),
+    ("Hz", Dimension([0, 0, -1, 0, 0, 0, 0])),
+    ("K", KELVIN),
+    ("m", METER),
+    ("kg", KILOGRAM),
+    ("mol", Dimension([0, 0, 0, 0, 0, 1, 0])),
+    ("cd", Dimension([0, 0, 0, 0, 0, 0, 1])),
+    ("N", Dimension([1, 1, -2, 0, 0, 0, 0])),
+    ("Pa", Dimension([-1, 1, -2, 0, 0, 0, 0])),
+    ("Wb", Dimension([2, 1, -2, -1, 0, 0, 0])),
+];
+
+/// Alternative spellings of named units.
+const ALIASES: [(&str, &str); 7] = [
+    ("ohm", "Ohm"),
+    ("Ohms", "Ohm"),
+    ("ohms", "Ohm"),
+    ("\u{3a9}", "Ohm"),
+    ("degC", "K"),
+    ("eV", "J"),
+    ("sec", "s"),
+];
+
+const PREFIXES: [char; 12] = ['f', 'p', 'n', 'u', '\u{b5}', 'm', 'c', 'd', 'k', 'M', 'G', 'T'];
+
+impl Dimension {
+    fn mul(self, other: Dimension) -> Option<Dimension> {
+        let mut res = self;
+        for (exp, other) in res.0.iter_mut().zip(other.0) {
+            *exp = exp.checked_add(other)?;
+        }
+        Some(res)
+    }
+
+    fn div(self, other: Dimension) -> Option<Dimension> {
+        self.mul(other.powi(-1)?)
+    }
+
+    fn powi(self, exp: i8) -> Option<Dimension> {
+        let mut res = self;
+        for base in &mut res.0 {
+            *base = base.checked_mul(exp)?;
+        }
+        Some(res)
+    }
+
+    fn sqrt(self) -> Option<Dimension> {
+        let mut res = self;
+        for exp in &mut res.0 {
+            if *exp % 2 != 0 {
+                return None;
+            }
+            *exp /= 2;
+        }
+        Some(res)
+    }
+
+    /// Parses a units attribute such as `"A/V^2"`, `"Ohm*m"` or `"cm^-3"`.
+    /// Returns `None` if the units are not known. Prefixes only change the scale of a unit
+    /// and are therefore ignored.
+    pub fn parse(units: &str) -> Option<Dimension> {
+        let mut parser = UnitParser { src: units.trim(), pos: 0 };
+        if matches!(parser.src, "" | "-" | "%") {
+            return Some(DIMENSIONLESS);
+        }
+        let res = parser.product()?;
+        if parser.pos == parser.src.len() {
+            Some(res)
+        } else {
+            None
+        }
+    }
+}
+
+impl fmt::Display for Dimension {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if *self == DIMENSIONLESS {
+            return f.write_str("1");
+        }
+        if let Some((name, _)) = NAMED_UNITS.iter().find(|(_, dim)| *dim == *self) {
+            return f.write_str(name);
+        }
+        for (num, num_dim) in &NAMED_UNITS {
+            for (den, den_dim) in &NAMED_UNITS {
+                if num_dim.div(*den_dim) == Some(*self) {
+                    return write!(f, "{num}/{den}");
+                }
+            }
+        }
+
+        let mut first = true;
+        for (name, exp) in BASE_UNITS.iter().zip(self.0) {
+            if exp == 0 {
+                continue;
+            }
+            if !first {
+                f.write_str("*")?;
+            }
+            first = false;
+            f.write_str(name)?;
+            if exp != 1 {
+                write!(f, "^{exp}")?;
+            }
+        }
+        Ok(())
+    }
+}
+
+struct UnitParser<'a> {
+    src: &'a str,
+    pos: usize,
+}
+
+impl UnitParser<'_> {
+    fn product(&mut self) -> Option<Dimension> {
+        let mut res = self.factor()?;
+        loop {
+            self.skip_whitespace();
+            match self.peek() {
+                Some(c @ ('*' | '.' | '\u{b7}')) => {
+                    self.pos += c.len_utf8();
+                    res = res.mul(self.factor()?)?;
+                }
+                Some('/') => {
+                    self.pos += 1;
+                    res = res.div(self.factor()?)?;
+                }
+                Some(c) if c.is_alphabetic() => res = res.mul(self.factor()?)?,
+                _ => return Some(res),
+            }
+        }
+    }
+
+    fn factor(&mut self) -> Option<Dimension> {
+        self.skip_whitespace();
+        let base = match self.peek()? {
+            '(' => {
+                self.pos += 1;
+                let res = self.product()?;
+                self.skip_whitespace();
+                if self.peek()? != ')' {
+                    return None;
+                }
+                self.pos += 1;
+                res
+            }
+            '1' => {
+                self.pos += 1;
+                DIMENSIONLESS
+            }
+            _ => {
+                let rest = &self.src[self.pos..];
+                let end = rest.find(|c: char| !c.is_alphabetic()).unwrap_or(rest.len());
+                self.pos += end;
+                symbol(&rest[..end])?
+            }
+        };
+
+        let rest = &self.src[self.pos..];
+        let exp = if let Some(exp) = rest.strip_prefix("**") {
+            self.pos += 2;
+            exp
+        } else if let Some(exp) = rest.strip_prefix('^') {
+            self.pos += 1;
+            exp
+        } else if rest.starts_with(|c: char| c.is_ascii_digit() || c == '-') {
+            rest
+        } else {
+            return Some(base);
+        };
+        let end = exp
+            .char_indices()
+            .find(|&(i, c)| !(c.is_ascii_digit() || (i == 0 && (c == '-' || c == '+'))))
+            .map_or(exp.len(), |(i, _)| i);
+        self.pos += end;
+        base.powi(exp[..end].parse().ok()?)
+    }
+
+    fn skip_whitespace(&mut self) {
+        while let Some(c) = self.peek().filter(|c| c.is_whitespace()) {
+            self.pos += c.len_utf8();
+        }
+    }
+
+    fn peek(&self) -> Option<char> {
+        self.src[self.pos..].chars().next()
+    }
+}
+
+fn symbol(name: &str) -> Option<Dimension> {
+    let name = ALIASES.iter().find(|(alias, _)| *alias == name).map_or(name, |&(_, name)| name);
+    if let Some((_, dim)) = NAMED_UNITS.iter().find(|(unit, _)| *unit == name) {
+        return Some(*dim);
+    }
+    // the gram is the only base unit with a prefix
+    if name == "g" {
+        return Some(KILOGRAM);
+    }
+    let unprefixed = name.strip_prefix(PREFIXES)?;
+    if unprefixed.is_empty() {
+        return None;
+    }
+    symbol(unprefixed)
+}
+
+/// The dimension of a value.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+enum Unit {
+    Unknown,
+    /// numeric literals adapt to the dimension of the values they are combined with
+    Literal,
+    Known(Dimension),
+}
+
+impl Unit {
+    fn join(self, other: Unit) -> Unit {
+        match (self, other) {
+            (Unit::Literal, other) | (other, Unit::Literal) => other,
+            (Unit::Known(dim1), Unit::Known(dim2)) if dim1 == dim2 => self,
+            _ => Unit::Unknown,
+        }
+    }
+
+    fn from_dim(dim: Option<Dimension>) -> Unit {
+        dim.map_or(Unit::Unknown, Unit::Known)
+    }
+
+    fn map(self, f: impl FnOnce(Dimension) -> Option<Dimension>) -> Unit {
+        match self {
+            Unit::Known(dim) => Unit::from_dim(f(dim)),
+            unit => unit,
+        }
+    }
+
+    fn mul(self, other: Unit) -> Unit {
+        match (self, other) {
+            (Unit::Literal, other) | (other, Unit::Literal) => other,
+            (Unit::Known(dim1), Unit::Known(dim2)) => Unit::from_dim(dim1.mul(dim2)),
+            _ => Unit::Unknown,
+        }
+    }
+
+    fn div(self, other: Unit) -> Unit {
+        match (self, other) {
+            (unit, Unit::Literal) => unit,
+            (Unit::Literal, Unit::Known(dim)) => Unit::from_dim(dim.powi(-1)),
+            (Unit::Known(dim1), Unit::Known(dim2)) => Unit::from_dim(dim1.div(dim2)),
+            _ => Unit::Unknown,
+        }
+    }
+}
+
+pub(super) fn collect(
+    db: &dyn HirTyDB,
+    body: &Body,
+    infer: &InferenceResult,
+    dst: &mut Vec<BodyValidationDiagnostic>,
+) {
+    let mut analysis = Analysis {
+        db,
+        body,
+        infer,
+        annotated: HashMap::default(),
+        vars: HashMap::default(),
+        params: HashMap::default(),
+        changed: true,
+        report: false,
+        diagnostics: Vec::new(),
+    };
+
+    while analysis.changed {
+        analysis.changed = false;
+        analysis.stmts(&body.entry_stmts);
+    }
+
+    analysis.report = true;
+    analysis.stmts(&body.entry_stmts);
+    dst.append(&mut analysis.diagnostics);
+}
+
+/// Returns the `units` attribute of a parameter or variable.
+fn units_attr(db: &dyn HirTyDB, root_file: FileId, ast: ErasedAstId) -> Option<String> {
+    let ast_id_map = db.ast_id_map(root_file);
+    let idx = ast_id_map.get_attr(ast, "units")?;
+    let root = db.parse(root_file).tree();
+    let node = ast_id_map.get_syntax(ast).to_node(root.syntax());
+    // the attributes of parameters and variables belong to their declaration
+    let mut attrs = if ast::Var::can_cast(node.kind()) || ast::Param::can_cast(node.kind()) {
+        ast::attrs(&node.parent()?)
+    } else {
+        ast::attrs(&node)
+    };
+    attrs.nth(idx)?.val()?.as_str_literal()
+}
+
+struct Analysis<'a> {
+    db: &'a dyn HirTyDB,
+    body: &'a Body,
+    infer: &'a InferenceResult,
+    /// variables with a `units` attribute
+    annotated: HashMap<VarId, Option<Dimension>>,
+    /// the units inferred for variables without a `units` attribute
+    vars: HashMap<VarId, Unit>,
+    params: HashMap<ParamId, Unit>,
+    /// set if the units of any variable changed during the last pass
+    changed: bool,
+    /// diagnostics are only reported during the final pass
+    report: bool,
+    diagnostics: Vec<BodyValidationDiagnostic>,
+}
+
+impl Analysis<'_> {
+    fn stmts(&mut self, stmts: &[StmtId]) {
+        for stmt in stmts {
+            self.stmt(*stmt)
+        }
+    }
+
+    fn stmt(&mut self, stmt: StmtId) {
+        let (body, infer) = (self.body, self.infer);
+        match body.stmts[stmt] {
+            Stmt::Missing | Stmt::Empty => (),
+            Stmt::Expr(expr) => {
+                self.expr(expr, stmt);
+            }
+            Stmt::Assignment { dst, val, assignment_kind } => {
+                let unit = self.expr(val, stmt);
+                let dst_unit = match infer.assignment_destination.get(&stmt) {
+                    Some(&AssignDst::Var(var)) if assignment_kind == AssignOp::Assign => {
+                        let annotation = self.annotation(var);
+                        if annotation.is_none() {
+                            self.infer_var(var, unit);
+                        }
+                        annotation.map(|dim| (Some(var), dim))
+                    }
+                    Some(&AssignDst::Flow(branch)) => {
+                        self.branch_units(branch, false).map(|dim| (None, dim))
+                    }
+                    Some(&AssignDst::Potential(branch)) => {
+                        self.branch_units(branch, true).map(|dim| (None, dim))
+                    }
+                    _ => None,
+                };
+                if let (Some((var, expected)), Unit::Known(found)) = (dst_unit, unit) {
+                    if expected != found {
+                        self.push(BodyValidationDiagnostic::AssignmentUnitMismatch {
+                            var,
+                            dst,
+                            val,
+                            expected,
+                            found,
+                            stmt,
+                        })
+                    }
+                }
+            }
+            Stmt::Block { ref body } => self.stmts(body),
+            Stmt::EventControl { body, .. } => self.stmt(body),
+            Stmt::If { cond, then_branch, else_branch } => {
+                self.expr(cond, stmt);
+                self.stmt(then_branch);
+                self.stmt(else_branch);
+            }
+            Stmt::WhileLoop { cond, body } => {
+                self.expr(cond, stmt);
+                self.stmt(body);
+            }
+            Stmt::ForLoop { init, cond, incr, body } => {
+                self.stmt(init);
+                self.expr(cond, stmt);
+                self.stmt(body);
+                self.stmt(incr);
+            }
+            Stmt::Case { discr, ref case_arms } => {
+                self.expr(discr, stmt);
+                for case in case_arms {
+                    if let CaseCond::Vals(ref vals) = case.cond {
+                        for val in vals {
+                            self.expr(*val, stmt);
+                        }
+                    }
+                    self.stmt(case.body)
+                }
+            }
+        }
+    }
+
+    fn annotation(&mut self, var: VarId) -> Option<Dimension> {
+        let db = self.db;
+        *self.annotated.entry(var).or_insert_with(|| {
+            let loc = var.lookup(db.upcast());
+            let ast = loc.ast_id(db.upcast()).erased();
+            units_attr(db, loc.scope.root_file, ast).and_then(|units| Dimension::parse(&units))
+        })
+    }
+
+    fn infer_var(&mut self, var: VarId, unit: Unit) {
+        let new = match self.vars.get(&var) {
+            Some(old) => old.join(unit),
+            None => unit,
+        };
+        if self.vars.insert(var, new) != Some(new) {
+            self.changed = true;
+        }
+    }
+
+    fn param(&mut self, param: ParamId) -> Unit {
+        let db = self.db;
+        *self.params.entry(param).or_insert_with(|| {
+            let loc = param.lookup(db.upcast());
+            let ast = loc.ast_id(db.upcast()).erased();
+            Unit::from_dim(
+                units_attr(db, loc.scope.root_file, ast).and_then(|units| Dimension::parse(&units)),
+            )
+        })
+    }
+
+    fn branch_units(&self, branch: BranchWrite, potential: bool) -> Option<Dimension> {
+        let discipline = match branch {
+            BranchWrite::Named(branch) => self.db.branch_info(branch)?.discipline,
+            BranchWrite::Unnamed { hi, .. } => self.db.node_discipline(hi)?,
+        };
+        self.discipline_units(discipline, potential)
+    }
+
+    fn node_units(&self, node: NodeId, potential: bool) -> Option<Dimension> {
+        self.discipline_units(self.db.node_discipline(node)?, potential)
+    }
+
+    fn discipline_units(&self, discipline: DisciplineId, potential: bool) -> Option<Dimension> {
+        let info = self.db.discipline_info(discipline);
+        let nature = if potential { info.potential } else { info.flow }?;
+        Dimension::parse(self.db.nature_info(nature).units.as_deref()?)
+    }
+
+    fn expr(&mut self, expr: ExprId, stmt: StmtId) -> Unit {
+        let (body, infer) = (self.body, self.infer);
+        match body.exprs[expr] {
+            Expr::Literal(Literal::String(_)) => Unit::Unknown,
+            Expr::Literal(_) => Unit::Literal,
+            Expr::Path { port: false, .. } => match infer.expr_types[expr] {
+                Ty::Var(_, var) => match self.annotation(var) {
+                    Some(dim) => Unit::Known(dim),
+                    None => self.vars.get(&var).copied().unwrap_or(Unit::Unknown),
+                },
+                Ty::Param(_, param) => self.param(param),
+                _ => Unit::Unknown,
+            },
+            Expr::BinaryOp { lhs, rhs, op } => {
+                let lhs_unit = self.expr(lhs, stmt);
+                let rhs_unit = self.expr(rhs, stmt);
+                match op {
+                    Some(BinaryOp::Addition | BinaryOp::Subtraction) => {
+                        if let (Unit::Known(lhs_dim), Unit::Known(rhs_dim)) = (lhs_unit, rhs_unit) {
+                            if lhs_dim != rhs_dim {
+                                self.push(BodyValidationDiagnostic::MixedUnits {
+                                    lhs,
+                                    lhs_dim,
+                                    rhs,
+                                    rhs_dim,
+                                    stmt,
+                                })
+                            }
+                        }
+                        lhs_unit.join(rhs_unit)
+                    }
+                    Some(BinaryOp::Multiplication) => lhs_unit.mul(rhs_unit),
+                    Some(BinaryOp::Division) => lhs_unit.div(rhs_unit),
+                    Some(BinaryOp::Remainder) => lhs_unit.join(rhs_unit),
+                    Some(BinaryOp::Power) => self.pow(lhs_unit, rhs),
+                    Some(
+                        BinaryOp::BooleanOr
+                        | BinaryOp::BooleanAnd
+                        | BinaryOp::EqualityTest
+                        | BinaryOp::NegatedEqualityTest
+                        | BinaryOp::LesserEqualTest
+                        | BinaryOp::GreaterEqualTest
+                        | BinaryOp::LesserTest
+                        | BinaryOp::GreaterTest,
+                    ) => Unit::Literal,
+                    _ => Unit::Unknown,
+                }
+            }
+            Expr::UnaryOp { expr: arg, op } => {
+                let unit = self.expr(arg, stmt);
+                match op {
+                    UnaryOp::Neg | UnaryOp::Identity => unit,
+                    UnaryOp::Not => Unit::Literal,
+                    UnaryOp::BitNegate => Unit::Unknown,
+                }
+            }
+            Expr::Select { cond, then_val, else_val } => {
+                self.expr(cond, stmt);
+                let unit = self.expr(then_val, stmt);
+                unit.join(self.expr(else_val, stmt))
+            }
+            Expr::Call { ref args, .. } => {
+                let units: Vec<_> = args.iter().map(|arg| self.expr(*arg, stmt)).collect();
+                match infer.resolved_calls.get(&expr) {
+                    Some(&ResolvedFun::BuiltIn(fun)) => self.builtin(fun, args, &units),
+                    _ => Unit::Unknown,
+                }
+            }
+            Expr::Array(ref vals) => {
+                for val in vals {
+                    self.expr(*val, stmt);
+                }
+                Unit::Unknown
+            }
+            Expr::Missing | Expr::Path { .. } => Unit::Unknown,
+        }
+    }
+
+    fn builtin(&self, fun: BuiltIn, args: &[ExprId], units: &[Unit]) -> Unit {
+        let arg = |i: usize| units.get(i).copied().unwrap_or(Unit::Unknown);
+        match fun {
+            BuiltIn::potential | BuiltIn::flow => {
+                let potential = fun == BuiltIn::potential;
+                let dim = match args.first().map(|arg| &self.infer.expr_types[*arg]) {
+                    Some(&Ty::Node(node) | &Ty::PortFlow(node)) => self.node_units(node, potential),
+                    Some(&Ty::Branch(branch)) => {
+                        self.branch_units(BranchWrite::Named(branch), potential)
+                    }
+                    _ => None,
+                };
+                Unit::from_dim(dim)
+            }
+            BuiltIn::abs
+            | BuiltIn::floor
+            | BuiltIn::ceil
+            | BuiltIn::limit
+            | BuiltIn::absdelay
+            | BuiltIn::transition
+            | BuiltIn::slew => arg(0),
+            BuiltIn::min | BuiltIn::max | BuiltIn::hypot => arg(0).join(arg(1)),
+            BuiltIn::sqrt => arg(0).map(Dimension::sqrt),
+            BuiltIn::pow => match args.get(1) {
+                Some(&exp) => self.pow(arg(0), exp),
+                None => Unit::Unknown,
+            },
+            BuiltIn::exp
+            | BuiltIn::limexp
+            | BuiltIn::ln
+            | BuiltIn::log
+            | BuiltIn::log10
+            | BuiltIn::sin
+            | BuiltIn::cos
+            | BuiltIn::tan
+            | BuiltIn::asin
+            | BuiltIn::acos
+            | BuiltIn::atan
+            | BuiltIn::atan2
+            | BuiltIn::sinh
+            | BuiltIn::cosh
+            | BuiltIn::tanh
+            | BuiltIn::asinh
+            | BuiltIn::acosh
+            | BuiltIn::atanh => Unit::Known(DIMENSIONLESS),
+            BuiltIn::ddt => arg(0).div(Unit::Known(SECOND)),
+            BuiltIn::idt | BuiltIn::idtmod => arg(0).mul(Unit::Known(SECOND)),
+            BuiltIn::ddx => arg(0).div(arg(1)),
+            BuiltIn::temperature => Unit::Known(KELVIN),
+            BuiltIn::vt => Unit::Known(VOLT),
+            BuiltIn::abstime => Unit::Known(SECOND),
+            _ => Unit::Unknown,
+        }
+    }
+
+    /// Exponents are only supported if they are integer literals.
+    fn pow(&self, base: Unit, exp: ExprId) -> Unit {
+        let exp = match self.body.exprs[exp] {
+            Expr::Literal(Literal::Int(exp)) => i8::try_from(exp).ok(),
+            Expr::Literal(Literal::Float(exp)) if f64::from(exp) == 0.5 => {
+                return base.map(Dimension::sqrt)
+            }
+            _ => None,
+        };
+        match (base, exp) {
+            (Unit::Literal, _) => Unit::Literal,
+            (_, Some(exp)) => base.map(|dim| dim.powi(exp)),
+            _ => Unit::Unknown,
+        }
+    }
+
+    fn push(&mut self, diagnostic: BodyValidationDiagnostic) {
+        if self.report {
+            self.diagnostics.push(diagnostic)
+        }
+    }
+}
@@ -0,0 +1,163 @@
+use super::{Dimension, AMPERE, DIMENSIONLESS, KELVIN, KILOGRAM, METER, SECOND, VOLT};
+
+const OHM: Dimension = Dimension([2, 1, -3, -2, 0, 0, 0]);
+const SIEMENS: Dimension = Dimension([-2, -1, 3, 2, 0, 0, 0]);
+const FARAD: Dimension = Dimension([-2, -1, 4, 2, 0, 0, 0]);
+const JOULE: Dimension = Dimension([2, 1, -2, 0, 0, 0, 0]);
+const HERTZ: Dimension = Dimension([0, 0, -1, 0, 0, 0, 0]);
+const MOL: Dimension = Dimension([0, 0, 0, 0, 0, 1, 0]);
+
+fn parse(units: &str) -> Dimension {
+    Dimension::parse(units).unwrap_or_else(|| panic!("failed to parse {units:?}"))
+}
+
+#[test]
+fn prefixes() {
+    assert_eq!(parse("mA"), AMPERE);
+    assert_eq!(parse("kOhm"), OHM);
+    assert_eq!(parse("pF"), FARAD);
+    assert_eq!(parse("fF"), FARAD);
+    assert_eq!(parse("nm"), METER);
+    assert_eq!(parse("um"), METER);
+    assert_eq!(parse("\u{b5}m"), METER);
+    assert_eq!(parse("mm"), METER);
+    assert_eq!(parse("cm"), METER);
+    assert_eq!(parse("ms"), SECOND);
+    assert_eq!(parse("MHz"), HERTZ);
+    assert_eq!(parse("GHz"), HERTZ);
+    assert_eq!(parse("TV"), VOLT);
+    assert_eq!(parse("dmol"), MOL);
+    // the gram is only valid with a prefix because the base unit is the kilogram
+    assert_eq!(parse("g"), KILOGRAM);
+    assert_eq!(parse("mg"), KILOGRAM);
+    assert_eq!(parse("kg"), KILOGRAM);
+    // named units that start with a prefix are not split
+    assert_eq!(parse("m"), METER);
+    assert_eq!(parse("cd"), Dimension([0, 0, 0, 0, 0, 0, 1]));
+    assert_eq!(parse("Pa"), Dimension([-1, 1, -2, 0, 0, 0, 0]));
+}
+
+#[test]
+fn exponents() {
+    assert_eq!(parse("m^2"), Dimension([2, 0, 0, 0, 0, 0, 0]));
+    assert_eq!(parse("m**2"), Dimension([2, 0, 0, 0, 0, 0, 0]));
+    assert_eq!(parse("m2"), Dimension([2, 0, 0, 0, 0, 0, 0]));
+    assert_eq!(parse("cm^-3"), Dimension([-3, 0, 0, 0, 0, 0, 0]));
+    assert_eq!(parse("cm-3"), Dimension([-3, 0, 0, 0, 0, 0, 0]));
+    assert_eq!(parse("s^+1"), SECOND);
+    assert_eq!(parse("s^-1"), HERTZ);
+    assert_eq!(parse("V^0"), DIMENSIONLESS);
+    assert_eq!(parse("(A/V)^2"), Dimension([-4, -2, 6, 4, 0, 0, 0]));
+}
+
+#[test]
+fn products_and_quotients() {
+    assert_eq!(parse("A/V"), SIEMENS);
+    assert_eq!(parse("V/A"), OHM);
+    assert_eq!(parse("A/V^2"), Dimension([-4, -2, 6, 3, 0, 0, 0]));
+    assert_eq!(parse("Ohm*m"), Dimension([3, 1, -3, -2, 0, 0, 0]));
+    assert_eq!(parse("V*s"), parse("Wb"));
+    assert_eq!(parse("V.s"), parse("Wb"));
+    assert_eq!(parse("V\u{b7}s"), parse("Wb"));
+    assert_eq!(parse("A s"), parse("C"));
+    assert_eq!(parse("J/K"), Dimension([2, 1, -2, 0, -1, 0, 0]));
+    assert_eq!(parse("W/(m*K)"), Dimension([1, 1, -3, 0, -1, 0, 0]));
+    assert_eq!(parse("1/s"), HERTZ);
+    // quotients are left associative
+    assert_eq!(parse("m/s/s"), Dimension([1, 0, -2, 0, 0, 0, 0]));
+    assert_eq!(parse(" F / m "), Dimension([-3, -1, 4, 2, 0, 0, 0]));
+}
+
+#[test]
+fn aliases() {
+    for ohm in ["Ohm", "ohm", "Ohms", "ohms", "\u{3a9}", "kohm", "M\u{3a9}"] {
+        assert_eq!(parse(ohm), OHM, "{ohm}");
+    }
+    assert_eq!(parse("S"), SIEMENS);
+    assert_eq!(parse("1/Ohm"), SIEMENS);
+    assert_eq!(parse("F"), FARAD);
+    assert_eq!(parse("C/V"), FARAD);
+    assert_eq!(parse("degC"), KELVIN);
+    assert_eq!(parse("eV"), JOULE);
+    assert_eq!(parse("sec"), SECOND);
+}
+
+#[test]
+fn dimensionless() {
+    for units in ["", " ", "-", "%", "1", "V/V", "A*Ohm/V", "s*Hz"] {
+        assert_eq!(parse(units), DIMENSIONLESS, "{units:?}");
+    }
+}
+
+#[test]
+fn unknown_units() {
+    for units in ["foo", "k", "V^x", "V^", "(V", "V)", "A/", "*A", "V//A", "m^200", "V + A"] {
+        assert_eq!(Dimension::parse(units), None, "{units:?}");
+    }
+}
+
+#[test]
+fn arithmetic() {
+    assert_eq!(VOLT.mul(AMPERE), Some(Dimension([2, 1, -3, 0, 0, 0, 0])));
+    assert_eq!(VOLT.div(AMPERE), Some(OHM));
+    assert_eq!(SIEMENS.powi(-1), Some(OHM));
+    assert_eq!(Dimension([2, 0, -4, 0, 0, 0, 0]).sqrt(), Some(Dimension([1, 0, -2, 0, 0, 0, 0])));
+    assert_eq!(VOLT.sqrt(), None);
+    assert_eq!(Dimension([100, 0, 0, 0, 0, 0, 0]).powi(2), None);
+    assert_eq!(Dimension([-100, 0, 0, 0, 0, 0, 0]).div(Dimension([100, 0, 0, 0, 0, 0, 0])), None);
+}
+
+#[test]
+fn display() {
+    assert_eq!(DIMENSIONLESS.to_string(), "1");
+    assert_eq!(VOLT.to_string(), "V");
+    assert_eq!(OHM.to_string(), "Ohm");
+    assert_eq!(SIEMENS.to_string(), "S");
+    assert_eq!(parse("V/m").to_string(), "V/m");
+    assert_eq!(parse("A/V^2").to_string(), "S/V");
+    // without a named numerator and denominator the base units are used
+    assert_eq!(parse("mol^2").to_string(), "mol^2");
+    assert_eq!(parse("m*mol").to_string(), "m*mol");
+    assert_eq!(parse("1/mol").to_string(), "mol^-1");
+}
+
+#[test]
+fn display_round_trip() {
+    let units = [
+        "1",
+        "V",
+        "A",
+        "Ohm",
+        "S",
+        "F",
+        "H",
+        "C",
+        "W",
+        "J",
+        "s",
+        "Hz",
+        "K",
+        "m",
+        "kg",
+        "mol",
+        "cd",
+        "N",
+        "Pa",
+        "Wb",
+        "V/m",
+        "A/V^2",
+        "Ohm*m",
+        "cm^-3",
+        "J/K",
+        "m/s^2",
+        "mol^2",
+        "m*mol",
+        "1/mol",
+        "A*s^3/(kg*m^3)",
+        "K^2*cd^-1",
+    ];
+    for units in units {
+        let dim = parse(units);
+        assert_eq!(Dimension::parse(&dim.to_string()), Some(dim), "{units} -> {dim}");
+    }
+}
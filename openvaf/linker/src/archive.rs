@@ -0,0 +1,192 @@
+use std::fs;
+use std::io::Write;
+
+use anyhow::{Context, Result};
+use camino::Utf8Path;
+use target::spec::Target;
+
+#[cfg(test)]
+mod tests;
+
+const MAGIC: &[u8] = b"!<arch>\n";
+const HEADER_LEN: usize = 60;
+
+struct Member<'a> {
+    name: &'a str,
+    data: Vec<u8>,
+    symbols: Vec<String>,
+}
+
+/// Writes a static library that contains the object files in `members` to `dst`.
+/// The symbols defined by each object file must be provided alongside it, so that
+/// they can be added to the symbol table of the archive. The archive is written
+/// without invoking any external tools: BSD archives are produced for apple targets
+/// and GNU archives (which are also understood by MSVC) for all other targets.
+pub fn write_archive(
+    target: &Target,
+    dst: &Utf8Path,
+    members: &[(&Utf8Path, &[String])],
+) -> Result<()> {
+    // some targets prefix C symbols with an underscore
+    let mangle = target.options.is_like_osx
+        || (target.options.is_like_windows && target.pointer_width == 32);
+    let members = members
+        .iter()
+        .map(|&(path, symbols)| {
+            let data = fs::read(path).with_context(|| format!("failed to read {path}"))?;
+            let symbols = symbols
+                .iter()
+                .map(|sym| if mangle { format!("_{sym}") } else { sym.clone() })
+                .collect();
+            let name = path.file_name().expect("object files are files");
+            Ok(Member { name, data, symbols })
+        })
+        .collect::<Result<Vec<_>>>()?;
+
+    let archive =
+        if target.options.is_like_osx { bsd_archive(&members) } else { gnu_archive(&members) };
+    fs::write(dst, archive).with_context(|| format!("failed to write {dst}"))
+}
+
+fn gnu_archive(members: &[Member]) -> Vec<u8> {
+    let num_symbols: usize = members.iter().map(|member| member.symbols.len()).sum();
+    let strtab_len: usize =
+        members.iter().flat_map(|member| &member.symbols).map(|sym| sym.len() + 1).sum();
+    let symtab_len = 4 + 4 * num_symbols + strtab_len;
+
+    // names that do not fit into the header are stored in a separate member
+    let mut long_names = String::new();
+    let names: Vec<_> = members
+        .iter()
+        .map(|member| {
+            if member.name.len() < 16 {
+                format!("{}/", member.name)
+            } else {
+                let name = format!("/{}", long_names.len());
+                long_names.push_str(member.name);
+                long_names.push_str("/\n");
+                name
+            }
+        })
+        .collect();
+
+    let mut pos = MAGIC.len() + HEADER_LEN + pad_to(symtab_len, 2);
+    if !long_names.is_empty() {
+        pos += HEADER_LEN + pad_to(long_names.len(), 2);
+    }
+    let offsets: Vec<_> = members
+        .iter()
+        .map(|member| {
+            let offset = pos;
+            pos += HEADER_LEN + pad_to(member.data.len(), 2);
+            offset as u32
+        })
+        .collect();
+
+    let mut buf = MAGIC.to_vec();
+    write_header(&mut buf, "/", symtab_len);
+    buf.extend_from_slice(&(num_symbols as u32).to_be_bytes());
+    for (member, offset) in members.iter().zip(&offsets) {
+        for _ in &member.symbols {
+            buf.extend_from_slice(&offset.to_be_bytes());
+        }
+    }
+    for sym in members.iter().flat_map(|member| &member.symbols) {
+        buf.extend_from_slice(sym.as_bytes());
+        buf.push(0);
+    }
+    pad_buf(&mut buf, 2);
+
+    if !long_names.is_empty() {
+        write_header(&mut buf, "//", long_names.len());
+        buf.extend_from_slice(long_names.as_bytes());
+        pad_buf(&mut buf, 2);
+    }
+
+    for (member, name) in members.iter().zip(names) {
+        write_header(&mut buf, &name, member.data.len());
+        buf.extend_from_slice(&member.data);
+        pad_buf(&mut buf, 2);
+    }
+
+    buf
+}
+
+fn bsd_archive(members: &[Member]) -> Vec<u8> {
+    const SYMDEF: &str = "__.SYMDEF";
+
+    let num_symbols: usize = members.iter().map(|member| member.symbols.len()).sum();
+    let strtab_len: usize =
+        members.iter().flat_map(|member| &member.symbols).map(|sym| sym.len() + 1).sum();
+    let strtab_len = pad_to(strtab_len, 4);
+    let symdef_len = 4 + 8 * num_symbols + 4 + strtab_len;
+
+    let mut pos = MAGIC.len();
+    pos += HEADER_LEN + bsd_name_len(pos, SYMDEF) + pad_to(symdef_len, 8);
+    let offsets: Vec<_> = members
+        .iter()
+        .map(|member| {
+            let offset = pos;
+            pos += HEADER_LEN + bsd_name_len(pos, member.name) + pad_to(member.data.len(), 8);
+            offset as u32
+        })
+        .collect();
+
+    let mut buf = MAGIC.to_vec();
+    write_bsd_header(&mut buf, SYMDEF, symdef_len);
+    buf.extend_from_slice(&(8 * num_symbols as u32).to_le_bytes());
+    let mut strx = 0u32;
+    for (member, offset) in members.iter().zip(&offsets) {
+        for sym in &member.symbols {
+            buf.extend_from_slice(&strx.to_le_bytes());
+            buf.extend_from_slice(&offset.to_le_bytes());
+            strx += sym.len() as u32 + 1;
+        }
+    }
+    buf.extend_from_slice(&(strtab_len as u32).to_le_bytes());
+    let strtab_start = buf.len();
+    for sym in members.iter().flat_map(|member| &member.symbols) {
+        buf.extend_from_slice(sym.as_bytes());
+        buf.push(0);
+    }
+    buf.resize(strtab_start + strtab_len, 0);
+    pad_buf(&mut buf, 8);
+
+    for member in members {
+        write_bsd_header(&mut buf, member.name, member.data.len());
+        buf.extend_from_slice(&member.data);
+        pad_buf(&mut buf, 8);
+    }
+
+    buf
+}
+
+fn write_header(buf: &mut Vec<u8>, name: &str, size: usize) {
+    // timestamp, owner and group are zeroed for deterministic output
+    writeln!(buf, "{name:<16}{:<12}{:<6}{:<6}{:<8}{size:<10}`", 0, 0, 0, 644).unwrap();
+}
+
+/// BSD archives store the member name in front of the data. The name is padded
+/// so that the data is 8 byte aligned.
+fn bsd_name_len(pos: usize, name: &str) -> usize {
+    let start = pos + HEADER_LEN;
+    pad_to(start + name.len(), 8) - start
+}
+
+fn write_bsd_header(buf: &mut Vec<u8>, name: &str, size: usize) {
+    let name_len = bsd_name_len(buf.len(), name);
+    // the size includes the name and the padding after the data
+    write_header(buf, &format!("#1/{name_len}"), name_len + pad_to(size, 8));
+    buf.extend_from_slice(name.as_bytes());
+    buf.resize(buf.len() + name_len - name.len(), 0);
+}
+
+/// Rounds `len` up to a multiple of `align` (which must be a power of two).
+fn pad_to(len: usize, align: usize) -> usize {
+    (len + align - 1) & !(align - 1)
+}
+
+fn pad_buf(buf: &mut Vec<u8>, align: usize) {
+    let fill = if align == 2 { b'\n' } else { 0 };
+    buf.resize(pad_to(buf.len(), align), fill)
+}
@@ -0,0 +1,150 @@
+use std::fs;
+
+use camino::Utf8PathBuf;
+use object::read::archive::{ArchiveFile, ArchiveKind};
+use target::spec::Target;
+
+use super::{bsd_archive, gnu_archive, write_archive, Member, HEADER_LEN, MAGIC};
+
+fn members() -> Vec<Member<'static>> {
+    // odd sizes to check the padding between members
+    vec![
+        Member {
+            name: "foo.o",
+            data: b"foo".to_vec(),
+            symbols: vec!["OSDI_DESCRIPTORS".to_owned(), "osdi_log".to_owned()],
+        },
+        Member { name: "no_symbols.o", data: vec![0xff; 8], symbols: Vec::new() },
+        Member {
+            name: "a_very_long_object_name.o",
+            data: b"a longer member".to_vec(),
+            symbols: vec!["OSDI_NUM_DESCRIPTORS".to_owned()],
+        },
+    ]
+}
+
+/// Reads the symbol table (the first member of the archive) as `(symbol, member offset)`.
+fn symbol_table(archive: &[u8], kind: ArchiveKind) -> Vec<(String, usize)> {
+    let header = &archive[MAGIC.len()..MAGIC.len() + HEADER_LEN];
+    let size: usize = std::str::from_utf8(&header[48..58]).unwrap().trim().parse().unwrap();
+    let mut data = &archive[MAGIC.len() + HEADER_LEN..][..size];
+    if kind == ArchiveKind::Bsd {
+        assert!(header.starts_with(b"#1/"));
+        let name_len: usize = std::str::from_utf8(&header[3..16]).unwrap().trim().parse().unwrap();
+        assert!(data.starts_with(b"__.SYMDEF\0"));
+        data = &data[name_len..];
+    }
+
+    let u32_at = |pos: usize| {
+        let bytes = data[pos..pos + 4].try_into().unwrap();
+        let val = if kind == ArchiveKind::Gnu {
+            u32::from_be_bytes(bytes)
+        } else {
+            u32::from_le_bytes(bytes)
+        };
+        val as usize
+    };
+    let name_at = |strtab: &[u8], pos: usize| {
+        let len = strtab[pos..].iter().position(|&c| c == 0).unwrap();
+        String::from_utf8(strtab[pos..pos + len].to_vec()).unwrap()
+    };
+
+    match kind {
+        ArchiveKind::Gnu => {
+            let num_symbols = u32_at(0);
+            let strtab = &data[4 + 4 * num_symbols..];
+            let mut strx = 0;
+            (0..num_symbols)
+                .map(|i| {
+                    let name = name_at(strtab, strx);
+                    strx += name.len() + 1;
+                    (name, u32_at(4 + 4 * i))
+                })
+                .collect()
+        }
+        ArchiveKind::Bsd => {
+            let ranlib_len = u32_at(0);
+            let strtab = &data[8 + ranlib_len..];
+            (0..ranlib_len / 8)
+                .map(|i| (name_at(strtab, u32_at(4 + 8 * i)), u32_at(8 + 8 * i)))
+                .collect()
+        }
+        kind => unreachable!("unexpected archive kind {kind:?}"),
+    }
+}
+
+fn check_archive(archive: &[u8], kind: ArchiveKind, members: &[Member]) {
+    let file = ArchiveFile::parse(archive).unwrap();
+    assert_eq!(file.kind(), kind);
+
+    let mut header_offsets = Vec::new();
+    let parsed: Vec<_> = file.members().map(|member| member.unwrap()).collect();
+    assert_eq!(parsed.len(), members.len());
+    for (member, expected) in parsed.iter().zip(members) {
+        assert_eq!(std::str::from_utf8(member.name()).unwrap(), expected.name);
+        let data = member.data(archive).unwrap();
+        let (data_start, _) = member.file_range();
+        let mut header_start = data_start as usize - HEADER_LEN;
+        if kind == ArchiveKind::Bsd {
+            // the size of BSD members includes the padding after the data
+            assert_eq!(&data[..expected.data.len()], &*expected.data);
+            assert!(data[expected.data.len()..].iter().all(|&c| c == 0));
+            assert_eq!(data_start % 8, 0, "data of {} is not aligned", expected.name);
+            let name = &member.header().unwrap().name;
+            let name_len: usize = std::str::from_utf8(&name[3..]).unwrap().trim().parse().unwrap();
+            header_start -= name_len;
+        } else {
+            assert_eq!(data, &*expected.data);
+        }
+        header_offsets.push(header_start);
+    }
+
+    let expected: Vec<_> = members
+        .iter()
+        .zip(header_offsets)
+        .flat_map(|(member, offset)| member.symbols.iter().map(move |sym| (sym.clone(), offset)))
+        .collect();
+    assert_eq!(symbol_table(archive, kind), expected);
+}
+
+#[test]
+fn gnu() {
+    let members = members();
+    let archive = gnu_archive(&members);
+    assert_eq!(archive.len() % 2, 0);
+    check_archive(&archive, ArchiveKind::Gnu, &members);
+}
+
+#[test]
+fn bsd() {
+    let members = members();
+    let archive = bsd_archive(&members);
+    assert_eq!(archive.len() % 8, 0);
+    check_archive(&archive, ArchiveKind::Bsd, &members);
+}
+
+#[test]
+fn symbol_mangling() {
+    let dir =
+        Utf8PathBuf::from_path_buf(std::env::temp_dir().join("openvaf_test_archive")).unwrap();
+    fs::create_dir_all(&dir).unwrap();
+    let obj = dir.join("foo.o");
+    fs::write(&obj, b"foo").unwrap();
+    let symbols = ["OSDI_DESCRIPTORS".to_owned()];
+
+    for (target, kind, sym) in [
+        ("x86_64-unknown-linux", ArchiveKind::Gnu, "OSDI_DESCRIPTORS"),
+        ("x86_64-pc-windows", ArchiveKind::Gnu, "OSDI_DESCRIPTORS"),
+        ("aarch64-apple-darwin", ArchiveKind::Bsd, "_OSDI_DESCRIPTORS"),
+    ] {
+        let target = Target::search(target).unwrap();
+        let dst = dir.join("libfoo.a");
+        write_archive(&target, &dst, &[(&obj, &symbols)]).unwrap();
+        let archive = fs::read(&dst).unwrap();
+        let expected =
+            [Member { name: "foo.o", data: b"foo".to_vec(), symbols: vec![sym.to_owned()] }];
+        check_archive(&archive, kind, &expected);
+    }
+
+    fs::remove_dir_all(&dir).unwrap();
+}
@@ -11,6 +11,10 @@ use std::process::{Output, Stdio};
 use std::{ascii, env, io};
 use target::spec::{LinkerFlavor, Target};
 
+pub use crate::archive::write_archive;
+
+mod archive;
+
 pub fn link(
     path: Option<Utf8PathBuf>,
     target: &Target,
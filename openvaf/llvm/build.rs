@@ -158,6 +158,7 @@ fn main() {
         "lto",
         "debuginfopdb",
         "windowsmanifest",
+        "orcjit",
         "libdriver", // "coverage",
                      // "instrumentation",
     ];
@@ -1,4 +1,4 @@
-use libc::{c_char, size_t};
+use libc::{c_char, c_int, size_t};
 
 use crate::{Bool, Context, MemoryBuffer, Module, Value};
 
@@ -15,5 +15,11 @@ extern "C" {
         dst_module: &mut Option<&'a Module>,
     ) -> Bool;
 
+    pub fn LLVMWriteBitcodeToFile(module: &Module, path: *const c_char) -> c_int;
+    pub fn LLVMWriteBitcodeToMemoryBuffer(module: &Module) -> &'static mut MemoryBuffer;
+    pub fn LLVMGetBufferStart(buf: &MemoryBuffer) -> *const c_char;
+    pub fn LLVMGetBufferSize(buf: &MemoryBuffer) -> size_t;
+    pub fn LLVMDisposeMemoryBuffer(buf: &'static mut MemoryBuffer);
+
     pub fn LLVMGetNamedFunction<'a>(module: &'a Module, name: *const c_char) -> Option<&'a Value>;
 }
@@ -32,6 +32,7 @@ pub mod context;
 pub mod initialization;
 // pub mod lld;
 pub mod module;
+pub mod orc;
 pub mod pass_manager;
 pub mod support;
 pub mod targets;
@@ -45,6 +46,7 @@ pub use builder::*;
 pub use context::*;
 pub use initialization::*;
 pub use module::*;
+pub use orc::*;
 pub use pass_manager::*;
 pub use targets::*;
 pub use types::*;
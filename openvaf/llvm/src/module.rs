@@ -54,6 +54,11 @@ extern "C" {
 
     // pub fn LLVMDumpModule(module: &Module);
     pub fn LLVMPrintModuleToString(module: &Module) -> *mut c_char;
+    pub fn LLVMPrintModuleToFile(
+        module: &Module,
+        file_name: *const c_char,
+        ErrorMessage: *mut *mut c_char,
+    ) -> Bool;
 
     // pub fn LLVMGetModuleInlineAsm(module: &Module, Len: *mut size_t) -> *const c_char;
     // pub fn LLVMSetModuleInlineAsm2(module: &Module, Asm: *const c_char, Len: size_t);
@@ -106,6 +111,8 @@ extern "C" {
     // fn LLVMGetLastFunction<'a>(module: &Module) -> Option<&'a Value>;
     fn LLVMGetNextFunction(fun: &Value) -> Option<&Value>;
     // fn LLVMGetPreviousFunction<'a>(Fn: &'a Value) -> Option<&'a Value>;
+    fn LLVMGetFirstGlobal(module: &Module) -> Option<&Value>;
+    fn LLVMGetNextGlobal(global: &Value) -> Option<&Value>;
 
     /// Verify that a module is valid, taking the specified action if not.
     ///
@@ -124,6 +131,11 @@ pub fn function_iter(module: &Module) -> impl Iterator<Item = &Value> + '_ {
     iter::successors(fun, |fun| unsafe { LLVMGetNextFunction(fun) })
 }
 
+pub fn global_iter(module: &Module) -> impl Iterator<Item = &Value> + '_ {
+    let global = unsafe { LLVMGetFirstGlobal(module) };
+    iter::successors(global, |global| unsafe { LLVMGetNextGlobal(global) })
+}
+
 #[repr(C)]
 #[derive(Clone, Copy, Debug, PartialEq, Eq)]
 pub enum VerifierFailureAction {
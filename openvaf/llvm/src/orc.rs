@@ -0,0 +1,73 @@
+//! Bindings to the ORC v2 JIT (LLJIT)
+
+use libc::{c_char, c_void};
+
+use crate::{Context, Module, TargetMachine};
+
+pub enum LLJIT {}
+pub enum LLJITBuilder {}
+pub enum JITTargetMachineBuilder {}
+pub enum JITDylib {}
+pub enum ThreadSafeContext {}
+pub enum ThreadSafeModule {}
+pub enum DefinitionGenerator {}
+pub enum Error {}
+
+pub type SymbolPredicate = extern "C" fn(ctx: *mut c_void, sym: *mut c_void) -> i32;
+
+extern "C" {
+    pub fn LLVMOrcCreateLLJITBuilder() -> &'static mut LLJITBuilder;
+    pub fn LLVMOrcLLJITBuilderSetJITTargetMachineBuilder(
+        builder: &LLJITBuilder,
+        jtmb: &'static mut JITTargetMachineBuilder,
+    );
+    /// Takes ownership of the target machine.
+    pub fn LLVMOrcJITTargetMachineBuilderCreateFromTargetMachine(
+        tm: &'static mut TargetMachine,
+    ) -> &'static mut JITTargetMachineBuilder;
+
+    /// Takes ownership of the builder.
+    pub fn LLVMOrcCreateLLJIT(
+        result: &mut Option<&'static mut LLJIT>,
+        builder: &'static mut LLJITBuilder,
+    ) -> Option<&'static mut Error>;
+    pub fn LLVMOrcDisposeLLJIT(jit: &'static mut LLJIT) -> Option<&'static mut Error>;
+    pub fn LLVMOrcLLJITGetMainJITDylib(jit: &LLJIT) -> &JITDylib;
+    pub fn LLVMOrcLLJITGetGlobalPrefix(jit: &LLJIT) -> c_char;
+    /// Takes ownership of the module.
+    pub fn LLVMOrcLLJITAddLLVMIRModule(
+        jit: &LLJIT,
+        dylib: &JITDylib,
+        module: &'static mut ThreadSafeModule,
+    ) -> Option<&'static mut Error>;
+    pub fn LLVMOrcLLJITLookup(
+        jit: &LLJIT,
+        result: &mut u64,
+        name: *const c_char,
+    ) -> Option<&'static mut Error>;
+
+    pub fn LLVMOrcCreateDynamicLibrarySearchGeneratorForProcess(
+        result: &mut Option<&'static mut DefinitionGenerator>,
+        global_prefix: c_char,
+        filter: Option<SymbolPredicate>,
+        filter_ctx: *mut c_void,
+    ) -> Option<&'static mut Error>;
+    /// Takes ownership of the generator.
+    pub fn LLVMOrcJITDylibAddGenerator(
+        dylib: &JITDylib,
+        generator: &'static mut DefinitionGenerator,
+    );
+
+    pub fn LLVMOrcCreateNewThreadSafeContext() -> &'static mut ThreadSafeContext;
+    pub fn LLVMOrcThreadSafeContextGetContext(ctx: &ThreadSafeContext) -> &Context;
+    pub fn LLVMOrcDisposeThreadSafeContext(ctx: &'static mut ThreadSafeContext);
+    /// Takes ownership of the module, the context is retained until the module is destroyed.
+    pub fn LLVMOrcCreateNewThreadSafeModule(
+        module: &Module,
+        ctx: &ThreadSafeContext,
+    ) -> &'static mut ThreadSafeModule;
+
+    /// Consumes the error. The returned message must be freed with `LLVMDisposeErrorMessage`.
+    pub fn LLVMGetErrorMessage(err: &'static mut Error) -> *mut c_char;
+    pub fn LLVMDisposeErrorMessage(msg: *mut c_char);
+}
@@ -32,7 +32,7 @@ impl LLVMString {
     // }
 
     // /// This method will allocate a c string through LLVM
-    pub(crate) fn create_from_str(string: &str) -> LLVMString {
+    pub fn create_from_str(string: &str) -> LLVMString {
         let msg = CString::new(string).unwrap();
         unsafe { LLVMString::new(LLVMCreateMessage(msg.as_ptr() as *const _)) }
     }
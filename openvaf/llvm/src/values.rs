@@ -1,4 +1,4 @@
-use libc::{c_char, c_double, c_uint, c_ulonglong};
+use libc::{c_char, c_double, c_uint, c_ulonglong, size_t};
 
 use crate::{
     BasicBlock, Bool, CallConv, Context, DLLStorageClass, Linkage, Module, Type, UnnamedAddr,
@@ -11,8 +11,8 @@ extern "C" {
     // Get the enumerated kind of a Value instance.
     pub fn LLVMTypeOf(val: &Value) -> &Type;
 
-    // pub fn LLVMGetValueName2(val: &'a Value, Length: *mut ::libc::size_t) -> *const ::libc::c_char;
-    // pub fn LLVMSetValueName2(val: &'a Value, Name: *const ::libc::c_char, NameLen: ::libc::size_t);
+    pub fn LLVMGetValueName2(val: &Value, Length: *mut size_t) -> *const c_char;
+    pub fn LLVMSetValueName2(val: &Value, Name: *const c_char, NameLen: size_t);
 
     // pub fn LLVMDumpValue(Val: &'a Value);
     pub fn LLVMPrintValueToString(val: &Value) -> *mut c_char;
@@ -237,7 +237,7 @@ extern "C" {
     // Core->Values->Constants->Global Values
     // pub fn LLVMGetGlobalParent(global: &'a Value) -> ModuleRef;
     pub fn LLVMIsDeclaration(global: &Value) -> Bool;
-    // pub fn LLVMGetLinkage(global: &Value) -> Linkage;
+    pub fn LLVMGetLinkage(global: &Value) -> Linkage;
     pub fn LLVMSetLinkage(global: &Value, Linkage: Linkage);
     // pub fn LLVMGetSection(global: &'a Value) -> *const ::libc::c_char;
     // pub fn LLVMSetSection(global: &'a Value, Section: *const ::libc::c_char);
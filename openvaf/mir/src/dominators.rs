@@ -103,6 +103,11 @@ impl DominatorTree {
         block == dominator
     }
 
+    /// The immediate dominator of `block`. `None` for the entry block and unreachable blocks.
+    pub fn idom(&self, block: Block) -> Option<Block> {
+        self.nodes[block].idom.expand()
+    }
+
     pub fn ipdom(&self, block: Block) -> Option<Block> {
         self.reverse_nodes[block].idom.expand()
     }
@@ -269,7 +269,7 @@ pub struct KnownDerivatives {
     // pub standin_calls: AHashMap<FuncRef, u32>,
 }
 
-#[derive(Clone, Copy, PartialEq, Eq, Hash)]
+#[derive(Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
 #[repr(transparent)]
 pub struct Unknown(pub u32);
 impl_idx_from!(Unknown(u32));
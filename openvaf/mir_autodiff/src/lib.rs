@@ -6,7 +6,7 @@ mod subgraph;
 
 use ahash::AHashMap;
 pub use builder::build_derivatives;
-pub use live_derivatives::LiveDerivatives;
+pub use live_derivatives::{reachable_unknowns, LiveDerivatives};
 use mir::{
     DataFlowGraph, DominatorTree, Function, Inst, InstructionData, KnownDerivatives, Opcode, Value,
 };
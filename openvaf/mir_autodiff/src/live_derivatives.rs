@@ -2,7 +2,10 @@ use std::mem::take;
 
 use ahash::AHashMap;
 use bitset::{BitSet, HybridBitSet, SparseBitMatrix};
-use mir::{DominatorTree, Function, Inst, InstructionData, Opcode, Value, ValueDef};
+use mir::{
+    DominatorTree, Function, Inst, InstructionData, KnownDerivatives, Opcode, Unknown, Value,
+    ValueDef,
+};
 use workqueue::WorkQueue;
 
 use crate::intern::{Derivative, DerivativeIntern};
@@ -244,6 +247,29 @@ impl<'a, 'b> LiveDerivativeBuilder<'a, 'b> {
     }
 }
 
+/// Determines which unknowns each instruction of `func` depends on with a (possibly) non-zero
+/// derivative. If an unknown is not reachable at an instruction the derivatives of its results
+/// by that unknown are zero. This is the first order part of the analysis that is used to build
+/// the [`LiveDerivatives`].
+pub fn reachable_unknowns(
+    func: &Function,
+    derivatives: &KnownDerivatives,
+) -> SparseBitMatrix<Inst, Unknown> {
+    let intern = DerivativeIntern::new(derivatives);
+    let mut res = SparseBitMatrix::new(func.dfg.num_insts(), intern.num_unknowns());
+    let parts = (BitSet::new_empty(func.dfg.num_insts()), Vec::new());
+    let mut post_order = Postorder::from_parts(&func.dfg, parts, &intern);
+    for (unknown, param) in intern.unknowns.iter_enumerated() {
+        post_order.populate(*param);
+        post_order.traverse_successor();
+        for inst in &mut post_order {
+            res.insert(inst, unknown);
+        }
+        post_order.clear();
+    }
+    res
+}
+
 #[derive(Debug, Clone)]
 pub struct LiveDerivatives {
     pub mat: SparseBitMatrix<Inst, Derivative>,
@@ -6,7 +6,7 @@ use mir::{ControlFlowGraph, DominatorTree, Function, Inst, KnownDerivatives, Val
 use mir_reader::parse_function;
 
 use crate::intern::Derivative;
-use crate::{DerivativeIntern, LiveDerivatives};
+use crate::{reachable_unknowns, DerivativeIntern, LiveDerivatives};
 
 struct DerivativeFmt<'a> {
     func: &'a Function,
@@ -40,9 +40,7 @@ impl Debug for DerivativeFmt<'_> {
     }
 }
 
-fn check(src: &str, data_flow_result: Expect) {
-    let (func, _) = parse_function(src).unwrap();
-
+fn known_derivatives() -> KnownDerivatives {
     let unknowns = [10u32.into(), 11u32.into()].into_iter().collect();
 
     let mut call1 = HybridBitSet::new_empty();
@@ -61,7 +59,12 @@ fn check(src: &str, data_flow_result: Expect) {
     .into_iter()
     .collect();
 
-    let derivative_info = KnownDerivatives { unknowns, ddx_calls };
+    KnownDerivatives { unknowns, ddx_calls }
+}
+
+fn check(src: &str, data_flow_result: Expect) {
+    let (func, _) = parse_function(src).unwrap();
+    let derivative_info = known_derivatives();
     let mut unknowns = DerivativeIntern::new(&derivative_info);
 
     let mut cfg = ControlFlowGraph::new();
@@ -216,3 +219,48 @@ fn back_edge() {
 
     check(src, data_flow_result)
 }
+
+#[test]
+fn reachable() {
+    let src = r##"
+        function %bar(v10, v11, v12) {
+            fn0 = const fn %ddx_v10(1) -> 1
+            fn3 = const fn %store(1) -> 1
+
+        block0:
+            v20 = fmul v10, v12
+            v21 = floor v20
+            v22 = fadd v21, v11
+            v23 = flt v20, v12
+            br v23, block1, block2
+
+        block1:
+            v24 = call fn3 (v20)
+            v25 = fadd v24, v12
+            jmp block2
+
+        block2:
+            v26 = phi [v22, block0], [v25, block1]
+            v27 = exp v26
+        }
+    "##;
+
+    let (func, _) = parse_function(src).unwrap();
+    let res = reachable_unknowns(&func, &known_derivatives());
+    let actual: Vec<_> = res
+        .rows()
+        .flat_map(|inst| {
+            let display = func.dfg.display_inst(inst).to_string();
+            res.iter(inst).map(move |unknown| format!("{display}: {unknown:?}"))
+        })
+        .collect();
+    expect![[r#"
+        [
+            "v20 = fmul v10, v12: unknown0",
+            "v22 = fadd v21, v11: unknown1",
+            "v26 = phi [v22, block0], [v25, block1]: unknown1",
+            "v27 = exp v26: unknown1",
+        ]
+    "#]]
+    .assert_debug_eq(&actual);
+}
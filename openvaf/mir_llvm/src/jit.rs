@@ -0,0 +1,128 @@
+use std::ffi::{CStr, CString};
+use std::os::raw::c_char;
+use std::ptr;
+
+use libc::c_void;
+use llvm::support::LLVMString;
+use llvm::OptLevel;
+
+use crate::LLVMBackend;
+
+/// A JIT compiler (LLVM ORC) that materializes LLVM modules directly in memory
+/// without writing any files or invoking a linker.
+pub struct Jit {
+    jit: &'static mut llvm::LLJIT,
+}
+
+impl Jit {
+    /// Creates a new JIT for the target of `back` (which must be the host). Symbols that are
+    /// not defined by the compiled modules (like `libm` functions) are resolved from the
+    /// current process.
+    ///
+    /// # Safety
+    ///
+    /// This function calls the LLVM-C Api which may not be entirely safe.
+    /// Exercise caution!
+    pub unsafe fn new(back: &LLVMBackend, opt_lvl: OptLevel) -> Result<Jit, LLVMString> {
+        let tm = llvm::create_target(
+            &back.target.llvm_target,
+            &back.target_cpu,
+            &back.features,
+            opt_lvl,
+            llvm::RelocMode::PIC,
+            llvm::CodeModel::Default,
+        )?;
+        let builder = llvm::LLVMOrcCreateLLJITBuilder();
+        let tm_builder = llvm::LLVMOrcJITTargetMachineBuilderCreateFromTargetMachine(tm);
+        llvm::LLVMOrcLLJITBuilderSetJITTargetMachineBuilder(builder, tm_builder);
+
+        let mut jit = None;
+        if let Some(err) = llvm::LLVMOrcCreateLLJIT(&mut jit, builder) {
+            return Err(error_message(err));
+        }
+        let jit = jit.unwrap();
+
+        let mut generator = None;
+        let prefix = llvm::LLVMOrcLLJITGetGlobalPrefix(jit);
+        let err = llvm::LLVMOrcCreateDynamicLibrarySearchGeneratorForProcess(
+            &mut generator,
+            prefix,
+            None,
+            ptr::null_mut(),
+        );
+        let jit = Jit { jit };
+        if let Some(err) = err {
+            return Err(error_message(err));
+        }
+        llvm::LLVMOrcJITDylibAddGenerator(jit.main_dylib(), generator.unwrap());
+
+        Ok(jit)
+    }
+
+    /// Parses `bitcode` (created with [`ModuleLlvm::to_bitcode`](crate::ModuleLlvm::to_bitcode))
+    /// and adds it to the JIT. Code is only generated once a symbol is looked up.
+    pub fn add_bitcode(&self, bitcode: &[u8]) -> Result<(), LLVMString> {
+        let name = CString::new("jit_bitcode").unwrap();
+        unsafe {
+            let ctx = llvm::LLVMOrcCreateNewThreadSafeContext();
+            let llcx = llvm::LLVMOrcThreadSafeContextGetContext(ctx);
+            let buff = llvm::LLVMCreateMemoryBufferWithMemoryRange(
+                bitcode.as_ptr() as *const c_char,
+                bitcode.len(),
+                name.as_ptr(),
+                llvm::False,
+            );
+            let mut module = None;
+            if llvm::LLVMParseBitcodeInContext2(llcx, buff, &mut module) != llvm::False {
+                llvm::LLVMOrcDisposeThreadSafeContext(ctx);
+                return Err(LLVMString::create_from_str("failed to parse bitcode"));
+            }
+
+            let module = llvm::LLVMOrcCreateNewThreadSafeModule(module.unwrap(), ctx);
+            // the module keeps the context alive
+            llvm::LLVMOrcDisposeThreadSafeContext(ctx);
+
+            if let Some(err) =
+                llvm::LLVMOrcLLJITAddLLVMIRModule(self.jit, self.main_dylib(), module)
+            {
+                return Err(error_message(err));
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Returns the address of the symbol `name`. The code for the symbol (and
+    /// everything it references) is generated on the first lookup.
+    pub fn lookup(&self, name: &str) -> Result<*mut c_void, LLVMString> {
+        let name = CString::new(name).unwrap();
+        let mut addr = 0;
+        unsafe {
+            if let Some(err) = llvm::LLVMOrcLLJITLookup(self.jit, &mut addr, name.as_ptr()) {
+                return Err(error_message(err));
+            }
+        }
+        Ok(addr as *mut c_void)
+    }
+
+    fn main_dylib(&self) -> &llvm::JITDylib {
+        unsafe { llvm::LLVMOrcLLJITGetMainJITDylib(self.jit) }
+    }
+}
+
+impl Drop for Jit {
+    fn drop(&mut self) {
+        unsafe {
+            if let Some(err) = llvm::LLVMOrcDisposeLLJIT(&mut *(self.jit as *mut _)) {
+                log::error!("{}", error_message(err));
+            }
+        }
+    }
+}
+
+unsafe fn error_message(err: &'static mut llvm::Error) -> LLVMString {
+    let msg = llvm::LLVMGetErrorMessage(err);
+    let res = LLVMString::create_from_str(&CStr::from_ptr(msg).to_string_lossy());
+    llvm::LLVMDisposeErrorMessage(msg);
+    res
+}
@@ -18,6 +18,7 @@ mod builder;
 mod context;
 mod declarations;
 mod intrinsics;
+mod jit;
 mod types;
 
 mod callbacks;
@@ -27,6 +28,7 @@ mod tests;
 pub use builder::{Builder, BuilderVal, MemLoc};
 pub use callbacks::CallbackFun;
 pub use context::CodegenCx;
+pub use jit::Jit;
 
 pub struct LLVMBackend<'t> {
     target: &'t Target,
@@ -212,6 +214,40 @@ impl ModuleLlvm {
         }
     }
 
+    /// Serializes this module to LLVM bitcode
+    pub fn to_bitcode(&self) -> Vec<u8> {
+        unsafe {
+            let buf = llvm::LLVMWriteBitcodeToMemoryBuffer(self.llmod());
+            let start = llvm::LLVMGetBufferStart(buf) as *const u8;
+            let len = llvm::LLVMGetBufferSize(buf);
+            let res = std::slice::from_raw_parts(start, len).to_vec();
+            llvm::LLVMDisposeMemoryBuffer(buf);
+            res
+        }
+    }
+
+    /// Parses `bitcode` (created with [`ModuleLlvm::to_bitcode`]) and links it into this module
+    pub fn link_bitcode(&self, bitcode: &[u8]) {
+        let name = CString::new("bitcode_buffer").unwrap();
+        unsafe {
+            let buff = llvm::LLVMCreateMemoryBufferWithMemoryRange(
+                bitcode.as_ptr() as *const c_char,
+                bitcode.len(),
+                name.as_ptr(),
+                llvm::False,
+            );
+            let mut module = None;
+            assert!(
+                llvm::LLVMParseBitcodeInContext2(self.llcx, buff, &mut module) == llvm::False,
+                "failed to parse bitcode"
+            );
+            assert!(
+                llvm::LLVMLinkModules2(self.llmod(), module.unwrap()) == llvm::False,
+                "failed to link parsed bitcode"
+            );
+        }
+    }
+
     /// Verifies this module and prints out  any errors
     ///
     /// # Returns
@@ -244,6 +280,46 @@ impl ModuleLlvm {
     }
 
     pub fn emit_object(&self, dst: &Path) -> Result<(), LLVMString> {
+        self.emit_file(dst, llvm::CodeGenFileType::ObjectFile)
+    }
+
+    pub fn emit_asm(&self, dst: &Path) -> Result<(), LLVMString> {
+        self.emit_file(dst, llvm::CodeGenFileType::AssemblyFile)
+    }
+
+    /// Writes the textual LLVM IR of this module to `dst`
+    pub fn emit_llvm_ir(&self, dst: &Path) -> Result<(), LLVMString> {
+        let path = CString::new(dst.to_str().unwrap()).unwrap();
+
+        let mut err_string = MaybeUninit::uninit();
+        let return_code = unsafe {
+            llvm::LLVMPrintModuleToFile(self.llmod(), path.as_ptr(), err_string.as_mut_ptr())
+        };
+
+        if return_code == llvm::True {
+            unsafe {
+                return Err(LLVMString::new(err_string.assume_init()));
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Writes the LLVM bitcode of this module to `dst`
+    pub fn emit_llvm_bc(&self, dst: &Path) -> Result<(), LLVMString> {
+        let path = CString::new(dst.to_str().unwrap()).unwrap();
+        let return_code = unsafe { llvm::LLVMWriteBitcodeToFile(self.llmod(), path.as_ptr()) };
+        if return_code != 0 {
+            return Err(LLVMString::create_from_str(&format!(
+                "failed to write bitcode to {}",
+                dst.display()
+            )));
+        }
+
+        Ok(())
+    }
+
+    fn emit_file(&self, dst: &Path, file_ty: llvm::CodeGenFileType) -> Result<(), LLVMString> {
         let path = CString::new(dst.to_str().unwrap()).unwrap();
 
         let mut err_string = MaybeUninit::uninit();
@@ -254,7 +330,7 @@ impl ModuleLlvm {
                 self.tm,
                 self.llmod(),
                 path.as_ptr(),
-                llvm::CodeGenFileType::ObjectFile,
+                file_ty,
                 err_string.as_mut_ptr(),
             )
         };
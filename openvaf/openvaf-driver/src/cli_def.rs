@@ -32,6 +32,10 @@ pub fn main_command() -> Command {
             supported_targets(),
             target_cpu(),
             codegen_opts(),
+            correlated_noise(),
+            emit(),
+            output_type(),
+            symbol_prefix(),
             interface(),
             expand(),
             dump_json(),
@@ -60,6 +64,10 @@ pub const DUMP_JSON: &str = "dump-json";
 pub const ALLOW: &str = "allow";
 pub const WARN: &str = "warn";
 pub const DENY: &str = "deny";
+pub const CORRELATED_NOISE: &str = "correlated-noise";
+pub const EMIT: &str = "emit";
+pub const OUTPUT_TYPE: &str = "output-type";
+pub const SYMBOL_PREFIX: &str = "symbol-prefix";
 
 fn interface() -> Arg {
     Arg::new(INTERFACE)
@@ -257,6 +265,57 @@ fn opt_lvl() -> Arg {
         .default_value("3").required(false)
 }
 
+fn correlated_noise() -> Arg {
+    Arg::new(CORRELATED_NOISE)
+        .long(CORRELATED_NOISE)
+        .help("Set how noise sources used in multiple contributions are represented.")
+        .long_help("Set how noise sources used in multiple contributions (correlated noise) are represented.\n\npossible values\n\nnodes - create an additional internal node for each correlated noise source (default)\nmatrix - export a (complex) correlation matrix for each correlated noise source (requires simulator support)")
+        .value_name("MODE")
+        .value_hint(ValueHint::Other)
+        .value_parser(["matrix", "nodes"])
+        .hide_possible_values(true)
+        .default_value("nodes")
+        .required(false)
+}
+
+fn emit() -> Arg {
+    Arg::new(EMIT)
+        .long(EMIT)
+        .help("Set the kinds of output generated by the compiler.")
+        .long_help("Set the kinds of output generated by the compiler (comma separated).\nEach group of functions (access, setup_model, setup_instance, eval) is written to a separate file next to the output.\n\npossible values\n\nllvm-ir - textual LLVM IR (.ll)\nllvm-bc - LLVM bitcode (.bc)\nasm - assembly (.s)\nobj - object files (.o)\nlib - OSDI shared library\nc-header - C header for each module with the indices of parameters, opvars, nodes and jacobian entries and the offsets into the instance/model data (<module>.h)")
+        .value_name("KIND")
+        .value_hint(ValueHint::Other)
+        .value_parser(["llvm-ir", "llvm-bc", "asm", "obj", "lib", "c-header"])
+        .value_delimiter(',')
+        .action(ArgAction::Append)
+        .hide_possible_values(true)
+        .default_value("lib")
+        .required(false)
+}
+
+fn output_type() -> Arg {
+    Arg::new(OUTPUT_TYPE)
+        .long(OUTPUT_TYPE)
+        .help("Set the kind of library that is produced.")
+        .long_help("Set the kind of library that is produced.\n\npossible values\n\nshared - shared library loaded by the simulator at runtime (.osdi)\nstatic - static library for linking the models into a simulator (.a/.lib)\nobject - single relocatable object for linking the models into a simulator (.o/.obj)")
+        .value_name("TYPE")
+        .value_hint(ValueHint::Other)
+        .value_parser(["shared", "static", "object"])
+        .hide_possible_values(true)
+        .default_value("shared")
+        .required(false)
+}
+
+fn symbol_prefix() -> Arg {
+    Arg::new(SYMBOL_PREFIX)
+        .long(SYMBOL_PREFIX)
+        .help("Prefix the exported symbols of static libraries and objects.")
+        .long_help("Prefix the symbols exported by static libraries and objects (OSDI_DESCRIPTORS, osdi_log, ...).\nAvoids collisions when multiple models are linked into the same binary.")
+        .value_name("PREFIX")
+        .value_hint(ValueHint::Other)
+        .required(false)
+}
+
 fn expand() -> Arg {
     flag(PRINT_EXPANSION, "print-expansion")
         .help("Abort after preprocessing and print expanded sourcecode.")
@@ -4,12 +4,16 @@ use std::process::exit;
 use anyhow::{bail, Context, Result};
 use camino::Utf8PathBuf;
 use clap::ArgMatches;
-use openvaf::{builtin_lints, get_target_names, host_triple, AbsPathBuf, LintLevel, OptLevel};
+use openvaf::{
+    builtin_lints, get_target_names, host_triple, AbsPathBuf, CorrelatedNoise, Emit, LintLevel,
+    OptLevel, OutputType,
+};
 use termcolor::{Color, ColorChoice, ColorSpec, WriteColor};
 
 use crate::cli_def::{
-    ALLOW, BATCHMODE, CACHE_DIR, CODEGEN, DEFINE, DENY, DRYRUN, INCLUDE, INPUT, LINTS, OPT_LVL,
-    OUTPUT, SUPPORTED_TARGETS, TARGET, TARGET_CPU, WARN,
+    ALLOW, BATCHMODE, CACHE_DIR, CODEGEN, CORRELATED_NOISE, DEFINE, DENY, DRYRUN, EMIT, INCLUDE,
+    INPUT, LINTS, OPT_LVL, OUTPUT, OUTPUT_TYPE, SUPPORTED_TARGETS, SYMBOL_PREFIX, TARGET,
+    TARGET_CPU, WARN,
 };
 use crate::{CompilationDestination, Opts};
 
@@ -38,6 +42,24 @@ pub fn matches_to_opts(matches: ArgMatches) -> Result<Opts> {
         lints.extend(deny.map(|lint| (lint.to_owned(), LintLevel::Deny)));
     }
 
+    let host = host_triple();
+    let target = matches.get_one::<String>(TARGET).cloned().unwrap_or_else(|| host.to_owned());
+    let default_cpu = if host != target { "generic" } else { "native" };
+
+    let target = if let Some(target) = openvaf::Target::search(&target) {
+        target
+    } else {
+        // should never happened but helpful to provide support just in case
+        bail!("The target {target} is not supported by  this binary")
+    };
+
+    let output_type = match &**matches.get_one::<String>(OUTPUT_TYPE).unwrap() {
+        "shared" => OutputType::SharedLib,
+        "static" => OutputType::StaticLib,
+        "object" => OutputType::Object,
+        ty => bail!("unknown output type {ty}"),
+    };
+
     let output = if matches.get_flag(BATCHMODE) {
         let cache_dir = if let Some(val) = matches.get_one::<Utf8PathBuf>(CACHE_DIR) {
             val.clone()
@@ -61,7 +83,7 @@ pub fn matches_to_opts(matches: ArgMatches) -> Result<Opts> {
         let lib_file = if let Some(output) = matches.get_one::<Utf8PathBuf>(OUTPUT) {
             output.clone()
         } else {
-            input.with_extension("osdi")
+            input.with_extension(output_type.extension(&target))
         };
 
         CompilationDestination::Path { lib_file }
@@ -90,17 +112,26 @@ pub fn matches_to_opts(matches: ArgMatches) -> Result<Opts> {
         lvl => bail!("unknown opt lvl {lvl}"),
     };
 
-    let host = host_triple();
-    let target = matches.get_one::<String>(TARGET).cloned().unwrap_or_else(|| host.to_owned());
-    let default_cpu = if host != target { "generic" } else { "native" };
-
-    let target = if let Some(target) = openvaf::Target::search(&target) {
-        target
-    } else {
-        // should never happened but helpful to provide support just in case
-        bail!("The target {target} is not supported by  this binary")
+    let correlated_noise = match &**matches.get_one::<String>(CORRELATED_NOISE).unwrap() {
+        "matrix" => CorrelatedNoise::Matrix,
+        "nodes" => CorrelatedNoise::InternalNode,
+        mode => bail!("unknown correlated noise mode {mode}"),
     };
 
+    let mut emit = Emit::default();
+    let mut emit_lib = false;
+    for kind in matches.get_many::<String>(EMIT).unwrap() {
+        match &**kind {
+            "llvm-ir" => emit.llvm_ir = true,
+            "llvm-bc" => emit.llvm_bc = true,
+            "asm" => emit.asm = true,
+            "obj" => emit.obj = true,
+            "lib" => emit_lib = true,
+            "c-header" => emit.c_header = true,
+            kind => bail!("unknown emit kind {kind}"),
+        }
+    }
+
     let target_cpu: String =
         matches.get_one(TARGET_CPU).cloned().unwrap_or_else(|| default_cpu.to_owned());
 
@@ -114,6 +145,11 @@ pub fn matches_to_opts(matches: ArgMatches) -> Result<Opts> {
         opt_lvl,
         target,
         target_cpu,
+        correlated_noise,
+        emit,
+        emit_lib,
+        output_type,
+        symbol_prefix: matches.get_one::<String>(SYMBOL_PREFIX).cloned().unwrap_or_default(),
         dry_run: matches.get_flag(DRYRUN),
     })
 }
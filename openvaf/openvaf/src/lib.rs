@@ -1,14 +1,14 @@
-use std::fs::{create_dir_all, remove_file};
+use std::fs::{copy, create_dir_all, remove_file};
 use std::io::Write;
 use std::time::Instant;
 
 use anyhow::Context;
-use anyhow::Result;
+use anyhow::{anyhow, Result};
 use basedb::diagnostics::{ConsoleSink, DiagnosticSink};
 use basedb::BaseDB;
 use camino::Utf8PathBuf;
 use hir::CompilationDB;
-use linker::link;
+use linker::{link, write_archive};
 use mir_llvm::LLVMBackend;
 use sim_back::collect_modules;
 use termcolor::{Color, ColorChoice, ColorSpec, StandardStream, WriteColor};
@@ -16,6 +16,8 @@ use termcolor::{Color, ColorChoice, ColorSpec, StandardStream, WriteColor};
 pub use basedb::lints::builtin as builtin_lints;
 pub use basedb::lints::LintLevel;
 pub use llvm::OptLevel;
+pub use mir_llvm::Jit;
+pub use osdi::{CorrelatedNoise, Emit, ObjectLayout};
 pub use paths::AbsPathBuf;
 pub use target::host_triple;
 pub use target::spec::{get_target_names, Target};
@@ -28,11 +30,41 @@ pub enum CompilationDestination {
     Cache { cache_dir: Utf8PathBuf },
 }
 
+/// The kind of library produced by [`compile`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub enum OutputType {
+    /// A shared library that is loaded by the simulator at runtime.
+    #[default]
+    SharedLib,
+    /// A static library (`.a`/`.lib`) for linking the models into a simulator.
+    StaticLib,
+    /// A single relocatable object for linking the models into a simulator.
+    Object,
+}
+
+impl OutputType {
+    /// The file extension of this output type on `target`.
+    pub fn extension(self, target: &Target) -> &'static str {
+        match self {
+            OutputType::SharedLib => "osdi",
+            OutputType::StaticLib if target.options.is_like_windows => "lib",
+            OutputType::StaticLib => "a",
+            OutputType::Object if target.options.is_like_windows => "obj",
+            OutputType::Object => "o",
+        }
+    }
+}
+
 pub enum CompilationTermination {
     Compiled { lib_file: Utf8PathBuf },
     FatalDiagnostic,
 }
 
+pub enum JitTermination {
+    Compiled { jit: Jit },
+    FatalDiagnostic,
+}
+
 #[derive(Debug, Clone)]
 pub struct Opts {
     pub dry_run: bool,
@@ -45,6 +77,14 @@ pub struct Opts {
     pub opt_lvl: OptLevel,
     pub target: Target,
     pub target_cpu: String,
+    pub correlated_noise: CorrelatedNoise,
+    /// additional artifacts (LLVM IR, assembly, ...) written next to the output
+    pub emit: Emit,
+    /// whether the library (see `output_type`) is produced
+    pub emit_lib: bool,
+    pub output_type: OutputType,
+    /// prefix for the symbols exported by static libraries and objects
+    pub symbol_prefix: String,
 }
 // pub fn dump_json(opts: &Opts) -> Result<CompilationTermination> {
 //     let input =
@@ -169,16 +209,54 @@ pub fn compile(opts: &Opts) -> Result<CompilationTermination> {
     if opts.dry_run {
         return Ok(CompilationTermination::Compiled { lib_file });
     }
-    let paths = osdi::compile(&db, &modules, &lib_file, &opts.target, &back, true, opts.opt_lvl);
-    // TODO configure linker
-    link(None, &opts.target, lib_file.as_ref(), |linker| {
-        for path in &paths {
-            linker.add_object(path);
+    // object files are always required for linking
+    let emit = Emit { obj: opts.emit.obj || opts.emit_lib, ..opts.emit };
+    let layout = match opts.output_type {
+        OutputType::SharedLib => ObjectLayout::PerGroup,
+        OutputType::StaticLib | OutputType::Object => {
+            ObjectLayout::Combined { symbol_prefix: &opts.symbol_prefix }
         }
-    })?;
+    };
+    let objects = osdi::compile(
+        &db,
+        &modules,
+        &lib_file,
+        &opts.target,
+        &back,
+        emit,
+        layout,
+        opts.opt_lvl,
+        opts.correlated_noise,
+    );
+    if opts.emit_lib {
+        match opts.output_type {
+            OutputType::SharedLib => {
+                // TODO configure linker
+                link(None, &opts.target, lib_file.as_ref(), |linker| {
+                    for path in &objects.paths {
+                        linker.add_object(path);
+                    }
+                })?;
+            }
+            OutputType::StaticLib => {
+                let members: Vec<_> =
+                    objects.paths.iter().map(|path| (&**path, &*objects.exports)).collect();
+                write_archive(&opts.target, &lib_file, &members)?;
+            }
+            OutputType::Object if objects.paths[0] != lib_file => {
+                copy(&objects.paths[0], &lib_file)
+                    .with_context(|| format!("failed to write {lib_file}"))?;
+            }
+            OutputType::Object => (),
+        }
+    }
 
-    for obj_file in paths {
-        remove_file(obj_file).context("failed to delete intermediate compile artifact")?;
+    if !opts.emit.obj {
+        for obj_file in objects.paths {
+            if obj_file != lib_file {
+                remove_file(obj_file).context("failed to delete intermediate compile artifact")?;
+            }
+        }
     }
 
     let seconds = Instant::elapsed(&start).as_secs_f64();
@@ -190,3 +268,46 @@ pub fn compile(opts: &Opts) -> Result<CompilationTermination> {
 
     Ok(CompilationTermination::Compiled { lib_file })
 }
+
+/// Compiles the models in memory instead of producing a library. The OSDI symbols
+/// (`OSDI_DESCRIPTORS`, `osdi_log`, ...) can be looked up with [`Jit::lookup`].
+/// The target must be the host. `output`, `emit`, `emit_lib`, `output_type` and
+/// `symbol_prefix` are ignored.
+pub fn compile_jit(opts: &Opts) -> Result<JitTermination> {
+    let start = Instant::now();
+
+    let input =
+        opts.input.canonicalize().with_context(|| format!("failed to resolve {}", opts.input))?;
+    let input = AbsPathBuf::assert(input);
+    let db = CompilationDB::new_fs(input, &opts.include, &opts.defines, &opts.lints)?;
+
+    let modules = if let Some(modules) = collect_modules(&db, false, &mut ConsoleSink::new(&db)) {
+        modules
+    } else {
+        return Ok(JitTermination::FatalDiagnostic);
+    };
+
+    let back = LLVMBackend::new(&opts.codegen_opts, &opts.target, opts.target_cpu.clone(), &[]);
+    let jit = unsafe { Jit::new(&back, opts.opt_lvl) }
+        .map_err(|err| anyhow!("failed to create JIT: {err}"))?;
+    let bitcode = osdi::compile_bitcode(
+        &db,
+        &modules,
+        &opts.target,
+        &back,
+        opts.opt_lvl,
+        opts.correlated_noise,
+    );
+    for module in bitcode {
+        jit.add_bitcode(&module).map_err(|err| anyhow!("failed to load module into JIT: {err}"))?;
+    }
+
+    let seconds = Instant::elapsed(&start).as_secs_f64();
+    let mut stderr = StandardStream::stderr(ColorChoice::Auto);
+    stderr.set_color(ColorSpec::new().set_fg(Some(Color::Green)).set_bold(true))?;
+    write!(&mut stderr, "Finished")?;
+    stderr.set_color(&ColorSpec::new())?;
+    writeln!(&mut stderr, " building {} in {:.2}s", opts.input.file_name().unwrap(), seconds)?;
+
+    Ok(JitTermination::Compiled { jit })
+}
@@ -1,12 +1,14 @@
+use std::env;
 use std::f64::consts;
+use std::fs::{create_dir_all, metadata, read_dir, remove_dir_all};
 use std::path::Path;
 
-use camino::Utf8Path;
+use camino::{Utf8Path, Utf8PathBuf};
 use expect_test::expect_file;
 use float_cmp::assert_approx_eq;
 use llvm::OptLevel;
 use mini_harness::{harness, Result};
-use openvaf::{CompilationDestination, CompilationTermination};
+use openvaf::{CompilationDestination, CompilationTermination, CorrelatedNoise, Emit, OutputType};
 use stdx::{ignore_dev_tests, openvaf_test_data, project_root};
 use target::spec::Target;
 
@@ -27,6 +29,11 @@ fn compile_and_load(root_file: &Utf8Path) -> &'static OsdiDescriptor {
         opt_lvl: OptLevel::Aggressive,
         target: Target::host_target().unwrap(),
         target_cpu: "native".to_owned(),
+        correlated_noise: CorrelatedNoise::default(),
+        emit: Emit::default(),
+        emit_lib: true,
+        output_type: OutputType::SharedLib,
+        symbol_prefix: String::new(),
         dry_run: false,
     };
 
@@ -212,8 +219,111 @@ fn test_noise() -> Result<()> {
     Ok(())
 }
 
+fn test_emit() -> Result<()> {
+    if stdx::IS_CI && cfg!(windows) {
+        return Ok(());
+    }
+
+    let input = openvaf_test_data("osdi").join("diode_lim.va");
+    let out_dir = Utf8PathBuf::from_path_buf(env::temp_dir().join("openvaf_test_emit")).unwrap();
+    if out_dir.exists() {
+        remove_dir_all(&out_dir)?;
+    }
+    create_dir_all(&out_dir)?;
+    let lib_file = out_dir.join("diode_lim.osdi");
+
+    let openvaf_opts = openvaf::Opts {
+        defines: Vec::new(),
+        codegen_opts: Vec::new(),
+        lints: Vec::new(),
+        input: Utf8PathBuf::from_path_buf(input).unwrap(),
+        output: CompilationDestination::Path { lib_file: lib_file.clone() },
+        include: Vec::new(),
+        opt_lvl: OptLevel::Aggressive,
+        target: Target::host_target().unwrap(),
+        target_cpu: "native".to_owned(),
+        correlated_noise: CorrelatedNoise::default(),
+        emit: Emit { llvm_ir: true, llvm_bc: true, asm: true, ..Emit::default() },
+        emit_lib: false,
+        output_type: OutputType::SharedLib,
+        symbol_prefix: String::new(),
+        dry_run: false,
+    };
+    let res = openvaf::compile(&openvaf_opts)?;
+    assert!(matches!(res, CompilationTermination::Compiled { .. }));
+    assert!(!lib_file.exists(), "library was linked without --emit=lib");
+
+    // one file for each group of functions (access, setup_model, setup_instance, eval)
+    // and one for the descriptors
+    let mut outputs: Vec<_> = read_dir(&out_dir)?
+        .map(|entry| entry.map(|entry| entry.path()))
+        .collect::<std::io::Result<_>>()?;
+    outputs.sort();
+    for ext in ["ll", "bc", "s"] {
+        let main_file = out_dir.join("diode_lim").with_extension(ext).into_std_path_buf();
+        let files: Vec<_> =
+            outputs.iter().filter(|path| path.extension().map_or(false, |it| it == ext)).collect();
+        assert_eq!(files.len(), 5, "unexpected .{ext} files: {files:?}");
+        assert!(files.contains(&&main_file), "{main_file:?} is missing");
+        for file in files {
+            assert_ne!(metadata(file)?.len(), 0, "{} is empty", file.display());
+        }
+    }
+    assert!(
+        outputs.iter().all(|path| path.extension().map_or(false, |it| it != "o")),
+        "object files were not cleaned up: {outputs:?}"
+    );
+
+    remove_dir_all(&out_dir)?;
+    Ok(())
+}
+
+fn test_cache_extension() -> Result<()> {
+    let input = openvaf_test_data("osdi").join("diode_lim.va");
+    let cache_dir = Utf8PathBuf::from_path_buf(env::temp_dir().join("openvaf_test_cache")).unwrap();
+    let target = Target::host_target().unwrap();
+
+    for output_type in [OutputType::SharedLib, OutputType::StaticLib, OutputType::Object] {
+        let openvaf_opts = openvaf::Opts {
+            defines: Vec::new(),
+            codegen_opts: Vec::new(),
+            lints: Vec::new(),
+            input: Utf8PathBuf::from_path_buf(input.clone()).unwrap(),
+            output: CompilationDestination::Cache { cache_dir: cache_dir.clone() },
+            include: Vec::new(),
+            opt_lvl: OptLevel::Aggressive,
+            target: target.clone(),
+            target_cpu: "native".to_owned(),
+            correlated_noise: CorrelatedNoise::default(),
+            emit: Emit::default(),
+            emit_lib: true,
+            output_type,
+            symbol_prefix: String::new(),
+            dry_run: true,
+        };
+        let lib_file = match openvaf::compile(&openvaf_opts)? {
+            CompilationTermination::Compiled { lib_file } => lib_file,
+            CompilationTermination::FatalDiagnostic => panic!("compilation failed"),
+        };
+        let expected = match output_type {
+            OutputType::SharedLib => "osdi",
+            OutputType::StaticLib if cfg!(windows) => "lib",
+            OutputType::StaticLib => "a",
+            OutputType::Object if cfg!(windows) => "obj",
+            OutputType::Object => "o",
+        };
+        assert_eq!(lib_file.extension(), Some(expected), "{output_type:?}");
+        assert!(lib_file.starts_with(&cache_dir));
+    }
+
+    if cache_dir.exists() {
+        remove_dir_all(&cache_dir)?;
+    }
+    Ok(())
+}
+
 harness! {
     // TODO: run this in CI, somehow this test is flakey tough regarding the linker invocation (and really slow)
     Test::from_dir("integration", &integration_test, &ignore_dev_tests, &project_root().join("integration_tests")),
-    [Test::new("$limit", &test_limit),Test::new("noise", &test_noise)]
+    [Test::new("$limit", &test_limit),Test::new("noise", &test_noise),Test::new("emit", &test_emit),Test::new("cache_extension", &test_cache_extension)]
 }
@@ -0,0 +1,30 @@
+#pragma once
+
+/* OpenVAF extension of OSDI 0.3: correlated noise sources.
+ *
+ * Only exported if a model is compiled with `--correlated-noise=matrix`.
+ * The library then additionally exports
+ *
+ *   const OsdiNoiseCorrelation OSDI_NOISE_CORRELATION[OSDI_NUM_DESCRIPTORS];
+ *
+ * The i-th entry belongs to the i-th entry of OSDI_DESCRIPTORS. Libraries
+ * without this symbol do not contain any correlated noise sources. */
+
+#include "osdi_0_3.h"
+
+typedef struct OsdiCorrelatedNoiseSource {
+  char *name;
+  uint32_t num_nodes;
+  OsdiNodePair *nodes;
+}OsdiCorrelatedNoiseSource;
+
+/* load_noise_correlation writes the complex correlation matrix of each
+ * correlated noise source to noise_dens. For a source with n node pairs a
+ * dense, row major n x n matrix of (re, im) pairs is written. The matrices of
+ * all correlated noise sources are stored consecutively. */
+typedef struct OsdiNoiseCorrelation {
+  uint32_t num_correlated_noise_src;
+  OsdiCorrelatedNoiseSource *correlated_noise_sources;
+  void (*load_noise_correlation)(void *inst, void *model, double freq,
+                                 double *noise_dens);
+}OsdiNoiseCorrelation;
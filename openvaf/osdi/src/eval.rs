@@ -327,6 +327,11 @@ impl<'ll> OsdiCompilationUnit<'_, '_, 'll> {
                         inst_data.store_eval_output(eval_output, instance, builder)
                     }
                 }
+                for source in &inst_data.correlated_noise {
+                    for eval_output in source.eval_outputs() {
+                        inst_data.store_eval_output(eval_output, instance, builder)
+                    }
+                }
             };
             Self::build_store_results(&builder, llfunc, &flags, CALC_NOISE, &store_noise);
 
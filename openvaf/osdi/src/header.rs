@@ -0,0 +1,135 @@
+//! Generation of C headers that describe the data of a compiled module, so that consumers
+//! don't have to walk the `OsdiDescriptor` at runtime to find parameters, nodes, ...
+
+use std::fmt::Write;
+
+use ahash::AHashSet;
+
+use crate::metadata::osdi_0_3::{OsdiDescriptor, OsdiNodePair, PARA_KIND_MASK, PARA_KIND_OPVAR};
+
+#[cfg(test)]
+mod tests;
+
+/// Generates a C header for `descriptor`. All definitions are prefixed with the (upper case)
+/// module name:
+///
+/// * `<MODULE>_PARAM_<name>`/`<MODULE>_OPVAR_<name>`: index into `param_opvar` (the id passed
+///   to `access`), aliases are defined as well
+/// * `<MODULE>_NODE_<name>`: index into `nodes`
+/// * `<MODULE>_JACOBIAN_<node1>_<node2>`: index into `jacobian_entries`
+/// * `<MODULE>_COLLAPSIBLE_<node1>_<node2>`: index into `collapsible`
+/// * sizes of the instance/model data and the offsets of the fields accessed by simulators
+pub(crate) fn c_header(descriptor: &OsdiDescriptor) -> String {
+    let prefix = c_ident(&descriptor.name);
+    let mut header =
+        CHeader { buf: String::new(), prefix: prefix.clone(), defined: AHashSet::default() };
+
+    writeln!(
+        header.buf,
+        "/* Generated by OpenVAF for the Verilog-A module {}, do not edit. */",
+        descriptor.name
+    )
+    .unwrap();
+    writeln!(header.buf, "#ifndef OSDI_{prefix}_H").unwrap();
+    writeln!(header.buf, "#define OSDI_{prefix}_H").unwrap();
+
+    header.section("counts");
+    header.define("NUM_NODES", descriptor.num_nodes);
+    header.define("NUM_TERMINALS", descriptor.num_terminals);
+    header.define("NUM_PARAMS", descriptor.num_params);
+    header.define("NUM_INSTANCE_PARAMS", descriptor.num_instance_params);
+    header.define("NUM_OPVARS", descriptor.num_opvars);
+    header.define("NUM_JACOBIAN_ENTRIES", descriptor.num_jacobian_entries);
+    header.define("NUM_COLLAPSIBLE", descriptor.num_collapsible);
+    header.define("NUM_STATES", descriptor.num_states);
+
+    header.section("instance/model data");
+    header.define("INSTANCE_SIZE", descriptor.instance_size);
+    header.define("MODEL_SIZE", descriptor.model_size);
+    header.define("NODE_MAPPING_OFFSET", descriptor.node_mapping_offset);
+    header.define("JACOBIAN_PTR_RESIST_OFFSET", descriptor.jacobian_ptr_resist_offset);
+    header.define("COLLAPSED_OFFSET", descriptor.collapsed_offset);
+    header.define("STATE_IDX_OFFSET", descriptor.state_idx_off);
+    if descriptor.bound_step_offset != u32::MAX {
+        header.define("BOUND_STEP_OFFSET", descriptor.bound_step_offset);
+    }
+
+    header.section("parameters and operating point variables (index into param_opvar)");
+    for (id, param) in descriptor.param_opvar.iter().enumerate() {
+        let kind = if param.flags & PARA_KIND_MASK == PARA_KIND_OPVAR { "OPVAR" } else { "PARAM" };
+        for name in &param.name {
+            header.define(&format!("{kind}_{}", c_ident(name)), id as u32);
+        }
+    }
+
+    header.section("nodes (index into nodes)");
+    let nodes: Vec<_> = descriptor
+        .nodes
+        .iter()
+        .enumerate()
+        .map(|(i, node)| {
+            let ident = header.define(&format!("NODE_{}", c_ident(&node.name)), i as u32);
+            ident["NODE_".len()..].to_owned()
+        })
+        .collect();
+    // the node pairs use the same (unique) names as the node definitions
+    let node_pair = |pair: &OsdiNodePair| {
+        let node_2 = nodes.get(pair.node_2 as usize).map_or("GND", String::as_str);
+        format!("{}_{node_2}", nodes[pair.node_1 as usize])
+    };
+
+    header.section("jacobian entries (index into jacobian_entries)");
+    for (i, entry) in descriptor.jacobian_entries.iter().enumerate() {
+        header.define(&format!("JACOBIAN_{}", node_pair(&entry.nodes)), i as u32);
+    }
+
+    header.section("collapsible node pairs (index into collapsible)");
+    for (i, pair) in descriptor.collapsible.iter().enumerate() {
+        header.define(&format!("COLLAPSIBLE_{}", node_pair(pair)), i as u32);
+    }
+
+    writeln!(header.buf, "\n#endif").unwrap();
+    header.buf
+}
+
+struct CHeader {
+    buf: String,
+    prefix: String,
+    defined: AHashSet<String>,
+}
+
+impl CHeader {
+    fn section(&mut self, comment: &str) {
+        writeln!(self.buf, "\n/* {comment} */").unwrap();
+    }
+
+    /// Defines `<PREFIX>_<name>` (or `<PREFIX>_<name>_<i>` if that is already defined) and
+    /// returns the defined name without the prefix.
+    fn define(&mut self, name: &str, val: u32) -> String {
+        let mut ident = name.to_owned();
+        // distinct Verilog-A names may map to the same C identifier (a and A for example)
+        let mut i = 1;
+        while self.defined.contains(&ident) {
+            ident = format!("{name}_{i}");
+            i += 1;
+        }
+        writeln!(self.buf, "#define {}_{ident} {val}u", self.prefix).unwrap();
+        self.defined.insert(ident.clone());
+        ident
+    }
+}
+
+/// Turns `name` into an upper case C identifier by replacing all characters that
+/// are not allowed in C identifiers with `_`: `flow(a,b)` becomes `FLOW_A_B`.
+fn c_ident(name: &str) -> String {
+    let ident: String = name
+        .chars()
+        .map(|c| if c.is_ascii_alphanumeric() { c.to_ascii_uppercase() } else { '_' })
+        .collect();
+    let ident = ident.trim_matches('_');
+    if ident.starts_with(|c: char| c.is_ascii_digit()) {
+        format!("_{ident}")
+    } else {
+        ident.to_owned()
+    }
+}
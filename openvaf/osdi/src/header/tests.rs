@@ -0,0 +1,30 @@
+use ahash::AHashSet;
+
+use super::{c_ident, CHeader};
+
+#[test]
+fn c_identifiers() {
+    assert_eq!(c_ident("vbe"), "VBE");
+    assert_eq!(c_ident("$mfactor"), "MFACTOR");
+    assert_eq!(c_ident("flow(a,b)"), "FLOW_A_B");
+    assert_eq!(c_ident("flow(<a>)"), "FLOW_A");
+    assert_eq!(c_ident("implicit_equation_0"), "IMPLICIT_EQUATION_0");
+    assert_eq!(c_ident("a.b"), "A_B");
+    assert_eq!(c_ident("1st"), "_1ST");
+}
+
+#[test]
+fn duplicate_identifiers() {
+    let mut header =
+        CHeader { buf: String::new(), prefix: "DIODE".to_owned(), defined: AHashSet::default() };
+    assert_eq!(header.define("PARAM_R", 0), "PARAM_R");
+    assert_eq!(header.define("PARAM_R", 1), "PARAM_R_1");
+    assert_eq!(header.define("PARAM_R", 2), "PARAM_R_2");
+    // a Verilog-A name that itself ends with _1 must not collide with the renamed definition
+    assert_eq!(header.define("PARAM_R_1", 3), "PARAM_R_1_1");
+    assert_eq!(
+        header.buf,
+        "#define DIODE_PARAM_R 0u\n#define DIODE_PARAM_R_1 1u\n#define DIODE_PARAM_R_2 2u\n\
+         #define DIODE_PARAM_R_1_1 3u\n"
+    );
+}
@@ -174,11 +174,7 @@ impl NoiseSource {
             val = strip_optbarrier(module.eval, val);
             EvalOutput::new(module, val, slots, false, ty_real)
         };
-        let args = match source.kind {
-            dae::NoiseSourceKind::WhiteNoise { pwr } => [get_output(pwr), EvalOutput::NONE],
-            dae::NoiseSourceKind::FlickerNoise { pwr, exp } => [get_output(pwr), get_output(exp)],
-            dae::NoiseSourceKind::NoiseTable { .. } => [EvalOutput::NONE; 2],
-        };
+        let args = noise_args(&source.kind, &mut get_output);
         NoiseSource { args, factor: get_output(source.factor) }
     }
 
@@ -187,6 +183,47 @@ impl NoiseSource {
     }
 }
 
+#[derive(Debug)]
+pub struct CorrelatedNoiseSource {
+    /// one factor for each contribution of the noise source
+    pub factors: Vec<EvalOutput>,
+    /// content of values depend on kind of noise source
+    pub args: [EvalOutput; 2],
+}
+
+impl CorrelatedNoiseSource {
+    pub fn new<'ll>(
+        source: &dae::CorrelatedNoiseSource,
+        module: &OsdiModule<'_>,
+        slots: &mut TiMap<EvalOutputSlot, mir::Value, &'ll llvm::Type>,
+        ty_real: &'ll llvm::Type,
+    ) -> CorrelatedNoiseSource {
+        let mut get_output = |mut val| {
+            val = strip_optbarrier(module.eval, val);
+            EvalOutput::new(module, val, slots, false, ty_real)
+        };
+        let args = noise_args(&source.kind, &mut get_output);
+        let factors =
+            source.contributions.iter().map(|contrib| get_output(contrib.factor)).collect();
+        CorrelatedNoiseSource { args, factors }
+    }
+
+    pub fn eval_outputs(&self) -> impl Iterator<Item = EvalOutput> + '_ {
+        self.factors.iter().copied().chain(self.args)
+    }
+}
+
+fn noise_args(
+    kind: &dae::NoiseSourceKind,
+    get_output: &mut impl FnMut(mir::Value) -> EvalOutput,
+) -> [EvalOutput; 2] {
+    match *kind {
+        dae::NoiseSourceKind::WhiteNoise { pwr } => [get_output(pwr), EvalOutput::NONE],
+        dae::NoiseSourceKind::FlickerNoise { pwr, exp } => [get_output(pwr), get_output(exp)],
+        dae::NoiseSourceKind::NoiseTable { .. } => [EvalOutput::NONE; 2],
+    }
+}
+
 pub struct OsdiInstanceData<'ll> {
     /// llvm type for the instance data struct
     pub ty: &'ll llvm::Type,
@@ -206,6 +243,7 @@ pub struct OsdiInstanceData<'ll> {
 
     pub residual: TiVec<SimUnknown, Residual>,
     pub noise: Vec<NoiseSource>,
+    pub correlated_noise: Vec<CorrelatedNoiseSource>,
     pub opvars: IndexMap<Variable, EvalOutput, RandomState>,
     pub jacobian: TiVec<MatrixEntryId, MatrixEntry>,
     pub bound_step: Option<EvalOutputSlot>,
@@ -266,6 +304,12 @@ impl<'ll> OsdiInstanceData<'ll> {
             .iter()
             .map(|source| NoiseSource::new(source, module, &mut eval_outputs, ty_f64))
             .collect();
+        let correlated_noise = module
+            .dae_system
+            .correlated_noise_sources
+            .iter()
+            .map(|source| CorrelatedNoiseSource::new(source, module, &mut eval_outputs, ty_f64))
+            .collect();
         let bound_step = module.intern.outputs.get(&PlaceKind::BoundStep).and_then(|val| {
             let mut val = val.expand()?;
             val = strip_optbarrier(module.eval, val);
@@ -320,6 +364,7 @@ impl<'ll> OsdiInstanceData<'ll> {
             cache_slots,
             residual,
             noise,
+            correlated_noise,
             opvars,
             jacobian,
             bound_step,
@@ -1,17 +1,21 @@
-use base_n::CASE_INSENSITIVE;
 use camino::{Utf8Path, Utf8PathBuf};
 use hir::{CompilationDB, ParamSysFun, Type};
 use hir_lower::{CallBackKind, HirInterner, ParamKind};
 use lasso::Rodeo;
 use llvm::{LLVMDisposeTargetData, OptLevel};
-use mir_llvm::{CodegenCx, LLVMBackend};
+use mir_llvm::{CodegenCx, LLVMBackend, ModuleLlvm};
 use salsa::ParallelDatabase;
 use sim_back::{CompiledModule, ModuleInfo};
+
+pub use sim_back::CorrelatedNoise;
 use stdx::{impl_debug_display, impl_idx_from};
 use target::spec::Target;
 use typed_indexmap::TiSet;
 
 use std::ffi::CString;
+use std::fs;
+use std::os::raw::c_char;
+use std::sync::Mutex;
 
 use crate::compilation_unit::{new_codegen, OsdiCompilationUnit, OsdiModule};
 use crate::metadata::osdi_0_3::OsdiTys;
@@ -20,6 +24,7 @@ use crate::metadata::OsdiLimFunction;
 mod access;
 mod bitfield;
 mod compilation_unit;
+mod header;
 mod inst_data;
 mod metadata;
 mod model_data;
@@ -31,21 +36,189 @@ mod setup;
 
 const OSDI_VERSION: (u32, u32) = (0, 3);
 
+/// The artifacts written by [`compile`]. Each group of functions (`access`, `setup_model`,
+/// `setup_instance` and `eval`) of a module is compiled to a separate LLVM module which is
+/// written to `<dst>_<group>_<sym>.<ext>` next to the destination. The descriptors are
+/// written to `<dst>.<ext>`. C headers are written to `<module>.h` next to the destination.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub struct Emit {
+    /// textual LLVM IR (`.ll`)
+    pub llvm_ir: bool,
+    /// LLVM bitcode (`.bc`)
+    pub llvm_bc: bool,
+    /// assembly (`.s`)
+    pub asm: bool,
+    /// object files (`.o`) that can be linked into an OSDI library
+    pub obj: bool,
+    /// a C header for each module with the indices of parameters, nodes, jacobian entries, ...
+    pub c_header: bool,
+}
+
+impl Emit {
+    /// Whether no LLVM module needs to be written (C headers are generated separately).
+    pub fn is_empty(&self) -> bool {
+        !(self.llvm_ir || self.llvm_bc || self.asm || self.obj)
+    }
+
+    fn write(&self, llmod: &ModuleLlvm, dst: &Utf8Path) {
+        if self.llvm_ir {
+            assert_eq!(llmod.emit_llvm_ir(dst.with_extension("ll").as_ref()), Ok(()))
+        }
+        if self.llvm_bc {
+            assert_eq!(llmod.emit_llvm_bc(dst.with_extension("bc").as_ref()), Ok(()))
+        }
+        if self.asm {
+            assert_eq!(llmod.emit_asm(dst.with_extension("s").as_ref()), Ok(()))
+        }
+        if self.obj {
+            assert_eq!(llmod.emit_object(dst.with_extension("o").as_ref()), Ok(()))
+        }
+    }
+}
+
+/// How the generated code is split into object files.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum ObjectLayout<'a> {
+    /// A separate object file for each group of functions (see [`Emit`]) which are linked into
+    /// a shared library.
+    PerGroup,
+    /// A single relocatable object file (`<dst>.o`) for embedding the models into another binary.
+    /// All symbols except for the OSDI exports (`OSDI_DESCRIPTORS`, `osdi_log`, ...) have internal
+    /// linkage. The exports are prefixed with `symbol_prefix` to avoid collisions when multiple
+    /// models are linked into the same binary.
+    Combined { symbol_prefix: &'a str },
+}
+
+#[derive(Debug, Default)]
+pub struct ObjectFiles {
+    pub paths: Vec<Utf8PathBuf>,
+    /// The symbols exported by a combined object file ([`ObjectLayout::Combined`]).
+    /// Empty for [`ObjectLayout::PerGroup`].
+    pub exports: Vec<String>,
+}
+
+struct Artifacts {
+    emit: Emit,
+    /// Keep the bitcode of each module in memory so that the modules can be linked afterwards.
+    capture_bitcode: bool,
+    paths: Vec<Utf8PathBuf>,
+    bitcode: Mutex<Vec<Vec<u8>>>,
+}
+
+impl Artifacts {
+    fn is_empty(&self) -> bool {
+        self.emit.is_empty() && !self.capture_bitcode
+    }
+
+    fn write(&self, llmod: &ModuleLlvm, i: usize) {
+        self.emit.write(llmod, &self.paths[i]);
+        if self.capture_bitcode {
+            self.bitcode.lock().unwrap()[i] = llmod.to_bitcode();
+        }
+    }
+}
+
+/// Compiles `modules` into an OSDI library and writes the artifacts selected by `emit`.
+/// Returns the emitted object files.
+#[allow(clippy::too_many_arguments)]
 pub fn compile(
     db: &CompilationDB,
     modules: &[ModuleInfo],
     dst: &Utf8Path,
     target: &Target,
     back: &LLVMBackend,
-    emit: bool,
+    emit: Emit,
+    layout: ObjectLayout,
     opt_lvl: OptLevel,
-) -> Vec<Utf8PathBuf> {
+    correlated_noise: CorrelatedNoise,
+) -> ObjectFiles {
+    let combine = matches!(layout, ObjectLayout::Combined { .. });
+    // the combined object file is only generated after all modules have been linked
+    let group_emit = if combine { Emit { obj: false, ..emit } } else { emit };
+    let artifacts = codegen(
+        db,
+        modules,
+        dst,
+        target,
+        back,
+        group_emit,
+        combine && emit.obj,
+        opt_lvl,
+        correlated_noise,
+    );
+    let main_module = artifacts.paths.len() - 1;
+
+    if !emit.obj {
+        return ObjectFiles::default();
+    }
+
+    match layout {
+        ObjectLayout::PerGroup => ObjectFiles {
+            paths: artifacts.paths.iter().map(|path| path.with_extension("o")).collect(),
+            exports: Vec::new(),
+        },
+        ObjectLayout::Combined { symbol_prefix } => {
+            let name = dst.file_stem().expect("destination is a file");
+            let llmod = unsafe { back.new_module(name, opt_lvl).unwrap() };
+            for bitcode in artifacts.bitcode.into_inner().unwrap() {
+                llmod.link_bitcode(&bitcode);
+            }
+            let exports = internalize(&llmod, symbol_prefix);
+            // the functions of all groups are visible to each other now
+            // and unused internal symbols can be removed
+            llmod.optimize();
+            debug_assert!(llmod.verify_and_print());
+
+            let path = artifacts.paths[main_module].with_extension("o");
+            assert_eq!(llmod.emit_object(path.as_ref()), Ok(()));
+            ObjectFiles { paths: vec![path], exports }
+        }
+    }
+}
+
+/// Compiles `modules` to optimized LLVM bitcode without writing any files. The returned
+/// buffers (one for each group of functions and one for the descriptors) reference each
+/// other and must all be loaded into the same JIT.
+pub fn compile_bitcode(
+    db: &CompilationDB,
+    modules: &[ModuleInfo],
+    target: &Target,
+    back: &LLVMBackend,
+    opt_lvl: OptLevel,
+    correlated_noise: CorrelatedNoise,
+) -> Vec<Vec<u8>> {
+    let artifacts = codegen(
+        db,
+        modules,
+        Utf8Path::new("osdi"),
+        target,
+        back,
+        Emit::default(),
+        true,
+        opt_lvl,
+        correlated_noise,
+    );
+    artifacts.bitcode.into_inner().unwrap()
+}
+
+#[allow(clippy::too_many_arguments)]
+fn codegen(
+    db: &CompilationDB,
+    modules: &[ModuleInfo],
+    dst: &Utf8Path,
+    target: &Target,
+    back: &LLVMBackend,
+    emit: Emit,
+    capture_bitcode: bool,
+    opt_lvl: OptLevel,
+    correlated_noise: CorrelatedNoise,
+) -> Artifacts {
     let mut literals = Rodeo::new();
     let mut lim_table = TiSet::default();
     let modules: Vec<_> = modules
         .iter()
         .map(|module| {
-            let mir = CompiledModule::new(db, module, &mut literals);
+            let mir = CompiledModule::new(db, module, &mut literals, correlated_noise);
             for cb in mir.intern.callbacks.iter() {
                 if let CallBackKind::BuiltinLimit { name, num_args } = *cb {
                     lim_table.ensure(OsdiLimFunction { name, num_args: num_args - 2 });
@@ -56,14 +229,6 @@ pub fn compile(
         .collect();
     let name = dst.file_stem().expect("destination is a file").to_owned();
 
-    let mut paths: Vec<Utf8PathBuf> = (0..modules.len() * 4)
-        .map(|i| {
-            let num = base_n::encode((i + 1) as u128, CASE_INSENSITIVE);
-            let extension = format!("o{num}");
-            dst.with_extension(extension)
-        })
-        .collect();
-
     let target_data = unsafe {
         let src = CString::new(target.data_layout.clone()).unwrap();
         llvm::LLVMCreateTargetData(src.as_ptr())
@@ -78,15 +243,30 @@ pub fn compile(
         })
         .collect();
 
-    let db = db.snapshot();
+    let mut paths: Vec<Utf8PathBuf> = modules
+        .iter()
+        .flat_map(|module| {
+            ["access", "setup_model", "setup_instance", "eval"]
+                .map(|group| dst.with_file_name(format!("{name}_{group}_{}", &module.sym)))
+        })
+        .collect();
+    let main_module = paths.len();
+    paths.push(dst.with_extension(""));
+
+    let artifacts = Artifacts {
+        emit,
+        capture_bitcode,
+        bitcode: Mutex::new(vec![Vec::new(); paths.len()]),
+        paths,
+    };
 
-    let main_file = dst.with_extension("o");
+    let db = db.snapshot();
 
     rayon_core::scope(|scope| {
         let db = db;
         let literals_ = &literals;
         let target_data_ = &target_data;
-        let paths = &paths;
+        let artifacts = &artifacts;
 
         for (i, module) in modules.iter().enumerate() {
             let _db = db.snapshot();
@@ -100,10 +280,9 @@ pub fn compile(
                 cguint.access_function();
                 debug_assert!(llmod.verify_and_print());
 
-                if emit {
-                    let path = &paths[i * 4];
+                if !artifacts.is_empty() {
                     llmod.optimize();
-                    assert_eq!(llmod.emit_object(path.as_ref()), Ok(()))
+                    artifacts.write(&llmod, i * 4);
                 }
             });
 
@@ -118,11 +297,8 @@ pub fn compile(
                 cguint.setup_model();
                 debug_assert!(llmod.verify_and_print());
 
-                if emit {
-                    let path = &paths[i * 4 + 1];
-                    // llmod.optimize();
-                    assert_eq!(llmod.emit_object(path.as_ref()), Ok(()))
-                }
+                // llmod.optimize();
+                artifacts.write(&llmod, i * 4 + 1);
             });
 
             let _db = db.snapshot();
@@ -136,10 +312,9 @@ pub fn compile(
                 cguint.setup_instance();
                 debug_assert!(llmod.verify_and_print());
 
-                if emit {
-                    let path = &paths[i * 4 + 2];
+                if !artifacts.is_empty() {
                     llmod.optimize();
-                    assert_eq!(llmod.emit_object(path.as_ref()), Ok(()))
+                    artifacts.write(&llmod, i * 4 + 2);
                 }
             });
 
@@ -156,10 +331,9 @@ pub fn compile(
                 // println!("{}", llmod.to_str());
                 debug_assert!(llmod.verify_and_print());
 
-                if emit {
-                    let path = &paths[i * 4 + 3];
+                if !artifacts.is_empty() {
                     llmod.optimize();
-                    assert_eq!(llmod.emit_object(path.as_ref()), Ok(()))
+                    artifacts.write(&llmod, i * 4 + 3);
                 }
             });
         }
@@ -168,16 +342,36 @@ pub fn compile(
         let cx = new_codegen(back, &llmod, &literals);
         let tys = OsdiTys::new(&cx, target_data);
 
+        let mut noise_correlation = Vec::new();
         let descriptors: Vec<_> = modules
             .iter()
             .map(|module| {
                 let cguint = OsdiCompilationUnit::new(&db, module, &cx, &tys, false);
                 let descriptor = cguint.descriptor(target_data, &db);
+                if emit.c_header {
+                    let path = dst.with_file_name(format!("{}.h", descriptor.name));
+                    fs::write(&path, header::c_header(&descriptor))
+                        .unwrap_or_else(|err| panic!("failed to write {path}: {err}"));
+                }
+                if correlated_noise == CorrelatedNoise::Matrix {
+                    noise_correlation.push(cguint.noise_correlation().to_ll_val(&cx, &tys));
+                }
                 descriptor.to_ll_val(&cx, &tys)
             })
             .collect();
 
         cx.export_array("OSDI_DESCRIPTORS", tys.osdi_descriptor, &descriptors, true, false);
+        if correlated_noise == CorrelatedNoise::Matrix {
+            // OpenVAF extension (see openvaf_noise_correlation.h), stored separately
+            // from the descriptors to retain compatibility with OSDI 0.3
+            cx.export_array(
+                "OSDI_NOISE_CORRELATION",
+                tys.osdi_noise_correlation,
+                &noise_correlation,
+                true,
+                false,
+            );
+        }
         cx.export_val(
             "OSDI_NUM_DESCRIPTORS",
             cx.ty_int(),
@@ -220,17 +414,47 @@ pub fn compile(
 
         debug_assert!(llmod.verify_and_print());
 
-        if emit {
+        if !artifacts.is_empty() {
             // println!("{}", llmod.to_str());
             llmod.optimize();
             // println!("{}", llmod.to_str());
-            assert_eq!(llmod.emit_object(main_file.as_ref()), Ok(()))
+            artifacts.write(&llmod, main_module);
         }
     });
 
-    paths.push(main_file);
     unsafe { LLVMDisposeTargetData(target_data) };
-    paths
+
+    artifacts
+}
+
+/// Gives all symbols defined in `llmod` internal linkage, except for the OSDI exports which are
+/// renamed to `<prefix><name>` instead. Returns the names of the exported symbols.
+fn internalize(llmod: &ModuleLlvm, prefix: &str) -> Vec<String> {
+    let llmod = llmod.llmod();
+    let mut exports = Vec::new();
+    for val in llvm::function_iter(llmod).chain(llvm::global_iter(llmod)) {
+        unsafe {
+            if llvm::LLVMIsDeclaration(val) != llvm::False
+                || llvm::LLVMGetLinkage(val) != llvm::Linkage::ExternalLinkage
+            {
+                continue;
+            }
+
+            let mut len = 0;
+            let name = llvm::LLVMGetValueName2(val, &mut len);
+            let name = std::slice::from_raw_parts(name as *const u8, len);
+            let name = std::str::from_utf8(name).expect("symbol names are valid utf8");
+            if name.starts_with("OSDI_") || name == "osdi_log" {
+                let name = format!("{prefix}{name}");
+                llvm::LLVMSetValueName2(val, name.as_ptr() as *const c_char, name.len());
+                exports.push(name);
+            } else {
+                llvm::LLVMSetLinkage(val, llvm::Linkage::Internal);
+                llvm::LLVMSetDLLStorageClass(val, llvm::DLLStorageClass::Default);
+            }
+        }
+    }
+    exports
 }
 
 impl OsdiModule<'_> {
@@ -1,3 +1,5 @@
+use std::f64::consts::PI;
+
 use llvm::{
     LLVMAppendBasicBlockInContext, LLVMBuildCall2, LLVMBuildFAdd, LLVMBuildFDiv, LLVMBuildFMul,
     LLVMBuildFSub, LLVMBuildGEP2, LLVMBuildRetVoid, LLVMBuildStore, LLVMCreateBuilderInContext,
@@ -9,6 +11,7 @@ use stdx::iter::zip;
 use typed_index_collections::TiVec;
 
 use crate::compilation_unit::OsdiCompilationUnit;
+use crate::inst_data::EvalOutput;
 
 #[derive(Debug, Clone, Copy)]
 pub enum JacobianLoadType {
@@ -40,6 +43,37 @@ impl JacobianLoadType {
 }
 
 impl<'ll> OsdiCompilationUnit<'_, '_, 'll> {
+    /// Computes the noise power density of a noise source (without any factors)
+    unsafe fn noise_power(
+        &self,
+        kind: &NoiseSourceKind,
+        args: [EvalOutput; 2],
+        inst: &'ll llvm::Value,
+        model: &'ll llvm::Value,
+        freq: &'ll llvm::Value,
+        llbuilder: &llvm::Builder<'ll>,
+    ) -> &'ll llvm::Value {
+        match kind {
+            NoiseSourceKind::WhiteNoise { .. } => {
+                self.load_eval_output(args[0], inst, model, llbuilder)
+            }
+            NoiseSourceKind::FlickerNoise { .. } => {
+                let pwr = self.load_eval_output(args[0], inst, model, llbuilder);
+                let exp = self.load_eval_output(args[1], inst, model, llbuilder);
+                let (ty, fun) = self
+                    .cx
+                    .intrinsic("llvm.pow.f64")
+                    .unwrap_or_else(|| unreachable!("intrinsic llvm.pow.f64 not found"));
+                let freq_exp = LLVMBuildCall2(llbuilder, ty, fun, [freq, exp].as_ptr(), 2, UNNAMED);
+                LLVMSetPartialFastMath(freq_exp);
+                let pwr = LLVMBuildFDiv(llbuilder, pwr, freq_exp, UNNAMED);
+                LLVMSetFastMath(pwr);
+                pwr
+            }
+            NoiseSourceKind::NoiseTable { .. } => unimplemented!("noise tables"),
+        }
+    }
+
     pub fn load_noise(&self) -> &'ll llvm::Value {
         let OsdiCompilationUnit { cx, module, .. } = self;
         let void_ptr = cx.ty_ptr();
@@ -61,28 +95,8 @@ impl<'ll> OsdiCompilationUnit<'_, '_, 'll> {
                 zip(&module.dae_system.noise_sources, &self.inst_data.noise).enumerate()
             {
                 let fac = self.load_eval_output(eval_outputs.factor, inst, model, llbuilder);
-                let mut pwr = match src.kind {
-                    NoiseSourceKind::WhiteNoise { .. } => {
-                        self.load_eval_output(eval_outputs.args[0], inst, model, llbuilder)
-                    }
-                    NoiseSourceKind::FlickerNoise { .. } => {
-                        let mut pwr =
-                            self.load_eval_output(eval_outputs.args[0], inst, model, llbuilder);
-                        let exp =
-                            self.load_eval_output(eval_outputs.args[1], inst, model, llbuilder);
-                        let (ty, fun) = self
-                            .cx
-                            .intrinsic("llvm.pow.f64")
-                            .unwrap_or_else(|| unreachable!("intrinsic {} not found", name));
-                        let freq_exp =
-                            LLVMBuildCall2(llbuilder, ty, fun, [freq, exp].as_ptr(), 2, UNNAMED);
-                        LLVMSetPartialFastMath(freq_exp);
-                        pwr = LLVMBuildFDiv(llbuilder, pwr, freq_exp, UNNAMED);
-                        LLVMSetFastMath(pwr);
-                        pwr
-                    }
-                    NoiseSourceKind::NoiseTable { .. } => unimplemented!("noise tables"),
-                };
+                let mut pwr =
+                    self.noise_power(&src.kind, eval_outputs.args, inst, model, freq, llbuilder);
                 pwr = LLVMBuildFMul(llbuilder, pwr, fac, UNNAMED);
                 LLVMSetFastMath(pwr);
                 let dst = LLVMBuildGEP2(
@@ -96,7 +110,98 @@ impl<'ll> OsdiCompilationUnit<'_, '_, 'll> {
                 LLVMBuildStore(llbuilder, pwr, dst);
             }
 
-            // TODO noise
+            LLVMBuildRetVoid(llbuilder);
+            LLVMDisposeBuilder(llbuilder);
+        }
+
+        llfunc
+    }
+
+    /// Builds a function that writes the complex correlation matrix of each correlated
+    /// noise source to `dst`. For a source with `n` contributions a dense, row major
+    /// `n x n` matrix of `(re, im)` pairs is written. The matrices of all correlated
+    /// noise sources are stored consecutively.
+    pub fn load_noise_correlation(&self) -> &'ll llvm::Value {
+        let OsdiCompilationUnit { cx, module, .. } = self;
+        let void_ptr = cx.ty_ptr();
+        let f64_ptr_ty = cx.ty_ptr();
+        let fun_ty = cx.ty_func(&[void_ptr, void_ptr, cx.ty_double(), f64_ptr_ty], cx.ty_void());
+        let name = &format!("load_noise_correlation_{}", module.sym);
+        let llfunc = cx.declare_int_c_fn(name, fun_ty);
+
+        unsafe {
+            let entry = LLVMAppendBasicBlockInContext(cx.llcx, llfunc, UNNAMED);
+            let llbuilder = LLVMCreateBuilderInContext(cx.llcx);
+            LLVMPositionBuilderAtEnd(llbuilder, entry);
+            let inst = LLVMGetParam(llfunc, 0);
+            let model = LLVMGetParam(llfunc, 1);
+            let freq = LLVMGetParam(llfunc, 2);
+            let dst = LLVMGetParam(llfunc, 3);
+
+            let store = |pos: u32, val| {
+                let dst = LLVMBuildGEP2(
+                    llbuilder,
+                    cx.ty_double(),
+                    dst,
+                    [cx.const_unsigned_int(pos)].as_ptr(),
+                    1,
+                    UNNAMED,
+                );
+                LLVMBuildStore(llbuilder, val, dst);
+            };
+
+            let omega = LLVMBuildFMul(llbuilder, freq, cx.const_real(2.0 * PI), UNNAMED);
+            LLVMSetFastMath(omega);
+            let neg_omega = LLVMBuildFMul(llbuilder, freq, cx.const_real(-2.0 * PI), UNNAMED);
+            LLVMSetFastMath(neg_omega);
+
+            let mut pos = 0;
+            for (src, eval_outputs) in
+                zip(&module.dae_system.correlated_noise_sources, &self.inst_data.correlated_noise)
+            {
+                let pwr =
+                    self.noise_power(&src.kind, eval_outputs.args, inst, model, freq, llbuilder);
+                let factors: Vec<_> = zip(&src.contributions, &eval_outputs.factors)
+                    .map(|(contrib, &factor)| {
+                        (self.load_eval_output(factor, inst, model, llbuilder), contrib.reactive)
+                    })
+                    .collect();
+                // the contributions of a correlated noise source are scaled copies of the same
+                // signal: S_kl = P * c_k * conj(c_l) where c_k = f_k for resistive contributions
+                // and c_k = j * omega * f_k for reactive contributions (contributed through ddt)
+                for &(fac1, reactive1) in &factors {
+                    let row = LLVMBuildFMul(llbuilder, pwr, fac1, UNNAMED);
+                    LLVMSetFastMath(row);
+                    for &(fac2, reactive2) in &factors {
+                        let mut val = LLVMBuildFMul(llbuilder, row, fac2, UNNAMED);
+                        LLVMSetFastMath(val);
+                        let (re, im) = match (reactive1, reactive2) {
+                            (false, false) => (val, cx.const_real(0.0)),
+                            (true, true) => {
+                                val = LLVMBuildFMul(llbuilder, val, omega, UNNAMED);
+                                LLVMSetFastMath(val);
+                                val = LLVMBuildFMul(llbuilder, val, omega, UNNAMED);
+                                LLVMSetFastMath(val);
+                                (val, cx.const_real(0.0))
+                            }
+                            (true, false) => {
+                                val = LLVMBuildFMul(llbuilder, val, omega, UNNAMED);
+                                LLVMSetFastMath(val);
+                                (cx.const_real(0.0), val)
+                            }
+                            (false, true) => {
+                                val = LLVMBuildFMul(llbuilder, val, neg_omega, UNNAMED);
+                                LLVMSetFastMath(val);
+                                (cx.const_real(0.0), val)
+                            }
+                        };
+                        store(pos, re);
+                        store(pos + 1, im);
+                        pos += 2;
+                    }
+                }
+            }
+
             LLVMBuildRetVoid(llbuilder);
             LLVMDisposeBuilder(llbuilder);
         }
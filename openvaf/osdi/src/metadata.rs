@@ -16,10 +16,10 @@ use crate::inst_data::{
 };
 use crate::load::JacobianLoadType;
 use crate::metadata::osdi_0_3::{
-    OsdiDescriptor, OsdiJacobianEntry, OsdiNode, OsdiNodePair, OsdiNoiseSource, OsdiParamOpvar,
-    OsdiTys, JACOBIAN_ENTRY_REACT, JACOBIAN_ENTRY_REACT_CONST, JACOBIAN_ENTRY_RESIST,
-    JACOBIAN_ENTRY_RESIST_CONST, PARA_KIND_INST, PARA_KIND_MODEL, PARA_KIND_OPVAR, PARA_TY_INT,
-    PARA_TY_REAL, PARA_TY_STR,
+    OsdiCorrelatedNoiseSource, OsdiDescriptor, OsdiJacobianEntry, OsdiNode, OsdiNodePair,
+    OsdiNoiseCorrelation, OsdiNoiseSource, OsdiParamOpvar, OsdiTys, JACOBIAN_ENTRY_REACT,
+    JACOBIAN_ENTRY_REACT_CONST, JACOBIAN_ENTRY_RESIST, JACOBIAN_ENTRY_RESIST_CONST, PARA_KIND_INST,
+    PARA_KIND_MODEL, PARA_KIND_OPVAR, PARA_TY_INT, PARA_TY_REAL, PARA_TY_STR,
 };
 use crate::ty_len;
 
@@ -313,6 +313,38 @@ impl<'ll> OsdiCompilationUnit<'_, '_, 'll> {
     }
 }
 
+impl<'ll> OsdiCompilationUnit<'_, '_, 'll> {
+    pub fn noise_correlation(&self) -> OsdiNoiseCorrelation<'ll> {
+        let correlated_noise_sources: Vec<_> = self
+            .module
+            .dae_system
+            .correlated_noise_sources
+            .iter()
+            .map(|source| {
+                let nodes: Vec<_> = source
+                    .contributions
+                    .iter()
+                    .map(|contrib| OsdiNodePair {
+                        node_1: contrib.hi.into(),
+                        node_2: contrib.lo.map_or(u32::MAX, u32::from),
+                    })
+                    .collect();
+                OsdiCorrelatedNoiseSource {
+                    name: self.cx.literals.resolve(&source.name).to_owned(),
+                    num_nodes: nodes.len() as u32,
+                    nodes,
+                }
+            })
+            .collect();
+
+        OsdiNoiseCorrelation {
+            num_correlated_noise_src: correlated_noise_sources.len() as u32,
+            correlated_noise_sources,
+            load_noise_correlation: self.load_noise_correlation(),
+        }
+    }
+}
+
 impl OsdiModule<'_> {
     pub fn intern_node_strs(&self, intern: &mut Rodeo, db: &CompilationDB) {
         for &unknown in self.dae_system.unknowns.iter() {
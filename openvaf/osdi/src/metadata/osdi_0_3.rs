@@ -424,6 +424,57 @@ impl OsdiTyBuilder<'_, '_, '_> {
         self.osdi_descriptor = Some(ty);
     }
 }
+pub struct OsdiCorrelatedNoiseSource {
+    pub name: String,
+    pub num_nodes: u32,
+    pub nodes: Vec<OsdiNodePair>,
+}
+impl OsdiCorrelatedNoiseSource {
+    pub fn to_ll_val<'ll>(&self, ctx: &CodegenCx<'_, 'll>, tys: &'ll OsdiTys) -> &'ll llvm::Value {
+        let arr_2: Vec<_> = self.nodes.iter().map(|it| it.to_ll_val(ctx, tys)).collect();
+        let fields = [
+            ctx.const_str_uninterned(&self.name),
+            ctx.const_unsigned_int(self.num_nodes),
+            ctx.const_arr_ptr(tys.osdi_node_pair, &arr_2),
+        ];
+        let ty = tys.osdi_correlated_noise_source;
+        ctx.const_struct(ty, &fields)
+    }
+}
+impl OsdiTyBuilder<'_, '_, '_> {
+    fn osdi_correlated_noise_source(&mut self) {
+        let ctx = self.ctx;
+        let fields = [ctx.ty_ptr(), ctx.ty_int(), ctx.ty_ptr()];
+        let ty = ctx.ty_struct("OsdiCorrelatedNoiseSource", &fields);
+        self.osdi_correlated_noise_source = Some(ty);
+    }
+}
+pub struct OsdiNoiseCorrelation<'ll> {
+    pub num_correlated_noise_src: u32,
+    pub correlated_noise_sources: Vec<OsdiCorrelatedNoiseSource>,
+    pub load_noise_correlation: &'ll llvm::Value,
+}
+impl<'ll> OsdiNoiseCorrelation<'ll> {
+    pub fn to_ll_val(&self, ctx: &CodegenCx<'_, 'll>, tys: &'ll OsdiTys) -> &'ll llvm::Value {
+        let arr_1: Vec<_> =
+            self.correlated_noise_sources.iter().map(|it| it.to_ll_val(ctx, tys)).collect();
+        let fields = [
+            ctx.const_unsigned_int(self.num_correlated_noise_src),
+            ctx.const_arr_ptr(tys.osdi_correlated_noise_source, &arr_1),
+            self.load_noise_correlation,
+        ];
+        let ty = tys.osdi_noise_correlation;
+        ctx.const_struct(ty, &fields)
+    }
+}
+impl OsdiTyBuilder<'_, '_, '_> {
+    fn osdi_noise_correlation(&mut self) {
+        let ctx = self.ctx;
+        let fields = [ctx.ty_int(), ctx.ty_ptr(), ctx.ty_ptr()];
+        let ty = ctx.ty_struct("OsdiNoiseCorrelation", &fields);
+        self.osdi_noise_correlation = Some(ty);
+    }
+}
 #[derive(Clone)]
 pub struct OsdiTys<'ll> {
     pub osdi_lim_function: &'ll llvm::Type,
@@ -438,6 +489,8 @@ pub struct OsdiTys<'ll> {
     pub osdi_param_opvar: &'ll llvm::Type,
     pub osdi_noise_source: &'ll llvm::Type,
     pub osdi_descriptor: &'ll llvm::Type,
+    pub osdi_correlated_noise_source: &'ll llvm::Type,
+    pub osdi_noise_correlation: &'ll llvm::Type,
 }
 impl<'ll> OsdiTys<'ll> {
     pub fn new(ctx: &CodegenCx<'_, 'll>, target_data: &llvm::TargetData) -> Self {
@@ -456,6 +509,8 @@ impl<'ll> OsdiTys<'ll> {
             osdi_param_opvar: None,
             osdi_noise_source: None,
             osdi_descriptor: None,
+            osdi_correlated_noise_source: None,
+            osdi_noise_correlation: None,
         };
         builder.osdi_lim_function();
         builder.osdi_sim_paras();
@@ -469,6 +524,8 @@ impl<'ll> OsdiTys<'ll> {
         builder.osdi_param_opvar();
         builder.osdi_noise_source();
         builder.osdi_descriptor();
+        builder.osdi_correlated_noise_source();
+        builder.osdi_noise_correlation();
         builder.finish()
     }
 }
@@ -487,6 +544,8 @@ struct OsdiTyBuilder<'a, 'b, 'll> {
     osdi_param_opvar: Option<&'ll llvm::Type>,
     osdi_noise_source: Option<&'ll llvm::Type>,
     osdi_descriptor: Option<&'ll llvm::Type>,
+    osdi_correlated_noise_source: Option<&'ll llvm::Type>,
+    osdi_noise_correlation: Option<&'ll llvm::Type>,
 }
 impl<'ll> OsdiTyBuilder<'_, '_, 'll> {
     fn finish(self) -> OsdiTys<'ll> {
@@ -503,6 +562,8 @@ impl<'ll> OsdiTyBuilder<'_, '_, 'll> {
             osdi_param_opvar: self.osdi_param_opvar.unwrap(),
             osdi_noise_source: self.osdi_noise_source.unwrap(),
             osdi_descriptor: self.osdi_descriptor.unwrap(),
+            osdi_correlated_noise_source: self.osdi_correlated_noise_source.unwrap(),
+            osdi_noise_correlation: self.osdi_noise_correlation.unwrap(),
         }
     }
 }
@@ -8,6 +8,8 @@ use sim_back::CompilationDB;
 use stdx::SKIP_HOST_TESTS;
 use target::spec::Target;
 
+use crate::{CorrelatedNoise, Emit, ObjectLayout};
+
 mod integration;
 mod sourcegen;
 
@@ -17,7 +19,7 @@ fn test_compile(root_file: &Path) {
     let modules = db.collect_modules().unwrap();
     let target = Target::host_target().unwrap();
     let back = LLVMBackend::new(&[], &target, "native".to_owned(), &[]);
-    let emit = !stdx::IS_CI;
+    let emit = Emit { obj: !stdx::IS_CI, ..Emit::default() };
     crate::compile(
         &db,
         &modules,
@@ -25,7 +27,9 @@ fn test_compile(root_file: &Path) {
         &target,
         &back,
         emit,
+        ObjectLayout::PerGroup,
         OptLevel::Aggressive,
+        CorrelatedNoise::default(),
     );
 }
 
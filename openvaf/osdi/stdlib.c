@@ -73,9 +73,9 @@ extern int strcmp(const char *__s1, const char *__s2);
 
 char *simparam_str(void *params_, void *handle, uint32_t *flags, char *name) {
   OsdiSimParas *params = params_;
-  for (int i = 0; params->names[i]; i++) {
+  for (int i = 0; params->names_str[i]; i++) {
     if (strcmp(params->names_str[i], name) == 0) {
-      return params->names_str[i];
+      return params->vals_str[i];
     }
   }
   *flags |= EVAL_RET_FLAG_FATAL;
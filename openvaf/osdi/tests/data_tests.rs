@@ -1,14 +1,19 @@
+use std::fs;
 use std::path::Path;
+use std::process::Command;
 
-use camino::Utf8Path;
+use camino::{Utf8Path, Utf8PathBuf};
+use expect_test::expect_file;
 use hir::diagnostics::ConsoleSink;
 use hir::CompilationDB;
 use llvm::OptLevel;
 use mini_harness::{harness, Result};
 use mir_llvm::LLVMBackend;
+use object::{Object, ObjectSymbol};
+use osdi::{CorrelatedNoise, Emit, ObjectLayout};
 use paths::AbsPathBuf;
 use sim_back::collect_modules;
-use stdx::{ignore_slow_tests, project_root};
+use stdx::{ignore_slow_tests, openvaf_test_data, project_root};
 use target::spec::Target;
 
 fn test_compile(root_file: &Path) {
@@ -17,8 +22,18 @@ fn test_compile(root_file: &Path) {
     let modules = collect_modules(&db, false, &mut ConsoleSink::new(&db)).unwrap();
     let target = Target::host_target().unwrap();
     let back = LLVMBackend::new(&[], &target, "native".to_owned(), &[]);
-    let emit = !stdx::IS_CI;
-    osdi::compile(&db, &modules, Utf8Path::new("foo.o"), &target, &back, emit, OptLevel::None);
+    let emit = Emit { obj: !stdx::IS_CI, ..Emit::default() };
+    osdi::compile(
+        &db,
+        &modules,
+        Utf8Path::new("foo.o"),
+        &target,
+        &back,
+        emit,
+        ObjectLayout::PerGroup,
+        OptLevel::None,
+        CorrelatedNoise::default(),
+    );
 }
 
 fn integration_test(dir: &Path) -> Result {
@@ -29,6 +44,123 @@ fn integration_test(dir: &Path) -> Result {
     Ok(())
 }
 
+/// Only the OSDI symbols of a combined object file must be visible (with the prefix applied).
+fn combined_object() -> Result {
+    if stdx::SKIP_HOST_TESTS {
+        return Ok(());
+    }
+
+    let root_file = openvaf_test_data("osdi").join("diode_lim.va");
+    let root_file = AbsPathBuf::assert(root_file.canonicalize().unwrap());
+    let db = CompilationDB::new_fs(root_file, &[], &[], &[]).unwrap();
+    let modules = collect_modules(&db, false, &mut ConsoleSink::new(&db)).unwrap();
+    let target = Target::host_target().unwrap();
+    let back = LLVMBackend::new(&[], &target, "native".to_owned(), &[]);
+
+    let dir = Utf8PathBuf::from_path_buf(std::env::temp_dir().join("osdi_test_combined")).unwrap();
+    fs::create_dir_all(&dir)?;
+    let objects = osdi::compile(
+        &db,
+        &modules,
+        &dir.join("diode_lim.o"),
+        &target,
+        &back,
+        Emit { obj: true, ..Emit::default() },
+        ObjectLayout::Combined { symbol_prefix: "diode_" },
+        OptLevel::Aggressive,
+        CorrelatedNoise::default(),
+    );
+    assert_eq!(objects.paths, vec![dir.join("diode_lim.o")]);
+
+    let mut exports = objects.exports.clone();
+    exports.sort();
+    for sym in ["OSDI_DESCRIPTORS", "OSDI_NUM_DESCRIPTORS", "OSDI_VERSION_MAJOR", "osdi_log"] {
+        let sym = format!("diode_{sym}");
+        assert!(exports.contains(&sym), "{sym} is not exported: {exports:?}");
+    }
+    assert!(exports.iter().all(|sym| sym.starts_with("diode_OSDI_") || sym == "diode_osdi_log"));
+
+    let data = fs::read(&objects.paths[0])?;
+    let file = object::File::parse(&*data)?;
+    // apple targets prefix C symbols with an underscore
+    let mangle = if target.options.is_like_osx { "_" } else { "" };
+    let demangle = |sym: &object::Symbol| {
+        let name = sym.name().unwrap();
+        name.strip_prefix(mangle).unwrap_or(name).to_owned()
+    };
+    let mut defined: Vec<_> = file
+        .symbols()
+        .filter(|sym| sym.is_global() && sym.is_definition())
+        .map(|sym| demangle(&sym))
+        .collect();
+    defined.sort();
+    assert_eq!(defined, exports, "only the prefixed OSDI symbols must be visible");
+    for sym in file.symbols().filter(|sym| sym.is_undefined()) {
+        let name = demangle(&sym);
+        assert!(!name.starts_with("OSDI_") && name != "osdi_log", "{name} is not defined");
+    }
+
+    fs::remove_dir_all(&dir)?;
+    Ok(())
+}
+
+/// The generated C header must be valid C (together with the OSDI header) and contain
+/// unique definitions for names that only differ in case.
+fn c_header() -> Result {
+    let root_file = openvaf_test_data("osdi").join("c_header.va");
+    let abs_file = AbsPathBuf::assert(root_file.canonicalize().unwrap());
+    let db = CompilationDB::new_fs(abs_file, &[], &[], &[]).unwrap();
+    let modules = collect_modules(&db, false, &mut ConsoleSink::new(&db)).unwrap();
+    let target = Target::host_target().unwrap();
+    let back = LLVMBackend::new(&[], &target, "native".to_owned(), &[]);
+
+    let dir = Utf8PathBuf::from_path_buf(std::env::temp_dir().join("osdi_test_c_header")).unwrap();
+    fs::create_dir_all(&dir)?;
+    osdi::compile(
+        &db,
+        &modules,
+        &dir.join("c_header.o"),
+        &target,
+        &back,
+        Emit { c_header: true, ..Emit::default() },
+        ObjectLayout::PerGroup,
+        OptLevel::None,
+        CorrelatedNoise::default(),
+    );
+    let header = fs::read_to_string(dir.join("c_header.h"))?;
+
+    // the sizes and offsets depend on the target
+    let snapshot: Vec<_> = header
+        .split("\n\n")
+        .filter(|section| !section.starts_with("/* instance/model data */"))
+        .collect();
+    expect_file![root_file.with_extension("snap")].assert_eq(&snapshot.join("\n\n"));
+
+    if !stdx::SKIP_HOST_TESTS {
+        let src = dir.join("check.c");
+        fs::write(
+            &src,
+            "#include \"osdi_0_3.h\"\n#include \"c_header.h\"\n\
+             _Static_assert(C_HEADER_NODE_CI < C_HEADER_NUM_NODES, \"node\");\n\
+             _Static_assert(C_HEADER_INSTANCE_SIZE > C_HEADER_NODE_MAPPING_OFFSET, \"size\");\n",
+        )?;
+        let osdi_headers = project_root().join("openvaf").join("osdi").join("header");
+        let status = Command::new("clang")
+            .args(["-fsyntax-only", "-std=c11", "-Wall", "-Werror", "-I"])
+            .arg(&osdi_headers)
+            .arg("-I")
+            .arg(&dir)
+            .arg(&src)
+            .status()?;
+        assert!(status.success(), "the generated header does not compile");
+    }
+
+    fs::remove_dir_all(&dir)?;
+    Ok(())
+}
+
 harness! {
-    Test::from_dir("integration", &integration_test, &ignore_slow_tests, &project_root().join("integration_tests"))
+    Test::from_dir("integration", &integration_test, &ignore_slow_tests, &project_root().join("integration_tests")),
+    Test::new("combined_object", &combined_object),
+    Test::new("c_header", &c_header)
 }
@@ -0,0 +1,330 @@
+//! Dataflow analysis of the variables of a module. Finds reads of variables that may not have
+//! been assigned yet (`uninitialized_variable`) and assignments whose value is never read
+//! (`dead_assignment`).
+//!
+//! The lints are computed on the MIR of the analog initial block and the analog block that
+//! `hir_lower` produces with tracked accesses. Every read and every assignment of a variable is
+//! a separate `optbarrier`, so the SSA construction resolves which assignments a read may
+//! observe. Before the first assignment a variable has its `HiddenState` parameter: its initial
+//! value or the value from the previous evaluation. User functions are inlined, so their bodies
+//! are only analyzed where they are called.
+
+use ahash::{AHashMap, AHashSet};
+use hir::diagnostics::{BodyValidationDiagnostic, ConsoleSink, PathStep};
+use hir::{Body, CompilationDB, ExprId, Module, Stmt, StmtId, Variable};
+use hir_lower::{BodyOwner, HirInterner, MirBuilder, ParamKind, PlaceKind, VarRead, VarWrite};
+use indexmap::IndexMap;
+use lasso::Rodeo;
+use mir::{
+    Block, ControlFlowGraph, DominatorTree, Function, InstructionData, PhiNode, Value, ValueDef,
+};
+use typed_index_collections::TiVec;
+
+use crate::ModuleInfo;
+
+pub(crate) fn collect(
+    db: &CompilationDB,
+    module: &ModuleInfo,
+) -> Vec<(BodyOwner, BodyValidationDiagnostic)> {
+    let (func, intern) = MirBuilder::new(
+        db,
+        module.module,
+        &|kind| matches!(kind, PlaceKind::Var(_)),
+        &mut std::iter::empty(),
+    )
+    .with_tracked_accesses()
+    .build(&mut Rodeo::new());
+
+    let cfg = ControlFlowGraph::with_function(&func);
+    let mut dom_tree = DominatorTree::default();
+    dom_tree.compute(&func, &cfg, true, false, false);
+
+    let mut analysis = Analysis {
+        db,
+        func: &func,
+        intern: &intern,
+        dom_tree: &dom_tree,
+        module: module.module,
+        bodies: AHashMap::new(),
+        diagnostics: Vec::new(),
+    };
+    analysis.report_uninitialized();
+    // operating point variables are read by the simulator
+    analysis.report_dead_assignments(|var| module.op_vars.contains_key(&var));
+    analysis.diagnostics
+}
+
+/// Reports the diagnostics found by [`collect`] for the bodies they belong to.
+pub(crate) fn report(
+    db: &CompilationDB,
+    module: Module,
+    diagnostics: Vec<(BodyOwner, BodyValidationDiagnostic)>,
+    sink: &mut ConsoleSink,
+) {
+    let mut bodies: IndexMap<BodyOwner, Vec<_>, ahash::RandomState> = IndexMap::default();
+    for (owner, diag) in diagnostics {
+        bodies.entry(owner).or_default().push(diag)
+    }
+    for (owner, diagnostics) in bodies {
+        match owner {
+            BodyOwner::AnalogInitialBlock => {
+                module.report_analog_initial_block_diagnostics(db, &diagnostics, sink)
+            }
+            BodyOwner::AnalogBlock => {
+                module.report_analog_block_diagnostics(db, &diagnostics, sink)
+            }
+            BodyOwner::Function(fun) => fun.report_diagnostics(db, &diagnostics, sink),
+        }
+    }
+}
+
+struct Analysis<'a> {
+    db: &'a CompilationDB,
+    func: &'a Function,
+    intern: &'a HirInterner,
+    dom_tree: &'a DominatorTree,
+    module: Module,
+    bodies: AHashMap<BodyOwner, (Body, TiVec<ExprId, Option<StmtId>>)>,
+    diagnostics: Vec<(BodyOwner, BodyValidationDiagnostic)>,
+}
+
+impl Analysis<'_> {
+    fn body(&mut self, owner: BodyOwner) -> &(Body, TiVec<ExprId, Option<StmtId>>) {
+        let (db, module) = (self.db, self.module);
+        self.bodies.entry(owner).or_insert_with(|| {
+            let body = match owner {
+                BodyOwner::AnalogInitialBlock => module.analog_initial_block(db),
+                BodyOwner::AnalogBlock => module.analog_block(db),
+                BodyOwner::Function(fun) => fun.body(db),
+            };
+            let expr_stmts = body.borrow().expr_stmts();
+            (body, expr_stmts)
+        })
+    }
+
+    /// The value observed by the read `read`.
+    fn observed(&self, read: Value) -> Value {
+        match self.func.dfg.value_def(read) {
+            ValueDef::Result(inst, _) => self.func.dfg.instr_args(inst)[0],
+            _ => unreachable!("reads are always optbarriers"),
+        }
+    }
+
+    /// The value of `var` at the end of the analog block.
+    fn exit_value(&self, var: Variable) -> Option<Value> {
+        let val = self.intern.outputs.get(&PlaceKind::Var(var))?.expand()?;
+        // outputs reuse the optbarrier of an assignment
+        if self.intern.accesses.writes.contains_key(&val) {
+            Some(val)
+        } else {
+            Some(self.observed(val))
+        }
+    }
+
+    fn initial_values(&self) -> impl Iterator<Item = (Variable, Value)> + '_ {
+        self.intern.params.iter().filter_map(|(kind, &val)| match *kind {
+            ParamKind::HiddenState(var) => Some((var, val)),
+            _ => None,
+        })
+    }
+
+    fn phi(&self, val: Value) -> Option<(Block, &PhiNode)> {
+        if let ValueDef::Result(inst, _) = self.func.dfg.value_def(val) {
+            if let InstructionData::PhiNode(ref phi) = self.func.dfg.insts[inst] {
+                return Some((self.func.layout.inst_block(inst)?, phi));
+            }
+        }
+        None
+    }
+
+    /// Finds all values that may be the initial value of a variable without initializer. Values
+    /// created by a phi are mapped to the predecessor and value of an edge that carries such an
+    /// initial value, the initial values themselves are mapped to `None`.
+    fn maybe_unassigned(&self) -> AHashMap<Value, Option<(Block, Value)>> {
+        let mut res: AHashMap<_, _> = self
+            .initial_values()
+            .filter(|(var, _)| !var.has_initializer(self.db))
+            .map(|(_, val)| (val, None))
+            .collect();
+
+        let phis: Vec<_> = self
+            .func
+            .layout
+            .blocks()
+            .flat_map(|bb| self.func.layout.block_insts(bb))
+            .filter_map(|inst| match self.func.dfg.insts[inst] {
+                InstructionData::PhiNode(ref phi) => Some((self.func.dfg.first_result(inst), phi)),
+                _ => None,
+            })
+            .collect();
+
+        let mut changed = true;
+        while changed {
+            changed = false;
+            for &(val, phi) in &phis {
+                if res.contains_key(&val) {
+                    continue;
+                }
+                let edge = self.func.dfg.phi_edges(phi).find(|(_, arg)| res.contains_key(arg));
+                if let Some(edge) = edge {
+                    res.insert(val, Some(edge));
+                    changed = true;
+                }
+            }
+        }
+        res
+    }
+
+    /// Calls `f` for all decisions on the dominator tree from `bb` up to (excluding) `stop`.
+    fn decisions_until(
+        &self,
+        bb: Block,
+        stop: Option<Block>,
+        mut f: impl FnMut(Block, usize, PathStep),
+    ) {
+        let mut bb = Some(bb);
+        while let Some(block) = bb {
+            if bb == stop {
+                break;
+            }
+            if let Some((i, _, &(_, step))) = self.intern.accesses.decisions.get_full(&block) {
+                f(block, i, step)
+            }
+            bb = self.dom_tree.idom(block);
+        }
+    }
+
+    /// The decisions along a path on which `val`, that is observed in `bb`, is the initial
+    /// value of a variable. Only decisions within `owner` can be shown to the user.
+    fn path(
+        &self,
+        maybe_unassigned: &AHashMap<Value, Option<(Block, Value)>>,
+        mut bb: Block,
+        mut val: Value,
+        owner: BodyOwner,
+    ) -> Vec<PathStep> {
+        let mut path = Vec::new();
+        while let Some(&Some((pred, arg))) = maybe_unassigned.get(&val) {
+            let join = self.phi(val).unwrap().0;
+            // loops are exited from their header so reaching the exit directly from the header
+            // before any iteration is a decision on the way to `bb`
+            self.decisions_until(bb, Some(join), |block, i, step| {
+                if matches!(step, PathStep::LoopSkipped { .. })
+                    && self.dom_tree.idom(block) == Some(join)
+                {
+                    path.push((i, step))
+                }
+            });
+            // only the decisions that lead to `pred` instead of another predecessor matter
+            self.decisions_until(pred, self.dom_tree.idom(join), |_, i, step| {
+                if !matches!(step, PathStep::LoopSkipped { .. }) {
+                    path.push((i, step))
+                }
+            });
+            bb = pred;
+            val = arg;
+        }
+        path.sort_unstable_by_key(|(i, _)| *i);
+        path.dedup();
+        path.into_iter()
+            .filter(|(i, _)| self.intern.accesses.decisions[*i].0 == owner)
+            .map(|(_, step)| step)
+            .collect()
+    }
+
+    fn report_uninitialized(&mut self) {
+        let maybe_unassigned = self.maybe_unassigned();
+        // every uninitialized variable is only reported at its first read
+        let mut reported = AHashSet::new();
+        let intern = self.intern;
+        for (&read, &VarRead { var, owner, expr }) in &intern.accesses.reads {
+            let val = self.observed(read);
+            if !maybe_unassigned.contains_key(&val) || !reported.insert(var) {
+                continue;
+            }
+            let stmt = match self.body(owner).1[expr] {
+                Some(stmt) => stmt,
+                None => continue,
+            };
+            let inst = self.func.dfg.value_def(read).inst().unwrap();
+            let bb = self.func.layout.inst_block(inst).unwrap();
+            let path = self.path(&maybe_unassigned, bb, val, owner);
+            let diag = BodyValidationDiagnostic::UninitializedVariable {
+                var: var.into(),
+                expr,
+                stmt,
+                path: path.into_boxed_slice(),
+            };
+            self.diagnostics.push((owner, diag))
+        }
+    }
+
+    /// `live_out` returns whether the value of a variable at the end of the analog block is
+    /// observed afterwards.
+    fn report_dead_assignments(&mut self, live_out: impl Fn(Variable) -> bool) {
+        let vars: Vec<Variable> = self
+            .intern
+            .outputs
+            .keys()
+            .filter_map(|kind| match *kind {
+                PlaceKind::Var(var) => Some(var),
+                _ => None,
+            })
+            .collect();
+
+        let mut live = AHashSet::new();
+        let mut worklist: Vec<_> =
+            self.intern.accesses.reads.keys().map(|&read| self.observed(read)).collect();
+        let mut observed_exits = AHashSet::new();
+        for &var in &vars {
+            if live_out(var) {
+                observed_exits.insert(var);
+                worklist.extend(self.exit_value(var));
+            }
+        }
+
+        loop {
+            while let Some(val) = worklist.pop() {
+                if live.insert(val) {
+                    if let Some((_, phi)) = self.phi(val) {
+                        worklist.extend(self.func.dfg.phi_edges(phi).map(|(_, arg)| arg))
+                    }
+                }
+            }
+
+            // a variable whose value from the previous evaluation is observed is live at exit
+            for (var, val) in self.initial_values() {
+                if live.contains(&val) && observed_exits.insert(var) {
+                    worklist.extend(self.exit_value(var))
+                }
+            }
+
+            if worklist.is_empty() {
+                break;
+            }
+        }
+
+        // function bodies are inlined for every call, an assignment is only dead if it is
+        // dead for all calls
+        let mut dead: IndexMap<(BodyOwner, StmtId), (Variable, bool), ahash::RandomState> =
+            IndexMap::default();
+        for (val, &VarWrite { var, owner, stmt }) in &self.intern.accesses.writes {
+            if let Some(stmt) = stmt {
+                let is_dead = !live.contains(val);
+                dead.entry((owner, stmt)).or_insert((var, true)).1 &= is_dead;
+            }
+        }
+
+        for ((owner, stmt), (var, is_dead)) in dead {
+            if !is_dead {
+                continue;
+            }
+            let dst = match self.body(owner).0.borrow().get_stmt(stmt) {
+                Some(Stmt::Assignment { dst, .. }) => dst,
+                _ => continue,
+            };
+            let diag = BodyValidationDiagnostic::DeadAssignment { var: var.into(), dst, stmt };
+            self.diagnostics.push((owner, diag))
+        }
+    }
+}
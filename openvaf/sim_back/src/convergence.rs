@@ -0,0 +1,420 @@
+//! Finds model code that is likely to cause convergence problems in the simulator:
+//! exponentials of probes without limiting (`unlimited_exp`), contributions that are not smooth
+//! functions of the probes (`non_smooth_contribution`), contributions whose derivative is lost
+//! by rounding (`rounding_derivative`), divisions by probe dependent values that may become zero
+//! (`probe_division`) and time derivatives of discontinuous values (`discontinuous_ddt`).
+//!
+//! The lints are computed on the (unoptimized) MIR of the analog block. Which values depend on
+//! the probes is determined with the derivative information from `mir_autodiff`. Discontinuities
+//! are not visible in the derivatives and are therefore tracked with a separate forward dataflow
+//! analysis that follows both data and control dependencies. The source location of each
+//! instruction is the expression it was lowered from, which is used to report the results.
+
+use ahash::AHashSet;
+use bitset::SparseBitMatrix;
+use hir::diagnostics::{lints, BodyValidationDiagnostic, Lint, NonSmooth};
+use hir::{
+    AssignmentLhs, BodyRef, BuiltIn, CompilationDB, Expr, ExprId, Ref, ResolvedFun, Stmt, StmtId,
+    Variable,
+};
+use hir_lower::{CallBackKind, HirInterner, ParamKind};
+use lasso::Rodeo;
+use mir::{
+    Block, Const, Function, Inst, InstructionData, KnownDerivatives, Opcode, Unknown, Value,
+    ValueDef,
+};
+use syntax::ast::BinaryOp;
+use typed_index_collections::TiVec;
+
+use crate::context::Context;
+use crate::ModuleInfo;
+
+/// The lints reported by [`collect`].
+pub(crate) const LINTS: [Lint; 5] = [
+    lints::unlimited_exp,
+    lints::non_smooth_contribution,
+    lints::rounding_derivative,
+    lints::probe_division,
+    lints::discontinuous_ddt,
+];
+
+pub(crate) fn collect(db: &CompilationDB, module: &ModuleInfo) -> Vec<BodyValidationDiagnostic> {
+    let mut cx = Context::new(db, &mut Rodeo::new(), module);
+    cx.compute_cfg();
+    cx.compute_domtree(false, true, false);
+    let mut control_dep = SparseBitMatrix::new_square(0);
+    cx.dom_tree.compute_postdom_frontiers(&cx.cfg, &mut control_dep);
+
+    let derivatives = cx.intern.unknowns(&cx.func, true);
+    let reachable = mir_autodiff::reachable_unknowns(&cx.func, &derivatives);
+    let mut deps: TiVec<Value, Dependence> =
+        vec![Dependence::default(); cx.func.dfg.num_values()].into();
+    for (kind, &val) in cx.intern.params.iter() {
+        if matches!(kind, ParamKind::Voltage { .. } | ParamKind::Current(_)) {
+            deps[val].unlimited = true;
+        }
+    }
+
+    let body = module.module.analog_block(db);
+    let body = body.borrow();
+    let expr_stmts = body.expr_stmts();
+    let mut analysis = Analysis {
+        func: &cx.func,
+        intern: &cx.intern,
+        body,
+        expr_stmts: &expr_stmts,
+        control_dep: &control_dep,
+        derivatives: &derivatives,
+        reachable: &reachable,
+        deps,
+        diagnostics: Vec::new(),
+    };
+
+    let mut changed = true;
+    while changed {
+        changed = false;
+        for bb in analysis.func.layout.blocks() {
+            for inst in analysis.func.layout.block_insts(bb) {
+                changed |= analysis.update(bb, inst);
+            }
+        }
+    }
+
+    analysis.report_insts();
+    analysis.report_contributions();
+    // the contributions are reported after all instructions but diagnostics are easier to read
+    // in the order of the statements they belong to
+    analysis.diagnostics.sort_by_key(|(stmt, _)| *stmt);
+    analysis.diagnostics.into_iter().map(|(_, diag)| diag).collect()
+}
+
+/// How a value depends on the probes of a module.
+#[derive(Clone, Copy, Default, PartialEq, Eq, Debug)]
+struct Dependence {
+    /// the value depends on a probe that was not limited with `$limit` or `limexp`
+    unlimited: bool,
+    /// the value is not a smooth function of the probes because of this function or condition
+    non_smooth: Option<NonSmooth>,
+    /// the value is not a continuous function of the probes because of this function or
+    /// condition
+    discontinuity: Option<NonSmooth>,
+}
+
+impl Dependence {
+    fn merge(&mut self, other: Dependence) {
+        self.unlimited |= other.unlimited;
+        self.non_smooth = self.non_smooth.or(other.non_smooth);
+        self.discontinuity = self.discontinuity.or(other.discontinuity);
+    }
+
+    fn add_discontinuity(&mut self, origin: NonSmooth) {
+        self.discontinuity.get_or_insert(origin);
+        self.non_smooth.get_or_insert(origin);
+    }
+}
+
+struct Analysis<'a> {
+    func: &'a Function,
+    intern: &'a HirInterner,
+    body: BodyRef<'a>,
+    expr_stmts: &'a TiVec<ExprId, Option<StmtId>>,
+    control_dep: &'a SparseBitMatrix<Block, Block>,
+    derivatives: &'a KnownDerivatives,
+    reachable: &'a SparseBitMatrix<Inst, Unknown>,
+    deps: TiVec<Value, Dependence>,
+    diagnostics: Vec<(StmtId, BodyValidationDiagnostic)>,
+}
+
+impl Analysis<'_> {
+    /// Returns the expression of the analog block that `inst` was lowered from.
+    /// The analog initial block (and variable initializers) are lowered into the same function
+    /// and may map to unrelated expressions. These never depend on a probe however so
+    /// they can not cause any diagnostics.
+    fn src_expr(&self, inst: Inst) -> Option<ExprId> {
+        let loc = self.func.srclocs.get(inst)?.bits();
+        if loc <= 0 || loc as usize > self.expr_stmts.len() {
+            return None;
+        }
+        let expr = ExprId::from(loc as usize - 1);
+        self.body.is_value(expr).then_some(expr)
+    }
+
+    fn src_builtin(&self, inst: Inst) -> Option<BuiltIn> {
+        match self.body.get_expr(self.src_expr(inst)?) {
+            Expr::Call { fun: ResolvedFun::BuiltIn(fun), .. } => Some(fun),
+            _ => None,
+        }
+    }
+
+    /// Returns whether `val` has a (possibly) non-zero derivative by any unknown or is a
+    /// discontinuous function of the unknowns.
+    fn depends_on_probe(&self, val: Value) -> bool {
+        if self.derivatives.unknowns.contains(&val) || self.deps[val].discontinuity.is_some() {
+            return true;
+        }
+        match self.func.dfg.value_def(val) {
+            ValueDef::Result(inst, _) => {
+                self.reachable.row(inst).map_or(false, |unknowns| !unknowns.is_empty())
+            }
+            _ => false,
+        }
+    }
+
+    fn update(&mut self, bb: Block, inst: Inst) -> bool {
+        let dependence = self.inst_dependence(bb, inst);
+        let mut changed = false;
+        for &val in self.func.dfg.inst_results(inst) {
+            let old = self.deps[val];
+            let mut new = old;
+            new.merge(dependence);
+            if new != old {
+                self.deps[val] = new;
+                changed = true;
+            }
+        }
+        changed
+    }
+
+    fn inst_dependence(&self, bb: Block, inst: Inst) -> Dependence {
+        let dfg = &self.func.dfg;
+        let mut res = Dependence::default();
+        match dfg.insts[inst] {
+            InstructionData::PhiNode(ref phi) => {
+                for (pred, val) in dfg.phi_edges(phi) {
+                    res.merge(self.deps[val]);
+                    self.phi_deciders(pred, bb, &mut res);
+                }
+                return res;
+            }
+            InstructionData::Call { func_ref, .. } => {
+                if let CallBackKind::StoreLimit(_) = self.intern.callbacks[func_ref] {
+                    // the limited value is a new unknown
+                    return res;
+                }
+            }
+            _ => (),
+        }
+
+        for &arg in dfg.instr_args(inst) {
+            res.merge(self.deps[arg]);
+        }
+
+        let expr = match self.src_expr(inst) {
+            Some(expr) => expr,
+            None => return res,
+        };
+        let builtin = match self.body.get_expr(expr) {
+            Expr::Call { fun: ResolvedFun::BuiltIn(BuiltIn::limexp), .. } => {
+                res.unlimited = false;
+                return res;
+            }
+            Expr::Call { fun: ResolvedFun::BuiltIn(fun), .. } => Some(fun),
+            // user functions are inlined with the source location of the call
+            Expr::Call { fun: ResolvedFun::User { .. }, .. } => return res,
+            _ => None,
+        };
+
+        let probe_arg = || dfg.instr_args(inst).iter().any(|arg| self.depends_on_probe(*arg));
+        match dfg.insts[inst].opcode() {
+            Opcode::Ilt
+            | Opcode::Igt
+            | Opcode::Ige
+            | Opcode::Ile
+            | Opcode::Flt
+            | Opcode::Fgt
+            | Opcode::Fge
+            | Opcode::Fle
+            | Opcode::Ieq
+            | Opcode::Feq
+            | Opcode::Beq
+            | Opcode::Ine
+            | Opcode::Fne
+            | Opcode::Bne
+                if probe_arg() =>
+            {
+                match builtin {
+                    // abs, min and max are continuous but their derivative jumps
+                    Some(fun @ (BuiltIn::abs | BuiltIn::min | BuiltIn::max)) => {
+                        res.non_smooth.get_or_insert(NonSmooth::Call { expr, fun });
+                    }
+                    Some(_) => (),
+                    None => res.add_discontinuity(NonSmooth::Condition(expr)),
+                }
+            }
+            Opcode::Floor | Opcode::Ceil if probe_arg() => {
+                if let Some(fun @ (BuiltIn::floor | BuiltIn::ceil)) = builtin {
+                    res.add_discontinuity(NonSmooth::Call { expr, fun })
+                }
+            }
+            _ => (),
+        }
+
+        res
+    }
+
+    /// Adds the conditions that decide whether the edge from `pred` to `bb` is taken
+    /// to a phi in `bb`.
+    fn phi_deciders(&self, pred: Block, bb: Block, res: &mut Dependence) {
+        let mut deciders: Vec<Block> = vec![pred];
+        deciders.extend(self.control_dep.iter(pred));
+        for decider in deciders {
+            if decider != bb && self.control_dep.contains(bb, decider) {
+                continue;
+            }
+            let term = match self.func.layout.last_inst(decider) {
+                Some(term) => term,
+                None => continue,
+            };
+            if let InstructionData::Branch { cond, .. } = self.func.dfg.insts[term] {
+                let cond = self.deps[cond];
+                if let Some(origin) = cond.discontinuity {
+                    res.add_discontinuity(origin)
+                } else if let Some(origin) = cond.non_smooth {
+                    res.non_smooth.get_or_insert(origin);
+                }
+            }
+        }
+    }
+
+    /// Returns `true` if `val` is known to be positive (and therefore non-zero).
+    fn is_positive(&self, val: Value) -> bool {
+        let dfg = &self.func.dfg;
+        let inst = match dfg.value_def(val) {
+            ValueDef::Const(Const::Float(val)) => return f64::from(val) > 0.0,
+            ValueDef::Const(Const::Int(val)) => return val > 0,
+            ValueDef::Result(inst, _) => inst,
+            _ => return false,
+        };
+        match dfg.insts[inst] {
+            InstructionData::Unary { opcode: Opcode::Exp | Opcode::Cosh, .. } => true,
+            InstructionData::Binary {
+                opcode: Opcode::Fadd | Opcode::Fmul | Opcode::Fdiv,
+                args,
+            } => self.is_positive(args[0]) && self.is_positive(args[1]),
+            InstructionData::PhiNode(ref phi) => match self.src_builtin(inst) {
+                Some(BuiltIn::limexp) => true,
+                Some(BuiltIn::max) => dfg.phi_edges(phi).any(|(_, val)| self.is_positive(val)),
+                _ => false,
+            },
+            _ => false,
+        }
+    }
+
+    fn push(&mut self, stmt: StmtId, diagnostic: BodyValidationDiagnostic) {
+        if !self.diagnostics.iter().any(|(_, diag)| diag == &diagnostic) {
+            self.diagnostics.push((stmt, diagnostic))
+        }
+    }
+
+    fn report_insts(&mut self) {
+        let func = self.func;
+        for bb in func.layout.blocks() {
+            for inst in func.layout.block_insts(bb) {
+                let expr = match self.src_expr(inst) {
+                    Some(expr) => expr,
+                    None => continue,
+                };
+                let stmt = match self.expr_stmts[expr] {
+                    Some(stmt) => stmt,
+                    None => continue,
+                };
+
+                match func.dfg.insts[inst] {
+                    InstructionData::Unary { opcode: Opcode::Exp, arg }
+                        if self.deps[arg].unlimited
+                            && self.src_builtin(inst) == Some(BuiltIn::exp) =>
+                    {
+                        self.push(stmt, BodyValidationDiagnostic::UnlimitedExp { expr, stmt })
+                    }
+                    InstructionData::Binary { opcode: Opcode::Fdiv | Opcode::Frem, args }
+                        if self.depends_on_probe(args[1]) && !self.is_positive(args[1]) =>
+                    {
+                        if let Expr::BinaryOp {
+                            rhs,
+                            op: BinaryOp::Division | BinaryOp::Remainder,
+                            ..
+                        } = self.body.get_expr(expr)
+                        {
+                            let diag =
+                                BodyValidationDiagnostic::ProbeDivision { denominator: rhs, stmt };
+                            self.push(stmt, diag)
+                        }
+                    }
+                    InstructionData::Call { func_ref, .. } => {
+                        if let CallBackKind::TimeDerivative = self.intern.callbacks[func_ref] {
+                            let arg = func.dfg.instr_args(inst)[0];
+                            if let Some(origin) = self.deps[arg].discontinuity {
+                                let diag = BodyValidationDiagnostic::DiscontinuousDdt {
+                                    expr,
+                                    origin,
+                                    stmt,
+                                };
+                                self.push(stmt, diag)
+                            }
+                        }
+                    }
+                    _ => (),
+                }
+            }
+        }
+    }
+
+    fn report_contributions(&mut self) {
+        // the dependence of all instructions that were lowered from an expression
+        let mut expr_deps: TiVec<ExprId, Dependence> =
+            vec![Dependence::default(); self.expr_stmts.len()].into();
+        let func = self.func;
+        for bb in func.layout.blocks() {
+            for inst in func.layout.block_insts(bb) {
+                if let Some(expr) = self.src_expr(inst) {
+                    for &val in func.dfg.inst_results(inst) {
+                        expr_deps[expr].merge(self.deps[val])
+                    }
+                }
+            }
+        }
+
+        // reading a variable does not create an instruction, so use the values that were
+        // assigned to it instead
+        let mut assignments: Vec<(Variable, ExprId)> = Vec::new();
+        for stmt in self.body.stmts() {
+            if let Some(Stmt::Assignment { lhs: AssignmentLhs::Variable(var), rhs, .. }) =
+                self.body.get_stmt(stmt)
+            {
+                assignments.push((var, rhs))
+            }
+        }
+        let expr_dependence = |expr: ExprId| match self.body.get_expr(expr) {
+            Expr::Read(Ref::Variable(var)) => {
+                let mut res = Dependence::default();
+                for (_, rhs) in assignments.iter().filter(|(dst, _)| *dst == var) {
+                    res.merge(expr_deps[*rhs])
+                }
+                res
+            }
+            _ => expr_deps[expr],
+        };
+
+        let mut reported = AHashSet::new();
+        let mut diagnostics = Vec::new();
+        for stmt in self.body.stmts() {
+            if let Some(Stmt::Contribute { dst, rhs, .. }) = self.body.get_stmt(stmt) {
+                let origin = match expr_dependence(rhs).non_smooth {
+                    Some(origin) if reported.insert(origin) => origin,
+                    _ => continue,
+                };
+                let diag = match origin {
+                    NonSmooth::Call { expr, fun: fun @ (BuiltIn::floor | BuiltIn::ceil) } => {
+                        BodyValidationDiagnostic::RoundingDerivative { expr, fun, dst, stmt }
+                    }
+                    _ => BodyValidationDiagnostic::NonSmoothContribution { origin, dst, stmt },
+                };
+                diagnostics.push((stmt, diag));
+            }
+        }
+
+        for (stmt, diag) in diagnostics {
+            self.push(stmt, diag)
+        }
+    }
+}
@@ -6,7 +6,7 @@ use typed_indexmap::TiSet;
 
 use crate::context::Context;
 use crate::dae::builder::Builder;
-pub use crate::noise::{NoiseSource, NoiseSourceKind};
+pub use crate::noise::{CorrelatedNoiseSource, NoiseContribution, NoiseSource, NoiseSourceKind};
 use crate::{topology, SimUnknownKind};
 
 mod builder;
@@ -43,6 +43,9 @@ pub struct DaeSystem {
     pub small_signal_parameters: IndexSet<Value, ahash::RandomState>,
     /// noise
     pub noise_sources: Vec<NoiseSource>,
+    /// noise sources that contribute to multiple branches, the correlation
+    /// between these contributions is exported as a matrix
+    pub correlated_noise_sources: Vec<CorrelatedNoiseSource>,
 }
 
 impl DaeSystem {
@@ -81,15 +84,13 @@ impl DaeSystem {
 
         self.noise_sources.retain_mut(|noise_src| {
             noise_src.map_vals(&mut sparsify);
-            if noise_src.factor == F_ZERO {
-                return false;
-            }
-            match noise_src.kind {
-                NoiseSourceKind::WhiteNoise { pwr } | NoiseSourceKind::FlickerNoise { pwr, .. } => {
-                    pwr != F_ZERO
-                }
-                NoiseSourceKind::NoiseTable { .. } => true,
-            }
+            noise_src.factor != F_ZERO && !noise_src.kind.is_zero()
+        });
+
+        self.correlated_noise_sources.retain_mut(|noise_src| {
+            noise_src.map_vals(&mut sparsify);
+            noise_src.contributions.retain(|contrib| contrib.factor != F_ZERO);
+            !noise_src.contributions.is_empty() && !noise_src.kind.is_zero()
         });
 
         self.jacobian.raw.retain_mut(|matrix_entry| {
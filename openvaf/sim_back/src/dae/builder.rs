@@ -17,7 +17,7 @@ use typed_index_collections::TiVec;
 
 use crate::context::Context;
 use crate::dae::{DaeSystem, MatrixEntry, Residual, SimUnknown};
-use crate::noise::NoiseSource;
+use crate::noise::{CorrelatedNoiseSource, NoiseContribution, NoiseSource};
 use crate::topology::{BranchInfo, Contribution};
 use crate::util::{add, is_op_dependent, update_optbarrier};
 use crate::SimUnknownKind;
@@ -56,6 +56,9 @@ pub(super) struct Builder<'a> {
     pub(super) dom_tree: &'a mut DominatorTree,
     pub(super) op_dependent_insts: &'a BitSet<Inst>,
     pub(super) output_values: &'a mut BitSet<Value>,
+    /// maps the analog operator of a correlated noise source to its
+    /// position in `system.correlated_noise_sources`
+    pub(super) correlated_noise: AHashMap<Inst, usize>,
 }
 
 impl<'a> Builder<'a> {
@@ -70,6 +73,7 @@ impl<'a> Builder<'a> {
             dom_tree: &mut ctx.dom_tree,
             op_dependent_insts: &ctx.op_dependent_insts,
             output_values: &mut ctx.output_values,
+            correlated_noise: AHashMap::new(),
         };
 
         // ensure ports are the first unknowns and always have an unknown
@@ -480,23 +484,47 @@ impl<'a> Builder<'a> {
         let mfactor = self
             .intern
             .ensure_param(&mut self.cursor, ParamKind::ParamSysFun(ParamSysFun::mfactor));
-        self.system.noise_sources.extend(contrib.noise.iter().map(|src| {
-            let mut ofactor = src.factor;
-            let factor = if is_current {
+        for src in &contrib.noise {
+            let mut factor = src.factor;
+            if let Some(operator) = src.correlation {
+                // the factor of a correlated noise source scales the noise amplitude
+                // (not the power) so scale with sqrt(mfactor) instead
+                update_optbarrier(self.cursor.func, &mut factor, |val, cursor| {
+                    let sqrt_mfactor = cursor.ins().sqrt(mfactor);
+                    if is_current {
+                        cursor.ins().fmul(sqrt_mfactor, val)
+                    } else {
+                        cursor.ins().fdiv(val, sqrt_mfactor)
+                    }
+                });
+                let sources = &mut self.system.correlated_noise_sources;
+                let pos = *self.correlated_noise.entry(operator).or_insert_with(|| {
+                    sources.push(CorrelatedNoiseSource {
+                        name: src.name,
+                        kind: src.kind.clone(),
+                        contributions: Vec::new(),
+                    });
+                    sources.len() - 1
+                });
+                let reactive = src.reactive;
+                sources[pos].contributions.push(NoiseContribution { hi, lo, factor, reactive });
+                continue;
+            }
+
+            if is_current {
                 // multiply power by mfactor for noise current (flow)
-                update_optbarrier(self.cursor.func, &mut ofactor, |val, cursor| {
+                update_optbarrier(self.cursor.func, &mut factor, |val, cursor| {
                     cursor.ins().fmul(mfactor, val)
                 });
-                ofactor
             } else {
                 // divide power by mfactor for noise voltage (potential)
-                update_optbarrier(self.cursor.func, &mut ofactor, |val, cursor| {
+                update_optbarrier(self.cursor.func, &mut factor, |val, cursor| {
                     cursor.ins().fdiv(val, mfactor)
                 });
-                ofactor
-            };
-            NoiseSource { name: src.name, kind: src.kind.clone(), hi, lo, factor }
-        }))
+            }
+            let kind = src.kind.clone();
+            self.system.noise_sources.push(NoiseSource { name: src.name, kind, hi, lo, factor });
+        }
     }
 
     fn add_kirchoff_law(&mut self, contrib: &Contribution, dst: BranchWrite) {
@@ -555,6 +583,10 @@ impl<'a> Builder<'a> {
             noise_src.map_vals(|val| ensure_optbarrier(val, false));
         }
 
+        for noise_src in &mut self.system.correlated_noise_sources {
+            noise_src.map_vals(|val| ensure_optbarrier(val, false));
+        }
+
         for entry in &mut self.system.jacobian {
             let is_kirchoff =
                 matches!(self.system.unknowns[entry.row], SimUnknownKind::KirchoffLaw(_));
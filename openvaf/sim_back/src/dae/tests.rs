@@ -9,7 +9,7 @@ use stdx::{integration_test_dir, openvaf_test_data};
 
 use crate::context::{Context, OptimiziationStage};
 use crate::dae::DaeSystem;
-use crate::topology;
+use crate::{topology, CorrelatedNoise};
 
 fn run_test(src: &str) {
     let db = CompilationDB::new_virtual(src).unwrap();
@@ -19,7 +19,7 @@ fn run_test(src: &str) {
     context.compute_outputs(true);
     context.compute_cfg();
     context.optimize(OptimiziationStage::Initial);
-    let topology = topology::Topology::new(&mut context);
+    let topology = topology::Topology::new(&mut context, CorrelatedNoise::default());
     let mut dae_system = DaeSystem::new(&mut context, topology);
     context.compute_cfg();
     context.optimize(OptimiziationStage::Final);
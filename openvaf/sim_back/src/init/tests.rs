@@ -11,6 +11,7 @@ use crate::context::{Context, OptimiziationStage};
 use crate::dae::DaeSystem;
 use crate::init::Initialization;
 use crate::topology::Topology;
+use crate::CorrelatedNoise;
 
 fn run_test(src: &str) {
     let db = CompilationDB::new_virtual(src).unwrap();
@@ -21,7 +22,7 @@ fn run_test(src: &str) {
     cx.compute_cfg();
     cx.optimize(OptimiziationStage::Initial);
 
-    let topology = Topology::new(&mut cx);
+    let topology = Topology::new(&mut cx, CorrelatedNoise::default());
     let mut dae_system = DaeSystem::new(&mut cx, topology);
 
     cx.compute_cfg();
@@ -13,7 +13,9 @@ use crate::init::Initialization;
 use crate::node_collapse::NodeCollapse;
 use crate::topology::Topology;
 
+mod assignments;
 mod context;
+mod convergence;
 pub mod dae;
 pub mod init;
 mod module_info;
@@ -41,6 +43,21 @@ impl_debug_display! {
     }
 }
 
+/// Determines how noise sources that contribute to multiple branches
+/// (correlated noise) are represented in the DAE system.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub enum CorrelatedNoise {
+    /// Keep the noise source as a single source with multiple contributions.
+    /// Simulators obtain the correlation between these contributions as a
+    /// matrix of complex (cross-)spectral densities. Noise sources that are
+    /// only contributed through `ddt` are exported the same way.
+    Matrix,
+    /// Create an additional internal node (correlation network) for each
+    /// correlated noise source so that only uncorrelated noise sources remain.
+    #[default]
+    InternalNode,
+}
+
 pub struct CompiledModule<'a> {
     pub info: &'a ModuleInfo,
     pub dae_system: DaeSystem,
@@ -57,6 +74,7 @@ impl<'a> CompiledModule<'a> {
         db: &CompilationDB,
         module: &'a ModuleInfo,
         literals: &mut Rodeo,
+        correlated_noise: CorrelatedNoise,
     ) -> CompiledModule<'a> {
         let mut cx = Context::new(db, literals, module);
         cx.compute_outputs(true);
@@ -64,7 +82,7 @@ impl<'a> CompiledModule<'a> {
         cx.optimize(OptimizationStage::Initial);
         debug_assert!(cx.func.validate());
 
-        let topology = Topology::new(&mut cx);
+        let topology = Topology::new(&mut cx, correlated_noise);
         debug_assert!(cx.func.validate());
         let mut dae_system = DaeSystem::new(&mut cx, topology);
         debug_assert!(cx.func.validate());
@@ -1,5 +1,5 @@
 use ahash::AHashSet;
-use hir::diagnostics::{BaseDB, ConsoleSink, Diagnostic, FileId, Label, LabelStyle, Report};
+use hir::diagnostics::{lints, BaseDB, ConsoleSink, Diagnostic, FileId, Label, LabelStyle, Report};
 use hir::{
     CompilationDB, CompilationUnit, DiagnosticSink, Module, ParamSysFun, Parameter,
     ResolvedAliasParameter, ScopeDef, Variable,
@@ -10,6 +10,8 @@ use syntax::ast::{self, Expr};
 use syntax::sourcemap::FileSpan;
 use syntax::AstNode;
 
+use crate::{assignments, convergence};
+
 #[cfg(test)]
 mod tests;
 
@@ -27,12 +29,27 @@ pub fn collect_modules(
         return None;
     }
 
-    let res = cu
+    let res: Vec<_> = cu
         .modules(db)
         .into_iter()
         .map(|module| ModuleInfo::collect(db, cu, module, sink, all_vars_opvars))
         .collect();
 
+    for info in &res {
+        if convergence::LINTS.iter().any(|&lint| info.module.lint_enabled(db, lint)) {
+            let diagnostics = convergence::collect(db, info);
+            info.module.report_analog_block_diagnostics(db, &diagnostics, sink);
+        }
+
+        if [lints::uninitialized_variable, lints::dead_assignment]
+            .into_iter()
+            .any(|lint| info.module.lint_enabled(db, lint))
+        {
+            let diagnostics = assignments::collect(db, info);
+            assignments::report(db, info.module, diagnostics, sink);
+        }
+    }
+
     if sink.summary(&name) {
         return None;
     }
@@ -171,3 +171,194 @@ fn opvars() {
     "#]]
     .assert_debug_eq(&params);
 }
+
+#[test]
+fn convergence_hazards() {
+    let src = indoc! {r#"
+        `include "disciplines.vams"
+
+        (* openvaf_warn="unlimited_exp", openvaf_warn="non_smooth_contribution", openvaf_warn="probe_division", openvaf_warn="discontinuous_ddt", openvaf_warn="rounding_derivative" *)
+        module hazards(a, c);
+            inout a, c;
+            electrical a, c;
+            real vd, id, q, g, n;
+
+            analog begin
+                vd = V(a, c);
+                id = exp(vd / 0.025);
+                if (vd > 0.5) q = 1e-12; else q = 0.0;
+                I(a, c) <+ id + ddt(q);
+                g = abs(vd);
+                I(a, c) <+ 1e-3 * g;
+                I(a, c) <+ 1.0 / vd + 1.0 / (limexp(vd) + 1.0);
+                n = floor(vd);
+                I(a, c) <+ 1e-3 * n;
+            end
+        endmodule
+    "#};
+    let db = CompilationDB::new_virtual(src).unwrap();
+    let mut buf = Buffer::no_color();
+    {
+        let mut sink = ConsoleSink::buffer(&db, &mut buf);
+        sink.annonymize_paths();
+        super::collect_modules(&db, false, &mut sink);
+    }
+    expect_test::expect![[r#"
+        warning[L020]: exponential of a probe is not limited
+           --> /root.va:11:14
+           |
+        11 |         id = exp(vd / 0.025);
+           |              ^^^^^^^^^^^^^^^ may overflow during Newton iterations
+           |
+           = help: use 'limexp' or limit the probe with '$limit'
+
+        warning[L023]: time derivative of a discontinuous value
+           --> /root.va:12:13
+           |
+        12 |         if (vd > 0.5) q = 1e-12; else q = 0.0;
+           |             ^^^^^^^^ condition depends on a probe
+        13 |         I(a, c) <+ id + ddt(q);
+           |                         ------ time derivative is infinite at the discontinuity
+           |
+           = help: blend between the branches with a smooth transition function
+
+        warning[L021]: contribution is not a smooth function of the probes
+           --> /root.va:12:13
+           |
+        12 |         if (vd > 0.5) q = 1e-12; else q = 0.0;
+           |             ^^^^^^^^ condition depends on a probe
+        13 |         I(a, c) <+ id + ddt(q);
+           |         ------- contributed here
+           |
+           = help: blend between the branches with a smooth transition function
+
+        warning[L021]: contribution is not a smooth function of the probes
+           --> /root.va:14:13
+           |
+        14 |         g = abs(vd);
+           |             ^^^^^^^ not differentiable for some probe values
+        15 |         I(a, c) <+ 1e-3 * g;
+           |         ------- contributed here
+           |
+           = help: replace abs(x) with a smooth approximation such as sqrt(x*x + eps)
+
+        warning[L022]: division by a probe dependent value that may be zero
+           --> /root.va:16:26
+           |
+        16 |         I(a, c) <+ 1.0 / vd + 1.0 / (limexp(vd) + 1.0);
+           |                          ^^ may be zero
+           |
+           = help: keep the denominator away from zero, for example by adding a small positive constant
+
+        warning[L006]: derivative of a rounded probe is zero
+           --> /root.va:17:13
+           |
+        17 |         n = floor(vd);
+           |             ^^^^^^^^^ 'floor' hides the probe from the simulator
+        18 |         I(a, c) <+ 1e-3 * n;
+           |         ------- contributed here
+           |
+           = help: the jacobian misses the dependence on the probe; avoid rounding probe dependent values
+
+        warning: `root.va` generated 6 warning
+
+    "#]]
+    .assert_eq(&String::from_utf8(buf.into_inner()).unwrap());
+}
+
+#[test]
+fn uninitialized_variable() {
+    let src = indoc! {r#"
+        `include "disciplines.vams"
+
+        (* openvaf_warn="uninitialized_variable" *)
+        module uninit(a, c);
+            inout a, c;
+            electrical a, c;
+            real x, y, z;
+            real init = 1.0;
+            integer i;
+            analog begin
+                if (V(a, c) > 0.0) x = 1.0;
+                y = x + init;
+                for (i = 0; i < 4; i = i + 1) z = y;
+                I(a, c) <+ x + y + z;
+            end
+        endmodule
+    "#};
+    let db = CompilationDB::new_virtual(src).unwrap();
+    let mut buf = Buffer::no_color();
+    {
+        let mut sink = ConsoleSink::buffer(&db, &mut buf);
+        sink.annonymize_paths();
+        super::collect_modules(&db, false, &mut sink);
+    }
+    expect_test::expect![[r#"
+        warning[L018]: 'x' may be read before it is assigned
+           --> /root.va:12:13
+           |
+        11 |         if (V(a, c) > 0.0) x = 1.0;
+           |             ------------- not assigned if this condition is false
+        12 |         y = x + init;
+           |             ^ 'x' is read here
+           |
+           = note: 'x' has its initial value or the value from the previous evaluation
+
+        warning[L018]: 'z' may be read before it is assigned
+           --> /root.va:14:28
+           |
+        13 |         for (i = 0; i < 4; i = i + 1) z = y;
+           |                     ----- not assigned if this loop is never entered
+        14 |         I(a, c) <+ x + y + z;
+           |                            ^ 'z' is read here
+           |
+           = note: 'z' has its initial value or the value from the previous evaluation
+
+        warning: `root.va` generated 2 warning
+
+    "#]]
+    .assert_eq(&String::from_utf8(buf.into_inner()).unwrap());
+}
+
+#[test]
+fn dead_assignment() {
+    let src = indoc! {r#"
+        `include "disciplines.vams"
+
+        (* openvaf_warn="dead_assignment" *)
+        module dead(a, c);
+            inout a, c;
+            electrical a, c;
+            real x, y;
+            (* desc="output" *) real op;
+
+            analog begin
+                x = 1.0;
+                x = V(a, c);
+                y = 2.0 * x;
+                op = y;
+                I(a, c) <+ x;
+            end
+        endmodule
+    "#};
+    let db = CompilationDB::new_virtual(src).unwrap();
+    let mut buf = Buffer::no_color();
+    {
+        let mut sink = ConsoleSink::buffer(&db, &mut buf);
+        sink.annonymize_paths();
+        super::collect_modules(&db, false, &mut sink);
+    }
+    expect_test::expect![[r#"
+        warning[L019]: value assigned to 'x' is never read
+           --> /root.va:11:9
+           |
+        11 |         x = 1.0;
+           |         ^ assigned value is never read
+           |
+           = help: 'x' is overwritten or not used after this assignment
+
+        warning: `root.va` generated 1 warning
+
+    "#]]
+    .assert_eq(&String::from_utf8(buf.into_inner()).unwrap());
+}
@@ -1,5 +1,5 @@
 use lasso::Spur;
-use mir::Value;
+use mir::{Value, F_ZERO};
 use stdx::Ieee64;
 
 use crate::dae::SimUnknown;
@@ -11,6 +11,29 @@ pub enum NoiseSourceKind {
     NoiseTable { log: bool, vals: Box<[(Ieee64, Ieee64)]> },
 }
 
+impl NoiseSourceKind {
+    pub fn map_vals(&mut self, mut f: impl FnMut(Value) -> Value) {
+        match self {
+            NoiseSourceKind::WhiteNoise { pwr } => *pwr = f(*pwr),
+            NoiseSourceKind::FlickerNoise { pwr, exp } => {
+                *pwr = f(*pwr);
+                *exp = f(*exp);
+            }
+            NoiseSourceKind::NoiseTable { .. } => (),
+        }
+    }
+
+    /// Whether this noise source is statically known to never produce any noise.
+    pub fn is_zero(&self) -> bool {
+        match *self {
+            NoiseSourceKind::WhiteNoise { pwr } | NoiseSourceKind::FlickerNoise { pwr, .. } => {
+                pwr == F_ZERO
+            }
+            NoiseSourceKind::NoiseTable { .. } => false,
+        }
+    }
+}
+
 #[derive(Debug)]
 pub struct NoiseSource {
     pub name: Spur,
@@ -23,13 +46,39 @@ pub struct NoiseSource {
 impl NoiseSource {
     pub fn map_vals(&mut self, mut f: impl FnMut(Value) -> Value) {
         self.factor = f(self.factor);
-        match &mut self.kind {
-            NoiseSourceKind::WhiteNoise { pwr } => *pwr = f(*pwr),
-            NoiseSourceKind::FlickerNoise { pwr, exp } => {
-                *pwr = f(*pwr);
-                *exp = f(*exp);
-            }
-            NoiseSourceKind::NoiseTable { .. } => (),
+        self.kind.map_vals(f)
+    }
+}
+
+/// A single noise source (a call to `white_noise`, `flicker_noise` or
+/// `noise_table`) that contributes to multiple branches (or through `ddt`).
+/// Each contribution injects the same noise signal scaled by a complex
+/// (amplitude) coefficient `c_k`. The coefficient is `factor` for resistive
+/// contributions and `j * omega * factor` for reactive contributions. The
+/// (cross-)spectral densities of these contributions are therefore fully correlated:
+///
+/// `S_kl(f) = P(f) * c_k * conj(c_l)`
+#[derive(Debug)]
+pub struct CorrelatedNoiseSource {
+    pub name: Spur,
+    pub kind: NoiseSourceKind,
+    pub contributions: Vec<NoiseContribution>,
+}
+
+impl CorrelatedNoiseSource {
+    pub fn map_vals(&mut self, mut f: impl FnMut(Value) -> Value) {
+        for contrib in &mut self.contributions {
+            contrib.factor = f(contrib.factor);
         }
+        self.kind.map_vals(f)
     }
 }
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct NoiseContribution {
+    pub hi: SimUnknown,
+    pub lo: Option<SimUnknown>,
+    pub factor: Value,
+    /// the noise is contributed through `ddt`
+    pub reactive: bool,
+}
@@ -32,8 +32,9 @@ use typed_indexmap::TiMap;
 use crate::context::Context;
 use crate::noise::NoiseSourceKind;
 use crate::topology::builder::Builder;
+use crate::topology::lineralize::AnalogOperators;
 use crate::util::strip_optbarrier_if_const;
-use crate::BranchWrite;
+use crate::{BranchWrite, CorrelatedNoise};
 
 mod builder;
 mod lineralize;
@@ -99,6 +100,17 @@ impl ContributeKind {
             | ContributeKind::ImplicitEquation { is_reactive, .. } => is_reactive,
         }
     }
+
+    pub fn to_reactive(self) -> ContributeKind {
+        match self {
+            ContributeKind::Branch { id, is_voltage_src, .. } => {
+                ContributeKind::Branch { id, is_voltage_src, is_reactive: true }
+            }
+            ContributeKind::ImplicitEquation { equation, .. } => {
+                ContributeKind::ImplicitEquation { equation, is_reactive: true }
+            }
+        }
+    }
 }
 
 #[derive(Debug, Clone)]
@@ -106,6 +118,12 @@ pub struct Noise {
     pub name: Spur,
     pub kind: NoiseSourceKind,
     pub factor: Value,
+    /// The analog operator (noise function call) this noise was created from
+    /// if it contributes to multiple branches or is contributed through `ddt` (correlated noise).
+    pub correlation: Option<Inst>,
+    /// Whether this noise is contributed through `ddt`, the small signal
+    /// contribution is then `j * omega * factor` times the noise source.
+    pub reactive: bool,
 }
 
 impl Noise {
@@ -113,6 +131,8 @@ impl Noise {
         inst: Inst,
         cb: &CallBackKind,
         factor: Value,
+        correlation: Option<Inst>,
+        reactive: bool,
         ssa_builder: &mut SSAVariableBuilder,
         func: &mut Function,
     ) -> Noise {
@@ -139,7 +159,7 @@ impl Noise {
             ),
             _ => unreachable!(),
         };
-        Noise { name, kind, factor }
+        Noise { name, kind, factor, correlation, reactive }
     }
 }
 
@@ -155,7 +175,7 @@ pub(crate) struct Topology {
 }
 
 impl Topology {
-    pub(crate) fn new(ctx: &mut Context) -> Self {
+    pub(crate) fn new(ctx: &mut Context, correlated_noise: CorrelatedNoise) -> Self {
         let mut branches = TiMap::with_capacity(128);
         let mut contributes = AHashMap::with_capacity(128);
         let mut implicit_equations: TiVec<_, _> = ctx
@@ -301,8 +321,29 @@ impl Topology {
             phis: Vec::with_capacity(128),
             op_dependent_insts: &ctx.op_dependent_insts,
             op_dependent_vals: &ctx.op_dependent_vals,
+            correlated_noise,
+        };
+        let operators = if correlated_noise == CorrelatedNoise::Matrix {
+            // linearize ddt first, noise that is contributed through ddt then shows
+            // up as a reactive contribution and can be exported with a complex coefficient
+            let ddts = builder.analog_operator_evaluations(
+                AnalogOperators::TimeDerivative,
+                &postdom_frontiers,
+                &mut ctx.intern,
+            );
+            builder.builid_analog_operators(ddts, &mut ctx.intern);
+            builder.analog_operator_evaluations(
+                AnalogOperators::Noise,
+                &postdom_frontiers,
+                &mut ctx.intern,
+            )
+        } else {
+            builder.analog_operator_evaluations(
+                AnalogOperators::All,
+                &postdom_frontiers,
+                &mut ctx.intern,
+            )
         };
-        let operators = builder.analog_operator_evaluations(&postdom_frontiers, &mut ctx.intern);
         drop(postdom_frontiers);
         builder.builid_analog_operators(operators, &mut ctx.intern);
         simplify_cfg_no_phi_merge(builder.func, builder.cfg);
@@ -6,6 +6,7 @@ use mir::cursor::{Cursor, FuncCursor};
 use mir::{Block, ControlFlowGraph, Function, Inst, InstructionData, Opcode, Value, F_ZERO};
 
 use crate::topology::Topology;
+use crate::CorrelatedNoise;
 
 pub(super) struct Builder<'a> {
     pub(super) topology: &'a mut Topology,
@@ -20,6 +21,7 @@ pub(super) struct Builder<'a> {
     pub(super) phis: Vec<Inst>,
     pub(super) op_dependent_insts: &'a BitSet<Inst>,
     pub(super) op_dependent_vals: &'a [Value],
+    pub(super) correlated_noise: CorrelatedNoise,
 }
 
 impl<'a> Builder<'a> {
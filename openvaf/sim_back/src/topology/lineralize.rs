@@ -14,8 +14,9 @@ use mir::{
 };
 use typed_indexmap::TiSet;
 
-use crate::topology::{Contribution, Noise};
+use crate::topology::{ContributeKind, Contribution, Noise};
 use crate::util::{add, update_optbarrier};
+use crate::CorrelatedNoise;
 
 #[derive(Debug)]
 pub(super) enum Evaluation {
@@ -33,6 +34,14 @@ pub(super) enum Evaluation {
     Dead,
 }
 
+/// Selects which analog operators are linearized.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(super) enum AnalogOperators {
+    All,
+    TimeDerivative,
+    Noise,
+}
+
 impl<'a> super::Builder<'a> {
     /// Build topology for a list of analog operators (noise and ddt) with a predetermined evaluation.
     pub(super) fn builid_analog_operators(
@@ -54,11 +63,19 @@ impl<'a> super::Builder<'a> {
                 Evaluation::Linear { contributes } => {
                     cov_mark::hit!(linear_operator);
                     let cb = &intern.callbacks[cb];
+                    let is_reactive = |contribute: Value| {
+                        self.topology.as_contribution(contribute).unwrap().is_reactive()
+                    };
+                    // a noise source that contributes to multiple branches is correlated noise,
+                    // reactive noise has a complex coefficient and is exported the same way
+                    let correlation = (contributes.len() > 1
+                        || contributes.iter().any(|&(contribute, _)| is_reactive(contribute)))
+                    .then_some(operator_inst);
                     for (contribute, mut dimension) in &*contributes {
                         let resistive_contribute = *contribute;
                         let inst = self.func.dfg.value_def(resistive_contribute).inst().unwrap();
-                        let contribute = self.topology.as_contribution(*contribute).unwrap();
-                        let contribute = self.topology.get_mut(contribute);
+                        let kind = self.topology.as_contribution(*contribute).unwrap();
+                        let contribute = self.topology.get_mut(kind);
                         if is_noise {
                             dimension = FuncCursor::new(self.func)
                                 .after_inst(inst)
@@ -68,6 +85,8 @@ impl<'a> super::Builder<'a> {
                                 operator_inst,
                                 cb,
                                 dimension,
+                                correlation,
+                                kind.is_reactive(),
                                 &mut ssa_builder,
                                 self.func,
                             );
@@ -81,6 +100,10 @@ impl<'a> super::Builder<'a> {
                                     val
                                 },
                             );
+                            // noise that is contributed through this ddt is recognized
+                            // by its use in the reactive contribution
+                            let react = contribute.react;
+                            self.topology.contributes.insert(react, kind.to_reactive());
                         }
                     }
                 }
@@ -115,14 +138,22 @@ impl<'a> super::Builder<'a> {
                                 operator_inst,
                                 &intern.callbacks[cb],
                                 F_ONE,
+                                None,
+                                false,
                                 &mut ssa_builder,
                                 self.func,
                             )],
                             ..Contribution::default()
                         }
                     } else {
-                        let arg0 =
+                        let mut arg0 =
                             ssa_builder.define_at_exit(self.func, F_ZERO, arg0, operator_inst);
+                        if self.correlated_noise == CorrelatedNoise::Matrix {
+                            // noise is linearized after ddt in this mode and only
+                            // recognizes contributions by their optbarrier
+                            arg0 =
+                                FuncCursor::new(self.func).at_exit().ins().ensure_optbarrier(arg0);
+                        }
                         Contribution {
                             unknown: Some(eq_val),
                             resist: neg_eq_val,
@@ -142,6 +173,7 @@ impl<'a> super::Builder<'a> {
 
     pub(super) fn analog_operator_evaluations(
         &mut self,
+        operators: AnalogOperators,
         postdom_frontiers: &SparseBitMatrix<Block, Block>,
         intern: &mut HirInterner,
     ) -> Vec<(Inst, Evaluation)> {
@@ -152,7 +184,7 @@ impl<'a> super::Builder<'a> {
         // function yet as otherwise the detection may return incorrect results
         for (cb, uses) in intern.callback_uses.iter_mut_enumerated() {
             match intern.callbacks[cb] {
-                CallBackKind::TimeDerivative => {
+                CallBackKind::TimeDerivative if operators != AnalogOperators::Noise => {
                     for inst in take(uses) {
                         if self.func.layout.inst_block(inst).is_none() {
                             continue;
@@ -179,7 +211,9 @@ impl<'a> super::Builder<'a> {
                 }
                 CallBackKind::WhiteNoise { .. }
                 | CallBackKind::FlickerNoise { .. }
-                | CallBackKind::NoiseTable(_) => {
+                | CallBackKind::NoiseTable(_)
+                    if operators != AnalogOperators::TimeDerivative =>
+                {
                     for inst in take(uses) {
                         analog_operators.push((
                             inst,
@@ -329,15 +363,23 @@ impl<'a> super::Builder<'a> {
                         output_values.contains(val)
                     };
                     if is_output {
-                        // multiple uses of a noise source indicate
-                        // correlated noise, for now just create a correlation network
-                        if noise && !contributes.is_empty() {
-                            return Evaluation::Equation;
-                        } else if self
-                            .topology
-                            .as_contribution(val)
-                            .map_or(false, |it| !it.is_reactive())
+                        // multiple uses of a noise source indicate correlated noise,
+                        // create a correlation network if requested. Otherwise the
+                        // correlation is exported as a matrix of spectral densities
+                        if noise
+                            && !contributes.is_empty()
+                            && self.correlated_noise == CorrelatedNoise::InternalNode
                         {
+                            cov_mark::hit!(correlation_network);
+                            return Evaluation::Equation;
+                        } else if self.topology.as_contribution(val).map_or(false, |it| {
+                            // noise contributed through ddt to a branch has a complex
+                            // coefficient that can be exported with the correlation matrix
+                            !it.is_reactive()
+                                || (noise
+                                    && self.correlated_noise == CorrelatedNoise::Matrix
+                                    && matches!(it, ContributeKind::Branch { .. }))
+                        }) {
                             contributes.push((val, F_ZERO))
                         } else {
                             return Evaluation::Equation;
@@ -8,8 +8,9 @@ use stdx::openvaf_test_data;
 
 use crate::context::{Context, OptimiziationStage};
 use crate::topology::Topology;
+use crate::CorrelatedNoise;
 
-fn compile(src: &str) -> (Function, Topology, String) {
+fn compile(src: &str, correlated_noise: CorrelatedNoise) -> (Function, Topology, String) {
     let db = CompilationDB::new_virtual(src).unwrap();
     let module = crate::collect_modules(&db, false, &mut ConsoleSink::new(&db)).unwrap().remove(0);
     let mut literals = Rodeo::new();
@@ -17,13 +18,13 @@ fn compile(src: &str) -> (Function, Topology, String) {
     context.compute_outputs(true);
     context.compute_cfg();
     context.optimize(OptimiziationStage::Initial);
-    let topology = Topology::new(&mut context);
+    let topology = Topology::new(&mut context, correlated_noise);
     assert!(context.func.validate());
     (context.func, topology, module.module.name(&db))
 }
 
 fn assert(src: &str) {
-    let (func, topology, name) = compile(src);
+    let (func, topology, name) = compile(src, CorrelatedNoise::default());
     println!("{func:?}");
     let test_dir = openvaf_test_data("contributions");
     let topology = format!("{topology:#?}");
@@ -157,6 +158,7 @@ fn unused_noise() {
 ///   signal contributions
 #[test]
 fn correlated_noise() {
+    cov_mark::check!(correlation_network);
     cov_mark::check!(prune_small_signal);
     cov_mark::check!(port_not_small_signal);
     let src = indoc! {r#"
@@ -176,6 +178,69 @@ fn correlated_noise() {
     assert(src);
 }
 
+/// With `CorrelatedNoise::Matrix` correlated noise is not turned into an internal node
+/// but instead into multiple linear contributions that share the same noise source.
+#[test]
+fn correlated_noise_matrix() {
+    let src = indoc! {r#"
+        `include "disciplines.vams"
+        module correlated_noise(inout a, inout c);
+            electrical a, c;
+            parameter real foo=1.0, bar=2.0;
+            real correlated_noise;
+            analog begin
+                correlated_noise = white_noise(foo);
+                I(a) <+ V(a) + white_noise(foo) + bar * correlated_noise;
+                I(c) <+ correlated_noise;
+            end
+        endmodule
+    "#};
+
+    let (_, topology, _) = compile(src, CorrelatedNoise::Matrix);
+    assert!(topology.implicit_equations.is_empty());
+    let noise: Vec<_> =
+        topology.branches.raw.values().flat_map(|branch| branch.current_src.noise.iter()).collect();
+    assert_eq!(noise.len(), 3);
+    let correlated: Vec<_> = noise.iter().filter_map(|noise| noise.correlation).collect();
+    assert_eq!(correlated.len(), 2);
+    assert_eq!(correlated[0], correlated[1]);
+    assert!(noise.iter().all(|noise| !noise.reactive));
+}
+
+/// Noise contributed through `ddt` (induced gate noise) has a complex coefficient.
+/// With `CorrelatedNoise::Matrix` it is exported as a reactive contribution of a
+/// correlated noise source instead of a correlation network.
+#[test]
+fn reactive_noise_matrix() {
+    let src = indoc! {r#"
+        `include "disciplines.vams"
+        module reactive_noise(inout a, inout c);
+            electrical a, c;
+            parameter real foo=1.0, bar=2.0;
+            real correlated_noise;
+            analog begin
+                correlated_noise = white_noise(foo);
+                I(a) <+ V(a) + correlated_noise;
+                I(c) <+ V(c) + ddt(bar * correlated_noise);
+            end
+        endmodule
+    "#};
+
+    let (_, topology, _) = compile(src, CorrelatedNoise::Matrix);
+    assert!(topology.implicit_equations.is_empty());
+    let noise: Vec<_> =
+        topology.branches.raw.values().flat_map(|branch| branch.current_src.noise.iter()).collect();
+    assert_eq!(noise.len(), 2);
+    assert!(noise.iter().all(|noise| noise.correlation.is_some()));
+    assert_eq!(noise[0].correlation, noise[1].correlation);
+    let reactive: Vec<_> = noise.iter().map(|noise| noise.reactive).collect();
+    assert_eq!(reactive, [false, true]);
+
+    // the correlation network is still used by default
+    let (_, topology, _) = compile(src, CorrelatedNoise::default());
+    assert_eq!(topology.implicit_equations.len(), 1);
+}
+
 #[test]
 fn manual_correlated_noise() {
     cov_mark::check!(node_is_small_signal);
@@ -9,14 +9,25 @@ use target::spec::get_targets;
 
 use crate::{add_preamble, ensure_file_contents, project_root, reformat, to_lower_snake_case};
 
+/// OpenVAF specific extensions of OSDI. These live in separate headers so that the
+/// standard headers remain unchanged. The structs are generated alongside the structs
+/// of the standard header.
+const EXTENSION_HEADERS: [&str; 1] = ["openvaf_noise_correlation.h"];
+
 #[test]
 fn gen_osdi_structs() {
     let header_dir = project_root().join("openvaf").join("osdi").join("header");
-    let headers: Vec<_> = read_dir(header_dir)
+    let extensions: String = EXTENSION_HEADERS
+        .iter()
+        .map(|name| read_to_string(header_dir.join(name)).unwrap())
+        .collect();
+    let headers: Vec<_> = read_dir(&header_dir)
         .unwrap()
         .filter_map(|entry| {
             let entry = entry.ok()?;
-            Header::new(entry)
+            let mut header = Header::new(entry)?;
+            header.src.push_str(&extensions);
+            Some(header)
         })
         .collect();
 
@@ -80,7 +91,8 @@ impl Header {
             return None;
         }
         let name = entry.file_name().to_str()?.to_owned();
-        if &name[0..5] != "osdi_" {
+        // extension headers are not versioned
+        if !name.starts_with("osdi_") {
             return None;
         }
 
@@ -1 +1,2 @@
 from .verilogae import *
+from .ac import y_parameters
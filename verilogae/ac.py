@@ -0,0 +1,26 @@
+import numpy as np
+
+
+def y_parameters(small_signal, terminals, freq):
+    """Calculates the Y-parameters between terminals from the result of
+    VaeModel.eval_small_signal at the frequency freq (in Hz).
+
+    The admittance matrix Y = G + j*2*pi*freq*C of the DAE system is reduced to the
+    node potentials of terminals (a list of node names) by eliminating all other
+    unknowns. The result is indexed [..., terminal, terminal] in the order of terminals.
+    freq must be a scalar or broadcastable with the operating points.
+    """
+    unknowns = small_signal["unknowns"]
+    ext = [unknowns.index(f"br_{terminal}") for terminal in terminals]
+    int_ = [i for i in range(len(unknowns)) if i not in ext]
+
+    omega = 2 * np.pi * np.asarray(freq)[..., None, None]
+    y = small_signal["G"] + 1j * omega * small_signal["C"]
+
+    y_ee = y[..., ext, :][..., :, ext]
+    if not int_:
+        return y_ee
+    y_ei = y[..., ext, :][..., :, int_]
+    y_ie = y[..., int_, :][..., :, ext]
+    y_ii = y[..., int_, :][..., :, int_]
+    return y_ee - y_ei @ np.linalg.solve(y_ii, y_ie)
@@ -30,3 +30,117 @@ for fun in hl2.functions.values():
     if not np.allclose(res,data[fun.name], atol=1e-16):
         print(f"assert failed for {fun.name}")
 
+
+# analytic derivatives must match finite differences
+hl2_ddx = verilogae.load("hicumL2V2p4p0_vae.va", derivative_params=["c10"])
+fun = hl2_ddx.functions["itf"]
+val, ddx = fun.eval(derivatives=["br_biei", "c10"], **args)
+assert np.allclose(val, itf)
+
+h = 1e-6
+voltages = dict(args["voltages"])
+voltages["br_biei"] = vbe_ + h
+ddx_fd = (fun.eval(**{**args, "voltages": voltages}) - itf) / h
+if not np.allclose(ddx["br_biei"], ddx_fd, rtol=1e-3):
+    print("assert failed for d(itf)/d(br_biei)")
+
+h = args["c10"] * 1e-6
+ddx_fd = (fun.eval(**{**args, "c10": args["c10"] + h}) - itf) / h
+if not np.allclose(ddx["c10"], ddx_fd, rtol=1e-3):
+    print("assert failed for d(itf)/d(c10)")
+
+# derivatives are only generated if they are requested
+assert hl2.functions["itf"].derivatives == []
+try:
+    hl2.functions["itf"].eval(derivatives=["br_biei"], **args)
+    raise AssertionError("derivatives of a model loaded without derivative_params")
+except TypeError:
+    pass
+hl2_dv = verilogae.load("hicumL2V2p4p0_vae.va", derivative_params=[])
+val, ddx = hl2_dv.functions["itf"].eval(derivatives=["br_biei"], **args)
+assert np.array_equal(val, itf)
+assert np.array_equal(ddx["br_biei"], fun.eval(derivatives=["br_biei"], **args)[1]["br_biei"])
+assert "c10" not in hl2_dv.functions["itf"].derivatives
+
+# the results must not depend on the number of threads
+for threads in [1, 3]:
+    res = hl2.functions["itf"].eval(threads=threads, **args)
+    if not np.array_equal(res, itf):
+        print(f"assert failed for itf with {threads} threads")
+
+# a larger sweep is split into many chunks per thread
+n = 10000
+vbe_sweep = np.linspace(0.5, 0.9, n)
+vbc_sweep = np.linspace(-1.0, 0.2, n)
+voltages = {name: vbe_sweep for name in args["voltages"] if name != "br_bpbi"}
+voltages.update({name: vbc_sweep for name in ["br_bici", "br_bpci", "br_bci"]})
+voltages.update({name: vbc_sweep - vbe_sweep for name in ["br_sici", "br_sc"]})
+voltages["br_bpbi"] = 0.0
+sweep = {**args, "voltages": voltages, "temperature": np.linspace(300.0, 400.0, n)}
+del sweep["itf"]
+itf_sweep = hl2.functions["itf"].eval(threads=1, **sweep)
+assert len(itf_sweep) == n
+for threads in [3, 0]:
+    res = hl2.functions["itf"].eval(threads=threads, **sweep)
+    if not np.array_equal(res, itf_sweep):
+        print(f"assert failed for itf with {threads} threads and {n} points")
+
+# model cards must produce the same results as passing the parameters directly
+mcard = hl2.load_modelcard("mcard.json")
+op = {"temperature": temp_, "voltages": args["voltages"], "itf": itf}
+for fun in hl2.functions.values():
+    if not np.array_equal(fun.eval(modelcard=mcard, **op), fun.eval(**args)):
+        print(f"assert failed for {fun.name} with a model card")
+
+mcard.save_spice("mcard.lib")
+assert hl2.load_modelcard("mcard.lib").to_dict() == mcard.to_dict()
+
+updated = mcard.copy()
+updated.update(c10=2 * args["c10"])
+res = hl2.functions["itf"].eval(modelcard=updated, **op)
+assert np.array_equal(res, hl2.functions["itf"].eval(**{**args, "c10": 2 * args["c10"]}))
+assert mcard.to_dict()["c10"] == args["c10"]
+
+# bins are selected by geometry
+Path("bins.lib").write_text(
+    """* binned model
+.model hbt.1 hicum (lmin=1u lmax=5u c10=1e-30)
+.model hbt.2 hicum lmin=5u lmax=10u
++ c10=2e-30 $ comment
+""",
+    encoding="utf-8",
+)
+assert hl2.load_modelcard("bins.lib", name="hbt", l=2e-6).to_dict()["c10"] == 1e-30
+assert hl2.load_modelcard("bins.lib", name="hbt", l=7e-6).to_dict()["c10"] == 2e-30
+
+# all operating point variables are calculated at once
+opvars = hl2.eval_opvars(modelcard=mcard, **op)
+assert sorted(opvars) == sorted(hl2.op_vars)
+for name in ["rbi", "re_t", "GMi"]:
+    if not np.allclose(opvars[name], hl2.functions[name].eval(**args)):
+        print(f"assert failed for opvar {name}")
+
+# the DAE system is evaluated at once and reduced to the terminals
+bias = {"br_b": 0.7, "br_c": 1.0}
+ss = hl2.eval_small_signal(modelcard=mcard, temperature=300.0, voltages=bias)
+n = len(ss["unknowns"])
+assert ss["I"].shape == ss["Q"].shape == (n,)
+assert ss["G"].shape == ss["C"].shape == (n, n)
+
+terminals = ["c", "b", "e", "s"]
+y = verilogae.y_parameters(ss, terminals, 0.0)
+assert y.shape == (4, 4)
+assert np.all(y.imag == 0.0)
+# the terminal currents sum to zero (charge conservation)
+if not np.allclose(verilogae.y_parameters(ss, terminals, 1e9).sum(axis=0), 0.0, atol=1e-12):
+    print("assert failed for the small signal y parameters")
+
+ss = hl2.eval_small_signal(modelcard=mcard, temperature=300.0, voltages={"br_b": vbe})
+assert ss["G"].shape == (len(vbe), n, n)
+
+# models compiled with the JIT produce the same results as the shared library
+hl2_jit = verilogae.load_jit("hicumL2V2p4p0_vae.va")
+assert sorted(hl2_jit.functions) == sorted(hl2.functions)
+for fun in hl2_jit.functions.values():
+    if not np.array_equal(fun.eval(**args), hl2.functions[fun.name].eval(**args)):
+        print(f"assert failed for {fun.name} compiled with the JIT")
@@ -1,14 +1,16 @@
 use std::ffi::{c_void, CStr};
 use std::os::raw::c_char;
 use std::panic::catch_unwind;
+use std::sync::{Arc, Mutex, PoisonError};
 use std::{ptr, slice};
 
 #[cfg(not(windows))]
 use libloading::os::unix::Library;
 #[cfg(windows)]
 use libloading::os::windows::Library;
+use rayon_core::{ThreadPool, ThreadPoolBuilder};
 
-use crate::{export_vfs, load};
+use crate::{export_vfs, load, load_jit, Jit};
 
 #[repr(C)]
 #[derive(Default)]
@@ -25,6 +27,9 @@ pub struct Opts {
     pub target: Slice<u8>,
     pub cg_flags: Slice<Slice<u8>>,
     pub vfs: Vfs,
+    /// The parameters that the model functions are differentiated by (in addition to the
+    /// voltages). No derivatives are calculated if this is null.
+    pub derivative_params: Slice<Slice<u8>>,
 }
 
 #[repr(C)]
@@ -48,13 +53,8 @@ macro_rules! expose_ptrs {
         /// `lib` must be a valid pointer returned by the `load` functions or `dlopen`
         #[no_mangle]
         pub unsafe extern "C" fn $name(lib: *const c_void) -> *$mut $ty {
-            catch_unwind(||{
-                let lib = Library::from_raw(lib as _);
-                let res = access_ptr::<$ty>(&lib, $sym.as_bytes()) as _;
-                // forget library so it doesn't get closed
-                std::mem::forget(lib);
-                res
-            }).unwrap_or_else(|_|ptr::null::<$ty>() as _)
+            catch_unwind(|| access_ptr::<$ty>(lib, $sym.as_bytes()) as _)
+                .unwrap_or_else(|_|ptr::null::<$ty>() as _)
         }
     )*
     };
@@ -77,6 +77,9 @@ expose_ptrs! {
     const verilogae_str_param_descriptions: *const c_char = "params.desc.string";
     const verilogae_str_param_groups: *const c_char = "params.group.string";
     const verilogae_nodes: *const c_char = "nodes";
+    const verilogae_small_signal_unknowns: *const c_char = "small_signal.unknowns";
+    const verilogae_small_signal_rows: u32 = "small_signal.jacobian.rows";
+    const verilogae_small_signal_cols: u32 = "small_signal.jacobian.cols";
 }
 
 macro_rules! expose_consts{
@@ -90,19 +93,17 @@ macro_rules! expose_consts{
         /// `lib` must be a valid pointer returned by the `load` functions or `dlopen`
         #[no_mangle]
         pub unsafe extern "C" fn $name(lib: *const c_void) -> $ty {
-            catch_unwind(||{
-                let lib = Library::from_raw(lib as _);
-                let res = access_val(&lib, $sym.as_bytes());
-                // forget library so it doesn't get closed
-                std::mem::forget(lib);
-                res
-            }).ok().unwrap_or_else(<$ty>::default)
+            catch_unwind(|| access_val(lib, $sym.as_bytes())).ok().unwrap_or_else(<$ty>::default)
         }
     )*
     };
 }
 
 pub type ParamFlags = u8;
+pub const PARAM_FLAGS_MIN_INCLUSIVE: ParamFlags = 1;
+pub const PARAM_FLAGS_MAX_INCLUSIVE: ParamFlags = 2;
+pub const PARAM_FLAGS_INVALID: ParamFlags = 4;
+pub const PARAM_FLAGS_GIVEN: ParamFlags = 8;
 pub type ModelcardInit = Option<
     extern "C" fn(
         *mut f64,
@@ -123,6 +124,8 @@ expose_consts! {
     verilogae_int_param_cnt: usize = "params.integer.cnt";
     verilogae_str_param_cnt: usize = "params.string.cnt";
     verilogae_node_cnt: usize = "nodes.cnt";
+    verilogae_small_signal_unknown_cnt: usize = "small_signal.unknowns.cnt";
+    verilogae_small_signal_entry_cnt: usize = "small_signal.jacobian.rows.cnt";
 }
 
 macro_rules! expose_named_ptrs {
@@ -142,12 +145,7 @@ macro_rules! expose_named_ptrs {
                 let mut sym_name = fun.to_bytes().to_vec();
                 sym_name.push(b'.');
                 sym_name.extend_from_slice($sym.as_bytes());
-                sym_name.push(b'\0');
-                let lib = Library::from_raw(lib as _);
-                let res = access_ptr::<$ty>(&lib, &sym_name) as _;
-                // forget library so it doesn't get closed
-                std::mem::forget(lib);
-                res
+                access_ptr::<$ty>(lib, &sym_name) as _
                 }
             )
             .unwrap_or_else(|_| ptr::null::<$ty>() as _)
@@ -168,6 +166,7 @@ expose_named_ptrs! {
     const verilogae_fun_currents: *const c_char = "currents";
     const verilogae_fun_voltage_defaults: f64 = "voltages.default";
     const verilogae_fun_current_defaults: f64 = "currents.default";
+    const verilogae_fun_derivatives: *const c_char = "derivatives";
 }
 
 macro_rules! expose_named_consts {
@@ -186,12 +185,7 @@ macro_rules! expose_named_consts {
                 let mut sym_name = fun.to_bytes().to_vec();
                 sym_name.push(b'.');
                 sym_name.extend_from_slice($sym.as_bytes());
-                sym_name.push(b'\0');
-                let lib = Library::from_raw(lib as _);
-                let res = access_val(&lib, &sym_name);
-                // forget library so it doesn't get closed
-                std::mem::forget(lib);
-                res
+                access_val(lib, &sym_name)
                 }
             )
             .ok().unwrap_or_else(<$ty>::default)
@@ -211,6 +205,7 @@ expose_named_consts! {
     verilogae_fun_current_cnt: usize = "currents.cnt";
     verilogae_fun_voltage_default_cnt: usize = "voltages.default.cnt";
     verilogae_fun_current_default_cnt: usize = "currents.default.cnt";
+    verilogae_fun_derivative_cnt: usize = "derivatives.cnt";
 }
 
 #[derive(Clone, Copy)]
@@ -256,6 +251,44 @@ pub type VaeFun = Option<
     ),
 >;
 
+/// A model function that also calculates the derivatives listed in the `derivatives` global.
+/// The last argument contains one pointer per derivative, derivatives are not calculated
+/// if their pointer is null.
+pub type VaeJacobian = Option<
+    extern "C" fn(
+        usize,
+        *mut FatPtr<f64>,
+        *mut FatPtr<f64>,
+        *mut FatPtr<f64>,
+        *mut FatPtr<i32>,
+        *mut *const c_char,
+        *mut FatPtr<f64>,
+        *mut FatPtr<i32>,
+        *mut FatPtr<f64>,
+        *mut c_void,
+        *mut *mut f64,
+    ),
+>;
+
+/// Calculates all operating point variables (listed in the `opvars` global). The last argument
+/// contains one pointer per operating point variable, variables are not calculated if their
+/// pointer is null. The arguments are described by the same globals as a model function
+/// with the name `opvars`.
+pub type VaeOpvars = Option<
+    extern "C" fn(
+        usize,
+        *mut FatPtr<f64>,
+        *mut FatPtr<f64>,
+        *mut FatPtr<f64>,
+        *mut FatPtr<i32>,
+        *mut *const c_char,
+        *mut FatPtr<f64>,
+        *mut FatPtr<i32>,
+        *mut FatPtr<f64>,
+        *mut *mut f64,
+    ),
+>;
+
 /// Obtains a pointer to the modelcard initialization function of a VerilogAE model loaded with `load`.
 ///
 /// # Safety
@@ -263,21 +296,7 @@ pub type VaeFun = Option<
 /// `lib` must be a valid pointer returned by the `load` functions or `dlopen`
 #[no_mangle]
 pub unsafe extern "C" fn verilogae_init_modelcard(lib: *const c_void) -> ModelcardInit {
-    catch_unwind(|| {
-        let lib = Library::from_raw(lib as _);
-        let res = match lib.get(b"init_modelcard\0") {
-            Ok(val) => Some(*val),
-            Err(err) => {
-                eprintln!("error: failed to access init_modelcard\n\n{}", err);
-                None
-            }
-        };
-        // forget library so it doesn't get closed
-        std::mem::forget(lib);
-        res
-    })
-    .ok()
-    .flatten()
+    catch_unwind(|| access_fn(lib, b"init_modelcard")).unwrap_or(None)
 }
 
 /// Obtains a pointer to a model functions of a VerilogAE model loaded with `load`.
@@ -287,22 +306,54 @@ pub unsafe extern "C" fn verilogae_init_modelcard(lib: *const c_void) -> Modelca
 /// `lib` must be a valid pointer returned by the `load` functions or `dlopen`
 #[no_mangle]
 pub unsafe extern "C" fn verilogae_fun_ptr(lib: *const c_void, fun: *const c_char) -> VaeFun {
+    catch_unwind(|| access_fn(lib, CStr::from_ptr(fun).to_bytes())).unwrap_or(None)
+}
+
+/// Obtains a pointer to the jacobian function of a model function of a VerilogAE model
+/// loaded with `load`. The jacobian functions are only generated if the `derivative_params`
+/// option is set (null otherwise).
+///
+/// # Safety
+///
+/// `lib` must be a valid pointer returned by the `load` functions or `dlopen`
+#[no_mangle]
+pub unsafe extern "C" fn verilogae_jacobian_ptr(
+    lib: *const c_void,
+    fun: *const c_char,
+) -> VaeJacobian {
     catch_unwind(|| {
-        let fun = CStr::from_ptr(fun);
-        let lib = Library::from_raw(lib as _);
-        let res = match lib.get(fun.to_bytes()) {
-            Ok(val) => Some(*val),
-            Err(err) => {
-                eprintln!("error: failed to access {}\n\n{}", fun.to_string_lossy(), err);
-                None
-            }
-        };
-        // forget library so it doesn't get closed
-        std::mem::forget(lib);
-        res
+        let mut sym_name = CStr::from_ptr(fun).to_bytes().to_vec();
+        sym_name.extend_from_slice(b".jacobian");
+        access_fn(lib, &sym_name)
     })
-    .ok()
-    .flatten()
+    .unwrap_or(None)
+}
+
+/// Obtains a pointer to the function that calculates all operating point variables of a
+/// VerilogAE model loaded with `load`.
+///
+/// # Safety
+///
+/// `lib` must be a valid pointer returned by the `load` functions or `dlopen`
+#[no_mangle]
+pub unsafe extern "C" fn verilogae_opvars_ptr(lib: *const c_void) -> VaeOpvars {
+    catch_unwind(|| access_fn(lib, b"opvars.eval")).unwrap_or(None)
+}
+
+/// Obtains a pointer to the function that evaluates the DAE system of a VerilogAE model
+/// loaded with `load`. It has the same signature as the function that calculates the
+/// operating point variables and can be called with `verilogae_call_opvars_parallel`.
+/// Its arguments are described by the globals of a model function with the name
+/// `small_signal`. The outputs are the resistive and reactive residual of each unknown
+/// (`small_signal.unknowns`) followed by the resistive and reactive jacobian entries
+/// (with the rows/columns in `small_signal.jacobian.rows`/`small_signal.jacobian.cols`).
+///
+/// # Safety
+///
+/// `lib` must be a valid pointer returned by the `load` functions or `dlopen`
+#[no_mangle]
+pub unsafe extern "C" fn verilogae_small_signal_ptr(lib: *const c_void) -> VaeOpvars {
+    catch_unwind(|| access_fn(lib, b"small_signal.eval")).unwrap_or(None)
 }
 
 /// # Safety
@@ -310,21 +361,39 @@ pub unsafe extern "C" fn verilogae_fun_ptr(lib: *const c_void, fun: *const c_cha
 #[no_mangle]
 pub unsafe extern "C" fn verilogae_module_name(lib: *const c_void) -> *const c_char {
     catch_unwind(|| {
-        let lib = Library::from_raw(lib as _);
-        let res = match lib.get::<*const *const c_char>("module_name\0".as_bytes()) {
-            Ok(val) => **val,
-            Err(err) => {
-                eprintln!("error: failed to access module_name\n\n{}", err);
-                std::ptr::null()
-            }
-        };
-        // forget library so it doesn't get closed
-        std::mem::forget(lib);
-        res
+        let name = access_ptr::<*const c_char>(lib, b"module_name");
+        if name.is_null() {
+            ptr::null()
+        } else {
+            *name
+        }
     })
     .unwrap_or(ptr::null())
 }
 
+// mark as as sync and send
+// this is technically not save In general but the compiled code is expected to perform only
+// valid operations
+#[derive(Copy, Clone)]
+struct PayLoad {
+    voltages: *mut FatPtr<f64>,
+    currents: *mut FatPtr<f64>,
+    real_params: *mut FatPtr<f64>,
+    int_params: *mut FatPtr<i32>,
+    str_params: *mut *const c_char,
+    real_dep_break: *mut FatPtr<f64>,
+    int_dep_break: *mut FatPtr<i32>,
+    temp: *mut FatPtr<f64>,
+    out: *mut c_void,
+    derivatives: *mut *mut f64,
+}
+
+unsafe impl Sync for PayLoad {}
+unsafe impl Send for PayLoad {}
+
+/// Evaluates `fun` for `cnt` operating points in parallel. Same as
+/// `verilogae_call_fun_parallel_threads` with one thread per logical core.
+///
 /// # Safety
 ///
 /// All required parameters must be initialized appropriately
@@ -341,31 +410,147 @@ pub unsafe extern "C" fn verilogae_call_fun_parallel(
     int_dep_break: *mut FatPtr<i32>,
     temp: *mut FatPtr<f64>,
     out: *mut c_void,
+) -> i32 {
+    verilogae_call_fun_parallel_threads(
+        fun,
+        cnt,
+        0,
+        voltages,
+        currents,
+        real_params,
+        int_params,
+        str_params,
+        real_dep_break,
+        int_dep_break,
+        temp,
+        out,
+    )
+}
+
+/// Evaluates `fun` for `cnt` operating points. The operating points are split into chunks
+/// that are evaluated by `threads` threads in parallel. If `threads` is 0 the number of
+/// threads is chosen automatically (one per logical core).
+///
+/// # Safety
+///
+/// All required parameters must be initialized appropriately
+#[no_mangle]
+pub unsafe extern "C" fn verilogae_call_fun_parallel_threads(
+    fun: VaeFun,
+    cnt: usize,
+    threads: usize,
+    voltages: *mut FatPtr<f64>,
+    currents: *mut FatPtr<f64>,
+    real_params: *mut FatPtr<f64>,
+    int_params: *mut FatPtr<i32>,
+    str_params: *mut *const c_char,
+    real_dep_break: *mut FatPtr<f64>,
+    int_dep_break: *mut FatPtr<i32>,
+    temp: *mut FatPtr<f64>,
+    out: *mut c_void,
 ) -> i32 {
     let fun = match fun {
         Some(fun) => fun,
         None => return -1,
     };
 
-    // mark as as sync and send
-    // this is technically not save In general but the compiled code is expected to perform only
-    // valid operations
-
-    #[derive(Copy, Clone)]
-    struct PayLoad {
-        voltages: *mut FatPtr<f64>,
-        currents: *mut FatPtr<f64>,
-        real_params: *mut FatPtr<f64>,
-        int_params: *mut FatPtr<i32>,
-        str_params: *mut *const c_char,
-        real_dep_break: *mut FatPtr<f64>,
-        int_dep_break: *mut FatPtr<i32>,
-        temp: *mut FatPtr<f64>,
-        out: *mut c_void,
-    }
+    let payload = PayLoad {
+        voltages,
+        currents,
+        real_params,
+        int_params,
+        real_dep_break,
+        int_dep_break,
+        str_params,
+        temp,
+        out,
+        derivatives: ptr::null_mut(),
+    };
+
+    for_each_chunked(cnt, threads, move |i| {
+        let payload = payload;
+        fun(
+            i,
+            payload.voltages,
+            payload.currents,
+            payload.real_params,
+            payload.int_params,
+            payload.str_params,
+            payload.real_dep_break,
+            payload.int_dep_break,
+            payload.temp,
+            payload.out,
+        )
+    });
 
-    unsafe impl Sync for PayLoad {}
-    unsafe impl Send for PayLoad {}
+    0
+}
+
+/// Same as `verilogae_call_fun_parallel` but also calculates the derivatives of the function.
+/// `derivatives` must contain one (possibly null) pointer for each derivative of the function.
+///
+/// # Safety
+///
+/// All required parameters must be initialized appropriately
+#[no_mangle]
+pub unsafe extern "C" fn verilogae_call_jacobian_parallel(
+    fun: VaeJacobian,
+    cnt: usize,
+    voltages: *mut FatPtr<f64>,
+    currents: *mut FatPtr<f64>,
+    real_params: *mut FatPtr<f64>,
+    int_params: *mut FatPtr<i32>,
+    str_params: *mut *const c_char,
+    real_dep_break: *mut FatPtr<f64>,
+    int_dep_break: *mut FatPtr<i32>,
+    temp: *mut FatPtr<f64>,
+    out: *mut c_void,
+    derivatives: *mut *mut f64,
+) -> i32 {
+    verilogae_call_jacobian_parallel_threads(
+        fun,
+        cnt,
+        0,
+        voltages,
+        currents,
+        real_params,
+        int_params,
+        str_params,
+        real_dep_break,
+        int_dep_break,
+        temp,
+        out,
+        derivatives,
+    )
+}
+
+/// Same as `verilogae_call_fun_parallel_threads` but also calculates the derivatives of the
+/// function. `derivatives` must contain one (possibly null) pointer for each derivative of
+/// the function.
+///
+/// # Safety
+///
+/// All required parameters must be initialized appropriately
+#[no_mangle]
+pub unsafe extern "C" fn verilogae_call_jacobian_parallel_threads(
+    fun: VaeJacobian,
+    cnt: usize,
+    threads: usize,
+    voltages: *mut FatPtr<f64>,
+    currents: *mut FatPtr<f64>,
+    real_params: *mut FatPtr<f64>,
+    int_params: *mut FatPtr<i32>,
+    str_params: *mut *const c_char,
+    real_dep_break: *mut FatPtr<f64>,
+    int_dep_break: *mut FatPtr<i32>,
+    temp: *mut FatPtr<f64>,
+    out: *mut c_void,
+    derivatives: *mut *mut f64,
+) -> i32 {
+    let fun = match fun {
+        Some(fun) => fun,
+        None => return -1,
+    };
 
     let payload = PayLoad {
         voltages,
@@ -377,61 +562,201 @@ pub unsafe extern "C" fn verilogae_call_fun_parallel(
         str_params,
         temp,
         out,
+        derivatives,
     };
 
-    rayon_core::scope(|s| {
-        for i in 0..cnt {
-            s.spawn(move |_| {
-                let payload = payload;
-                fun(
-                    i,
-                    payload.voltages,
-                    payload.currents,
-                    payload.real_params,
-                    payload.int_params,
-                    payload.str_params,
-                    payload.real_dep_break,
-                    payload.int_dep_break,
-                    payload.temp,
-                    payload.out,
-                )
-            })
-        }
+    for_each_chunked(cnt, threads, move |i| {
+        let payload = payload;
+        fun(
+            i,
+            payload.voltages,
+            payload.currents,
+            payload.real_params,
+            payload.int_params,
+            payload.str_params,
+            payload.real_dep_break,
+            payload.int_dep_break,
+            payload.temp,
+            payload.out,
+            payload.derivatives,
+        )
     });
 
     0
 }
 
-unsafe fn access_ptr<T>(lib: &Library, sym_name: &[u8]) -> *const T {
-    match access_global(lib, sym_name) {
-        Ok(val) => val,
-        Err(err) => {
-            eprintln!("error: failed to access {}\n\n{}", String::from_utf8_lossy(sym_name), err);
-            ptr::null()
+/// Calculates the operating point variables for `cnt` operating points with `fun` (obtained
+/// from `verilogae_opvars_ptr`). `opvars` must contain one (possibly null) pointer for each
+/// operating point variable. The parallelization is the same as for
+/// `verilogae_call_fun_parallel_threads`.
+///
+/// # Safety
+///
+/// All required parameters must be initialized appropriately
+#[no_mangle]
+pub unsafe extern "C" fn verilogae_call_opvars_parallel(
+    fun: VaeOpvars,
+    cnt: usize,
+    threads: usize,
+    voltages: *mut FatPtr<f64>,
+    currents: *mut FatPtr<f64>,
+    real_params: *mut FatPtr<f64>,
+    int_params: *mut FatPtr<i32>,
+    str_params: *mut *const c_char,
+    temp: *mut FatPtr<f64>,
+    opvars: *mut *mut f64,
+) -> i32 {
+    let fun = match fun {
+        Some(fun) => fun,
+        None => return -1,
+    };
+
+    let payload = PayLoad {
+        voltages,
+        currents,
+        real_params,
+        int_params,
+        real_dep_break: ptr::null_mut(),
+        int_dep_break: ptr::null_mut(),
+        str_params,
+        temp,
+        out: ptr::null_mut(),
+        derivatives: opvars,
+    };
+
+    for_each_chunked(cnt, threads, move |i| {
+        let payload = payload;
+        fun(
+            i,
+            payload.voltages,
+            payload.currents,
+            payload.real_params,
+            payload.int_params,
+            payload.str_params,
+            payload.real_dep_break,
+            payload.int_dep_break,
+            payload.temp,
+            payload.derivatives,
+        )
+    });
+
+    0
+}
+
+/// Operating points are evaluated in chunks of at least this size. Evaluating a single
+/// operating point is usually very cheap so scheduling each of them individually would
+/// add a lot of overhead.
+const MIN_CHUNK_SIZE: usize = 256;
+
+/// Calls `f` for each index in `0..cnt`. The indices are split into contiguous chunks that
+/// are processed by a thread pool with `threads` threads (or the global pool if `threads`
+/// is 0). Each index is passed to `f` exactly once so the results do not depend on the
+/// number of threads.
+fn for_each_chunked(cnt: usize, threads: usize, f: impl Fn(usize) + Sync) {
+    let num_threads = if threads == 0 { rayon_core::current_num_threads() } else { threads };
+    if num_threads <= 1 || cnt <= MIN_CHUNK_SIZE {
+        (0..cnt).for_each(f);
+        return;
+    }
+
+    // create a few chunks per thread so that threads which finish early can steal work
+    let chunk_size = (cnt / (4 * num_threads)).max(MIN_CHUNK_SIZE);
+    let f = &f;
+    let run = || {
+        rayon_core::scope(|s| {
+            for start in (0..cnt).step_by(chunk_size) {
+                let end = cnt.min(start + chunk_size);
+                s.spawn(move |_| (start..end).for_each(f))
+            }
+        })
+    };
+
+    if threads == 0 {
+        run()
+    } else {
+        thread_pool(threads).install(run)
+    }
+}
+
+/// Returns a thread pool with `threads` threads. Only the most recently used pool is kept
+/// (and reused by subsequent calls with the same number of threads), so at most one pool
+/// is idle at any time.
+fn thread_pool(threads: usize) -> Arc<ThreadPool> {
+    static POOL: Mutex<Option<(usize, Arc<ThreadPool>)>> = Mutex::new(None);
+
+    let mut cached = POOL.lock().unwrap_or_else(PoisonError::into_inner);
+    match &*cached {
+        Some((num_threads, pool)) if *num_threads == threads => pool.clone(),
+        _ => {
+            let pool = ThreadPoolBuilder::new()
+                .num_threads(threads)
+                .thread_name(|i| format!("verilogae-eval-{i}"))
+                .build()
+                .expect("failed to spawn threads");
+            let pool = Arc::new(pool);
+            // the previous pool shuts down once all evaluations that use it are finished
+            *cached = Some((threads, pool.clone()));
+            pool
         }
     }
 }
 
-unsafe fn access_val<T: Copy + Default>(lib: &Library, sym_name: &[u8]) -> T {
-    match access_global(lib, sym_name) {
-        Ok(val) => *val,
+/// Models compiled with `verilogae_load_jit`. The handle of such a model is the address of
+/// its [`Jit`], all other handles are libraries opened with `dlopen`.
+// the JITs are boxed so that their address does not change
+#[allow(clippy::vec_box)]
+static JITS: Mutex<Vec<Box<Jit>>> = Mutex::new(Vec::new());
+
+fn jit_handle(jit: &Jit) -> *const c_void {
+    jit as *const Jit as *const c_void
+}
+
+/// Returns the address of the symbol `sym_name` of a model loaded with `verilogae_load`,
+/// `verilogae_load_jit` or `dlopen`.
+unsafe fn lookup(lib: *const c_void, sym_name: &[u8]) -> Result<*mut c_void, String> {
+    let sym_name = sym_name.strip_suffix(b"\0").unwrap_or(sym_name);
+    let jits = JITS.lock().unwrap_or_else(PoisonError::into_inner);
+    if let Some(jit) = jits.iter().find(|jit| jit_handle(jit) == lib) {
+        let sym_name = std::str::from_utf8(sym_name).map_err(|err| err.to_string())?;
+        return jit.lookup(sym_name).map_err(|err| err.to_string());
+    }
+    drop(jits);
+
+    let lib = Library::from_raw(lib as _);
+    let res = lib.get::<*mut c_void>(sym_name).map(|val| *val).map_err(|err| err.to_string());
+    // forget library so it doesn't get closed
+    std::mem::forget(lib);
+    res
+}
+
+unsafe fn access_ptr<T>(lib: *const c_void, sym_name: &[u8]) -> *const T {
+    match lookup(lib, sym_name) {
+        Ok(val) => val as *const T,
         Err(err) => {
             eprintln!("error: failed to access {}\n\n{}", String::from_utf8_lossy(sym_name), err);
-            T::default()
+            ptr::null()
         }
     }
 }
 
-// false positive
-#[allow(clippy::explicit_auto_deref)]
-unsafe fn access_global<'a, T>(
-    lib: &'a Library,
-    sym_name: &[u8],
-) -> Result<&'a T, libloading::Error> {
-    lib.get(sym_name).map(|val| {
-        let val: &'a T = *val;
-        val
-    })
+unsafe fn access_val<T: Copy + Default>(lib: *const c_void, sym_name: &[u8]) -> T {
+    let val = access_ptr::<T>(lib, sym_name);
+    if val.is_null() {
+        T::default()
+    } else {
+        *val
+    }
+}
+
+/// Looks up a function, `F` must be an `Option` of a function pointer.
+unsafe fn access_fn<F: Copy + Default>(lib: *const c_void, sym_name: &[u8]) -> F {
+    debug_assert_eq!(std::mem::size_of::<F>(), std::mem::size_of::<*mut c_void>());
+    let fun = access_ptr::<c_void>(lib, sym_name);
+    if fun.is_null() {
+        F::default()
+    } else {
+        std::mem::transmute_copy(&fun)
+    }
 }
 
 #[no_mangle]
@@ -594,3 +919,64 @@ pub unsafe extern "C" fn verilogae_load(
     }
     ptr::null()
 }
+
+/// Same as `verilogae_load` but compiles the model in memory with a JIT instead of
+/// building (and caching) a shared library. The returned handle can be used with all
+/// functions that accept a handle returned by `verilogae_load`. It must be freed with
+/// `verilogae_unload` (instead of `dlclose`).
+///
+/// # Safety
+/// * path must be valid for reads
+/// * opts must be valid for reads or null
+/// * opts must only contain valid data
+#[no_mangle]
+pub unsafe extern "C" fn verilogae_load_jit(
+    path: Slice<u8>,
+    full_compile: bool,
+    opts: *const Opts,
+) -> *const c_void {
+    let path = path.to_path();
+    let opts_;
+
+    let opts = if opts.is_null() {
+        opts_ = Opts::default();
+        &opts_
+    } else {
+        &*opts
+    };
+
+    let res = std::panic::catch_unwind(|| load_jit(&path, full_compile, opts));
+
+    if let Ok(res) = res {
+        match res {
+            Ok(jit) => return register_jit(jit),
+            Err(err) => eprintln!("{:?}", err),
+        }
+    }
+    ptr::null()
+}
+
+/// Returns a handle for a model compiled with a JIT that can be passed to all functions
+/// that accept a handle returned by `verilogae_load`.
+pub(crate) fn register_jit(jit: Jit) -> *const c_void {
+    let jit = Box::new(jit);
+    let handle = jit_handle(&jit);
+    JITS.lock().unwrap_or_else(PoisonError::into_inner).push(jit);
+    handle
+}
+
+/// Frees a model loaded with `verilogae_load` or `verilogae_load_jit`.
+/// None of the functions of the model may be used afterwards.
+///
+/// # Safety
+/// `lib` must be a valid pointer returned by the `load` functions
+#[no_mangle]
+pub unsafe extern "C" fn verilogae_unload(lib: *const c_void) {
+    let mut jits = JITS.lock().unwrap_or_else(PoisonError::into_inner);
+    if let Some(pos) = jits.iter().position(|jit| jit_handle(jit) == lib) {
+        drop(jits.swap_remove(pos));
+        return;
+    }
+    drop(jits);
+    drop(Library::from_raw(lib as _))
+}
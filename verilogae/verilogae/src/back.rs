@@ -1,19 +1,25 @@
 use std::borrow::Borrow;
+use std::sync::Mutex;
 
+use ahash::AHashMap;
 use camino::Utf8Path;
-use hir::Type;
+use hir::{Parameter, Type, Variable};
 use hir_lower::{CallBackKind, CurrentKind, HirInterner, ParamInfoKind, ParamKind, PlaceKind};
 use lasso::Rodeo;
 use llvm::{OptLevel, UNNAMED};
-use mir::{ControlFlowGraph, FuncRef, Function};
-use mir_llvm::{Builder, BuilderVal, CallbackFun, CodegenCx, LLVMBackend};
+use mir::{Block, ControlFlowGraph, FuncRef, Function, Param, Value, F_ZERO};
+use mir_llvm::{Builder, BuilderVal, CallbackFun, CodegenCx, LLVMBackend, MemLoc, ModuleLlvm};
+use sim_back::dae::SimUnknown;
+use sim_back::init::CacheSlot;
+use sim_back::{CompiledModule, SimUnknownKind};
 use stdx::iter::multiunzip;
 use typed_index_collections::TiVec;
 use typed_indexmap::TiSet;
 
 use crate::compiler_db::{
-    current_name, voltage_name, CompilationDB, FuncSpec, InternedModel, ModelInfo,
+    current_name, unknown_name, voltage_name, CompilationDB, FuncSpec, InternedModel, ModelInfo,
 };
+use crate::middle::FuncDerivatives;
 
 pub fn sim_param_stub<'ll>(cx: &CodegenCx<'_, 'll>) -> CallbackFun<'ll> {
     cx.const_callback(&[cx.ty_ptr()], cx.const_real(0.0))
@@ -77,6 +83,126 @@ pub fn stub_callbacks<'ll>(
         .collect()
 }
 
+/// Callbacks for the functions of the DAE system. Limiting is always disabled so the limit
+/// functions simply return the unlimited value.
+fn dae_callbacks<'ll>(
+    cb: &TiSet<FuncRef, CallBackKind>,
+    cx: &CodegenCx<'_, 'll>,
+) -> TiVec<FuncRef, Option<CallbackFun<'ll>>> {
+    let mut callbacks = stub_callbacks(cb, cx);
+    for (func, kind) in cb.iter_enumerated() {
+        match *kind {
+            CallBackKind::BuiltinLimit { num_args, .. } => {
+                let args = vec![cx.ty_double(); num_args as usize];
+                callbacks[func] = Some(cx.const_return(&args, 0));
+            }
+            CallBackKind::StoreLimit(_) => {
+                callbacks[func] = Some(cx.const_return(&[cx.ty_double()], 0));
+            }
+            _ => (),
+        }
+    }
+    callbacks
+}
+
+/// The arguments of the function generated for the DAE system.
+struct DaeInputs<'a, 'll> {
+    unknowns: &'a TiSet<SimUnknown, SimUnknownKind>,
+    unknown_vals: TiVec<SimUnknown, &'ll llvm::Value>,
+    params: AHashMap<Parameter, &'ll llvm::Value>,
+    temperature: &'ll llvm::Value,
+}
+
+impl<'ll> DaeInputs<'_, 'll> {
+    /// Returns the value of `unknown`, unknowns that are not part of the DAE system
+    /// (like the ground node) are always zero.
+    fn unknown(&self, cx: &CodegenCx<'_, 'll>, unknown: SimUnknownKind) -> &'ll llvm::Value {
+        match self.unknowns.index(&unknown) {
+            Some(unknown) => self.unknown_vals[unknown],
+            None => cx.const_real(0.0),
+        }
+    }
+
+    /// Maps the parameters of `intern` (the init or the eval function of the DAE system) to
+    /// their values. Must be called in the entry block so that the values are available in
+    /// both functions.
+    unsafe fn param_vals(
+        &self,
+        builder: &Builder<'_, '_, 'll>,
+        intern: &HirInterner,
+    ) -> TiVec<Param, BuilderVal<'ll>> {
+        let cx = builder.cx;
+        intern
+            .params
+            .raw
+            .keys()
+            .map(|kind| {
+                let val = match *kind {
+                    ParamKind::Param(param) => match self.params.get(&param) {
+                        Some(&val) => val,
+                        None => return BuilderVal::Undef,
+                    },
+                    ParamKind::Voltage { hi, lo } => {
+                        let hi = self.unknown(cx, SimUnknownKind::KirchoffLaw(hi));
+                        if let Some(lo) = lo {
+                            let lo = self.unknown(cx, SimUnknownKind::KirchoffLaw(lo));
+                            llvm::LLVMBuildFSub(builder.llbuilder, hi, lo, UNNAMED)
+                        } else {
+                            hi
+                        }
+                    }
+                    ParamKind::Current(CurrentKind::Port(_)) => cx.const_real(0.0),
+                    ParamKind::Current(kind) => self.unknown(cx, SimUnknownKind::Current(kind)),
+                    ParamKind::ImplicitUnknown(equation) => {
+                        self.unknown(cx, SimUnknownKind::Implicit(equation))
+                    }
+                    ParamKind::Temperature => self.temperature,
+                    ParamKind::ParamGiven { .. } | ParamKind::PortConnected { .. } => {
+                        cx.const_bool(true)
+                    }
+                    ParamKind::ParamSysFun(param) => cx.const_real(param.default_value()),
+                    ParamKind::HiddenState(_) => return BuilderVal::Undef,
+                    // the reactive contributions are required for the capacitance matrix
+                    ParamKind::EnableIntegration => cx.const_bool(true),
+                    ParamKind::EnableLim => cx.const_bool(false),
+                    ParamKind::Abstime | ParamKind::PrevState(_) | ParamKind::NewState(_) => {
+                        cx.const_real(0.0)
+                    }
+                };
+                val.into()
+            })
+            .collect()
+    }
+}
+
+/// Where the code generated for a LLVM module is written to.
+#[derive(Clone, Copy)]
+pub(crate) enum Output<'a> {
+    Object(&'a Utf8Path),
+    /// LLVM bitcode that is loaded into a JIT
+    Bitcode(&'a Mutex<Vec<u8>>),
+}
+
+impl Output<'_> {
+    fn write(self, module: &ModuleLlvm) {
+        match self {
+            Output::Object(dst) => {
+                module.emit_object(dst.as_ref()).expect("code generation failed!")
+            }
+            Output::Bitcode(dst) => *dst.lock().unwrap() = module.to_bitcode(),
+        }
+    }
+}
+
+/// Returns the block the function returns from.
+fn exit_block(func: &Function, cfg: &ControlFlowGraph) -> Block {
+    cfg.postorder(func)
+        .find(|bb| {
+            func.layout.last_inst(*bb).map_or(true, |term| !func.dfg.insts[term].is_terminator())
+        })
+        .unwrap()
+}
+
 pub struct CodegenCtx<'a, 't> {
     pub model_info: &'a ModelInfo,
     pub llbackend: &'a LLVMBackend<'t>,
@@ -90,13 +216,84 @@ struct Codegen<'a, 'b, 'll> {
     intern: &'a HirInterner,
     builder: &'b mut Builder<'a, 'a, 'll>,
     func: &'a Function,
-    spec: &'a FuncSpec,
+    /// prefix of the exported globals
+    prefix: &'a str,
+    dependency_breaking: &'a [Variable],
+    /// whether the names of the arguments are exported, only one of the functions that
+    /// share the same arguments exports them
+    export_args: bool,
 }
 
 impl<'ll> Codegen<'_, '_, 'll> {
+    /// Reads the arguments (shared by all generated functions) of `llfun` into the
+    /// parameters of the MIR function.
+    unsafe fn read_args(&mut self, llfun: &'ll llvm::Value, offset: &'ll llvm::Value) {
+        let (cx, intern, func) = (self.builder.cx, self.intern, self.func);
+        let true_val = cx.const_bool(true);
+        self.builder.params = intern
+            .params
+            .raw
+            .iter()
+            .map(|(kind, val)| {
+                if func.dfg.value_dead(*val) {
+                    return BuilderVal::Undef;
+                }
+
+                let val = match kind {
+                    ParamKind::Param(_)
+                    | ParamKind::Voltage { .. }
+                    | ParamKind::Current(_)
+                    | ParamKind::HiddenState(_) => return BuilderVal::Undef,
+                    ParamKind::Temperature => {
+                        let temperature = llvm::LLVMGetParam(llfun, 8);
+                        self.read_fat_ptr_at(0, offset, temperature, cx.ty_double())
+                    }
+                    ParamKind::ParamGiven { .. } | ParamKind::PortConnected { .. } => true_val,
+                    ParamKind::ParamSysFun(param) => cx.const_real(param.default_value()),
+                    ParamKind::ImplicitUnknown(_)
+                    | ParamKind::Abstime
+                    | ParamKind::PrevState(_)
+                    | ParamKind::NewState(_) => cx.const_real(0.0),
+                    ParamKind::EnableIntegration | ParamKind::EnableLim => cx.const_bool(false),
+                };
+
+                val.into()
+            })
+            .collect();
+
+        self.read_voltages(offset, llvm::LLVMGetParam(llfun, 1));
+        self.read_currents(offset, llvm::LLVMGetParam(llfun, 2));
+        self.read_params(offset, llvm::LLVMGetParam(llfun, 3), Type::Real);
+        self.read_params(offset, llvm::LLVMGetParam(llfun, 4), Type::Integer);
+        self.read_str_params(llvm::LLVMGetParam(llfun, 5));
+        self.read_depbreak(offset, llvm::LLVMGetParam(llfun, 6), Type::Real);
+        self.read_depbreak(offset, llvm::LLVMGetParam(llfun, 7), Type::Integer);
+    }
+
+    /// Returns pointers to the `offset`th element of the `cnt` arrays in `ptrs`. If an array is
+    /// null (not requested by the caller) values are written to a dummy instead.
+    unsafe fn out_ptrs(
+        &self,
+        ptrs: &'ll llvm::Value,
+        offset: &'ll llvm::Value,
+        cnt: usize,
+    ) -> Vec<&'ll llvm::Value> {
+        let builder = &self.builder;
+        let cx = builder.cx;
+        let dummy = builder.alloca(cx.ty_double());
+        (0..cnt)
+            .map(|i| {
+                let ptr = builder.gep(cx.ty_ptr(), ptrs, &[cx.const_usize(i)]);
+                let ptr = builder.load(cx.ty_ptr(), ptr);
+                let is_null = builder.is_null_ptr(ptr);
+                let dst = builder.gep(cx.ty_double(), ptr, &[offset]);
+                builder.select(is_null, dummy, dst)
+            })
+            .collect()
+    }
+
     unsafe fn read_depbreak(&mut self, offset: &'ll llvm::Value, ptr: &'ll llvm::Value, ty: Type) {
-        let vars =
-            self.spec.dependency_breaking.iter().copied().filter(|var| var.ty(self.db) == ty);
+        let vars = self.dependency_breaking.iter().copied().filter(|var| var.ty(self.db) == ty);
         let llty = lltype(&ty, self.builder.cx);
         for (i, var) in vars.clone().enumerate() {
             if let Some(id) = self.intern.params.index(&ParamKind::HiddenState(var)) {
@@ -104,7 +301,7 @@ impl<'ll> Codegen<'_, '_, 'll> {
             }
         }
 
-        let global_name = format!("{}.depbreak.{}", self.spec.prefix, ty);
+        let global_name = format!("{}.depbreak.{}", self.prefix, ty);
         let names = vars.clone().map(|var| &*self.model_info.var_names[&var]);
         self.export_names(names, &global_name);
     }
@@ -127,7 +324,7 @@ impl<'ll> Codegen<'_, '_, 'll> {
             self.builder.params[id] = self.builder.load(self.builder.cx.ty_ptr(), ptr).into();
         }
 
-        let global_name = format!("{}.params.{}", self.spec.prefix, Type::String);
+        let global_name = format!("{}.params.{}", self.prefix, Type::String);
         let names = params.map(|(_, param)| &*self.model_info.params[&param].name);
         self.export_names(names, &global_name);
     }
@@ -146,7 +343,7 @@ impl<'ll> Codegen<'_, '_, 'll> {
             self.builder.params[id] = self.read_fat_ptr_at(i, offset, ptr, llty).into();
         }
 
-        let global_name = format!("{}.params.{}", self.spec.prefix, ty);
+        let global_name = format!("{}.params.{}", self.prefix, ty);
         let names = params.clone().map(|(_, param)| &*self.model_info.params[&param].name);
         self.export_names(names, &global_name);
     }
@@ -180,16 +377,18 @@ impl<'ll> Codegen<'_, '_, 'll> {
                 self.read_fat_ptr_at(i, offset, ptr, self.builder.cx.ty_double()).into();
         }
 
-        let global_name = format!("{}.voltages.default", self.spec.prefix);
-        self.builder.cx.export_array(
-            &global_name,
-            self.builder.cx.ty_double(),
-            &default_vals,
-            true,
-            true,
-        );
+        if self.export_args {
+            let global_name = format!("{}.voltages.default", self.prefix);
+            self.builder.cx.export_array(
+                &global_name,
+                self.builder.cx.ty_double(),
+                &default_vals,
+                true,
+                true,
+            );
+        }
 
-        let global_name = format!("{}.voltages", self.spec.prefix);
+        let global_name = format!("{}.voltages", self.prefix);
         let names = voltages.map(|(_, (hi, lo))| voltage_name(self.db, hi, lo));
         self.export_names(names, &global_name);
     }
@@ -225,21 +424,123 @@ impl<'ll> Codegen<'_, '_, 'll> {
                 self.read_fat_ptr_at(i, offset, ptr, self.builder.cx.ty_double()).into();
         }
 
-        let global_name = format!("{}.currents.default", self.spec.prefix);
-        self.builder.cx.export_array(
-            &global_name,
-            self.builder.cx.ty_double(),
-            &default_vals,
-            true,
-            true,
-        );
+        if self.export_args {
+            let global_name = format!("{}.currents.default", self.prefix);
+            self.builder.cx.export_array(
+                &global_name,
+                self.builder.cx.ty_double(),
+                &default_vals,
+                true,
+                true,
+            );
+        }
 
-        let global_name = format!("{}.currents", self.spec.prefix);
+        let global_name = format!("{}.currents", self.prefix);
         let names = voltages.map(|(_, kind)| current_name(self.db, kind));
         self.export_names(names, &global_name);
     }
 
+    /// Reads the arguments of the function generated for the DAE system. The unknowns are read
+    /// from the voltages (node potentials and implicit unknowns) and currents (branch currents).
+    unsafe fn read_dae_inputs<'a>(
+        &mut self,
+        llfun: &'ll llvm::Value,
+        offset: &'ll llvm::Value,
+        unknowns: &'a TiSet<SimUnknown, SimUnknownKind>,
+        params: &[Parameter],
+    ) -> DaeInputs<'a, 'll> {
+        let cx = self.builder.cx;
+        let is_current = |kind: &SimUnknownKind| matches!(kind, SimUnknownKind::Current(_));
+
+        let voltages = llvm::LLVMGetParam(llfun, 1);
+        let currents = llvm::LLVMGetParam(llfun, 2);
+        let mut unknown_vals = TiVec::with_capacity(unknowns.len());
+        let (mut voltage_pos, mut current_pos) = (0, 0);
+        for kind in unknowns.iter() {
+            let (ptr, pos) = if is_current(kind) {
+                (currents, &mut current_pos)
+            } else {
+                (voltages, &mut voltage_pos)
+            };
+            unknown_vals.push(self.read_fat_ptr_at(*pos, offset, ptr, cx.ty_double()));
+            *pos += 1;
+        }
+
+        // unknowns that are not specified are grounded
+        for (currents, name, cnt) in
+            [(false, "voltages", voltage_pos), (true, "currents", current_pos)]
+        {
+            let names = unknowns
+                .iter()
+                .filter(|kind| is_current(kind) == currents)
+                .map(|kind| unknown_name(self.db, *kind));
+            self.export_names(names, &format!("{}.{}", self.prefix, name));
+            let global_name = format!("{}.{}.default", self.prefix, name);
+            let defaults = vec![cx.const_real(0.0); cnt];
+            cx.export_array(&global_name, cx.ty_double(), &defaults, true, true);
+        }
+
+        let mut param_vals = AHashMap::new();
+        for (arg, ty) in [(3, Type::Real), (4, Type::Integer), (5, Type::String)] {
+            let ptr = llvm::LLVMGetParam(llfun, arg);
+            let params: Vec<_> =
+                params.iter().copied().filter(|param| param.ty(self.db) == ty).collect();
+            for (i, param) in params.iter().enumerate() {
+                let val = if ty == Type::String {
+                    let ptr = self.builder.gep(cx.ty_ptr(), ptr, &[cx.const_usize(i)]);
+                    self.builder.load(cx.ty_ptr(), ptr)
+                } else {
+                    self.read_fat_ptr_at(i, offset, ptr, lltype(&ty, cx))
+                };
+                param_vals.insert(*param, val);
+            }
+
+            let global_name = format!("{}.params.{}", self.prefix, ty);
+            let names = params.iter().map(|param| &*self.model_info.params[param].name);
+            self.export_names(names, &global_name);
+        }
+
+        // the DAE system has no dependency breaking
+        self.read_depbreak(offset, llvm::LLVMGetParam(llfun, 6), Type::Real);
+        self.read_depbreak(offset, llvm::LLVMGetParam(llfun, 7), Type::Integer);
+
+        let temperature = llvm::LLVMGetParam(llfun, 8);
+        let temperature = self.read_fat_ptr_at(0, offset, temperature, cx.ty_double());
+
+        DaeInputs { unknowns, unknown_vals, params: param_vals, temperature }
+    }
+
+    /// Returns the derivatives calculated by the jacobian function: the derivatives by all
+    /// voltages the function depends on followed by the derivatives by the selected parameters.
+    fn derivatives(&mut self, derivatives: &FuncDerivatives) -> Vec<Value> {
+        let voltages = self.intern.live_params(&self.func.dfg).filter_map(|(_, kind, _)| {
+            if let ParamKind::Voltage { hi, lo } = *kind {
+                Some((hi, lo))
+            } else {
+                None
+            }
+        });
+
+        let mut names = Vec::new();
+        let mut vals = Vec::new();
+        for (hi, lo) in voltages {
+            names.push(voltage_name(self.db, hi, lo));
+            vals.push(derivatives.voltages.get(&(hi, lo)).copied().unwrap_or(F_ZERO));
+        }
+        for (param, val) in &derivatives.params {
+            names.push(self.model_info.params[param].name.to_string());
+            vals.push(*val);
+        }
+
+        let global_name = format!("{}.derivatives", self.prefix);
+        self.export_names(names.into_iter(), &global_name);
+        vals
+    }
+
     fn export_names<T: Borrow<str>>(&mut self, names: impl Iterator<Item = T>, global_name: &str) {
+        if !self.export_args {
+            return;
+        }
         let cx = &mut self.builder.cx;
         let names: Vec<_> = names
             .map(|name| {
@@ -285,6 +586,11 @@ impl<'ll> Codegen<'_, '_, 'll> {
 }
 
 impl CodegenCtx<'_, '_> {
+    /// Generates the function `{prefix}` that calculates the value of `spec`. If `derivatives`
+    /// are requested, the function `{prefix}.jacobian` is generated as well. It also calculates
+    /// the derivatives and has an additional argument: an array with one pointer per
+    /// derivative that the derivative is written to (null pointers are skipped).
+    #[allow(clippy::too_many_arguments)]
     pub(crate) fn gen_func_obj(
         &self,
         db: &CompilationDB,
@@ -292,7 +598,8 @@ impl CodegenCtx<'_, '_> {
         func: &Function,
         cfg: &ControlFlowGraph,
         intern: &HirInterner,
-        dst: &Utf8Path,
+        derivatives: Option<&FuncDerivatives>,
+        dst: Output,
     ) {
         let module =
             unsafe { self.llbackend.new_module(&spec.var.name(db), self.opt_lvl).unwrap() };
@@ -300,129 +607,334 @@ impl CodegenCtx<'_, '_> {
 
         let ret_ty = lltype(&spec.var.ty(db), &cx);
 
-        let fun_ty = cx.ty_func(
-            &[
-                cx.ty_size(), // offset
-                cx.ty_ptr(),  // voltages
-                cx.ty_ptr(),  // curents
-                cx.ty_ptr(),  // real paras
-                cx.ty_ptr(),  // int paras
-                cx.ty_ptr(),  // str paras
-                cx.ty_ptr(),  // real dependency_breaking
-                cx.ty_ptr(),  // int dependency_breaking
-                cx.ty_ptr(),  // temperature
-                cx.ty_ptr(),  // ret
-            ],
-            cx.ty_void(),
-        );
-        let llfun = cx.declare_ext_fn(&spec.prefix, fun_ty);
+        let args = [
+            cx.ty_size(), // offset
+            cx.ty_ptr(),  // voltages
+            cx.ty_ptr(),  // curents
+            cx.ty_ptr(),  // real paras
+            cx.ty_ptr(),  // int paras
+            cx.ty_ptr(),  // str paras
+            cx.ty_ptr(),  // real dependency_breaking
+            cx.ty_ptr(),  // int dependency_breaking
+            cx.ty_ptr(),  // temperature
+            cx.ty_ptr(),  // ret
+            cx.ty_ptr(),  // derivatives (only jacobian)
+        ];
+        let fun_ty = cx.ty_func(&args[..10], cx.ty_void());
+        let mut funs = Vec::with_capacity(2);
+        if let Some(derivatives) = derivatives {
+            let jacobian_ty = cx.ty_func(&args, cx.ty_void());
+            let llfun = cx.declare_ext_fn(&format!("{}.jacobian", spec.prefix), jacobian_ty);
+            funs.push((llfun, Some(derivatives)));
+        }
+        funs.push((cx.declare_ext_fn(&spec.prefix, fun_ty), None));
+
+        // both functions are generated from the same MIR (the derivatives are removed from the
+        // value function by LLVM), so they have the same arguments that are only exported once
+        for (i, (llfun, derivatives)) in funs.into_iter().enumerate() {
+            // setup builder
+            let mut builder = Builder::new(&cx, func, llfun);
+
+            let mut codegen = Codegen {
+                db,
+                model_info: self.model_info,
+                intern,
+                builder: &mut builder,
+                func,
+                prefix: &spec.prefix,
+                dependency_breaking: &spec.dependency_breaking,
+                export_args: i == 0,
+            };
 
-        // setup builder
-        let mut builder = Builder::new(&cx, func, llfun);
+            let offset = unsafe { llvm::LLVMGetParam(llfun, 0) };
+            unsafe { codegen.read_args(llfun, offset) };
 
-        let mut codegen =
-            Codegen { db, model_info: self.model_info, intern, builder: &mut builder, func, spec };
+            let (derivative_vals, derivative_dsts) = match derivatives {
+                Some(derivatives) => {
+                    let vals = codegen.derivatives(derivatives);
+                    let ptrs = unsafe { llvm::LLVMGetParam(llfun, 10) };
+                    let dsts = unsafe { codegen.out_ptrs(ptrs, offset, vals.len()) };
+                    (vals, dsts)
+                }
+                None => {
+                    let global_name = format!("{}.derivatives", spec.prefix);
+                    codegen.export_names(std::iter::empty::<&str>(), &global_name);
+                    (Vec::new(), Vec::new())
+                }
+            };
 
-        // read parameters
+            // setup callbacks
 
-        let offset = unsafe { llvm::LLVMGetParam(llfun, 0) };
+            codegen.builder.callbacks = stub_callbacks(&intern.callbacks, codegen.builder.cx);
+            let exit_bb = exit_block(func, cfg);
 
-        let true_val = cx.const_bool(true);
-        codegen.builder.params = intern
-            .params
-            .raw
-            .iter()
-            .map(|(kind, val)| {
-                if func.dfg.value_dead(*val) {
-                    return BuilderVal::Undef;
-                }
+            unsafe {
+                // the actual compiled function
+                builder.build_consts();
+                builder.build_func();
 
-                let val = match kind {
-                    ParamKind::Param(_)
-                    | ParamKind::Voltage { .. }
-                    | ParamKind::Current(_)
-                    | ParamKind::HiddenState(_) => return BuilderVal::Undef,
-                    ParamKind::Temperature => unsafe {
-                        let temperature = llvm::LLVMGetParam(llfun, 8);
-                        codegen.read_fat_ptr_at(0, offset, temperature, cx.ty_double())
-                    },
-                    ParamKind::ParamGiven { .. } | ParamKind::PortConnected { .. } => true_val,
-                    ParamKind::ParamSysFun(param) => {
-                        codegen.builder.cx.const_real(param.default_value())
-                    }
-                    ParamKind::ImplicitUnknown(_)
-                    | ParamKind::Abstime
-                    | ParamKind::PrevState(_)
-                    | ParamKind::NewState(_) => codegen.builder.cx.const_real(0.0),
-                    ParamKind::EnableIntegration | ParamKind::EnableLim => {
-                        codegen.builder.cx.const_bool(false)
-                    }
-                };
+                // write the return value
+                builder.select_bb(exit_bb);
 
-                val.into()
-            })
-            .collect();
+                let out = llvm::LLVMGetParam(llfun, 9);
+                let out = builder.gep(ret_ty, out, &[offset]);
 
-        let voltages = unsafe { llvm::LLVMGetParam(llfun, 1) };
-        unsafe { codegen.read_voltages(offset, voltages) };
+                let ret_val = intern.outputs[&PlaceKind::Var(spec.var)].unwrap();
+                let ret_val = builder.values[ret_val].get(&builder);
 
-        let currents = unsafe { llvm::LLVMGetParam(llfun, 2) };
-        unsafe { codegen.read_currents(offset, currents) };
+                builder.store(out, ret_val);
 
-        let real_params = unsafe { llvm::LLVMGetParam(llfun, 3) };
-        unsafe { codegen.read_params(offset, real_params, Type::Real) };
+                for (&dst, val) in derivative_dsts.iter().zip(derivative_vals) {
+                    let val = builder.values[val].get(&builder);
+                    builder.store(dst, val);
+                }
 
-        let int_params = unsafe { llvm::LLVMGetParam(llfun, 4) };
-        unsafe { codegen.read_params(offset, int_params, Type::Integer) };
+                builder.ret_void();
+            }
+        }
 
-        let str_params = unsafe { llvm::LLVMGetParam(llfun, 5) };
-        unsafe { codegen.read_str_params(str_params) };
+        // build object file
+        debug_assert!(module.verify_and_print(), "Invalid code generated");
+        module.optimize();
 
-        let real_dep_break = unsafe { llvm::LLVMGetParam(llfun, 6) };
-        unsafe { codegen.read_depbreak(offset, real_dep_break, Type::Real) };
+        dst.write(&module)
+    }
 
-        let int_dep_break = unsafe { llvm::LLVMGetParam(llfun, 7) };
-        unsafe { codegen.read_depbreak(offset, int_dep_break, Type::Integer) };
+    /// Generates the function `opvars.eval` that calculates all operating point variables.
+    /// It has the same arguments as a jacobian function (see [`Self::gen_func_obj`]) except
+    /// that it has no return value: the last argument contains one pointer for each entry in
+    /// the `opvars` global that the value is written to (null pointers are skipped). The
+    /// globals that describe the arguments are prefixed with `opvars`.
+    pub(crate) fn gen_opvars_obj(
+        &self,
+        db: &CompilationDB,
+        func: &Function,
+        cfg: &ControlFlowGraph,
+        intern: &HirInterner,
+        opvars: &[Option<Value>],
+        dst: Output,
+    ) {
+        let module = unsafe { self.llbackend.new_module("opvars", self.opt_lvl).unwrap() };
+        let cx = unsafe { self.llbackend.new_ctx(self.literals, &module) };
 
-        // setup callbacks
+        let args = [
+            cx.ty_size(), // offset
+            cx.ty_ptr(),  // voltages
+            cx.ty_ptr(),  // curents
+            cx.ty_ptr(),  // real paras
+            cx.ty_ptr(),  // int paras
+            cx.ty_ptr(),  // str paras
+            cx.ty_ptr(),  // real dependency_breaking
+            cx.ty_ptr(),  // int dependency_breaking
+            cx.ty_ptr(),  // temperature
+            cx.ty_ptr(),  // opvars
+        ];
+        let fun_ty = cx.ty_func(&args, cx.ty_void());
+        let llfun = cx.declare_ext_fn("opvars.eval", fun_ty);
 
-        codegen.builder.callbacks = stub_callbacks(&intern.callbacks, codegen.builder.cx);
-        let postorder: Vec<_> = cfg.postorder(func).collect();
+        let mut builder = Builder::new(&cx, func, llfun);
+        let mut codegen = Codegen {
+            db,
+            model_info: self.model_info,
+            intern,
+            builder: &mut builder,
+            func,
+            prefix: "opvars",
+            dependency_breaking: &[],
+            export_args: true,
+        };
 
-        let exit_bb = *postorder
-            .iter()
-            .find(|bb| {
-                func.layout
-                    .last_inst(**bb)
-                    .map_or(true, |term| !func.dfg.insts[term].is_terminator())
-            })
-            .unwrap();
+        let offset = unsafe { llvm::LLVMGetParam(llfun, 0) };
+        unsafe { codegen.read_args(llfun, offset) };
+
+        // the opvars can not be differentiated
+        codegen.export_names(std::iter::empty::<&str>(), "opvars.derivatives");
+
+        let opvar_ptrs = unsafe { llvm::LLVMGetParam(llfun, 9) };
+        let opvar_dsts = unsafe { codegen.out_ptrs(opvar_ptrs, offset, opvars.len()) };
+
+        codegen.builder.callbacks = stub_callbacks(&intern.callbacks, codegen.builder.cx);
+        let exit_bb = exit_block(func, cfg);
 
         unsafe {
-            // the actual compiled function
             builder.build_consts();
             builder.build_func();
 
-            // write the return value
             builder.select_bb(exit_bb);
-
-            let out = llvm::LLVMGetParam(llfun, 9);
-            let out = builder.gep(ret_ty, out, &[offset]);
-
-            let ret_val = intern.outputs[&PlaceKind::Var(spec.var)].unwrap();
-            let ret_val = builder.values[ret_val].get(&builder);
-
-            builder.store(out, ret_val);
-
+            // string variables can not be represented as reals
+            let nan = cx.const_real(f64::NAN);
+            for (&dst, val) in opvar_dsts.iter().zip(opvars) {
+                let val = val.map_or(nan, |val| builder.values[val].get(&builder));
+                builder.store(dst, val);
+            }
             builder.ret_void();
         }
 
-        // build object file
         drop(builder);
         debug_assert!(module.verify_and_print(), "Invalid code generated");
         module.optimize();
 
-        module.emit_object(dst.as_ref()).expect("code generation failed!")
+        dst.write(&module)
+    }
+
+    /// Generates the function `small_signal.eval` that evaluates the DAE system of the module
+    /// (as built by `sim_back`) for the values of its unknowns (listed in the
+    /// `small_signal.unknowns` global). It has the same arguments as the function that
+    /// calculates the operating point variables (see [`Self::gen_opvars_obj`]): node potentials
+    /// and implicit unknowns are passed as voltages and branch currents as currents.
+    ///
+    /// The last argument contains one (possibly null) pointer for each output. The outputs are
+    /// the resistive residuals and the reactive residuals of all unknowns, followed by the
+    /// resistive and the reactive entries of the jacobian. The row and column of each jacobian
+    /// entry are stored in the `small_signal.jacobian.rows` and `small_signal.jacobian.cols`
+    /// globals.
+    pub(crate) fn gen_small_signal_obj(
+        &self,
+        db: &CompilationDB,
+        module: &CompiledModule,
+        dst: Output,
+    ) {
+        let llmodule = unsafe { self.llbackend.new_module("small_signal", self.opt_lvl).unwrap() };
+        let cx = unsafe { self.llbackend.new_ctx(self.literals, &llmodule) };
+
+        let args = [
+            cx.ty_size(), // offset
+            cx.ty_ptr(),  // voltages
+            cx.ty_ptr(),  // curents
+            cx.ty_ptr(),  // real paras
+            cx.ty_ptr(),  // int paras
+            cx.ty_ptr(),  // str paras
+            cx.ty_ptr(),  // real dependency_breaking
+            cx.ty_ptr(),  // int dependency_breaking
+            cx.ty_ptr(),  // temperature
+            cx.ty_ptr(),  // outputs
+        ];
+        let fun_ty = cx.ty_func(&args, cx.ty_void());
+        let llfun = cx.declare_ext_fn("small_signal.eval", fun_ty);
+
+        let init = &module.init;
+        let dae_system = &module.dae_system;
+        let params: Vec<_> = self
+            .model_info
+            .params
+            .keys()
+            .copied()
+            .filter(|param| {
+                let kind = ParamKind::Param(*param);
+                init.intern.params.index(&kind).is_some()
+                    || module.intern.params.index(&kind).is_some()
+            })
+            .collect();
+
+        // the init function is called first, it calculates the values cached for the eval
+        // function
+        let mut init_builder = Builder::new(&cx, &init.func, llfun);
+        let mut codegen = Codegen {
+            db,
+            model_info: self.model_info,
+            intern: &init.intern,
+            builder: &mut init_builder,
+            func: &init.func,
+            prefix: "small_signal",
+            dependency_breaking: &[],
+            export_args: true,
+        };
+
+        let offset = unsafe { llvm::LLVMGetParam(llfun, 0) };
+        let inputs =
+            unsafe { codegen.read_dae_inputs(llfun, offset, &dae_system.unknowns, &params) };
+        codegen.export_names(std::iter::empty::<&str>(), "small_signal.derivatives");
+
+        let names = dae_system.unknowns.iter().map(|kind| unknown_name(db, *kind));
+        codegen.export_names(names, "small_signal.unknowns");
+        let (rows, cols): (Vec<_>, Vec<_>) = dae_system
+            .jacobian
+            .iter()
+            .map(|entry| {
+                (cx.const_unsigned_int(entry.row.into()), cx.const_unsigned_int(entry.col.into()))
+            })
+            .unzip();
+        cx.export_array("small_signal.jacobian.rows", cx.ty_int(), &rows, true, true);
+        cx.export_array("small_signal.jacobian.cols", cx.ty_int(), &cols, true, true);
+
+        let output_ptrs = unsafe { llvm::LLVMGetParam(llfun, 9) };
+        let output_cnt = 2 * dae_system.unknowns.len() + 2 * dae_system.jacobian.len();
+        let output_dsts = unsafe { codegen.out_ptrs(output_ptrs, offset, output_cnt) };
+
+        let cache_slots: TiVec<CacheSlot, _> = init
+            .cache_slots
+            .raw
+            .values()
+            .map(|ty| {
+                let ty = lltype(ty, &cx);
+                (unsafe { init_builder.alloca(ty) }, ty)
+            })
+            .collect();
+
+        let eval_params = unsafe {
+            init_builder.params = inputs.param_vals(&init_builder, &init.intern);
+            inputs.param_vals(&init_builder, &module.intern)
+        };
+        init_builder.callbacks = dae_callbacks(&init.intern.callbacks, &cx);
+
+        unsafe {
+            init_builder.build_consts();
+            init_builder.build_func();
+        }
+
+        let mut eval_builder = Builder::new(&cx, &module.eval, llfun);
+        unsafe {
+            init_builder.select_bb(init.func.layout.last_block().unwrap());
+            llvm::LLVMBuildBr(init_builder.llbuilder, eval_builder.prepend_pos);
+
+            for (&val, &slot) in init.cached_vals.iter() {
+                let inst = init.func.dfg.value_def(val).unwrap_inst();
+                let bb = init.func.layout.inst_block(inst).unwrap();
+                init_builder.select_bb_before_terminator(bb);
+                let val = init_builder.values[val].get(&init_builder);
+                init_builder.store(cache_slots[slot].0, val);
+            }
+        }
+
+        let cache_vals = cache_slots.iter().map(|&(ptr, ty)| {
+            BuilderVal::Load(Box::new(MemLoc { ptr, ptr_ty: ty, ty, indices: Box::new([]) }))
+        });
+        eval_builder.params = eval_params;
+        eval_builder.params.extend(cache_vals);
+        eval_builder.callbacks = dae_callbacks(&module.intern.callbacks, &cx);
+
+        unsafe {
+            eval_builder.build_consts();
+            eval_builder.build_func();
+
+            eval_builder.select_bb(module.eval.layout.last_block().unwrap());
+            let resist_residual = dae_system.residual.iter().map(|residual| residual.resist);
+            let react_residual = dae_system.residual.iter().map(|residual| residual.react);
+            let resist_jacobian = dae_system.jacobian.iter().map(|entry| entry.resist);
+            let react_jacobian = dae_system.jacobian.iter().map(|entry| entry.react);
+            let outputs =
+                resist_residual.chain(react_residual).chain(resist_jacobian).chain(react_jacobian);
+            for (&dst, val) in output_dsts.iter().zip(outputs) {
+                let val = eval_builder.values[val].get(&eval_builder);
+                eval_builder.store(dst, val);
+            }
+            eval_builder.ret_void();
+        }
+
+        drop(init_builder);
+        drop(eval_builder);
+        debug_assert!(llmodule.verify_and_print(), "Invalid code generated");
+        llmodule.optimize();
+
+        dst.write(&llmodule)
+    }
+
+    /// Ensures that the names of the unknowns of the DAE system are interned so that they
+    /// can be exported by [`Self::gen_small_signal_obj`].
+    pub(crate) fn ensure_unknown_names(&mut self, db: &CompilationDB, module: &CompiledModule) {
+        for unknown in module.dae_system.unknowns.iter() {
+            self.literals.get_or_intern(&unknown_name(db, *unknown));
+        }
     }
 
     pub(crate) fn ensure_names(&mut self, db: &CompilationDB, intern: &HirInterner) {
@@ -613,7 +1125,7 @@ impl CodegenCtx<'_, '_> {
 
     pub(crate) fn compile_model_info(
         &self,
-        dst: &Utf8Path,
+        dst: Output,
         interned_model: InternedModel,
         param_init_func: Function,
         param_init_intern: HirInterner,
@@ -775,7 +1287,7 @@ impl CodegenCtx<'_, '_> {
         module.optimize();
         // println!("{}", module.to_str());
 
-        module.emit_object(dst.as_ref()).expect("code generation failed!");
+        dst.write(&module);
     }
 }
 
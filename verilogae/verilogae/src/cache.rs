@@ -1,7 +1,9 @@
 use core::slice;
+use std::fs;
 use std::mem::{size_of, size_of_val};
+use std::time::SystemTime;
 
-use anyhow::Result;
+use anyhow::{Context, Result};
 use basedb::lints::LintLevel;
 use basedb::{BaseDB, VfsStorage};
 use camino::Utf8PathBuf;
@@ -9,7 +11,7 @@ use camino::Utf8PathBuf;
 use crate::compiler_db::CompilationDB;
 use crate::Opts;
 
-fn hash(db: &CompilationDB, module: Option<&str>) -> md5::Digest {
+fn hash(db: &CompilationDB, module: Option<&str>, opts: &Opts) -> md5::Digest {
     let mut hash_builder = md5::Context::new();
     let cu = db.compilation_unit();
 
@@ -18,6 +20,13 @@ fn hash(db: &CompilationDB, module: Option<&str>) -> md5::Digest {
     if let Some(module) = module {
         hash_builder.consume(module);
     }
+    if opts.derivatives() {
+        hash_builder.consume("derivatives");
+    }
+    for param in opts.derivative_params() {
+        hash_builder.consume(param);
+        hash_builder.consume(" ");
+    }
 
     hash_builder.consume(env!("CARGO_PKG_VERSION"));
     let lints = db.global_lint_overwrites(cu.root_file());
@@ -52,10 +61,73 @@ pub(crate) fn lookup(
     full_compile: bool,
     opts: &Opts,
 ) -> Result<(Utf8PathBuf, bool)> {
-    let hash = u128::from_ne_bytes(*hash(db, opts.module_name()?));
+    let hash = u128::from_ne_bytes(*hash(db, opts.module_name()?, opts));
     let hash = base_n::encode(hash, base_n::CASE_INSENSITIVE);
     let extension = if full_compile { "mod" } else { "modinfo" };
     let path = opts.cache_dir()?.join(format!("{}.{}", hash, extension));
     let exists = !cfg!(debug_assertions) && path.exists();
     Ok((path, exists))
 }
+
+/// A compiled model stored in the cache directory.
+pub struct CacheEntry {
+    pub path: Utf8PathBuf,
+    /// whether the model functions were compiled (`load` with `full_compile`)
+    /// or only the model info
+    pub full_compile: bool,
+    pub size: u64,
+    pub modified: SystemTime,
+}
+
+/// Lists all compiled models in the cache directory.
+pub fn entries(opts: &Opts) -> Result<Vec<CacheEntry>> {
+    let dir = opts.cache_dir()?;
+    if !dir.exists() {
+        return Ok(Vec::new());
+    }
+
+    let mut res = Vec::new();
+    for entry in dir.read_dir_utf8().with_context(|| format!("failed to read {}", dir))? {
+        let entry = entry?;
+        if !entry.file_type()?.is_file() {
+            continue;
+        }
+        let full_compile = match entry.path().extension() {
+            Some("mod") => true,
+            Some("modinfo") => false,
+            _ => continue,
+        };
+        let metadata = entry.metadata()?;
+        res.push(CacheEntry {
+            path: entry.path().to_owned(),
+            full_compile,
+            size: metadata.len(),
+            modified: metadata.modified()?,
+        })
+    }
+    res.sort_by_key(|entry| entry.modified);
+    Ok(res)
+}
+
+/// Removes all compiled models (and object files left behind by aborted compilations)
+/// from the cache directory. Returns the number of removed files.
+pub fn clean(opts: &Opts) -> Result<usize> {
+    let dir = opts.cache_dir()?;
+    if !dir.exists() {
+        return Ok(0);
+    }
+
+    let mut cnt = 0;
+    for entry in dir.read_dir_utf8().with_context(|| format!("failed to read {}", dir))? {
+        let entry = entry?;
+        if !entry.file_type()?.is_file() {
+            continue;
+        }
+        let path = entry.into_path();
+        if matches!(path.extension(), Some("mod" | "modinfo" | "o")) {
+            fs::remove_file(&path).with_context(|| format!("failed to remove {}", path))?;
+            cnt += 1;
+        }
+    }
+    Ok(cnt)
+}
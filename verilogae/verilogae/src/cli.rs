@@ -0,0 +1,55 @@
+use std::io::{self, Write};
+
+use anyhow::Result;
+use camino::Utf8PathBuf;
+use clap::ArgMatches;
+use verilogae::Model;
+
+use crate::cli::def::INPUT;
+
+pub mod cache;
+pub mod def;
+pub mod eval;
+pub mod info;
+mod opts;
+
+/// Compiles the model passed to a subcommand (or loads it from the cache).
+fn load_model(matches: &ArgMatches, full_compile: bool) -> Result<Model> {
+    let input: &Utf8PathBuf = matches.get_one(INPUT).unwrap();
+    let opts = opts::matches_to_opts(matches);
+    Model::load(input, full_compile, &opts)
+}
+
+/// Formats `val` without exponent if it is reasonably close to one.
+fn fmt_real(val: f64) -> String {
+    if val == 0.0 || (1e-3..1e6).contains(&val.abs()) {
+        val.to_string()
+    } else {
+        format!("{val:e}")
+    }
+}
+
+/// Prints `rows` as a table with left aligned columns that is indented by `indent` spaces.
+fn print_table(
+    dst: &mut impl Write,
+    indent: usize,
+    header: &[&str],
+    rows: &[Vec<String>],
+) -> io::Result<()> {
+    let mut widths: Vec<_> = header.iter().map(|it| it.len()).collect();
+    for row in rows {
+        for (width, cell) in widths.iter_mut().zip(row) {
+            *width = (*width).max(cell.len());
+        }
+    }
+
+    let header: Vec<_> = header.iter().map(|it| it.to_string()).collect();
+    for row in [header].iter().chain(rows) {
+        let mut line = String::new();
+        for (cell, &width) in row.iter().zip(&widths) {
+            line.push_str(&format!("{cell:width$}  "));
+        }
+        writeln!(dst, "{:indent$}{}", "", line.trim_end())?;
+    }
+    Ok(())
+}
@@ -0,0 +1,40 @@
+use std::time::SystemTime;
+
+use anyhow::Result;
+use clap::ArgMatches;
+use verilogae::cache;
+
+use crate::cli::def::{CLEAN, LIST};
+use crate::cli::opts::cache_opts;
+use crate::cli::print_table;
+
+pub fn run(matches: &ArgMatches) -> Result<i32> {
+    match matches.subcommand() {
+        Some((LIST, matches)) => {
+            let opts = cache_opts(matches);
+            let entries = cache::entries(&opts)?;
+            println!("{}", opts.cache_dir()?);
+            let now = SystemTime::now();
+            let rows: Vec<_> = entries
+                .iter()
+                .map(|entry| {
+                    let kind = if entry.full_compile { "full" } else { "info" };
+                    let age = now.duration_since(entry.modified).unwrap_or_default();
+                    vec![
+                        entry.path.file_name().unwrap_or_default().to_owned(),
+                        kind.to_owned(),
+                        format!("{} KiB", entry.size / 1024),
+                        format!("{}h ago", age.as_secs() / 3600),
+                    ]
+                })
+                .collect();
+            print_table(&mut std::io::stdout(), 4, &["FILE", "KIND", "SIZE", "MODIFIED"], &rows)?;
+        }
+        Some((CLEAN, matches)) => {
+            let cnt = cache::clean(&cache_opts(matches))?;
+            println!("removed {cnt} files");
+        }
+        _ => unreachable!("a subcommand is required"),
+    }
+    Ok(0)
+}
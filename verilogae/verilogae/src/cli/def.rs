@@ -0,0 +1,244 @@
+use std::fs;
+
+use anyhow::bail;
+use camino::Utf8Path;
+use clap::builder::ValueParser;
+use clap::{value_parser, Arg, ArgAction, Command, ValueHint};
+
+const ABOUT: &str = r"For further information visit https://openvaf.semimod.de/docs/verilogae.";
+
+pub fn main_command() -> Command {
+    Command::new("verilogae")
+        .version(env!("CARGO_PKG_VERSION"))
+        .author("Pascal Kuthe")
+        .about("Compile Verilog-A files to obtain equations of compact models")
+        .after_long_help(ABOUT)
+        .after_help(ABOUT)
+        .subcommands([info(), eval(), cache()])
+        .subcommand_required(true)
+        .arg_required_else_help(true)
+}
+
+pub const INFO: &str = "info";
+pub const EVAL: &str = "eval";
+pub const CACHE: &str = "cache";
+pub const LIST: &str = "list";
+pub const CLEAN: &str = "clean";
+
+pub const INPUT: &str = "input";
+pub const MODULE: &str = "module";
+pub const INCLUDE: &str = "include";
+pub const DEFINE: &str = "define";
+pub const ALLOW: &str = "allow";
+pub const WARN: &str = "warn";
+pub const DENY: &str = "deny";
+pub const CACHE_DIR: &str = "cache-dir";
+pub const OPT_LVL: &str = "opt_lvl";
+pub const FUNC: &str = "func";
+pub const PARAMS: &str = "params";
+pub const PARAM: &str = "param";
+pub const SWEEP: &str = "sweep";
+pub const CSV: &str = "csv";
+pub const THREADS: &str = "threads";
+
+fn info() -> Command {
+    Command::new(INFO)
+        .about("Print the functions, parameters, nodes and operating point variables of a model.")
+        .args(compile_args())
+        .arg_required_else_help(true)
+}
+
+fn eval() -> Command {
+    Command::new(EVAL)
+        .about("Evaluate model functions.")
+        .long_about("Evaluate model functions.\nThe functions are evaluated for all combinations of the swept values (the first sweep is the outermost loop). Voltages, currents, real parameters, dependency breaking variables and the temperature (300.15 K by default) can be swept.")
+        .args(compile_args())
+        .args([func(), params(), param(), sweep(), csv(), threads()])
+        .arg_required_else_help(true)
+}
+
+fn cache() -> Command {
+    Command::new(CACHE)
+        .about("Manage the compiled models stored in the cache directory.")
+        .subcommands([
+            Command::new(LIST).about("List all compiled models in the cache directory."),
+            Command::new(CLEAN).about("Remove all compiled models from the cache directory."),
+        ])
+        .arg(cache_dir().global(true))
+        .subcommand_required(true)
+}
+
+fn compile_args() -> [Arg; 9] {
+    [
+        input(),
+        module(),
+        include_dir(),
+        def_arg(),
+        lint_arg(ALLOW),
+        lint_arg(WARN),
+        lint_arg(DENY),
+        cache_dir(),
+        opt_lvl(),
+    ]
+}
+
+fn input() -> Arg {
+    input_file_path_arg(INPUT).help("The root Verilog-A file.").required(true)
+}
+
+fn module() -> Arg {
+    Arg::new(MODULE)
+        .long(MODULE)
+        .short('m')
+        .help("The module to compile.")
+        .long_help("The module to compile.\nOnly required if the file contains multiple modules.")
+        .value_name("MODULE")
+        .value_hint(ValueHint::Other)
+        .required(false)
+}
+
+fn include_dir() -> Arg {
+    dir_path_arg(INCLUDE)
+        .long(INCLUDE)
+        .short('I')
+        .help("Search directory for include files.")
+        .required(false)
+        .action(ArgAction::Append)
+}
+
+fn def_arg() -> Arg {
+    Arg::new(DEFINE)
+        .short('D')
+        .help("Defines a MACRO for use within the preprocessors")
+        .long_help("Defines a MACRO for use within the preprocessors.\nIf the value is omitted \"1\" is used.")
+        .value_name("MACRO[=VALUE]")
+        .action(ArgAction::Append)
+        .value_hint(ValueHint::Other)
+        .required(false)
+}
+
+fn lint_arg(lvl: &'static str) -> Arg {
+    let arg = match lvl {
+        ALLOW => Arg::new(ALLOW).long(ALLOW).short('A').help("Ignore this lint."),
+        WARN => Arg::new(WARN).long(WARN).short('W').help("Make this lint a warning."),
+        _ => Arg::new(DENY).long(DENY).short('E').help("Make this lint an error."),
+    };
+    arg.num_args(1)
+        .action(ArgAction::Append)
+        .value_name("LINT")
+        .value_hint(ValueHint::Other)
+        .required(false)
+}
+
+fn cache_dir() -> Arg {
+    dir_path_arg(CACHE_DIR)
+        .long(CACHE_DIR)
+        .help("Directory where compiled models are stored.")
+        .required(false)
+}
+
+fn opt_lvl() -> Arg {
+    Arg::new(OPT_LVL)
+        .long(OPT_LVL)
+        .short('O')
+        .help("Set how much the code is optimized.")
+        .long_help("Set how much the generated machine code is optimized:\nA higher optimization level means slower compile times but faster evaluation.\n\npossible values\n\n0 - no optimizations\n1 - optimize minimally\n2 - optimize more\n3 - optimize even more")
+        .value_name("LEVEL")
+        .value_hint(ValueHint::Other)
+        .value_parser(["0", "1", "2", "3"])
+        .hide_possible_values(true)
+        .default_value("3")
+        .required(false)
+}
+
+fn func() -> Arg {
+    Arg::new(FUNC)
+        .long(FUNC)
+        .short('f')
+        .help("The function to evaluate.")
+        .long_help("The function to evaluate.\nCan be passed multiple times to evaluate multiple functions. All functions are listed by the info subcommand.")
+        .value_name("FUNCTION")
+        .value_hint(ValueHint::Other)
+        .action(ArgAction::Append)
+        .required(true)
+}
+
+fn params() -> Arg {
+    input_file_path_arg(PARAMS)
+        .long(PARAMS)
+        .help("Read the model parameters from a JSON file or SPICE model card.")
+        .long_help("Read the model parameters from a JSON file or SPICE model card.\nFiles with a .json extension must contain a single object that maps parameter names to their values. All other files must contain a single SPICE .model statement. Parameters that are not specified use their default value.")
+        .required(false)
+}
+
+fn param() -> Arg {
+    Arg::new(PARAM)
+        .long(PARAM)
+        .short('P')
+        .help("Set a model parameter or dependency breaking variable.")
+        .long_help("Set a model parameter or dependency breaking variable.\nOverwrites the values read with --params.")
+        .value_name("NAME=VALUE")
+        .value_hint(ValueHint::Other)
+        .action(ArgAction::Append)
+        .required(false)
+}
+
+fn sweep() -> Arg {
+    Arg::new(SWEEP)
+        .long(SWEEP)
+        .short('s')
+        .help("Sweep an input of the functions.")
+        .long_help("Sweep an input of the functions.\nThe values are either a range START:STOP:STEP (including STOP), a list of comma separated values or a single value. V(hi,lo) and V(node) refer to the voltage between two nodes and the potential of a node. I(branch), I(hi,lo) and I(<port>) refer to branch currents and port flows.\n\nEXAMPLES: V(b,e)=0:1:0.01, temperature=300,350,400, c10=2e-30")
+        .value_name("INPUT=VALUES")
+        .value_hint(ValueHint::Other)
+        .action(ArgAction::Append)
+        .required(false)
+}
+
+fn csv() -> Arg {
+    Arg::new(CSV).long(CSV).help("Print the results as CSV.").action(ArgAction::SetTrue)
+}
+
+fn threads() -> Arg {
+    Arg::new(THREADS)
+        .long(THREADS)
+        .short('j')
+        .help("Number of threads used to evaluate the functions.")
+        .long_help("Number of threads used to evaluate the functions.\nBy default one thread per logical core is used.")
+        .value_name("THREADS")
+        .value_hint(ValueHint::Other)
+        .value_parser(value_parser!(usize))
+        .default_value("0")
+        .required(false)
+}
+
+fn dir_path_arg(name: &'static str) -> Arg {
+    let parse = |raw: &str| {
+        let path = Utf8Path::new(raw).to_owned();
+
+        match fs::metadata(&path) {
+            Err(err) => bail!("{err}"),
+            Ok(info) if !info.is_dir() => bail!("is not a directory"),
+            _ => Ok(path),
+        }
+    };
+
+    Arg::new(name)
+        .value_name("DIR")
+        .value_hint(ValueHint::DirPath)
+        .value_parser(ValueParser::new(parse))
+}
+
+fn input_file_path_arg(name: &'static str) -> Arg {
+    let parse = |raw: &str| {
+        let path = Utf8Path::new(raw).to_owned();
+
+        match fs::metadata(&path) {
+            Err(err) => bail!("{err}"),
+            Ok(info) if !info.is_file() => bail!("is not a file"),
+            _ => Ok(path),
+        }
+    };
+
+    Arg::new(name).value_name("FILE").value_hint(ValueHint::FilePath).value_parser(parse)
+}
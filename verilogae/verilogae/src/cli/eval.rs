@@ -0,0 +1,186 @@
+use std::io::{self, Write};
+
+use anyhow::{bail, Context, Result};
+use camino::Utf8PathBuf;
+use clap::ArgMatches;
+use verilogae::{ParamValue, Voltages};
+
+use crate::cli::def::{CSV, FUNC, PARAM, PARAMS, SWEEP, THREADS};
+use crate::cli::{fmt_real, load_model, print_table};
+
+#[cfg(test)]
+mod tests;
+
+/// The temperature (in Kelvin) used if it is not swept.
+const DEFAULT_TEMPERATURE: f64 = 300.15;
+
+pub fn run(matches: &ArgMatches) -> Result<i32> {
+    let sweeps = matches.get_many::<String>(SWEEP).into_iter().flatten();
+    let sweeps = sweeps.map(|sweep| Sweep::parse(sweep)).collect::<Result<_>>()?;
+    let grid = Grid::new(sweeps)?;
+
+    let model = load_model(matches, true)?;
+    let mut params = model.default_params();
+    if let Some(path) = matches.get_one::<Utf8PathBuf>(PARAMS) {
+        if path.extension() == Some("json") {
+            params.load_json(path)?;
+        } else {
+            params.load_spice(path, None, None, None)?;
+        }
+    }
+
+    // values that are not model parameters are dependency breaking variables
+    let mut voltages = Voltages::new();
+    voltages.set_scalar("temperature", DEFAULT_TEMPERATURE);
+    for assignment in matches.get_many::<String>(PARAM).into_iter().flatten() {
+        let (name, val) = match assignment.split_once('=') {
+            Some((name, val)) => (name.trim(), val.trim()),
+            None => bail!("invalid parameter {assignment}\nhelp: expected NAME=VALUE"),
+        };
+        let val = match val.parse() {
+            Ok(val) => ParamValue::Real(val),
+            Err(_) => ParamValue::from(val),
+        };
+        if params.contains(name) {
+            params.set(name, val)?;
+        } else if let ParamValue::Real(val) = val {
+            voltages.set_scalar(name, val);
+        } else {
+            bail!("unknown parameter {name}")
+        }
+    }
+    for (sweep, column) in grid.sweeps.iter().zip(&grid.columns) {
+        voltages.set(sweep.input.as_str(), column.as_slice());
+    }
+
+    let threads = *matches.get_one::<usize>(THREADS).unwrap();
+    let mut results = Vec::new();
+    for name in matches.get_many::<String>(FUNC).unwrap() {
+        let fun = match model.function(name) {
+            Some(fun) => fun,
+            None => bail!("unknown function {name}\nhelp: the info subcommand lists all functions"),
+        };
+        let res = fun
+            .eval_with_threads(&params, &voltages, threads)
+            .with_context(|| format!("failed to evaluate {name}"))?;
+        results.push((name.as_str(), res));
+    }
+
+    let header: Vec<_> = grid
+        .sweeps
+        .iter()
+        .map(|sweep| sweep.label.as_str())
+        .chain(results.iter().map(|(name, _)| *name))
+        .collect();
+    let rows: Vec<Vec<_>> = (0..grid.len)
+        .map(|i| {
+            let sweeps = grid.columns.iter().map(|col| col[i]);
+            let results = results.iter().map(|(_, res)| res[i]);
+            sweeps.chain(results).map(fmt_real).collect()
+        })
+        .collect();
+
+    let mut stdout = io::stdout().lock();
+    if matches.get_flag(CSV) {
+        writeln!(stdout, "{}", header.join(","))?;
+        for row in rows {
+            writeln!(stdout, "{}", row.join(","))?;
+        }
+    } else {
+        print_table(&mut stdout, 0, &header, &rows)?;
+    }
+
+    Ok(0)
+}
+
+/// An input of the model functions that is swept.
+struct Sweep {
+    /// the name passed on the command line
+    label: String,
+    /// the name of the function input
+    input: String,
+    vals: Vec<f64>,
+}
+
+impl Sweep {
+    /// Parses `INPUT=START:STOP:STEP`, `INPUT=VAL1,VAL2,...` or `INPUT=VAL`.
+    fn parse(raw: &str) -> Result<Sweep> {
+        let (label, vals) = match raw.split_once('=') {
+            Some((label, vals)) => (label.trim(), vals.trim()),
+            None => bail!("invalid sweep {raw}\nhelp: expected INPUT=VALUES"),
+        };
+        let parse = |val: &str| {
+            val.trim().parse::<f64>().with_context(|| format!("invalid value {val} for {label}"))
+        };
+
+        let vals = if vals.contains(':') {
+            let (start, stop, step) = match *vals.split(':').collect::<Vec<_>>() {
+                [start, stop, step] => (parse(start)?, parse(stop)?, parse(step)?),
+                _ => bail!("invalid sweep {raw}\nhelp: expected INPUT=START:STOP:STEP"),
+            };
+            if !step.is_finite() || step == 0.0 || (stop - start) / step < 0.0 {
+                bail!("invalid sweep {raw}\nhelp: STOP can not be reached from START with STEP")
+            }
+            // allow for rounding errors so that STOP is included
+            let cnt = ((stop - start) / step + 1e-9).floor() as usize + 1;
+            (0..cnt).map(|i| start + i as f64 * step).collect()
+        } else {
+            vals.split(',').map(parse).collect::<Result<_>>()?
+        };
+
+        Ok(Sweep { input: input_name(label), label: label.to_owned(), vals })
+    }
+}
+
+/// Converts the names used on the command line to the names of the function inputs:
+/// `V(hi,lo)` and `V(node)` are the voltages `br_<hi><lo>` and `br_<node>`, `I(branch)`,
+/// `I(hi,lo)` and `I(<port>)` are the currents through a named branch, an unnamed branch and
+/// the flow through a port.
+fn input_name(name: &str) -> String {
+    if let Some(args) = name.strip_prefix("V(").and_then(|it| it.strip_suffix(')')) {
+        let (hi, lo) = args.split_once(',').unwrap_or((args, ""));
+        return format!("br_{}{}", hi.trim(), lo.trim());
+    }
+
+    if let Some(args) = name.strip_prefix("I(").and_then(|it| it.strip_suffix(')')) {
+        let args = args.trim();
+        if let Some(port) = args.strip_prefix('<').and_then(|it| it.strip_suffix('>')) {
+            return format!("< {} >", port.trim());
+        }
+        return match args.split_once(',') {
+            Some((hi, lo)) => format!(" {} {} ", hi.trim(), lo.trim()),
+            None => args.to_owned(),
+        };
+    }
+
+    name.to_owned()
+}
+
+/// All combinations of the swept values. The first sweep is the outermost loop.
+struct Grid {
+    sweeps: Vec<Sweep>,
+    /// the value of each sweep at every point of the grid
+    columns: Vec<Vec<f64>>,
+    len: usize,
+}
+
+impl Grid {
+    fn new(sweeps: Vec<Sweep>) -> Result<Grid> {
+        for (i, sweep) in sweeps.iter().enumerate() {
+            if sweeps[..i].iter().any(|it| it.input == sweep.input) {
+                bail!("{} is swept multiple times", sweep.label)
+            }
+        }
+
+        let len = sweeps.iter().map(|sweep| sweep.vals.len()).product();
+        let mut stride = len;
+        let columns = sweeps
+            .iter()
+            .map(|sweep| {
+                stride /= sweep.vals.len();
+                (0..len).map(|i| sweep.vals[(i / stride) % sweep.vals.len()]).collect()
+            })
+            .collect();
+        Ok(Grid { sweeps, columns, len })
+    }
+}
@@ -0,0 +1,106 @@
+use super::{input_name, Grid, Sweep};
+
+fn sweep(raw: &str) -> Vec<f64> {
+    match Sweep::parse(raw) {
+        Ok(sweep) => sweep.vals,
+        Err(err) => panic!("failed to parse {raw}: {err:?}"),
+    }
+}
+
+fn sweep_err(raw: &str) -> String {
+    match Sweep::parse(raw) {
+        Ok(sweep) => panic!("{raw} was parsed to {:?}", sweep.vals),
+        Err(err) => format!("{err:#}"),
+    }
+}
+
+fn assert_close(vals: &[f64], expected: &[f64]) {
+    assert_eq!(vals.len(), expected.len(), "{vals:?} != {expected:?}");
+    for (val, expected) in vals.iter().zip(expected) {
+        assert!((val - expected).abs() < 1e-12, "{vals:?} != {expected:?}");
+    }
+}
+
+#[test]
+fn range() {
+    let parsed = Sweep::parse("V(b,e)=0:1:0.25").unwrap();
+    assert_eq!(parsed.label, "V(b,e)");
+    assert_eq!(parsed.input, "br_be");
+    assert_eq!(parsed.vals, [0.0, 0.25, 0.5, 0.75, 1.0]);
+
+    assert_eq!(sweep("V(b,e) = 0.5 : 0.5 : 0.1"), [0.5]);
+    // STOP is only included if it is reached
+    assert_eq!(sweep("V(b,e)=0:1:0.3").len(), 4);
+    assert_close(&sweep("V(b,e)=0:1:0.3"), &[0.0, 0.3, 0.6, 0.9]);
+}
+
+#[test]
+fn range_rounding() {
+    // (0.3 - 0) / 0.1 evaluates to 2.9999999999999996
+    assert_close(&sweep("V(b,e)=0:0.3:0.1"), &[0.0, 0.1, 0.2, 0.3]);
+    let vals = sweep("V(b,e)=0:1:0.01");
+    assert_eq!(vals.len(), 101);
+    assert!((vals[100] - 1.0).abs() < 1e-12);
+    let vals = sweep("V(b,e)=0.55:0.8:0.05");
+    assert_eq!(vals.len(), 6);
+    assert!((vals[5] - 0.8).abs() < 1e-12);
+}
+
+#[test]
+fn negative_step() {
+    assert_close(&sweep("V(b,c)=0:-1:-0.25"), &[0.0, -0.25, -0.5, -0.75, -1.0]);
+    let vals = sweep("V(b,c)=1:0:-0.1");
+    assert_eq!(vals.len(), 11);
+    assert!(vals[10].abs() < 1e-12);
+
+    let unreachable = "STOP can not be reached from START with STEP";
+    assert!(sweep_err("V(b,c)=0:-1:0.1").contains(unreachable));
+    assert!(sweep_err("V(b,c)=0:1:-0.1").contains(unreachable));
+    assert!(sweep_err("V(b,c)=0:1:0").contains(unreachable));
+    assert!(sweep_err("V(b,c)=0:1:inf").contains(unreachable));
+    assert!(sweep_err("V(b,c)=0:1:nan").contains(unreachable));
+}
+
+#[test]
+fn invalid_range() {
+    let expected = "expected INPUT=START:STOP:STEP";
+    assert!(sweep_err("V(b,e)=0:1").contains(expected));
+    assert!(sweep_err("V(b,e)=0:1:0.1:2").contains(expected));
+    assert!(sweep_err("V(b,e)=0:x:0.1").contains("invalid value x for V(b,e)"));
+    assert!(sweep_err("V(b,e)").contains("expected INPUT=VALUES"));
+}
+
+#[test]
+fn list() {
+    assert_eq!(sweep("temperature=300"), [300.0]);
+    assert_eq!(sweep("temperature=300, 350,400"), [300.0, 350.0, 400.0]);
+    assert_eq!(sweep("V(b,e)=1e-3,-2"), [1e-3, -2.0]);
+    assert!(sweep_err("temperature=300,,400").contains("invalid value  for temperature"));
+}
+
+#[test]
+fn input_names() {
+    assert_eq!(input_name("V(b, e)"), "br_be");
+    assert_eq!(input_name("V(b)"), "br_b");
+    assert_eq!(input_name("I(br_a)"), "br_a");
+    assert_eq!(input_name("I(a, b)"), " a b ");
+    assert_eq!(input_name("I(<a>)"), "< a >");
+    assert_eq!(input_name("temperature"), "temperature");
+}
+
+#[test]
+fn grid() {
+    let grid =
+        Grid::new(vec![Sweep::parse("V(a)=0,1").unwrap(), Sweep::parse("V(b)=0:2:1").unwrap()])
+            .unwrap();
+    assert_eq!(grid.len, 6);
+    assert_eq!(grid.columns[0], [0.0, 0.0, 0.0, 1.0, 1.0, 1.0]);
+    assert_eq!(grid.columns[1], [0.0, 1.0, 2.0, 0.0, 1.0, 2.0]);
+
+    let res =
+        Grid::new(vec![Sweep::parse("V(a,b)=0").unwrap(), Sweep::parse("V(a, b)=1").unwrap()]);
+    match res {
+        Ok(_) => panic!("a duplicate sweep was accepted"),
+        Err(err) => assert_eq!(err.to_string(), "V(a, b) is swept multiple times"),
+    }
+}
@@ -0,0 +1,63 @@
+use std::io::Write;
+
+use anyhow::Result;
+use clap::ArgMatches;
+use termcolor::{Color, ColorChoice, ColorSpec, StandardStream, WriteColor};
+use verilogae::ParamValue;
+
+use crate::cli::{fmt_real, load_model, print_table};
+
+pub fn run(matches: &ArgMatches) -> Result<i32> {
+    let model = load_model(matches, false)?;
+    let mut stdout = StandardStream::stdout(ColorChoice::Auto);
+
+    print_header(&mut stdout, "MODULE:")?;
+    writeln!(&mut stdout, "    {}", model.name())?;
+
+    print_header(&mut stdout, "NODES:")?;
+    for node in model.nodes() {
+        writeln!(&mut stdout, "    {node}")?;
+    }
+
+    print_header(&mut stdout, "FUNCTIONS:")?;
+    for fun in model.function_names() {
+        writeln!(&mut stdout, "    {fun}")?;
+    }
+
+    let rows: Vec<_> = model
+        .params()
+        .iter()
+        .map(|param| {
+            let default = match &param.default {
+                ParamValue::Real(val) => fmt_real(*val),
+                ParamValue::Integer(val) => val.to_string(),
+                ParamValue::String(val) => format!("{val:?}"),
+            };
+            vec![
+                param.name.clone(),
+                param.ty.to_string(),
+                default,
+                param.unit.clone(),
+                param.group.clone(),
+                param.description.clone(),
+            ]
+        })
+        .collect();
+    print_header(&mut stdout, "PARAMETERS:")?;
+    let header = ["NAME", "TYPE", "DEFAULT", "UNIT", "GROUP", "DESCRIPTION"];
+    print_table(&mut stdout, 4, &header, &rows)?;
+
+    print_header(&mut stdout, "OPVARS:")?;
+    for opvar in model.opvars() {
+        writeln!(&mut stdout, "    {opvar}")?;
+    }
+
+    Ok(0)
+}
+
+fn print_header(stdout: &mut StandardStream, header: &str) -> Result<()> {
+    stdout.set_color(ColorSpec::new().set_fg(Some(Color::Yellow)))?;
+    writeln!(stdout, "{header}")?;
+    stdout.set_color(&ColorSpec::new())?;
+    Ok(())
+}
@@ -0,0 +1,46 @@
+use camino::Utf8PathBuf;
+use clap::ArgMatches;
+use verilogae::api::{OptLevel, Opts, Slice};
+
+use crate::cli::def::{ALLOW, CACHE_DIR, DEFINE, DENY, INCLUDE, MODULE, OPT_LVL, WARN};
+
+/// Converts the compile options passed to a subcommand to the [`Opts`] of the VerilogAE API.
+/// The strings are leaked as they are used until the process exits anyway.
+pub fn matches_to_opts(matches: &ArgMatches) -> Opts {
+    let mut opts = cache_opts(matches);
+    if let Some(module) = matches.get_one::<String>(MODULE) {
+        opts.model = leak_str(module);
+    }
+    let list = |name: &str| matches.get_many::<String>(name).into_iter().flatten();
+    let include = matches.get_many::<Utf8PathBuf>(INCLUDE).into_iter().flatten();
+    opts.include_dirs = leak_list(include.map(|path| path.as_str()));
+    opts.macro_flags = leak_list(list(DEFINE));
+    opts.allow_lints = leak_list(list(ALLOW));
+    opts.warn_lints = leak_list(list(WARN));
+    opts.deny_lints = leak_list(list(DENY));
+    opts.opt_lvl = match &**matches.get_one::<String>(OPT_LVL).unwrap() {
+        "0" => OptLevel::None,
+        "1" => OptLevel::Less,
+        "2" => OptLevel::Default,
+        _ => OptLevel::Aggressive,
+    };
+    opts
+}
+
+/// Converts the `--cache-dir` option to the [`Opts`] of the VerilogAE API.
+pub fn cache_opts(matches: &ArgMatches) -> Opts {
+    let mut opts = Opts::default();
+    if let Some(cache_dir) = matches.get_one::<Utf8PathBuf>(CACHE_DIR) {
+        opts.cache_dir = leak_str(cache_dir.as_str());
+    }
+    opts
+}
+
+fn leak_str(val: &str) -> Slice<u8> {
+    Box::<str>::from(val).into()
+}
+
+fn leak_list(vals: impl Iterator<Item = impl AsRef<str>>) -> Slice<Slice<u8>> {
+    let vals: Box<[_]> = vals.map(|val| leak_str(val.as_ref())).collect();
+    vals.into()
+}
@@ -13,6 +13,7 @@ use hir::{
 use hir_lower::CurrentKind;
 use indexmap::IndexMap;
 use lasso::{Rodeo, Spur};
+use sim_back::SimUnknownKind;
 use smol_str::SmolStr;
 use stdx::iter::zip;
 use syntax::ast::{self, Attr, Expr, LiteralKind, PathExpr};
@@ -73,6 +74,18 @@ pub fn current_name(db: &CompilationDB, kind: CurrentKind) -> String {
     }
 }
 
+/// The name of an unknown of the DAE system. Node potentials are named like the voltage
+/// between the node and ground and branch currents like the currents passed to a function.
+pub fn unknown_name(db: &CompilationDB, unknown: SimUnknownKind) -> String {
+    match unknown {
+        SimUnknownKind::KirchoffLaw(node) => voltage_name(db, node, None),
+        SimUnknownKind::Current(kind) => current_name(db, kind),
+        SimUnknownKind::Implicit(equation) => {
+            format!("implicit_equation_{}", u32::from(equation))
+        }
+    }
+}
+
 struct IllegalExpr {
     expr: Expr,
     expected: &'static str,
@@ -138,7 +151,7 @@ pub struct ModelInfo {
     pub params: IndexMap<Parameter, ParamInfo, ahash::RandomState>,
     pub functions: Vec<FuncSpec>,
     pub var_names: AHashMap<Variable, SmolStr>,
-    pub op_vars: Vec<SmolStr>,
+    pub op_vars: Vec<(Variable, SmolStr)>,
     pub module: Module,
     pub ports: Vec<SmolStr>,
     pub optional_currents: AHashMap<Branch, f64>,
@@ -203,7 +216,7 @@ impl ModelInfo {
                     let units = var.get_attr(db, &ast, "units");
                     let desc = var.get_attr(db, &ast, "desc");
                     if (units.is_some() || desc.is_some()) && path.len() == name_len {
-                        op_vars.push(path.clone())
+                        op_vars.push((var, path.clone()))
                     }
 
                     var_names.insert(var, path);
@@ -427,6 +440,23 @@ impl ModelInfo {
         })
     }
 
+    /// Resolves the (real) parameters selected with the `derivative_params` option.
+    pub(crate) fn derivative_params<'a>(
+        &self,
+        names: impl Iterator<Item = &'a str>,
+    ) -> Result<Vec<Parameter>> {
+        names
+            .map(|name| {
+                let param = self.params.iter().find(|(_, info)| info.name == name);
+                match param {
+                    Some((param, info)) if info.ty == Type::Real => Ok(*param),
+                    Some(_) => bail!("can not differentiate by the non-real parameter '{}'", name),
+                    None => bail!("unknown parameter '{}' in derivative_params", name),
+                }
+            })
+            .collect()
+    }
+
     pub(crate) fn intern_model(&self, db: &CompilationDB, literals: &mut Rodeo) -> InternedModel {
         let params = self
             .params
@@ -450,7 +480,7 @@ impl ModelInfo {
             })
             .collect();
 
-        let opvars = self.op_vars.iter().map(|name| literals.get_or_intern(&**name)).collect();
+        let opvars = self.op_vars.iter().map(|(_, name)| literals.get_or_intern(&**name)).collect();
 
         let nodes = self.ports.iter().map(|name| literals.get_or_intern(&**name)).collect();
 
@@ -1,22 +1,25 @@
 use std::fs;
 use std::io::Write;
+use std::sync::Mutex;
 use std::time::Instant;
 
-use anyhow::{bail, Context, Result};
+use anyhow::{anyhow, bail, Context, Result};
 use basedb::VfsStorage;
 use camino::{Utf8Path, Utf8PathBuf};
 use lasso::Rodeo;
 use linker::link;
-use mir_llvm::LLVMBackend;
+use mir_llvm::{Jit, LLVMBackend};
 use salsa::ParallelDatabase;
+use sim_back::{CompiledModule, CorrelatedNoise};
 use stdx::iter::zip;
 use stdx::pretty;
 use termcolor::ColorChoice::Auto;
 use termcolor::{Color, ColorSpec, StandardStream, WriteColor};
 
 use crate::api::{Opts, VfsEntry};
+use crate::back::Output;
 use crate::compiler_db::{CompilationDB, ModelInfo};
-use crate::middle::{build_module_mir, build_param_init_mir};
+use crate::middle::{build_module_mir, build_param_init_mir, collect_sim_module, slice_opvars_mir};
 use crate::opts::abs_path;
 pub use llvm::OptLevel;
 
@@ -28,10 +31,18 @@ use libloading::os::unix::Library;
 
 pub mod api;
 mod back;
-mod cache;
+pub mod cache;
 mod compiler_db;
 mod middle;
+mod model;
 mod opts;
+mod params;
+
+pub use model::{Function, Model, Voltages};
+pub use params::{ParamInfo, ParamSet, ParamType, ParamValue};
+
+#[cfg(test)]
+mod tests;
 
 pub fn export_vfs(path: &Utf8Path, opts: &Opts) -> Result<Box<[VfsEntry]>> {
     let db = compiler_db::new(path, opts)?;
@@ -62,6 +73,37 @@ pub fn load(path: &Utf8Path, full_compile: bool, opts: &Opts) -> Result<Library>
     Ok(lib)
 }
 
+/// Compiles the model in memory with a JIT instead of building a shared library.
+/// The model symbols can be looked up with [`Jit::lookup`]. No files (or cache entries)
+/// are written and the system linker is not required.
+pub fn load_jit(path: &Utf8Path, full_compile: bool, opts: &Opts) -> Result<Jit> {
+    let start = Instant::now();
+    let db = compiler_db::new(path, opts)?;
+    let file = path.file_name().to_owned().unwrap();
+    let info = ModelInfo::collect(&db, file, opts.module_name()?)?;
+
+    let target_cpu = opts.target_cpu()?.unwrap_or("native");
+    let cg_opts: Vec<_> = opts.cg_flags().map(str::to_owned).collect();
+    let target = opts.target()?;
+    let backend = LLVMBackend::new(&cg_opts, &target, target_cpu.to_owned(), &[]);
+
+    let bitcode: Vec<_> = (0..info.functions.len() + 3).map(|_| Mutex::new(Vec::new())).collect();
+    let outputs: Vec<_> = bitcode.iter().map(Output::Bitcode).collect();
+    codegen(db, &info, full_compile, &backend, opts, &outputs)?;
+
+    let jit = unsafe { Jit::new(&backend, opts.opt_lvl.into()) }
+        .map_err(|err| anyhow!("failed to create JIT: {err}"))?;
+    for module in bitcode {
+        let module = module.into_inner().unwrap();
+        if !module.is_empty() {
+            jit.add_bitcode(&module).map_err(|err| anyhow!("failed to load {file}: {err}"))?;
+        }
+    }
+
+    print_finished(file, start)?;
+    Ok(jit)
+}
+
 fn build_local_model(path: &Utf8Path, full_compile: bool, opts: &Opts) -> Result<Utf8PathBuf> {
     let db = compiler_db::new(path, opts)?;
     let (file, found) = cache::lookup(&db, full_compile, opts)?;
@@ -103,78 +145,124 @@ fn build_model(
     }
 
     let mut object_files = vec![cache_dir.join(format!("{}_modelinfo.o", file))];
+    if full_compile {
+        let dst_name = dst.file_name().to_owned().unwrap();
+        object_files.extend(
+            info.functions
+                .iter()
+                .map(|fun| cache_dir.join(format!("{}{}.o", dst_name, fun.prefix))),
+        );
+        object_files.push(cache_dir.join(format!("{}opvars.o", dst_name)));
+        object_files.push(cache_dir.join(format!("{}small_signal.o", dst_name)));
+    }
+    let outputs: Vec<_> = object_files.iter().map(|path| Output::Object(path)).collect();
+    codegen(db, &info, full_compile, &backend, opts, &outputs)?;
 
+    // TODO configure linker
+    link(None, &target, dst, |linker| {
+        for obj in &object_files {
+            linker.add_object(obj)
+        }
+    })
+    .context("linking failed!")?;
+
+    #[allow(unused_must_use)]
+    for file in object_files {
+        fs::remove_file(file);
+    }
+
+    print_finished(file, start)
+}
+
+/// Generates the model info (written to `outputs[0]`) and, if `full_compile` is set,
+/// the model functions (written to `outputs[1..]`) followed by the function that
+/// calculates the operating point variables and the function that evaluates the DAE
+/// system (written to the last two outputs).
+fn codegen(
+    db: CompilationDB,
+    info: &ModelInfo,
+    full_compile: bool,
+    backend: &LLVMBackend,
+    opts: &Opts,
+    outputs: &[Output],
+) -> Result<()> {
     if full_compile {
-        let (func, intern, mut literals, cfg) = build_module_mir(&db, &info);
+        let derivative_params = if opts.derivatives() {
+            Some(info.derivative_params(opts.derivative_params())?)
+        } else {
+            None
+        };
+        let (func, intern, mut literals, cfg, derivatives) =
+            build_module_mir(&db, info, derivative_params.as_deref());
         let interned_model = info.intern_model(&db, &mut literals);
-        let param_init = build_param_init_mir(&db, &info, &mut literals);
+        let param_init = build_param_init_mir(&db, info, &mut literals);
+        let sim_module = collect_sim_module(&db, info)?;
+        let compiled_module =
+            CompiledModule::new(&db, &sim_module, &mut literals, CorrelatedNoise::default());
 
         let mut cx = back::CodegenCtx {
-            model_info: &info,
-            llbackend: &backend,
+            model_info: info,
+            llbackend: backend,
             literals: &mut literals,
             opt_lvl: opts.opt_lvl.into(),
         };
 
-        cx.compile_model_info(&object_files[0], interned_model, param_init.0, param_init.1);
-
-        let dst_name = dst.file_name().to_owned().unwrap();
-        object_files.extend(
-            info.functions
-                .iter()
-                .map(|fun| cache_dir.join(format!("{}{}.o", dst_name, fun.prefix))),
-        );
+        cx.compile_model_info(outputs[0], interned_model, param_init.0, param_init.1);
 
         // ensure all voltage/current names are in the interner so that the interner can be
         // shared (readonly) betwenn threads
         cx.ensure_names(&db, &intern);
+        cx.ensure_unknown_names(&db, &compiled_module);
 
         rayon_core::scope(|s| {
             let db = db;
-            for (spec, file) in zip(&info.functions, &object_files[1..]) {
+            let (func, cfg, intern, cx) = (&func, &cfg, &intern, &cx);
+            let compiled_module = &compiled_module;
+            let (small_signal_dst, fun_dsts) = outputs[1..].split_last().unwrap();
+            let (opvars_dst, fun_dsts) = fun_dsts.split_last().unwrap();
+            for (i, (spec, dst)) in zip(&info.functions, fun_dsts).enumerate() {
+                let derivatives = derivatives.as_ref().map(|derivatives| &derivatives[i]);
                 let db_snap = db.snapshot();
-                s.spawn(|_| {
-                    let db_snap = db_snap;
-                    let (func, cfg) = spec.slice_mir(&func, &cfg, &intern);
-                    cx.gen_func_obj(&db_snap, spec, &func, &cfg, &intern, file)
+                s.spawn(move |_| {
+                    let (func, cfg) = spec.slice_mir(func, cfg, intern, derivatives);
+                    cx.gen_func_obj(&db_snap, spec, &func, &cfg, intern, derivatives, *dst)
                 })
             }
-        })
+
+            let db_snap = db.snapshot();
+            s.spawn(move |_| {
+                let (func, cfg, opvars) = slice_opvars_mir(&db_snap, info, func, cfg, intern);
+                cx.gen_opvars_obj(&db_snap, &func, &cfg, intern, &opvars, *opvars_dst)
+            });
+
+            let db_snap = db.snapshot();
+            s.spawn(move |_| cx.gen_small_signal_obj(&db_snap, compiled_module, *small_signal_dst));
+        });
     } else {
         let mut literals = Rodeo::default();
 
         let interned_model = info.intern_model(&db, &mut literals);
-        let param_init = build_param_init_mir(&db, &info, &mut literals);
+        let param_init = build_param_init_mir(&db, info, &mut literals);
 
         let cx = back::CodegenCtx {
-            model_info: &info,
-            llbackend: &backend,
+            model_info: info,
+            llbackend: backend,
             literals: &mut literals,
             opt_lvl: opts.opt_lvl.into(),
         };
 
-        cx.compile_model_info(&object_files[0], interned_model, param_init.0, param_init.1);
+        cx.compile_model_info(outputs[0], interned_model, param_init.0, param_init.1);
     }
 
-    // TODO configure linker
-    link(None, &target, dst, |linker| {
-        for obj in &object_files {
-            linker.add_object(obj)
-        }
-    })
-    .context("linking failed!")?;
-
-    #[allow(unused_must_use)]
-    for file in object_files {
-        fs::remove_file(file);
-    }
+    Ok(())
+}
 
+fn print_finished(file: &str, start: Instant) -> Result<()> {
     let seconds = Instant::elapsed(&start).as_secs_f64();
     let mut stderr = StandardStream::stderr(Auto);
     stderr.set_color(ColorSpec::new().set_fg(Some(Color::Green)).set_bold(true))?;
     write!(&mut stderr, "Finished")?;
     stderr.set_color(&ColorSpec::new())?;
     writeln!(&mut stderr, " building {} in {:.2}s", file, seconds)?;
-
     Ok(())
 }
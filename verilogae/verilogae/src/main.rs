@@ -1,7 +1,38 @@
-use verilogae::api::Opts;
-use verilogae::load;
+use std::io::Write;
+use std::process::exit;
+
+use anyhow::Result;
+use clap::ArgMatches;
+use termcolor::{Color, ColorChoice, ColorSpec, StandardStream, WriteColor};
+
+use crate::cli::def::{main_command, CACHE, EVAL, INFO};
+
+mod cli;
 
 fn main() {
-    let arg = std::env::args().nth(1).unwrap();
-    load(arg.as_ref(), true, &Opts::default()).unwrap();
+    let matches = main_command().get_matches();
+    match wrapped_main(&matches) {
+        Ok(err_code) => exit(err_code),
+        Err(err) => {
+            let mut stderr = StandardStream::stderr(ColorChoice::Auto);
+            for cause in err.chain() {
+                stderr.set_color(ColorSpec::new().set_fg(Some(Color::Red)).set_bold(true)).unwrap();
+                write!(&mut stderr, "error").unwrap();
+                stderr.set_color(ColorSpec::new().set_bold(true)).unwrap();
+                write!(&mut stderr, ":").unwrap();
+                stderr.set_color(&ColorSpec::new()).unwrap();
+                writeln!(&mut stderr, " {cause}").unwrap();
+            }
+            exit(1)
+        }
+    }
+}
+
+fn wrapped_main(matches: &ArgMatches) -> Result<i32> {
+    match matches.subcommand() {
+        Some((INFO, matches)) => cli::info::run(matches),
+        Some((EVAL, matches)) => cli::eval::run(matches),
+        Some((CACHE, matches)) => cli::cache::run(matches),
+        _ => unreachable!("a subcommand is required"),
+    }
 }
@@ -1,73 +1,188 @@
-use ahash::AHashSet;
+use std::io::{self, Write};
+
+use ahash::{AHashMap, AHashSet};
+use anyhow::{bail, Result};
+use basedb::diagnostics::sink::Buffer;
+use basedb::diagnostics::ConsoleSink;
 use bitset::{BitSet, SparseBitMatrix};
+use hir::{Node, Parameter, Type, Variable};
 use hir_lower::{CallBackKind, HirInterner, MirBuilder, ParamKind, PlaceKind};
 use lasso::Rodeo;
-use mir::{ControlFlowGraph, DominatorTree, Function, ValueDef};
+use mir::builder::InstBuilder;
+use mir::cursor::{Cursor, FuncCursor};
+use mir::{ControlFlowGraph, DominatorTree, Function, Unknown, Value, ValueDef, F_ZERO};
 use mir_autodiff::auto_diff;
 use mir_opt::{
     aggressive_dead_code_elimination, dead_code_elimination, inst_combine, simplify_cfg,
-    sparse_conditional_constant_propagation,
+    sparse_conditional_constant_propagation, GVN,
 };
 
 use crate::compiler_db::{CompilationDB, FuncSpec, ModelInfo};
 
+/// The derivatives of the output of a [`FuncSpec`] by all voltages (a zero derivative is not
+/// stored) and by the parameters selected with the `derivative_params` option.
+#[derive(Default)]
+pub struct FuncDerivatives {
+    pub voltages: AHashMap<(Node, Option<Node>), Value>,
+    pub params: Vec<(Parameter, Value)>,
+}
+
 impl FuncSpec {
     pub fn slice_mir(
         &self,
         func: &Function,
         cfg: &ControlFlowGraph,
         intern: &HirInterner,
+        derivatives: Option<&FuncDerivatives>,
     ) -> (Function, ControlFlowGraph) {
         let ret_val = intern.outputs[&PlaceKind::Var(self.var)].unwrap();
-        let mut func = func.clone();
-        let mut cfg = cfg.clone();
-
-        let depbreak_vars: Vec<_> = self
-            .dependency_breaking
-            .iter()
-            .map(|var| intern.params.raw[&ParamKind::HiddenState(*var)])
-            .collect();
-
-        for (val, var) in &intern.tagged_reads {
-            let new_val = if let Some(i) = self.dependency_breaking.iter().position(|it| it == var)
-            {
-                depbreak_vars[i]
-            } else if let ValueDef::Result(inst, _) = func.dfg.value_def(*val) {
-                func.dfg.instr_args(inst)[0]
-            } else {
-                continue;
+        let mut live_values = BitSet::new_empty(func.dfg.num_values());
+        live_values.insert(ret_val);
+        if let Some(derivatives) = derivatives {
+            live_values.extend(derivatives.voltages.values().copied());
+            live_values.extend(derivatives.params.iter().map(|(_, val)| *val));
+        }
+
+        slice_mir(func, cfg, intern, &live_values, &self.dependency_breaking)
+    }
+}
+
+/// Slices the module MIR so that only the operating point variables are calculated. All
+/// operating point variables are calculated by the same function so common subexpressions
+/// are shared with GVN. Returns the (real) value of each variable in [`ModelInfo::op_vars`],
+/// string variables can not be represented as a real and are `None`.
+pub fn slice_opvars_mir(
+    db: &CompilationDB,
+    info: &ModelInfo,
+    func: &Function,
+    cfg: &ControlFlowGraph,
+    intern: &HirInterner,
+) -> (Function, ControlFlowGraph, Vec<Option<Value>>) {
+    let outputs: Vec<_> = info
+        .op_vars
+        .iter()
+        .map(|(var, _)| {
+            let val = intern.outputs.get(&PlaceKind::Var(*var))?.expand()?;
+            Some((val, var.ty(db)))
+        })
+        .collect();
+
+    let mut live_values = BitSet::new_empty(func.dfg.num_values());
+    live_values.extend(outputs.iter().filter_map(|it| Some(it?.0)));
+    let (mut func, mut cfg) = slice_mir(func, cfg, intern, &live_values, &[]);
+
+    // protect the outputs from being replaced by GVN
+    let mut cursor = FuncCursor::new(&mut func).at_exit();
+    let outputs: Vec<_> = outputs
+        .into_iter()
+        .map(|output| {
+            let val = match output? {
+                (val, Type::Real) => val,
+                (val, Type::Integer) => cursor.ins().ifcast(val),
+                _ => return None,
             };
+            Some(cursor.ins().ensure_optbarrier(val))
+        })
+        .collect();
+
+    let mut dom_tree = DominatorTree::default();
+    dom_tree.compute(&func, &cfg, true, false, true);
+    let mut gvn = GVN::default();
+    gvn.init(&func, &dom_tree, intern.params.len() as u32);
+    gvn.solve(&mut func);
+    gvn.remove_unnecessary_insts(&mut func, &dom_tree);
+    gvn.clear(&mut func);
+
+    let mut output_values = BitSet::new_empty(func.dfg.num_values());
+    output_values.extend(outputs.iter().filter_map(|it| *it));
+    dead_code_elimination(&mut func, &output_values);
+    simplify_cfg(&mut func, &mut cfg);
+
+    (func, cfg, outputs)
+}
 
-            func.dfg.replace_uses(*val, new_val)
+/// Collects the information that `sim_back` requires to build the DAE system of the module.
+/// The residuals and jacobians of the DAE system describe the small-signal behaviour of the
+/// model, including the reactive contributions that are dropped from the model functions.
+pub fn collect_sim_module(db: &CompilationDB, info: &ModelInfo) -> Result<sim_back::ModuleInfo> {
+    // the diagnostics were already reported by `ModelInfo::collect`
+    let mut buffer = Buffer::no_color();
+    let mut sink = ConsoleSink::buffer(db, &mut buffer);
+    let modules = sim_back::collect_modules(db, false, &mut sink);
+    drop(sink);
+    let modules = match modules {
+        Some(modules) => modules,
+        None => {
+            io::stderr().write_all(buffer.as_slice())?;
+            bail!("compilation failed")
         }
+    };
+    match modules.into_iter().find(|module| module.module == info.module) {
+        Some(module) => Ok(module),
+        None => bail!("failed to find module {}", info.module.name(db)),
+    }
+}
 
-        simplify_cfg(&mut func, &mut cfg);
+/// Removes everything from `func` that is not required to calculate `live_values`.
+/// Reads of the `dependency_breaking` variables are replaced with (hidden state)
+/// parameters, all other tagged reads are replaced with the variable value.
+fn slice_mir(
+    func: &Function,
+    cfg: &ControlFlowGraph,
+    intern: &HirInterner,
+    live_values: &BitSet<Value>,
+    dependency_breaking: &[Variable],
+) -> (Function, ControlFlowGraph) {
+    let mut func = func.clone();
+    let mut cfg = cfg.clone();
 
-        let mut dom_tree = DominatorTree::default();
-        dom_tree.compute(&func, &cfg, false, true, false);
-        let mut control_dep = SparseBitMatrix::new(0, 0);
-        dom_tree.compute_postdom_frontiers(&cfg, &mut control_dep);
+    let depbreak_vars: Vec<_> = dependency_breaking
+        .iter()
+        .map(|var| intern.params.raw[&ParamKind::HiddenState(*var)])
+        .collect();
 
-        aggressive_dead_code_elimination(
-            &mut func,
-            &mut cfg,
-            &|val, _| val == ret_val,
-            &control_dep,
-        );
-        simplify_cfg(&mut func, &mut cfg);
+    for (val, var) in &intern.tagged_reads {
+        let new_val = if let Some(i) = dependency_breaking.iter().position(|it| it == var) {
+            depbreak_vars[i]
+        } else if let ValueDef::Result(inst, _) = func.dfg.value_def(*val) {
+            func.dfg.instr_args(inst)[0]
+        } else {
+            continue;
+        };
 
-        (func, cfg)
+        func.dfg.replace_uses(*val, new_val)
     }
+
+    simplify_cfg(&mut func, &mut cfg);
+
+    let mut dom_tree = DominatorTree::default();
+    dom_tree.compute(&func, &cfg, false, true, false);
+    let mut control_dep = SparseBitMatrix::new(0, 0);
+    dom_tree.compute_postdom_frontiers(&cfg, &mut control_dep);
+
+    aggressive_dead_code_elimination(
+        &mut func,
+        &mut cfg,
+        &|val, _| live_values.contains(val),
+        &control_dep,
+    );
+    simplify_cfg(&mut func, &mut cfg);
+
+    (func, cfg)
 }
 
+/// Builds the MIR of the module. If `derivative_params` is set, the derivatives of the
+/// functions by all voltages and by `derivative_params` are calculated as well.
 pub fn build_module_mir(
     db: &CompilationDB,
     info: &ModelInfo,
-) -> (Function, HirInterner, Rodeo, ControlFlowGraph) {
+    derivative_params: Option<&[Parameter]>,
+) -> (Function, HirInterner, Rodeo, ControlFlowGraph, Option<Vec<FuncDerivatives>>) {
     let dep_break: AHashSet<_> =
         info.functions.iter().flat_map(|func| func.dependency_breaking.iter().copied()).collect();
 
-    let outputs: AHashSet<_> = info.functions.iter().map(|func| func.var).collect();
+    let op_vars = info.op_vars.iter().map(|(var, _)| *var);
+    let outputs: AHashSet<_> = info.functions.iter().map(|func| func.var).chain(op_vars).collect();
     let mut literals = Rodeo::new();
     let (mut func, mut intern) = MirBuilder::new(
         db,
@@ -113,16 +228,83 @@ pub fn build_module_mir(
 
     let mut dom_tree = DominatorTree::default();
     dom_tree.compute(&func, &cfg, true, false, true);
-    let unknowns = intern.unknowns(&mut func, false);
-    auto_diff(&mut func, &dom_tree, &unknowns, &[]);
-    cfg.clear();
-    cfg.compute(&func);
-    sparse_conditional_constant_propagation(&mut func, &cfg);
-    inst_combine(&mut func);
-    simplify_cfg(&mut func, &mut cfg);
+    let mut unknowns = intern.unknowns(&mut func, false);
+    let derivative_params = match derivative_params {
+        Some(derivative_params) => derivative_params,
+        None => {
+            auto_diff(&mut func, &dom_tree, &unknowns, &[]);
+            optimize(&mut func, &mut cfg);
+            return (func, intern, literals, cfg, None);
+        }
+    };
+
+    // the outputs are differentiated by all voltages and the selected parameters
+    let mut voltages = Vec::new();
+    for (_, kind, val) in intern.live_params(&func.dfg) {
+        if let ParamKind::Voltage { hi, lo } = *kind {
+            voltages.push(((hi, lo), unknowns.unknowns.ensure(val).0));
+        }
+    }
+    let params: Vec<_> = derivative_params
+        .iter()
+        .map(|param| {
+            let val = intern.params.raw.get(&ParamKind::Param(*param)).copied();
+            let val = val.filter(|val| !func.dfg.value_dead(*val));
+            val.map(|val| unknowns.unknowns.ensure(val).0)
+        })
+        .collect();
+    let ret_vals: Vec<_> = info
+        .functions
+        .iter()
+        .map(|spec| intern.outputs[&PlaceKind::Var(spec.var)].unwrap())
+        .collect();
+    let extra_derivatives: Vec<_> = ret_vals
+        .iter()
+        .flat_map(|&ret_val| {
+            let param_unknowns = params.iter().filter_map(|unknown| *unknown);
+            voltages
+                .iter()
+                .map(|(_, unknown)| *unknown)
+                .chain(param_unknowns)
+                .map(move |unknown| (ret_val, unknown))
+        })
+        .collect();
+    let derivatives = auto_diff(&mut func, &dom_tree, &unknowns, &extra_derivatives);
+
+    // protect the derivatives from being optimized away
+    let mut cursor = FuncCursor::new(&mut func).at_exit();
+    let mut derivative = |ret_val, unknown: Unknown| {
+        let val = *derivatives.get(&(ret_val, unknown))?;
+        Some(cursor.ins().ensure_optbarrier(val))
+    };
+    let func_derivatives = ret_vals
+        .iter()
+        .map(|&ret_val| FuncDerivatives {
+            voltages: voltages
+                .iter()
+                .filter_map(|&(voltage, unknown)| Some((voltage, derivative(ret_val, unknown)?)))
+                .collect(),
+            params: derivative_params
+                .iter()
+                .zip(&params)
+                .map(|(param, unknown)| {
+                    let val = unknown.and_then(|unknown| derivative(ret_val, unknown));
+                    (*param, val.unwrap_or(F_ZERO))
+                })
+                .collect(),
+        })
+        .collect();
 
-    (func, intern, literals, cfg)
-    // (func, intern, literals, Vec::new(), cfg)
+    optimize(&mut func, &mut cfg);
+    (func, intern, literals, cfg, Some(func_derivatives))
+}
+
+fn optimize(func: &mut Function, cfg: &mut ControlFlowGraph) {
+    cfg.clear();
+    cfg.compute(func);
+    sparse_conditional_constant_propagation(func, cfg);
+    inst_combine(func);
+    simplify_cfg(func, cfg);
 }
 
 pub fn build_param_init_mir(
@@ -0,0 +1,670 @@
+use std::ffi::{c_void, CStr, CString};
+use std::os::raw::c_char;
+use std::ptr;
+use std::slice;
+use std::sync::Arc;
+
+use anyhow::{bail, Context, Result};
+use camino::Utf8Path;
+
+use crate::api::{
+    register_jit, verilogae_call_fun_parallel_threads, verilogae_fun_current_cnt,
+    verilogae_fun_current_default_cnt, verilogae_fun_current_defaults, verilogae_fun_currents,
+    verilogae_fun_derivative_cnt, verilogae_fun_derivatives, verilogae_fun_ptr,
+    verilogae_fun_voltage_cnt, verilogae_fun_voltage_default_cnt, verilogae_fun_voltage_defaults,
+    verilogae_fun_voltages, verilogae_function_cnt, verilogae_function_symbols,
+    verilogae_functions, verilogae_init_modelcard, verilogae_int_fun_depbreak,
+    verilogae_int_fun_depbreak_cnt, verilogae_int_fun_param_cnt, verilogae_int_fun_params,
+    verilogae_int_param_cnt, verilogae_int_param_descriptions, verilogae_int_param_groups,
+    verilogae_int_param_units, verilogae_int_params, verilogae_jacobian_ptr, verilogae_module_name,
+    verilogae_node_cnt, verilogae_nodes, verilogae_opvars, verilogae_opvars_cnt,
+    verilogae_real_fun_depbreak, verilogae_real_fun_depbreak_cnt, verilogae_real_fun_param_cnt,
+    verilogae_real_fun_params, verilogae_real_param_cnt, verilogae_real_param_descriptions,
+    verilogae_real_param_groups, verilogae_real_param_units, verilogae_real_params,
+    verilogae_str_fun_param_cnt, verilogae_str_fun_params, verilogae_str_param_cnt,
+    verilogae_str_param_descriptions, verilogae_str_param_groups, verilogae_str_param_units,
+    verilogae_str_params, verilogae_unload, FatPtr, Meta, Opts, ParamFlags, VaeFun, VaeJacobian,
+    PARAM_FLAGS_INVALID, PARAM_FLAGS_MAX_INCLUSIVE, PARAM_FLAGS_MIN_INCLUSIVE,
+};
+use crate::params::{ParamInfo, ParamSet, ParamType, ParamValue};
+use crate::{load, load_jit};
+
+/// A compiled Verilog-A model.
+///
+/// This is a safe wrapper around the functions in [`api`](crate::api) that reads all
+/// information about the model once when it is loaded.
+pub struct Model {
+    handle: *const c_void,
+    name: String,
+    nodes: Vec<String>,
+    opvars: Vec<String>,
+    function_names: Vec<String>,
+    functions: Vec<Function>,
+    opvars_function: Option<Function>,
+    small_signal_function: Option<Function>,
+    params: Arc<[ParamInfo]>,
+}
+
+// the loaded library is never modified, the model functions can be called from any thread
+unsafe impl Send for Model {}
+unsafe impl Sync for Model {}
+
+impl Model {
+    /// Compiles the model `path` (or loads it from the cache). If `full_compile` is not set,
+    /// only information about the model is available and [`Model::functions`] is empty.
+    pub fn load(path: &Utf8Path, full_compile: bool, opts: &Opts) -> Result<Model> {
+        let lib = load(path, full_compile, opts)?;
+        unsafe { Model::from_raw(lib.into_raw() as *const c_void, full_compile) }
+    }
+
+    /// Same as [`Model::load`] but compiles the model in memory with a JIT instead of
+    /// building (and caching) a shared library.
+    pub fn load_jit(path: &Utf8Path, full_compile: bool, opts: &Opts) -> Result<Model> {
+        let jit = load_jit(path, full_compile, opts)?;
+        unsafe { Model::from_raw(register_jit(jit), full_compile) }
+    }
+
+    /// Creates a model from a handle returned by
+    /// [`verilogae_load`](crate::api::verilogae_load) or
+    /// [`verilogae_load_jit`](crate::api::verilogae_load_jit). `full_compile` must match the
+    /// value the handle was loaded with.
+    ///
+    /// # Safety
+    ///
+    /// `handle` must be a valid model handle. The model takes ownership of the handle and
+    /// unloads it when it is dropped (or if reading the model fails).
+    pub unsafe fn from_raw(handle: *const c_void, full_compile: bool) -> Result<Model> {
+        let mut model = Model {
+            handle,
+            name: String::new(),
+            nodes: Vec::new(),
+            opvars: Vec::new(),
+            function_names: Vec::new(),
+            functions: Vec::new(),
+            opvars_function: None,
+            small_signal_function: None,
+            params: Arc::new([]),
+        };
+
+        model.name = CStr::from_ptr(verilogae_module_name(handle)).to_string_lossy().into();
+        model.nodes = read_strs(verilogae_nodes(handle), verilogae_node_cnt(handle));
+        model.opvars = read_strs(verilogae_opvars(handle), verilogae_opvars_cnt(handle));
+        let cnt = verilogae_function_cnt(handle);
+        model.function_names = read_strs(verilogae_functions(handle), cnt);
+        model.params = read_params(handle).into();
+        if full_compile {
+            let syms = verilogae_function_symbols(handle);
+            let functions = model.function_names.iter().enumerate().map(|(i, name)| {
+                let sym = *syms.add(i);
+                let fun = verilogae_fun_ptr(handle, sym);
+                if fun.is_none() {
+                    bail!("failed to load the function {name}")
+                }
+                Function::new(handle, name.clone(), sym, fun, &model.params)
+            });
+            model.functions = functions.collect::<Result<_>>()?;
+
+            // these are called with verilogae_call_opvars_parallel instead
+            let opvars = "opvars\0".as_ptr() as *const c_char;
+            let opvars = Function::new(handle, "opvars".to_owned(), opvars, None, &model.params);
+            model.opvars_function = Some(opvars?);
+            let small_signal = "small_signal\0".as_ptr() as *const c_char;
+            let small_signal =
+                Function::new(handle, "small_signal".to_owned(), small_signal, None, &model.params);
+            model.small_signal_function = Some(small_signal?);
+        }
+
+        Ok(model)
+    }
+
+    /// The handle of the model that is passed to the functions in [`api`](crate::api). It
+    /// remains valid until the model is dropped.
+    pub fn as_raw(&self) -> *const c_void {
+        self.handle
+    }
+
+    /// The name of the compiled module.
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    pub fn nodes(&self) -> &[String] {
+        &self.nodes
+    }
+
+    /// The names of all operating point variables.
+    pub fn opvars(&self) -> &[String] {
+        &self.opvars
+    }
+
+    pub fn params(&self) -> &[ParamInfo] {
+        &self.params
+    }
+
+    /// The names of all functions, also available if the model was not fully compiled.
+    pub fn function_names(&self) -> &[String] {
+        &self.function_names
+    }
+
+    pub fn functions(&self) -> &[Function] {
+        &self.functions
+    }
+
+    pub fn function(&self, name: &str) -> Option<&Function> {
+        self.functions.iter().find(|fun| fun.name == name)
+    }
+
+    /// The inputs of the function that calculates all operating point variables. The function
+    /// itself is obtained with [`verilogae_opvars_ptr`](crate::api::verilogae_opvars_ptr) and
+    /// can not be called with [`Function::eval`]. `None` if the model was not fully compiled.
+    pub fn opvars_function(&self) -> Option<&Function> {
+        self.opvars_function.as_ref()
+    }
+
+    /// The inputs of the function that evaluates the DAE system, obtained with
+    /// [`verilogae_small_signal_ptr`](crate::api::verilogae_small_signal_ptr). Like
+    /// [`Model::opvars_function`] it can not be called with [`Function::eval`].
+    pub fn small_signal_function(&self) -> Option<&Function> {
+        self.small_signal_function.as_ref()
+    }
+
+    /// Returns a [`ParamSet`] in which all parameters have their default value.
+    pub fn default_params(&self) -> ParamSet {
+        ParamSet::new(self.params.clone())
+    }
+}
+
+impl Drop for Model {
+    fn drop(&mut self) {
+        unsafe { verilogae_unload(self.handle) }
+    }
+}
+
+/// A function of a [`Model`] that calculates a variable of the Verilog-A source.
+pub struct Function {
+    name: String,
+    fun: VaeFun,
+    /// only generated if derivatives were requested when the model was compiled
+    jacobian: VaeJacobian,
+    derivatives: Vec<String>,
+    voltages: Vec<String>,
+    voltage_defaults: Vec<f64>,
+    currents: Vec<String>,
+    current_defaults: Vec<f64>,
+    /// the position of the parameters in the model parameters
+    real_params: Vec<usize>,
+    int_params: Vec<usize>,
+    str_params: Vec<usize>,
+    real_depbreak: Vec<String>,
+    int_depbreak: Vec<String>,
+    params: Arc<[ParamInfo]>,
+}
+
+impl Function {
+    unsafe fn new(
+        handle: *const c_void,
+        name: String,
+        sym: *const c_char,
+        fun: VaeFun,
+        params: &Arc<[ParamInfo]>,
+    ) -> Result<Function> {
+        let derivatives = read_strs(
+            verilogae_fun_derivatives(handle, sym),
+            verilogae_fun_derivative_cnt(handle, sym),
+        );
+        let jacobian = if fun.is_none() || derivatives.is_empty() {
+            None
+        } else {
+            let jacobian = verilogae_jacobian_ptr(handle, sym);
+            if jacobian.is_none() {
+                bail!("failed to load the jacobian of the function {name}")
+            }
+            jacobian
+        };
+
+        let param_pos = |names: *const *const c_char, cnt, ty| {
+            read_strs(names, cnt)
+                .iter()
+                .map(|name| {
+                    params
+                        .iter()
+                        .position(|param| param.ty == ty && &param.name == name)
+                        .expect("function parameters are model parameters")
+                })
+                .collect()
+        };
+
+        Ok(Function {
+            voltages: read_strs(
+                verilogae_fun_voltages(handle, sym),
+                verilogae_fun_voltage_cnt(handle, sym),
+            ),
+            voltage_defaults: read_vals(
+                verilogae_fun_voltage_defaults(handle, sym),
+                verilogae_fun_voltage_default_cnt(handle, sym),
+            ),
+            currents: read_strs(
+                verilogae_fun_currents(handle, sym),
+                verilogae_fun_current_cnt(handle, sym),
+            ),
+            current_defaults: read_vals(
+                verilogae_fun_current_defaults(handle, sym),
+                verilogae_fun_current_default_cnt(handle, sym),
+            ),
+            real_params: param_pos(
+                verilogae_real_fun_params(handle, sym),
+                verilogae_real_fun_param_cnt(handle, sym),
+                ParamType::Real,
+            ),
+            int_params: param_pos(
+                verilogae_int_fun_params(handle, sym),
+                verilogae_int_fun_param_cnt(handle, sym),
+                ParamType::Integer,
+            ),
+            str_params: param_pos(
+                verilogae_str_fun_params(handle, sym),
+                verilogae_str_fun_param_cnt(handle, sym),
+                ParamType::String,
+            ),
+            real_depbreak: read_strs(
+                verilogae_real_fun_depbreak(handle, sym),
+                verilogae_real_fun_depbreak_cnt(handle, sym),
+            ),
+            int_depbreak: read_strs(
+                verilogae_int_fun_depbreak(handle, sym),
+                verilogae_int_fun_depbreak_cnt(handle, sym),
+            ),
+            name,
+            fun,
+            jacobian,
+            derivatives,
+            params: params.clone(),
+        })
+    }
+
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    /// The voltages the function depends upon.
+    pub fn voltages(&self) -> &[String] {
+        &self.voltages
+    }
+
+    /// The currents the function depends upon.
+    pub fn currents(&self) -> &[String] {
+        &self.currents
+    }
+
+    /// The real dependency breaking variables the function depends upon.
+    pub fn real_depbreak(&self) -> &[String] {
+        &self.real_depbreak
+    }
+
+    /// The integer dependency breaking variables the function depends upon.
+    pub fn int_depbreak(&self) -> &[String] {
+        &self.int_depbreak
+    }
+
+    /// The default values of the first [`Function::voltages`]. The remaining voltages have
+    /// no default value and must always be provided.
+    pub fn voltage_defaults(&self) -> &[f64] {
+        &self.voltage_defaults
+    }
+
+    /// The default values of the first [`Function::currents`].
+    pub fn current_defaults(&self) -> &[f64] {
+        &self.current_defaults
+    }
+
+    /// The position of the real parameters the function depends upon in
+    /// [`Model::params`].
+    pub fn real_params(&self) -> &[usize] {
+        &self.real_params
+    }
+
+    /// The position of the integer parameters the function depends upon in
+    /// [`Model::params`].
+    pub fn int_params(&self) -> &[usize] {
+        &self.int_params
+    }
+
+    /// The position of the string parameters the function depends upon in
+    /// [`Model::params`].
+    pub fn str_params(&self) -> &[usize] {
+        &self.str_params
+    }
+
+    /// The voltages and parameters the jacobian function calculates derivatives by.
+    /// Empty if no derivatives were requested when the model was compiled.
+    pub fn derivatives(&self) -> &[String] {
+        &self.derivatives
+    }
+
+    /// The compiled function, which can be called with
+    /// [`verilogae_call_fun_parallel_threads`] by bindings that pass the arguments directly.
+    /// `None` for [`Model::opvars_function`] and [`Model::small_signal_function`].
+    pub fn as_raw(&self) -> VaeFun {
+        self.fun
+    }
+
+    /// The compiled jacobian function (`None` if [`Function::derivatives`] is empty).
+    pub fn jacobian_as_raw(&self) -> VaeJacobian {
+        self.jacobian
+    }
+
+    /// Evaluates the function at all operating points in `voltages` and returns one value per
+    /// operating point. The evaluation is spread across one thread per logical core.
+    pub fn eval(&self, params: &ParamSet, voltages: &Voltages) -> Result<Vec<f64>> {
+        self.eval_with_threads(params, voltages, 0)
+    }
+
+    /// Same as [`Function::eval`] but uses `threads` threads (or one thread per logical core
+    /// if `threads` is 0).
+    pub fn eval_with_threads(
+        &self,
+        params: &ParamSet,
+        voltages: &Voltages,
+        threads: usize,
+    ) -> Result<Vec<f64>> {
+        assert!(
+            Arc::ptr_eq(&params.info, &self.params),
+            "parameters were created for a different model"
+        );
+        if self.fun.is_none() {
+            bail!("{} can not be evaluated with Function::eval", self.name)
+        }
+
+        let mut args = self.raw_args(params, voltages)?;
+        let mut out = vec![0f64; args.cnt];
+        let res = unsafe {
+            verilogae_call_fun_parallel_threads(
+                self.fun,
+                args.cnt,
+                threads,
+                args.voltages.as_mut_ptr(),
+                args.currents.as_mut_ptr(),
+                args.real_params.as_mut_ptr(),
+                args.int_params.as_mut_ptr(),
+                args.str_params.as_mut_ptr(),
+                args.real_depbreak.as_mut_ptr(),
+                args.int_depbreak.as_mut_ptr(),
+                &mut args.temperature,
+                out.as_mut_ptr() as *mut c_void,
+            )
+        };
+        if res != 0 {
+            bail!("failed to evaluate {}", self.name)
+        }
+        Ok(out)
+    }
+
+    /// Converts `params` and `voltages` to the arguments of the compiled function. The
+    /// arguments point into `voltages` and must not outlive it.
+    pub(crate) fn raw_args(&self, params: &ParamSet, voltages: &Voltages) -> Result<RawArgs> {
+        let cnt = voltages.op_cnt()?;
+        let input = |name: &str, default: Option<f64>| -> Result<FatPtr<f64>> {
+            let mut res = FatPtr { ptr: ptr::null_mut(), meta: Meta { stride: 0 } };
+            match (voltages.get(name), default) {
+                (Some([val]), _) => res.set_scalar(*val),
+                (Some(vals), _) => res.set_ptr(vals.as_ptr() as *mut f64, 1),
+                (None, Some(default)) => res.set_scalar(default),
+                (None, None) => bail!("missing value for {name:?}"),
+            }
+            Ok(res)
+        };
+        let int_input = |name: &str, default: Option<i32>| -> Result<FatPtr<i32>> {
+            let mut res = FatPtr { ptr: ptr::null_mut(), meta: Meta { stride: 0 } };
+            match (voltages.get(name), default) {
+                (Some(&[val]), _) if val.fract() == 0.0 => res.set_scalar(val as i32),
+                (Some(_), _) => bail!("{name} must be a single integer value"),
+                (None, Some(default)) => res.set_scalar(default),
+                (None, None) => bail!("missing value for {name:?}"),
+            }
+            Ok(res)
+        };
+        let inputs = |names: &[String], defaults: &[f64]| {
+            names
+                .iter()
+                .enumerate()
+                .map(|(i, name)| input(name, defaults.get(i).copied()))
+                .collect::<Result<Vec<_>>>()
+        };
+
+        let voltage_vals = inputs(&self.voltages, &self.voltage_defaults)?;
+        let current_vals = inputs(&self.currents, &self.current_defaults)?;
+
+        // real parameters can be passed with the voltages to sweep them
+        let real_params = self
+            .real_params
+            .iter()
+            .map(|&pos| match params.vals[pos] {
+                ParamValue::Real(val) => input(&self.params[pos].name, Some(val)),
+                _ => unreachable!("parameter types are checked by ParamSet::set"),
+            })
+            .collect::<Result<Vec<_>>>()?;
+        let int_params = self
+            .int_params
+            .iter()
+            .map(|&pos| match params.vals[pos] {
+                ParamValue::Integer(val) => int_input(&self.params[pos].name, Some(val)),
+                _ => unreachable!("parameter types are checked by ParamSet::set"),
+            })
+            .collect::<Result<Vec<_>>>()?;
+        let strings = self
+            .str_params
+            .iter()
+            .map(|&pos| match &params.vals[pos] {
+                ParamValue::String(val) => CString::new(val.as_str())
+                    .with_context(|| format!("invalid value for {}", self.params[pos].name)),
+                _ => unreachable!("parameter types are checked by ParamSet::set"),
+            })
+            .collect::<Result<Vec<_>>>()?;
+        let str_params: Vec<_> = strings.iter().map(|val| val.as_ptr()).collect();
+
+        let real_depbreak = inputs(&self.real_depbreak, &[])?;
+        let int_depbreak = self
+            .int_depbreak
+            .iter()
+            .map(|name| int_input(name, None))
+            .collect::<Result<Vec<_>>>()?;
+        let temperature = input("temperature", None)?;
+
+        Ok(RawArgs {
+            cnt,
+            voltages: voltage_vals,
+            currents: current_vals,
+            real_params,
+            int_params,
+            _strings: strings,
+            str_params,
+            real_depbreak,
+            int_depbreak,
+            temperature,
+        })
+    }
+}
+
+/// The arguments of a compiled function created by [`Function::raw_args`].
+pub(crate) struct RawArgs {
+    /// the number of operating points
+    pub cnt: usize,
+    pub voltages: Vec<FatPtr<f64>>,
+    pub currents: Vec<FatPtr<f64>>,
+    pub real_params: Vec<FatPtr<f64>>,
+    pub int_params: Vec<FatPtr<i32>>,
+    /// the values `str_params` points to
+    _strings: Vec<CString>,
+    pub str_params: Vec<*const c_char>,
+    pub real_depbreak: Vec<FatPtr<f64>>,
+    pub int_depbreak: Vec<FatPtr<i32>>,
+    pub temperature: FatPtr<f64>,
+}
+
+/// The operating points at which a [`Function`] is evaluated.
+///
+/// Besides the voltages this contains the currents, the dependency breaking variables and the
+/// temperature (`temperature`) of each operating point. Real parameters can be added to
+/// overwrite the values from the [`ParamSet`], for example to sweep them.
+/// Every input either has a single value that is used for all operating points or one value
+/// per operating point.
+#[derive(Clone, Debug, Default)]
+pub struct Voltages {
+    vals: Vec<(String, Vec<f64>)>,
+}
+
+impl Voltages {
+    pub fn new() -> Voltages {
+        Voltages::default()
+    }
+
+    /// Sets the values of the input `name`, replacing any previous values.
+    pub fn set(&mut self, name: impl Into<String>, vals: impl Into<Vec<f64>>) -> &mut Voltages {
+        let name = name.into();
+        let vals = vals.into();
+        match self.vals.iter_mut().find(|(it, _)| *it == name) {
+            Some((_, dst)) => *dst = vals,
+            None => self.vals.push((name, vals)),
+        }
+        self
+    }
+
+    /// Sets the input `name` to `val` at all operating points.
+    pub fn set_scalar(&mut self, name: impl Into<String>, val: f64) -> &mut Voltages {
+        self.set(name, [val])
+    }
+
+    pub fn get(&self, name: &str) -> Option<&[f64]> {
+        self.vals.iter().find(|(it, _)| it == name).map(|(_, vals)| vals.as_slice())
+    }
+
+    /// The number of operating points.
+    pub fn op_cnt(&self) -> Result<usize> {
+        let mut res: Option<(&str, usize)> = None;
+        for (name, vals) in &self.vals {
+            match (vals.len(), res) {
+                (0, _) => bail!("no values provided for {name:?}"),
+                (1, _) => (),
+                (len, None) => res = Some((name.as_str(), len)),
+                (len, Some((other, cnt))) if len != cnt => {
+                    bail!("{name:?} has {len} values but {other:?} has {cnt} values")
+                }
+                _ => (),
+            }
+        }
+        Ok(res.map_or(1, |(_, cnt)| cnt))
+    }
+}
+
+unsafe fn read_params(handle: *const c_void) -> Vec<ParamInfo> {
+    let real_cnt = verilogae_real_param_cnt(handle);
+    let int_cnt = verilogae_int_param_cnt(handle);
+    let str_cnt = verilogae_str_param_cnt(handle);
+
+    // the defaults are followed by the lower and the upper bounds
+    let mut real = vec![0f64; 3 * real_cnt];
+    let mut int = vec![0; 3 * int_cnt];
+    let mut str_ = vec![ptr::null(); str_cnt];
+    let mut flags = vec![ParamFlags::default(); real_cnt + int_cnt + str_cnt];
+    let init = verilogae_init_modelcard(handle).expect("invalid model handle");
+    init(
+        real.as_mut_ptr(),
+        int.as_mut_ptr(),
+        str_.as_mut_ptr(),
+        real.as_mut_ptr().add(real_cnt),
+        int.as_mut_ptr().add(int_cnt),
+        real.as_mut_ptr().add(2 * real_cnt),
+        int.as_mut_ptr().add(2 * int_cnt),
+        flags.as_mut_ptr(),
+    );
+
+    let mut res = Vec::with_capacity(real_cnt + int_cnt + str_cnt);
+    let mut add_params =
+        |ty,
+         cnt,
+         attrs: [*const *const c_char; 4],
+         defaults: Vec<(ParamValue, Option<(ParamValue, ParamValue)>)>| {
+            let [names, units, groups, descriptions] = attrs.map(|attr| read_strs(attr, cnt));
+            let flags = &flags[res.len()..];
+            let params = names.into_iter().zip(units).zip(groups).zip(descriptions).zip(defaults);
+            let params = params.zip(flags).map(
+                |(((((name, unit), group), description), (default, bounds)), &flags)| ParamInfo {
+                    name,
+                    ty,
+                    unit,
+                    group,
+                    description,
+                    default,
+                    bounds,
+                    min_inclusive: flags & PARAM_FLAGS_MIN_INCLUSIVE != 0,
+                    max_inclusive: flags & PARAM_FLAGS_MAX_INCLUSIVE != 0,
+                    invalid_default: flags & PARAM_FLAGS_INVALID != 0,
+                },
+            );
+            res.extend(params)
+        };
+    add_params(
+        ParamType::Real,
+        real_cnt,
+        [
+            verilogae_real_params(handle),
+            verilogae_real_param_units(handle),
+            verilogae_real_param_groups(handle),
+            verilogae_real_param_descriptions(handle),
+        ],
+        (0..real_cnt)
+            .map(|i| {
+                let bounds = (real[real_cnt + i].into(), real[2 * real_cnt + i].into());
+                (real[i].into(), Some(bounds))
+            })
+            .collect(),
+    );
+    add_params(
+        ParamType::Integer,
+        int_cnt,
+        [
+            verilogae_int_params(handle),
+            verilogae_int_param_units(handle),
+            verilogae_int_param_groups(handle),
+            verilogae_int_param_descriptions(handle),
+        ],
+        (0..int_cnt)
+            .map(|i| {
+                let bounds = (int[int_cnt + i].into(), int[2 * int_cnt + i].into());
+                (int[i].into(), Some(bounds))
+            })
+            .collect(),
+    );
+    add_params(
+        ParamType::String,
+        str_cnt,
+        [
+            verilogae_str_params(handle),
+            verilogae_str_param_units(handle),
+            verilogae_str_param_groups(handle),
+            verilogae_str_param_descriptions(handle),
+        ],
+        str_.iter()
+            .map(|&val| (CStr::from_ptr(val).to_string_lossy().into_owned().into(), None))
+            .collect(),
+    );
+    res
+}
+
+/// Reads a list of `cnt` strings exported by a model.
+unsafe fn read_strs(names: *const *const c_char, cnt: usize) -> Vec<String> {
+    if cnt == 0 {
+        return Vec::new();
+    }
+    slice::from_raw_parts(names, cnt)
+        .iter()
+        .map(|&name| CStr::from_ptr(name).to_string_lossy().into_owned())
+        .collect()
+}
+
+unsafe fn read_vals(vals: *const f64, cnt: usize) -> Vec<f64> {
+    if cnt == 0 {
+        return Vec::new();
+    }
+    slice::from_raw_parts(vals, cnt).to_vec()
+}
@@ -53,7 +53,8 @@ impl Opts {
         Ok(target)
     }
 
-    pub(crate) fn cache_dir(&self) -> Result<Utf8PathBuf> {
+    /// The directory in which compiled models are stored.
+    pub fn cache_dir(&self) -> Result<Utf8PathBuf> {
         let res = if self.cache_dir.ptr.is_null() {
             let path = directories_next::ProjectDirs::from("com", "semimod", "verilogae")
                 .context("failed to find cache directory\nhelp: consider setting it manually")?
@@ -91,6 +92,16 @@ impl Opts {
         Self::str_list_iter(&self.macro_flags)
     }
 
+    /// Whether the `{fun}.jacobian` functions are generated. That is the case if
+    /// `derivative_params` is set, an empty list only enables the voltage derivatives.
+    pub(crate) fn derivatives(&self) -> bool {
+        !self.derivative_params.ptr.is_null()
+    }
+
+    pub(crate) fn derivative_params(&self) -> impl Iterator<Item = &str> {
+        Self::str_list_iter(&self.derivative_params)
+    }
+
     #[allow(clippy::type_complexity)]
     pub(crate) fn vfs(&self) -> Result<Option<Vec<(&str, &[u8])>>> {
         if self.vfs.ptr.is_null() {
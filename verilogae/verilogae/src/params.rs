@@ -0,0 +1,198 @@
+use std::fmt;
+use std::fs;
+use std::sync::Arc;
+
+use anyhow::{anyhow, bail, Context, Result};
+use camino::Utf8Path;
+use modelcard::{parse_json, parse_models, select_bin, write_model, Value, BIN_PARAMS};
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ParamType {
+    Real,
+    Integer,
+    String,
+}
+
+impl fmt::Display for ParamType {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ParamType::Real => f.write_str("real"),
+            ParamType::Integer => f.write_str("integer"),
+            ParamType::String => f.write_str("string"),
+        }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum ParamValue {
+    Real(f64),
+    Integer(i32),
+    String(String),
+}
+
+impl From<f64> for ParamValue {
+    fn from(val: f64) -> Self {
+        ParamValue::Real(val)
+    }
+}
+
+impl From<i32> for ParamValue {
+    fn from(val: i32) -> Self {
+        ParamValue::Integer(val)
+    }
+}
+
+impl From<&str> for ParamValue {
+    fn from(val: &str) -> Self {
+        ParamValue::String(val.to_owned())
+    }
+}
+
+impl From<String> for ParamValue {
+    fn from(val: String) -> Self {
+        ParamValue::String(val)
+    }
+}
+
+impl From<Value> for ParamValue {
+    fn from(val: Value) -> Self {
+        match val {
+            Value::Real(val) => ParamValue::Real(val),
+            Value::Int(val) => ParamValue::Integer(val),
+            Value::Str(val) => ParamValue::String(val),
+        }
+    }
+}
+
+impl From<ParamValue> for Value {
+    fn from(val: ParamValue) -> Self {
+        match val {
+            ParamValue::Real(val) => Value::Real(val),
+            ParamValue::Integer(val) => Value::Int(val),
+            ParamValue::String(val) => Value::Str(val),
+        }
+    }
+}
+
+/// A parameter of a model.
+#[derive(Clone, Debug)]
+pub struct ParamInfo {
+    pub name: String,
+    pub ty: ParamType,
+    pub unit: String,
+    pub group: String,
+    pub description: String,
+    pub default: ParamValue,
+    /// The lower and upper bound of real and integer parameters.
+    pub bounds: Option<(ParamValue, ParamValue)>,
+    pub min_inclusive: bool,
+    pub max_inclusive: bool,
+    /// Whether the default value lies outside of the bounds.
+    pub invalid_default: bool,
+}
+
+/// The values of all parameters of a [`Model`](crate::Model), created with
+/// [`Model::default_params`](crate::Model::default_params).
+#[derive(Clone, Debug)]
+pub struct ParamSet {
+    pub(crate) info: Arc<[ParamInfo]>,
+    pub(crate) vals: Vec<ParamValue>,
+}
+
+impl ParamSet {
+    pub(crate) fn new(info: Arc<[ParamInfo]>) -> ParamSet {
+        let vals = info.iter().map(|param| param.default.clone()).collect();
+        ParamSet { info, vals }
+    }
+
+    pub fn params(&self) -> &[ParamInfo] {
+        &self.info
+    }
+
+    pub fn contains(&self, name: &str) -> bool {
+        self.position(name).is_some()
+    }
+
+    pub fn get(&self, name: &str) -> Option<&ParamValue> {
+        self.position(name).map(|pos| &self.vals[pos])
+    }
+
+    /// Sets the parameter `name` to `val`. Integers are accepted for real parameters and
+    /// real numbers without fractional part for integer parameters.
+    pub fn set(&mut self, name: &str, val: impl Into<ParamValue>) -> Result<()> {
+        let pos = match self.position(name) {
+            Some(pos) => pos,
+            None => bail!("unknown parameter {name}"),
+        };
+        let ty = self.info[pos].ty;
+        self.vals[pos] = match (ty, val.into()) {
+            (ParamType::Real, ParamValue::Real(val)) => ParamValue::Real(val),
+            (ParamType::Real, ParamValue::Integer(val)) => ParamValue::Real(val as f64),
+            (ParamType::Integer, ParamValue::Integer(val)) => ParamValue::Integer(val),
+            (ParamType::Integer, ParamValue::Real(val)) if val.fract() == 0.0 => {
+                ParamValue::Integer(val as i32)
+            }
+            (ParamType::String, ParamValue::String(val)) => ParamValue::String(val),
+            _ => bail!("parameter {name} must be a {ty} value"),
+        };
+        Ok(())
+    }
+
+    /// Sets the parameters in the JSON file `path`, which must contain a single object
+    /// that maps parameter names to their values.
+    pub fn load_json(&mut self, path: &Utf8Path) -> Result<()> {
+        let src = fs::read_to_string(path).with_context(|| format!("failed to read {path}"))?;
+        let params = parse_json(&src)
+            .map_err(|err| anyhow!(err))
+            .with_context(|| format!("failed to parse {path}"))?;
+        for (name, val) in params {
+            self.set(&name, val).with_context(|| format!("failed to load {path}"))?;
+        }
+        Ok(())
+    }
+
+    /// Sets the parameters of the `.model` card in the SPICE file `path` that matches `name`
+    /// (if provided) and the geometry `l`/`w`. The parameters remain unchanged if the card
+    /// contains an unknown parameter or an illegal value.
+    pub fn load_spice(
+        &mut self,
+        path: &Utf8Path,
+        name: Option<&str>,
+        l: Option<f64>,
+        w: Option<f64>,
+    ) -> Result<()> {
+        let src = fs::read_to_string(path).with_context(|| format!("failed to read {path}"))?;
+        let models = parse_models(&src)
+            .map_err(|err| anyhow!("invalid SPICE model card: {err}"))
+            .with_context(|| format!("failed to parse {path}"))?;
+        let model = select_bin(&models, name, l, w).map_err(|err| anyhow!(err))?;
+
+        let mut res = self.clone();
+        for (param, val) in &model.params {
+            // SPICE is case insensitive, but Verilog-A is not: prefer exact matches
+            let pos = self.position(param).or_else(|| {
+                self.info.iter().position(|info| info.name.eq_ignore_ascii_case(param))
+            });
+            let pos = match pos {
+                Some(pos) => pos,
+                None if BIN_PARAMS.iter().any(|it| it.eq_ignore_ascii_case(param)) => continue,
+                None => bail!("model card '{}' sets unknown parameter '{param}'", model.name),
+            };
+            res.set(&self.info[pos].name, val.clone()).with_context(|| {
+                format!("model card '{}' sets '{param}' to an illegal value", model.name)
+            })?;
+        }
+        *self = res;
+        Ok(())
+    }
+
+    /// Returns a SPICE `.model` card called `name` of type `ty` that sets all parameters.
+    pub fn to_spice(&self, name: &str, ty: &str) -> String {
+        let params = self.info.iter().zip(&self.vals);
+        write_model(name, ty, params.map(|(info, val)| (&*info.name, val.clone().into())))
+    }
+
+    fn position(&self, name: &str) -> Option<usize> {
+        self.info.iter().position(|param| param.name == name)
+    }
+}
@@ -0,0 +1,441 @@
+use std::ffi::CStr;
+use std::slice;
+
+use camino::Utf8PathBuf;
+
+use crate::api::{
+    verilogae_call_opvars_parallel, verilogae_opvars_ptr, verilogae_small_signal_cols,
+    verilogae_small_signal_entry_cnt, verilogae_small_signal_ptr, verilogae_small_signal_rows,
+    verilogae_small_signal_unknown_cnt, verilogae_small_signal_unknowns, Opts,
+};
+use crate::{cache, Model, ParamSet, ParamType, ParamValue, Voltages};
+
+const P_K: f64 = 1.380_649e-23;
+const P_Q: f64 = 1.602_176_634e-19;
+
+fn test_model() -> Utf8PathBuf {
+    Utf8PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("../tests/diode_vae.va")
+}
+
+fn rc_model() -> Utf8PathBuf {
+    Utf8PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("../tests/rc_vae.va")
+}
+
+fn test_opts(name: &str) -> Opts {
+    let cache_dir = std::env::temp_dir().join(name);
+    let cache_dir = cache_dir.to_str().expect("only utf8 paths are supported");
+    Opts { cache_dir: Box::<str>::from(cache_dir).into(), ..Opts::default() }
+}
+
+fn eval_all(model: &Model, vd: &[f64]) -> Vec<(String, Vec<f64>)> {
+    let params = model.default_params();
+    model
+        .functions()
+        .iter()
+        .map(|fun| {
+            let mut voltages = Voltages::new();
+            for voltage in fun.voltages() {
+                voltages.set(voltage.as_str(), vd);
+            }
+            voltages.set_scalar("temperature", 300.0);
+            (fun.name().to_owned(), fun.eval(&params, &voltages).unwrap())
+        })
+        .collect()
+}
+
+#[test]
+fn jit_matches_library() {
+    let path = test_model();
+    let opts = test_opts("verilogae_test_jit");
+    let lib = Model::load(&path, true, &opts).unwrap();
+    let jit = Model::load_jit(&path, true, &opts).unwrap();
+
+    assert_eq!(jit.name(), "diode_vae");
+    assert_eq!(jit.name(), lib.name());
+    assert_eq!(jit.nodes(), lib.nodes());
+    assert_eq!(jit.function_names(), lib.function_names());
+    let param_names = |model: &Model| -> Vec<String> {
+        model.params().iter().map(|param| param.name.clone()).collect()
+    };
+    assert_eq!(param_names(&jit), param_names(&lib));
+
+    let vd = [0.3, 0.5, 0.7];
+    let res = eval_all(&jit, &vd);
+    assert_eq!(res, eval_all(&lib, &vd));
+
+    let (_, id) = res.iter().find(|(name, _)| name == "id").expect("id is retrieved");
+    let vt = P_K * 300.0 / P_Q;
+    for (id, vd) in id.iter().zip(vd) {
+        let expected = 1e-14 * ((vd / vt).exp() - 1.0);
+        assert!((id - expected).abs() <= 1e-9 * expected, "{id} != {expected}");
+    }
+}
+
+#[test]
+fn model_metadata() {
+    let opts = test_opts("verilogae_test_metadata");
+    let model = Model::load(&test_model(), true, &opts).unwrap();
+    assert_eq!(model.nodes(), ["A", "C"]);
+
+    let param = |name: &str| model.params().iter().find(|param| param.name == name).unwrap();
+    let is = param("is");
+    assert_eq!(is.ty, ParamType::Real);
+    assert_eq!(is.default, ParamValue::Real(1e-14));
+    assert_eq!(is.bounds, Some((ParamValue::Real(0.0), ParamValue::Real(f64::INFINITY))));
+    assert!(!is.min_inclusive && !is.max_inclusive && !is.invalid_default);
+    let mult = param("mult");
+    assert_eq!(mult.ty, ParamType::Integer);
+    assert_eq!(mult.default, ParamValue::Integer(1));
+    assert_eq!(mult.bounds.as_ref().map(|(min, _)| min), Some(&ParamValue::Integer(1)));
+    assert!(mult.min_inclusive && !mult.invalid_default);
+
+    let names = |ids: &[usize]| -> Vec<&str> {
+        let mut res: Vec<_> = ids.iter().map(|&id| model.params()[id].name.as_str()).collect();
+        res.sort_unstable();
+        res
+    };
+    let id = model.function("id").unwrap();
+    assert_eq!(id.voltages().len(), 1);
+    assert!(id.currents().is_empty());
+    assert_eq!(names(id.real_params()), ["is", "n"]);
+    assert_eq!(names(id.int_params()), ["mult"]);
+    assert!(id.str_params().is_empty());
+    // the jacobian is only generated if derivatives were requested
+    assert!(id.derivatives().is_empty());
+    assert!(id.as_raw().is_some());
+    assert!(id.jacobian_as_raw().is_none());
+    let vt = model.function("vt").unwrap();
+    assert!(vt.voltages().is_empty());
+    assert!(vt.real_params().is_empty());
+
+    // these are called through the raw API instead
+    let opvars = model.opvars_function().unwrap();
+    assert!(opvars.as_raw().is_none());
+    let err = opvars.eval(&model.default_params(), &Voltages::new()).unwrap_err();
+    assert!(err.to_string().contains("can not be evaluated"), "{err}");
+    assert!(model.small_signal_function().is_some());
+}
+
+#[test]
+fn eval_opvars() {
+    let opts = test_opts("verilogae_test_opvars");
+    let model = Model::load(&rc_model(), true, &opts).unwrap();
+    // vt has neither a description nor units
+    let mut opvars = model.opvars().to_vec();
+    opvars.sort_unstable();
+    assert_eq!(opvars, ["ir", "pwr", "qj"]);
+
+    let fun = model.opvars_function().unwrap();
+    let mut voltage_names = fun.voltages().to_vec();
+    voltage_names.sort_unstable();
+    assert_eq!(voltage_names, ["br_AB", "br_BC"]);
+
+    let mut params = model.default_params();
+    params.set("r", 2e3).unwrap();
+    let vab = [0.5, 1.0, -2.0];
+    let vbc = [0.1, 0.2, -0.3];
+    let mut voltages = Voltages::new();
+    voltages.set("br_AB", vab).set("br_BC", vbc).set_scalar("temperature", 300.0);
+
+    let mut args = fun.raw_args(&params, &voltages).unwrap();
+    assert_eq!(args.cnt, 3);
+    let mut res = vec![vec![f64::NAN; args.cnt]; model.opvars().len()];
+    let mut dsts: Vec<_> = res.iter_mut().map(|vals| vals.as_mut_ptr()).collect();
+    let ret = unsafe {
+        verilogae_call_opvars_parallel(
+            verilogae_opvars_ptr(model.as_raw()),
+            args.cnt,
+            1,
+            args.voltages.as_mut_ptr(),
+            args.currents.as_mut_ptr(),
+            args.real_params.as_mut_ptr(),
+            args.int_params.as_mut_ptr(),
+            args.str_params.as_mut_ptr(),
+            &mut args.temperature,
+            dsts.as_mut_ptr(),
+        )
+    };
+    assert_eq!(ret, 0);
+
+    for (name, vals) in model.opvars().iter().zip(&res) {
+        for (i, &val) in vals.iter().enumerate() {
+            let expected = match name.as_str() {
+                "ir" => vab[i] / 2e3,
+                "pwr" => vab[i] * vab[i] / 2e3,
+                "qj" => 1e-15 * vbc[i] * vbc[i],
+                _ => unreachable!(),
+            };
+            assert!(
+                (val - expected).abs() <= 1e-12 * expected.abs(),
+                "{name}: {val} != {expected}"
+            );
+        }
+    }
+}
+
+/// The DAE system evaluated at every operating point. The residuals are indexed by
+/// `[unknown][op]` and the jacobians by `[row][col][op]`.
+struct SmallSignal {
+    unknowns: Vec<String>,
+    resist_residual: Vec<Vec<f64>>,
+    react_residual: Vec<Vec<f64>>,
+    resist_jacobian: Vec<Vec<Vec<f64>>>,
+    react_jacobian: Vec<Vec<Vec<f64>>>,
+}
+
+fn eval_small_signal(model: &Model, params: &ParamSet, voltages: &Voltages) -> SmallSignal {
+    let handle = model.as_raw();
+    let fun = model.small_signal_function().unwrap();
+    let mut args = fun.raw_args(params, voltages).unwrap();
+    let cnt = args.cnt;
+
+    let (unknowns, rows, cols) = unsafe {
+        let names = slice::from_raw_parts(
+            verilogae_small_signal_unknowns(handle),
+            verilogae_small_signal_unknown_cnt(handle),
+        );
+        let unknowns: Vec<_> =
+            names.iter().map(|&name| CStr::from_ptr(name).to_str().unwrap().to_owned()).collect();
+        let entry_cnt = verilogae_small_signal_entry_cnt(handle);
+        let rows = slice::from_raw_parts(verilogae_small_signal_rows(handle), entry_cnt);
+        let cols = slice::from_raw_parts(verilogae_small_signal_cols(handle), entry_cnt);
+        (unknowns, rows, cols)
+    };
+
+    let n = unknowns.len();
+    let mut residual = vec![vec![f64::NAN; cnt]; 2 * n];
+    let mut entries = vec![vec![f64::NAN; cnt]; 2 * rows.len()];
+    let mut dsts: Vec<_> =
+        residual.iter_mut().chain(&mut entries).map(|vals| vals.as_mut_ptr()).collect();
+    let ret = unsafe {
+        verilogae_call_opvars_parallel(
+            verilogae_small_signal_ptr(handle),
+            cnt,
+            1,
+            args.voltages.as_mut_ptr(),
+            args.currents.as_mut_ptr(),
+            args.real_params.as_mut_ptr(),
+            args.int_params.as_mut_ptr(),
+            args.str_params.as_mut_ptr(),
+            &mut args.temperature,
+            dsts.as_mut_ptr(),
+        )
+    };
+    assert_eq!(ret, 0);
+
+    // entries that do not appear in the jacobian are zero
+    let mut jacobians = vec![vec![vec![vec![0f64; cnt]; n]; n]; 2];
+    for (i, vals) in entries.into_iter().enumerate() {
+        let entry = i % rows.len();
+        jacobians[i / rows.len()][rows[entry] as usize][cols[entry] as usize] = vals;
+    }
+    let react_residual = residual.split_off(n);
+    let react_jacobian = jacobians.pop().unwrap();
+    let resist_jacobian = jacobians.pop().unwrap();
+    SmallSignal {
+        unknowns,
+        resist_residual: residual,
+        react_residual,
+        resist_jacobian,
+        react_jacobian,
+    }
+}
+
+#[test]
+fn small_signal() {
+    const R: f64 = 2e3;
+    const C: f64 = 3e-12;
+    const CJ: f64 = 1e-15;
+    const VB: f64 = 0.5;
+    const H: f64 = 1e-6;
+
+    let opts = test_opts("verilogae_test_small_signal");
+    let model = Model::load(&rc_model(), true, &opts).unwrap();
+    let mut params = model.default_params();
+    params.set("r", R).unwrap();
+    params.set("c", C).unwrap();
+
+    // the second operating point is evaluated, the others are used for finite differences
+    let mut voltages = Voltages::new();
+    voltages
+        .set_scalar("br_A", 1.0)
+        .set("br_B", [VB - H, VB, VB + H])
+        .set_scalar("br_C", 0.0)
+        .set_scalar("temperature", 300.0);
+    let res = eval_small_signal(&model, &params, &voltages);
+    let mut unknowns = res.unknowns.clone();
+    unknowns.sort_unstable();
+    assert_eq!(unknowns, ["br_A", "br_B", "br_C"]);
+    let [a, b, c] =
+        ["br_A", "br_B", "br_C"].map(|name| res.unknowns.iter().position(|it| it == name).unwrap());
+
+    let assert_close = |val: f64, expected: f64, rtol: f64| {
+        assert!((val - expected).abs() <= rtol * expected.abs(), "{val} != {expected}");
+    };
+    let (g, cap) = (&res.resist_jacobian, &res.react_jacobian);
+
+    // the linear RC between A and B: G = 1/R and C = C
+    for op in 0..3 {
+        assert_close(g[a][a][op], 1.0 / R, 1e-12);
+        assert_close(g[a][b][op], -1.0 / R, 1e-12);
+        assert_close(cap[a][a][op], C, 1e-12);
+        assert_close(cap[a][b][op], -C, 1e-12);
+        assert_eq!(g[a][c][op], 0.0);
+        assert_eq!(cap[a][c][op], 0.0);
+    }
+    assert_close(res.resist_residual[a][1], (1.0 - VB) / R, 1e-12);
+    assert_close(res.react_residual[a][1], C * (1.0 - VB), 1e-12);
+
+    // the junction between B and C is nonlinear, compare with central differences
+    let fd = |residual: &[Vec<f64>]| (residual[b][2] - residual[b][0]) / (2.0 * H);
+    assert_close(g[b][b][1], fd(&res.resist_residual), 1e-6);
+    assert_close(cap[b][b][1], fd(&res.react_residual), 1e-6);
+    assert_close(cap[b][b][1], C + 2.0 * CJ * VB, 1e-12);
+    assert_close(g[b][c][1], -g[b][b][1] + 1.0 / R, 1e-9);
+}
+
+#[test]
+fn eval_inputs() {
+    let opts = test_opts("verilogae_test_eval_inputs");
+    let model = Model::load(&test_model(), true, &opts).unwrap();
+    let params = model.default_params();
+    let id = model.function("id").unwrap();
+    let vd = id.voltages()[0].as_str();
+
+    // the temperature has no default value
+    let mut voltages = Voltages::new();
+    voltages.set(vd, [0.5]);
+    assert!(id.eval(&params, &voltages).is_err());
+    voltages.set_scalar("temperature", 300.0);
+    let base = id.eval(&params, &voltages).unwrap();
+    assert_eq!(base.len(), 1);
+
+    // real parameters can be swept, single values are used for all operating points
+    voltages.set("is", [1e-14, 2e-14, 4e-14]);
+    let swept = id.eval(&params, &voltages).unwrap();
+    assert_eq!(swept.len(), 3);
+    assert_eq!(swept[0], base[0]);
+    assert!((swept[2] - 4.0 * base[0]).abs() <= 1e-12 * swept[2]);
+
+    // all inputs with multiple values must have the same length
+    voltages.set(vd, [0.5, 0.6]);
+    assert!(id.eval(&params, &voltages).is_err());
+    voltages.set(vd, [0.5]);
+
+    // integer parameters can only be overwritten with a single integer
+    voltages.set("mult", [2.0]);
+    let doubled = id.eval(&params, &voltages).unwrap();
+    assert!((doubled[2] - 2.0 * swept[2]).abs() <= 1e-12 * doubled[2]);
+    voltages.set("mult", [1.5]);
+    assert!(id.eval(&params, &voltages).is_err());
+    voltages.set("mult", [1.0, 2.0, 3.0]);
+    assert!(id.eval(&params, &voltages).is_err());
+}
+
+#[test]
+fn operating_point_count() {
+    let mut voltages = Voltages::new();
+    assert_eq!(voltages.op_cnt().unwrap(), 1);
+    voltages.set_scalar("temperature", 300.0);
+    assert_eq!(voltages.op_cnt().unwrap(), 1);
+    voltages.set("a", [0.1, 0.2]).set("b", [0.3, 0.4]);
+    assert_eq!(voltages.op_cnt().unwrap(), 2);
+
+    voltages.set("c", [0.1, 0.2, 0.3]);
+    let err = voltages.op_cnt().unwrap_err().to_string();
+    assert!(err.contains("\"c\" has 3 values"), "{err}");
+    voltages.set("c", Vec::new());
+    assert!(voltages.op_cnt().is_err());
+}
+
+#[test]
+fn jit_without_functions() {
+    let opts = test_opts("verilogae_test_jit_info");
+    let model = Model::load_jit(&test_model(), false, &opts).unwrap();
+    assert_eq!(model.name(), "diode_vae");
+    assert!(model.function_names().iter().any(|name| name == "id"));
+    assert!(model.functions().is_empty());
+    let mut params = model.default_params();
+    params.set("mult", 2).unwrap();
+    assert!(params.set("mult", 0.5).is_err());
+}
+
+#[test]
+fn spice_modelcard() {
+    let opts = test_opts("verilogae_test_spice");
+    let model = Model::load_jit(&test_model(), false, &opts).unwrap();
+    let dir = Utf8PathBuf::from_path_buf(std::env::temp_dir().join("verilogae_test_spice_cards"))
+        .unwrap();
+    std::fs::create_dir_all(&dir).unwrap();
+    let path = dir.join("diode.lib");
+    std::fs::write(
+        &path,
+        "* binned diode model cards
+.model d.1 diode_vae lmin=1u lmax=10u IS=2f
++ n=1.5 mult=2
+.model d.2 diode_vae lmin=10u lmax=100u is=3f
+.model bad diode_vae is=1f foo=2
+.model bad_int diode_vae mult=1.5
+",
+    )
+    .unwrap();
+
+    let mut params = model.default_params();
+    params.load_spice(&path, Some("d"), Some(5e-6), None).unwrap();
+    assert_eq!(params.get("is"), Some(&ParamValue::Real(2e-15)));
+    assert_eq!(params.get("n"), Some(&ParamValue::Real(1.5)));
+    assert_eq!(params.get("mult"), Some(&ParamValue::Integer(2)));
+
+    params.load_spice(&path, Some("d"), Some(50e-6), None).unwrap();
+    assert_eq!(params.get("is"), Some(&ParamValue::Real(3e-15)));
+    assert_eq!(params.get("n"), Some(&ParamValue::Real(1.5)));
+
+    // overlapping or missing bins are rejected
+    assert!(params.load_spice(&path, Some("d"), None, None).is_err());
+    assert!(params.load_spice(&path, Some("d"), Some(1.0), None).is_err());
+    // invalid cards do not change the parameters
+    assert!(params.load_spice(&path, Some("bad"), None, None).is_err());
+    assert!(params.load_spice(&path, Some("bad_int"), None, None).is_err());
+    assert_eq!(params.get("is"), Some(&ParamValue::Real(3e-15)));
+
+    // cards written by to_spice are read back unchanged
+    let card = params.to_spice("copy", model.name());
+    assert!(card.starts_with(".model copy diode_vae\n"), "{card}");
+    let copy_path = dir.join("copy.lib");
+    std::fs::write(&copy_path, card).unwrap();
+    let mut copy = model.default_params();
+    copy.load_spice(&copy_path, None, None, None).unwrap();
+    assert_eq!(copy.vals, params.vals);
+
+    std::fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn clean_cache() {
+    let opts = test_opts("verilogae_test_clean");
+    let dir = opts.cache_dir().unwrap();
+    if dir.exists() {
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+    assert_eq!(cache::clean(&opts).unwrap(), 0);
+
+    Model::load(&test_model(), true, &opts).unwrap();
+    Model::load(&test_model(), false, &opts).unwrap();
+    let entries = cache::entries(&opts).unwrap();
+    assert_eq!(entries.len(), 2);
+    assert!(entries.iter().any(|entry| entry.full_compile));
+    assert!(entries.iter().any(|entry| !entry.full_compile));
+
+    // leftovers of an aborted compilation are removed, unrelated files are kept
+    std::fs::write(dir.join("aborted.o"), b"").unwrap();
+    std::fs::write(dir.join("unrelated.txt"), b"").unwrap();
+    std::fs::create_dir(dir.join("subdir.mod")).unwrap();
+
+    assert_eq!(cache::clean(&opts).unwrap(), 3);
+    assert!(cache::entries(&opts).unwrap().is_empty());
+    assert!(dir.join("unrelated.txt").exists());
+    assert!(dir.join("subdir.mod").exists());
+    assert_eq!(cache::clean(&opts).unwrap(), 0);
+
+    std::fs::remove_dir_all(&dir).unwrap();
+}
@@ -36,6 +36,42 @@ pub type VaeFun = ::std::option::Option<
         arg10: *mut ::std::os::raw::c_void,
     ),
 >;
+#[doc = " A model function that also calculates the derivatives listed in the `derivatives` global."]
+#[doc = " The last argument contains one pointer per derivative, derivatives are not calculated"]
+#[doc = " if their pointer is null."]
+pub type VaeJacobian = ::std::option::Option<
+    unsafe extern "C" fn(
+        arg1: usize,
+        arg2: *mut FatPtr<f64>,
+        arg3: *mut FatPtr<f64>,
+        arg4: *mut FatPtr<f64>,
+        arg5: *mut FatPtr<i32>,
+        arg6: *mut *const ::std::os::raw::c_char,
+        arg7: *mut FatPtr<f64>,
+        arg8: *mut FatPtr<i32>,
+        arg9: *mut FatPtr<f64>,
+        arg10: *mut ::std::os::raw::c_void,
+        arg11: *mut *mut f64,
+    ),
+>;
+#[doc = " Calculates all operating point variables (listed in the `opvars` global). The last argument"]
+#[doc = " contains one pointer per operating point variable, variables are not calculated if their"]
+#[doc = " pointer is null. The arguments are described by the same globals as a model function"]
+#[doc = " with the name `opvars`."]
+pub type VaeOpvars = ::std::option::Option<
+    unsafe extern "C" fn(
+        arg1: usize,
+        arg2: *mut FatPtr<f64>,
+        arg3: *mut FatPtr<f64>,
+        arg4: *mut FatPtr<f64>,
+        arg5: *mut FatPtr<i32>,
+        arg6: *mut *const ::std::os::raw::c_char,
+        arg7: *mut FatPtr<f64>,
+        arg8: *mut FatPtr<i32>,
+        arg9: *mut FatPtr<f64>,
+        arg10: *mut *mut f64,
+    ),
+>;
 #[repr(C)]
 #[derive(Debug, Copy, Clone)]
 pub struct Slice<T> {
@@ -64,6 +100,9 @@ pub struct Opts {
     pub target: Slice<u8>,
     pub cg_flags: Slice<Slice<u8>>,
     pub vfs: Vfs,
+    #[doc = " The parameters that the model functions are differentiated by (in addition to the"]
+    #[doc = " voltages). No derivatives are calculated if this is null."]
+    pub derivative_params: Slice<Slice<u8>>,
 }
 extern "C" {
     #[doc = "This function returns a pointer to the `functions` global"]
@@ -241,6 +280,35 @@ extern "C" {
         lib: *const ::std::os::raw::c_void,
     ) -> *const *const ::std::os::raw::c_char;
 }
+extern "C" {
+    #[doc = "This function returns a pointer to the `small_signal.unknowns` global"]
+    #[doc = " of a VerilogAE model loaded with `load`."]
+    #[doc = ""]
+    #[doc = " # Safety"]
+    #[doc = ""]
+    #[doc = " `lib` must be a valid pointer returned by the `load` functions or `dlopen`"]
+    pub fn verilogae_small_signal_unknowns(
+        lib: *const ::std::os::raw::c_void,
+    ) -> *const *const ::std::os::raw::c_char;
+}
+extern "C" {
+    #[doc = "This function returns a pointer to the `small_signal.jacobian.rows` global"]
+    #[doc = " of a VerilogAE model loaded with `load`."]
+    #[doc = ""]
+    #[doc = " # Safety"]
+    #[doc = ""]
+    #[doc = " `lib` must be a valid pointer returned by the `load` functions or `dlopen`"]
+    pub fn verilogae_small_signal_rows(lib: *const ::std::os::raw::c_void) -> *const u32;
+}
+extern "C" {
+    #[doc = "This function returns a pointer to the `small_signal.jacobian.cols` global"]
+    #[doc = " of a VerilogAE model loaded with `load`."]
+    #[doc = ""]
+    #[doc = " # Safety"]
+    #[doc = ""]
+    #[doc = " `lib` must be a valid pointer returned by the `load` functions or `dlopen`"]
+    pub fn verilogae_small_signal_cols(lib: *const ::std::os::raw::c_void) -> *const u32;
+}
 extern "C" {
     #[doc = "This function returns the value stored in the `functions.cnt` global"]
     #[doc = " of a VerilogAE model loaded with `load`."]
@@ -295,6 +363,24 @@ extern "C" {
     #[doc = " `lib` must be a valid pointer returned by the `load` functions or `dlopen`"]
     pub fn verilogae_node_cnt(lib: *const ::std::os::raw::c_void) -> usize;
 }
+extern "C" {
+    #[doc = "This function returns the value stored in the `small_signal.unknowns.cnt` global"]
+    #[doc = " of a VerilogAE model loaded with `load`."]
+    #[doc = ""]
+    #[doc = " # Safety"]
+    #[doc = ""]
+    #[doc = " `lib` must be a valid pointer returned by the `load` functions or `dlopen`"]
+    pub fn verilogae_small_signal_unknown_cnt(lib: *const ::std::os::raw::c_void) -> usize;
+}
+extern "C" {
+    #[doc = "This function returns the value stored in the `small_signal.jacobian.rows.cnt` global"]
+    #[doc = " of a VerilogAE model loaded with `load`."]
+    #[doc = ""]
+    #[doc = " # Safety"]
+    #[doc = ""]
+    #[doc = " `lib` must be a valid pointer returned by the `load` functions or `dlopen`"]
+    pub fn verilogae_small_signal_entry_cnt(lib: *const ::std::os::raw::c_void) -> usize;
+}
 extern "C" {
     #[doc = "This function returns a pointer to the `params.real` global"]
     #[doc = " of a VerilogAE model loaded with `load`."]
@@ -412,6 +498,19 @@ extern "C" {
         fun: *const ::std::os::raw::c_char,
     ) -> *const f64;
 }
+extern "C" {
+    #[doc = "This function returns a pointer to the `derivatives` global"]
+    #[doc = " of a VerilogAE model loaded with `load`."]
+    #[doc = ""]
+    #[doc = " # Safety"]
+    #[doc = ""]
+    #[doc = " `lib` must be a valid pointer returned by the `load` functions or `dlopen`"]
+    #[doc = "`sym_name` must batch the schema fun.{NUM}derivatives"]
+    pub fn verilogae_fun_derivatives(
+        lib: *const ::std::os::raw::c_void,
+        fun: *const ::std::os::raw::c_char,
+    ) -> *const *const ::std::os::raw::c_char;
+}
 extern "C" {
     #[doc = "This function returns a pointer to the `params.real.cnt` global"]
     #[doc = " of a VerilogAE model loaded with `load`."]
@@ -520,6 +619,18 @@ extern "C" {
         fun: *const ::std::os::raw::c_char,
     ) -> usize;
 }
+extern "C" {
+    #[doc = "This function returns a pointer to the `derivatives.cnt` global"]
+    #[doc = " of a VerilogAE model loaded with `load`."]
+    #[doc = ""]
+    #[doc = " # Safety"]
+    #[doc = ""]
+    #[doc = " `lib` must be a valid pointer returned by the `load` functions or `dlopen`"]
+    pub fn verilogae_fun_derivative_cnt(
+        lib: *const ::std::os::raw::c_void,
+        fun: *const ::std::os::raw::c_char,
+    ) -> usize;
+}
 extern "C" {
     #[doc = " Obtains a pointer to the modelcard initialization function of a VerilogAE model loaded with `load`."]
     #[doc = ""]
@@ -539,6 +650,42 @@ extern "C" {
         fun: *const ::std::os::raw::c_char,
     ) -> VaeFun;
 }
+extern "C" {
+    #[doc = " Obtains a pointer to the jacobian function of a model function of a VerilogAE model"]
+    #[doc = " loaded with `load`. The jacobian functions are only generated if the `derivative_params`"]
+    #[doc = " option is set (null otherwise)."]
+    #[doc = ""]
+    #[doc = " # Safety"]
+    #[doc = ""]
+    #[doc = " `lib` must be a valid pointer returned by the `load` functions or `dlopen`"]
+    pub fn verilogae_jacobian_ptr(
+        lib: *const ::std::os::raw::c_void,
+        fun: *const ::std::os::raw::c_char,
+    ) -> VaeJacobian;
+}
+extern "C" {
+    #[doc = " Obtains a pointer to the function that calculates all operating point variables of a"]
+    #[doc = " VerilogAE model loaded with `load`."]
+    #[doc = ""]
+    #[doc = " # Safety"]
+    #[doc = ""]
+    #[doc = " `lib` must be a valid pointer returned by the `load` functions or `dlopen`"]
+    pub fn verilogae_opvars_ptr(lib: *const ::std::os::raw::c_void) -> VaeOpvars;
+}
+extern "C" {
+    #[doc = " Obtains a pointer to the function that evaluates the DAE system of a VerilogAE model"]
+    #[doc = " loaded with `load`. It has the same signature as the function that calculates the"]
+    #[doc = " operating point variables and can be called with `verilogae_call_opvars_parallel`."]
+    #[doc = " Its arguments are described by the globals of a model function with the name"]
+    #[doc = " `small_signal`. The outputs are the resistive and reactive residual of each unknown"]
+    #[doc = " (`small_signal.unknowns`) followed by the resistive and reactive jacobian entries"]
+    #[doc = " (with the rows/columns in `small_signal.jacobian.rows`/`small_signal.jacobian.cols`)."]
+    #[doc = ""]
+    #[doc = " # Safety"]
+    #[doc = ""]
+    #[doc = " `lib` must be a valid pointer returned by the `load` functions or `dlopen`"]
+    pub fn verilogae_small_signal_ptr(lib: *const ::std::os::raw::c_void) -> VaeOpvars;
+}
 extern "C" {
     #[doc = " # Safety"]
     #[doc = " handle must be a valid model compiled with VerilogAE"]
@@ -547,6 +694,9 @@ extern "C" {
     ) -> *const ::std::os::raw::c_char;
 }
 extern "C" {
+    #[doc = " Evaluates `fun` for `cnt` operating points in parallel. Same as"]
+    #[doc = " `verilogae_call_fun_parallel_threads` with one thread per logical core."]
+    #[doc = ""]
     #[doc = " # Safety"]
     #[doc = ""]
     #[doc = " All required parameters must be initialized appropriately"]
@@ -564,6 +714,97 @@ extern "C" {
         out: *mut ::std::os::raw::c_void,
     ) -> i32;
 }
+extern "C" {
+    #[doc = " Evaluates `fun` for `cnt` operating points. The operating points are split into chunks"]
+    #[doc = " that are evaluated by `threads` threads in parallel. If `threads` is 0 the number of"]
+    #[doc = " threads is chosen automatically (one per logical core)."]
+    #[doc = ""]
+    #[doc = " # Safety"]
+    #[doc = ""]
+    #[doc = " All required parameters must be initialized appropriately"]
+    pub fn verilogae_call_fun_parallel_threads(
+        fun: VaeFun,
+        cnt: usize,
+        threads: usize,
+        voltages: *mut FatPtr<f64>,
+        currents: *mut FatPtr<f64>,
+        real_params: *mut FatPtr<f64>,
+        int_params: *mut FatPtr<i32>,
+        str_params: *mut *const ::std::os::raw::c_char,
+        real_dep_break: *mut FatPtr<f64>,
+        int_dep_break: *mut FatPtr<i32>,
+        temp: *mut FatPtr<f64>,
+        out: *mut ::std::os::raw::c_void,
+    ) -> i32;
+}
+extern "C" {
+    #[doc = " Same as `verilogae_call_fun_parallel` but also calculates the derivatives of the function."]
+    #[doc = " `derivatives` must contain one (possibly null) pointer for each derivative of the function."]
+    #[doc = ""]
+    #[doc = " # Safety"]
+    #[doc = ""]
+    #[doc = " All required parameters must be initialized appropriately"]
+    pub fn verilogae_call_jacobian_parallel(
+        fun: VaeJacobian,
+        cnt: usize,
+        voltages: *mut FatPtr<f64>,
+        currents: *mut FatPtr<f64>,
+        real_params: *mut FatPtr<f64>,
+        int_params: *mut FatPtr<i32>,
+        str_params: *mut *const ::std::os::raw::c_char,
+        real_dep_break: *mut FatPtr<f64>,
+        int_dep_break: *mut FatPtr<i32>,
+        temp: *mut FatPtr<f64>,
+        out: *mut ::std::os::raw::c_void,
+        derivatives: *mut *mut f64,
+    ) -> i32;
+}
+extern "C" {
+    #[doc = " Same as `verilogae_call_fun_parallel_threads` but also calculates the derivatives of the"]
+    #[doc = " function. `derivatives` must contain one (possibly null) pointer for each derivative of"]
+    #[doc = " the function."]
+    #[doc = ""]
+    #[doc = " # Safety"]
+    #[doc = ""]
+    #[doc = " All required parameters must be initialized appropriately"]
+    pub fn verilogae_call_jacobian_parallel_threads(
+        fun: VaeJacobian,
+        cnt: usize,
+        threads: usize,
+        voltages: *mut FatPtr<f64>,
+        currents: *mut FatPtr<f64>,
+        real_params: *mut FatPtr<f64>,
+        int_params: *mut FatPtr<i32>,
+        str_params: *mut *const ::std::os::raw::c_char,
+        real_dep_break: *mut FatPtr<f64>,
+        int_dep_break: *mut FatPtr<i32>,
+        temp: *mut FatPtr<f64>,
+        out: *mut ::std::os::raw::c_void,
+        derivatives: *mut *mut f64,
+    ) -> i32;
+}
+extern "C" {
+    #[doc = " Calculates the operating point variables for `cnt` operating points with `fun` (obtained"]
+    #[doc = " from `verilogae_opvars_ptr`). `opvars` must contain one (possibly null) pointer for each"]
+    #[doc = " operating point variable. The parallelization is the same as for"]
+    #[doc = " `verilogae_call_fun_parallel_threads`."]
+    #[doc = ""]
+    #[doc = " # Safety"]
+    #[doc = ""]
+    #[doc = " All required parameters must be initialized appropriately"]
+    pub fn verilogae_call_opvars_parallel(
+        fun: VaeOpvars,
+        cnt: usize,
+        threads: usize,
+        voltages: *mut FatPtr<f64>,
+        currents: *mut FatPtr<f64>,
+        real_params: *mut FatPtr<f64>,
+        int_params: *mut FatPtr<i32>,
+        str_params: *mut *const ::std::os::raw::c_char,
+        temp: *mut FatPtr<f64>,
+        opvars: *mut *mut f64,
+    ) -> i32;
+}
 extern "C" {
     pub fn verilogae_new_opts() -> *mut Opts;
 }
@@ -597,6 +838,30 @@ extern "C" {
         opts: *const Opts,
     ) -> *const ::std::os::raw::c_void;
 }
+extern "C" {
+    #[doc = " Same as `verilogae_load` but compiles the model in memory with a JIT instead of"]
+    #[doc = " building (and caching) a shared library. The returned handle can be used with all"]
+    #[doc = " functions that accept a handle returned by `verilogae_load`. It must be freed with"]
+    #[doc = " `verilogae_unload` (instead of `dlclose`)."]
+    #[doc = ""]
+    #[doc = " # Safety"]
+    #[doc = " * path must be valid for reads"]
+    #[doc = " * opts must be valid for reads or null"]
+    #[doc = " * opts must only contain valid data"]
+    pub fn verilogae_load_jit(
+        path: NativePath,
+        full_compile: bool,
+        opts: *const Opts,
+    ) -> *const ::std::os::raw::c_void;
+}
+extern "C" {
+    #[doc = " Frees a model loaded with `verilogae_load` or `verilogae_load_jit`."]
+    #[doc = " None of the functions of the model may be used afterwards."]
+    #[doc = ""]
+    #[doc = " # Safety"]
+    #[doc = " `lib` must be a valid pointer returned by the `load` functions"]
+    pub fn verilogae_unload(lib: *const ::std::os::raw::c_void);
+}
 pub const PARAM_FLAGS_MIN_INCLUSIVE: ParamFlags = 1;
 pub const PARAM_FLAGS_MAX_INCLUSIVE: ParamFlags = 2;
 pub const PARAM_FLAGS_INVALID: ParamFlags = 4;
@@ -4,15 +4,9 @@ mod ffi;
 use verilogae::api as ffi;
 
 pub use ffi::*;
-
-#[cfg(feature = "static")]
-pub const PARAM_FLAGS_MIN_INCLUSIVE: ParamFlags = 1;
-#[cfg(feature = "static")]
-pub const PARAM_FLAGS_MAX_INCLUSIVE: ParamFlags = 2;
-#[cfg(feature = "static")]
-pub const PARAM_FLAGS_INVALID: ParamFlags = 4;
+/// The safe API is only available if VerilogAE is linked statically.
 #[cfg(feature = "static")]
-pub const PARAM_FLAGS_GIVEN: ParamFlags = 8;
+pub use verilogae::{Function, Model, ParamInfo, ParamSet, ParamType, ParamValue, Voltages};
 
 use core::slice;
 use std::ptr;
@@ -91,6 +85,7 @@ impl Drop for Opts {
                 opts.deny_lints.into_box_opt();
                 opts.cg_flags.into_box_opt();
                 opts.vfs.into_box_opt();
+                opts.derivative_params.into_box_opt();
             }
             unsafe { ffi::verilogae_free_opts(opts as *mut ffi::Opts) }
         }
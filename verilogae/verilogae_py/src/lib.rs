@@ -4,6 +4,7 @@ mod offsets;
 mod ffi;
 mod load;
 mod model;
+mod modelcard;
 mod numpy;
 mod typeref;
 mod unicode;
@@ -12,8 +13,9 @@ mod util;
 use std::os::raw::{c_char, c_int};
 use std::ptr;
 
-use crate::load::{load_info_py, load_py, load_vfs};
+use crate::load::{load_info_py, load_jit_py, load_py, load_vfs};
 use crate::model::{VAE_FUNCTION_TY, VAE_MODEL_TY, VAE_PARAM_TY};
+use crate::modelcard::MODELCARD_TY;
 use crate::typeref::init_typerefs;
 use pyo3_ffi::*;
 
@@ -22,7 +24,7 @@ const FUN_FLAG: c_int = METH_FASTCALL;
 #[cfg(not(Py_3_8))]
 const FUN_FLAG: c_int = METH_VARARGS;
 
-static mut FUNCTIONS: [PyMethodDef; 4] = unsafe {
+static mut FUNCTIONS: [PyMethodDef; 5] = unsafe {
     [
     PyMethodDef {
             ml_name: "load\0".as_ptr() as *const c_char,
@@ -33,6 +35,15 @@ static mut FUNCTIONS: [PyMethodDef; 4] = unsafe {
             ml_flags: FUN_FLAG | METH_KEYWORDS,
             ml_doc: "loads a Verilog-A model by either loading it from the object cache or compiling it\0".as_ptr() as *const c_char,
     },
+    PyMethodDef {
+            ml_name: "load_jit\0".as_ptr() as *const c_char,
+            #[cfg(Py_3_8)]
+            ml_meth: PyMethodDefPointer{_PyCFunctionFastWithKeywords: load_jit_py},
+            #[cfg(not(Py_3_8))]
+            ml_meth: PyMethodDefPointer{PyCFunctionWithKeywords: load_jit_py},
+            ml_flags: FUN_FLAG | METH_KEYWORDS,
+            ml_doc: "compiles a Verilog-A model in memory with a JIT instead of building (and caching) a shared library\nThis does not require a linker\0".as_ptr() as *const c_char,
+    },
     PyMethodDef {
             ml_name: "load_info\0".as_ptr() as *const c_char,
             #[cfg(Py_3_8)]
@@ -84,6 +95,10 @@ pub unsafe extern "C" fn PyInit_verilogae() -> *mut PyObject {
         return ptr::null_mut();
     }
 
+    if PyType_Ready(&mut MODELCARD_TY) < 0 {
+        return ptr::null_mut();
+    }
+
     let mptr = PyModule_Create(Box::into_raw(Box::new(init)));
     init_typerefs();
     let version = env!("CARGO_PKG_VERSION");
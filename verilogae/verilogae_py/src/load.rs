@@ -1,6 +1,6 @@
 use libc::c_char;
 use pyo3_ffi::*;
-use verilogae_ffi::{verilogae_load, Opts, Slice, Vfs, VfsEntry, VfsExport};
+use verilogae_ffi::{verilogae_load, verilogae_load_jit, Opts, Slice, Vfs, VfsEntry, VfsExport};
 
 use crate::ffi::PyDict_GET_SIZE;
 use crate::model::VaeModel;
@@ -26,6 +26,12 @@ macro_rules! handle_opt {
                 None => return ptr::null_mut(),
             }
             true
+        } else if $arg == typeref::DERIVATIVE_PARAMS_STR {
+            match py_to_str_list($fun, "derivative_params", $val) {
+                Some(params) => $dst.write().derivative_params = params,
+                None => return ptr::null_mut(),
+            }
+            true
         } else {
             false
         }
@@ -147,7 +153,7 @@ fn raise_type_exception(msg: &str) -> *mut PyObject {
 
 #[cold]
 #[inline(never)]
-fn raise_runtime_runtime_exception(msg: &str) -> *mut PyObject {
+pub(crate) fn raise_runtime_runtime_exception(msg: &str) -> *mut PyObject {
     unsafe {
         let err_msg =
             PyUnicode_FromStringAndSize(msg.as_ptr() as *const c_char, msg.len() as isize);
@@ -192,6 +198,41 @@ pub unsafe extern "C" fn load_py(
     VaeModel::new(model, true)
 }
 
+#[cfg(not(Py_3_8))]
+#[no_mangle]
+pub unsafe extern "C" fn load_jit_py(
+    _self: *mut PyObject,
+    args: *mut PyObject,
+    kwds: *mut PyObject,
+) -> *mut PyObject {
+    parse_args!("load_jit", args, kwds, path, opts);
+    let model = verilogae_load_jit(path.data, true, opts.to_ffi());
+
+    if model.is_null() {
+        return raise_runtime_runtime_exception("load_jit() compilation failed");
+    }
+
+    VaeModel::new(model, true)
+}
+
+#[cfg(Py_3_8)]
+#[no_mangle]
+pub unsafe extern "C" fn load_jit_py(
+    _self: *mut PyObject,
+    args: *const *mut PyObject,
+    nargs: Py_ssize_t,
+    kwnames: *mut PyObject,
+) -> *mut PyObject {
+    parse_args!("load_jit", args, nargs, kwnames, path, opts);
+    let model = verilogae_load_jit(path.data, true, opts.to_ffi());
+
+    if model.is_null() {
+        return raise_runtime_runtime_exception("load_jit() compilation failed");
+    }
+
+    VaeModel::new(model, true)
+}
+
 #[cfg(not(Py_3_8))]
 #[no_mangle]
 pub unsafe extern "C" fn load_info_py(
@@ -313,3 +354,27 @@ unsafe fn py_to_vfs(fun: &str, obj: *mut PyObject) -> Option<Vfs> {
 
     Some(vfs.into_boxed_slice().into())
 }
+
+unsafe fn py_to_str_list(fun: &str, arg: &str, obj: *mut PyObject) -> Option<Slice<Slice<u8>>> {
+    if PyList_Check(obj) == 0 {
+        raise_type_exception(&format!("{}() arguments '{}' must have type list(str) ", fun, arg));
+        return None;
+    }
+
+    let len = PyList_GET_SIZE(obj);
+    let mut res = Vec::with_capacity(len as usize);
+    for i in 0..len {
+        let mut size = 0;
+        let item = PyUnicode_AsUTF8AndSize(PyList_GET_ITEM(obj, i), &mut size);
+        if unlikely(item.is_null()) {
+            raise_type_exception(&format!(
+                "{}() arguments '{}' must have type list(str) ",
+                fun, arg
+            ));
+            return None;
+        }
+        res.push(Slice::from_raw_parts(item as *const u8, size as usize));
+    }
+
+    Some(res.into_boxed_slice().into())
+}
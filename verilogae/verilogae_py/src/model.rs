@@ -3,35 +3,31 @@ use std::mem::take;
 use std::os::raw::c_long;
 use std::ptr;
 use std::slice;
+use std::sync::Arc;
 
-use libc::{c_char, c_void};
+use libc::{c_char, c_int, c_void};
 use pyo3_ffi::structmember::{PyMemberDef, READONLY, T_OBJECT, T_OBJECT_EX};
 use pyo3_ffi::*;
 use verilogae_ffi::{
-    verilogae_call_fun_parallel, verilogae_fun_current_cnt, verilogae_fun_current_default_cnt,
-    verilogae_fun_current_defaults, verilogae_fun_currents, verilogae_fun_ptr,
-    verilogae_fun_voltage_cnt, verilogae_fun_voltage_default_cnt, verilogae_fun_voltage_defaults,
-    verilogae_fun_voltages, verilogae_function_cnt, verilogae_function_symbols,
-    verilogae_functions, verilogae_init_modelcard, verilogae_int_fun_depbreak,
-    verilogae_int_fun_depbreak_cnt, verilogae_int_fun_param_cnt, verilogae_int_fun_params,
-    verilogae_int_param_cnt, verilogae_int_param_descriptions, verilogae_int_param_groups,
-    verilogae_int_param_units, verilogae_int_params, verilogae_module_name, verilogae_node_cnt,
-    verilogae_nodes, verilogae_opvars, verilogae_opvars_cnt, verilogae_real_fun_depbreak,
-    verilogae_real_fun_depbreak_cnt, verilogae_real_fun_param_cnt, verilogae_real_fun_params,
-    verilogae_real_param_cnt, verilogae_real_param_descriptions, verilogae_real_param_groups,
-    verilogae_real_param_units, verilogae_real_params, verilogae_str_fun_param_cnt,
-    verilogae_str_fun_params, verilogae_str_param_cnt, verilogae_str_param_descriptions,
-    verilogae_str_param_groups, verilogae_str_param_units, verilogae_str_params, FatPtr, Meta,
-    ParamFlags, PARAM_FLAGS_INVALID, PARAM_FLAGS_MAX_INCLUSIVE, PARAM_FLAGS_MIN_INCLUSIVE,
+    verilogae_call_fun_parallel_threads, verilogae_call_jacobian_parallel_threads,
+    verilogae_call_opvars_parallel, verilogae_opvars_ptr, verilogae_small_signal_cols,
+    verilogae_small_signal_entry_cnt, verilogae_small_signal_ptr, verilogae_small_signal_rows,
+    verilogae_small_signal_unknown_cnt, verilogae_small_signal_unknowns, FatPtr, Function, Meta,
+    Model, ParamInfo, ParamType, ParamValue,
 };
 
 use crate::ffi::new_type;
+use crate::load::raise_runtime_runtime_exception;
+use crate::modelcard::{load_modelcard, ModelCard};
 use crate::numpy::{ItemType, NumpyArray, PyArrayError};
+use crate::typeref::MODELCARD_STR;
 use crate::typeref::NUMPY_API;
 use crate::typeref::NUMPY_ARR_TYPE;
 use crate::typeref::TEMPERATURE_STR;
+use crate::typeref::THREADS_STR;
 use crate::typeref::VOLTAGES_STR;
-use crate::typeref::{CURRENTS_STR, NUMPY_CDOUBLE_DESCR};
+use crate::typeref::{CURRENTS_STR, DERIVATIVES_STR, NUMPY_CDOUBLE_DESCR};
+use crate::unicode::{intern_str, new_str};
 use crate::util::likely;
 use crate::util::unlikely;
 
@@ -41,6 +37,7 @@ pub static mut VAE_MODEL_TY: PyTypeObject = {
     res.tp_doc =
         "A Verilog-A module compiled and loaded with Verilog-AE\0".as_ptr() as *const c_char;
     res.tp_members = unsafe { &mut VAE_MODEL_MEMBERS } as *mut _;
+    res.tp_methods = unsafe { &mut VAE_MODEL_METHODS } as *mut _;
     res.tp_dealloc = Some(VaeModel::dealloc);
     res
 };
@@ -84,6 +81,53 @@ static mut VAE_MODEL_MEMBERS: [PyMemberDef; 6] = [
     unsafe { zero!(PyMemberDef) },
 ];
 
+static mut VAE_MODEL_METHODS: [PyMethodDef; 5] = [
+    PyMethodDef {
+        ml_name: "new_modelcard\0".as_ptr() as *const c_char,
+        ml_meth: PyMethodDefPointer { PyCFunctionWithKeywords: VaeModel::new_modelcard },
+        ml_flags: METH_VARARGS | METH_KEYWORDS,
+        ml_doc: "new_modelcard(**params)\n--\n\ncreates a ModelCard that contains the default \
+                 values of all parameters, overwritten by the parameters passed as keyword \
+                 arguments\0"
+            .as_ptr() as *const c_char,
+    },
+    PyMethodDef {
+        ml_name: "load_modelcard\0".as_ptr() as *const c_char,
+        ml_meth: PyMethodDefPointer { PyCFunctionWithKeywords: VaeModel::load_modelcard },
+        ml_flags: METH_VARARGS | METH_KEYWORDS,
+        ml_doc: "load_modelcard(path, name=None, l=None, w=None)\n--\n\ncreates a ModelCard \
+                 from a JSON file (.json) or a SPICE .model card.\nFor SPICE files that contain \
+                 multiple (binned) models the model called name (or name.<n>) whose lmin/lmax \
+                 and wmin/wmax range contains the geometry l/w is selected\0"
+            .as_ptr() as *const c_char,
+    },
+    PyMethodDef {
+        ml_name: "eval_opvars\0".as_ptr() as *const c_char,
+        ml_meth: PyMethodDefPointer { PyCFunctionWithKeywords: VaeModel::eval_opvars },
+        ml_flags: METH_VARARGS | METH_KEYWORDS,
+        ml_doc: "eval_opvars(temperature, voltages={}, currents={}, modelcard=None, threads=0, \
+                 **params)\n--\n\ncalculates all operating point variables (op_vars) and returns \
+                 a dict that maps their names to the calculated values. The arguments are the \
+                 same as for VaeFun.eval. String variables are returned as nan\0"
+            .as_ptr() as *const c_char,
+    },
+    PyMethodDef {
+        ml_name: "eval_small_signal\0".as_ptr() as *const c_char,
+        ml_meth: PyMethodDefPointer { PyCFunctionWithKeywords: VaeModel::eval_small_signal },
+        ml_flags: METH_VARARGS | METH_KEYWORDS,
+        ml_doc: "eval_small_signal(temperature, voltages={}, currents={}, modelcard=None, \
+                 threads=0, **params)\n--\n\nevaluates the DAE system of the model at the given \
+                 bias and returns a dict with the names of its unknowns (unknowns), the \
+                 resistive and reactive residuals (I and Q) and the resistive and reactive \
+                 jacobian (G and C, indexed [row, column] in the order of unknowns). The node \
+                 potentials (br_<node>) and currents of the unknowns are passed as voltages \
+                 and currents and default to zero. If arrays are passed the operating point is \
+                 the first axis of the results\0"
+            .as_ptr() as *const c_char,
+    },
+    unsafe { zero!(PyMethodDef) },
+];
+
 with_offsets! {
     #[repr(C)]
     pub struct VaeModel {
@@ -93,66 +137,139 @@ with_offsets! {
         op_vars: *mut PyObject,
         module_name: *mut PyObject,
         nodes: *mut PyObject,
+        model: Option<Arc<Model>>,
+        opvars_fun: *mut PyObject,
+        small_signal_fun: *mut PyObject,
     }
 }
 
 impl VaeModel {
     #[allow(clippy::new_ret_no_self)]
     pub unsafe fn new(handle: *const c_void, full: bool) -> *mut PyObject {
+        let model = match Model::from_raw(handle, full) {
+            Ok(model) => Arc::new(model),
+            Err(err) => return raise_runtime_runtime_exception(&format!("{err:#}")),
+        };
+
         let ptr = VAE_MODEL_TY.tp_alloc.unwrap()(&mut VAE_MODEL_TY, 0);
         if ptr.is_null() {
             return ptr::null_mut();
         }
 
         let res = &mut *(ptr as *mut Self);
+        // the memory is zeroed, so the field must not be dropped
+        ptr::write(&mut res.model, Some(model.clone()));
         if full {
-            let functions = VaeFun::new_dict(handle);
+            let functions = VaeFun::new_dict(&model);
             if functions.is_null() {
                 Py_DECREF(ptr);
                 return ptr::null_mut();
             }
-            res.functions = functions
+            res.functions = functions;
+
+            let fun = model.opvars_function().unwrap();
+            res.opvars_fun = VaeFun::new(&model, fun, FunKind::Opvars);
+            if res.opvars_fun.is_null() {
+                Py_DECREF(ptr);
+                return ptr::null_mut();
+            }
+
+            let fun = model.small_signal_function().unwrap();
+            res.small_signal_fun = VaeFun::new(&model, fun, FunKind::SmallSignal);
+            if res.small_signal_fun.is_null() {
+                Py_DECREF(ptr);
+                return ptr::null_mut();
+            }
         }
 
-        res.modelcard = VaeParam::new_mcard(handle);
+        res.modelcard = VaeParam::new_mcard(&model);
         if res.modelcard.is_null() {
             Py_DECREF(ptr);
             return ptr::null_mut();
         }
 
-        let opvar_cnt = verilogae_opvars_cnt(handle);
-        let opvars = verilogae_opvars(handle);
-        res.op_vars = PyList_New(opvar_cnt as isize);
+        res.op_vars = new_str_list(model.opvars());
         if res.op_vars.is_null() {
             Py_DECREF(ptr);
             return ptr::null_mut();
         }
-        for i in 0..opvar_cnt {
-            let var = *opvars.add(i);
-            let var = PyUnicode_InternFromString(var);
-            PyList_SetItem(res.op_vars, i as isize, var);
-        }
 
-        let module_name = verilogae_module_name(handle);
-        res.module_name = PyUnicode_InternFromString(module_name);
-
-        let nodes = verilogae_nodes(handle);
-        let node_cnt = verilogae_node_cnt(handle);
-
-        res.nodes = PyList_New(node_cnt as isize);
-        for i in 0..node_cnt {
-            let node = PyUnicode_InternFromString(*nodes.add(i));
-            PyList_SetItem(res.nodes, i as isize, node);
-        }
+        res.module_name = intern_str(model.name());
+        res.nodes = new_str_list(model.nodes());
 
         ptr
     }
 
+    fn model(&self) -> &Arc<Model> {
+        self.model.as_ref().unwrap()
+    }
+
     unsafe extern "C" fn dealloc(sel: *mut PyObject) {
         let sel = &mut *(sel as *mut Self);
         Py_XDECREF(sel.functions);
         Py_XDECREF(sel.modelcard);
         Py_XDECREF(sel.op_vars);
+        Py_XDECREF(sel.opvars_fun);
+        Py_XDECREF(sel.small_signal_fun);
+        sel.model = None;
+    }
+
+    unsafe extern "C" fn new_modelcard(
+        self_: *mut PyObject,
+        args: *mut PyObject,
+        kwds: *mut PyObject,
+    ) -> *mut PyObject {
+        if unlikely(PyTuple_GET_SIZE(args) != 0) {
+            return raise_eval_exception("new_modelcard() takes only keyword arguments");
+        }
+
+        let self_ = &*(self_ as *const Self);
+        let card = ModelCard::new(self_.model());
+        if card.is_null() {
+            return ptr::null_mut();
+        }
+        if !(*(card as *mut ModelCard)).update("new_modelcard", kwds) {
+            Py_DECREF(card);
+            return ptr::null_mut();
+        }
+        card
+    }
+
+    unsafe extern "C" fn load_modelcard(
+        self_: *mut PyObject,
+        args: *mut PyObject,
+        kwds: *mut PyObject,
+    ) -> *mut PyObject {
+        let self_ = &*(self_ as *const Self);
+        load_modelcard(self_.model(), args, kwds)
+    }
+
+    unsafe extern "C" fn eval_opvars(
+        self_: *mut PyObject,
+        args: *mut PyObject,
+        kwds: *mut PyObject,
+    ) -> *mut PyObject {
+        let self_ = &*(self_ as *const Self);
+        if unlikely(self_.opvars_fun.is_null()) {
+            return raise_eval_exception(
+                "eval_opvars() requires a model that was loaded with full_compile",
+            );
+        }
+        VaeFun::eval(self_.opvars_fun, args, kwds)
+    }
+
+    unsafe extern "C" fn eval_small_signal(
+        self_: *mut PyObject,
+        args: *mut PyObject,
+        kwds: *mut PyObject,
+    ) -> *mut PyObject {
+        let self_ = &*(self_ as *const Self);
+        if unlikely(self_.small_signal_fun.is_null()) {
+            return raise_eval_exception(
+                "eval_small_signal() requires a model that was loaded with full_compile",
+            );
+        }
+        VaeFun::eval(self_.small_signal_fun, args, kwds)
     }
 }
 
@@ -250,101 +367,14 @@ with_offsets! {
 }
 
 impl VaeParam {
-    pub unsafe fn new_mcard(handle: *const c_void) -> *mut PyObject {
+    pub unsafe fn new_mcard(model: &Model) -> *mut PyObject {
         let res = PyDict_New();
         if res.is_null() {
             return ptr::null_mut();
         }
 
-        let real_param_cnt = verilogae_real_param_cnt(handle);
-        let int_param_cnt = verilogae_int_param_cnt(handle);
-        let str_param_cnt = verilogae_str_param_cnt(handle);
-
-        let mut flags = vec![0u8; real_param_cnt + int_param_cnt + str_param_cnt];
-        let mut real_data = vec![0f64; 3 * real_param_cnt];
-        let mut int_data = vec![0; 3 * int_param_cnt];
-        let mut str_data = vec![ptr::null(); str_param_cnt];
-        let modelcard_init = verilogae_init_modelcard(handle).expect("invalid model handle");
-        modelcard_init(
-            real_data.as_mut_ptr(),
-            int_data.as_mut_ptr(),
-            str_data.as_mut_ptr(),
-            real_data.as_mut_ptr().add(real_param_cnt),
-            int_data.as_mut_ptr().add(int_param_cnt),
-            real_data.as_mut_ptr().add(2 * real_param_cnt),
-            int_data.as_mut_ptr().add(2 * int_param_cnt),
-            flags.as_mut_ptr(),
-        );
-
-        let param_names = verilogae_real_params(handle);
-        let param_units = verilogae_real_param_units(handle);
-        let param_descr = verilogae_real_param_descriptions(handle);
-        let param_groups = verilogae_real_param_groups(handle);
-
-        for (i, (((val, min), max), flags)) in real_data[..real_param_cnt]
-            .iter()
-            .zip(&real_data[real_param_cnt..])
-            .zip(&real_data[2 * real_param_cnt..])
-            .zip(&flags)
-            .enumerate()
-        {
-            let name = *param_names.add(i);
-            let unit = *param_units.add(i);
-            let description = *param_descr.add(i);
-            let group = *param_groups.add(i);
-
-            let (param, name) =
-                VaeParam::new_real(name, *val, *min, *max, *flags, description, unit, group);
-            if param.is_null() {
-                return ptr::null_mut();
-            }
-
-            PyDict_SetItem(res, name, param);
-
-            Py_DECREF(param);
-        }
-
-        let param_names = verilogae_int_params(handle);
-        let param_units = verilogae_int_param_units(handle);
-        let param_descr = verilogae_int_param_descriptions(handle);
-        let param_groups = verilogae_int_param_groups(handle);
-
-        for (i, (((val, min), max), flags)) in int_data[..int_param_cnt]
-            .iter()
-            .zip(&int_data[int_param_cnt..])
-            .zip(&int_data[2 * int_param_cnt..])
-            .zip(&flags[real_param_cnt..])
-            .enumerate()
-        {
-            let name = *param_names.add(i);
-            let unit = *param_units.add(i);
-            let description = *param_descr.add(i);
-            let group = *param_groups.add(i);
-
-            let (param, name) =
-                VaeParam::new_int(name, *val, *min, *max, *flags, description, unit, group);
-            if param.is_null() {
-                return ptr::null_mut();
-            }
-
-            PyDict_SetItem(res, name, param);
-            Py_DECREF(param);
-        }
-
-        let param_names = verilogae_str_params(handle);
-        let param_units = verilogae_str_param_units(handle);
-        let param_descr = verilogae_str_param_descriptions(handle);
-        let param_groups = verilogae_str_param_groups(handle);
-
-        for (i, (val, flags)) in
-            str_data.iter().zip(&flags[(real_param_cnt + int_param_cnt)..]).enumerate()
-        {
-            let name = *param_names.add(i);
-            let unit = *param_units.add(i);
-            let description = *param_descr.add(i);
-            let group = *param_groups.add(i);
-
-            let (param, name) = VaeParam::new_str(name, *val, *flags, description, unit, group);
+        for info in model.params() {
+            let (param, name) = VaeParam::new(info);
             if param.is_null() {
                 return ptr::null_mut();
             }
@@ -355,93 +385,29 @@ impl VaeParam {
         res
     }
 
-    unsafe fn new_str(
-        name: *const c_char,
-        default: *const c_char,
-        flags: ParamFlags,
-        description: *const c_char,
-        unit: *const c_char,
-        group: *const c_char,
-    ) -> (*mut PyObject, *mut PyObject) {
-        let default = PyUnicode_FromString(default);
-        VaeParam::new(
-            name,
-            default,
-            ptr::null_mut(),
-            ptr::null_mut(),
-            flags,
-            description,
-            unit,
-            group,
-        )
-    }
-
-    #[allow(clippy::too_many_arguments)]
-    unsafe fn new_real(
-        name: *const c_char,
-        default: f64,
-        min: f64,
-        max: f64,
-        flags: ParamFlags,
-        description: *const c_char,
-        unit: *const c_char,
-        group: *const c_char,
-    ) -> (*mut PyObject, *mut PyObject) {
-        let min = PyFloat_FromDouble(min);
-        let max = PyFloat_FromDouble(max);
-        let default = PyFloat_FromDouble(default);
-        VaeParam::new(name, default, min, max, flags, description, unit, group)
-    }
-
-    #[allow(clippy::too_many_arguments)]
-    unsafe fn new_int(
-        name: *const c_char,
-        default: i32,
-        min: i32,
-        max: i32,
-        flags: ParamFlags,
-        description: *const c_char,
-        unit: *const c_char,
-        group: *const c_char,
-    ) -> (*mut PyObject, *mut PyObject) {
-        let min = PyLong_FromLong(min as c_long);
-        let max = PyLong_FromLong(max as c_long);
-        let default = PyLong_FromLong(default as c_long);
-        VaeParam::new(name, default, min, max, flags, description, unit, group)
-    }
-
-    #[allow(clippy::too_many_arguments)]
     #[allow(clippy::new_ret_no_self)]
-    unsafe fn new(
-        name: *const c_char,
-        default: *mut PyObject,
-        min: *mut PyObject,
-        max: *mut PyObject,
-        flags: ParamFlags,
-        description: *const c_char,
-        unit: *const c_char,
-        group: *const c_char,
-    ) -> (*mut PyObject, *mut PyObject) {
+    unsafe fn new(info: &ParamInfo) -> (*mut PyObject, *mut PyObject) {
         let ptr = VAE_PARAM_TY.tp_alloc.unwrap()(&mut VAE_PARAM_TY, 0);
         if ptr.is_null() {
-            Py_XDECREF(default);
-            Py_XDECREF(min);
-            Py_XDECREF(max);
             return (ptr::null_mut(), ptr::null_mut());
         }
 
-        if unlikely((flags & PARAM_FLAGS_INVALID) != 0) {
-            let name = CStr::from_ptr(name).to_str().unwrap();
-            eprintln!("warning: default value for {} is out of bounds!", name);
+        if unlikely(info.invalid_default) {
+            eprintln!("warning: default value for {} is out of bounds!", info.name);
         }
 
-        let min_inclusive = PyBool_FromLong((flags & PARAM_FLAGS_MIN_INCLUSIVE) as c_long);
-        let max_inclusive = PyBool_FromLong((flags & PARAM_FLAGS_MAX_INCLUSIVE) as c_long);
+        let default = new_param_value(&info.default);
+        let (min, max) = match &info.bounds {
+            Some((min, max)) => (new_param_value(min), new_param_value(max)),
+            None => (ptr::null_mut(), ptr::null_mut()),
+        };
+        let min_inclusive = PyBool_FromLong(info.min_inclusive as c_long);
+        let max_inclusive = PyBool_FromLong(info.max_inclusive as c_long);
 
-        let name = PyUnicode_InternFromString(name);
-        let description = PyUnicode_FromString(description);
-        let unit = PyUnicode_InternFromString(unit);
-        let group = PyUnicode_InternFromString(group);
+        let name = intern_str(&info.name);
+        let description = new_str(&info.description);
+        let unit = intern_str(&info.unit);
+        let group = intern_str(&info.group);
 
         let res = &mut *(ptr as *mut Self);
         res.name = name;
@@ -470,6 +436,27 @@ impl VaeParam {
     }
 }
 
+/// Converts the value of a parameter to the corresponding Python object.
+unsafe fn new_param_value(val: &ParamValue) -> *mut PyObject {
+    match val {
+        ParamValue::Real(val) => PyFloat_FromDouble(*val),
+        ParamValue::Integer(val) => PyLong_FromLong(*val as c_long),
+        ParamValue::String(val) => new_str(val),
+    }
+}
+
+/// Creates a new list of interned strings.
+unsafe fn new_str_list(names: &[String]) -> *mut PyObject {
+    let res = PyList_New(names.len() as isize);
+    if res.is_null() {
+        return res;
+    }
+    for (i, name) in names.iter().enumerate() {
+        PyList_SetItem(res, i as isize, intern_str(name));
+    }
+    res
+}
+
 pub static mut VAE_FUNCTION_TY: PyTypeObject = {
     let mut res = new_type::<VaeFun>();
     res.tp_name = "verilogae.VaeFun\0".as_ptr() as *const c_char;
@@ -481,7 +468,7 @@ pub static mut VAE_FUNCTION_TY: PyTypeObject = {
     res
 };
 
-static mut VAE_FUNCTION_MEMBERS: [PyMemberDef; 6] = [
+static mut VAE_FUNCTION_MEMBERS: [PyMemberDef; 7] = [
     PyMemberDef {
         name: "name\0".as_ptr() as *mut c_char,
         type_code: T_OBJECT,
@@ -518,6 +505,14 @@ static mut VAE_FUNCTION_MEMBERS: [PyMemberDef; 6] = [
         doc: "The names of all variables the function requires for dependency breaking".as_ptr()
             as *mut c_char,
     },
+    PyMemberDef {
+        name: "derivatives\0".as_ptr() as *mut c_char,
+        type_code: T_OBJECT,
+        offset: VaeFun::offset_to.derivatives as isize,
+        flags: READONLY,
+        doc: "The names of all voltages and parameters the function can be differentiated by"
+            .as_ptr() as *mut c_char,
+    },
     unsafe { zero!(PyMemberDef) },
 ];
 
@@ -562,6 +557,7 @@ with_offsets! {
         currents: *mut PyObject,
         parameters: *mut PyObject,
         depbreak: *mut PyObject,
+        derivatives: *mut PyObject,
 
         int_depbreak_offset: usize,
         real_depbreak_offset: usize,
@@ -571,14 +567,42 @@ with_offsets! {
         str_params:  Box<[(*mut PyObject, &'static str)]>,
         voltages_:   Box<[(*mut PyObject, &'static str, f64)]>,
         currents_:   Box<[(*mut PyObject, &'static str, f64)]>,
+        derivatives_: Box<[*mut PyObject]>,
+
+        // keeps the library (and the names borrowed from the model) alive
+        model: Arc<Model>,
+        // position of each parameter in the model card
+        real_param_ids: Box<[usize]>,
+        int_param_ids: Box<[usize]>,
+        str_param_ids: Box<[usize]>,
 
         required_kwargs: usize,
 
         ffi_data: Box<[ErasedFatPtr]>,
         ffi_str_data: Box<[*const c_char]>,
         ffi: verilogae_ffi::VaeFun,
+        ffi_jacobian: verilogae_ffi::VaeJacobian,
+
+        // only set for the functions that calculate the operating point variables
+        // or evaluate the DAE system
+        ffi_opvars: verilogae_ffi::VaeOpvars,
+        // the names of the operating point variables or the unknowns of the DAE system
+        outputs_: Box<[*mut PyObject]>,
+        kind: FunKind,
     }
 }
+
+/// The compiled function that is called by a [`VaeFun`].
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum FunKind {
+    /// a function that calculates a single variable
+    Retrieve,
+    /// the function that calculates all operating point variables
+    Opvars,
+    /// the function that evaluates the residual and jacobian of the DAE system
+    SmallSignal,
+}
+
 macro_rules! read_array {
     ($name: expr, $val: expr,  $kind: ident, $len: expr,  $dst: expr $(, convert $convert_kind: ident)?) => {
         match NumpyArray::new($val) {
@@ -674,30 +698,21 @@ macro_rules! populate_default_branches {
 }
 
 impl VaeFun {
-    unsafe fn new_dict(handle: *const c_void) -> *mut PyObject {
+    unsafe fn new_dict(model: &Arc<Model>) -> *mut PyObject {
         let functions = PyDict_New();
         if functions.is_null() {
             return ptr::null_mut();
         }
 
-        let fun_cnt = verilogae_function_cnt(handle);
-        let fun_names = verilogae_functions(handle);
-        let fun_symbols = verilogae_function_symbols(handle);
-
-        let fun_handles = slice::from_raw_parts(fun_symbols, fun_cnt);
-        let fun_names = slice::from_raw_parts(fun_names, fun_cnt);
-
-        for (name, sym) in fun_names.iter().copied().zip(fun_handles.iter().copied()) {
-            // intern for faster lookups with constants (so all the time)
-            let name = PyUnicode_InternFromString(name);
-            let fun = VaeFun::new(handle, name, sym);
-            if fun.is_null() {
+        for fun in model.functions() {
+            let py_fun = VaeFun::new(model, fun, FunKind::Retrieve);
+            if py_fun.is_null() {
                 Py_DECREF(functions);
-                Py_DECREF(name);
                 return ptr::null_mut();
             }
-            let code = PyDict_SetItem(functions, name, fun);
-            Py_DECREF(fun);
+            let name = (*(py_fun as *mut VaeFun)).name;
+            let code = PyDict_SetItem(functions, name, py_fun);
+            Py_DECREF(py_fun);
             if code != 0 {
                 Py_DECREF(functions);
                 return ptr::null_mut();
@@ -706,111 +721,106 @@ impl VaeFun {
 
         functions
     }
+
+    /// Creates the Python object for `fun`. For [`FunKind::Opvars`] and [`FunKind::SmallSignal`]
+    /// `fun` must be [`Model::opvars_function`] or [`Model::small_signal_function`], which are
+    /// called with the entry points that calculate all operating point variables or evaluate
+    /// the DAE system instead.
     #[allow(clippy::new_ret_no_self)]
-    unsafe fn new(handle: *const c_void, name: *mut PyObject, sym: *const c_char) -> *mut PyObject {
+    unsafe fn new(model: &Arc<Model>, fun: &Function, kind: FunKind) -> *mut PyObject {
         let ptr = PyType_GenericAlloc(&mut VAE_FUNCTION_TY, 0);
         if ptr.is_null() {
             return ptr::null_mut();
         }
 
-        let voltage_names = verilogae_fun_voltages(handle, sym);
-        let voltage_cnt = verilogae_fun_voltage_cnt(handle, sym);
-        let voltage_default_cnt = verilogae_fun_voltage_default_cnt(handle, sym);
-        let voltage_defaults = verilogae_fun_voltage_defaults(handle, sym);
+        // intern for faster lookups with constants (so all the time)
+        let name = intern_str(fun.name());
 
+        let voltage_cnt = fun.voltages().len();
         let voltages = PyList_New(voltage_cnt as isize);
-
-        let voltages_: Box<[_]> = (0..voltage_cnt)
-            .map(|i| {
-                let name = *voltage_names.add(i);
-                let name_py = PyUnicode_InternFromString(name);
-                PyList_SetItem(voltages, i as isize, name_py);
-                let name = std::str::from_utf8(CStr::from_ptr(name).to_bytes()).unwrap();
-                let default =
-                    if i < voltage_default_cnt { *voltage_defaults.add(i) } else { f64::NAN };
+        let voltages_: Box<[_]> = intern_names(strs(fun.voltages()), voltages, 0)
+            .into_iter()
+            .enumerate()
+            .map(|(i, (name_py, name))| {
+                let default = fun.voltage_defaults().get(i).copied().unwrap_or(f64::NAN);
                 (name_py, name, default)
             })
             .collect();
 
-        let current_names = verilogae_fun_currents(handle, sym);
-        let currents_cnt = verilogae_fun_current_cnt(handle, sym);
-        let current_default_cnt = verilogae_fun_current_default_cnt(handle, sym);
-        let current_defaults = verilogae_fun_current_defaults(handle, sym);
-
+        let currents_cnt = fun.currents().len();
         let currents = PyList_New(currents_cnt as isize);
-
-        let currents_: Box<[_]> = (0..currents_cnt)
-            .map(|i| {
-                let name = *current_names.add(i);
-                let name_py = PyUnicode_InternFromString(name);
-                PyList_SetItem(currents, i as isize, name_py);
-                let name = std::str::from_utf8(CStr::from_ptr(name).to_bytes()).unwrap();
-                let default =
-                    if i < current_default_cnt { *current_defaults.add(i) } else { f64::NAN };
+        let currents_: Box<[_]> = intern_names(strs(fun.currents()), currents, 0)
+            .into_iter()
+            .enumerate()
+            .map(|(i, (name_py, name))| {
+                let default = fun.current_defaults().get(i).copied().unwrap_or(f64::NAN);
                 (name_py, name, default)
             })
             .collect();
 
-        let real_param_names = verilogae_real_fun_params(handle, sym);
-        let real_param_cnt = verilogae_real_fun_param_cnt(handle, sym);
-
-        let int_param_names = verilogae_int_fun_params(handle, sym);
-        let int_param_cnt = verilogae_int_fun_param_cnt(handle, sym);
-
-        let str_param_names = verilogae_str_fun_params(handle, sym);
-        let str_param_cnt = verilogae_str_fun_param_cnt(handle, sym);
-
-        let parameters = PyList_New((real_param_cnt + int_param_cnt) as isize);
-
-        let real_params = (0..real_param_cnt).map(|i| {
-            let name = *real_param_names.add(i);
-            let name_py = PyUnicode_InternFromString(name);
-            PyList_SetItem(parameters, i as isize, name_py);
-            let name = std::str::from_utf8(CStr::from_ptr(name).to_bytes()).unwrap();
-            (name_py, name)
-        });
-
-        let int_params = (0..int_param_cnt).map(|i| {
-            let name = *int_param_names.add(i);
-            let name_py = PyUnicode_InternFromString(name);
-            PyList_SetItem(parameters, (i + real_param_cnt) as isize, name_py);
-            let name = std::str::from_utf8(CStr::from_ptr(name).to_bytes()).unwrap();
-            (name_py, name)
-        });
-
-        let str_params = (0..str_param_cnt).map(|i| {
-            let name = *str_param_names.add(i);
-            let name_py = PyUnicode_InternFromString(name);
-            PyList_SetItem(parameters, (i + real_param_cnt) as isize, name_py);
-            let name = std::str::from_utf8(CStr::from_ptr(name).to_bytes()).unwrap();
-            (name_py, name)
-        });
-
-        let real_depbreak_names = verilogae_real_fun_depbreak(handle, sym);
-        let real_depbreak_cnt = verilogae_real_fun_depbreak_cnt(handle, sym);
-        let int_depbreak_names = verilogae_int_fun_depbreak(handle, sym);
-        let int_depbreak_cnt = verilogae_int_fun_depbreak_cnt(handle, sym);
+        // the model parameters are sorted by type (real, integer and then string parameters),
+        // the model card stores each type in a separate array
+        let params = model.params();
+        let int_offset = params.iter().take_while(|param| param.ty == ParamType::Real).count();
+        let str_offset = int_offset
+            + params[int_offset..]
+                .iter()
+                .take_while(|param| param.ty == ParamType::Integer)
+                .count();
+
+        let real_param_cnt = fun.real_params().len();
+        let int_param_cnt = fun.int_params().len();
+        let str_param_cnt = fun.str_params().len();
+
+        let parameters = PyList_New((real_param_cnt + int_param_cnt + str_param_cnt) as isize);
+        let param_names = |ids: &[usize]| -> Vec<&str> {
+            ids.iter().map(|&id| params[id].name.as_str()).collect()
+        };
+        let real_params = param_names(fun.real_params());
+        let real_params = intern_names(real_params.into_iter(), parameters, 0);
+        let int_params = param_names(fun.int_params());
+        let int_params = intern_names(int_params.into_iter(), parameters, real_param_cnt);
+        let str_params = param_names(fun.str_params());
+        let str_params =
+            intern_names(str_params.into_iter(), parameters, real_param_cnt + int_param_cnt);
+
+        let real_param_ids = fun.real_params().into();
+        let int_param_ids = fun.int_params().iter().map(|id| id - int_offset).collect();
+        let str_param_ids = fun.str_params().iter().map(|id| id - str_offset).collect();
+
+        let real_depbreak_cnt = fun.real_depbreak().len();
+        let int_depbreak_cnt = fun.int_depbreak().len();
 
         let depbreak = PyList_New((real_depbreak_cnt + int_depbreak_cnt) as isize);
+        let real_depbreak = intern_names(strs(fun.real_depbreak()), depbreak, 0);
+        let int_depbreak = intern_names(strs(fun.int_depbreak()), depbreak, real_depbreak_cnt);
+
+        let derivatives = PyList_New(fun.derivatives().len() as isize);
+        let derivatives_: Box<[_]> = intern_names(strs(fun.derivatives()), derivatives, 0)
+            .into_iter()
+            .map(|(name_py, _)| name_py)
+            .collect();
 
-        let real_depbreak = (0..real_depbreak_cnt).map(|i| {
-            let name = *real_depbreak_names.add(i);
-            let name_py = PyUnicode_InternFromString(name);
-            PyList_SetItem(depbreak, i as isize, name_py);
-            let name = std::str::from_utf8(CStr::from_ptr(name).to_bytes()).unwrap();
-            (name_py, name)
-        });
-
-        let int_depbreak = (0..int_depbreak_cnt).map(|i| {
-            let name = *int_depbreak_names.add(i);
-            let name_py = PyUnicode_InternFromString(name);
-            PyList_SetItem(depbreak, (i + real_depbreak_cnt) as isize, name_py);
-            let name = std::str::from_utf8(CStr::from_ptr(name).to_bytes()).unwrap();
-            (name_py, name)
-        });
-
-        let ffi = verilogae_fun_ptr(handle, sym);
-        assert!(ffi.is_some(), "failed to read verilogae function");
+        let handle = model.as_raw();
+        let (ffi, ffi_jacobian, ffi_opvars, outputs_) = match kind {
+            FunKind::Opvars => {
+                let ffi_opvars = verilogae_opvars_ptr(handle);
+                assert!(ffi_opvars.is_some(), "failed to read verilogae function");
+                let outputs_ = model.opvars().iter().map(|name| intern_str(name)).collect();
+                (None, None, ffi_opvars, outputs_)
+            }
+            FunKind::SmallSignal => {
+                let ffi_opvars = verilogae_small_signal_ptr(handle);
+                assert!(ffi_opvars.is_some(), "failed to read verilogae function");
+                let names = verilogae_small_signal_unknowns(handle);
+                let outputs_ = (0..verilogae_small_signal_unknown_cnt(handle))
+                    .map(|i| PyUnicode_InternFromString(*names.add(i)))
+                    .collect();
+                (None, None, ffi_opvars, outputs_)
+            }
+            // the jacobian is only generated if derivatives were requested
+            FunKind::Retrieve => (fun.as_raw(), fun.jacobian_as_raw(), None, Box::default()),
+        };
 
         let res = VaeFun {
             ob_base: ptr::read(ptr),
@@ -819,13 +829,20 @@ impl VaeFun {
             currents,
             parameters,
             depbreak,
+            derivatives,
             real_depbreak_offset: real_param_cnt,
             int_depbreak_offset: int_param_cnt,
-            real_params: real_params.chain(real_depbreak).collect(),
-            int_params: int_params.chain(int_depbreak).collect(),
-            str_params: str_params.collect(),
+            real_params: real_params.into_iter().chain(real_depbreak).collect(),
+            int_params: int_params.into_iter().chain(int_depbreak).collect(),
+            str_params: str_params.into_boxed_slice(),
             voltages_,
             currents_,
+            derivatives_,
+
+            model: model.clone(),
+            real_param_ids,
+            int_param_ids,
+            str_param_ids,
 
             required_kwargs: 1
                 + real_depbreak_cnt
@@ -847,6 +864,10 @@ impl VaeFun {
             ]
             .into_boxed_slice(),
             ffi,
+            ffi_jacobian,
+            ffi_opvars,
+            outputs_,
+            kind,
 
             ffi_str_data: vec![ptr::null(); str_param_cnt].into_boxed_slice(),
         };
@@ -864,6 +885,7 @@ impl VaeFun {
         Py_XDECREF(self_.currents);
         Py_XDECREF(self_.parameters);
         Py_XDECREF(self_.depbreak);
+        Py_XDECREF(self_.derivatives);
 
         // make drop a noop
         take(&mut self_.real_params);
@@ -871,8 +893,16 @@ impl VaeFun {
         take(&mut self_.str_params);
         take(&mut self_.voltages_);
         take(&mut self_.currents_);
+        take(&mut self_.derivatives_);
+        take(&mut self_.real_param_ids);
+        take(&mut self_.int_param_ids);
+        take(&mut self_.str_param_ids);
         take(&mut self_.ffi_data);
         take(&mut self_.ffi_str_data);
+        for name in take(&mut self_.outputs_).iter() {
+            Py_DECREF(*name);
+        }
+        ptr::drop_in_place(&mut self_.model);
     }
 
     // #[cfg(not(Py_3_8))]
@@ -924,8 +954,30 @@ impl VaeFun {
             return raise_eval_illegal_data_type_exception("temperature");
         }
 
+        let modelcard = PyDict_GetItem(kwds, MODELCARD_STR);
+        let modelcard = if modelcard.is_null() {
+            None
+        } else {
+            match ModelCard::downcast(modelcard, &self_.model, "eval") {
+                Some(modelcard) => Some(modelcard),
+                None => return ptr::null_mut(),
+            }
+        };
+
         let mut dst = self_.ffi_data.iter_mut();
-        for ((name_, name), dst) in self_.real_params.iter().copied().zip(&mut dst) {
+
+        // parameters are read from the model card instead of the kwargs if one is provided,
+        // variables used for dependency breaking are always read from the kwargs
+        let real_params = match modelcard {
+            Some(modelcard) => {
+                for (&id, dst) in self_.real_param_ids.iter().zip(&mut dst) {
+                    dst.float.set_scalar(modelcard.real[id]);
+                }
+                &self_.real_params[self_.real_depbreak_offset..]
+            }
+            None => &self_.real_params[..],
+        };
+        for ((name_, name), dst) in real_params.iter().copied().zip(&mut dst) {
             let dst = &mut dst.float;
             // This is somewhat of an hotloop
             // There are easily hundreds of parameters
@@ -956,7 +1008,16 @@ impl VaeFun {
             }
         }
 
-        for ((name_, name), dst) in self_.int_params.iter().copied().zip(&mut dst) {
+        let int_params = match modelcard {
+            Some(modelcard) => {
+                for (&id, dst) in self_.int_param_ids.iter().zip(&mut dst) {
+                    dst.int.set_scalar(modelcard.int[id]);
+                }
+                &self_.int_params[self_.int_depbreak_offset..]
+            }
+            None => &self_.int_params[..],
+        };
+        for ((name_, name), dst) in int_params.iter().copied().zip(&mut dst) {
             let dst = &mut dst.int;
             // There are usually very few integer parameters and they are usually flags/single
             // values
@@ -982,7 +1043,16 @@ impl VaeFun {
             }
         }
 
-        for ((name_, name), dst) in self_.str_params.iter().copied().zip(&mut *self_.ffi_str_data) {
+        let str_params = match modelcard {
+            Some(modelcard) => {
+                for (&id, dst) in self_.str_param_ids.iter().zip(&mut *self_.ffi_str_data) {
+                    *dst = modelcard.str_data[id];
+                }
+                &[][..]
+            }
+            None => &self_.str_params[..],
+        };
+        for ((name_, name), dst) in str_params.iter().copied().zip(&mut *self_.ffi_str_data) {
             // There are usually very few integer parameters and they are usually flags/single
             // values
             let val = PyDict_GetItem(kwds, name_);
@@ -1018,58 +1088,350 @@ impl VaeFun {
             read_branch_val!(self_.currents_, currents, len, dst);
         }
 
-        let ptr = self_.ffi_data.as_mut_ptr();
+        let threads = match read_threads(kwds) {
+            Some(threads) => threads,
+            None => return ptr::null_mut(),
+        };
+
+        let derivatives = PyDict_GetItem(kwds, DERIVATIVES_STR);
+        match self_.kind {
+            FunKind::Opvars | FunKind::SmallSignal if unlikely(!derivatives.is_null()) => {
+                let msg = if self_.kind == FunKind::Opvars {
+                    "eval_opvars() does not support calculating derivatives"
+                } else {
+                    "eval_small_signal() does not support calculating derivatives"
+                };
+                return raise_eval_exception(msg);
+            }
+            FunKind::Opvars => return self_.eval_opvars(len, threads, &mut temp),
+            FunKind::SmallSignal => return self_.eval_small_signal(len, threads, &mut temp),
+            FunKind::Retrieve => (),
+        }
+
+        if unlikely(!derivatives.is_null()) {
+            return self_.eval_jacobian(derivatives, len, threads, &mut temp);
+        }
+
         if likely(len != 1) {
-            let new_arr = NUMPY_API.unwrap();
-            Py_INCREF(NUMPY_CDOUBLE_DESCR);
-            let dst = new_arr(
-                NUMPY_ARR_TYPE.unwrap(), // base_type (normal numpy array)
-                NUMPY_CDOUBLE_DESCR,     // type descriptor
-                1,                       //nd
-                &mut len,                //dims
-                &mut 8,                  // strides
-                ptr::null_mut(),         //data (to be allocated)
-                0,                       // flags
-                ptr::null_mut(),         // obj (to be created)
-            );
+            let dst = new_real_array(len);
             let arr = NumpyArray::new(dst).unwrap();
-            verilogae_call_fun_parallel(
-                self_.ffi,
-                len as usize,
-                &mut (*ptr.add(self_.int_params.len() + self_.real_params.len())).float,
-                &mut (*ptr
-                    .add(self_.int_params.len() + self_.real_params.len() + self_.voltages_.len()))
-                .float,
-                &mut (*ptr).float,
-                &mut (*ptr.add(self_.real_params.len())).int,
-                self_.ffi_str_data.as_mut_ptr(),
-                &mut (*ptr.add(self_.real_depbreak_offset)).float,
-                &mut (*ptr.add(self_.int_depbreak_offset + self_.real_params.len())).int,
-                &mut temp,
-                arr.data(),
-            );
+            self_.call(len, threads, &mut temp, arr.data(), ptr::null_mut());
             dst
         } else {
             let mut val = 0f64;
-            let val_ptr: *mut f64 = &mut val;
-            verilogae_call_fun_parallel(
-                self_.ffi,
+            self_.call(len, threads, &mut temp, &mut val as *mut f64 as *mut _, ptr::null_mut());
+            PyFloat_FromDouble(val)
+        }
+    }
+
+    /// Calls the compiled function (or its jacobian if `derivatives` is not null) for `len`
+    /// operating points. For the opvars and small signal functions `derivatives` contains the
+    /// destinations of the outputs instead. The GIL is released while arrays are evaluated,
+    /// so the argument buffers are copied first: another thread may call `eval` on this
+    /// function meanwhile.
+    unsafe fn call(
+        &mut self,
+        len: isize,
+        threads: usize,
+        temp: &mut FatPtr<f64>,
+        out: *mut c_void,
+        derivatives: *mut *mut f64,
+    ) {
+        let mut ffi_data;
+        let mut ffi_str_data;
+        let (ptr, str_ptr) = if len == 1 {
+            (self.ffi_data.as_mut_ptr(), self.ffi_str_data.as_mut_ptr())
+        } else {
+            ffi_data = self.ffi_data.clone();
+            ffi_str_data = self.ffi_str_data.clone();
+            (ffi_data.as_mut_ptr(), ffi_str_data.as_mut_ptr())
+        };
+
+        let voltages = &mut (*ptr.add(self.int_params.len() + self.real_params.len())).float;
+        let currents = &mut (*ptr
+            .add(self.int_params.len() + self.real_params.len() + self.voltages_.len()))
+        .float;
+        let real_params = &mut (*ptr).float;
+        let int_params = &mut (*ptr.add(self.real_params.len())).int;
+        let real_dep_break = &mut (*ptr.add(self.real_depbreak_offset)).float;
+        let int_dep_break = &mut (*ptr.add(self.int_depbreak_offset + self.real_params.len())).int;
+
+        let thread_state = if len == 1 { ptr::null_mut() } else { PyEval_SaveThread() };
+        if self.ffi_opvars.is_some() {
+            verilogae_call_opvars_parallel(
+                self.ffi_opvars,
+                len as usize,
+                threads,
+                voltages,
+                currents,
+                real_params,
+                int_params,
+                str_ptr,
+                temp,
+                derivatives,
+            );
+        } else if derivatives.is_null() || self.ffi_jacobian.is_none() {
+            // without a jacobian there are no derivatives that could be requested
+            verilogae_call_fun_parallel_threads(
+                self.ffi,
+                len as usize,
+                threads,
+                voltages,
+                currents,
+                real_params,
+                int_params,
+                str_ptr,
+                real_dep_break,
+                int_dep_break,
+                temp,
+                out,
+            );
+        } else {
+            verilogae_call_jacobian_parallel_threads(
+                self.ffi_jacobian,
                 len as usize,
-                &mut (*ptr.add(self_.int_params.len() + self_.real_params.len())).float,
-                &mut (*ptr
-                    .add(self_.int_params.len() + self_.real_params.len() + self_.voltages_.len()))
-                .float,
-                &mut (*ptr).float,
-                &mut (*ptr.add(self_.real_params.len())).int,
-                self_.ffi_str_data.as_mut_ptr(),
-                &mut (*ptr.add(self_.real_depbreak_offset)).float,
-                &mut (*ptr.add(self_.int_depbreak_offset + self_.real_params.len())).int,
-                &mut temp,
-                &mut val as *mut f64 as *mut _,
+                threads,
+                voltages,
+                currents,
+                real_params,
+                int_params,
+                str_ptr,
+                real_dep_break,
+                int_dep_break,
+                temp,
+                out,
+                derivatives,
             );
-            PyFloat_FromDouble(*val_ptr)
+        }
+        if !thread_state.is_null() {
+            PyEval_RestoreThread(thread_state);
         }
     }
+
+    /// Evaluates the function and the derivatives in `derivatives` (a list of names).
+    /// Returns a tuple of the value and a dict that maps each name to its derivative.
+    unsafe fn eval_jacobian(
+        &mut self,
+        derivatives: *mut PyObject,
+        len: isize,
+        threads: usize,
+        temp: &mut FatPtr<f64>,
+    ) -> *mut PyObject {
+        if unlikely(PyList_Check(derivatives) == 0) {
+            return raise_eval_exception("eval() argument 'derivatives' must have type list(str)");
+        }
+
+        let cnt = PyList_GET_SIZE(derivatives);
+        let mut requested = Vec::with_capacity(cnt as usize);
+        for i in 0..cnt {
+            let name = PyList_GET_ITEM(derivatives, i);
+            let pos = self.derivatives_.iter().position(|it| PyUnicode_Compare(*it, name) == 0);
+            match pos {
+                Some(pos) => requested.push((name, pos)),
+                None => return raise_eval_unknown_derivative_exception(name),
+            }
+        }
+
+        let res = PyDict_New();
+        let mut dsts = vec![ptr::null_mut::<f64>(); self.derivatives_.len()];
+        let mut scalars = vec![0f64; self.derivatives_.len()];
+        let mut val = 0f64;
+        let out = if likely(len != 1) {
+            for &(name, pos) in &requested {
+                let arr = new_real_array(len);
+                dsts[pos] = NumpyArray::new(arr).unwrap().data() as *mut f64;
+                PyDict_SetItem(res, name, arr);
+                Py_DECREF(arr);
+            }
+            new_real_array(len)
+        } else {
+            for &(_, pos) in &requested {
+                dsts[pos] = &mut scalars[pos];
+            }
+            ptr::null_mut()
+        };
+        let out_ptr = if out.is_null() {
+            &mut val as *mut f64 as *mut _
+        } else {
+            NumpyArray::new(out).unwrap().data()
+        };
+
+        self.call(len, threads, temp, out_ptr, dsts.as_mut_ptr());
+
+        let out = if out.is_null() {
+            for &(name, pos) in &requested {
+                let val = PyFloat_FromDouble(scalars[pos]);
+                PyDict_SetItem(res, name, val);
+                Py_DECREF(val);
+            }
+            PyFloat_FromDouble(val)
+        } else {
+            out
+        };
+
+        let tuple = PyTuple_New(2);
+        PyTuple_SetItem(tuple, 0, out);
+        PyTuple_SetItem(tuple, 1, res);
+        tuple
+    }
+
+    /// Calculates all operating point variables. Returns a dict that maps the name of each
+    /// variable to its value.
+    unsafe fn eval_opvars(
+        &mut self,
+        len: isize,
+        threads: usize,
+        temp: &mut FatPtr<f64>,
+    ) -> *mut PyObject {
+        let res = PyDict_New();
+        let mut dsts = vec![ptr::null_mut::<f64>(); self.outputs_.len()];
+        let mut scalars = vec![0f64; self.outputs_.len()];
+        if likely(len != 1) {
+            for (&name, dst) in self.outputs_.iter().zip(&mut dsts) {
+                let arr = new_real_array(len);
+                *dst = NumpyArray::new(arr).unwrap().data() as *mut f64;
+                PyDict_SetItem(res, name, arr);
+                Py_DECREF(arr);
+            }
+        } else {
+            for (dst, val) in dsts.iter_mut().zip(&mut scalars) {
+                *dst = val;
+            }
+        }
+
+        self.call(len, threads, temp, ptr::null_mut(), dsts.as_mut_ptr());
+
+        if len == 1 {
+            for (&name, &val) in self.outputs_.iter().zip(&scalars) {
+                let val = PyFloat_FromDouble(val);
+                PyDict_SetItem(res, name, val);
+                Py_DECREF(val);
+            }
+        }
+        res
+    }
+
+    /// Evaluates the DAE system. Returns a dict with the names of the unknowns (`unknowns`),
+    /// the resistive and reactive residuals (`I` and `Q`) and the resistive and reactive
+    /// jacobian (`G` and `C`).
+    unsafe fn eval_small_signal(
+        &mut self,
+        len: isize,
+        threads: usize,
+        temp: &mut FatPtr<f64>,
+    ) -> *mut PyObject {
+        let unknown_cnt = self.outputs_.len();
+        let handle = self.model.as_raw();
+        let entry_cnt = verilogae_small_signal_entry_cnt(handle);
+        let rows = slice::from_raw_parts(verilogae_small_signal_rows(handle), entry_cnt);
+        let cols = slice::from_raw_parts(verilogae_small_signal_cols(handle), entry_cnt);
+
+        let n = unknown_cnt as isize;
+        let residual = [new_real_nd_array(len, &[n]), new_real_nd_array(len, &[n])];
+        let jacobian = [new_real_nd_array(len, &[n, n]), new_real_nd_array(len, &[n, n])];
+
+        // the compiled function writes each output to `len` contiguous values
+        let mut dsts = Vec::with_capacity(2 * unknown_cnt + 2 * entry_cnt);
+        for &arr in &residual {
+            let data = NumpyArray::new(arr).unwrap().data() as *mut f64;
+            dsts.extend((0..unknown_cnt).map(|i| data.add(i * len as usize)));
+        }
+        for &arr in &jacobian {
+            let data = NumpyArray::new(arr).unwrap().data() as *mut f64;
+            // entries that do not appear in the jacobian are zero
+            ptr::write_bytes(data, 0, unknown_cnt * unknown_cnt * len as usize);
+            dsts.extend(rows.iter().zip(cols).map(|(&row, &col)| {
+                data.add((row as usize * unknown_cnt + col as usize) * len as usize)
+            }));
+        }
+
+        self.call(len, threads, temp, ptr::null_mut(), dsts.as_mut_ptr());
+
+        let unknowns = PyList_New(n);
+        for (i, &name) in self.outputs_.iter().enumerate() {
+            Py_INCREF(name);
+            PyList_SetItem(unknowns, i as isize, name);
+        }
+
+        let res = PyDict_New();
+        let names = ["unknowns\0", "I\0", "Q\0", "G\0", "C\0"];
+        let vals = [unknowns, residual[0], residual[1], jacobian[0], jacobian[1]];
+        for (name, val) in names.into_iter().zip(vals) {
+            PyDict_SetItemString(res, name.as_ptr() as *const c_char, val);
+            Py_DECREF(val);
+        }
+        res
+    }
+}
+
+fn strs(names: &[String]) -> impl Iterator<Item = &str> {
+    names.iter().map(String::as_str)
+}
+
+/// Interns `names` and stores them in `list` starting at `offset`. Returns the interned
+/// names together with the names themselves.
+unsafe fn intern_names<'a>(
+    names: impl Iterator<Item = &'a str>,
+    list: *mut PyObject,
+    offset: usize,
+) -> Vec<(*mut PyObject, &'static str)> {
+    names
+        .enumerate()
+        .map(|(i, name)| {
+            let name_py = intern_str(name);
+            PyList_SetItem(list, (offset + i) as isize, name_py);
+            // the list keeps the interned string (and its UTF-8 buffer) alive
+            let name = PyUnicode_AsUTF8(name_py);
+            (name_py, CStr::from_ptr(name).to_str().unwrap())
+        })
+        .collect()
+}
+
+/// Allocates a new (uninitialized) 1D float64 numpy array with `len` elements.
+unsafe fn new_real_array(mut len: isize) -> *mut PyObject {
+    let new_arr = NUMPY_API.unwrap();
+    Py_INCREF(NUMPY_CDOUBLE_DESCR);
+    new_arr(
+        NUMPY_ARR_TYPE.unwrap(), // base_type (normal numpy array)
+        NUMPY_CDOUBLE_DESCR,     // type descriptor
+        1,                       //nd
+        &mut len,                //dims
+        &mut 8,                  // strides
+        ptr::null_mut(),         //data (to be allocated)
+        0,                       // flags
+        ptr::null_mut(),         // obj (to be created)
+    )
+}
+
+/// Allocates a new (uninitialized) float64 numpy array with the shape `dims`. If `len` is not
+/// one, the operating points are added as the first axis. The values of each element for all
+/// operating points are stored contiguously so they can be written like a 1D array.
+unsafe fn new_real_nd_array(len: isize, dims: &[isize]) -> *mut PyObject {
+    let mut shape = Vec::with_capacity(dims.len() + 1);
+    let mut strides = vec![0; dims.len()];
+    let mut stride = 8 * len;
+    for (dst, &dim) in strides.iter_mut().zip(dims).rev() {
+        *dst = stride;
+        stride *= dim;
+    }
+    if len != 1 {
+        shape.push(len);
+        strides.insert(0, 8);
+    }
+    shape.extend_from_slice(dims);
+
+    let new_arr = NUMPY_API.unwrap();
+    Py_INCREF(NUMPY_CDOUBLE_DESCR);
+    new_arr(
+        NUMPY_ARR_TYPE.unwrap(),
+        NUMPY_CDOUBLE_DESCR,
+        shape.len() as c_int,
+        shape.as_mut_ptr(),
+        strides.as_mut_ptr(),
+        ptr::null_mut(),
+        0,
+        ptr::null_mut(),
+    )
 }
 
 #[cold]
@@ -1103,6 +1465,30 @@ unsafe fn is_int(ty: *mut PyTypeObject) -> bool {
     PyType_FastSubclass(ty, Py_TPFLAGS_LONG_SUBCLASS) != 0
 }
 
+/// Reads the optional `threads` argument of `eval` (0 if it is missing).
+/// Returns `None` if an exception was raised.
+unsafe fn read_threads(kwds: *mut PyObject) -> Option<usize> {
+    let threads = PyDict_GetItem(kwds, THREADS_STR);
+    if likely(threads.is_null()) {
+        return Some(0);
+    }
+
+    if unlikely(!is_int(ob_type!(threads))) {
+        raise_eval_exception("eval() argument 'threads' must have type int");
+        return None;
+    }
+
+    let val = PyLong_AsLong(threads);
+    if unlikely(!PyErr_Occurred().is_null()) {
+        return None;
+    }
+    if unlikely(val < 0) {
+        raise_eval_exception("eval() argument 'threads' must not be negative");
+        return None;
+    }
+    Some(val as usize)
+}
+
 #[cold]
 #[inline(never)]
 fn raise_eval_illegal_array_exception(
@@ -1133,6 +1519,22 @@ fn raise_eval_illegal_array_exception(
     raise_eval_exception(&msg)
 }
 
+#[cold]
+#[inline(never)]
+unsafe fn raise_eval_unknown_derivative_exception(name: *mut PyObject) -> *mut PyObject {
+    // PyUnicode_Compare raises an exception if name is not a str
+    PyErr_Clear();
+    let name = PyUnicode_AsUTF8(name);
+    if name.is_null() {
+        PyErr_Clear();
+        return raise_eval_exception("eval() argument 'derivatives' must have type list(str)");
+    }
+    raise_eval_exception(&format!(
+        "eval() can not calculate the derivative by '{}'\n\thelp: derivatives are only calculated for models loaded with load(derivative_params=[...])\n\thelp: only voltages and the parameters in derivative_params are supported (pass an empty list for voltages only)",
+        CStr::from_ptr(name).to_string_lossy()
+    ))
+}
+
 #[cold]
 #[inline(never)]
 fn raise_eval_illegal_data_type_exception(name: &str) -> *mut PyObject {
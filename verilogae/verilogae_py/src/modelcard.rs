@@ -0,0 +1,682 @@
+//! Model cards cache the parameter values of a model in the format expected by the compiled
+//! functions. Values are only converted when the card is created or updated, so that
+//! repeated `eval` calls do not have to read hundreds of parameters from their kwargs.
+
+use std::collections::HashMap;
+use std::ffi::CStr;
+use std::mem::take;
+use std::os::raw::c_long;
+use std::sync::Arc;
+use std::{fs, ptr};
+
+use libc::{c_char, c_void};
+use modelcard::{parse_models, select_bin, write_model, Value, BIN_PARAMS};
+use pyo3_ffi::*;
+use verilogae_ffi::{Model, ParamValue};
+
+use crate::ffi::{new_type, PyDict_GET_SIZE};
+use crate::unicode::{new_str, OsStr};
+use crate::util::unlikely;
+
+pub static mut MODELCARD_TY: PyTypeObject = {
+    let mut res = new_type::<ModelCard>();
+    res.tp_name = "verilogae.ModelCard\0".as_ptr() as *const c_char;
+    res.tp_doc = "The parameters of a Verilog-A module compiled and loaded with Verilog-AE.\nThe \
+                  values are converted once when the card is updated and reused by `eval`\0"
+        .as_ptr() as *const c_char;
+    res.tp_methods = unsafe { &mut MODELCARD_METHODS } as *mut _;
+    res.tp_dealloc = Some(ModelCard::dealloc);
+    res
+};
+
+static mut MODELCARD_METHODS: [PyMethodDef; 6] = [
+    PyMethodDef {
+        ml_name: "update\0".as_ptr() as *const c_char,
+        ml_meth: PyMethodDefPointer { PyCFunctionWithKeywords: ModelCard::update_py },
+        ml_flags: METH_VARARGS | METH_KEYWORDS,
+        ml_doc: "update(**params)\n--\n\nchanges the value of the parameters passed as keyword \
+                 arguments\0"
+            .as_ptr() as *const c_char,
+    },
+    PyMethodDef {
+        ml_name: "copy\0".as_ptr() as *const c_char,
+        ml_meth: PyMethodDefPointer { PyCFunction: ModelCard::copy_py },
+        ml_flags: METH_NOARGS,
+        ml_doc: "copy()\n--\n\nreturns an independent copy of this model card\0".as_ptr()
+            as *const c_char,
+    },
+    PyMethodDef {
+        ml_name: "to_dict\0".as_ptr() as *const c_char,
+        ml_meth: PyMethodDefPointer { PyCFunction: ModelCard::to_dict_py },
+        ml_flags: METH_NOARGS,
+        ml_doc: "to_dict()\n--\n\nreturns a dict that maps each parameter to its value\0".as_ptr()
+            as *const c_char,
+    },
+    PyMethodDef {
+        ml_name: "save_json\0".as_ptr() as *const c_char,
+        ml_meth: PyMethodDefPointer { PyCFunctionWithKeywords: ModelCard::save_json_py },
+        ml_flags: METH_VARARGS | METH_KEYWORDS,
+        ml_doc: "save_json(path)\n--\n\nwrites all parameters to a JSON file\0".as_ptr()
+            as *const c_char,
+    },
+    PyMethodDef {
+        ml_name: "save_spice\0".as_ptr() as *const c_char,
+        ml_meth: PyMethodDefPointer { PyCFunctionWithKeywords: ModelCard::save_spice_py },
+        ml_flags: METH_VARARGS | METH_KEYWORDS,
+        ml_doc: "save_spice(path, name=None)\n--\n\nwrites all parameters to a SPICE .model \
+                 card called name (the module name by default)\0"
+            .as_ptr() as *const c_char,
+    },
+    unsafe { zero!(PyMethodDef) },
+];
+
+#[derive(Clone, Copy)]
+enum Param {
+    Real(usize),
+    Int(usize),
+    Str(usize),
+}
+
+enum ParamVal {
+    Real(f64),
+    Int(i32),
+    Str(*mut PyObject),
+}
+
+#[repr(C)]
+pub struct ModelCard {
+    ob_base: PyObject,
+    /// the names in `params` and `lookup` are owned by the model
+    model: Arc<Model>,
+    params: Box<[(&'static str, Param)]>,
+    lookup: HashMap<&'static str, usize>,
+    pub real: Box<[f64]>,
+    pub int: Box<[i32]>,
+    str_: Box<[*mut PyObject]>,
+    /// The UTF-8 contents of `str_`, these remain valid as long as `str_` is not changed
+    pub str_data: Box<[*const c_char]>,
+}
+
+impl ModelCard {
+    /// Creates a new model card that contains the default values of all parameters.
+    #[allow(clippy::new_ret_no_self)]
+    pub unsafe fn new(model: &Arc<Model>) -> *mut PyObject {
+        let mut params = Vec::with_capacity(model.params().len());
+        let mut real = Vec::new();
+        let mut int = Vec::new();
+        let mut str_ = Vec::new();
+        for info in model.params() {
+            // the model card keeps the model alive
+            let name = &*(info.name.as_str() as *const str);
+            let param = match &info.default {
+                ParamValue::Real(val) => {
+                    real.push(*val);
+                    Param::Real(real.len() - 1)
+                }
+                ParamValue::Integer(val) => {
+                    int.push(*val);
+                    Param::Int(int.len() - 1)
+                }
+                ParamValue::String(val) => {
+                    str_.push(new_str(val));
+                    Param::Str(str_.len() - 1)
+                }
+            };
+            params.push((name, param));
+        }
+        let lookup = params.iter().enumerate().map(|(i, (name, _))| (*name, i)).collect();
+        let str_data = str_.iter().map(|&val| PyUnicode_AsUTF8(val)).collect();
+
+        ModelCard::alloc(ModelCard {
+            ob_base: zero!(PyObject),
+            model: model.clone(),
+            params: params.into_boxed_slice(),
+            lookup,
+            real: real.into_boxed_slice(),
+            int: int.into_boxed_slice(),
+            str_: str_.into_boxed_slice(),
+            str_data,
+        })
+    }
+
+    unsafe fn alloc(card: ModelCard) -> *mut PyObject {
+        let ptr = PyType_GenericAlloc(&mut MODELCARD_TY, 0);
+        if ptr.is_null() {
+            for &val in &*card.str_ {
+                Py_DECREF(val)
+            }
+            return ptr::null_mut();
+        }
+        ptr::write(ptr as *mut ModelCard, ModelCard { ob_base: ptr::read(ptr), ..card });
+        ptr
+    }
+
+    unsafe extern "C" fn dealloc(self_: *mut PyObject) {
+        let sel = &mut *(self_ as *mut Self);
+        for &val in &*sel.str_ {
+            Py_DECREF(val)
+        }
+        take(&mut sel.params);
+        take(&mut sel.lookup);
+        take(&mut sel.real);
+        take(&mut sel.int);
+        take(&mut sel.str_);
+        take(&mut sel.str_data);
+        ptr::drop_in_place(&mut sel.model);
+        if let Some(free) = (*ob_type!(self_)).tp_free {
+            free(self_ as *mut c_void)
+        }
+    }
+
+    /// Returns the model card `obj` if it is a model card of `model`.
+    /// Otherwise an exception is raised.
+    pub unsafe fn downcast<'a>(
+        obj: *mut PyObject,
+        model: &Arc<Model>,
+        fun: &str,
+    ) -> Option<&'a ModelCard> {
+        if unlikely(PyObject_TypeCheck(obj, &mut MODELCARD_TY) == 0) {
+            raise_exception(
+                PyExc_TypeError,
+                &format!("{fun}() argument 'modelcard' must have type ModelCard"),
+            );
+            return None;
+        }
+        let card = &*(obj as *const ModelCard);
+        if unlikely(!Arc::ptr_eq(&card.model, model)) {
+            raise_exception(
+                PyExc_TypeError,
+                &format!("{fun}() argument 'modelcard' belongs to a different model"),
+            );
+            return None;
+        }
+        Some(card)
+    }
+
+    /// Converts the parameter values in the dict `params` and updates the model card.
+    /// The model card remains unchanged if any of the values is invalid.
+    /// Returns `false` if an exception was raised.
+    pub unsafe fn update(&mut self, fun: &str, params: *mut PyObject) -> bool {
+        if params.is_null() {
+            return true;
+        }
+
+        let mut vals = Vec::with_capacity(PyDict_GET_SIZE(params) as usize);
+        let mut pos = 0;
+        let mut name: *mut PyObject = ptr::null_mut();
+        let mut val: *mut PyObject = ptr::null_mut();
+        while PyDict_Next(params, &mut pos, &mut name, &mut val) != 0 {
+            let name = PyUnicode_AsUTF8(name);
+            if unlikely(name.is_null()) {
+                return false;
+            }
+            let name = CStr::from_ptr(name).to_str().unwrap();
+            let param = match self.lookup.get(name) {
+                Some(&i) => self.params[i].1,
+                None => {
+                    raise_exception(
+                        PyExc_TypeError,
+                        &format!("{fun}() got an unknown parameter '{name}'"),
+                    );
+                    return false;
+                }
+            };
+            match py_to_param_val(fun, name, param, val) {
+                Some(val) => vals.push((param, val)),
+                None => return false,
+            }
+        }
+
+        for (param, val) in vals {
+            self.set(param, val)
+        }
+        true
+    }
+
+    unsafe fn set(&mut self, param: Param, val: ParamVal) {
+        match (param, val) {
+            (Param::Real(i), ParamVal::Real(val)) => self.real[i] = val,
+            (Param::Int(i), ParamVal::Int(val)) => self.int[i] = val,
+            (Param::Str(i), ParamVal::Str(val)) => {
+                Py_DECREF(self.str_[i]);
+                self.str_[i] = val;
+                self.str_data[i] = PyUnicode_AsUTF8(val);
+            }
+            _ => unreachable!("parameter value has the wrong type"),
+        }
+    }
+
+    unsafe fn to_dict(&self) -> *mut PyObject {
+        let res = PyDict_New();
+        if res.is_null() {
+            return ptr::null_mut();
+        }
+        for &(name, param) in &*self.params {
+            let val = match param {
+                Param::Real(i) => PyFloat_FromDouble(self.real[i]),
+                Param::Int(i) => PyLong_FromLong(self.int[i] as c_long),
+                Param::Str(i) => {
+                    Py_INCREF(self.str_[i]);
+                    self.str_[i]
+                }
+            };
+            let name =
+                PyUnicode_FromStringAndSize(name.as_ptr() as *const c_char, name.len() as isize);
+            let code = PyDict_SetItem(res, name, val);
+            Py_DECREF(name);
+            Py_DECREF(val);
+            if code != 0 {
+                Py_DECREF(res);
+                return ptr::null_mut();
+            }
+        }
+        res
+    }
+
+    /// Applies the parameters of the `.model` card that matches `name`, `l` and `w`
+    /// in the SPICE file `src`. Returns `false` if an exception was raised.
+    pub unsafe fn update_spice(
+        &mut self,
+        src: &str,
+        name: Option<&str>,
+        l: Option<f64>,
+        w: Option<f64>,
+    ) -> bool {
+        let models = match parse_models(src) {
+            Ok(models) => models,
+            Err(err) => {
+                raise_exception(PyExc_ValueError, &format!("invalid SPICE model card: {err}"));
+                return false;
+            }
+        };
+        let model = match select_bin(&models, name, l, w) {
+            Ok(model) => model,
+            Err(err) => {
+                raise_exception(PyExc_ValueError, &err);
+                return false;
+            }
+        };
+
+        let mut vals = Vec::with_capacity(model.params.len());
+        for (name, val) in &model.params {
+            // SPICE is case insensitive, but Verilog-A is not: prefer exact matches
+            let param = self.lookup.get(&**name).map(|&i| self.params[i].1).or_else(|| {
+                self.params
+                    .iter()
+                    .find(|(param, _)| param.eq_ignore_ascii_case(name))
+                    .map(|it| it.1)
+            });
+            let param = match param {
+                Some(param) => param,
+                None if BIN_PARAMS.iter().any(|it| it.eq_ignore_ascii_case(name)) => continue,
+                None => {
+                    raise_exception(
+                        PyExc_ValueError,
+                        &format!("model card '{}' sets unknown parameter '{name}'", model.name),
+                    );
+                    return false;
+                }
+            };
+            let val = match (param, val) {
+                (Param::Real(_), Value::Real(val)) => ParamVal::Real(*val),
+                (Param::Int(_), Value::Real(val)) if val.fract() == 0.0 => {
+                    ParamVal::Int(*val as i32)
+                }
+                (Param::Str(_), Value::Str(val)) => ParamVal::Str(PyUnicode_FromStringAndSize(
+                    val.as_ptr() as *const c_char,
+                    val.len() as isize,
+                )),
+                _ => {
+                    raise_exception(
+                        PyExc_ValueError,
+                        &format!("model card '{}' sets '{name}' to an illegal value", model.name),
+                    );
+                    return false;
+                }
+            };
+            vals.push((param, val));
+        }
+
+        for (param, val) in vals {
+            self.set(param, val)
+        }
+        true
+    }
+
+    fn spice_card(&self, name: &str) -> String {
+        let params = self.params.iter().map(|&(name, param)| {
+            let val = match param {
+                Param::Real(i) => Value::Real(self.real[i]),
+                Param::Int(i) => Value::Int(self.int[i]),
+                Param::Str(i) => {
+                    let val = unsafe { CStr::from_ptr(self.str_data[i]) };
+                    Value::Str(val.to_string_lossy().into_owned())
+                }
+            };
+            (name, val)
+        });
+        write_model(name, self.module_name(), params)
+    }
+
+    fn module_name(&self) -> &str {
+        self.model.name()
+    }
+
+    unsafe extern "C" fn update_py(
+        self_: *mut PyObject,
+        args: *mut PyObject,
+        kwds: *mut PyObject,
+    ) -> *mut PyObject {
+        if unlikely(PyTuple_GET_SIZE(args) != 0) {
+            return raise_exception(PyExc_TypeError, "update() takes only keyword arguments");
+        }
+        let sel = &mut *(self_ as *mut Self);
+        if !sel.update("update", kwds) {
+            return ptr::null_mut();
+        }
+        Py_INCREF(Py_None());
+        Py_None()
+    }
+
+    unsafe extern "C" fn copy_py(self_: *mut PyObject, _args: *mut PyObject) -> *mut PyObject {
+        let sel = &*(self_ as *const Self);
+        for &val in &*sel.str_ {
+            Py_INCREF(val)
+        }
+        ModelCard::alloc(ModelCard {
+            ob_base: zero!(PyObject),
+            model: sel.model.clone(),
+            params: sel.params.clone(),
+            lookup: sel.lookup.clone(),
+            real: sel.real.clone(),
+            int: sel.int.clone(),
+            str_: sel.str_.clone(),
+            str_data: sel.str_data.clone(),
+        })
+    }
+
+    unsafe extern "C" fn to_dict_py(self_: *mut PyObject, _args: *mut PyObject) -> *mut PyObject {
+        (*(self_ as *const Self)).to_dict()
+    }
+
+    unsafe extern "C" fn save_json_py(
+        self_: *mut PyObject,
+        args: *mut PyObject,
+        kwds: *mut PyObject,
+    ) -> *mut PyObject {
+        let path = match parse_path_arg("save_json", args, kwds, &[]) {
+            Some((path, _)) => path,
+            None => return ptr::null_mut(),
+        };
+
+        let params = (*(self_ as *const Self)).to_dict();
+        if params.is_null() {
+            return ptr::null_mut();
+        }
+        let json = json_call("dumps\0", params, true);
+        Py_DECREF(params);
+        if json.is_null() {
+            return ptr::null_mut();
+        }
+        let contents = CStr::from_ptr(PyUnicode_AsUTF8(json)).to_str().unwrap().to_owned();
+        Py_DECREF(json);
+        write_file(&path, contents)
+    }
+
+    unsafe extern "C" fn save_spice_py(
+        self_: *mut PyObject,
+        args: *mut PyObject,
+        kwds: *mut PyObject,
+    ) -> *mut PyObject {
+        let (path, name) = match parse_path_arg("save_spice", args, kwds, &["name"]) {
+            Some((path, [name])) => (path, name),
+            None => return ptr::null_mut(),
+        };
+        let sel = &*(self_ as *const Self);
+        let name = match name.filter(|&name| name != Py_None()) {
+            Some(name) => match py_to_str(name) {
+                Some(name) => name,
+                None => {
+                    return raise_exception(
+                        PyExc_TypeError,
+                        "save_spice() argument 'name' must have type str",
+                    )
+                }
+            },
+            None => sel.module_name(),
+        };
+        write_file(&path, sel.spice_card(name))
+    }
+}
+
+/// Implementation of `VaeModel.load_modelcard(path, name=None, l=None, w=None)`.
+/// Files with a `.json` extension must contain a single object that maps parameter
+/// names to values. All other files are parsed as SPICE model cards.
+pub unsafe fn load_modelcard(
+    model: &Arc<Model>,
+    args: *mut PyObject,
+    kwds: *mut PyObject,
+) -> *mut PyObject {
+    const FUN: &str = "load_modelcard";
+    let (path, [name, l, w]) = match parse_path_arg(FUN, args, kwds, &["name", "l", "w"]) {
+        Some(args) => args,
+        None => return ptr::null_mut(),
+    };
+
+    let src = match fs::read_to_string(&path) {
+        Ok(src) => src,
+        Err(err) => {
+            return raise_exception(PyExc_OSError, &format!("failed to read {path}: {err}"))
+        }
+    };
+
+    let card = ModelCard::new(model);
+    if card.is_null() {
+        return ptr::null_mut();
+    }
+    let sel = &mut *(card as *mut ModelCard);
+
+    let success = if path.ends_with(".json") {
+        let src = PyUnicode_FromStringAndSize(src.as_ptr() as *const c_char, src.len() as isize);
+        let params = json_call("loads\0", src, false);
+        Py_DECREF(src);
+        if params.is_null() {
+            false
+        } else if PyDict_Check(params) == 0 {
+            Py_DECREF(params);
+            raise_exception(PyExc_ValueError, &format!("{path} does not contain a JSON object"));
+            false
+        } else {
+            let success = sel.update(FUN, params);
+            Py_DECREF(params);
+            success
+        }
+    } else {
+        let name = name.filter(|&name| name != Py_None());
+        let name = match name.map(|name| py_to_str(name)) {
+            Some(None) => {
+                Py_DECREF(card);
+                return raise_exception(
+                    PyExc_TypeError,
+                    "load_modelcard() argument 'name' must have type str",
+                );
+            }
+            Some(Some(name)) => Some(name),
+            None => None,
+        };
+        let (l, w) = match (py_to_geometry("l", l), py_to_geometry("w", w)) {
+            (Some(l), Some(w)) => (l, w),
+            _ => {
+                Py_DECREF(card);
+                return ptr::null_mut();
+            }
+        };
+        sel.update_spice(&src, name, l, w)
+    };
+
+    if !success {
+        Py_DECREF(card);
+        return ptr::null_mut();
+    }
+    card
+}
+
+unsafe fn py_to_param_val(
+    fun: &str,
+    name: &str,
+    param: Param,
+    val: *mut PyObject,
+) -> Option<ParamVal> {
+    let is_int = PyLong_Check(val) != 0;
+    let res = match param {
+        Param::Real(_) if PyFloat_Check(val) != 0 => ParamVal::Real(PyFloat_AS_DOUBLE(val)),
+        Param::Real(_) if is_int => ParamVal::Real(PyLong_AsLong(val) as f64),
+        Param::Int(_) if is_int => ParamVal::Int(PyLong_AsLong(val) as i32),
+        Param::Str(_) if PyUnicode_Check(val) != 0 => {
+            Py_INCREF(val);
+            ParamVal::Str(val)
+        }
+        _ => {
+            raise_exception(
+                PyExc_TypeError,
+                &format!("{fun}() argument '{name}' has an illegal data type"),
+            );
+            return None;
+        }
+    };
+    if unlikely(!PyErr_Occurred().is_null()) {
+        return None;
+    }
+    Some(res)
+}
+
+unsafe fn py_to_str<'a>(obj: *mut PyObject) -> Option<&'a str> {
+    let res = PyUnicode_AsUTF8(obj);
+    if res.is_null() {
+        PyErr_Clear();
+        return None;
+    }
+    Some(CStr::from_ptr(res).to_str().unwrap())
+}
+
+/// Reads the optional geometry argument `arg` (`None` if it is missing).
+/// Returns `None` if an exception was raised.
+unsafe fn py_to_geometry(arg: &str, obj: Option<*mut PyObject>) -> Option<Option<f64>> {
+    let obj = match obj {
+        Some(obj) if obj != Py_None() => obj,
+        _ => return Some(None),
+    };
+    let val = PyFloat_AsDouble(obj);
+    if unlikely(!PyErr_Occurred().is_null()) {
+        PyErr_Clear();
+        raise_exception(
+            PyExc_TypeError,
+            &format!("load_modelcard() argument '{arg}' must have type float"),
+        );
+        return None;
+    }
+    Some(Some(val))
+}
+
+/// Parses the arguments of a method that accepts a path as its only positional argument
+/// and the optional keyword arguments `kwargs`. Returns `None` if an exception was raised.
+unsafe fn parse_path_arg<const N: usize>(
+    fun: &str,
+    args: *mut PyObject,
+    kwds: *mut PyObject,
+    kwargs: &[&str; N],
+) -> Option<(String, [Option<*mut PyObject>; N])> {
+    if unlikely(PyTuple_GET_SIZE(args) != 1) {
+        raise_exception(
+            PyExc_TypeError,
+            &format!("{fun}() takes exactly 1 positional argument 'path'"),
+        );
+        return None;
+    }
+
+    let path = match OsStr::new_path(PyTuple_GET_ITEM(args, 0)) {
+        Some(Some(path)) => format!("{path:?}"),
+        Some(None) => {
+            raise_exception(
+                PyExc_TypeError,
+                &format!("{fun}() positional argument 'path' must be a pathlib Path or str"),
+            );
+            return None;
+        }
+        None => return None,
+    };
+
+    let mut res = [None; N];
+    if !kwds.is_null() {
+        let mut pos = 0;
+        let mut name: *mut PyObject = ptr::null_mut();
+        let mut val: *mut PyObject = ptr::null_mut();
+        while PyDict_Next(kwds, &mut pos, &mut name, &mut val) != 0 {
+            let name = py_to_str(name).unwrap_or_default();
+            match kwargs.iter().position(|&arg| arg == name) {
+                Some(i) => res[i] = Some(val),
+                None => {
+                    raise_exception(
+                        PyExc_TypeError,
+                        &format!("{fun}() got an unexpected keyword argument '{name}'"),
+                    );
+                    return None;
+                }
+            }
+        }
+    }
+
+    Some((path, res))
+}
+
+/// Calls `json.<fun>(arg)` (with 3 spaces of indentation if `indent` is set).
+unsafe fn json_call(fun: &str, arg: *mut PyObject, indent: bool) -> *mut PyObject {
+    let json = PyImport_ImportModule("json\0".as_ptr() as *const c_char);
+    if json.is_null() {
+        return ptr::null_mut();
+    }
+    let fun = PyObject_GetAttrString(json, fun.as_ptr() as *const c_char);
+    Py_DECREF(json);
+    if fun.is_null() {
+        return ptr::null_mut();
+    }
+
+    let args = PyTuple_New(1);
+    Py_INCREF(arg);
+    PyTuple_SET_ITEM(args, 0, arg);
+    let kwargs = if indent {
+        let kwargs = PyDict_New();
+        let indent = PyLong_FromLong(3);
+        PyDict_SetItemString(kwargs, "indent\0".as_ptr() as *const c_char, indent);
+        Py_DECREF(indent);
+        kwargs
+    } else {
+        ptr::null_mut()
+    };
+
+    let res = PyObject_Call(fun, args, kwargs);
+    Py_DECREF(fun);
+    Py_DECREF(args);
+    Py_XDECREF(kwargs);
+    res
+}
+
+unsafe fn write_file(path: &str, contents: String) -> *mut PyObject {
+    if let Err(err) = fs::write(path, contents) {
+        return raise_exception(PyExc_OSError, &format!("failed to write {path}: {err}"));
+    }
+    Py_INCREF(Py_None());
+    Py_None()
+}
+
+#[cold]
+#[inline(never)]
+fn raise_exception(ty: *mut PyObject, msg: &str) -> *mut PyObject {
+    unsafe {
+        let err_msg =
+            PyUnicode_FromStringAndSize(msg.as_ptr() as *const c_char, msg.len() as isize);
+        PyErr_SetObject(ty, err_msg);
+        Py_DECREF(err_msg);
+    };
+    ptr::null_mut()
+}
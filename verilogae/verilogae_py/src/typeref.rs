@@ -43,6 +43,10 @@ pub static mut VFS_STR: *mut PyObject = 0 as *mut PyObject;
 pub static mut VOLTAGES_STR: *mut PyObject = 0 as *mut PyObject;
 pub static mut CURRENTS_STR: *mut PyObject = 0 as *mut PyObject;
 pub static mut TEMPERATURE_STR: *mut PyObject = 0 as *mut PyObject;
+pub static mut DERIVATIVES_STR: *mut PyObject = 0 as *mut PyObject;
+pub static mut THREADS_STR: *mut PyObject = 0 as *mut PyObject;
+pub static mut MODELCARD_STR: *mut PyObject = 0 as *mut PyObject;
+pub static mut DERIVATIVE_PARAMS_STR: *mut PyObject = 0 as *mut PyObject;
 
 static INIT: Once = Once::new();
 
@@ -60,6 +64,11 @@ pub fn init_typerefs() {
         VOLTAGES_STR = PyUnicode_InternFromString("voltages\0".as_ptr() as *const c_char);
         CURRENTS_STR = PyUnicode_InternFromString("currents\0".as_ptr() as *const c_char);
         TEMPERATURE_STR = PyUnicode_InternFromString("temperature\0".as_ptr() as *const c_char);
+        DERIVATIVES_STR = PyUnicode_InternFromString("derivatives\0".as_ptr() as *const c_char);
+        THREADS_STR = PyUnicode_InternFromString("threads\0".as_ptr() as *const c_char);
+        MODELCARD_STR = PyUnicode_InternFromString("modelcard\0".as_ptr() as *const c_char);
+        DERIVATIVE_PARAMS_STR =
+            PyUnicode_InternFromString("derivative_params\0".as_ptr() as *const c_char);
         EMPTY_UNICODE = PyUnicode_New(0, 255);
 
         ARRAY_STRUCT_STR =
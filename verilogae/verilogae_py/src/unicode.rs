@@ -1,5 +1,6 @@
 use std::fmt::Debug;
 
+use libc::c_char;
 use pyo3_ffi::*;
 use verilogae_ffi::Slice;
 
@@ -75,3 +76,17 @@ impl Debug for OsStr {
         write!(f, "{}", slice)
     }
 }
+
+/// Creates a new Python string from `src`.
+pub unsafe fn new_str(src: &str) -> *mut PyObject {
+    PyUnicode_FromStringAndSize(src.as_ptr() as *const c_char, src.len() as isize)
+}
+
+/// Creates a new interned Python string from `src` (for faster lookups).
+pub unsafe fn intern_str(src: &str) -> *mut PyObject {
+    let mut res = new_str(src);
+    if !res.is_null() {
+        PyUnicode_InternInPlace(&mut res);
+    }
+    res
+}